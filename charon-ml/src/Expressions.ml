@@ -17,6 +17,7 @@ class ['self] iter_field_proj_kind_base =
     method visit_var_id : 'env -> var_id -> unit = fun _ _ -> ()
     method visit_variant_id : 'env -> variant_id -> unit = fun _ _ -> ()
     method visit_field_id : 'env -> field_id -> unit = fun _ _ -> ()
+    method visit_assumed_ty : 'env -> assumed_ty -> unit = fun _ _ -> ()
   end
 
 (** Ancestor the field_proj_kind map visitor *)
@@ -30,6 +31,7 @@ class ['self] map_field_proj_kind_base =
     method visit_var_id : 'env -> var_id -> var_id = fun _ x -> x
     method visit_variant_id : 'env -> variant_id -> variant_id = fun _ x -> x
     method visit_field_id : 'env -> field_id -> field_id = fun _ x -> x
+    method visit_assumed_ty : 'env -> assumed_ty -> assumed_ty = fun _ x -> x
   end
 
 type field_proj_kind =
@@ -37,8 +39,12 @@ type field_proj_kind =
   | ProjOption of variant_id
       (** Option is an assumed type, coming from the standard library *)
   | ProjTuple of int  (** The integer gives the arity of the tuple *)
+  | ProjRange of assumed_ty
+      (** The range types are assumed: the field ids index the fields given
+          by {!TypesUtils.range_field_names} *)
 [@@deriving
   show,
+    ord,
     visitors
       {
         name = "iter_field_proj_kind";
@@ -57,9 +63,25 @@ type field_proj_kind =
       }]
 
 (* Remark: no `Index` variant, as it is eliminated by a micro-pass *)
-type projection_elem = Deref | DerefBox | Field of field_proj_kind * field_id
+type projection_elem =
+  | Deref
+  | DerefBox
+  | DerefRawPtr  (** Dereference of a raw pointer *)
+  | Field of field_proj_kind * field_id
+  | UnionField of type_decl_id * field_id
+      (** Access to a field of a union: we give the union identifier *)
+  | ConstantIndex of int * bool
+      (** Access to an element of an array or a slice at a constant offset,
+          as introduced by the slice patterns. If the boolean is [true],
+          the offset is counted from the end. *)
+  | Subslice of int * int * bool
+      (** A subslice of an array or a slice, as introduced by the slice
+          patterns (`[x, rest @ ..]`): we give the bounds of the subslice.
+          If the boolean is [true], the upper bound is counted from the
+          end. *)
 [@@deriving
   show,
+    ord,
     visitors
       {
         name = "iter_projection_elem";
@@ -80,6 +102,7 @@ type projection_elem = Deref | DerefBox | Field of field_proj_kind * field_id
 type projection = projection_elem list
 [@@deriving
   show,
+    ord,
     visitors
       {
         name = "iter_projection";
@@ -112,6 +135,7 @@ class ['self] map_place_base =
 type place = { var_id : var_id; projection : projection }
 [@@deriving
   show,
+    ord,
     visitors
       {
         name = "iter_place";
@@ -131,22 +155,14 @@ type place = { var_id : var_id; projection : projection }
 
 type borrow_kind = Shared | Mut | TwoPhaseMut | Shallow [@@deriving show]
 
-(* Remark: no `ArrayToSlice` variant: it gets eliminated in a micro-pass *)
-type unop =
+type unop = Types.unop =
   | Not
   | Neg
   | Cast of integer_type * integer_type
-      (** Cast an integer from a source type to a target type *)
+  | PtrCast of ptr_cast_kind
 [@@deriving show, ord]
 
-(** A binary operation
-
-    Note that we merge checked binops and unchecked binops: we perform a
-    micro-pass on the MIR AST to remove the assertions introduced by rustc,
-    and later extract the binops which can fail (addition, substraction, etc.)
-    or have preconditions (division, remainder...) to monadic functions.
- *)
-type binop =
+type binop = Types.binop =
   | BitXor
   | BitAnd
   | BitOr
@@ -201,9 +217,15 @@ class ['self] map_operand_base =
     method visit_ety : 'env -> ety -> ety = fun _ x -> x
   end
 
-type operand = Copy of place | Move of place | Constant of ety * literal
+type operand =
+  | Copy of place
+  | Move of place
+  | Constant of ety * literal
+  | ConstGenericVar of ety * const_generic_var_id
+      (** A const generic variable used as a value (`N` in `x + N`) *)
 [@@deriving
   show,
+    ord,
     visitors
       {
         name = "iter_operand";
@@ -221,11 +243,21 @@ type operand = Copy of place | Move of place | Constant of ety * literal
         concrete = true;
       }]
 
+(** The kind of a closure, which determines how the closure state is given
+    to the closure body *)
+type closure_kind =
+  | Fn  (** The state is borrowed immutably (the closure implements `Fn`) *)
+  | FnMut  (** The state is borrowed mutably (the closure implements `FnMut`) *)
+  | FnOnce  (** The state is moved (the closure implements `FnOnce`) *)
+[@@deriving show]
+
 (** Ancestor the operand iter visitor *)
 class ['self] iter_aggregate_kind_base =
   object (_self : 'self)
     inherit [_] iter_operand
     method visit_erased_region : 'env -> erased_region -> unit = fun _ _ -> ()
+    method visit_closure_kind : 'env -> closure_kind -> unit = fun _ _ -> ()
+    method visit_ref_kind : 'env -> ref_kind -> unit = fun _ _ -> ()
   end
 
 (** Ancestor the operand map visitor *)
@@ -235,6 +267,11 @@ class ['self] map_aggregate_kind_base =
 
     method visit_erased_region : 'env -> erased_region -> erased_region =
       fun _ x -> x
+
+    method visit_closure_kind : 'env -> closure_kind -> closure_kind =
+      fun _ x -> x
+
+    method visit_ref_kind : 'env -> ref_kind -> ref_kind = fun _ x -> x
   end
 
 (** An aggregated ADT.
@@ -268,8 +305,40 @@ type aggregate_kind =
       * erased_region list
       * ety list
       * const_generic list
-  | AggregatedRange of ety (* TODO: merge with the Rust *)
+  | AggregatedStructUpdate of type_decl_id * operand * (field_id * operand) list
+      (** A structure built with the struct update syntax
+          ([Foo { x: 1, ..a }]): we give the structure identifier, the
+          structure the fields which are not updated come from, and the
+          updated fields. The rvalue has no other operands. *)
+  | AggregatedRange of assumed_ty * ety list
+      (** A range ([a..b], [a..], etc.): we give the range type (one of the
+          range types of {!Types.assumed_ty}) and its type parameters (there
+          are none for [RangeFull]). The operands are the fields. *)
+  | AggregatedPhantomData of ety
+      (** A [PhantomData] value (there are no operands): we give the type
+          parameter *)
   | AggregatedArray of ety * const_generic
+  | AggregatedRepeat of ety * operand * const_generic
+      (** An array whose elements are all the same ([[x; N]]): we give the
+          type of the elements, the repeated element and the length. The
+          rvalue has no other operands. *)
+  | AggregatedClosure of fun_decl_id * closure_kind * ety list
+      (** A closure: we give the identifier of the function implementing the
+          closure body, the closure kind, and the types of the captured
+          variables (the operands of the aggregate) *)
+  | AggregatedUnion of
+      type_decl_id
+      * field_id
+      * erased_region list
+      * ety list
+      * const_generic list
+      (** A union value: we give the union identifier, the field we initialize
+          and the generic arguments. There is exactly one operand. *)
+  | AggregatedRawPtr of ety * ref_kind
+      (** A raw pointer built from its parts: we give the type of the pointed
+          value and the kind of the pointer. There are two operands: the data
+          pointer and the metadata ([()] for the pointers to sized values,
+          the length for the pointers to slices). *)
 [@@deriving
   show,
     visitors
@@ -293,8 +362,6 @@ type aggregate_kind =
 class ['self] iter_rvalue_base =
   object (_self : 'self)
     inherit [_] iter_aggregate_kind
-    method visit_unop : 'env -> unop -> unit = fun _ _ -> ()
-    method visit_binop : 'env -> binop -> unit = fun _ _ -> ()
     method visit_borrow_kind : 'env -> borrow_kind -> unit = fun _ _ -> ()
   end
 
@@ -302,8 +369,6 @@ class ['self] iter_rvalue_base =
 class ['self] map_rvalue_base =
   object (_self : 'self)
     inherit [_] map_aggregate_kind
-    method visit_unop : 'env -> unop -> unop = fun _ x -> x
-    method visit_binop : 'env -> binop -> binop = fun _ x -> x
     method visit_borrow_kind : 'env -> borrow_kind -> borrow_kind = fun _ x -> x
   end
 
@@ -311,11 +376,19 @@ class ['self] map_rvalue_base =
 type rvalue =
   | Use of operand
   | Ref of place * borrow_kind
+  | RawPtr of place * ref_kind
+      (** Raw borrow (`&raw const p`, `&raw mut p`) *)
   | UnaryOp of unop * operand
   | BinaryOp of binop * operand * operand
   | Discriminant of place
   | Aggregate of aggregate_kind * operand list
   | Global of global_decl_id
+  | FnPtr of fun_decl_id * ety list * const_generic list
+      (** A pointer to a function: we give the function identifier and the
+          generic arguments *)
+  | Len of place * ety * const_generic option
+      (** The length of an array or a slice: we give the type of the
+          elements and, for the arrays, the length *)
 [@@deriving
   show,
     visitors
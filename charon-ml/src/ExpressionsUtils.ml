@@ -1,7 +1,9 @@
 module E = Expressions
 
 let unop_can_fail (unop : E.unop) : bool =
-  match unop with Neg | Cast _ -> true | Not -> false
+  match unop with
+  | Neg | Cast _ | PtrCast (IntToPtr _ | PtrToInt _) -> true
+  | Not | PtrCast PtrToPtr -> false
 
 let binop_can_fail (binop : E.binop) : bool =
   match binop with
@@ -6,8 +6,12 @@ open Types
 open PrimitiveValues
 open Expressions
 open Meta
-module FunDeclId = IdGen ()
+module FunDeclId = Types.FunDeclId
 module GlobalDeclId = Expressions.GlobalDeclId
+module AssertionId = IdGen ()
+
+(** The identifier of an assertion, see {!LlbcAst.raw_statement.TaggedAssert} *)
+type assertion_id = AssertionId.id [@@deriving show, ord]
 
 (** A variable, as used in a function definition *)
 type var = {
@@ -47,7 +51,16 @@ type assumed_fun_id =
   | SliceSubsliceMut
 [@@deriving show, ord]
 
-type fun_id = Regular of FunDeclId.id | Assumed of assumed_fun_id
+type fun_id =
+  | Regular of FunDeclId.id
+  | Assumed of assumed_fun_id
+  | Virtual of trait_name * string * int
+      (** A call through a trait object (virtual dispatch): we give the name
+          of the trait, the name of the method and the index of the method in
+          the vtable. The first argument of the call is the trait object. *)
+  | Ptr of operand
+      (** An indirect call through a function pointer: the operand evaluates
+          to the function pointer *)
 [@@deriving show, ord]
 
 (** Ancestor the AST iter visitors *)
@@ -127,12 +140,16 @@ type call = {
 class ['self] iter_statement_base =
   object (_self : 'self)
     inherit [_] iter_call
+    method visit_assertion_id : 'env -> assertion_id -> unit = fun _ _ -> ()
   end
 
 (** Ancestor the {!LlbcAst.statement} and {!Charon.UllbcAst.statement} map visitors *)
 class ['self] map_statement_base =
   object (_self : 'self)
     inherit [_] map_call
+
+    method visit_assertion_id : 'env -> assertion_id -> assertion_id =
+      fun _ x -> x
   end
 
 (** A function signature, as used when declaring functions *)
@@ -68,6 +68,9 @@ let file_name_of_json (js : json) : (file_name, string) result =
     | `Assoc [ ("Local", name) ] ->
         let* name = string_of_json name in
         Ok (Local name)
+    | `Assoc [ ("NotReal", name) ] ->
+        let* name = string_of_json name in
+        Ok (NotReal name)
     | _ -> Error "")
 
 (** Deserialize a map from file id to file name.
@@ -112,7 +115,14 @@ let meta_of_json (id_to_file : id_to_file_map) (js : json) :
     (meta, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
-    | `Assoc [ ("span", span); ("generated_from_span", generated_from_span) ] ->
+    | `Assoc
+        [
+          ("span", span);
+          ("generated_from_span", generated_from_span);
+          ("is_from_macro", _);
+          ("macro_name", _);
+        ] ->
+        (* We ignore the macro information *)
         let* span = span_of_json id_to_file span in
         let* generated_from_span =
           option_of_json (span_of_json id_to_file) generated_from_span
@@ -137,6 +147,9 @@ let name_of_json (js : json) : (name, string) result =
 let fun_name_of_json (js : json) : (fun_name, string) result =
   combine_error_msgs js __FUNCTION__ (name_of_json js)
 
+let trait_name_of_json (js : json) : (trait_name, string) result =
+  combine_error_msgs js __FUNCTION__ (name_of_json js)
+
 let type_var_of_json (js : json) : (T.type_var, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
@@ -198,7 +211,8 @@ let const_generic_var_of_json (js : json) : (T.const_generic_var, string) result
     =
   combine_error_msgs js __FUNCTION__
     (match js with
-    | `Assoc [ ("index", index); ("name", name); ("ty", ty) ] ->
+    | `Assoc [ ("index", index); ("name", name); ("ty", ty); ("default", _) ] ->
+        (* We ignore the default value *)
         let* index = T.ConstGenericVarId.id_of_json index in
         let* name = string_of_json name in
         let* ty = literal_type_of_json ty in
@@ -221,6 +235,12 @@ let assumed_ty_of_json (js : json) : (T.assumed_ty, string) result =
     | `String "Slice" -> Ok T.Slice
     | `String "Str" -> Ok T.Str
     | `String "Range" -> Ok T.Range
+    | `String "RangeFrom" -> Ok T.RangeFrom
+    | `String "RangeTo" -> Ok T.RangeTo
+    | `String "RangeFull" -> Ok T.RangeFull
+    | `String "RangeInclusive" -> Ok T.RangeInclusive
+    | `String "PhantomData" -> Ok T.PhantomData
+    | `String "UnsafeCell" -> Ok T.UnsafeCell
     | _ -> Error "")
 
 let type_id_of_json (js : json) : (T.type_id, string) result =
@@ -252,7 +272,8 @@ let scalar_value_of_json (js : json) : (PV.scalar_value, string) result =
   let res =
     combine_error_msgs js __FUNCTION__
       (match js with
-      | `Assoc [ ("Isize", bi) ] ->
+      | `Assoc [ ("Isize", `List [ _ptr_size; bi ]) ] ->
+          (* We ignore the pointer size *)
           let* bi = big_int_of_json bi in
           Ok { PV.value = bi; int_ty = Isize }
       | `Assoc [ ("I8", bi) ] ->
@@ -270,7 +291,8 @@ let scalar_value_of_json (js : json) : (PV.scalar_value, string) result =
       | `Assoc [ ("I128", bi) ] ->
           let* bi = big_int_of_json bi in
           Ok { PV.value = bi; int_ty = I128 }
-      | `Assoc [ ("Usize", bi) ] ->
+      | `Assoc [ ("Usize", `List [ _ptr_size; bi ]) ] ->
+          (* We ignore the pointer size *)
           let* bi = big_int_of_json bi in
           Ok { PV.value = bi; int_ty = Usize }
       | `Assoc [ ("U8", bi) ] ->
@@ -311,9 +333,59 @@ let literal_of_json (js : json) : (PV.literal, string) result =
     | `Assoc [ ("Char", v) ] ->
         let* v = char_of_json v in
         Ok (PV.Char v)
+    | `Assoc [ ("RawPtr", addr) ] ->
+        let* addr = big_int_of_json addr in
+        Ok (PV.RawPtrAddr addr)
+    | `Assoc [ ("Str", v) ] ->
+        let* v = string_of_json v in
+        Ok (PV.Str v)
     | _ -> Error "")
 
-let const_generic_of_json (js : json) : (T.const_generic, string) result =
+let ptr_cast_kind_of_json (js : json) : (T.ptr_cast_kind, string) result =
+  match js with
+  | `String "PtrToPtr" -> Ok T.PtrToPtr
+  | `Assoc [ ("IntToPtr", int_ty) ] ->
+      let* int_ty = integer_type_of_json int_ty in
+      Ok (T.IntToPtr int_ty)
+  | `Assoc [ ("PtrToInt", int_ty) ] ->
+      let* int_ty = integer_type_of_json int_ty in
+      Ok (T.PtrToInt int_ty)
+  | _ -> Error ("ptr_cast_kind_of_json failed on:" ^ show js)
+
+let unop_of_json (js : json) : (E.unop, string) result =
+  match js with
+  | `String "Not" -> Ok E.Not
+  | `String "Neg" -> Ok E.Neg
+  | `Assoc [ ("Cast", `List [ src_ty; tgt_ty ]) ] ->
+      let* src_ty = integer_type_of_json src_ty in
+      let* tgt_ty = integer_type_of_json tgt_ty in
+      Ok (E.Cast (src_ty, tgt_ty))
+  | `Assoc [ ("PtrCast", kind) ] ->
+      let* kind = ptr_cast_kind_of_json kind in
+      Ok (E.PtrCast kind)
+  | _ -> Error ("unop_of_json failed on:" ^ show js)
+
+let binop_of_json (js : json) : (E.binop, string) result =
+  match js with
+  | `String "BitXor" -> Ok E.BitXor
+  | `String "BitAnd" -> Ok E.BitAnd
+  | `String "BitOr" -> Ok E.BitOr
+  | `String "Eq" -> Ok E.Eq
+  | `String "Lt" -> Ok E.Lt
+  | `String "Le" -> Ok E.Le
+  | `String "Ne" -> Ok E.Ne
+  | `String "Ge" -> Ok E.Ge
+  | `String "Gt" -> Ok E.Gt
+  | `String "Div" -> Ok E.Div
+  | `String "Rem" -> Ok E.Rem
+  | `String "Add" -> Ok E.Add
+  | `String "Sub" -> Ok E.Sub
+  | `String "Mul" -> Ok E.Mul
+  | `String "Shl" -> Ok E.Shl
+  | `String "Shr" -> Ok E.Shr
+  | _ -> Error ("binop_of_json failed on:" ^ show js)
+
+let rec const_generic_of_json (js : json) : (T.const_generic, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
     | `Assoc [ ("Global", id) ] ->
@@ -325,6 +397,32 @@ let const_generic_of_json (js : json) : (T.const_generic, string) result =
     | `Assoc [ ("Value", lit) ] ->
         let* lit = literal_of_json lit in
         Ok (T.ConstGenericValue lit)
+    | `Assoc [ ("Expr", e) ] ->
+        let* e = const_generic_expr_of_json e in
+        Ok (T.ConstGenericExpr e)
+    | _ -> Error "")
+
+and const_generic_expr_of_json (js : json) :
+    (T.const_generic_expr, string) result =
+  combine_error_msgs js __FUNCTION__
+    (match js with
+    | `Assoc [ ("BinaryOp", `List [ binop; cg0; cg1 ]) ] ->
+        let* binop = binop_of_json binop in
+        let* cg0 = const_generic_of_json cg0 in
+        let* cg1 = const_generic_of_json cg1 in
+        Ok (T.ConstGenericBinaryOp (binop, cg0, cg1))
+    | `Assoc [ ("UnaryOp", `List [ unop; cg ]) ] ->
+        let* unop = unop_of_json unop in
+        let* cg = const_generic_of_json cg in
+        Ok (T.ConstGenericUnaryOp (unop, cg))
+    | `Assoc [ ("FunCall", `List [ fid; args ]) ] ->
+        let* fid = T.FunDeclId.id_of_json fid in
+        let* args = list_of_json const_generic_of_json args in
+        Ok (T.ConstGenericFunCall (fid, args))
+    | `Assoc [ ("Cast", `List [ cg; ty ]) ] ->
+        let* cg = const_generic_of_json cg in
+        let* ty = literal_type_of_json ty in
+        Ok (T.ConstGenericCast (cg, ty))
     | _ -> Error "")
 
 let rec ty_of_json (r_of_json : json -> ('r, string) result) (js : json) :
@@ -350,6 +448,53 @@ let rec ty_of_json (r_of_json : json -> ('r, string) result) (js : json) :
         let* ty = ty_of_json r_of_json ty in
         let* ref_kind = ref_kind_of_json ref_kind in
         Ok (T.Ref (region, ty, ref_kind))
+    | `Assoc [ ("RawPtr", `List [ ty; ref_kind ]) ] ->
+        let* ty = ty_of_json r_of_json ty in
+        let* ref_kind = ref_kind_of_json ref_kind in
+        Ok (T.RawPtr (ty, ref_kind))
+    | `Assoc [ ("FnPtr", `List [ inputs; output ]) ] ->
+        let* inputs = list_of_json (ty_of_json r_of_json) inputs in
+        let* output = ty_of_json r_of_json output in
+        Ok (T.FnPtr (inputs, output))
+    | `Assoc [ ("TraitAssoc", `List [ trait_id; id; name ]) ] ->
+        let* trait_id = T.TraitDeclId.id_of_json trait_id in
+        let* id = T.TypeVarId.id_of_json id in
+        let* name = string_of_json name in
+        Ok (T.TraitAssoc (trait_id, id, name))
+    | `Assoc [ ("TraitObject", `List [ traits; region ]) ] ->
+        let* traits = list_of_json (trait_ref_of_json r_of_json) traits in
+        let* region = r_of_json region in
+        Ok (T.TraitObject (traits, region))
+    | _ -> Error "")
+
+and trait_ref_of_json (r_of_json : json -> ('r, string) result) (js : json) :
+    ('r T.trait_ref, string) result =
+  combine_error_msgs js __FUNCTION__
+    (match js with
+    | `Assoc
+        [
+          ("trait_name", trait_name);
+          ("types", types);
+          ("const_generics", const_generics);
+          ("assoc_types", assoc_types);
+        ] ->
+        let* trait_name = trait_name_of_json trait_name in
+        let* trait_types = list_of_json (ty_of_json r_of_json) types in
+        let* trait_const_generics =
+          list_of_json const_generic_of_json const_generics
+        in
+        let* trait_assoc_types =
+          list_of_json
+            (pair_of_json string_of_json (ty_of_json r_of_json))
+            assoc_types
+        in
+        Ok
+          {
+            T.trait_name;
+            trait_types;
+            trait_const_generics;
+            trait_assoc_types;
+          }
     | _ -> Error "")
 
 let sty_of_json (js : json) : (T.sty, string) result =
@@ -362,7 +507,10 @@ let field_of_json (id_to_file : id_to_file_map) (js : json) :
     (T.field, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
-    | `Assoc [ ("meta", meta); ("name", name); ("ty", ty) ] ->
+    | `Assoc
+        [ ("meta", meta); ("name", name); ("ty", ty); ("interior_mut", _) ]
+      ->
+        (* We ignore the interior mutability flag *)
         let* meta = meta_of_json id_to_file meta in
         let* name = option_of_json string_of_json name in
         let* ty = sty_of_json ty in
@@ -390,6 +538,15 @@ let type_decl_kind_of_json (id_to_file : id_to_file_map) (js : json) :
     | `Assoc [ ("Enum", variants) ] ->
         let* variants = list_of_json (variant_of_json id_to_file) variants in
         Ok (T.Enum variants)
+    | `Assoc [ ("Union", fields) ] ->
+        let* fields = list_of_json (field_of_json id_to_file) fields in
+        Ok (T.Union fields)
+    | `Assoc [ ("ImplTrait", traits) ] ->
+        let* traits = list_of_json (trait_ref_of_json region_of_json) traits in
+        Ok (T.ImplTrait traits)
+    | `Assoc [ ("Alias", ty) ] ->
+        let* ty = sty_of_json ty in
+        Ok (T.Alias ty)
     | `String "Opaque" -> Ok T.Opaque
     | _ -> Error "")
 
@@ -420,8 +577,16 @@ let type_decl_of_json (id_to_file : id_to_file_map) (js : json) :
           ("type_params", type_params);
           ("const_generic_params", const_generic_params);
           ("kind", kind);
+          ("repr", _);
+          ("is_root", _);
+          ("stable_id", _);
+          ("local_methods", _);
+          ("variance", _);
+          ("is_recursive", _);
           ("regions_hierarchy", regions_hierarchy);
         ] ->
+        (* We ignore the additional information about the type (its
+           representation, the variance of its parameters, etc.) *)
         let* def_id = T.TypeDeclId.id_of_json def_id in
         let* meta = meta_of_json id_to_file meta in
         let* name = name_of_json name in
@@ -470,6 +635,9 @@ let field_proj_kind_of_json (js : json) : (E.field_proj_kind, string) result =
     | `Assoc [ ("ProjOption", variant_id) ] ->
         let* variant_id = T.VariantId.id_of_json variant_id in
         Ok (E.ProjOption variant_id)
+    | `Assoc [ ("ProjRange", aty) ] ->
+        let* aty = assumed_ty_of_json aty in
+        Ok (E.ProjRange aty)
     | _ -> Error "")
 
 let projection_elem_of_json (js : json) : (E.projection_elem, string) result =
@@ -477,10 +645,32 @@ let projection_elem_of_json (js : json) : (E.projection_elem, string) result =
     (match js with
     | `String "Deref" -> Ok E.Deref
     | `String "DerefBox" -> Ok E.DerefBox
+    | `String "DerefRawPtr" -> Ok E.DerefRawPtr
     | `Assoc [ ("Field", `List [ proj_kind; field_id ]) ] ->
         let* proj_kind = field_proj_kind_of_json proj_kind in
         let* field_id = T.FieldId.id_of_json field_id in
         Ok (E.Field (proj_kind, field_id))
+    | `Assoc [ ("UnionField", `List [ id; field_id ]) ] ->
+        let* id = T.TypeDeclId.id_of_json id in
+        let* field_id = T.FieldId.id_of_json field_id in
+        Ok (E.UnionField (id, field_id))
+    | `Assoc
+        [
+          ( "ConstantIndex",
+            `Assoc [ ("offset", offset); ("from_end", from_end) ] );
+        ] ->
+        let* offset = int_of_json offset in
+        let* from_end = bool_of_json from_end in
+        Ok (E.ConstantIndex (offset, from_end))
+    | `Assoc
+        [
+          ( "Subslice",
+            `Assoc [ ("from", from); ("to", to_); ("from_end", from_end) ] );
+        ] ->
+        let* from = int_of_json from in
+        let* to_ = int_of_json to_ in
+        let* from_end = bool_of_json from_end in
+        Ok (E.Subslice (from, to_, from_end))
     | _ -> Error ("projection_elem_of_json failed on:" ^ show js))
 
 let projection_of_json (js : json) : (E.projection, string) result =
@@ -503,36 +693,6 @@ let borrow_kind_of_json (js : json) : (E.borrow_kind, string) result =
   | `String "Shallow" -> Ok E.Shallow
   | _ -> Error ("borrow_kind_of_json failed on:" ^ show js)
 
-let unop_of_json (js : json) : (E.unop, string) result =
-  match js with
-  | `String "Not" -> Ok E.Not
-  | `String "Neg" -> Ok E.Neg
-  | `Assoc [ ("Cast", `List [ src_ty; tgt_ty ]) ] ->
-      let* src_ty = integer_type_of_json src_ty in
-      let* tgt_ty = integer_type_of_json tgt_ty in
-      Ok (E.Cast (src_ty, tgt_ty))
-  | _ -> Error ("unop_of_json failed on:" ^ show js)
-
-let binop_of_json (js : json) : (E.binop, string) result =
-  match js with
-  | `String "BitXor" -> Ok E.BitXor
-  | `String "BitAnd" -> Ok E.BitAnd
-  | `String "BitOr" -> Ok E.BitOr
-  | `String "Eq" -> Ok E.Eq
-  | `String "Lt" -> Ok E.Lt
-  | `String "Le" -> Ok E.Le
-  | `String "Ne" -> Ok E.Ne
-  | `String "Ge" -> Ok E.Ge
-  | `String "Gt" -> Ok E.Gt
-  | `String "Div" -> Ok E.Div
-  | `String "Rem" -> Ok E.Rem
-  | `String "Add" -> Ok E.Add
-  | `String "Sub" -> Ok E.Sub
-  | `String "Mul" -> Ok E.Mul
-  | `String "Shl" -> Ok E.Shl
-  | `String "Shr" -> Ok E.Shr
-  | _ -> Error ("binop_of_json failed on:" ^ show js)
-
 let literal_of_json (js : json) : (PV.literal, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
@@ -545,6 +705,12 @@ let literal_of_json (js : json) : (PV.literal, string) result =
     | `Assoc [ ("Char", v) ] ->
         let* v = char_of_json v in
         Ok (PV.Char v)
+    | `Assoc [ ("RawPtr", addr) ] ->
+        let* addr = big_int_of_json addr in
+        Ok (PV.RawPtrAddr addr)
+    | `Assoc [ ("Str", v) ] ->
+        let* v = string_of_json v in
+        Ok (PV.Str v)
     | _ -> Error "")
 
 let operand_of_json (js : json) : (E.operand, string) result =
@@ -556,12 +722,23 @@ let operand_of_json (js : json) : (E.operand, string) result =
     | `Assoc [ ("Move", place) ] ->
         let* place = place_of_json place in
         Ok (E.Move place)
+    | `Assoc [ ("Const", `List [ ty; `Assoc [ ("Var", id) ] ]) ] ->
+        let* ty = ety_of_json ty in
+        let* id = T.ConstGenericVarId.id_of_json id in
+        Ok (E.ConstGenericVar (ty, id))
     | `Assoc [ ("Const", `List [ ty; cv ]) ] ->
         let* ty = ety_of_json ty in
         let* cv = literal_of_json cv in
         Ok (E.Constant (ty, cv))
     | _ -> Error "")
 
+let closure_kind_of_json (js : json) : (E.closure_kind, string) result =
+  match js with
+  | `String "Fn" -> Ok E.Fn
+  | `String "FnMut" -> Ok E.FnMut
+  | `String "FnOnce" -> Ok E.FnOnce
+  | _ -> Error ("closure_kind_of_json failed on:" ^ show js)
+
 let aggregate_kind_of_json (js : json) : (E.aggregate_kind, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
@@ -579,13 +756,56 @@ let aggregate_kind_of_json (js : json) : (E.aggregate_kind, string) result =
         let* tys = list_of_json ety_of_json tys in
         let* cgs = list_of_json const_generic_of_json cgs in
         Ok (E.AggregatedAdt (id, opt_variant_id, regions, tys, cgs))
-    | `Assoc [ ("Range", ty) ] ->
+    | `Assoc
+        [
+          ( "StructUpdate",
+            `Assoc
+              [
+                ("type_id", id);
+                ("base", base);
+                ("updated_fields", updated_fields);
+              ] );
+        ] ->
+        let* id = T.TypeDeclId.id_of_json id in
+        let* base = operand_of_json base in
+        let* updated_fields =
+          list_of_json
+            (pair_of_json T.FieldId.id_of_json operand_of_json)
+            updated_fields
+        in
+        Ok (E.AggregatedStructUpdate (id, base, updated_fields))
+    | `Assoc [ ("Range", `List [ aty; tys ]) ] ->
+        let* aty = assumed_ty_of_json aty in
+        let* tys = list_of_json ety_of_json tys in
+        Ok (E.AggregatedRange (aty, tys))
+    | `Assoc [ ("PhantomData", ty) ] ->
         let* ty = ety_of_json ty in
-        Ok (E.AggregatedRange ty)
+        Ok (E.AggregatedPhantomData ty)
     | `Assoc [ ("Array", `List [ ty; cg ]) ] ->
         let* ty = ety_of_json ty in
         let* cg = const_generic_of_json cg in
         Ok (E.AggregatedArray (ty, cg))
+    | `Assoc [ ("Repeat", `List [ ty; op; cg ]) ] ->
+        let* ty = ety_of_json ty in
+        let* op = operand_of_json op in
+        let* cg = const_generic_of_json cg in
+        Ok (E.AggregatedRepeat (ty, op, cg))
+    | `Assoc [ ("Closure", `List [ fid; kind; tys ]) ] ->
+        let* fid = T.FunDeclId.id_of_json fid in
+        let* kind = closure_kind_of_json kind in
+        let* tys = list_of_json ety_of_json tys in
+        Ok (E.AggregatedClosure (fid, kind, tys))
+    | `Assoc [ ("Union", `List [ id; field_id; regions; tys; cgs ]) ] ->
+        let* id = T.TypeDeclId.id_of_json id in
+        let* field_id = T.FieldId.id_of_json field_id in
+        let* regions = list_of_json erased_region_of_json regions in
+        let* tys = list_of_json ety_of_json tys in
+        let* cgs = list_of_json const_generic_of_json cgs in
+        Ok (E.AggregatedUnion (id, field_id, regions, tys, cgs))
+    | `Assoc [ ("RawPtr", `List [ ty; ref_kind ]) ] ->
+        let* ty = ety_of_json ty in
+        let* ref_kind = ref_kind_of_json ref_kind in
+        Ok (E.AggregatedRawPtr (ty, ref_kind))
     | _ -> Error "")
 
 let rvalue_of_json (js : json) : (E.rvalue, string) result =
@@ -598,6 +818,10 @@ let rvalue_of_json (js : json) : (E.rvalue, string) result =
         let* place = place_of_json place in
         let* borrow_kind = borrow_kind_of_json borrow_kind in
         Ok (E.Ref (place, borrow_kind))
+    | `Assoc [ ("RawPtr", `List [ place; ref_kind ]) ] ->
+        let* place = place_of_json place in
+        let* ref_kind = ref_kind_of_json ref_kind in
+        Ok (E.RawPtr (place, ref_kind))
     | `Assoc [ ("UnaryOp", `List [ unop; op ]) ] ->
         let* unop = unop_of_json unop in
         let* op = operand_of_json op in
@@ -617,6 +841,16 @@ let rvalue_of_json (js : json) : (E.rvalue, string) result =
         let* aggregate_kind = aggregate_kind_of_json aggregate_kind in
         let* ops = list_of_json operand_of_json ops in
         Ok (E.Aggregate (aggregate_kind, ops))
+    | `Assoc [ ("FnPtr", `List [ fid; tys; cgs ]) ] ->
+        let* fid = T.FunDeclId.id_of_json fid in
+        let* tys = list_of_json ety_of_json tys in
+        let* cgs = list_of_json const_generic_of_json cgs in
+        Ok (E.FnPtr (fid, tys, cgs))
+    | `Assoc [ ("Len", `List [ place; ty; cg ]) ] ->
+        let* place = place_of_json place in
+        let* ty = ety_of_json ty in
+        let* cg = option_of_json const_generic_of_json cg in
+        Ok (E.Len (place, ty, cg))
     | _ -> Error "")
 
 let assumed_fun_id_of_json (js : json) : (A.assumed_fun_id, string) result =
@@ -654,6 +888,14 @@ let fun_id_of_json (js : json) : (A.fun_id, string) result =
     | `Assoc [ ("Assumed", fid) ] ->
         let* fid = assumed_fun_id_of_json fid in
         Ok (A.Assumed fid)
+    | `Assoc [ ("Virtual", `List [ trait_name; method_name; index ]) ] ->
+        let* trait_name = trait_name_of_json trait_name in
+        let* method_name = string_of_json method_name in
+        let* index = int_of_json index in
+        Ok (A.Virtual (trait_name, method_name, index))
+    | `Assoc [ ("Ptr", op) ] ->
+        let* op = operand_of_json op in
+        Ok (A.Ptr op)
     | _ -> Error "")
 
 let fun_sig_of_json (js : json) : (A.fun_sig, string) result =
@@ -665,10 +907,12 @@ let fun_sig_of_json (js : json) : (A.fun_sig, string) result =
           ("num_early_bound_regions", num_early_bound_regions);
           ("type_params", type_params);
           ("const_generic_params", const_generic_params);
+          ("predicates", _);
           ("inputs", inputs);
           ("output", output);
           ("regions_hierarchy", regions_hierarchy);
         ] ->
+        (* We ignore the predicates (the where clauses) *)
         let* region_params = list_of_json region_var_of_json region_params in
         let* num_early_bound_regions = int_of_json num_early_bound_regions in
         let* regions_hierarchy = region_var_groups_of_json regions_hierarchy in
@@ -744,7 +988,25 @@ let gfun_decl_of_json (body_of_json : json -> ('body, string) result)
           ("name", name);
           ("signature", signature);
           ("body", body);
+          ("builtin", _);
+          ("calling_convention", _);
+          ("is_const_fn", _);
+          ("is_unsafe", _);
+          ("calls_unsafe", _);
+          ("self_type", _);
+          ("trait_id", _);
+          ("trait_impl_info", _);
+          ("is_trait_default", _);
+          ("overrides_default", _);
+          ("captures", _);
+          ("implemented_drop", _);
+          ("attributes", _);
+          ("is_root", _);
+          ("stable_id", _);
         ] ->
+        (* We ignore the remaining fields, which give additional information
+           about the function (its built-in kind, its calling convention,
+           etc.) *)
         let* def_id = A.FunDeclId.id_of_json def_id in
         let* meta = meta_of_json id_to_file meta in
         let* name = fun_name_of_json name in
@@ -778,7 +1040,12 @@ let gglobal_decl_of_json (body_of_json : json -> ('body, string) result)
           ("name", name);
           ("ty", ty);
           ("body", body);
+          ("trait_id", _);
+          ("is_root", _);
+          ("stable_id", _);
         ] ->
+        (* We ignore the remaining fields, which give additional information
+           about the global (the trait it belongs to, etc.) *)
         let* global_id = A.GlobalDeclId.id_of_json def_id in
         let* meta = meta_of_json id_to_file meta in
         let* name = fun_name_of_json name in
@@ -841,3 +1108,16 @@ let length_of_json_list (js : json) : (int, string) result =
     (match js with
     | `List jsl -> Ok (List.length jsl)
     | _ -> Error ("not a list: " ^ show js))
+
+(** Remove the optional fields of a crate, which we ignore (they are omitted
+    depending on the options given to Charon), so that we can match on the
+    exact list of the remaining fields.
+ *)
+let crate_remove_optional_fields (js : json) : json =
+  match js with
+  | `Assoc fields ->
+      `Assoc
+        (List.filter
+           (fun (f, _) -> f <> "dependencies" && f <> "def_ids")
+           fields)
+  | _ -> js
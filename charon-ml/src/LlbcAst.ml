@@ -15,7 +15,14 @@ and raw_statement =
   | FakeRead of place
   | SetDiscriminant of place * variant_id
   | Drop of place
+  | StorageLive of var_id
+      (** See {!UllbcAst.raw_statement.StorageLive} *)
+  | StorageDead of var_id
+      (** See {!UllbcAst.raw_statement.StorageDead} *)
   | Assert of assertion
+  | TaggedAssert of assertion_id * assertion
+      (** An assertion with a unique identifier, which the verification
+          backends use to link their proof obligations to the source code *)
   | Call of call
   | Panic
   | Return
@@ -32,6 +39,48 @@ and raw_statement =
   | Sequence of statement * statement
   | Switch of switch
   | Loop of statement
+  | LetElse of place * variant_id list * statement
+      (** A [let ... else] statement, over an ADT:
+          {[
+            let Some(v) = x else { return; };
+          ]}
+          If the variant of the place is one of the given variants, we
+          continue with the subsequent statements, otherwise we evaluate the
+          [else] statement, which diverges. The bindings of the pattern are
+          introduced by the subsequent statements.
+       *)
+  | IfLet of place * variant_id list * statement * statement
+      (** An [if let ... else] statement, over an ADT:
+          {[
+            if let Some(v) = x { ... } else { ... }
+          ]}
+          If the variant of the place is one of the given variants, we
+          evaluate the first statement, otherwise we evaluate the second one.
+          As for [LetElse], the bindings of the pattern are introduced by the
+          first statement.
+       *)
+  | TryOp of try_op
+  | MacroExpansion of string
+      (** A placeholder for statements coming from the expansion of a macro,
+          which were removed to make the output more readable. The string is
+          the name of the macro. *)
+
+(** The [?] operator, which MIR desugars to a call to [Try::branch] followed
+    by a match over the resulting [ControlFlow]:
+    {[
+      let v = x?;
+    ]}
+    If [Try::branch(scrutinee)] is [Continue], we store its content in
+    [ok_place] and continue with the subsequent statements. Otherwise, we
+    store the residual in [err_place] and evaluate [err_return], which
+    diverges.
+ *)
+and try_op = {
+  scrutinee : operand;
+  ok_place : place;
+  err_place : place;
+  err_return : statement;
+}
 
 and switch =
   | If of operand * statement * statement
@@ -32,8 +32,9 @@ let mk_sequence (st1 : statement) (st2 : statement) : statement =
  *)
 let rec chain_statements (st1 : statement) (st2 : statement) : statement =
   match st1.content with
-  | SetDiscriminant _ | Assert _ | Call _ | Assign _ | FakeRead _ | Drop _
-  | Loop _ ->
+  | SetDiscriminant _ | Assert _ | TaggedAssert _ | Call _ | Assign _ | FakeRead _ | Drop _
+  | StorageLive _ | StorageDead _ | Loop _ | LetElse _ | TryOp _
+  | MacroExpansion _ ->
       (* Simply create a sequence *)
       mk_sequence st1 st2
   | Nop -> (* Ignore the nop *) st2
@@ -44,6 +45,13 @@ let rec chain_statements (st1 : statement) (st2 : statement) : statement =
       let meta = MetaUtils.combine_meta st1.meta st2.meta in
       let content = Switch (chain_statements_in_switch switch st2) in
       { meta; content }
+  | IfLet (p, variants, st3, st4) ->
+      (* Insert inside the branches *)
+      let meta = MetaUtils.combine_meta st1.meta st2.meta in
+      let content =
+        IfLet (p, variants, chain_statements st3 st2, chain_statements st4 st2)
+      in
+      { meta; content }
   | Sequence (st3, st4) ->
       (* Insert at the end of the statement *)
       mk_sequence st3 (chain_statements st4 st2)
@@ -44,9 +44,19 @@ and raw_statement_of_json (id_to_file : id_to_file_map) (js : json) :
     | `Assoc [ ("Drop", place) ] ->
         let* place = place_of_json place in
         Ok (A.Drop place)
+    | `Assoc [ ("StorageLive", var_id) ] ->
+        let* var_id = E.VarId.id_of_json var_id in
+        Ok (A.StorageLive var_id)
+    | `Assoc [ ("StorageDead", var_id) ] ->
+        let* var_id = E.VarId.id_of_json var_id in
+        Ok (A.StorageDead var_id)
     | `Assoc [ ("Assert", assertion) ] ->
         let* assertion = assertion_of_json assertion in
         Ok (A.Assert assertion)
+    | `Assoc [ ("TaggedAssert", `List [ id; assertion ]) ] ->
+        let* id = A.AssertionId.id_of_json id in
+        let* assertion = assertion_of_json assertion in
+        Ok (A.TaggedAssert (id, assertion))
     | `Assoc [ ("Call", call) ] ->
         let* call = call_of_json call in
         Ok (A.Call call)
@@ -69,6 +79,36 @@ and raw_statement_of_json (id_to_file : id_to_file_map) (js : json) :
     | `Assoc [ ("Loop", st) ] ->
         let* st = statement_of_json id_to_file st in
         Ok (A.Loop st)
+    | `Assoc [ ("LetElse", `List [ p; variants; else_st ]) ] ->
+        let* p = place_of_json p in
+        let* variants = list_of_json T.VariantId.id_of_json variants in
+        let* else_st = statement_of_json id_to_file else_st in
+        Ok (A.LetElse (p, variants, else_st))
+    | `Assoc [ ("IfLet", `List [ p; variants; st1; st2 ]) ] ->
+        let* p = place_of_json p in
+        let* variants = list_of_json T.VariantId.id_of_json variants in
+        let* st1 = statement_of_json id_to_file st1 in
+        let* st2 = statement_of_json id_to_file st2 in
+        Ok (A.IfLet (p, variants, st1, st2))
+    | `Assoc
+        [
+          ( "TryOp",
+            `Assoc
+              [
+                ("scrutinee", scrutinee);
+                ("ok_place", ok_place);
+                ("err_place", err_place);
+                ("err_return", err_return);
+              ] );
+        ] ->
+        let* scrutinee = operand_of_json scrutinee in
+        let* ok_place = place_of_json ok_place in
+        let* err_place = place_of_json err_place in
+        let* err_return = statement_of_json id_to_file err_return in
+        Ok (A.TryOp { A.scrutinee; ok_place; err_place; err_return })
+    | `Assoc [ ("MacroExpansion", name) ] ->
+        let* name = string_of_json name in
+        Ok (A.MacroExpansion name)
     | _ -> Error "")
 
 and switch_of_json (id_to_file : id_to_file_map) (js : json) :
@@ -159,7 +199,7 @@ let global_decl_of_json (id_to_file : id_to_file_map) (js : json)
 
 let crate_of_json (js : json) : (A.crate, string) result =
   combine_error_msgs js __FUNCTION__
-    (match js with
+    (match crate_remove_optional_fields js with
     | `Assoc
         [
           ("name", name);
@@ -168,7 +208,10 @@ let crate_of_json (js : json) : (A.crate, string) result =
           ("types", types);
           ("functions", functions);
           ("globals", globals);
+          ("traits", _);
+          ("modules", _);
         ] ->
+        (* We ignore the trait declarations and the module tree *)
         (* We first deserialize the declaration groups (which simply contain ids)
          * and all the declarations *butù* the globals *)
         let* name = string_of_json name in
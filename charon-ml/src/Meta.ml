@@ -11,6 +11,8 @@ type file_name =
   | Virtual of string  (** A remapped path (namely paths into stdlib) *)
   | Local of string
       (** A local path (a file coming from the current crate for instance) *)
+  | NotReal of string
+      (** A "not real" file name (macro, query, etc.) *)
 [@@deriving show]
 
 (** Span data *)
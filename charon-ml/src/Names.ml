@@ -51,6 +51,7 @@ type module_name = name [@@deriving show, ord]
 type type_name = name [@@deriving show, ord]
 type fun_name = name [@@deriving show, ord]
 type global_name = name [@@deriving show, ord]
+type trait_name = name [@@deriving show, ord]
 
 (** Filter the disambiguators equal to 0 in a name *)
 let filter_disambiguators_zero (n : name) : name =
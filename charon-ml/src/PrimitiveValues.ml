@@ -118,6 +118,7 @@ class ['self] iter_literal_base =
   object (_self : 'self)
     inherit [_] VisitorsRuntime.iter
     method visit_scalar_value : 'env -> scalar_value -> unit = fun _ _ -> ()
+    method visit_big_int : 'env -> big_int -> unit = fun _ _ -> ()
   end
 
 (** Ancestor the literal map visitor *)
@@ -127,6 +128,8 @@ class ['self] map_literal_base =
 
     method visit_scalar_value : 'env -> scalar_value -> scalar_value =
       fun _ x -> x
+
+    method visit_big_int : 'env -> big_int -> big_int = fun _ x -> x
   end
 
 (** A literal value.
@@ -134,7 +137,19 @@ class ['self] map_literal_base =
     Can be used by operands (in which case it represents a constant) or by
     the interpreter to represent a concrete, literal value.
  *)
-type literal = Scalar of scalar_value | Bool of bool | Char of char
+type literal =
+  | Scalar of scalar_value
+  | Bool of bool
+  | Char of char
+  | RawPtrAddr of big_int
+      (** A raw pointer constant, given by its address (this is mostly used
+          for the null pointer).
+
+          Remark: we can't name this variant `RawPtr` because of a name
+          collision with {!Expressions.rvalue.RawPtr} in the visitors. *)
+  | Str of string
+      (** A string literal. Its type is [&'static str]: the literal is the
+          value of the reference, not of the (unsized) string. *)
 [@@deriving
   show,
     ord,
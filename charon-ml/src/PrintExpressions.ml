@@ -62,12 +62,18 @@ let rec projection_to_string (fmt : expr_formatter) (s : string)
         match pe with
         | E.Deref -> "*(" ^ s ^ ")"
         | E.DerefBox -> "deref_box(" ^ s ^ ")"
+        | E.DerefRawPtr -> "deref_raw_ptr(" ^ s ^ ")"
         | E.Field (E.ProjOption variant_id, fid) ->
             assert (variant_id = T.option_some_id);
             assert (fid = T.FieldId.zero);
             "(" ^ s ^ " as Option::Some)." ^ T.FieldId.to_string fid
         | E.Field (E.ProjTuple _, fid) ->
             "(" ^ s ^ ")." ^ T.FieldId.to_string fid
+        | E.Field (E.ProjRange aty, fid) ->
+            let field_name =
+              List.nth (TU.range_field_names aty) (T.FieldId.to_int fid)
+            in
+            "(" ^ s ^ ")." ^ field_name
         | E.Field (E.ProjAdt (adt_id, opt_variant_id), fid) -> (
             let field_name =
               match fmt.adt_field_to_string adt_id opt_variant_id fid with
@@ -81,6 +87,22 @@ let rec projection_to_string (fmt : expr_formatter) (s : string)
                   fmt.adt_variant_to_string adt_id variant_id
                 in
                 "(" ^ s ^ " as " ^ variant_name ^ ")." ^ field_name)
+        | E.UnionField (adt_id, fid) ->
+            let field_name =
+              match fmt.adt_field_to_string adt_id None fid with
+              | Some field_name -> field_name
+              | None -> T.FieldId.to_string fid
+            in
+            "(" ^ s ^ " as union)." ^ field_name
+        | E.ConstantIndex (offset, from_end) ->
+            let minus = if from_end then "-" else "" in
+            "(" ^ s ^ ")[" ^ minus ^ string_of_int offset ^ "]"
+        | E.Subslice (from, 0, true) ->
+            "(" ^ s ^ ")[" ^ string_of_int from ^ "..]"
+        | E.Subslice (from, to_, from_end) ->
+            let minus = if from_end then "-" else "" in
+            "(" ^ s ^ ")[" ^ string_of_int from ^ ".." ^ minus
+            ^ string_of_int to_ ^ "]"
       in
       projection_to_string fmt s p'
 
@@ -88,35 +110,11 @@ let place_to_string (fmt : expr_formatter) (p : E.place) : string =
   let var = fmt.var_id_to_string p.E.var_id in
   projection_to_string fmt var p.E.projection
 
-let unop_to_string (unop : E.unop) : string =
-  match unop with
-  | E.Not -> "¬"
-  | E.Neg -> "-"
-  | E.Cast (src, tgt) ->
-      "cast<"
-      ^ PPV.integer_type_to_string src
-      ^ ","
-      ^ PPV.integer_type_to_string tgt
-      ^ ">"
+let closure_kind_to_string (kind : E.closure_kind) : string =
+  match kind with E.Fn -> "Fn" | E.FnMut -> "FnMut" | E.FnOnce -> "FnOnce"
 
-let binop_to_string (binop : E.binop) : string =
-  match binop with
-  | E.BitXor -> "^"
-  | E.BitAnd -> "&"
-  | E.BitOr -> "|"
-  | E.Eq -> "=="
-  | E.Lt -> "<"
-  | E.Le -> "<="
-  | E.Ne -> "!="
-  | E.Ge -> ">="
-  | E.Gt -> ">"
-  | E.Div -> "/"
-  | E.Rem -> "%"
-  | E.Add -> "+"
-  | E.Sub -> "-"
-  | E.Mul -> "*"
-  | E.Shl -> "<<"
-  | E.Shr -> ">>"
+let unop_to_string = PT.unop_to_string
+let binop_to_string = PT.binop_to_string
 
 let operand_to_string (fmt : expr_formatter) (op : E.operand) : string =
   match op with
@@ -126,6 +124,10 @@ let operand_to_string (fmt : expr_formatter) (op : E.operand) : string =
       "(" ^ PPV.literal_to_string cv ^ " : "
       ^ PT.ety_to_string (expr_to_etype_formatter fmt) ty
       ^ ")"
+  | E.ConstGenericVar (ty, id) ->
+      "(" ^ fmt.const_generic_var_id_to_string id ^ " : "
+      ^ PT.ety_to_string (expr_to_etype_formatter fmt) ty
+      ^ ")"
 
 let rvalue_to_string (fmt : expr_formatter) (rv : E.rvalue) : string =
   match rv with
@@ -137,12 +139,18 @@ let rvalue_to_string (fmt : expr_formatter) (rv : E.rvalue) : string =
       | E.Mut -> "&mut " ^ p
       | E.TwoPhaseMut -> "&two-phase " ^ p
       | E.Shallow -> "&shallow " ^ p)
+  | E.RawPtr (p, rk) -> (
+      let p = place_to_string fmt p in
+      match rk with T.Shared -> "&raw const " ^ p | T.Mut -> "&raw mut " ^ p)
   | E.UnaryOp (unop, op) -> unop_to_string unop ^ " " ^ operand_to_string fmt op
   | E.BinaryOp (binop, op1, op2) ->
       operand_to_string fmt op1 ^ " " ^ binop_to_string binop ^ " "
       ^ operand_to_string fmt op2
   | E.Discriminant p -> "discriminant(" ^ place_to_string fmt p ^ ")"
   | E.Global gid -> "global " ^ fmt.global_decl_id_to_string gid
+  | E.FnPtr (fid, _tys, _cgs) ->
+      "@FnPtr<" ^ fmt.fun_decl_id_to_string fid ^ ">"
+  | E.Len (p, _ty, _cg) -> "len(" ^ place_to_string fmt p ^ ")"
   | E.Aggregate (akind, ops) -> (
       let ops = List.map (operand_to_string fmt) ops in
       match akind with
@@ -178,11 +186,54 @@ let rvalue_to_string (fmt : expr_formatter) (rv : E.rvalue) : string =
                 "{ " ^ fields ^ " }"
           in
           variant_name ^ " " ^ fields
-      | E.AggregatedRange ty ->
-          let fmt = expr_to_etype_formatter fmt in
-          "@Range " ^ PT.ety_to_string fmt ty
+      | E.AggregatedStructUpdate (def_id, base, updated_fields) ->
+          assert (ops = []);
+          let fields =
+            List.map
+              (fun (fid, op) ->
+                let field_name =
+                  match fmt.adt_field_to_string def_id None fid with
+                  | Some field_name -> field_name
+                  | None -> T.FieldId.to_string fid
+                in
+                field_name ^ ": " ^ operand_to_string fmt op)
+              updated_fields
+          in
+          let fields = fields @ [ ".." ^ operand_to_string fmt base ] in
+          fmt.type_decl_id_to_string def_id
+          ^ " { " ^ String.concat ", " fields ^ " }"
+      | E.AggregatedRange (aty, _tys) ->
+          let fields =
+            List.map2
+              (fun name op -> name ^ ": " ^ op)
+              (TU.range_field_names aty) ops
+          in
+          PT.assumed_ty_to_string aty ^ " { " ^ String.concat ", " fields ^ " }"
+      | E.AggregatedPhantomData _ -> "@PhantomData"
       | E.AggregatedArray (ty, cg) ->
           let fmt = expr_to_etype_formatter fmt in
           "@Array(" ^ PT.ety_to_string fmt ty ^ ", "
           ^ PT.const_generic_to_string fmt cg
-          ^ ")")
+          ^ ")"
+      | E.AggregatedRepeat (_ty, op, cg) ->
+          assert (ops = []);
+          let cg =
+            PT.const_generic_to_string (expr_to_etype_formatter fmt) cg
+          in
+          "[" ^ operand_to_string fmt op ^ "; " ^ cg ^ "]"
+      | E.AggregatedClosure (fid, kind, _tys) ->
+          "@Closure<"
+          ^ fmt.fun_decl_id_to_string fid
+          ^ ", "
+          ^ closure_kind_to_string kind
+          ^ ">(" ^ String.concat ", " ops ^ ")"
+      | E.AggregatedUnion (def_id, field_id, _regions, _types, _cgs) ->
+          let field_name =
+            match fmt.adt_field_to_string def_id None field_id with
+            | Some field_name -> field_name
+            | None -> T.FieldId.to_string field_id
+          in
+          fmt.type_decl_id_to_string def_id
+          ^ " { " ^ field_name ^ " = " ^ String.concat ", " ops ^ "; }"
+      | E.AggregatedRawPtr (_ty, _ref_kind) ->
+          "raw_ptr_from_parts(" ^ String.concat ", " ops ^ ")")
@@ -117,6 +117,10 @@ let call_to_string (fmt : ast_formatter) (indent : string) (call : GA.call) :
         | GA.SliceIndexMut -> "@SliceIndexMut" ^ t_params
         | GA.SliceSubsliceShared -> "@SliceSubsliceShared" ^ t_params
         | GA.SliceSubsliceMut -> "@SliceSubsliceMut" ^ t_params)
+    | GA.Virtual (trait_name, method_name, _) ->
+        "@dyn(" ^ trait_name_to_string trait_name ^ "::" ^ method_name ^ ")"
+        ^ t_params
+    | GA.Ptr op -> "(" ^ PE.operand_to_string fmt op ^ ")"
   in
   let dest = PE.place_to_string fmt call.GA.dest in
   indent ^ dest ^ " := move " ^ name_args ^ args
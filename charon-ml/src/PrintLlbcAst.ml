@@ -36,7 +36,15 @@ module Ast = struct
         ^ T.VariantId.to_string variant_id
         ^ ")"
     | A.Drop p -> indent ^ "drop " ^ PE.place_to_string fmt p
+    | A.StorageLive var_id ->
+        indent ^ "storage_live " ^ fmt.var_id_to_string var_id
+    | A.StorageDead var_id ->
+        indent ^ "storage_dead " ^ fmt.var_id_to_string var_id
     | A.Assert a -> assertion_to_string fmt indent a
+    | A.TaggedAssert (id, a) ->
+        let cond = PE.operand_to_string fmt a.A.cond in
+        let cond = if a.A.expected then cond else "¬" ^ cond in
+        indent ^ "assert@" ^ A.AssertionId.to_string id ^ "(" ^ cond ^ ")"
     | A.Call call -> call_to_string fmt indent call
     | A.Panic -> indent ^ "panic"
     | A.Return -> indent ^ "return"
@@ -113,6 +121,34 @@ module Ast = struct
         indent ^ "loop {\n"
         ^ statement_to_string fmt (indent ^ indent_incr) indent_incr loop_st
         ^ "\n" ^ indent ^ "}"
+    | A.LetElse (p, variants, else_st) ->
+        let p = PE.place_to_string fmt p in
+        let variants = List.map T.VariantId.to_string variants in
+        indent ^ "let " ^ String.concat " | " variants ^ " = " ^ p
+        ^ " else {\n"
+        ^ statement_to_string fmt (indent ^ indent_incr) indent_incr else_st
+        ^ "\n" ^ indent ^ "}"
+    | A.IfLet (p, variants, true_st, false_st) ->
+        let p = PE.place_to_string fmt p in
+        let variants = List.map T.VariantId.to_string variants in
+        let inner_indent = indent ^ indent_incr in
+        let inner_to_string =
+          statement_to_string fmt inner_indent indent_incr
+        in
+        let true_st = inner_to_string true_st in
+        let false_st = inner_to_string false_st in
+        indent ^ "if let " ^ String.concat " | " variants ^ " = " ^ p ^ " {\n"
+        ^ true_st ^ "\n" ^ indent ^ "}\n" ^ indent ^ "else {\n" ^ false_st
+        ^ "\n" ^ indent ^ "}"
+    | A.TryOp { A.scrutinee; ok_place; err_place; err_return } ->
+        let scrutinee = PE.operand_to_string fmt scrutinee in
+        let ok_place = PE.place_to_string fmt ok_place in
+        let err_place = PE.place_to_string fmt err_place in
+        indent ^ ok_place ^ " := " ^ scrutinee ^ "? else " ^ err_place
+        ^ " => {\n"
+        ^ statement_to_string fmt (indent ^ indent_incr) indent_incr err_return
+        ^ "\n" ^ indent ^ "}"
+    | A.MacroExpansion name -> indent ^ name ^ "!(...)"
 
   let fun_decl_to_string (fmt : ast_formatter) (indent : string)
       (indent_incr : string) (def : A.fun_decl) : string =
@@ -36,3 +36,5 @@ let literal_to_string (lit : literal) : string =
   | Scalar sv -> scalar_value_to_string sv
   | Bool b -> Bool.to_string b
   | Char c -> String.make 1 c
+  | RawPtrAddr addr -> "0x" ^ Z.format "%x" addr ^ " as ptr"
+  | Str s -> "\"" ^ String.escaped s ^ "\""
@@ -52,26 +52,91 @@ type stype_formatter = T.RegionVarId.id T.region type_formatter
 type rtype_formatter = T.RegionId.id T.region type_formatter
 type etype_formatter = T.erased_region type_formatter
 
+let assumed_ty_to_string (aty : T.assumed_ty) : string =
+  match aty with
+  | Box -> "alloc::boxed::Box"
+  | Vec -> "alloc::vec::Vec"
+  | Option -> "core::option::Option"
+  | Str -> "str"
+  | Array -> "@Array"
+  | Slice -> "@Slice"
+  | Range -> "@Range"
+  | RangeFrom -> "@RangeFrom"
+  | RangeTo -> "@RangeTo"
+  | RangeFull -> "@RangeFull"
+  | RangeInclusive -> "@RangeInclusive"
+  | PhantomData -> "core::marker::PhantomData"
+  | UnsafeCell -> "core::cell::UnsafeCell"
+
 let type_id_to_string (fmt : 'r type_formatter) (id : T.type_id) : string =
   match id with
   | T.AdtId id -> fmt.type_decl_id_to_string id
   | T.Tuple -> ""
-  | T.Assumed aty -> (
-      match aty with
-      | Box -> "alloc::boxed::Box"
-      | Vec -> "alloc::vec::Vec"
-      | Option -> "core::option::Option"
-      | Str -> "str"
-      | Array -> "@Array"
-      | Slice -> "@Slice"
-      | Range -> "@Range")
-
-let const_generic_to_string (fmt : 'r type_formatter) (cg : T.const_generic) :
-    string =
+  | T.Assumed aty -> assumed_ty_to_string aty
+
+let unop_to_string (unop : T.unop) : string =
+  match unop with
+  | T.Not -> "¬"
+  | T.Neg -> "-"
+  | T.Cast (src, tgt) ->
+      "cast<" ^ integer_type_to_string src ^ "," ^ integer_type_to_string tgt
+      ^ ">"
+  | T.PtrCast T.PtrToPtr -> "ptr_cast"
+  | T.PtrCast (T.IntToPtr src) ->
+      "ptr_cast<" ^ integer_type_to_string src ^ ",ptr>"
+  | T.PtrCast (T.PtrToInt tgt) ->
+      "ptr_cast<ptr," ^ integer_type_to_string tgt ^ ">"
+
+let binop_to_string (binop : T.binop) : string =
+  match binop with
+  | T.BitXor -> "^"
+  | T.BitAnd -> "&"
+  | T.BitOr -> "|"
+  | T.Eq -> "=="
+  | T.Lt -> "<"
+  | T.Le -> "<="
+  | T.Ne -> "!="
+  | T.Ge -> ">="
+  | T.Gt -> ">"
+  | T.Div -> "/"
+  | T.Rem -> "%"
+  | T.Add -> "+"
+  | T.Sub -> "-"
+  | T.Mul -> "*"
+  | T.Shl -> "<<"
+  | T.Shr -> ">>"
+
+let rec const_generic_to_string (fmt : 'r type_formatter)
+    (cg : T.const_generic) : string =
   match cg with
   | ConstGenericGlobal id -> fmt.global_decl_id_to_string id
   | ConstGenericVar id -> fmt.const_generic_var_id_to_string id
   | ConstGenericValue lit -> literal_to_string lit
+  | ConstGenericExpr e -> const_generic_expr_to_string fmt e
+
+and const_generic_expr_to_string (fmt : 'r type_formatter)
+    (e : T.const_generic_expr) : string =
+  match e with
+  | ConstGenericBinaryOp (binop, cg0, cg1) ->
+      const_generic_operand_to_string fmt cg0
+      ^ " " ^ binop_to_string binop ^ " "
+      ^ const_generic_operand_to_string fmt cg1
+  | ConstGenericUnaryOp (unop, cg) ->
+      unop_to_string unop ^ "(" ^ const_generic_to_string fmt cg ^ ")"
+  | ConstGenericFunCall (fid, args) ->
+      (* We don't have a function formatter at hand: we print the id *)
+      let args = List.map (const_generic_to_string fmt) args in
+      "@Fun" ^ T.FunDeclId.to_string fid ^ "(" ^ String.concat ", " args ^ ")"
+  | ConstGenericCast (cg, ty) ->
+      "(" ^ const_generic_to_string fmt cg ^ " as "
+      ^ literal_type_to_string ty ^ ")"
+
+(** Wrap the expressions between parentheses, when they are used as operands *)
+and const_generic_operand_to_string (fmt : 'r type_formatter)
+    (cg : T.const_generic) : string =
+  match cg with
+  | ConstGenericExpr _ -> "(" ^ const_generic_to_string fmt cg ^ ")"
+  | _ -> const_generic_to_string fmt cg
 
 let rec ty_to_string (fmt : 'r type_formatter) (ty : 'r T.ty) : string =
   match ty with
@@ -86,6 +151,32 @@ let rec ty_to_string (fmt : 'r type_formatter) (ty : 'r T.ty) : string =
       match ref_kind with
       | T.Mut -> "&" ^ fmt.r_to_string r ^ " mut (" ^ ty_to_string fmt rty ^ ")"
       | T.Shared -> "&" ^ fmt.r_to_string r ^ " (" ^ ty_to_string fmt rty ^ ")")
+  | T.RawPtr (rty, ref_kind) -> (
+      match ref_kind with
+      | T.Mut -> "*mut (" ^ ty_to_string fmt rty ^ ")"
+      | T.Shared -> "*const (" ^ ty_to_string fmt rty ^ ")")
+  | T.FnPtr (inputs, output) ->
+      let inputs = List.map (ty_to_string fmt) inputs in
+      "fn(" ^ String.concat ", " inputs ^ ") -> " ^ ty_to_string fmt output
+  | T.TraitAssoc (_, tv, name) -> fmt.type_var_id_to_string tv ^ "::" ^ name
+  | T.TraitObject (traits, r) ->
+      let bounds = List.map (trait_ref_to_string fmt) traits in
+      let bounds = bounds @ [ fmt.r_to_string r ] in
+      "dyn (" ^ String.concat " + " bounds ^ ")"
+
+and trait_ref_to_string (fmt : 'r type_formatter) (tr : 'r T.trait_ref) :
+    string =
+  let types = List.map (ty_to_string fmt) tr.trait_types in
+  let cgs = List.map (const_generic_to_string fmt) tr.trait_const_generics in
+  let assoc_types =
+    List.map
+      (fun (name, ty) -> name ^ " = " ^ ty_to_string fmt ty)
+      tr.trait_assoc_types
+  in
+  let params = List.flatten [ types; cgs; assoc_types ] in
+  let name = trait_name_to_string tr.trait_name in
+  if List.length params > 0 then name ^ "<" ^ String.concat ", " params ^ ">"
+  else name
 
 and params_to_string (fmt : 'r type_formatter) (is_tuple : bool)
     (regions : 'r list) (types : 'r T.ty list) (cgs : T.const_generic list) :
@@ -176,6 +267,16 @@ let type_decl_to_string (type_decl_id_to_string : T.TypeDeclId.id -> string)
       in
       let variants = String.concat "\n" variants in
       "enum " ^ name ^ params ^ " =\n" ^ variants
+  | T.Union fields ->
+      let fields =
+        String.concat ","
+          (List.map (fun f -> "\n  " ^ field_to_string fmt f) fields)
+      in
+      "union " ^ name ^ params ^ "{" ^ fields ^ "}"
+  | T.ImplTrait traits ->
+      let traits = List.map (trait_ref_to_string fmt) traits in
+      "opaque type " ^ name ^ params ^ " : impl " ^ String.concat " + " traits
+  | T.Alias ty -> "type " ^ name ^ params ^ " = " ^ ty_to_string fmt ty
   | T.Opaque -> "opaque type " ^ name ^ params
 
 let type_ctx_to_adt_variant_to_string_fun (ctx : T.type_decl T.TypeDeclId.Map.t)
@@ -183,7 +284,8 @@ let type_ctx_to_adt_variant_to_string_fun (ctx : T.type_decl T.TypeDeclId.Map.t)
  fun def_id variant_id ->
   let def = T.TypeDeclId.Map.find def_id ctx in
   match def.kind with
-  | Struct _ | Opaque -> raise (Failure "Unreachable")
+  | Struct _ | Union _ | ImplTrait _ | Alias _ | Opaque ->
+      raise (Failure "Unreachable")
   | Enum variants ->
       let variant = T.VariantId.nth variants variant_id in
       name_to_string def.name ^ "::" ^ variant.variant_name
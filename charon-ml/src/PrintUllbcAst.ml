@@ -35,6 +35,8 @@ module Ast = struct
         indent ^ "set_discriminant(" ^ PE.place_to_string fmt p ^ ", "
         ^ T.VariantId.to_string variant_id
         ^ ")"
+    | A.StorageLive var_id ->
+        indent ^ "storage_live " ^ fmt.var_id_to_string var_id
     | A.StorageDead var_id ->
         indent ^ "storage_dead " ^ fmt.var_id_to_string var_id
     | A.Deinit p -> indent ^ "deinit " ^ PE.place_to_string fmt p
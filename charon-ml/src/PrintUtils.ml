@@ -10,6 +10,7 @@ let option_to_string (to_string : 'a -> string) (x : 'a option) : string =
 let name_to_string (name : name) : string = Names.name_to_string name
 let fun_name_to_string (name : fun_name) : string = name_to_string name
 let global_name_to_string (name : global_name) : string = name_to_string name
+let trait_name_to_string (name : trait_name) : string = name_to_string name
 
 let block_id_to_string (id : UllbcAst.BlockId.id) : string =
   "block@" ^ UllbcAst.BlockId.to_string id
@@ -8,6 +8,8 @@ module VariantId = IdGen ()
 module FieldId = IdGen ()
 module GlobalDeclId = IdGen ()
 module ConstGenericVarId = IdGen ()
+module FunDeclId = IdGen ()
+module TraitDeclId = IdGen ()
 
 (** We define this type to control the name of the visitor functions
     (see e.g., {!Types.iter_ty_base} and {!Types.TypeVar}).
@@ -20,6 +22,12 @@ type const_generic_var_id = ConstGenericVarId.id [@@deriving show, ord]
 (** Same remark as for {!type_var_id} *)
 type global_decl_id = GlobalDeclId.id [@@deriving show, ord]
 
+(** Same remark as for {!type_var_id} *)
+type fun_decl_id = FunDeclId.id [@@deriving show, ord]
+
+(** Same remark as for {!type_var_id} *)
+type trait_decl_id = TraitDeclId.id [@@deriving show, ord]
+
 type integer_type = PrimitiveValues.integer_type [@@deriving show, ord]
 
 (** Same remark as for {!type_var_id} *)
@@ -103,7 +111,20 @@ let all_int_types = List.append all_signed_int_types all_unsigned_int_types
 type ref_kind = Mut | Shared [@@deriving show, ord]
 
 (* TODO: Str should be a literal *)
-type assumed_ty = Box | Vec | Option | Array | Slice | Str | Range
+type assumed_ty =
+  | Box
+  | Vec
+  | Option
+  | Array
+  | Slice
+  | Str
+  | Range
+  | RangeFrom
+  | RangeTo
+  | RangeFull
+  | RangeInclusive
+  | PhantomData
+  | UnsafeCell
 [@@deriving show, ord]
 
 (** The variant id for [Option::None] *)
@@ -120,6 +141,53 @@ let option_some_id = VariantId.of_int 1
 type type_id = AdtId of TypeDeclId.id | Tuple | Assumed of assumed_ty
 [@@deriving show, ord]
 
+(** A cast involving raw pointers. See {!Types.unop.PtrCast}. *)
+type ptr_cast_kind =
+  | PtrToPtr  (** `p as *const T`, where `p` is a raw pointer *)
+  | IntToPtr of integer_type
+      (** `x as *const T`, where `x` is an integer of the given type *)
+  | PtrToInt of integer_type
+      (** `p as usize`, where `p` is a raw pointer: we give the target
+          integer type *)
+[@@deriving show, ord]
+
+(* Remark: no `ArrayToSlice` variant: it gets eliminated in a micro-pass *)
+type unop =
+  | Not
+  | Neg
+  | Cast of integer_type * integer_type
+      (** Cast an integer from a source type to a target type *)
+  | PtrCast of ptr_cast_kind
+      (** Casts involving raw pointers. The pointed types can be retrieved
+          from the types of the operand and of the destination. *)
+[@@deriving show, ord]
+
+(** A binary operation
+
+    Note that we merge checked binops and unchecked binops: we perform a
+    micro-pass on the MIR AST to remove the assertions introduced by rustc,
+    and later extract the binops which can fail (addition, substraction, etc.)
+    or have preconditions (division, remainder...) to monadic functions.
+ *)
+type binop =
+  | BitXor
+  | BitAnd
+  | BitOr
+  | Eq
+  | Lt
+  | Le
+  | Ne
+  | Ge
+  | Gt
+  | Div
+  | Rem
+  | Add
+  | Sub
+  | Mul
+  | Shl
+  | Shr
+[@@deriving show, ord]
+
 (** Ancestor for iter visitor for {!Types.const_generic} *)
 class ['self] iter_const_generic_base =
   object (_self : 'self)
@@ -130,6 +198,10 @@ class ['self] iter_const_generic_base =
       fun _ _ -> ()
 
     method visit_literal : 'env -> literal -> unit = fun _ _ -> ()
+    method visit_literal_type : 'env -> literal_type -> unit = fun _ _ -> ()
+    method visit_fun_decl_id : 'env -> fun_decl_id -> unit = fun _ _ -> ()
+    method visit_unop : 'env -> unop -> unit = fun _ _ -> ()
+    method visit_binop : 'env -> binop -> unit = fun _ _ -> ()
   end
 
 (** Ancestor for map visitor for {!Types.const_generic} *)
@@ -145,6 +217,15 @@ class ['self] map_const_generic_base =
       fun _ x -> x
 
     method visit_literal : 'env -> literal -> literal = fun _ x -> x
+
+    method visit_literal_type : 'env -> literal_type -> literal_type =
+      fun _ x -> x
+
+    method visit_fun_decl_id : 'env -> fun_decl_id -> fun_decl_id =
+      fun _ x -> x
+
+    method visit_unop : 'env -> unop -> unop = fun _ x -> x
+    method visit_binop : 'env -> binop -> binop = fun _ x -> x
   end
 
 (** Ancestor for reduce visitor for {!Types.const_generic} *)
@@ -159,6 +240,13 @@ class virtual ['self] reduce_const_generic_base =
       fun _ _ -> self#zero
 
     method visit_literal : 'env -> literal -> 'a = fun _ _ -> self#zero
+
+    method visit_literal_type : 'env -> literal_type -> 'a =
+      fun _ _ -> self#zero
+
+    method visit_fun_decl_id : 'env -> fun_decl_id -> 'a = fun _ _ -> self#zero
+    method visit_unop : 'env -> unop -> 'a = fun _ _ -> self#zero
+    method visit_binop : 'env -> binop -> 'a = fun _ _ -> self#zero
   end
 
 (** Ancestor for mapreduce visitor for {!Types.const_generic} *)
@@ -176,6 +264,15 @@ class virtual ['self] mapreduce_const_generic_base =
 
     method visit_literal : 'env -> literal -> literal * 'a =
       fun _ x -> (x, self#zero)
+
+    method visit_literal_type : 'env -> literal_type -> literal_type * 'a =
+      fun _ x -> (x, self#zero)
+
+    method visit_fun_decl_id : 'env -> fun_decl_id -> fun_decl_id * 'a =
+      fun _ x -> (x, self#zero)
+
+    method visit_unop : 'env -> unop -> unop * 'a = fun _ x -> (x, self#zero)
+    method visit_binop : 'env -> binop -> binop * 'a = fun _ x -> (x, self#zero)
   end
 
 (** Remark: we have to use long names because otherwise we have collisions in
@@ -184,6 +281,22 @@ type const_generic =
   | ConstGenericGlobal of global_decl_id
   | ConstGenericVar of const_generic_var_id
   | ConstGenericValue of literal
+  | ConstGenericExpr of const_generic_expr
+      (** A const generic expression, like `N + 1` *)
+
+(** Const generic expressions.
+
+    Those appear in the types when using the [generic_const_exprs] feature,
+    for instance: `[u8; N * 2]`.
+ *)
+and const_generic_expr =
+  | ConstGenericBinaryOp of binop * const_generic * const_generic
+  | ConstGenericUnaryOp of unop * const_generic
+      (** Only [Not] and [Neg] can appear here *)
+  | ConstGenericFunCall of fun_decl_id * const_generic list
+      (** A call to a `const fn` *)
+  | ConstGenericCast of const_generic * literal_type
+      (** A cast, like in `N as usize` *)
 [@@deriving
   show,
     ord,
@@ -230,7 +343,8 @@ class ['self] iter_ty_base =
     method visit_type_var_id : 'env -> type_var_id -> unit = fun _ _ -> ()
     method visit_type_id : 'env -> type_id -> unit = fun _ _ -> ()
     method visit_ref_kind : 'env -> ref_kind -> unit = fun _ _ -> ()
-    method visit_literal_type : 'env -> literal_type -> unit = fun _ _ -> ()
+    method visit_trait_name : 'env -> trait_name -> unit = fun _ _ -> ()
+    method visit_trait_decl_id : 'env -> trait_decl_id -> unit = fun _ _ -> ()
   end
 
 (** Ancestor for map visitor for {!type: Types.ty} *)
@@ -245,7 +359,10 @@ class virtual ['self] map_ty_base =
     method visit_type_id : 'env -> type_id -> type_id = fun _ id -> id
     method visit_ref_kind : 'env -> ref_kind -> ref_kind = fun _ rk -> rk
 
-    method visit_literal_type : 'env -> literal_type -> literal_type =
+    method visit_trait_name : 'env -> trait_name -> trait_name =
+      fun _ x -> x
+
+    method visit_trait_decl_id : 'env -> trait_decl_id -> trait_decl_id =
       fun _ x -> x
   end
 
@@ -256,6 +373,38 @@ type 'r ty =
   | Literal of literal_type
   | Never
   | Ref of 'r * 'r ty * ref_kind
+  | RawPtr of 'r ty * ref_kind  (** A raw pointer (`*const T`, `*mut T`) *)
+  | FnPtr of 'r ty list * 'r ty
+      (** A function pointer type (`fn(u32) -> u32`): we give the types of the
+          inputs and the type of the output *)
+  | TraitAssoc of trait_decl_id * type_var_id * string
+      (** An associated type of a trait, projected from a type parameter (for
+          instance `T::Item` where `T: Iterator`): we give the trait which
+          defines the associated type, the type parameter and the name of the
+          associated type *)
+  | TraitObject of 'r trait_ref list * 'r
+      (** A trait object (`dyn Trait`).
+
+          We give the list of traits (the principal trait first, if there is
+          one, followed by the auto traits like `Send`), and the lifetime bound
+          of the trait object.
+       *)
+
+(** A reference to a trait, as found in trait objects.
+
+    We don't translate the trait declarations yet: we identify the trait
+    with its name.
+ *)
+and 'r trait_ref = {
+  trait_name : trait_name;
+  trait_types : 'r ty list;
+      (** The type parameters, without the (implicit) `Self` type *)
+  trait_const_generics : const_generic list;
+  trait_assoc_types : (string * 'r ty) list;
+      (** The constraints on the associated types. For instance,
+          `dyn Fn(u32) -> u32` refers to the trait `Fn<(u32,)>` and
+          constrains `Output` to be `u32`. *)
+}
 [@@deriving
   show,
     ord,
@@ -326,6 +475,14 @@ type type_decl_kind =
 
           See {!Identifiers.Id.mapi} for instance.
        *)
+  | Union of field list
+      (** The fields of the union can be indexed with {!FieldId.id}. *)
+  | ImplTrait of RegionVarId.id region trait_ref list
+      (** A type introduced by `impl Trait` in return position: we don't know
+          the underlying type, but we know the traits it implements. *)
+  | Alias of sty
+      (** A type alias ([type MyVec = Vec<u32>]): we give its expansion. The
+          aliases are only preserved with [--preserve-type-aliases]. *)
   | Opaque
       (** An opaque type: either a local type marked as opaque, or an external type *)
 [@@deriving show]
@@ -2,7 +2,9 @@ open Types
 open Utils
 
 let type_decl_is_opaque (d : type_decl) : bool =
-  match d.kind with Struct _ | Enum _ -> false | Opaque -> true
+  match d.kind with
+  | Struct _ | Enum _ | Union _ | Alias _ -> false
+  | ImplTrait _ | Opaque -> true
 
 (** Retrieve the list of fields for the given variant of a {!Charon.Types.type_decl}.
 
@@ -12,7 +14,7 @@ let type_decl_get_fields (def : type_decl)
     (opt_variant_id : VariantId.id option) : field list =
   match (def.kind, opt_variant_id) with
   | Enum variants, Some variant_id -> (VariantId.nth variants variant_id).fields
-  | Struct fields, None -> fields
+  | (Struct fields | Union fields), None -> fields
   | _ ->
       let opt_variant_id =
         match opt_variant_id with None -> "None" | Some _ -> "Some"
@@ -25,7 +27,10 @@ let type_decl_get_fields (def : type_decl)
           ^ opt_variant_id))
 
 let type_decl_is_enum (def : type_decl) : bool =
-  match def.kind with Struct _ -> false | Enum _ -> true | Opaque -> false
+  match def.kind with
+  | Struct _ | Union _ -> false
+  | Enum _ -> true
+  | ImplTrait _ | Alias _ | Opaque -> false
 
 (** Return [true] if a {!type: Types.ty} is actually [unit] *)
 let ty_is_unit (ty : 'r ty) : bool =
@@ -89,6 +94,22 @@ let mk_box_ty (ty : 'r ty) : 'r ty = Adt (Assumed Box, [], [ ty ], [])
 (** Make a vec type *)
 let mk_vec_ty (ty : 'r ty) : 'r ty = Adt (Assumed Vec, [], [ ty ], [])
 
+(** The names of the fields of a range type ([Range], [RangeFrom], etc.): the
+    field ids of the range projections and the operands of the range
+    aggregates follow this order.
+
+    Raises [Failure] if the type is not a range type.
+ *)
+let range_field_names (aty : assumed_ty) : string list =
+  match aty with
+  | Range -> [ "start"; "end" ]
+  | RangeFrom -> [ "start" ]
+  | RangeTo -> [ "end" ]
+  | RangeInclusive -> [ "start"; "end"; "exhausted" ]
+  | RangeFull -> []
+  | Box | Vec | Option | Array | Slice | Str | PhantomData | UnsafeCell ->
+      raise (Failure "Not a range type")
+
 (** Check if a region is in a set of regions *)
 let region_in_set (r : RegionId.id region) (rset : RegionId.Set.t) : bool =
   match r with Static -> false | Var id -> RegionId.Set.mem id rset
@@ -132,6 +153,16 @@ let rec ety_no_regions_to_gr_ty (ty : ety) : 'a gr_ty =
         (Failure
            "Can't convert a ref with erased regions to a ref with non-erased \
             regions")
+  | RawPtr (ty, ref_kind) -> RawPtr (ety_no_regions_to_gr_ty ty, ref_kind)
+  | FnPtr (inputs, output) ->
+      let inputs = List.map ety_no_regions_to_gr_ty inputs in
+      FnPtr (inputs, ety_no_regions_to_gr_ty output)
+  | TraitAssoc (trait_id, v, name) -> TraitAssoc (trait_id, v, name)
+  | TraitObject (_, _) ->
+      raise
+        (Failure
+           "Can't convert a trait object with erased regions to a trait \
+            object with non-erased regions")
 
 let ety_no_regions_to_rty (ty : ety) : rty = ety_no_regions_to_gr_ty ty
 let ety_no_regions_to_sty (ty : ety) : sty = ety_no_regions_to_gr_ty ty
@@ -149,6 +180,10 @@ let ty_has_regions_in_set (rset : RegionId.Set.t) (ty : rty) : bool =
       method! visit_Ref env r ty rkind =
         if region_in_set r rset then raise Found
         else super#visit_Ref env r ty rkind
+
+      method! visit_TraitObject env traits r =
+        if region_in_set r rset then raise Found
+        else super#visit_TraitObject env traits r
     end
   in
   try
@@ -171,9 +206,15 @@ let rec ty_is_primitively_copyable (ty : 'r ty) : bool =
       List.for_all ty_is_primitively_copyable tys
   | Adt ((AdtId _ | Assumed (Box | Vec | Str | Slice | Range)), _, _, _) ->
       false
+  | Adt (Assumed (RangeFrom | RangeTo | RangeFull | RangeInclusive), _, _, _)
+  | Adt (Assumed UnsafeCell, _, _, _) ->
+      false
   | Adt ((Tuple | Assumed Array), _, tys, _) ->
       List.for_all ty_is_primitively_copyable tys
-  | TypeVar _ | Never -> false
+  | Adt (Assumed PhantomData, _, _, _) -> true
+  | TypeVar _ | TraitAssoc _ | Never -> false
   | Literal (Bool | Char | Integer _) -> true
   | Ref (_, _, Mut) -> false
   | Ref (_, _, Shared) -> true
+  | RawPtr (_, _) | FnPtr (_, _) -> true
+  | TraitObject (_, _) -> false
@@ -35,7 +35,12 @@ and raw_statement =
   | Assign of place * rvalue
   | FakeRead of place
   | SetDiscriminant of place * variant_id
+  | StorageLive of var_id
+      (** The storage of the local becomes valid (this marks the beginning of
+          its lexical lifetime) *)
   | StorageDead of var_id
+      (** The storage of the local becomes invalid (this marks the end of its
+          lexical lifetime) *)
   | Deinit of place
 [@@deriving
   show,
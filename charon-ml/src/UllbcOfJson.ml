@@ -31,6 +31,9 @@ and raw_statement_of_json (js : json) : (A.raw_statement, string) result =
         let* place = place_of_json place in
         let* variant_id = T.VariantId.id_of_json variant_id in
         Ok (A.SetDiscriminant (place, variant_id))
+    | `Assoc [ ("StorageLive", var_id) ] ->
+        let* var_id = E.VarId.id_of_json var_id in
+        Ok (A.StorageLive var_id)
     | `Assoc [ ("StorageDead", var_id) ] ->
         let* var_id = E.VarId.id_of_json var_id in
         Ok (A.StorageDead var_id)
@@ -91,7 +94,12 @@ and raw_terminator_of_json (js : json) : (A.raw_terminator, string) result =
     | `String "Panic" -> Ok A.Panic
     | `String "Return" -> Ok A.Return
     | `String "Unreachable" -> Ok A.Unreachable
-    | `Assoc [ ("Drop", `Assoc [ ("place", place); ("target", target) ]) ] ->
+    | `Assoc
+        [
+          ( "Drop",
+            `Assoc [ ("place", place); ("target", target); ("unwind", _) ] );
+        ] ->
+        (* We ignore the unwind target *)
         let* place = place_of_json place in
         let* target = A.BlockId.id_of_json target in
         Ok (A.Drop (place, target))
@@ -112,7 +120,13 @@ let block_of_json (id_to_file : id_to_file_map) (js : json) :
     (A.block, string) result =
   combine_error_msgs js __FUNCTION__
     (match js with
-    | `Assoc [ ("statements", statements); ("terminator", terminator) ] ->
+    | `Assoc
+        [
+          ("statements", statements);
+          ("terminator", terminator);
+          ("is_loop_header", _);
+        ] ->
+        (* We ignore the loop header flag *)
         let* statements =
           list_of_json (statement_of_json id_to_file) statements
         in
@@ -141,7 +155,7 @@ let global_decl_of_json (id_to_file : id_to_file_map) (js : json) :
 
 let crate_of_json (js : json) : (A.crate, string) result =
   combine_error_msgs js __FUNCTION__
-    (match js with
+    (match crate_remove_optional_fields js with
     | `Assoc
         [
           ("name", name);
@@ -150,7 +164,10 @@ let crate_of_json (js : json) : (A.crate, string) result =
           ("types", types);
           ("functions", functions);
           ("globals", globals);
+          ("traits", _);
+          ("modules", _);
         ] ->
+        (* We ignore the trait declarations and the module tree *)
         let* name = string_of_json name in
         let* id_to_file = id_to_file_of_json id_to_file in
         let* declarations =
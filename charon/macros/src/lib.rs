@@ -99,6 +99,31 @@ pub mod {} {{
             serializer.serialize_u32(self.index as u32)
         }}
     }}
+
+    impl<'de> serde::Deserialize<'de> for Id {{
+        fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {{
+            let index = <u32 as serde::Deserialize>::deserialize(deserializer)?;
+            Ok(Id::new(index as usize))
+        }}
+    }}
+
+    impl schemars::JsonSchema for Id {{
+        fn schema_name() -> String {{
+            \"Id\".to_string()
+        }}
+
+        // The ids are serialized as integers: we inline them
+        fn is_referenceable() -> bool {{
+            false
+        }}
+
+        fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {{
+            <u32 as schemars::JsonSchema>::json_schema(gen)
+        }}
+    }}
  
     impl Generator {{
         pub fn new() -> Generator {{
@@ -128,14 +153,19 @@ pub mod {} {{
       }}
 
       pub fn insert(&mut self, k: K) -> Id {{
-        match self.map.get(&k) {{
-          Option::Some(id) => *id,
-          Option::None => {{
-            let id = self.counter.fresh_id();
-            self.map.insert(k, id);
-            id
-          }}
-        }}
+        self.insert_new(k).0
+      }}
+
+      /// Same as [MapGenerator::insert], but also return `true` if the key
+      /// was not in the map (i.e., if we generated a fresh id).
+      pub fn insert_new(&mut self, k: K) -> (Id, bool) {{
+        let counter = &mut self.counter;
+        let mut fresh = false;
+        let id = *self.map.entry(k).or_insert_with(|| {{
+          fresh = true;
+          counter.fresh_id()
+        }});
+        (id, fresh)
       }}
 
       pub fn get(&self, k: K) -> Option<Id> {{
@@ -6,6 +6,7 @@
 // TODO: rename to "primitive"
 #![allow(dead_code)]
 
+use crate::gast;
 use crate::names::*;
 use crate::types;
 use crate::ullbc_ast;
@@ -16,10 +17,37 @@ pub static BOX_NAME: [&str; 3] = ["alloc", "boxed", "Box"];
 pub static VEC_NAME: [&str; 3] = ["alloc", "vec", "Vec"];
 pub static OPTION_NAME: [&str; 3] = ["core", "option", "Option"];
 pub static RANGE_NAME: [&str; 4] = ["core", "ops", "range", "Range"];
+pub static RANGE_FROM_NAME: [&str; 4] = ["core", "ops", "range", "RangeFrom"];
+pub static RANGE_TO_NAME: [&str; 4] = ["core", "ops", "range", "RangeTo"];
+pub static RANGE_FULL_NAME: [&str; 4] = ["core", "ops", "range", "RangeFull"];
+pub static RANGE_INCLUSIVE_NAME: [&str; 4] = ["core", "ops", "range", "RangeInclusive"];
+pub static PHANTOM_DATA_NAME: [&str; 3] = ["core", "marker", "PhantomData"];
+pub static UNSAFE_CELL_NAME: [&str; 3] = ["core", "cell", "UnsafeCell"];
+
+// External types built on top of `UnsafeCell`. We don't translate the external
+// types (they are opaque): we need this list to detect the interior mutability
+// (see [crate::detect_interior_mutability]). Note that it is not exhaustive.
+pub static CELL_NAME: [&str; 3] = ["core", "cell", "Cell"];
+pub static REF_CELL_NAME: [&str; 3] = ["core", "cell", "RefCell"];
+pub static ONCE_CELL_NAME: [&str; 4] = ["core", "cell", "once", "OnceCell"];
 
 pub static OPTION_NONE_VARIANT_ID: types::VariantId::Id = types::VariantId::ZERO;
 pub static OPTION_SOME_VARIANT_ID: types::VariantId::Id = types::VariantId::ONE;
 
+/// The names of the fields of the range types, in the order of their
+/// declarations (the field ids of the range projections index this list).
+pub fn range_field_names(id: types::AssumedTy) -> &'static [&'static str] {
+    use types::AssumedTy;
+    match id {
+        AssumedTy::Range => &["start", "end"],
+        AssumedTy::RangeFrom => &["start"],
+        AssumedTy::RangeTo => &["end"],
+        AssumedTy::RangeInclusive => &["start", "end", "exhausted"],
+        AssumedTy::RangeFull => &[],
+        _ => unreachable!("Not a range type: {:?}", id),
+    }
+}
+
 //
 // Assumed functions/traits
 //
@@ -51,6 +79,23 @@ pub static VEC_LEN_NAME: [&str; 4] = ["alloc", "vec", "Vec", "len"];
 // Pointers
 pub static PTR_UNIQUE_NAME: [&str; 3] = ["core", "ptr", "Unique"];
 pub static PTR_NON_NULL_NAME: [&str; 3] = ["core", "ptr", "NonNull"];
+// The calls to those functions are translated to raw pointer aggregates
+pub static PTR_FROM_RAW_PARTS_NAME: [&str; 4] = ["core", "ptr", "metadata", "from_raw_parts"];
+pub static PTR_FROM_RAW_PARTS_MUT_NAME: [&str; 4] =
+    ["core", "ptr", "metadata", "from_raw_parts_mut"];
+
+// Compiler built-ins (see [get_builtin_fn_kind_from_name])
+pub static MEM_SIZE_OF_NAME: [&str; 3] = ["core", "mem", "size_of"];
+pub static MEM_ALIGN_OF_NAME: [&str; 3] = ["core", "mem", "align_of"];
+pub static MEM_FORGET_NAME: [&str; 3] = ["core", "mem", "forget"];
+pub static MEM_DROP_NAME: [&str; 3] = ["core", "mem", "drop"];
+pub static MANUALLY_DROP_NEW_NAME: [&str; 5] =
+    ["core", "mem", "manually_drop", "ManuallyDrop", "new"];
+
+// The `?` operator (see [crate::reconstruct_try])
+pub static TRY_BRANCH_NAME: [&str; 5] = ["core", "ops", "try_trait", "Try", "branch"];
+pub static CONTROL_FLOW_CONTINUE_VARIANT_ID: types::VariantId::Id = types::VariantId::ZERO;
+pub static CONTROL_FLOW_BREAK_VARIANT_ID: types::VariantId::Id = types::VariantId::ONE;
 
 // We ignore this trait, which is implicitly given to all the type parameters
 pub static MARKER_SIZED_NAME: [&str; 3] = ["core", "marker", "Sized"];
@@ -88,6 +133,14 @@ pub fn get_type_id_from_name(name: &TypeName) -> Option<types::AssumedTy> {
         Option::Some(types::AssumedTy::Box)
     } else if name.equals_ref_name(&RANGE_NAME) {
         Option::Some(types::AssumedTy::Range)
+    } else if name.equals_ref_name(&RANGE_FROM_NAME) {
+        Option::Some(types::AssumedTy::RangeFrom)
+    } else if name.equals_ref_name(&RANGE_TO_NAME) {
+        Option::Some(types::AssumedTy::RangeTo)
+    } else if name.equals_ref_name(&RANGE_FULL_NAME) {
+        Option::Some(types::AssumedTy::RangeFull)
+    } else if name.equals_ref_name(&RANGE_INCLUSIVE_NAME) {
+        Option::Some(types::AssumedTy::RangeInclusive)
     } else if name.equals_ref_name(&VEC_NAME) {
         Option::Some(types::AssumedTy::Vec)
     } else if name.equals_ref_name(&OPTION_NAME) {
@@ -96,6 +149,10 @@ pub fn get_type_id_from_name(name: &TypeName) -> Option<types::AssumedTy> {
         Option::Some(types::AssumedTy::PtrUnique)
     } else if name.equals_ref_name(&PTR_NON_NULL_NAME) {
         Option::Some(types::AssumedTy::PtrNonNull)
+    } else if name.equals_ref_name(&PHANTOM_DATA_NAME) {
+        Option::Some(types::AssumedTy::PhantomData)
+    } else if name.equals_ref_name(&UNSAFE_CELL_NAME) {
+        Option::Some(types::AssumedTy::UnsafeCell)
     } else {
         Option::None
     }
@@ -106,10 +163,16 @@ pub fn get_name_from_type_id(id: types::AssumedTy) -> Vec<String> {
     match id {
         AssumedTy::Box => BOX_NAME.iter().map(|s| s.to_string()).collect(),
         AssumedTy::Range => RANGE_NAME.iter().map(|s| s.to_string()).collect(),
+        AssumedTy::RangeFrom => RANGE_FROM_NAME.iter().map(|s| s.to_string()).collect(),
+        AssumedTy::RangeTo => RANGE_TO_NAME.iter().map(|s| s.to_string()).collect(),
+        AssumedTy::RangeFull => RANGE_FULL_NAME.iter().map(|s| s.to_string()).collect(),
+        AssumedTy::RangeInclusive => RANGE_INCLUSIVE_NAME.iter().map(|s| s.to_string()).collect(),
         AssumedTy::Vec => VEC_NAME.iter().map(|s| s.to_string()).collect(),
         AssumedTy::Option => OPTION_NAME.iter().map(|s| s.to_string()).collect(),
         AssumedTy::PtrUnique => PTR_UNIQUE_NAME.iter().map(|s| s.to_string()).collect(),
         AssumedTy::PtrNonNull => PTR_NON_NULL_NAME.iter().map(|s| s.to_string()).collect(),
+        AssumedTy::PhantomData => PHANTOM_DATA_NAME.iter().map(|s| s.to_string()).collect(),
+        AssumedTy::UnsafeCell => UNSAFE_CELL_NAME.iter().map(|s| s.to_string()).collect(),
         AssumedTy::Str => vec!["Str".to_string()],
         AssumedTy::Array => vec!["Array".to_string()],
         AssumedTy::Slice => vec!["Slice".to_string()],
@@ -160,6 +223,11 @@ pub fn get_fun_id_from_name(
                 FunId::Panic | FunId::BeginPanic => unreachable!(),
                 FunId::Replace => ullbc_ast::AssumedFunId::Replace,
                 FunId::BoxNew => ullbc_ast::AssumedFunId::BoxNew,
+                // The other implementations of `Deref` are regular trait
+                // methods
+                FunId::BoxDeref | FunId::BoxDerefMut if type_args[0].as_box().is_none() => {
+                    return Option::None
+                }
                 FunId::BoxDeref => ullbc_ast::AssumedFunId::BoxDeref,
                 FunId::BoxDerefMut => ullbc_ast::AssumedFunId::BoxDerefMut,
                 FunId::BoxFree => ullbc_ast::AssumedFunId::BoxFree,
@@ -215,7 +283,9 @@ pub fn get_fun_id_from_name(
                                 _ => unimplemented!("ty: {:?}", aty),
                             }
                         }
-                        _ => unimplemented!(),
+                        // The implementations of `Index` for the other types
+                        // are regular trait methods
+                        _ => return Option::None,
                     }
                 }
             };
@@ -245,15 +315,24 @@ pub fn type_to_used_params(name: &TypeName) -> Option<Vec<bool>> {
                 AssumedTy::Option => {
                     vec![true]
                 }
-                AssumedTy::PtrUnique | AssumedTy::PtrNonNull => {
+                AssumedTy::PtrUnique
+                | AssumedTy::PtrNonNull
+                | AssumedTy::PhantomData
+                | AssumedTy::UnsafeCell => {
                     vec![true]
                 }
                 AssumedTy::Str => {
                     vec![]
                 }
-                AssumedTy::Range => {
+                AssumedTy::Range
+                | AssumedTy::RangeFrom
+                | AssumedTy::RangeTo
+                | AssumedTy::RangeInclusive => {
                     vec![true]
                 }
+                AssumedTy::RangeFull => {
+                    vec![]
+                }
                 AssumedTy::Array | AssumedTy::Slice => vec![true],
             };
             Option::Some(id)
@@ -337,3 +416,68 @@ pub fn function_to_info(name: &FunName) -> Option<FunInfo> {
         }
     }
 }
+
+/// Detect the compiler built-ins from their diagnostic items (the names the
+/// compiler gives to the items it knows, with `#[rustc_diagnostic_item]`).
+pub fn get_builtin_fn_kind_from_diagnostic_item(item: &str) -> Option<gast::BuiltinFnKind> {
+    use gast::BuiltinFnKind;
+    match item {
+        "mem_size_of" => Option::Some(BuiltinFnKind::SizeOf),
+        "mem_align_of" => Option::Some(BuiltinFnKind::AlignOf),
+        "mem_forget" => Option::Some(BuiltinFnKind::Forget),
+        "mem_drop" => Option::Some(BuiltinFnKind::Drop),
+        _ => Option::None,
+    }
+}
+
+/// Detect the functions which are known to the compiler and have guaranteed
+/// semantics (see [crate::gast::BuiltinFnKind]). We only use the names for
+/// the built-ins which don't have a diagnostic item (see
+/// [get_builtin_fn_kind_from_diagnostic_item]): the caller must check that
+/// the function is not defined in the current crate.
+pub fn get_builtin_fn_kind_from_name(name: &FunName) -> Option<gast::BuiltinFnKind> {
+    use gast::BuiltinFnKind;
+    if name.equals_ref_name(&MEM_SIZE_OF_NAME) {
+        Option::Some(BuiltinFnKind::SizeOf)
+    } else if name.equals_ref_name(&MEM_ALIGN_OF_NAME) {
+        Option::Some(BuiltinFnKind::AlignOf)
+    } else if name.equals_ref_name(&MEM_FORGET_NAME) {
+        Option::Some(BuiltinFnKind::Forget)
+    } else if name.equals_ref_name(&MEM_DROP_NAME) {
+        Option::Some(BuiltinFnKind::Drop)
+    } else if name.equals_ref_name(&MANUALLY_DROP_NEW_NAME) {
+        Option::Some(BuiltinFnKind::ManuallyDrop)
+    } else {
+        Option::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gast::BuiltinFnKind;
+
+    fn mk_name(path: &[&str]) -> FunName {
+        Name {
+            name: path
+                .iter()
+                .map(|s| PathElem::Ident(s.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_builtin_fn_kind() {
+        let kind = |path: &[&str]| get_builtin_fn_kind_from_name(&mk_name(path));
+        assert!(kind(&MEM_SIZE_OF_NAME) == Some(BuiltinFnKind::SizeOf));
+        assert!(kind(&MANUALLY_DROP_NEW_NAME) == Some(BuiltinFnKind::ManuallyDrop));
+        // The functions of other modules are not built-ins
+        assert!(kind(&["krate", "mem", "size_of"]).is_none());
+        assert!(kind(&["core", "mem", "replace"]).is_none());
+
+        let kind = get_builtin_fn_kind_from_diagnostic_item;
+        assert!(kind("mem_forget") == Some(BuiltinFnKind::Forget));
+        assert!(kind("mem_drop") == Some(BuiltinFnKind::Drop));
+        assert!(kind("mem_replace").is_none());
+    }
+}
@@ -0,0 +1,159 @@
+//! Compute the call graph of the translated functions.
+//!
+//! This is meant to be used by the tools consuming the translated crate (for
+//! instance, to know which functions are recursive, or which functions a given
+//! entry point depends on).
+#![allow(dead_code)]
+
+use crate::expressions::SharedExprVisitor;
+use crate::types::SharedTypeVisitor;
+use crate::ullbc_ast::{FunDeclId, FunDecls, SharedAstVisitor};
+use linked_hash_set::LinkedHashSet;
+use petgraph::algo::tarjan_scc;
+use petgraph::graphmap::DiGraphMap;
+use std::collections::HashSet;
+
+pub struct CallGraph {
+    /// For every function, the list of functions it calls (without
+    /// duplicates, in the order in which the calls appear in the body).
+    /// Assumed functions (`Box::new`, etc.) are not included.
+    pub callees: FunDeclId::Map<Vec<FunDeclId::Id>>,
+    /// The functions which are (mutually) recursive
+    recursive: HashSet<FunDeclId::Id>,
+}
+
+/// Collect the functions called in a body
+struct CalleesCollector {
+    callees: LinkedHashSet<FunDeclId::Id>,
+}
+
+impl SharedTypeVisitor for CalleesCollector {}
+
+impl SharedExprVisitor for CalleesCollector {
+    fn visit_fun_decl_id(&mut self, id: &FunDeclId::Id) {
+        self.callees.insert(*id);
+    }
+}
+
+impl SharedAstVisitor for CalleesCollector {}
+
+impl CallGraph {
+    /// Build the call graph from the callees of every function.
+    pub fn new(callees: FunDeclId::Map<Vec<FunDeclId::Id>>) -> Self {
+        let mut graph: DiGraphMap<FunDeclId::Id, ()> = DiGraphMap::new();
+        for (id, ids) in callees.iter_indexed() {
+            graph.add_node(*id);
+            for callee in ids {
+                graph.add_edge(*id, *callee, ());
+            }
+        }
+
+        // A function is recursive if it belongs to a strongly connected
+        // component with several functions, or if it calls itself.
+        let mut recursive = HashSet::new();
+        for scc in tarjan_scc(&graph) {
+            if scc.len() > 1 || graph.contains_edge(scc[0], scc[0]) {
+                recursive.extend(scc);
+            }
+        }
+
+        CallGraph { callees, recursive }
+    }
+
+    /// Return the functions directly called by a function.
+    pub fn get_callees(&self, id: FunDeclId::Id) -> &[FunDeclId::Id] {
+        match self.callees.get(id) {
+            Option::None => &[],
+            Option::Some(ids) => ids,
+        }
+    }
+
+    /// Compute the set of functions (transitively) called by `root`.
+    ///
+    /// The set contains `root` only if `root` is recursive.
+    pub fn transitive_callees(&self, root: FunDeclId::Id) -> HashSet<FunDeclId::Id> {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<FunDeclId::Id> = self.get_callees(root).to_vec();
+        while let Some(id) = stack.pop() {
+            if visited.insert(id) {
+                stack.extend(self.get_callees(id));
+            }
+        }
+        visited
+    }
+
+    /// Return `true` if the function is recursive, or part of a group of
+    /// mutually recursive functions.
+    pub fn is_recursive(&self, id: FunDeclId::Id) -> bool {
+        self.recursive.contains(&id)
+    }
+}
+
+/// Build the call graph of a set of translated functions.
+pub fn build_call_graph(fun_defs: &FunDecls) -> CallGraph {
+    let mut callees = FunDeclId::Map::new();
+    for (id, decl) in fun_defs.iter_indexed() {
+        let mut collector = CalleesCollector {
+            callees: LinkedHashSet::new(),
+        };
+        if let Some(body) = &decl.body {
+            for block in &body.body {
+                collector.visit_block_data(block);
+            }
+        }
+        callees.insert(*id, collector.callees.into_iter().collect());
+    }
+    CallGraph::new(callees)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::call_graph::CallGraph;
+    use crate::ullbc_ast::FunDeclId;
+
+    /// Build a call graph from a list of adjacency lists: the function `i`
+    /// calls the functions in `calls[i]`.
+    fn mk_graph(calls: Vec<Vec<usize>>) -> (Vec<FunDeclId::Id>, CallGraph) {
+        let mut gen = FunDeclId::Generator::new();
+        let ids: Vec<FunDeclId::Id> = calls.iter().map(|_| gen.fresh_id()).collect();
+        let mut callees = FunDeclId::Map::new();
+        for (i, tgts) in calls.iter().enumerate() {
+            callees.insert(ids[i], tgts.iter().map(|j| ids[*j]).collect());
+        }
+        (ids, CallGraph::new(callees))
+    }
+
+    #[test]
+    fn test_direct_recursion() {
+        // 0 -> 0, 0 -> 1
+        let (ids, graph) = mk_graph(vec![vec![0, 1], vec![]]);
+        assert!(graph.is_recursive(ids[0]));
+        assert!(!graph.is_recursive(ids[1]));
+        assert!(graph.transitive_callees(ids[0]) == ids.iter().copied().collect());
+        assert!(graph.transitive_callees(ids[1]).is_empty());
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        // 0 -> 1 -> 2 -> 0, 2 -> 3
+        let (ids, graph) = mk_graph(vec![vec![1], vec![2], vec![0, 3], vec![]]);
+        for id in &ids[0..3] {
+            assert!(graph.is_recursive(*id));
+            assert!(graph.transitive_callees(*id).contains(id));
+        }
+        assert!(!graph.is_recursive(ids[3]));
+    }
+
+    #[test]
+    fn test_dag() {
+        // 0 -> 1, 0 -> 2, 1 -> 3, 2 -> 3
+        let (ids, graph) = mk_graph(vec![vec![1, 2], vec![3], vec![3], vec![]]);
+        for id in &ids {
+            assert!(!graph.is_recursive(*id));
+            assert!(!graph.transitive_callees(*id).contains(id));
+        }
+        assert!(graph.transitive_callees(ids[0]) == ids[1..].iter().copied().collect());
+        assert!(graph.transitive_callees(ids[1]) == vec![ids[3]].into_iter().collect());
+        assert!(graph.get_callees(ids[0]) == [ids[1], ids[2]]);
+    }
+}
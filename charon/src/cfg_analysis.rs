@@ -0,0 +1,123 @@
+//! Analyses of the control-flow graphs of the ULLBC bodies (see
+//! [crate::detect_loops::build_cfg]).
+//!
+//! A block `d` dominates a block `b` if all the paths from the entry block to
+//! `b` go through `d`. The dominance frontier of a block `d` is the set of
+//! blocks `b` such that `d` dominates a predecessor of `b`, but doesn't
+//! strictly dominate `b`: this is where the control-flow paths coming from
+//! `d` join the other paths. This is where the SSA construction inserts the
+//! phi nodes for the variables assigned in `d`.
+
+#![allow(dead_code)]
+
+use crate::detect_loops::Cfg;
+use crate::ullbc_ast::*;
+use petgraph::algo::dominators::{simple_fast, Dominators};
+use petgraph::Direction;
+use std::collections::{HashMap, HashSet};
+
+/// The dominator tree of a control-flow graph
+pub type DomTree = Dominators<BlockId::Id>;
+
+/// Compute the dominator tree of a control-flow graph, whose entry is the
+/// first block.
+pub fn compute_dominators(cfg: &Cfg) -> DomTree {
+    simple_fast(cfg, BlockId::ZERO)
+}
+
+/// Return `true` if the block is reachable from the entry block.
+fn is_reachable(dom_tree: &DomTree, b: BlockId::Id) -> bool {
+    dom_tree.dominators(b).is_some()
+}
+
+/// Compute the dominance frontiers of the blocks of a control-flow graph,
+/// with the algorithm of Cooper, Harvey and Kennedy ("A Simple, Fast
+/// Dominance Algorithm"): the blocks with several predecessors are in the
+/// frontier of their predecessors, and of the dominators of those
+/// predecessors up to (and excluding) their own immediate dominator.
+///
+/// Every block of the graph has an entry (possibly empty). We ignore the
+/// unreachable blocks.
+pub fn dominance_frontier(
+    cfg: &Cfg,
+    dom_tree: &DomTree,
+) -> HashMap<BlockId::Id, HashSet<BlockId::Id>> {
+    let mut frontiers: HashMap<BlockId::Id, HashSet<BlockId::Id>> =
+        cfg.nodes().map(|b| (b, HashSet::new())).collect();
+    for b in cfg.nodes().filter(|b| is_reachable(dom_tree, *b)) {
+        let preds: Vec<BlockId::Id> = cfg
+            .neighbors_directed(b, Direction::Incoming)
+            .filter(|p| is_reachable(dom_tree, *p))
+            .collect();
+        if preds.len() < 2 {
+            continue;
+        }
+        let idom = dom_tree.immediate_dominator(b);
+        for p in preds {
+            // Walk up the dominator tree from the predecessor
+            let mut runner = p;
+            while Some(runner) != idom {
+                frontiers.get_mut(&runner).unwrap().insert(b);
+                match dom_tree.immediate_dominator(runner) {
+                    Option::Some(d) => runner = d,
+                    // We reached the entry block
+                    Option::None => break,
+                }
+            }
+        }
+    }
+    frontiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mk_cfg(edges: &[(usize, usize)]) -> Cfg {
+        Cfg::from_edges(
+            edges
+                .iter()
+                .map(|(src, tgt)| (BlockId::Id::new(*src), BlockId::Id::new(*tgt))),
+        )
+    }
+
+    fn ids(ids: &[usize]) -> HashSet<BlockId::Id> {
+        ids.iter().map(|id| BlockId::Id::new(*id)).collect()
+    }
+
+    /// ```text
+    /// bb0: switch [bb1, bb2]   // entry
+    /// bb1: goto bb3            // left
+    /// bb2: goto bb3            // right
+    /// bb3: return              // join
+    /// ```
+    #[test]
+    fn test_diamond() {
+        let cfg = mk_cfg(&[(0, 1), (0, 2), (1, 3), (2, 3)]);
+        let frontiers = dominance_frontier(&cfg, &compute_dominators(&cfg));
+        let frontier = |id: usize| frontiers.get(&BlockId::Id::new(id)).unwrap();
+        assert!(*frontier(0) == ids(&[]));
+        assert!(*frontier(1) == ids(&[3]));
+        assert!(*frontier(2) == ids(&[3]));
+        assert!(*frontier(3) == ids(&[]));
+    }
+
+    /// ```text
+    /// bb0: goto bb1
+    /// bb1: switch [bb2, bb3]   // loop header
+    /// bb2: goto bb1
+    /// bb3: return
+    /// ```
+    #[test]
+    fn test_loop() {
+        let cfg = mk_cfg(&[(0, 1), (1, 2), (1, 3), (2, 1)]);
+        let frontiers = dominance_frontier(&cfg, &compute_dominators(&cfg));
+        let frontier = |id: usize| frontiers.get(&BlockId::Id::new(id)).unwrap();
+        assert!(*frontier(0) == ids(&[]));
+        // The header is in its own frontier: it doesn't strictly dominate
+        // itself
+        assert!(*frontier(1) == ids(&[1]));
+        assert!(*frontier(2) == ids(&[1]));
+        assert!(*frontier(3) == ids(&[]));
+    }
+}
@@ -0,0 +1,251 @@
+//! Export the control-flow graph of a ULLBC function body to a GraphViz
+//! `.dot` file. This is only used for debugging: it is a lot easier to
+//! understand the shape of a translated body by looking at a picture than by
+//! reading a list of blocks with `goto`s.
+//!
+//! We generate one node per block, labeled with the block's statements and
+//! terminator, and one edge per successor of the terminator, labeled with
+//! the branch condition (if there is one).
+#![allow(dead_code)]
+
+use crate::formatter::Formatter;
+use crate::types::*;
+use crate::ullbc_ast::*;
+use crate::values::*;
+use std::io::Write;
+
+/// Write the control-flow graph of a function to `w`, in the GraphViz format.
+///
+/// Opaque functions (i.e., functions without bodies) lead to an empty graph.
+pub fn write_cfg_dot<'ctx, FD, GD>(
+    decl: &FunDecl,
+    ty_ctx: &'ctx TypeDecls,
+    fun_ctx: &'ctx FD,
+    global_ctx: &'ctx GD,
+    w: &mut impl Write,
+) -> std::io::Result<()>
+where
+    FD: Formatter<FunDeclId::Id>,
+    GD: Formatter<GlobalDeclId::Id>,
+{
+    let name = decl.name.to_string();
+    match &decl.body {
+        Option::None => {
+            writeln!(w, "digraph \"{}\" {{", escape(&name))?;
+            writeln!(w, "}}")
+        }
+        Option::Some(body) => {
+            let ctx = GAstFormatter::new(
+                ty_ctx,
+                fun_ctx,
+                global_ctx,
+                Some(&decl.signature.type_params),
+                Some(&body.locals),
+                Some(&decl.signature.const_generic_params),
+            );
+            write_blocks_dot(&name, &body.body, &ctx, w)
+        }
+    }
+}
+
+/// Write the graph for a list of blocks. The entry block is the block
+/// [START_BLOCK_ID].
+fn write_blocks_dot<'a, C>(
+    name: &str,
+    blocks: &'a BlockId::Vector<BlockData>,
+    ctx: &C,
+    w: &mut impl Write,
+) -> std::io::Result<()>
+where
+    C: Formatter<VarId::Id>
+        + Formatter<TypeVarId::Id>
+        + Formatter<&'a ErasedRegion>
+        + Formatter<TypeDeclId::Id>
+        + Formatter<ConstGenericVarId::Id>
+        + Formatter<FunDeclId::Id>
+        + Formatter<GlobalDeclId::Id>
+        + Formatter<(TypeDeclId::Id, VariantId::Id)>
+        + Formatter<(TypeDeclId::Id, Option<VariantId::Id>, FieldId::Id)>,
+{
+    writeln!(w, "digraph \"{}\" {{", escape(name))?;
+    writeln!(w, "  node [shape=box, fontname=\"monospace\"];")?;
+
+    // The nodes
+    for (bid, block) in blocks.iter_indexed_values() {
+        let mut label = format!("bb{bid}:\\l");
+        for st in &block.statements {
+            label.push_str(&escape(&st.fmt_with_ctx(ctx)));
+            label.push_str(";\\l");
+        }
+        label.push_str(&escape(&block.terminator.fmt_with_ctx(ctx)));
+        label.push_str(";\\l");
+        if bid == START_BLOCK_ID {
+            writeln!(w, "  bb{bid} [label=\"{label}\", penwidth=2];")?;
+        } else {
+            writeln!(w, "  bb{bid} [label=\"{label}\"];")?;
+        }
+    }
+
+    // The edges
+    for (bid, block) in blocks.iter_indexed_values() {
+        for (target, label) in get_labeled_targets(&block.terminator) {
+            match label {
+                Option::None => writeln!(w, "  bb{bid} -> bb{target};")?,
+                Option::Some(label) => {
+                    writeln!(w, "  bb{bid} -> bb{target} [label=\"{}\"];", escape(&label))?
+                }
+            }
+        }
+    }
+
+    writeln!(w, "}}")
+}
+
+/// Return the successors of a terminator, together with the condition under
+/// which we branch to them (if there is one).
+fn get_labeled_targets(terminator: &Terminator) -> Vec<(BlockId::Id, Option<String>)> {
+    match &terminator.content {
+        RawTerminator::Goto { target } | RawTerminator::Call { target, .. } => {
+            vec![(*target, None)]
+        }
+        RawTerminator::Drop { target, unwind, .. } => {
+            let mut targets = vec![(*target, None)];
+            if let Some(unwind) = unwind {
+                targets.push((*unwind, Some("unwind".to_string())));
+            }
+            targets
+        }
+        RawTerminator::Assert {
+            expected, target, ..
+        } => vec![(*target, Some(format!("{expected}")))],
+        RawTerminator::Switch { targets, .. } => match targets {
+            SwitchTargets::If(then_tgt, else_tgt) => vec![
+                (*then_tgt, Some("true".to_string())),
+                (*else_tgt, Some("false".to_string())),
+            ],
+            SwitchTargets::SwitchInt(_, branches, otherwise) => {
+                let mut targets: Vec<(BlockId::Id, Option<String>)> = branches
+                    .iter()
+                    .map(|(v, tgt)| (*tgt, Some(v.to_string())))
+                    .collect();
+                targets.push((*otherwise, Some("otherwise".to_string())));
+                targets
+            }
+        },
+        RawTerminator::Yield {
+            resume,
+            drop_target,
+            ..
+        } => {
+            let mut targets = vec![(*resume, Some("resume".to_string()))];
+            if let Some(drop_target) = drop_target {
+                targets.push((*drop_target, Some("drop".to_string())));
+            }
+            targets
+        }
+        RawTerminator::Panic
+        | RawTerminator::Return
+        | RawTerminator::Unreachable
+        | RawTerminator::GeneratorDrop => vec![],
+    }
+}
+
+/// Escape a string so that it can be used inside a quoted dot identifier.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\l"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::types::*;
+    use crate::ullbc_ast::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn block(content: RawTerminator) -> BlockData {
+        BlockData {
+            statements: vec![],
+            terminator: Terminator {
+                meta: dummy_meta(),
+                content,
+            },
+            is_loop_header: false,
+        }
+    }
+
+    /// `if b { return } else { return }`
+    #[test]
+    fn test_if_then_else() {
+        let mut block_ids = BlockId::Generator::new();
+        let entry = block_ids.fresh_id();
+        let then_block = block_ids.fresh_id();
+        let else_block = block_ids.fresh_id();
+
+        let discr = Operand::Move(Place {
+            var_id: VarId::Generator::new().fresh_id(),
+            projection: vec![],
+        });
+        let mut blocks = BlockId::Vector::new();
+        blocks.push_back(block(RawTerminator::Switch {
+            discr,
+            targets: SwitchTargets::If(then_block, else_block),
+        }));
+        blocks.push_back(block(RawTerminator::Return));
+        blocks.push_back(block(RawTerminator::Return));
+        assert!(entry == START_BLOCK_ID);
+
+        let type_decls = TypeDecls::new();
+        let fun_decls = FunDecls::new();
+        let global_decls = GlobalDecls::new();
+        let fun_ctx = FunDeclsFormatter::new(&fun_decls);
+        let global_ctx = GlobalDeclsFormatter::new(&global_decls);
+        let ctx = GAstFormatter::new(&type_decls, &fun_ctx, &global_ctx, None, None, None);
+
+        let mut out: Vec<u8> = Vec::new();
+        super::write_blocks_dot("test", &blocks, &ctx, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // Basic well-formedness
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines.first() == Some(&"digraph \"test\" {"));
+        assert!(lines.last() == Some(&"}"));
+        assert!(out.matches('"').count() % 2 == 0);
+        for line in &lines[1..lines.len() - 1] {
+            assert!(line.ends_with(';'));
+        }
+
+        // One node per block, and one edge per branch (the labels of the
+        // nodes may contain arrows: we only look at what precedes them)
+        let nodes = lines.iter().filter(|l| match l.split_once(" [label=") {
+            Some((head, _)) => !head.contains("->"),
+            None => false,
+        });
+        assert!(nodes.count() == 3);
+        assert!(out.contains("bb0 -> bb1 [label=\"true\"];"));
+        assert!(out.contains("bb0 -> bb2 [label=\"false\"];"));
+    }
+}
@@ -34,8 +34,21 @@ extern crate take_mut;
 #[macro_use]
 mod common;
 mod assumed;
+mod call_graph;
+mod cfg_analysis;
+mod cfg_dot;
+mod check_storage;
 mod cli_options;
+mod constant_propagation;
+mod cse;
+mod def_ids;
+mod deserialize;
+mod detect_interior_mutability;
+mod detect_loops;
+mod diagnostics;
+mod diff;
 mod driver;
+mod eliminate_asserts;
 mod export;
 mod expressions;
 mod expressions_utils;
@@ -46,19 +59,26 @@ mod gast_utils;
 mod generics;
 mod get_mir;
 mod graphs;
+mod html_export;
 mod id_map;
 mod id_vector;
 mod index_to_function_calls;
 mod insert_assign_return_unit;
+mod liveness;
 mod llbc_ast;
 mod llbc_ast_utils;
 mod logger;
 mod meta;
 mod meta_utils;
+mod name_matcher;
 mod names;
 mod names_utils;
+mod normalize_projections;
 mod ops_to_function_calls;
 mod reconstruct_asserts;
+mod reconstruct_if_let;
+mod reconstruct_let_else;
+mod reconstruct_try;
 mod regions_hierarchy;
 mod regularize_constant_adts;
 mod remove_drop_never;
@@ -66,18 +86,32 @@ mod remove_dynamic_checks;
 mod remove_read_discriminant;
 mod remove_unused_locals;
 mod reorder_decls;
+mod restore_type_aliases;
+mod schema;
+mod stable_ids;
+mod stats;
+mod strip_macro_metadata;
+mod substitute;
+mod transform;
 mod translate_constants;
 mod translate_crate_to_ullbc;
 mod translate_ctx;
 mod translate_functions_to_ullbc;
+mod translate_traits;
 mod translate_types;
+mod type_dep_graph;
+mod typecheck;
 mod types;
+mod types_interner;
 mod types_utils;
 mod ullbc_ast;
 mod ullbc_ast_utils;
 mod ullbc_to_llbc;
+mod unsafe_propagation;
 mod values;
 mod values_utils;
+mod wellformed;
+mod workspace;
 
 use crate::driver::{arg_value, get_args_crate_index, get_args_source_index, CharonCallbacks};
 use rustc_driver::RunCompiler;
@@ -138,6 +172,9 @@ fn main() {
     if options.use_polonius {
         compiler_args.push("-Zpolonius".to_string());
     }
+    if options.error_on_warnings {
+        compiler_args.push("-Dwarnings".to_string());
+    }
 
     // In order to have some flexibility in our tests, we give the possibility
     // of specifying the source (the input file which gives the entry to the
@@ -0,0 +1,79 @@
+//! Print the JSON schema of the files generated by Charon, or validate
+//! generated files against this schema.
+//!
+//! Usage:
+//! ```text
+//! charon-schema [--ullbc]                     # print the schema
+//! charon-schema [--ullbc] --validate FILE...  # validate some files
+//! ```
+
+#![cfg_attr(feature = "rustc", feature(rustc_private))]
+
+// Needed to link with the rustc libraries used by [charon_lib]
+#[cfg(feature = "rustc")]
+extern crate rustc_driver;
+
+use charon_lib::export::{llbc_schema, ullbc_schema};
+use charon_lib::schema::validate;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(name = "charon-schema")]
+struct Opts {
+    /// Use the schema of the ULLBC files (the default is LLBC)
+    #[structopt(long = "ullbc")]
+    ullbc: bool,
+    /// Validate the given files instead of printing the schema
+    #[structopt(long = "validate", parse(from_os_str))]
+    validate: Vec<PathBuf>,
+}
+
+/// Read a generated file. The statements of the LLBC bodies are nested: we
+/// can't use the recursion limit of the JSON parser (see
+/// [charon_lib::deserialize::gdeserialize_crate]).
+fn read_file(path: &Path) -> Result<serde_json::Value, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut deserializer = serde_json::Deserializer::from_str(&content);
+    deserializer.disable_recursion_limit();
+    serde_json::Value::deserialize(&mut deserializer).map_err(|e| e.to_string())
+}
+
+fn main() {
+    let opts = Opts::from_args();
+    let schema = if opts.ullbc {
+        ullbc_schema()
+    } else {
+        llbc_schema()
+    };
+
+    if opts.validate.is_empty() {
+        println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+        return;
+    }
+
+    let mut error = false;
+    for path in &opts.validate {
+        let instance = match read_file(path) {
+            Ok(v) => v,
+            Err(e) => {
+                eprintln!("{}: could not read the file: {}", path.display(), e);
+                error = true;
+                continue;
+            }
+        };
+        match validate(&schema, &instance) {
+            Ok(()) => println!("{}: ok", path.display()),
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("{}: {}", path.display(), e);
+                }
+                error = true;
+            }
+        }
+    }
+    if error {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,204 @@
+//! Check that the `StorageLive` and `StorageDead` statements of the ULLBC
+//! bodies are well-bracketed: whatever the path we take, the storage of a local
+//! which was made live by a `StorageLive` must have been made dead by a
+//! `StorageDead` when we return from the function.
+//!
+//! We perform a forward dataflow analysis to compute the locals whose storage
+//! may be live at the entry of every block: the storage of a local may be live
+//! at the entry of a block if it may be live at the exit of one of its
+//! predecessors. We iterate until we reach a fixpoint.
+//!
+//! Note that we don't check the paths which lead to a panic: the cleanup blocks
+//! (see the `unwind` field of the drops) end with a panic.
+//!
+//! We don't check the bodies of the globals either: the temporaries borrowed
+//! by the value of a constant (`const S: &[u8] = &[1, 2, 3];`) have their
+//! lifetime extended to `'static`, and are never made dead.
+
+use crate::ullbc_ast::*;
+use crate::ullbc_to_llbc::get_block_targets;
+use crate::values::*;
+use std::collections::{HashMap, HashSet};
+
+/// Compute the locals whose storage may be live at the exit of a block, given
+/// the locals whose storage may be live at its entry.
+fn transfer_block(block: &BlockData, live: &HashSet<VarId::Id>) -> HashSet<VarId::Id> {
+    let mut live = live.clone();
+    for st in &block.statements {
+        match &st.content {
+            RawStatement::StorageLive(var_id) => {
+                live.insert(*var_id);
+            }
+            RawStatement::StorageDead(var_id) => {
+                live.remove(var_id);
+            }
+            RawStatement::Assign(..)
+            | RawStatement::FakeRead(_)
+            | RawStatement::SetDiscriminant(..)
+            | RawStatement::Deinit(_) => (),
+        }
+    }
+    live
+}
+
+/// Compute, for every block of a body, the locals whose storage may be live at
+/// the entry of the block.
+pub fn compute_storage_live(body: &ExprBody) -> HashMap<BlockId::Id, HashSet<VarId::Id>> {
+    let mut storage: HashMap<BlockId::Id, HashSet<VarId::Id>> = body
+        .body
+        .iter_indexed_values()
+        .map(|(bid, _)| (bid, HashSet::new()))
+        .collect();
+
+    // The sets only grow: we stop once they don't change anymore
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (bid, block) in body.body.iter_indexed_values() {
+            let live_out = transfer_block(block, &storage[&bid]);
+            for succ in get_block_targets(body, bid) {
+                let live_in = storage.get_mut(&succ).unwrap();
+                for var_id in &live_out {
+                    changed |= live_in.insert(*var_id);
+                }
+            }
+        }
+    }
+
+    storage
+}
+
+/// Check a body, and return the locals whose storage may still be live when
+/// returning.
+fn check_body(body: &ExprBody) -> Vec<VarId::Id> {
+    let storage = compute_storage_live(body);
+    let mut errors = Vec::new();
+    for (bid, block) in body.body.iter_indexed_values() {
+        if !block.terminator.content.is_return() {
+            continue;
+        }
+        errors.extend(transfer_block(block, &storage[&bid]));
+    }
+    errors.sort();
+    errors.dedup();
+    errors
+}
+
+/// Check the bodies of the functions. We report the errors, and return `false`
+/// if there were any.
+pub fn check(funs: &FunDecls) -> bool {
+    let bodies = funs
+        .iter()
+        .filter_map(|d| d.body.as_ref().map(|b| (&d.name, b)));
+
+    let mut ok = true;
+    for (name, body) in bodies {
+        for var_id in check_body(body) {
+            error!(
+                "In {}: the storage of {} may still be live when returning",
+                name,
+                body.locals.get(var_id).unwrap().to_string()
+            );
+            ok = false;
+        }
+    }
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::types::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn st(content: RawStatement) -> Statement {
+        Statement::new(dummy_meta(), content)
+    }
+
+    fn block(statements: Vec<Statement>, content: RawTerminator) -> BlockData {
+        BlockData {
+            statements,
+            terminator: Terminator::new(dummy_meta(), content),
+            is_loop_header: false,
+        }
+    }
+
+    /// ```text
+    /// fn f(b: bool) {
+    ///   bb0: storage_live(x); switch b [true -> bb1, false -> bb2]
+    ///   bb1: storage_dead(x); goto bb3
+    ///   bb2: <else_sts>; goto bb3
+    ///   bb3: return
+    /// }
+    /// ```
+    fn mk_body(else_sts: Vec<Statement>) -> ExprBody {
+        let mut locals = VarId::Vector::new();
+        locals.fresh_var(None, Ty::mk_unit());
+        let b = locals.fresh_var(Some("b".to_string()), Ty::Literal(LiteralTy::Bool));
+        let x = locals.fresh_var(
+            Some("x".to_string()),
+            Ty::Literal(LiteralTy::Integer(IntegerTy::U32)),
+        );
+
+        let mut blocks = BlockId::Vector::new();
+        let bb1 = BlockId::Id::new(1);
+        let bb2 = BlockId::Id::new(2);
+        let bb3 = BlockId::Id::new(3);
+        blocks.push_back(block(
+            vec![st(RawStatement::StorageLive(x))],
+            RawTerminator::Switch {
+                discr: Operand::Move(Place::new(b)),
+                targets: SwitchTargets::If(bb1, bb2),
+            },
+        ));
+        blocks.push_back(block(
+            vec![st(RawStatement::StorageDead(x))],
+            RawTerminator::Goto { target: bb3 },
+        ));
+        blocks.push_back(block(else_sts, RawTerminator::Goto { target: bb3 }));
+        blocks.push_back(block(vec![], RawTerminator::Return));
+
+        ExprBody {
+            meta: dummy_meta(),
+            arg_count: 1,
+            locals,
+            body: blocks,
+            liveness: None,
+        }
+    }
+
+    #[test]
+    fn test_matching_storage() {
+        let x = VarId::Id::new(2);
+        let body = mk_body(vec![st(RawStatement::StorageDead(x))]);
+        assert!(check_body(&body).is_empty());
+        // The storage of `x` is live in both branches
+        let storage = compute_storage_live(&body);
+        assert!(storage[&BlockId::Id::new(1)].contains(&x));
+        assert!(storage[&BlockId::Id::new(2)].contains(&x));
+        assert!(storage[&BlockId::Id::new(3)].is_empty());
+    }
+
+    #[test]
+    fn test_missing_storage_dead() {
+        let x = VarId::Id::new(2);
+        let body = mk_body(vec![]);
+        let errors = check_body(&body);
+        assert!(errors == vec![x]);
+    }
+}
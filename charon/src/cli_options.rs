@@ -2,7 +2,7 @@
 
 /// The options received as input by cargo-charon
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 // This structure is used to store the command-line instructions.
@@ -19,12 +19,128 @@ pub struct CliOpts {
     /// Extract the unstructured LLBC (i.e., don't reconstruct the control-flow)
     #[structopt(long = "ullbc")]
     pub ullbc: bool,
+    /// Compute the liveness information of the local variables, and include
+    /// it in the bodies of the extracted functions and globals (only with
+    /// `--ullbc`).
+    #[structopt(long = "liveness")]
+    pub liveness: bool,
+    /// Print the functions as pseudo-code on the standard output, after the
+    /// translation to ULLBC (i.e., before reconstructing the control-flow).
+    #[structopt(long = "pretty")]
+    pub pretty: bool,
+    /// Also generate the output in the CBOR format (in a `.cbor` file), which
+    /// is more compact and faster to parse than JSON.
+    #[structopt(long = "cbor")]
+    pub cbor: bool,
+    /// Also generate an HTML rendering of the LLBC functions (in a `.html`
+    /// file), in which the functions link to their location in the source.
+    /// This is only used for debugging purposes (not with `--ullbc`).
+    #[structopt(long = "html")]
+    pub html: bool,
+    /// The format of the links to the sources in the HTML rendering (see
+    /// `--html`): `{path}` is replaced with the absolute path of the file,
+    /// `{line}` and `{col}` with the position of the function in the file.
+    /// The default format gives plain `file://{path}` links: to open the
+    /// function in VS Code instead, use `vscode://file{path}:{line}:{col}`.
+    #[structopt(long = "html-link-format")]
+    pub html_link_format: Option<String>,
+    /// Print statistics about the functions (number of blocks, statements,
+    /// locals and terminators) on the standard output, and also save them in
+    /// a `.stats.json` file.
+    #[structopt(long = "stats")]
+    pub stats: bool,
+    /// Generate one file per module rather than a single file: the file of a
+    /// module `crate::a::b` is `crate.a.b.llbc` (or `.ullbc`). The declarations
+    /// keep their (crate-wide) ids, and every file lists the files it depends
+    /// on in its `dependencies` field.
+    #[structopt(long = "split-output")]
+    pub split_output: bool,
+    /// Merge the consecutive projections of the places which can be
+    /// expressed as a single projection, for instance the indexing in a
+    /// subslice.
+    #[structopt(long = "normalize-projections")]
+    pub normalize_projections: bool,
+    /// Propagate the constants assigned to temporaries to the places where
+    /// those temporaries are used, and remove the temporaries.
+    #[structopt(long = "constant-propagation")]
+    pub constant_propagation: bool,
+    /// Reconstruct the `?` operators: MIR desugars them to a call to
+    /// `Try::branch` followed by a match, which we replace with a
+    /// `TryOp` statement.
+    #[structopt(long = "reconstruct-try")]
+    pub reconstruct_try: bool,
+    /// Eliminate the common subexpressions: when the same operation is
+    /// computed several times in a sequence of statements, we compute it
+    /// once and reuse the result.
+    #[structopt(long = "cse")]
+    pub cse: bool,
+    /// Compute stable, name-based ids for the declarations, which don't
+    /// change when we add or remove unrelated declarations. The ids are
+    /// stored in the `stable_id` field of the declarations.
+    #[structopt(long = "stable-ids")]
+    pub stable_ids: bool,
+    /// Replace the statements coming from macro expansions (`assert!`,
+    /// `println!`, etc.) with placeholders, to make the output more
+    /// readable.
+    #[structopt(long = "strip-macro-metadata")]
+    pub strip_macro_metadata: bool,
+    /// Remove the overflow checks that rustc inserts after the arithmetic
+    /// operations in debug mode, and use the unchecked operations instead.
+    #[structopt(long = "eliminate-asserts")]
+    pub eliminate_asserts: bool,
+    /// Evaluate the constants whose value is fully known at compile time
+    /// (`S` if `const S: u64 = square(7)`) and inline their value, rather
+    /// than referring to the constant declarations. We fall back to the
+    /// references if the evaluation fails.
+    #[structopt(long = "inline-const-eval")]
+    pub inline_const_eval: bool,
+    /// Record the rustc identifiers (`DefId`) of the declarations in the
+    /// generated files, in a `def_ids` field which maps the ids of the
+    /// declarations to their rustc identifiers.
+    #[structopt(long = "emit-def-ids")]
+    pub emit_def_ids: bool,
+    /// Extract the MIR of a specific function at the given level, rather than
+    /// at the level of the crate: `--mir-level-for module::f=optimized`. The
+    /// level is "built", "promoted" or "optimized". This can be repeated. The
+    /// level can also be given with a `#[charon::mir_level = "optimized"]`
+    /// attribute on the function.
+    #[structopt(long = "mir-level-for")]
+    pub mir_level_for: Vec<String>,
+    /// Remove the opaque declarations (see `--opaque`) from the generated
+    /// files, except those which the transparent declarations refer to. The
+    /// remaining declarations keep their ids.
+    #[structopt(long = "strip-opaque")]
+    pub strip_opaque: bool,
+    /// Preserve the type aliases: rustc expands them, but we translate them
+    /// to type declarations, and replace the types which are exactly the
+    /// expansion of an alias (without generic parameters) with the alias.
+    #[structopt(long = "preserve-type-aliases")]
+    pub preserve_type_aliases: bool,
+    /// Dump the MIR of the translated functions, as printed by rustc, next
+    /// to the generated files: the MIR of `krate::m::f` is written to
+    /// `krate.m.f.mir`. This is the MIR at the level we translate (see
+    /// `--mir_promoted`, `--mir_optimized` and `--mir-level-for`).
+    #[structopt(long = "emit-mir")]
+    pub emit_mir: bool,
+    /// Preserve the `StorageLive` and `StorageDead` statements of the MIR,
+    /// which mark the lexical lifetimes of the local variables, as
+    /// `storage_live(x)` and `storage_dead(x)`. By default, we ignore the
+    /// `StorageLive` statements and translate the `StorageDead` statements to
+    /// drops.
+    #[structopt(long = "storage-annotations")]
+    pub storage_annotations: bool,
     /// Compile the package's library
     #[structopt(long = "lib")]
     pub lib: bool,
     /// Compile the specified binary
     #[structopt(long = "bin")]
     pub bin: Option<String>,
+    /// Translate all the members of the Cargo workspace of the current
+    /// directory, one after the other: we generate one file per member crate.
+    /// The references to the declarations of the other members are resolved
+    /// when reading the files back with the Charon library.
+    #[structopt(long = "workspace")]
+    pub workspace: bool,
     /// Extract the promoted MIR instead of the built MIR
     #[structopt(long = "mir_promoted")]
     pub mir_promoted: bool,
@@ -94,16 +210,266 @@ performs: `y := (x as E2).1`). Producing a better reconstruction is non-trivial.
     /// A list of modules of the extracted crate that we consider as opaque: we
     /// extract only the signature information, without the definition content
     /// (of the functions, types, etc.).
+    ///
+    /// We also accept patterns. A path like `krate::module::f` selects this
+    /// item and everything it contains, and a single identifier `module` is
+    /// a module of the extracted crate. A glob like `krate::module::*` matches
+    /// the paths written with `::`, where `*` is any sequence of characters
+    /// and `?` is a single character. A pattern `regex:RE`, like
+    /// `regex:krate::.*_utils`, selects the items whose whole path matches
+    /// the regular expression `RE`.
     #[structopt(long = "opaque")]
     pub opaque_modules: Vec<String>,
+    /// Only translate the given function (for instance `list_length`, or
+    /// `module::f`), together with the declarations it transitively refers
+    /// to. This is useful to debug the translation of a specific function.
+    #[structopt(long = "function")]
+    pub function: Option<String>,
     /// Do not provide a Rust version argument to Cargo (e.g., `+nightly-2022-01-29`).
     /// This is for Nix: outside of Nix, we use Rustup to call the proper version
     /// of Cargo (and thus need this argument), but within Nix we build and call a very
     /// specific version of Cargo.
     #[structopt(long = "cargo-no-rust-version")]
     pub cargo_no_rust_version: bool,
+    /// Treat the warnings emitted by rustc as errors.
+    #[structopt(long = "error-on-warnings")]
+    pub error_on_warnings: bool,
+    /// Don't abort if some declarations can't be translated: we report the
+    /// errors, but still generate the files. The functions whose bodies we
+    /// couldn't translate and the unsupported types are made opaque.
+    #[structopt(long = "errors-as-warnings")]
+    pub errors_as_warnings: bool,
+    /// Type-check the generated LLBC bodies, and abort if we find an error:
+    /// an assignment whose sides have different types, a call with arguments
+    /// of the wrong types, or a switch on a value which is not an integer or a
+    /// boolean. This is useful to debug the micro-passes.
+    #[structopt(long = "typecheck")]
+    pub typecheck: bool,
+}
+
+/// The name of the configuration file. We look for it in the current
+/// directory and in its ancestors (see [ConfigFile::find]).
+pub const CONFIG_FILE_NAME: &str = ".charon.toml";
+
+/// The content of a configuration file. For instance:
+/// ```text
+/// opaque_modules = ["hashmap_utils"]
+/// mir_level = "promoted"
+/// output_path = "llbc"
+/// error_on_warnings = true
+/// ```
+/// The options given on the command-line take precedence over the options
+/// given in the configuration file (see [CliOpts::merge_config]).
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// See [CliOpts::opaque_modules]
+    pub opaque_modules: Vec<String>,
+    /// The level of MIR to extract: "built", "promoted" or "optimized"
+    pub mir_level: Option<String>,
+    /// See [CliOpts::dest_dir]
+    pub output_path: Option<String>,
+    /// See [CliOpts::error_on_warnings]
+    pub error_on_warnings: bool,
+}
+
+impl ConfigFile {
+    /// Look for a configuration file in the given directory and its
+    /// ancestors, and return the path to the first one we find.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(CONFIG_FILE_NAME))
+            .find(|f| f.is_file())
+    }
+
+    pub fn parse(content: &str) -> Result<ConfigFile, String> {
+        toml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    pub fn read(path: &Path) -> Result<ConfigFile, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ConfigFile::parse(&content)
+    }
+}
+
+impl CliOpts {
+    /// Complete the command-line options with the options given in a
+    /// configuration file. The command-line options take precedence.
+    pub fn merge_config(&mut self, config: ConfigFile) -> Result<(), String> {
+        if self.opaque_modules.is_empty() {
+            self.opaque_modules = config.opaque_modules;
+        }
+        if !self.mir_promoted && !self.mir_optimized {
+            match config.mir_level.as_deref() {
+                None | Some("built") => (),
+                Some("promoted") => self.mir_promoted = true,
+                Some("optimized") => self.mir_optimized = true,
+                Some(level) => return Err(format!("Unknown MIR level: {level}")),
+            }
+        }
+        if self.dest_dir.is_none() {
+            self.dest_dir = config.output_path.map(PathBuf::from);
+        }
+        self.error_on_warnings |= config.error_on_warnings;
+        Ok(())
+    }
+}
+
+/// The part of a `Cargo.toml` file we need to translate a workspace (see
+/// [CliOpts::workspace]).
+#[derive(Debug, Default, Deserialize)]
+pub struct CargoManifest {
+    pub package: Option<CargoPackage>,
+    pub workspace: Option<CargoWorkspace>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CargoPackage {
+    pub name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CargoWorkspace {
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+impl CargoManifest {
+    pub fn parse(content: &str) -> Result<CargoManifest, String> {
+        toml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    /// Read the `Cargo.toml` file of a directory.
+    pub fn read(dir: &Path) -> Result<CargoManifest, String> {
+        let path = dir.join("Cargo.toml");
+        let content = std::fs::read_to_string(&path).map_err(|e| format!("{path:?}: {e}"))?;
+        CargoManifest::parse(&content).map_err(|e| format!("{path:?}: {e}"))
+    }
+
+    /// Compute the names of the packages of the workspace whose root is `dir`,
+    /// in the order in which the members are listed. We support the members
+    /// of the shape `dir/*`, which select all the packages of a directory.
+    pub fn workspace_members(dir: &Path) -> Result<Vec<String>, String> {
+        let manifest = CargoManifest::read(dir)?;
+        let members = match manifest.workspace {
+            Some(workspace) => workspace.members,
+            None => return Err(format!("{:?} is not the root of a workspace", dir)),
+        };
+
+        let mut member_dirs = Vec::new();
+        for member in members {
+            match member.strip_suffix("/*") {
+                Some(parent) => {
+                    let entries = std::fs::read_dir(dir.join(parent)).map_err(|e| e.to_string())?;
+                    let mut dirs: Vec<PathBuf> = entries
+                        .filter_map(|e| e.ok().map(|e| e.path()))
+                        .filter(|d| d.join("Cargo.toml").is_file())
+                        .collect();
+                    dirs.sort();
+                    member_dirs.extend(dirs);
+                }
+                None => member_dirs.push(dir.join(member)),
+            }
+        }
+
+        member_dirs
+            .iter()
+            .map(|d| match CargoManifest::read(d)?.package {
+                Some(package) => Ok(package.name),
+                None => Err(format!("{:?} doesn't define a package", d)),
+            })
+            .collect()
+    }
 }
 
 /// The name of the environment variable we use to save the serialized Cli options
 /// when calling charon-driver from cargo-charon.
 pub const CHARON_ARGS: &str = "CHARON_ARGS";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_options() -> CliOpts {
+        CliOpts::from_iter(vec!["charon"])
+    }
+
+    #[test]
+    fn test_config_opaque_modules() {
+        let config = ConfigFile::parse("opaque_modules = [\"std\"]").unwrap();
+        let mut options = no_options();
+        options.merge_config(config).unwrap();
+        assert_eq!(options.opaque_modules, vec!["std".to_string()]);
+        assert!(!options.mir_promoted && !options.mir_optimized);
+        assert!(options.dest_dir.is_none());
+        assert!(options.function.is_none());
+    }
+
+    #[test]
+    fn test_config_cli_precedence() {
+        let config = ConfigFile::parse(
+            "opaque_modules = [\"std\"]\nmir_level = \"optimized\"\noutput_path = \"out\"",
+        )
+        .unwrap();
+        let mut options = CliOpts::from_iter(vec!["charon", "--opaque", "core", "--mir_promoted"]);
+        options.merge_config(config).unwrap();
+        assert_eq!(options.opaque_modules, vec!["core".to_string()]);
+        assert!(options.mir_promoted && !options.mir_optimized);
+        assert_eq!(options.dest_dir, Some(PathBuf::from("out")));
+
+        let config = ConfigFile::parse("mir_level = \"unknown\"").unwrap();
+        assert!(no_options().merge_config(config).is_err());
+        assert!(ConfigFile::parse("unknown_field = true").is_err());
+    }
+
+    #[test]
+    fn test_function() {
+        let options = CliOpts::from_iter(vec!["charon", "--function", "list_length"]);
+        assert_eq!(options.function, Some("list_length".to_string()));
+    }
+
+    #[test]
+    fn test_mir_level_for() {
+        let options = CliOpts::from_iter(vec![
+            "charon",
+            "--mir-level-for",
+            "f=optimized",
+            "--mir-level-for",
+            "module::g=promoted",
+        ]);
+        assert_eq!(
+            options.mir_level_for,
+            vec!["f=optimized".to_string(), "module::g=promoted".to_string()]
+        );
+        assert!(no_options().mir_level_for.is_empty());
+    }
+
+    #[test]
+    fn test_workspace_members() {
+        let root = std::env::temp_dir().join("charon_test_workspace_members");
+        for (dir, name) in [("a", "crate-a"), ("crates/b", "crate_b"), ("crates/c", "c")] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            let manifest = format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"");
+            std::fs::write(root.join(dir).join("Cargo.toml"), manifest).unwrap();
+        }
+        let manifest = "[workspace]\nmembers = [\"a\", \"crates/*\"]\nresolver = \"2\"";
+        std::fs::write(root.join("Cargo.toml"), manifest).unwrap();
+
+        let members = CargoManifest::workspace_members(&root).unwrap();
+        assert_eq!(members, vec!["crate-a", "crate_b", "c"]);
+        assert!(CargoManifest::workspace_members(&root.join("a")).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_config_find() {
+        let root = std::env::temp_dir().join("charon_test_config_find");
+        let sub = root.join("a").join("b");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(root.join(CONFIG_FILE_NAME), "error_on_warnings = true").unwrap();
+        let path = ConfigFile::find(&sub).unwrap();
+        assert_eq!(path, root.join(CONFIG_FILE_NAME));
+        assert!(ConfigFile::read(&path).unwrap().error_on_warnings);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,9 +1,13 @@
 #![allow(dead_code)]
 
+#[cfg(feature = "rustc")]
 use crate::meta;
 use im::Vector;
+#[cfg(feature = "rustc")]
 use rustc_error_messages::MultiSpan;
+#[cfg(feature = "rustc")]
 use rustc_errors::DiagnosticId;
+#[cfg(feature = "rustc")]
 use rustc_session::Session;
 use serde::{Serialize, Serializer};
 use std::iter::FromIterator;
@@ -37,12 +41,14 @@ where
 ///   the user, with the same formatting as the compiler messages.
 /// - On the other hand, the logger allows us to report and filter a big number
 ///   of detailed messages, for debugging purposes.
+#[cfg(feature = "rustc")]
 pub trait ErrorEmitter {
     fn span_err<S: Into<MultiSpan>>(&self, s: S, msg: &'static str);
 
     fn span_warn<S: Into<MultiSpan>>(&self, s: S, msg: &'static str);
 }
 
+#[cfg(feature = "rustc")]
 impl ErrorEmitter for Session {
     fn span_err<S: Into<MultiSpan>>(&self, s: S, msg: &'static str) {
         self.span_err_with_code(s, msg, DiagnosticId::Error(String::from("Aeneas")));
@@ -53,11 +59,13 @@ impl ErrorEmitter for Session {
     }
 }
 
+#[cfg(feature = "rustc")]
 pub fn span_err(sess: &Session, span: rustc_span::Span, msg: &'static str) {
     log::error!("{}:\n{}", meta::span_to_string(sess, span), msg);
     sess.span_err(span, msg);
 }
 
+#[cfg(feature = "rustc")]
 pub fn span_warn(sess: &Session, span: rustc_span::Span, msg: &'static str) {
     log::warn!("{}:\n{}", meta::span_to_string(sess, span), msg);
     sess.span_warn(span, msg);
@@ -0,0 +1,388 @@
+//! The MIR code often contains assignments of constants to temporary
+//! variables, which are then immediately used:
+//! ```text
+//! tmp := const 5;
+//! x := tmp + y;
+//! ```
+//!
+//! This micro-pass propagates the constants to the place where the temporary
+//! is used, and removes the initial assignment:
+//! ```text
+//! x := const 5 + y;
+//! ```
+//!
+//! We are quite conservative: we only propagate a constant if the temporary
+//! is assigned exactly once and used exactly once, and if the use appears in
+//! the same sequence of statements as the assignment, without any control-flow
+//! in between (we never propagate to the branches of a `switch` or the body
+//! of a loop). The drops and the fake reads of the temporaries are not
+//! considered as uses: we remove them together with the assignment. The
+//! unused temporaries are then filtered by [crate::remove_unused_locals].
+
+use crate::expressions::*;
+use crate::id_vector::ToUsize;
+use crate::llbc_ast::{
+    CtxNames, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, SharedAstVisitor, Statement,
+};
+use crate::remove_unused_locals::{remove_nops, ComputeUsedLocals};
+use crate::types::*;
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+use crate::values::*;
+use std::collections::{HashMap, HashSet};
+
+struct PropagateConstants {
+    /// The number of occurrences of the local variables in the body
+    /// (including the occurrences in the left-hand side of the assignments).
+    counts: im::HashMap<VarId::Id, usize>,
+    /// The number of drops and fake reads of the local variables (those are
+    /// included in [Self::counts]).
+    drops: HashMap<VarId::Id, usize>,
+    /// The number of input arguments
+    arg_count: usize,
+    /// The variables we propagated, and whose drops and fake reads must be
+    /// removed
+    propagated: HashSet<VarId::Id>,
+}
+
+impl PropagateConstants {
+    fn new(body: &Statement, arg_count: usize) -> Self {
+        let mut drops = CountDrops {
+            drops: HashMap::new(),
+        };
+        drops.visit_statement(body);
+        PropagateConstants {
+            counts: ComputeUsedLocals::compute_in_statement(body),
+            drops: drops.drops,
+            arg_count,
+            propagated: HashSet::new(),
+        }
+    }
+
+    /// Check if a statement is an assignment of a constant to a temporary we
+    /// can get rid of.
+    fn get_candidate(&self, st: &Statement) -> Option<(VarId::Id, ETy, OperandConstantValue)> {
+        match &st.content {
+            RawStatement::Assign(p, Rvalue::Use(Operand::Const(ty, cv)))
+                if p.projection.is_empty()
+                    // We must not touch the return variable nor the inputs
+                    && p.var_id.to_usize() > self.arg_count
+                    // One assignment, one use (ignoring the drops and fake reads)
+                    && self.counts.get(&p.var_id).copied()
+                        == Some(2 + self.drops.get(&p.var_id).copied().unwrap_or(0)) =>
+            {
+                Some((p.var_id, ty.clone(), cv.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl MutTypeVisitor for PropagateConstants {}
+impl MutExprVisitor for PropagateConstants {}
+
+impl MutAstVisitor for PropagateConstants {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        if let RawStatement::Sequence(s1, s2) = &mut st.content {
+            if let Some((var_id, ty, cv)) = self.get_candidate(s1) {
+                if substitute_in_sequence(var_id, &ty, &cv, s2) {
+                    s1.content = RawStatement::Nop;
+                    self.propagated.insert(var_id);
+                }
+            }
+        }
+        self.default_visit_raw_statement(&mut st.content)
+    }
+}
+
+/// Count the drops and fake reads of the (whole) local variables.
+struct CountDrops {
+    drops: HashMap<VarId::Id, usize>,
+}
+
+impl SharedTypeVisitor for CountDrops {}
+impl SharedExprVisitor for CountDrops {}
+
+impl SharedAstVisitor for CountDrops {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_fake_read(&mut self, p: &Place) {
+        if p.projection.is_empty() {
+            *self.drops.entry(p.var_id).or_insert(0) += 1;
+        }
+    }
+
+    fn visit_drop(&mut self, p: &Place) {
+        self.visit_fake_read(p)
+    }
+}
+
+/// Remove the drops and fake reads of the variables we propagated: those
+/// variables are not initialized anymore.
+struct RemoveDrops<'a> {
+    propagated: &'a HashSet<VarId::Id>,
+}
+
+impl<'a> MutTypeVisitor for RemoveDrops<'a> {}
+impl<'a> MutExprVisitor for RemoveDrops<'a> {}
+
+impl<'a> MutAstVisitor for RemoveDrops<'a> {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        match &st.content {
+            RawStatement::Drop(p) | RawStatement::FakeRead(p)
+                if p.projection.is_empty() && self.propagated.contains(&p.var_id) =>
+            {
+                st.content = RawStatement::Nop
+            }
+            _ => self.default_visit_raw_statement(&mut st.content),
+        }
+    }
+}
+
+/// Replace the operands `copy x` and `move x` with a constant.
+struct SubstituteOperand<'a> {
+    var_id: VarId::Id,
+    ty: &'a ETy,
+    cv: &'a OperandConstantValue,
+    /// Set to `true` once we performed the substitution
+    done: bool,
+}
+
+impl<'a> MutTypeVisitor for SubstituteOperand<'a> {}
+
+impl<'a> MutExprVisitor for SubstituteOperand<'a> {
+    fn visit_operand(&mut self, o: &mut Operand) {
+        match o {
+            Operand::Copy(p) | Operand::Move(p)
+                if p.var_id == self.var_id && p.projection.is_empty() =>
+            {
+                *o = Operand::Const(self.ty.clone(), self.cv.clone());
+                self.done = true;
+            }
+            _ => self.default_visit_operand(o),
+        }
+    }
+}
+
+impl<'a> MutAstVisitor for SubstituteOperand<'a> {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+}
+
+/// Return `true` if the statement doesn't contain any control-flow.
+fn is_straight_line(st: &Statement) -> bool {
+    use RawStatement::*;
+    match &st.content {
+        Assign(..) | FakeRead(_) | SetDiscriminant(..) | Drop(_) | StorageLive(_)
+        | StorageDead(_) | Assert(_) | TaggedAssert(..) | Call(_) | Nop | MacroExpansion(_) => true,
+        Panic
+        | Return
+        | Break(_)
+        | Continue(_)
+        | Sequence(..)
+        | Switch(_)
+        | Loop(_)
+        | LetElse(..)
+        | IfLet(..)
+        | TryOp { .. } => false,
+    }
+}
+
+/// Try to substitute the use of `var_id` in a statement which is not a
+/// sequence.
+///
+/// Return `None` if the statement doesn't use `var_id` and we can keep on
+/// looking in the subsequent statements, and `Some(b)` otherwise, where `b`
+/// tells whether we performed the substitution.
+fn substitute_in_statement(
+    var_id: VarId::Id,
+    ty: &ETy,
+    cv: &OperandConstantValue,
+    st: &mut Statement,
+) -> Option<bool> {
+    if !is_straight_line(st) {
+        return Some(false);
+    }
+    // Skip the drops and fake reads (they will be removed)
+    if let RawStatement::Drop(p) | RawStatement::FakeRead(p) = &st.content {
+        if p.var_id == var_id && p.projection.is_empty() {
+            return None;
+        }
+    }
+    if !ComputeUsedLocals::compute_in_statement(st).contains_key(&var_id) {
+        return None;
+    }
+
+    // The variable may be used in a way which doesn't allow us to replace it
+    // with a constant (if it is borrowed, for instance)
+    let mut visitor = SubstituteOperand {
+        var_id,
+        ty,
+        cv,
+        done: false,
+    };
+    visitor.visit_statement(st);
+    Some(visitor.done)
+}
+
+/// Look for the (unique) use of `var_id` in a sequence of statements, and
+/// substitute it with the constant `cv`. We stop at the first statement
+/// which is not straight-line code.
+///
+/// Return `true` if we performed the substitution.
+fn substitute_in_sequence(
+    var_id: VarId::Id,
+    ty: &ETy,
+    cv: &OperandConstantValue,
+    st: &mut Statement,
+) -> bool {
+    match &mut st.content {
+        RawStatement::Sequence(s1, s2) => match substitute_in_statement(var_id, ty, cv, s1) {
+            Option::Some(done) => done,
+            Option::None => substitute_in_sequence(var_id, ty, cv, s2),
+        },
+        _ => substitute_in_statement(var_id, ty, cv, st).unwrap_or(false),
+    }
+}
+
+/// `fmt_ctx` is used for pretty-printing purposes.
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to propagate the constants in decl: {name}:\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+
+        let mut visitor = PropagateConstants::new(&b.body, b.arg_count);
+        visitor.visit_statement(&mut b.body);
+        RemoveDrops {
+            propagated: &visitor.propagated,
+        }
+        .visit_statement(&mut b.body);
+        remove_nops(&mut b.body);
+
+        trace!(
+            "# After constant propagation in decl: {name}:\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expressions::*;
+    use crate::llbc_ast::{ExprBody, MutAstVisitor, RawStatement, Statement, Var};
+    use crate::llbc_ast_utils::chain_statements;
+    use crate::meta::*;
+    use crate::remove_unused_locals::remove_unused_locals_in_body;
+    use crate::types::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn mk_place(var_id: VarId::Id) -> Place {
+        Place {
+            var_id,
+            projection: vec![],
+        }
+    }
+
+    /// The body of `add_test`, after a constant has been introduced:
+    /// ```text
+    /// fn add_test(x: u32) -> u32 {
+    ///   tmp := const 5;
+    ///   @return := copy x + move tmp;
+    ///   return;
+    /// }
+    /// ```
+    #[test]
+    fn test_add_test() {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut ids = VarId::Generator::new();
+        let ret = ids.fresh_id();
+        let x = ids.fresh_id();
+        let tmp = ids.fresh_id();
+        let mut locals = VarId::Vector::new();
+        for (index, name) in [(ret, None), (x, Some("x")), (tmp, None)] {
+            locals.push_back(Var {
+                index,
+                name: name.map(|s| s.to_string()),
+                ty: u32_ty.clone().into(),
+            });
+        }
+
+        let five = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(5)));
+        let meta = dummy_meta();
+        let body = chain_statements(
+            vec![
+                Statement::new(
+                    meta,
+                    RawStatement::Assign(mk_place(tmp), Rvalue::Use(Operand::Const(u32_ty, five))),
+                ),
+                Statement::new(
+                    meta,
+                    RawStatement::Assign(
+                        mk_place(ret),
+                        Rvalue::BinaryOp(
+                            BinOp::Add,
+                            Operand::Copy(mk_place(x)),
+                            Operand::Move(mk_place(tmp)),
+                        ),
+                    ),
+                ),
+            ],
+            Statement::new(meta, RawStatement::Return),
+        );
+        let mut body = ExprBody {
+            meta,
+            arg_count: 1,
+            locals,
+            body,
+            liveness: None,
+        };
+
+        let mut visitor = super::PropagateConstants::new(&body.body, body.arg_count);
+        visitor.visit_statement(&mut body.body);
+        super::remove_nops(&mut body.body);
+        remove_unused_locals_in_body(&mut body);
+
+        assert!(body.locals.len() == 2);
+        match &body.body.content {
+            RawStatement::Sequence(st, _) => match &st.content {
+                RawStatement::Assign(_, Rvalue::BinaryOp(_, _, Operand::Const(..))) => (),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! Common subexpression elimination: the LLBC code often contains the same
+//! computation several times in a row:
+//! ```text
+//! x := copy a + copy b;
+//! ...
+//! y := copy a + copy b;
+//! ```
+//!
+//! We store the result of the first computation in a fresh temporary, and
+//! reuse it:
+//! ```text
+//! tmp := copy a + copy b;
+//! x := copy tmp;
+//! ...
+//! y := copy tmp;
+//! ```
+//!
+//! We only consider the unary and binary operations whose operands are
+//! copies or constants. rustc often introduces temporaries for the operands
+//! (`tmp1 := copy a; tmp2 := copy b; x := move tmp1 + move tmp2`): we look
+//! through those, and compare the rvalues as if they were written with the
+//! copied operands. We are quite conservative: we only look for the
+//! duplicates in the same sequence of statements, without any control-flow in
+//! between, and we forget about a computation as soon as one of its operands
+//! may have been modified (because it is assigned, moved or borrowed, or
+//! because we call a function). When a computation has no duplicate, we
+//! don't introduce a temporary. The temporaries we introduce and end up not
+//! using are filtered by [crate::remove_unused_locals].
+
+use crate::expressions::*;
+use crate::llbc_ast::{
+    CtxNames, ExprBody, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, Statement, Var,
+};
+use crate::remove_unused_locals::{remove_nops, ComputeUsedLocals};
+use crate::types::*;
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+use crate::values::*;
+use std::collections::{HashMap, HashSet};
+
+struct EliminateCommonSubexprs<'a> {
+    locals: &'a mut VarId::Vector<Var>,
+    /// The computations whose result is available, and the temporaries which
+    /// hold their result
+    available: HashMap<Rvalue, VarId::Id>,
+    /// The variables which hold a copy of a variable or a constant, with the
+    /// copied operand
+    copies: HashMap<VarId::Id, Operand>,
+    /// The temporaries we introduced
+    fresh_vars: HashSet<VarId::Id>,
+}
+
+/// Check if a rvalue is a computation we can share.
+fn is_candidate(rv: &Rvalue) -> bool {
+    let is_pure = |op: &Operand| matches!(op, Operand::Copy(_) | Operand::Const(..));
+    match rv {
+        Rvalue::UnaryOp(_, op) => is_pure(op),
+        Rvalue::BinaryOp(_, op1, op2) => is_pure(op1) && is_pure(op2),
+        _ => false,
+    }
+}
+
+/// The operands of a rvalue
+fn rvalue_operands(rv: &Rvalue) -> Vec<&Operand> {
+    match rv {
+        Rvalue::Use(op) | Rvalue::UnaryOp(_, op) => vec![op],
+        Rvalue::BinaryOp(_, op1, op2) => vec![op1, op2],
+        Rvalue::Aggregate(_, ops) => ops.iter().collect(),
+        Rvalue::Ref(..)
+        | Rvalue::RawPtr(..)
+        | Rvalue::Discriminant(_)
+        | Rvalue::Global(_)
+        | Rvalue::FnPtr(..)
+        | Rvalue::Len(..) => vec![],
+    }
+}
+
+/// Check if a rvalue reads a variable.
+fn reads_var(rv: &Rvalue, var_id: VarId::Id) -> bool {
+    rvalue_operands(rv).into_iter().any(|op| match op {
+        Operand::Copy(p) | Operand::Move(p) => p.var_id == var_id,
+        Operand::Const(..) => false,
+    })
+}
+
+/// Check if writing to a place may modify other variables than the one at
+/// the root of the place.
+fn writes_through_pointer(p: &Place) -> bool {
+    p.projection.iter().any(|pe| {
+        matches!(
+            pe,
+            ProjectionElem::Deref
+                | ProjectionElem::DerefBox
+                | ProjectionElem::DerefRawPtr
+                | ProjectionElem::DerefPtrUnique
+                | ProjectionElem::DerefPtrNonNull
+        )
+    })
+}
+
+impl<'a> EliminateCommonSubexprs<'a> {
+    /// Forget about the computations which involve a variable.
+    fn invalidate(&mut self, var_id: VarId::Id) {
+        self.available
+            .retain(|rv, tmp| *tmp != var_id && !reads_var(rv, var_id));
+        self.copies
+            .retain(|v, op| *v != var_id && !matches!(op, Operand::Copy(p) if p.var_id == var_id));
+    }
+
+    /// Forget about all the computations
+    fn clear(&mut self) {
+        self.available.clear();
+        self.copies.clear();
+    }
+
+    /// Replace the moves of the variables which hold a copy with the copied
+    /// operands.
+    fn normalize(&self, rv: &Rvalue) -> Rvalue {
+        let normalize_op = |op: &Operand| match op {
+            Operand::Move(p) if p.projection.is_empty() => match self.copies.get(&p.var_id) {
+                Some(copied) => copied.clone(),
+                None => op.clone(),
+            },
+            _ => op.clone(),
+        };
+        match rv {
+            Rvalue::UnaryOp(unop, op) => Rvalue::UnaryOp(unop.clone(), normalize_op(op)),
+            Rvalue::BinaryOp(binop, op1, op2) => {
+                Rvalue::BinaryOp(*binop, normalize_op(op1), normalize_op(op2))
+            }
+            _ => rv.clone(),
+        }
+    }
+
+    /// Forget about the computations which may be modified by a write to a
+    /// place.
+    fn invalidate_place(&mut self, p: &Place) {
+        if writes_through_pointer(p) {
+            self.clear();
+        } else {
+            self.invalidate(p.var_id);
+        }
+    }
+
+    /// Update the available computations before an assignment: the moved and
+    /// borrowed variables may be modified.
+    fn invalidate_rvalue(&mut self, rv: &Rvalue) {
+        if let Rvalue::Ref(p, _) | Rvalue::RawPtr(p, _) = rv {
+            self.invalidate(p.var_id);
+        }
+        for op in rvalue_operands(rv) {
+            if let Operand::Move(p) = op {
+                self.invalidate(p.var_id);
+            }
+        }
+    }
+
+    /// Visit the first statement of a sequence. If it is the first occurrence
+    /// of a computation, we return the statements to insert after it.
+    fn visit_statement_in_sequence(&mut self, st: &mut Statement) -> Option<Statement> {
+        if !st.content.is_assign() {
+            self.visit_statement(st);
+            return None;
+        }
+        let (p, rv) = match &mut st.content {
+            RawStatement::Assign(p, rv) => (p, rv),
+            _ => unreachable!(),
+        };
+        // We normalize before invalidating the moved temporaries
+        let key = self.normalize(rv);
+        self.invalidate_rvalue(rv);
+        self.invalidate_place(p);
+        if !p.projection.is_empty() {
+            return None;
+        }
+        if let Rvalue::Use(op @ (Operand::Copy(_) | Operand::Const(..))) = &key {
+            if !reads_var(&key, p.var_id) {
+                self.copies.insert(p.var_id, op.clone());
+            }
+            return None;
+        }
+        if !is_candidate(&key) {
+            return None;
+        }
+
+        if let Some(tmp) = self.available.get(&key) {
+            // Reuse the previous computation
+            *rv = Rvalue::Use(Operand::Copy(Place::new(*tmp)));
+            return None;
+        }
+
+        // If the computation reads the variable we assign, it is not
+        // available after the assignment
+        if reads_var(&key, p.var_id) {
+            return None;
+        }
+
+        // Save the result in a fresh temporary: `p := rv` becomes
+        // `tmp := rv; p := copy tmp`
+        let ty = (*self.locals.get(p.var_id).unwrap().ty).clone();
+        let tmp = self.locals.fresh_var(None, ty);
+        self.fresh_vars.insert(tmp);
+        self.available.insert(key, tmp);
+        let p = std::mem::replace(p, Place::new(tmp));
+        let copy = Rvalue::Use(Operand::Copy(Place::new(tmp)));
+        Some(Statement::new(st.meta, RawStatement::Assign(p, copy)))
+    }
+}
+
+impl<'a> MutTypeVisitor for EliminateCommonSubexprs<'a> {}
+impl<'a> MutExprVisitor for EliminateCommonSubexprs<'a> {}
+
+impl<'a> MutAstVisitor for EliminateCommonSubexprs<'a> {
+    /// We don't propagate the available computations to the branches
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        self.clear();
+        visitor(self);
+        self.clear();
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        use RawStatement::*;
+        match &mut st.content {
+            Sequence(s1, s2) => {
+                if let Some(next) = self.visit_statement_in_sequence(s1) {
+                    let rest = std::mem::replace(&mut **s2, Statement::new(next.meta, Nop));
+                    s2.content = Sequence(Box::new(next), Box::new(rest));
+                }
+                self.visit_statement(s2);
+            }
+            Assign(p, rv) => {
+                // The last statement of a sequence: there is nothing to share
+                // the computation with
+                self.invalidate_rvalue(rv);
+                self.invalidate_place(p);
+            }
+            StorageLive(var_id) | StorageDead(var_id) => self.invalidate(*var_id),
+            SetDiscriminant(p, _) | Drop(p) => self.invalidate_place(p),
+            FakeRead(_) | Assert(_) | TaggedAssert(..) | Nop => (),
+            // We don't know what the macro computed
+            MacroExpansion(_) => self.clear(),
+            Call(_) => self.clear(),
+            Panic
+            | Return
+            | Break(_)
+            | Continue(_)
+            | Switch(_)
+            | Loop(_)
+            | LetElse(..)
+            | IfLet(..)
+            | TryOp { .. } => {
+                self.clear();
+                self.default_visit_raw_statement(&mut st.content);
+                self.clear();
+            }
+        }
+    }
+}
+
+/// Undo the sharing of the computations which don't have duplicates:
+/// `tmp := rv; p := copy tmp` becomes `p := rv` if `tmp` is not used
+/// anywhere else.
+struct UnshareUnique {
+    fresh_vars: HashSet<VarId::Id>,
+    counts: im::HashMap<VarId::Id, usize>,
+}
+
+impl MutTypeVisitor for UnshareUnique {}
+impl MutExprVisitor for UnshareUnique {}
+
+impl MutAstVisitor for UnshareUnique {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        if let RawStatement::Sequence(s1, s2) = &mut st.content {
+            let next = match &mut s2.content {
+                RawStatement::Sequence(next, _) => next,
+                _ => &mut *s2,
+            };
+            if let (RawStatement::Assign(tmp, _), RawStatement::Assign(p, next_rv)) =
+                (&mut s1.content, &mut next.content)
+            {
+                let is_unique_copy = self.fresh_vars.contains(&tmp.var_id)
+                    && self.counts.get(&tmp.var_id) == Some(&2)
+                    && *next_rv == Rvalue::Use(Operand::Copy(tmp.clone()));
+                if is_unique_copy {
+                    *tmp = p.clone();
+                    next.content = RawStatement::Nop;
+                }
+            }
+        }
+        self.default_visit_raw_statement(&mut st.content)
+    }
+}
+
+/// Eliminate the common subexpressions in a body.
+pub(crate) fn eliminate_common_subexprs_in_body(b: &mut ExprBody) {
+    let mut visitor = EliminateCommonSubexprs {
+        locals: &mut b.locals,
+        available: HashMap::new(),
+        copies: HashMap::new(),
+        fresh_vars: HashSet::new(),
+    };
+    visitor.visit_statement(&mut b.body);
+    let fresh_vars = visitor.fresh_vars;
+
+    let mut visitor = UnshareUnique {
+        fresh_vars,
+        counts: ComputeUsedLocals::compute_in_statement(&b.body),
+    };
+    visitor.visit_statement(&mut b.body);
+    remove_nops(&mut b.body);
+}
+
+/// `fmt_ctx` is used for pretty-printing purposes.
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to eliminate the common subexpressions in decl: {name}:\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+
+        eliminate_common_subexprs_in_body(b);
+
+        trace!(
+            "# After common subexpression elimination in decl: {name}:\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llbc_ast_utils::chain_statements;
+    use crate::meta::*;
+    use crate::remove_unused_locals::remove_unused_locals_in_body;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn assign(dest: VarId::Id, rv: Rvalue) -> Statement {
+        Statement::new(dummy_meta(), RawStatement::Assign(Place::new(dest), rv))
+    }
+
+    /// Count the additions in a sequence of statements.
+    fn count_additions(st: &Statement) -> usize {
+        match &st.content {
+            RawStatement::Sequence(s1, s2) => count_additions(s1) + count_additions(s2),
+            RawStatement::Assign(_, Rvalue::BinaryOp(BinOp::Add, _, _)) => 1,
+            _ => 0,
+        }
+    }
+
+    /// ```text
+    /// fn f(a: u32, b: u32) -> u32 {
+    ///   x := copy a + copy b;
+    ///   y := copy a + copy b;
+    ///   @return := move x * move y;
+    ///   return;
+    /// }
+    /// ```
+    #[test]
+    fn test_shared_addition() {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut locals = VarId::Vector::new();
+        let ret = locals.fresh_var(None, u32_ty.clone());
+        let a = locals.fresh_var(Some("a".to_string()), u32_ty.clone());
+        let b = locals.fresh_var(Some("b".to_string()), u32_ty.clone());
+        let x = locals.fresh_var(Some("x".to_string()), u32_ty.clone());
+        let y = locals.fresh_var(Some("y".to_string()), u32_ty);
+
+        let add = || {
+            Rvalue::BinaryOp(
+                BinOp::Add,
+                Operand::Copy(Place::new(a)),
+                Operand::Copy(Place::new(b)),
+            )
+        };
+        let mul = Rvalue::BinaryOp(
+            BinOp::Mul,
+            Operand::Move(Place::new(x)),
+            Operand::Move(Place::new(y)),
+        );
+        let body = chain_statements(
+            vec![assign(x, add()), assign(y, add()), assign(ret, mul)],
+            Statement::new(dummy_meta(), RawStatement::Return),
+        );
+        let mut body = ExprBody {
+            meta: dummy_meta(),
+            arg_count: 2,
+            locals,
+            body,
+            liveness: None,
+        };
+        assert!(count_additions(&body.body) == 2);
+
+        eliminate_common_subexprs_in_body(&mut body);
+        remove_unused_locals_in_body(&mut body);
+        assert!(count_additions(&body.body) == 1);
+        // We introduced one temporary
+        assert!(body.locals.len() == 6);
+    }
+
+    /// The operands are copied to temporaries, which are then moved (this is
+    /// what rustc generates):
+    /// ```text
+    /// tmp1 := copy a; tmp2 := copy b; x := move tmp1 + move tmp2;
+    /// tmp3 := copy a; tmp4 := copy b; y := move tmp3 + move tmp4;
+    /// @return := move x * move y;
+    /// ```
+    #[test]
+    fn test_shared_addition_through_temporaries() {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut locals = VarId::Vector::new();
+        let ret = locals.fresh_var(None, u32_ty.clone());
+        let a = locals.fresh_var(Some("a".to_string()), u32_ty.clone());
+        let b = locals.fresh_var(Some("b".to_string()), u32_ty.clone());
+        let x = locals.fresh_var(Some("x".to_string()), u32_ty.clone());
+        let y = locals.fresh_var(Some("y".to_string()), u32_ty.clone());
+        let tmps: Vec<VarId::Id> = (0..4)
+            .map(|_| locals.fresh_var(None, u32_ty.clone()))
+            .collect();
+
+        let copy = |v: VarId::Id| Rvalue::Use(Operand::Copy(Place::new(v)));
+        let add = |t1: VarId::Id, t2: VarId::Id| {
+            Rvalue::BinaryOp(
+                BinOp::Add,
+                Operand::Move(Place::new(t1)),
+                Operand::Move(Place::new(t2)),
+            )
+        };
+        let mul = Rvalue::BinaryOp(
+            BinOp::Mul,
+            Operand::Move(Place::new(x)),
+            Operand::Move(Place::new(y)),
+        );
+        let body = chain_statements(
+            vec![
+                assign(tmps[0], copy(a)),
+                assign(tmps[1], copy(b)),
+                assign(x, add(tmps[0], tmps[1])),
+                assign(tmps[2], copy(a)),
+                assign(tmps[3], copy(b)),
+                assign(y, add(tmps[2], tmps[3])),
+                assign(ret, mul),
+            ],
+            Statement::new(dummy_meta(), RawStatement::Return),
+        );
+        let mut body = ExprBody {
+            meta: dummy_meta(),
+            arg_count: 2,
+            locals,
+            body,
+            liveness: None,
+        };
+        assert!(count_additions(&body.body) == 2);
+
+        eliminate_common_subexprs_in_body(&mut body);
+        assert!(count_additions(&body.body) == 1);
+    }
+
+    /// The operands are modified between the two additions: we can't share
+    /// them
+    #[test]
+    fn test_modified_operand() {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut locals = VarId::Vector::new();
+        let ret = locals.fresh_var(None, u32_ty.clone());
+        let a = locals.fresh_var(Some("a".to_string()), u32_ty.clone());
+        let x = locals.fresh_var(Some("x".to_string()), u32_ty);
+
+        let one = || {
+            Operand::Const(
+                Ty::Literal(LiteralTy::Integer(IntegerTy::U32)),
+                OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(1))),
+            )
+        };
+        let incr = || Rvalue::BinaryOp(BinOp::Add, Operand::Copy(Place::new(a)), one());
+        let body = chain_statements(
+            vec![
+                assign(x, incr()),
+                assign(a, Rvalue::Use(Operand::Move(Place::new(x)))),
+                assign(ret, incr()),
+            ],
+            Statement::new(dummy_meta(), RawStatement::Return),
+        );
+        let mut body = ExprBody {
+            meta: dummy_meta(),
+            arg_count: 1,
+            locals,
+            body,
+            liveness: None,
+        };
+
+        eliminate_common_subexprs_in_body(&mut body);
+        remove_unused_locals_in_body(&mut body);
+        assert!(count_additions(&body.body) == 2);
+        assert!(body.locals.len() == 3);
+    }
+}
@@ -0,0 +1,132 @@
+//! Record the rustc identifiers ([DefId]) of the translated declarations
+//! (`--emit-def-ids`).
+//!
+//! This allows the tools which also have access to rustc to correlate the
+//! Charon identifiers with the rustc identifiers. A [DefId] is a pair
+//! `(krate, index)`: the crate numbers are attributed by the rustc session, so
+//! the mapping is only meaningful in a session which compiles the same crate
+//! with the same dependencies.
+#![allow(dead_code)]
+
+use crate::gast::{FunDeclId, TraitDeclId};
+#[cfg(feature = "rustc")]
+use crate::translate_ctx::TransCtx;
+use crate::types::{GlobalDeclId, TypeDeclId};
+#[cfg(feature = "rustc")]
+use rustc_hir::def_id::{CrateNum, DefId, DefIndex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "rustc")]
+use std::collections::BTreeMap;
+
+/// The rustc identifiers of the translated declarations. Every entry is a
+/// triple `(id, krate, index)`, sorted by id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DefIdMapping {
+    pub fun_ids: Vec<(FunDeclId::Id, u64, u64)>,
+    pub type_ids: Vec<(TypeDeclId::Id, u64, u64)>,
+    pub global_ids: Vec<(GlobalDeclId::Id, u64, u64)>,
+    pub trait_ids: Vec<(TraitDeclId::Id, u64, u64)>,
+}
+
+/// Convert a [DefId] to the pair `(krate, index)` we serialize.
+#[cfg(feature = "rustc")]
+pub fn encode_def_id(def_id: DefId) -> (u64, u64) {
+    (def_id.krate.as_u32() as u64, def_id.index.as_u32() as u64)
+}
+
+/// Convert back a pair computed by [encode_def_id].
+#[cfg(feature = "rustc")]
+pub fn decode_def_id(krate: u64, index: u64) -> DefId {
+    DefId {
+        krate: CrateNum::from_u32(krate as u32),
+        index: DefIndex::from_u32(index as u32),
+    }
+}
+
+#[cfg(feature = "rustc")]
+fn encode_map<Id: Copy + Ord>(map: &BTreeMap<DefId, Id>) -> Vec<(Id, u64, u64)> {
+    let mut ids: Vec<(Id, u64, u64)> = map
+        .iter()
+        .map(|(def_id, id)| {
+            let (krate, index) = encode_def_id(*def_id);
+            (*id, krate, index)
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[cfg(feature = "rustc")]
+fn lookup<Id: Eq>(ids: &[(Id, u64, u64)], id: Id) -> Option<DefId> {
+    ids.iter()
+        .find(|(id1, _, _)| *id1 == id)
+        .map(|(_, krate, index)| decode_def_id(*krate, *index))
+}
+
+// Without the `rustc` feature, the mapping can still be (de)serialized, but
+// we can't convert its entries back to [DefId]s
+#[cfg(feature = "rustc")]
+impl DefIdMapping {
+    /// Compute the mapping from the identifier maps of the translation
+    /// context. Note that the promoted constants don't have a [DefId] of
+    /// their own: they don't appear in the mapping.
+    pub fn compute(ctx: &TransCtx) -> Self {
+        DefIdMapping {
+            fun_ids: encode_map(&ctx.fun_id_map.map),
+            type_ids: encode_map(&ctx.type_id_map.map),
+            global_ids: encode_map(&ctx.global_id_map.map),
+            trait_ids: encode_map(&ctx.trait_id_map.map),
+        }
+    }
+
+    pub fn fun_def_id(&self, id: FunDeclId::Id) -> Option<DefId> {
+        lookup(&self.fun_ids, id)
+    }
+
+    pub fn type_def_id(&self, id: TypeDeclId::Id) -> Option<DefId> {
+        lookup(&self.type_ids, id)
+    }
+
+    pub fn global_def_id(&self, id: GlobalDeclId::Id) -> Option<DefId> {
+        lookup(&self.global_ids, id)
+    }
+
+    pub fn trait_def_id(&self, id: TraitDeclId::Id) -> Option<DefId> {
+        lookup(&self.trait_ids, id)
+    }
+}
+
+#[cfg(all(test, feature = "rustc"))]
+mod tests {
+    use super::*;
+
+    fn def_id(krate: u32, index: u32) -> DefId {
+        DefId {
+            krate: CrateNum::from_u32(krate),
+            index: DefIndex::from_u32(index),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut map = BTreeMap::new();
+        map.insert(def_id(0, 12), FunDeclId::Id::new(1));
+        map.insert(def_id(3, 7), FunDeclId::Id::new(0));
+        let mapping = DefIdMapping {
+            fun_ids: encode_map(&map),
+            ..DefIdMapping::default()
+        };
+        // The entries are sorted by id
+        assert!(
+            mapping.fun_ids
+                == vec![
+                    (FunDeclId::Id::new(0), 3, 7),
+                    (FunDeclId::Id::new(1), 0, 12)
+                ]
+        );
+        assert!(mapping.fun_def_id(FunDeclId::Id::new(1)) == Some(def_id(0, 12)));
+        assert!(mapping.fun_def_id(FunDeclId::Id::new(2)).is_none());
+        assert!(mapping.type_def_id(TypeDeclId::ZERO).is_none());
+    }
+}
@@ -0,0 +1,392 @@
+//! Read back the files generated by Charon (see [crate::export]).
+//!
+//! This allows writing analyses which operate on the (U)LLBC files without
+//! depending on the Rust compiler. Note that the files store the maps (the
+//! declarations, the files) as sequences: we rebuild the maps from the ids
+//! stored in the declarations.
+#![allow(dead_code)]
+
+use crate::def_ids::DefIdMapping;
+use crate::export::{serialize_crate, ExportedCrate, OutputFormat, SerializeError};
+use crate::gast::{GFunDecl, GGlobalDecl, ModuleDecl, ModuleDecls, TraitDecl, TraitDecls};
+use crate::llbc_ast;
+use crate::meta::{FileId, FileName};
+use crate::reorder_decls::DeclarationsGroups;
+use crate::types::{TypeDecl, TypeDecls};
+use crate::ullbc_ast;
+use crate::ullbc_ast::{FunDeclId, GlobalDeclId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+
+/// The content of a generated file. This mirrors the crate serializer of
+/// [crate::export], with the vectors of declarations.
+#[derive(Deserialize)]
+#[serde(rename = "Crate")]
+struct GCrateDeserializer<T: Debug + Clone + Serialize> {
+    name: String,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    id_to_file: Vec<(FileId::Id, FileName)>,
+    declarations: DeclarationsGroups,
+    types: Vec<TypeDecl>,
+    functions: Vec<GFunDecl<T>>,
+    globals: Vec<GGlobalDecl<T>>,
+    traits: Vec<TraitDecl>,
+    modules: Vec<ModuleDecl>,
+    #[serde(default)]
+    def_ids: Option<DefIdMapping>,
+}
+
+/// A translated crate, read from a generated file.
+/// "G" stands for "generic": the crate is parameterized by the kind of
+/// function bodies (ULLBC or LLBC).
+pub struct GTranslatedCrate<T: Debug + Clone + Serialize> {
+    pub name: String,
+    /// The files this file depends on, if the output was split per module
+    /// (see [crate::export::gexport]).
+    pub dependencies: Vec<String>,
+    pub file_to_id: HashMap<FileName, FileId::Id>,
+    pub id_to_file: HashMap<FileId::Id, FileName>,
+    pub ordered_decls: DeclarationsGroups,
+    pub type_defs: TypeDecls,
+    pub fun_defs: FunDeclId::Map<GFunDecl<T>>,
+    pub global_defs: GlobalDeclId::Map<GGlobalDecl<T>>,
+    pub trait_defs: TraitDecls,
+    pub module_defs: ModuleDecls,
+    /// The rustc identifiers of the declarations, if the file contains them
+    /// (see [crate::def_ids]).
+    pub def_ids: Option<DefIdMapping>,
+}
+
+/// A crate read from a `.llbc` file
+pub type TranslatedCrate = GTranslatedCrate<llbc_ast::Statement>;
+
+/// A crate read from a `.ullbc` file
+pub type UllbcTranslatedCrate = GTranslatedCrate<ullbc_ast::BlockId::Vector<ullbc_ast::BlockData>>;
+
+#[derive(Debug)]
+pub enum DeserializeError {
+    /// We couldn't read the file
+    Io(std::io::Error),
+    /// The file content is not a valid crate
+    Format(String),
+}
+
+impl std::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            DeserializeError::Io(e) => write!(f, "could not read the file: {e}"),
+            DeserializeError::Format(e) => write!(f, "invalid file: {e}"),
+        }
+    }
+}
+
+impl<T: Debug + Clone + Serialize> From<GCrateDeserializer<T>> for GTranslatedCrate<T> {
+    fn from(c: GCrateDeserializer<T>) -> Self {
+        let file_to_id = c
+            .id_to_file
+            .iter()
+            .map(|(id, f)| (f.clone(), *id))
+            .collect();
+        GTranslatedCrate {
+            name: c.name,
+            dependencies: c.dependencies,
+            file_to_id,
+            id_to_file: c.id_to_file.into_iter().collect(),
+            ordered_decls: c.declarations,
+            type_defs: c.types.into_iter().map(|d| (d.def_id, d)).collect(),
+            fun_defs: c.functions.into_iter().map(|d| (d.def_id, d)).collect(),
+            global_defs: c.globals.into_iter().map(|d| (d.def_id, d)).collect(),
+            trait_defs: c.traits.into_iter().map(|d| (d.def_id, d)).collect(),
+            module_defs: c.modules.into_iter().map(|d| (d.def_id, d)).collect(),
+            def_ids: c.def_ids,
+        }
+    }
+}
+
+impl<T: Debug + Clone + Serialize> GTranslatedCrate<T> {
+    /// Serialize the crate to a file. Serializing a crate we just read gives
+    /// back the original file.
+    pub fn serialize_to_file(
+        &self,
+        target_filename: &Path,
+        format: OutputFormat,
+    ) -> std::result::Result<(), SerializeError> {
+        let krate = ExportedCrate {
+            crate_name: self.name.clone(),
+            dependencies: self.dependencies.clone(),
+            id_to_file: &self.id_to_file,
+            ordered_decls: &self.ordered_decls,
+            type_defs: &self.type_defs,
+            fun_defs: &self.fun_defs,
+            global_defs: &self.global_defs,
+            trait_defs: &self.trait_defs,
+            module_defs: &self.module_defs,
+            def_ids: self.def_ids.as_ref(),
+        };
+        serialize_crate(&krate, target_filename, format)
+    }
+}
+
+/// The stack size of the thread which deserializes the files (see
+/// [gdeserialize_crate])
+const DESERIALIZER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn read_crate<T>(path: &Path) -> std::result::Result<GCrateDeserializer<T>, DeserializeError>
+where
+    T: Debug + Clone + Serialize + for<'de> Deserialize<'de>,
+{
+    let file = std::fs::File::open(path).map_err(DeserializeError::Io)?;
+    let reader = std::io::BufReader::new(file);
+    let is_cbor = path.extension().map_or(false, |ext| ext == "cbor");
+    if is_cbor {
+        ciborium::de::from_reader_with_recursion_limit(reader, usize::MAX)
+            .map_err(|e| DeserializeError::Format(e.to_string()))
+    } else {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        deserializer.disable_recursion_limit();
+        GCrateDeserializer::deserialize(&mut deserializer)
+            .map_err(|e| DeserializeError::Format(e.to_string()))
+    }
+}
+
+/// Read a crate from a generated file. We use the extension of the file to
+/// know its format: the files whose extension ends with `.cbor` are CBOR
+/// files, the other files are JSON files.
+///
+/// This is a generic function, used both for LLBC and ULLBC.
+pub fn gdeserialize_crate<T>(
+    path: &Path,
+) -> std::result::Result<GTranslatedCrate<T>, DeserializeError>
+where
+    T: Debug + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    // The statements of the LLBC bodies are nested (see
+    // [llbc_ast::RawStatement::Sequence]): the depth of a file grows with the
+    // size of the bodies. We can't use the recursion limits of the
+    // deserializers, and we need a large stack, hence the dedicated thread.
+    let path = path.to_path_buf();
+    let c = std::thread::Builder::new()
+        .stack_size(DESERIALIZER_STACK_SIZE)
+        .spawn(move || read_crate(&path))
+        .map_err(DeserializeError::Io)?
+        .join()
+        .unwrap_or_else(|e| std::panic::resume_unwind(e))?;
+    Ok(GTranslatedCrate::from(c))
+}
+
+/// Read a crate from a `.llbc` file (or a `.llbc.cbor` file).
+pub fn deserialize_crate(path: &Path) -> std::result::Result<TranslatedCrate, DeserializeError> {
+    gdeserialize_crate(path)
+}
+
+/// Read a crate from a `.ullbc` file (or a `.ullbc.cbor` file).
+pub fn deserialize_ullbc_crate(
+    path: &Path,
+) -> std::result::Result<UllbcTranslatedCrate, DeserializeError> {
+    gdeserialize_crate(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::gast::*;
+    use crate::llbc_ast::{RawStatement, Statement};
+    use crate::meta::*;
+    use crate::names::{Name, PathElem};
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::reorder_decls::{DeclarationGroup, GDeclarationGroup};
+    use crate::types::*;
+    use crate::values::*;
+    use std::path::PathBuf;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn name(elems: &[&str]) -> Name {
+        Name {
+            name: elems
+                .iter()
+                .map(|s| PathElem::Ident(s.to_string()))
+                .collect(),
+        }
+    }
+
+    /// A crate with a struct and a global.
+    fn test_crate() -> TranslatedCrate {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut crate_ = TranslatedCrate {
+            name: "test".to_string(),
+            dependencies: vec![],
+            file_to_id: HashMap::new(),
+            id_to_file: HashMap::new(),
+            ordered_decls: vec![
+                DeclarationGroup::Type(GDeclarationGroup::NonRec(TypeDeclId::ZERO)),
+                DeclarationGroup::Global(GDeclarationGroup::NonRec(GlobalDeclId::ZERO)),
+            ],
+            type_defs: TypeDecls::new(),
+            fun_defs: FunDeclId::Map::new(),
+            global_defs: GlobalDeclId::Map::new(),
+            trait_defs: TraitDecls::new(),
+            module_defs: ModuleDecls::new(),
+            def_ids: None,
+        };
+        let file_id = FileId::Id::LocalId(LocalFileId::ZERO);
+        let file = FileName::Local(PathBuf::from("src/lib.rs"));
+        crate_.id_to_file.insert(file_id, file.clone());
+        crate_.file_to_id.insert(file, file_id);
+
+        let fields = (0..2)
+            .map(|i| Field {
+                meta: dummy_meta(),
+                name: Some(format!("x{i}")),
+                ty: Ty::Literal(LiteralTy::Integer(IntegerTy::Isize)),
+                interior_mut: false,
+            })
+            .collect();
+        let point = TypeDecl {
+            def_id: TypeDeclId::ZERO,
+            meta: dummy_meta(),
+            name: name(&["test", "Point"]),
+            region_params: RegionVarId::Vector::new(),
+            type_params: TypeVarId::Vector::new(),
+            const_generic_params: ConstGenericVarId::Vector::new(),
+            kind: TypeDeclKind::Struct(fields),
+            repr: ReprConfig::rust(),
+            is_root: true,
+            stable_id: None,
+            local_methods: Vec::new(),
+            variance: vec![],
+            is_recursive: false,
+            regions_hierarchy: RegionGroups::new(),
+        };
+        crate_.type_defs.insert(point.def_id, point);
+
+        // ret := 3 : u32; nop; ...; nop; return
+        // The sequences nest: with the nops, the file is deeper than the
+        // default recursion limits of the deserializers.
+        let mut locals = VarId::Vector::new();
+        locals.fresh_var(None, u32_ty.clone());
+        let three = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(3)));
+        let assign = RawStatement::Assign(
+            Place::new(VarId::ZERO),
+            Rvalue::Use(Operand::Const(u32_ty.clone(), three)),
+        );
+        let mut statements = vec![assign];
+        statements.extend(std::iter::repeat(RawStatement::Nop).take(300));
+        let body = statements
+            .into_iter()
+            .rev()
+            .fold(RawStatement::Return, |next, st| {
+                RawStatement::Sequence(
+                    Box::new(Statement::new(dummy_meta(), st)),
+                    Box::new(Statement::new(dummy_meta(), next)),
+                )
+            });
+        let global = GGlobalDecl {
+            def_id: GlobalDeclId::ZERO,
+            meta: dummy_meta(),
+            name: name(&["test", "THREE"]),
+            ty: u32_ty,
+            body: Some(GExprBody {
+                meta: dummy_meta(),
+                arg_count: 0,
+                locals,
+                body: Statement::new(dummy_meta(), body),
+                liveness: None,
+            }),
+            trait_id: None,
+            is_root: true,
+            stable_id: None,
+        };
+        crate_.global_defs.insert(global.def_id, global);
+
+        let root = ModuleDecl {
+            def_id: ROOT_MODULE_ID,
+            name: name(&["test"]),
+            submodules: vec![],
+            types: vec![TypeDeclId::ZERO],
+            funs: vec![],
+            globals: vec![GlobalDeclId::ZERO],
+        };
+        crate_.module_defs.insert(root.def_id, root);
+        crate_
+    }
+
+    /// Check that serializing a crate, reading it back and serializing it
+    /// again gives the same file.
+    fn check_round_trip(extension: &str, format: OutputFormat) {
+        let dir = std::env::temp_dir();
+        let pid = std::process::id();
+        let file0 = dir.join(format!("charon_round_trip_{pid}_0.{extension}"));
+        let file1 = dir.join(format!("charon_round_trip_{pid}_1.{extension}"));
+
+        test_crate().serialize_to_file(&file0, format).unwrap();
+        let crate_ = deserialize_crate(&file0).unwrap();
+        assert!(crate_.name == "test");
+        assert!(crate_.type_defs.len() == 1 && crate_.global_defs.len() == 1);
+        assert!(crate_.file_to_id.len() == 1);
+        crate_.serialize_to_file(&file1, format).unwrap();
+
+        let content0 = std::fs::read(&file0).unwrap();
+        let content1 = std::fs::read(&file1).unwrap();
+        let _ = std::fs::remove_file(&file0);
+        let _ = std::fs::remove_file(&file1);
+        assert!(content0 == content1);
+    }
+
+    #[test]
+    fn test_round_trip_json() {
+        check_round_trip("llbc", OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_round_trip_cbor() {
+        check_round_trip("llbc.cbor", OutputFormat::Cbor);
+    }
+
+    /// The const generic variables can't be eliminated from the operands
+    /// (`x + N`): we serialize them next to the literals
+    #[test]
+    fn test_constant_var() {
+        let three = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(3)));
+        let var = OperandConstantValue::Var(ConstGenericVarId::ZERO);
+        for cv in [three, var] {
+            let json = serde_json::to_string(&cv).unwrap();
+            let cv1: OperandConstantValue = serde_json::from_str(&json).unwrap();
+            assert!(cv1 == cv);
+        }
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let path = std::env::temp_dir().join(format!("charon_invalid_{}.llbc", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+        let res = deserialize_crate(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(res, Err(DeserializeError::Format(_))));
+    }
+
+    /// We report the errors which happen while writing a file
+    #[test]
+    fn test_serialize_error() {
+        let dir = std::env::temp_dir().join(format!("charon_missing_{}", std::process::id()));
+        let path = dir.join("test.llbc");
+        let res = test_crate().serialize_to_file(&path, OutputFormat::Json);
+        assert!(matches!(res, Err(SerializeError::Io(p, _)) if p == path));
+    }
+}
@@ -0,0 +1,104 @@
+//! Detect the fields which (transitively) contain an `UnsafeCell`, and set
+//! their [Field::interior_mut] flag.
+//!
+//! The content of an `UnsafeCell` can be mutated through a shared reference:
+//! the tools which model the aliasing need to know where the interior
+//! mutability is. We consider that a type contains an `UnsafeCell` if:
+//! - it is an `UnsafeCell`, or one of the external types built on top of it
+//!   (`Cell`, `RefCell`, etc. - the external types are opaque, so we use a
+//!   list of names, see [crate::assumed::CELL_NAME])
+//! - it is a type declaration with a field which contains an `UnsafeCell`
+//! - one of its type arguments contains an `UnsafeCell` (`Option<Cell<u32>>`).
+//!   This is an over-approximation for the type declarations whose fields
+//!   don't own their type parameters.
+//!
+//! We ignore the `UnsafeCell` which are behind a reference or a pointer: it
+//! is the referenced value which has interior mutability.
+
+use crate::assumed;
+use crate::types::*;
+use std::collections::HashSet;
+
+/// Check if a type contains an `UnsafeCell`, given the set of type declarations
+/// which contain one.
+fn ty_has_interior_mut<R: Clone + Eq>(decls: &HashSet<TypeDeclId::Id>, ty: &Ty<R>) -> bool {
+    match ty {
+        Ty::Adt(TypeId::Assumed(AssumedTy::UnsafeCell), ..) => true,
+        Ty::Adt(id, _, tys, _) => {
+            let decl_has_interior_mut = match id {
+                TypeId::Adt(id) => decls.contains(id),
+                TypeId::Tuple | TypeId::Assumed(_) => false,
+            };
+            decl_has_interior_mut || tys.iter().any(|ty| ty_has_interior_mut(decls, ty))
+        }
+        Ty::TypeVar(_)
+        | Ty::Literal(_)
+        | Ty::Never
+        | Ty::Ref(..)
+        | Ty::RawPtr(..)
+        | Ty::TraitObject(..)
+        | Ty::FnPtr(..)
+        | Ty::TraitAssoc(..) => false,
+    }
+}
+
+/// Compute the set of type declarations which contain an `UnsafeCell`.
+///
+/// The types can be recursive: we iterate until we reach a fixed point.
+pub fn compute_interior_mut_decls(type_defs: &TypeDecls) -> HashSet<TypeDeclId::Id> {
+    let mut decls: HashSet<TypeDeclId::Id> = type_defs
+        .iter()
+        .filter(|d| {
+            d.name.equals_ref_name(&assumed::CELL_NAME)
+                || d.name.equals_ref_name(&assumed::REF_CELL_NAME)
+                || d.name.equals_ref_name(&assumed::ONCE_CELL_NAME)
+        })
+        .map(|d| d.def_id)
+        .collect();
+
+    let mut updated = true;
+    while updated {
+        updated = false;
+        for d in type_defs.iter() {
+            if decls.contains(&d.def_id) {
+                continue;
+            }
+            let has_interior_mut = match &d.kind {
+                TypeDeclKind::Struct(fields) | TypeDeclKind::Union(fields) => {
+                    fields.iter().any(|f| ty_has_interior_mut(&decls, &f.ty))
+                }
+                TypeDeclKind::Enum(variants) => variants
+                    .iter()
+                    .any(|v| v.fields.iter().any(|f| ty_has_interior_mut(&decls, &f.ty))),
+                TypeDeclKind::Alias(ty) => ty_has_interior_mut(&decls, ty),
+                TypeDeclKind::ImplTrait(_) | TypeDeclKind::Opaque => false,
+            };
+            if has_interior_mut {
+                decls.insert(d.def_id);
+                updated = true;
+            }
+        }
+    }
+    decls
+}
+
+pub fn transform(type_defs: &mut TypeDecls) {
+    let decls = compute_interior_mut_decls(type_defs);
+    for d in type_defs.iter_mut() {
+        let fields: Vec<&mut Field> = match &mut d.kind {
+            TypeDeclKind::Struct(fields) | TypeDeclKind::Union(fields) => {
+                fields.iter_mut().collect()
+            }
+            TypeDeclKind::Enum(variants) => variants
+                .iter_mut()
+                .flat_map(|v| v.fields.iter_mut())
+                .collect(),
+            TypeDeclKind::ImplTrait(_) | TypeDeclKind::Alias(_) | TypeDeclKind::Opaque => {
+                Vec::new()
+            }
+        };
+        for f in fields {
+            f.interior_mut = ty_has_interior_mut(&decls, &f.ty);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Detect the loops of the ULLBC bodies, and set the [BlockData::is_loop_header]
+//! flag of their headers.
+//!
+//! We compute the dominator tree of the control-flow graph: a block `d`
+//! dominates a block `b` if all the paths from the entry block to `b` go through
+//! `d`. An edge from a block to one of its dominators is a back-edge, and its
+//! target is a loop header. The blocks of a (natural) loop are the blocks from
+//! which we can reach the source of a back-edge without going through the
+//! header.
+//!
+//! The MIR generated by rustc is reducible (all the cycles of its CFG have a
+//! header which dominates them), which makes the loop headers coincide with the
+//! targets of the backward edges we find in [crate::ullbc_to_llbc].
+
+#![allow(dead_code)]
+
+use crate::cfg_analysis::{compute_dominators, DomTree};
+use crate::ullbc_ast::*;
+use crate::ullbc_to_llbc::get_block_targets;
+use petgraph::graphmap::DiGraphMap;
+use petgraph::Direction;
+use std::collections::HashSet;
+
+/// Control-Flow Graph
+pub type Cfg = DiGraphMap<BlockId::Id, ()>;
+
+/// Build the control-flow graph of a body.
+pub fn build_cfg(body: &ExprBody) -> Cfg {
+    let mut cfg = Cfg::new();
+    for block_id in body.body.iter_indices() {
+        cfg.add_node(block_id);
+    }
+    for block_id in body.body.iter_indices() {
+        for target in get_block_targets(body, block_id) {
+            cfg.add_edge(block_id, target, ());
+        }
+    }
+    cfg
+}
+
+/// Return `true` if `d` dominates `b`. The unreachable blocks are not dominated
+/// by any block.
+fn dominates(doms: &DomTree, d: BlockId::Id, b: BlockId::Id) -> bool {
+    match doms.dominators(b) {
+        Option::None => false,
+        Option::Some(mut dominators) => dominators.any(|id| id == d),
+    }
+}
+
+/// Compute the back-edges of a control-flow graph, that is the edges which go
+/// from a block to one of its dominators.
+pub fn back_edges(cfg: &Cfg) -> HashSet<(BlockId::Id, BlockId::Id)> {
+    let doms = compute_dominators(cfg);
+    cfg.all_edges()
+        .filter(|(src, tgt, _)| dominates(&doms, *tgt, *src))
+        .map(|(src, tgt, _)| (src, tgt))
+        .collect()
+}
+
+/// Compute the loop headers of a control-flow graph.
+pub fn loop_headers(cfg: &Cfg) -> HashSet<BlockId::Id> {
+    back_edges(cfg).into_iter().map(|(_, tgt)| tgt).collect()
+}
+
+/// Compute the blocks of the natural loop whose header is `header`: the header,
+/// together with the blocks which can reach a back-edge to the header without
+/// going through it.
+pub fn natural_loop_body(header: BlockId::Id, cfg: &Cfg) -> HashSet<BlockId::Id> {
+    let doms = compute_dominators(cfg);
+    let mut body = HashSet::new();
+    body.insert(header);
+
+    // Explore the graph backward, starting from the sources of the back-edges
+    let mut stack: Vec<BlockId::Id> = cfg
+        .neighbors_directed(header, Direction::Incoming)
+        .filter(|src| dominates(&doms, header, *src))
+        .collect();
+    while let Some(block_id) = stack.pop() {
+        if body.insert(block_id) {
+            stack.extend(cfg.neighbors_directed(block_id, Direction::Incoming));
+        }
+    }
+    body
+}
+
+/// Set the [BlockData::is_loop_header] flag of the blocks of a body.
+pub fn annotate_body(body: &mut ExprBody) {
+    let headers = loop_headers(&build_cfg(body));
+    let block_ids: Vec<BlockId::Id> = body.body.iter_indices().collect();
+    for block_id in block_ids {
+        let block = body.body.get_mut(block_id).unwrap();
+        block.is_loop_header = headers.contains(&block_id);
+    }
+}
+
+pub fn transform(funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (_, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        annotate_body(b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mk_cfg(edges: &[(usize, usize)]) -> Cfg {
+        Cfg::from_edges(
+            edges
+                .iter()
+                .map(|(src, tgt)| (BlockId::Id::new(*src), BlockId::Id::new(*tgt))),
+        )
+    }
+
+    fn ids(ids: &[usize]) -> HashSet<BlockId::Id> {
+        ids.iter().map(|id| BlockId::Id::new(*id)).collect()
+    }
+
+    fn edges(edges: &[(usize, usize)]) -> HashSet<(BlockId::Id, BlockId::Id)> {
+        edges
+            .iter()
+            .map(|(src, tgt)| (BlockId::Id::new(*src), BlockId::Id::new(*tgt)))
+            .collect()
+    }
+
+    /// ```text
+    /// bb0: goto bb1
+    /// bb1: switch [bb2, bb5]   // outer loop header
+    /// bb2: switch [bb3, bb4]   // inner loop header
+    /// bb3: goto bb2
+    /// bb4: goto bb1
+    /// bb5: return
+    /// ```
+    #[test]
+    fn test_nested_loops() {
+        let cfg = mk_cfg(&[(0, 1), (1, 2), (1, 5), (2, 3), (2, 4), (3, 2), (4, 1)]);
+        assert!(loop_headers(&cfg) == ids(&[1, 2]));
+        assert!(back_edges(&cfg) == edges(&[(3, 2), (4, 1)]));
+        assert!(natural_loop_body(BlockId::Id::new(1), &cfg) == ids(&[1, 2, 3, 4]));
+        assert!(natural_loop_body(BlockId::Id::new(2), &cfg) == ids(&[2, 3]));
+    }
+
+    /// A diamond doesn't contain loops.
+    #[test]
+    fn test_no_loop() {
+        let cfg = mk_cfg(&[(0, 1), (0, 2), (1, 3), (2, 3)]);
+        assert!(loop_headers(&cfg).is_empty());
+    }
+}
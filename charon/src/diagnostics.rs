@@ -0,0 +1,112 @@
+//! The (non-fatal) diagnostics we emit during the translation.
+//!
+//! Contrary to the [crate::translate_ctx::TranslationError], the diagnostics
+//! don't prevent us from translating a declaration: they signal that we
+//! translated some code in a way the user should be aware of (for instance,
+//! because we couldn't evaluate a constant).
+
+#![allow(dead_code)]
+
+use crate::meta::Meta;
+use rustc_session::Session;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiagLevel {
+    Warning,
+    Note,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: DiagLevel,
+    pub message: String,
+    pub span: Option<Meta>,
+    /// An additional explanation, printed after the message
+    pub note: Option<String>,
+    /// The original span, that we use to print the diagnostic with the
+    /// formatting of the compiler (see [PrintingSink])
+    pub rust_span: Option<rustc_span::Span>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.level {
+            DiagLevel::Warning => "warning",
+            DiagLevel::Note => "note",
+        };
+        write!(f, "{level}: {}", self.message)?;
+        if let Some(note) = &self.note {
+            write!(f, "\n  = note: {note}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the diagnostics go.
+pub trait DiagSink {
+    fn emit(&mut self, d: Diagnostic);
+}
+
+/// Collect the diagnostics in a vector.
+#[derive(Default)]
+pub struct CollectingSink {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagSink for CollectingSink {
+    fn emit(&mut self, d: Diagnostic) {
+        self.diagnostics.push(d)
+    }
+}
+
+/// Print the diagnostics on the standard error with the compiler's
+/// formatting, as soon as we emit them.
+pub struct PrintingSink<'ctx> {
+    sess: &'ctx Session,
+}
+
+impl<'ctx> PrintingSink<'ctx> {
+    pub fn new(sess: &'ctx Session) -> Self {
+        PrintingSink { sess }
+    }
+}
+
+impl<'ctx> DiagSink for PrintingSink<'ctx> {
+    fn emit(&mut self, d: Diagnostic) {
+        trace!("{}", d);
+        let mut diag = match d.level {
+            DiagLevel::Warning => self.sess.struct_warn(d.message),
+            DiagLevel::Note => self.sess.struct_note_without_error(d.message),
+        };
+        if let Some(span) = d.rust_span {
+            diag.set_span(span);
+        }
+        if let Some(note) = d.note {
+            diag.note(note);
+        }
+        diag.emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collecting_sink() {
+        let mut sink = CollectingSink::default();
+        let sink_ref: &mut dyn DiagSink = &mut sink;
+        sink_ref.emit(Diagnostic {
+            level: DiagLevel::Warning,
+            message: "could not evaluate the constant".to_string(),
+            span: None,
+            note: Some("the global is translated as opaque".to_string()),
+            rust_span: None,
+        });
+        assert_eq!(sink.diagnostics.len(), 1);
+        assert_eq!(
+            sink.diagnostics[0].to_string(),
+            "warning: could not evaluate the constant\n  = note: the global is translated as opaque"
+        );
+    }
+}
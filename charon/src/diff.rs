@@ -0,0 +1,310 @@
+//! Compare two translations of a crate.
+//!
+//! This is useful to check which declarations changed after modifying the
+//! source code (for instance, to check that refactoring a crate doesn't alter
+//! the semantics of its functions as seen by Charon). We match the
+//! declarations by name, because the identifiers depend on the order in which
+//! we translate the declarations.
+
+#![allow(dead_code)]
+
+use crate::formatter::Formatter;
+use crate::llbc_ast::{FunDecls, GlobalDecls, GlobalDeclsFormatter};
+use crate::names::Name;
+use crate::types::{TypeDeclFormatter, TypeDecls};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The LLBC declarations of a translated crate.
+pub struct TranslatedCrate<'a> {
+    pub type_defs: &'a TypeDecls,
+    pub fun_defs: &'a FunDecls,
+    pub global_defs: &'a GlobalDecls,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize)]
+pub enum DeclKind {
+    Type,
+    Fun,
+    Global,
+}
+
+/// A line of a text diff.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub enum LineDiff {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// A declaration which exists in both crates, but whose pretty-printed
+/// representation changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclDiff {
+    pub kind: DeclKind,
+    pub name: Name,
+    pub diff: Vec<LineDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrateDiff {
+    pub added: Vec<(DeclKind, Name)>,
+    pub removed: Vec<(DeclKind, Name)>,
+    pub changed: Vec<DeclDiff>,
+}
+
+impl CrateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl<'a> TranslatedCrate<'a> {
+    /// Pretty-print the declarations
+    fn fmt_decls(&self) -> Vec<(DeclKind, Name, String)> {
+        let global_ctx = GlobalDeclsFormatter::new(self.global_defs);
+        let types = self.type_defs.iter().map(|d| {
+            let ctx = TypeDeclFormatter {
+                type_defs: self.type_defs,
+                global_defs: &global_ctx,
+                region_params: &d.region_params,
+                type_params: &d.type_params,
+                const_generic_params: &d.const_generic_params,
+                // A change of variance may break the code which uses the type
+                show_variance: true,
+            };
+            (DeclKind::Type, d.name.clone(), ctx.format_object(d))
+        });
+        let funs = self.fun_defs.iter().map(|d| {
+            let s = d.fmt_with_decls(self.type_defs, self.fun_defs, self.global_defs);
+            (DeclKind::Fun, d.name.clone(), s)
+        });
+        let globals = self.global_defs.iter().map(|d| {
+            let s = d.fmt_with_decls(self.type_defs, self.fun_defs, self.global_defs);
+            (DeclKind::Global, d.name.clone(), s)
+        });
+        types.chain(funs).chain(globals).collect()
+    }
+}
+
+/// Compute a line by line diff of two texts (we compute a longest common
+/// subsequence of the lines).
+pub fn diff_lines(old: &str, new: &str) -> Vec<LineDiff> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // `lcs[i][j]` is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(LineDiff::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            // We list the removed lines before the added lines
+            diff.push(LineDiff::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(LineDiff::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Compare the declarations of two translations of a crate.
+pub fn diff(old: &TranslatedCrate<'_>, new: &TranslatedCrate<'_>) -> CrateDiff {
+    let old_decls = old.fmt_decls();
+    let new_decls = new.fmt_decls();
+    let old_map: HashMap<(DeclKind, &Name), &String> =
+        old_decls.iter().map(|(k, n, s)| ((*k, n), s)).collect();
+    let new_map: HashMap<(DeclKind, &Name), &String> =
+        new_decls.iter().map(|(k, n, s)| ((*k, n), s)).collect();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (kind, name, new_s) in &new_decls {
+        match old_map.get(&(*kind, name)) {
+            None => added.push((*kind, name.clone())),
+            Some(old_s) if *old_s != new_s => changed.push(DeclDiff {
+                kind: *kind,
+                name: name.clone(),
+                diff: diff_lines(old_s, new_s),
+            }),
+            Some(_) => (),
+        }
+    }
+    let removed = old_decls
+        .iter()
+        .filter(|(kind, name, _)| !new_map.contains_key(&(*kind, name)))
+        .map(|(kind, name, _)| (*kind, name.clone()))
+        .collect();
+
+    CrateDiff {
+        added,
+        removed,
+        changed,
+    }
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+impl std::fmt::Display for DeclKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            DeclKind::Type => write!(f, "type"),
+            DeclKind::Fun => write!(f, "fn"),
+            DeclKind::Global => write!(f, "global"),
+        }
+    }
+}
+
+/// Print the diff with ANSI color codes (for the terminal)
+impl std::fmt::Display for CrateDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        for (kind, name) in &self.added {
+            writeln!(f, "{GREEN}+ {kind} {name}{RESET}")?;
+        }
+        for (kind, name) in &self.removed {
+            writeln!(f, "{RED}- {kind} {name}{RESET}")?;
+        }
+        for d in &self.changed {
+            writeln!(f, "~ {} {}", d.kind, d.name)?;
+            for line in &d.diff {
+                match line {
+                    LineDiff::Same(l) => writeln!(f, "  {l}")?,
+                    LineDiff::Added(l) => writeln!(f, "{GREEN}+ {l}{RESET}")?,
+                    LineDiff::Removed(l) => writeln!(f, "{RED}- {l}{RESET}")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id_map::Map;
+    use crate::meta::*;
+    use crate::names::*;
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::types::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc", "a\nx\nc\nd");
+        assert_eq!(
+            diff,
+            vec![
+                LineDiff::Same("a".to_string()),
+                LineDiff::Removed("b".to_string()),
+                LineDiff::Added("x".to_string()),
+                LineDiff::Same("c".to_string()),
+                LineDiff::Added("d".to_string()),
+            ]
+        );
+    }
+
+    fn struct_decl(id: usize, name: &str, field_tys: Vec<RTy>) -> TypeDecl {
+        let meta = Meta {
+            span: Span {
+                file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+                beg: Loc { line: 1, col: 0 },
+                end: Loc { line: 1, col: 0 },
+            },
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        };
+        let fields = field_tys
+            .into_iter()
+            .map(|ty| Field {
+                meta,
+                name: None,
+                ty,
+                interior_mut: false,
+            })
+            .collect();
+        TypeDecl {
+            def_id: TypeDeclId::Id::new(id),
+            meta,
+            name: Name {
+                name: vec![PathElem::Ident(name.to_string())],
+            },
+            region_params: RegionVarId::Vector::new(),
+            type_params: TypeVarId::Vector::new(),
+            const_generic_params: ConstGenericVarId::Vector::new(),
+            kind: TypeDeclKind::Struct(fields),
+            repr: ReprConfig::rust(),
+            is_root: true,
+            stable_id: None,
+            local_methods: Vec::new(),
+            variance: Vec::new(),
+            is_recursive: false,
+            regions_hierarchy: RegionGroups::new(),
+        }
+    }
+
+    /// The declarations are matched by name, not by id
+    #[test]
+    fn test_diff_types() {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let bool_ty = Ty::Literal(LiteralTy::Bool);
+        let mut old_types = Map::new();
+        old_types.insert(TypeDeclId::Id::new(0), struct_decl(0, "A", vec![u32_ty]));
+        old_types.insert(TypeDeclId::Id::new(1), struct_decl(1, "B", vec![]));
+        let mut new_types = Map::new();
+        new_types.insert(TypeDeclId::Id::new(0), struct_decl(0, "C", vec![]));
+        new_types.insert(TypeDeclId::Id::new(1), struct_decl(1, "A", vec![bool_ty]));
+        let funs = Map::new();
+        let globals = Map::new();
+        let old = TranslatedCrate {
+            type_defs: &old_types,
+            fun_defs: &funs,
+            global_defs: &globals,
+        };
+        let new = TranslatedCrate {
+            type_defs: &new_types,
+            fun_defs: &funs,
+            global_defs: &globals,
+        };
+
+        let d = diff(&old, &new);
+        assert_eq!(
+            d.added,
+            vec![(
+                DeclKind::Type,
+                new_types.get(TypeDeclId::Id::new(0)).unwrap().name.clone()
+            )]
+        );
+        assert_eq!(
+            d.removed,
+            vec![(
+                DeclKind::Type,
+                old_types.get(TypeDeclId::Id::new(1)).unwrap().name.clone()
+            )]
+        );
+        assert_eq!(d.changed.len(), 1);
+        assert!(d.changed[0]
+            .diff
+            .iter()
+            .any(|l| matches!(l, LineDiff::Added(l) if l.contains("bool"))));
+
+        assert!(diff(&old, &old).is_empty());
+    }
+}
@@ -16,7 +16,8 @@ fn statement_diverges(divergent: &HashMap<ast::FunDeclId::Id, bool>, st: &llbc::
         | RawStatement::Return
         | RawStatement::Break(_)
         | RawStatement::Continue(_)
-        | RawStatement::Nop => false,
+        | RawStatement::Nop
+        | RawStatement::MacroExpansion(_) => false,
         RawStatement::Call(call) => match &call.func {
             ast::FunId::Regular(id) => *divergent.get(id).unwrap(),
             ast::FunId::Assumed(id) => match id {
@@ -1,30 +1,39 @@
 #![allow(dead_code)]
 
+use crate::check_storage;
 use crate::cli_options;
+use crate::def_ids::DefIdMapping;
+use crate::detect_interior_mutability;
+use crate::detect_loops;
+use crate::eliminate_asserts;
 use crate::export;
 use crate::extract_global_assignments;
-use crate::get_mir::MirLevel;
-use crate::index_to_function_calls;
-use crate::insert_assign_return_unit;
+use crate::get_mir::{self, MirLevel};
+use crate::html_export;
+use crate::liveness;
 use crate::llbc_ast::{CtxNames, FunDeclId, GlobalDeclId};
-use crate::ops_to_function_calls;
-use crate::reconstruct_asserts;
+use crate::name_matcher::NameMatcher;
 use crate::regions_hierarchy;
 use crate::regularize_constant_adts;
-use crate::remove_drop_never;
-use crate::remove_dynamic_checks;
-use crate::remove_read_discriminant;
-use crate::remove_unused_locals;
 use crate::reorder_decls;
+use crate::restore_type_aliases;
+use crate::stable_ids;
+use crate::stats;
+use crate::transform::{self, TranslatedCrate};
 use crate::translate_crate_to_ullbc;
 use crate::translate_ctx;
+use crate::type_dep_graph;
+use crate::typecheck;
+use crate::ullbc_ast_utils;
 use crate::ullbc_to_llbc;
+use crate::unsafe_propagation;
 use regex::Regex;
 use rustc_driver::{Callbacks, Compilation};
 use rustc_interface::{interface::Compiler, Queries};
 use rustc_middle::ty::TyCtxt;
 use rustc_session::Session;
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::io::Write;
 use std::iter::FromIterator;
 use std::ops::Deref;
 
@@ -148,15 +157,117 @@ pub fn translate(sess: &Session, tcx: TyCtxt, internal: &CharonCallbacks) -> Res
     // - whenever there is a `mod MODULE` in a file (for instance, in the
     //   "main.rs" file), it becomes a Module HIR item
 
+    // Lookup the function we should restrict the translation to, if the user
+    // gave one
+    let function = match &options.function {
+        Option::None => Option::None,
+        Option::Some(path) => {
+            match translate_crate_to_ullbc::find_local_function(tcx, &crate_name, path) {
+                Option::Some(def_id) => Option::Some(def_id),
+                Option::None => {
+                    error!("Could not find the function `{}` in the crate", path);
+                    return Err(());
+                }
+            }
+        }
+    };
+
+    // Lookup the functions whose MIR level was given by the user
+    let mut per_item_mir_level = HashMap::new();
+    for arg in &options.mir_level_for {
+        let (path, level) = match get_mir::parse_item_mir_level(arg) {
+            Ok(item_level) => item_level,
+            Err(err) => {
+                error!("Invalid argument for `--mir-level-for`: {}", err);
+                return Err(());
+            }
+        };
+        match translate_crate_to_ullbc::find_local_function(tcx, &crate_name, path) {
+            Option::Some(def_id) => {
+                let _ = per_item_mir_level.insert(def_id, level);
+            }
+            Option::None => {
+                error!("Could not find the function `{}` in the crate", path);
+                return Err(());
+            }
+        }
+    }
+
+    // Parse the patterns selecting the opaque items
+    let mut opaque_mods = Vec::new();
+    for pattern in &options.opaque_modules {
+        match NameMatcher::parse(pattern, &crate_name) {
+            Ok(m) => opaque_mods.push(m),
+            Err(err) => {
+                error!("Invalid opaque pattern `{}`: {}", pattern, err);
+                return Err(());
+            }
+        }
+    }
+
     let crate_info = translate_ctx::CrateInfo {
         crate_name: crate_name.clone(),
-        opaque_mods: HashSet::from_iter(options.opaque_modules.clone().into_iter()),
+        opaque_mods,
+        function,
+        inline_const_eval: options.inline_const_eval,
+        preserve_type_aliases: options.preserve_type_aliases,
+        emit_mir: options.emit_mir,
+        storage_annotations: options.storage_annotations,
     };
 
     // # Translate the declarations in the crate.
     // We translate the declarations in an ad-hoc order, and do not group
     // the mutually recursive groups - we do this in the next step.
-    let mut ctx = translate_crate_to_ullbc::translate(crate_info, sess, tcx, mir_level);
+    let (mut ctx, mut errors) =
+        translate_crate_to_ullbc::translate(crate_info, sess, tcx, mir_level, per_item_mir_level);
+
+    // The control-flow reconstruction doesn't support the generators yet (we
+    // would need to reconstruct the state machine): we can only generate ULLBC
+    // for their bodies.
+    if !options.ullbc {
+        let gen_ids: Vec<FunDeclId::Id> = ctx.generator_decls.iter().map(|g| g.def_id).collect();
+        for id in gen_ids {
+            // We can't reconstruct the control-flow of the body: we make
+            // the function opaque
+            let decl = ctx.fun_defs.get_mut(id).unwrap();
+            decl.body = None;
+            let span = decl.meta;
+            ctx.push_error(translate_ctx::TranslationError::UnsupportedItem {
+                span,
+                kind: "generator (only supported in ULLBC: use --ullbc)".to_string(),
+            });
+        }
+        errors.append(&mut ctx.errors);
+    }
+
+    // The errors were reported as soon as we encountered them: we stop if
+    // the translation is incomplete, unless the user asked us to generate
+    // the partial translation
+    if !errors.is_empty() {
+        if options.errors_as_warnings {
+            log::warn!(
+                "The translation is partial: {} unsupported declaration(s)",
+                errors.len()
+            );
+        } else {
+            error!(
+                "The translation failed with {} unsupported declaration(s)",
+                errors.len()
+            );
+            return Err(());
+        }
+    }
+
+    // # Optionally remove the opaque declarations nobody refers to
+    if options.strip_opaque {
+        ctx.strip_opaque();
+    }
+
+    // # Optionally replace the expansions of the type aliases with the aliases.
+    // This must be done before computing the dependencies of the declarations.
+    if options.preserve_type_aliases {
+        restore_type_aliases::transform(&mut ctx.type_defs, &mut ctx.fun_defs);
+    }
 
     // # Reorder the graph of dependencies and compute the strictly
     // connex components to:
@@ -165,10 +276,49 @@ pub fn translate(sess: &Session, tcx: TyCtxt, internal: &CharonCallbacks) -> Res
     // - group the mutually recursive definitions
     let ordered_decls = reorder_decls::reorder_declarations(&ctx)?;
 
+    // # If we split the output per module, we need the dependencies of the
+    // declarations to compute the dependencies between the generated files
+    let decl_deps = if options.split_output {
+        Some(reorder_decls::compute_dependencies(&ctx))
+    } else {
+        None
+    };
+    let export_options = export::ExportOptions {
+        dest_dir: &options.dest_dir,
+        cbor: options.cbor,
+        split: decl_deps.as_ref(),
+    };
+
+    // # Optionally record the rustc identifiers of the declarations
+    let def_ids = if options.emit_def_ids {
+        Some(DefIdMapping::compute(&ctx))
+    } else {
+        None
+    };
+
+    // # Optionally dump the MIR of the translated functions
+    if options.emit_mir {
+        if let Err(err) = get_mir::export_mir(
+            &crate_name,
+            &ctx.fun_defs,
+            &ctx.mir_dumps,
+            &options.dest_dir,
+        ) {
+            error!("Could not dump the MIR: {}", err);
+            return Err(());
+        }
+    }
+
     // # Compute the regions hierarchies for the types and the function signatures
     // TODO: move to Aeneas
     regions_hierarchy::compute(&mut ctx, &ordered_decls);
 
+    // # Detect the fields which contain an `UnsafeCell`
+    detect_interior_mutability::transform(&mut ctx.type_defs);
+
+    // # Detect the recursive types
+    type_dep_graph::compute_recursive_types(&mut ctx.type_defs);
+
     //
     // =================
     // **Micro-passes**:
@@ -181,6 +331,12 @@ pub fn translate(sess: &Session, tcx: TyCtxt, internal: &CharonCallbacks) -> Res
     let mut ullbc_funs = &mut ctx.fun_defs;
     let mut ullbc_globals = &mut ctx.global_defs;
 
+    // # Optionally compute the stable ids of the declarations. This must be
+    // done before we borrow the type declarations for pretty-printing.
+    if options.stable_ids {
+        stable_ids::transform(type_defs, ullbc_funs, ullbc_globals);
+    }
+
     // Compute the list of function and global names in the context.
     // We need this for pretty-printing (i.e., debugging) purposes.
     // We could use the [FunDecls] and [GlobalDecls] contexts, but we often
@@ -203,80 +359,119 @@ pub fn translate(sess: &Session, tcx: TyCtxt, internal: &CharonCallbacks) -> Res
     // in constant ADTs).
     extract_global_assignments::transform(&fmt_ctx, &mut ullbc_funs, &mut ullbc_globals);
 
+    // # Mark the loop headers. This must be done before reconstructing the
+    // control-flow (see [crate::ullbc_to_llbc]).
+    detect_loops::transform(ullbc_funs, ullbc_globals);
+
+    // # Compute the functions which (transitively) call unsafe functions
+    unsafe_propagation::transform(ullbc_funs);
+
+    // # Check that the `StorageLive` and `StorageDead` statements are
+    // well-bracketed, if we preserve them.
+    if options.storage_annotations && !check_storage::check(ullbc_funs) {
+        error!("The storage annotations of the translated ULLBC are ill-formed");
+        return Err(());
+    }
+
+    // # Optionally remove the overflow checks
+    if options.eliminate_asserts {
+        eliminate_asserts::transform(ullbc_funs, ullbc_globals);
+    }
+
+    // # Optionally compute and print statistics about the functions
+    if options.stats {
+        let stats = stats::compute_stats(ullbc_funs);
+        let stdout = std::io::stdout();
+        if let Err(err) = stats::print_stats_table(&stats, &mut stdout.lock()) {
+            error!("Could not print the statistics: {}", err);
+            return Err(());
+        }
+        if let Err(err) = stats::export_stats(&crate_name, &stats, &options.dest_dir) {
+            error!("Could not generate the statistics file: {}", err);
+            return Err(());
+        }
+    }
+
+    // # Optionally print the functions as pseudo-code
+    if options.pretty {
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        for decl in ullbc_funs.iter() {
+            // We separate the functions with an empty line
+            let res = ullbc_ast_utils::pretty_print(decl, &fmt_ctx, &mut out)
+                .and_then(|()| writeln!(out));
+            if let Err(err) = res {
+                error!("Could not print the functions: {}", err);
+                return Err(());
+            }
+        }
+    }
+
     // # There are two options:
     // - either the user wants the unstructured LLBC, in which case we stop there
     // - or they want the structured LLBC, in which case we reconstruct the
     //   control-flow and apply micro-passes
 
     if options.ullbc {
+        // # Optionally annotate the bodies with the liveness information
+        if options.liveness {
+            liveness::annotate(ullbc_funs, ullbc_globals);
+        }
+
         // # Extract the files
-        export::export_ullbc(
+        let krate = export::ExportedCrate {
             crate_name,
-            &ctx.id_to_file,
-            &ordered_decls,
-            &type_defs,
-            &ullbc_funs,
-            &ullbc_globals,
-            &options.dest_dir,
-        )?;
+            dependencies: Vec::new(),
+            id_to_file: &ctx.id_to_file,
+            ordered_decls: &ordered_decls,
+            type_defs,
+            fun_defs: ullbc_funs,
+            global_defs: ullbc_globals,
+            trait_defs: &ctx.trait_defs,
+            module_defs: &ctx.module_defs,
+            def_ids: def_ids.as_ref(),
+        };
+        if let Err(err) = export::export_ullbc(&krate, &export_options) {
+            error!("Could not generate the files: {}", err);
+            return Err(());
+        }
     } else {
         // # Go from ULLBC to LLBC (Low-Level Borrow Calculus) by reconstructing
         // the control flow.
-        let (mut llbc_funs, mut llbc_globals) = ullbc_to_llbc::translate_functions(
+        let (llbc_funs, llbc_globals) = ullbc_to_llbc::translate_functions(
             options.no_code_duplication,
+            options.storage_annotations,
             &type_defs,
             &ullbc_funs,
             &ullbc_globals,
         );
 
-        // # Micro-pass: remove the dynamic checks for array/slice bounds
-        // and division by zero.
-        // **WARNING**: this pass uses the fact that the dynamic checks
-        // introduced by Rustc use a special "assert" construct. Because of
-        // this, it must happen *before* the [reconstruct_asserts] pass.
-        // See the comments in [crate::remove_dynamic_checks].
-        remove_dynamic_checks::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
-        // # Micro-pass: reconstruct the asserts
-        reconstruct_asserts::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
+        // # Apply the micro-passes (see [crate::transform::llbc_pipeline])
+        let mut pipeline = transform::llbc_pipeline(
+            options.normalize_projections,
+            options.constant_propagation,
+            options.reconstruct_try,
+            options.cse,
+            options.strip_macro_metadata,
+        );
         // TODO: we should mostly use the TransCtx to format declarations
-        for (_, def) in &llbc_funs {
-            trace!(
-                "# After asserts reconstruction:\n{}\n",
-                def.fmt_with_decls(&type_defs, &llbc_funs, &llbc_globals)
-            );
+        pipeline.after(
+            "reconstruct_asserts",
+            Box::new(|krate: &TranslatedCrate<'_>| {
+                for (_, def) in &krate.fun_defs {
+                    trace!(
+                        "# After asserts reconstruction:\n{}\n",
+                        def.fmt_with_decls(krate.type_defs, &krate.fun_defs, &krate.global_defs)
+                    );
+                }
+            }),
+        );
+        let mut krate = TranslatedCrate::new(type_defs, llbc_funs, llbc_globals);
+        if let Err(err) = pipeline.run(&mut krate) {
+            error!("{}", err);
+            return Err(());
         }
-
-        // # Micro-pass: replace some unops/binops with function calls
-        // (introduces: ArrayToSlice, etc.)
-        ops_to_function_calls::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
-        // # Micro-pass: replace the arrays/slices index operations with function
-        // calls.
-        // (introduces: ArrayIndexShared, ArrayIndexMut, etc.)
-        index_to_function_calls::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
-        // # Micro-pass: Remove the discriminant reads (merge them with the switches)
-        remove_read_discriminant::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
-        // # Micro-pass: add the missing assignments to the return value.
-        // When the function return type is unit, the generated MIR doesn't
-        // set the return value to `()`. This can be a concern: in the case
-        // of Aeneas, it means the return variable contains ⊥ upon returning.
-        // For this reason, when the function has return type unit, we insert
-        // an extra assignment just before returning.
-        // This also applies to globals (for checking or executing code before
-        // the main or at compile-time).
-        insert_assign_return_unit::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
-        // # Micro-pass: remove the drops of locals whose type is `Never` (`!`). This
-        // is in preparation of the next transformation.
-        remove_drop_never::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
-
-        // # Micro-pass: remove the locals which are never used. After doing so, we
-        // check that there are no remaining locals with type `Never`.
-        remove_unused_locals::transform(&fmt_ctx, &mut llbc_funs, &mut llbc_globals);
+        let (llbc_funs, llbc_globals) = krate.into_decls();
 
         trace!("# Final LLBC:\n");
         for (_, def) in &llbc_funs {
@@ -286,16 +481,51 @@ pub fn translate(sess: &Session, tcx: TyCtxt, internal: &CharonCallbacks) -> Res
             );
         }
 
+        // # Optionally check that the micro-passes didn't break the typing of
+        // the bodies.
+        if options.typecheck && !typecheck::check(&fmt_ctx, &llbc_funs, &llbc_globals) {
+            error!("The translated LLBC is ill-typed");
+            return Err(());
+        }
+
+        // # Optionally generate the HTML rendering of the functions
+        if options.html {
+            let link_format = options
+                .html_link_format
+                .as_deref()
+                .unwrap_or(html_export::DEFAULT_LINK_FORMAT);
+            let res = html_export::export_html(
+                &crate_name,
+                link_format,
+                &ctx.id_to_file,
+                type_defs,
+                &llbc_funs,
+                &llbc_globals,
+                &options.dest_dir,
+            );
+            if let Err(err) = res {
+                error!("Could not generate the HTML file: {}", err);
+                return Err(());
+            }
+        }
+
         // # Final step: generate the files.
-        export::export_llbc(
+        let krate = export::ExportedCrate {
             crate_name,
-            &ctx.id_to_file,
-            &ordered_decls,
-            &type_defs,
-            &llbc_funs,
-            &llbc_globals,
-            &options.dest_dir,
-        )?;
+            dependencies: Vec::new(),
+            id_to_file: &ctx.id_to_file,
+            ordered_decls: &ordered_decls,
+            type_defs,
+            fun_defs: &llbc_funs,
+            global_defs: &llbc_globals,
+            trait_defs: &ctx.trait_defs,
+            module_defs: &ctx.module_defs,
+            def_ids: def_ids.as_ref(),
+        };
+        if let Err(err) = export::export_llbc(&krate, &export_options) {
+            error!("Could not generate the files: {}", err);
+            return Err(());
+        }
     }
     trace!("Done");
 
@@ -0,0 +1,247 @@
+//! Remove the overflow checks introduced by rustc (`--eliminate-asserts`).
+//!
+//! In debug mode, rustc follows the arithmetic operations with an assertion
+//! which checks that they didn't overflow:
+//! ```text
+//! bb0:
+//!   tmp := copy x + copy y; // `CheckedAdd` in MIR: `tmp: (u32, bool)`
+//!   assert(move (tmp.1) == false) -> bb1
+//! bb1:
+//!   dest := move (tmp.0);
+//! ```
+//! On the verification side, the semantics of the arithmetic operations
+//! already take the overflows into account: those assertions are redundant
+//! and only bloat the output. We replace them with unconditional jumps:
+//! ```text
+//! bb0:
+//!   tmp := copy x + copy y;
+//!   goto bb1
+//! ```
+//! The checked operation is then the same as the unchecked one: we give `tmp`
+//! the type of the result (instead of a pair of the result and of the flag),
+//! and replace `tmp.0` with `tmp`, so that the body is the one we get in
+//! release mode:
+//! ```text
+//! bb1:
+//!   dest := move tmp;
+//! ```
+//! We only remove the [RawTerminator::Assert] terminators whose condition is
+//! the overflow flag computed in the same block. The other dynamic checks
+//! (array bounds, division by zero) are handled by
+//! [crate::remove_dynamic_checks] after the control-flow reconstruction. The
+//! `assert!` written by the user are not [RawTerminator::Assert] terminators
+//! (they are translated to a switch followed by a call to `panic`), and are
+//! thus preserved.
+
+use crate::expressions::{FieldProjKind, MutExprVisitor, Operand, Place, ProjectionElem, Rvalue};
+use crate::types::{FieldId, MutTypeVisitor, Ty, TypeId};
+use crate::types_interner::InternedTy;
+use crate::ullbc_ast::*;
+use crate::values::VarId;
+use std::collections::HashSet;
+
+/// Return `true` if `place` is the overflow flag of the result of a binary
+/// operation computed by `statements`, that is: `place` is `tmp.1` and
+/// `statements` contain `tmp := op1 binop op2`.
+fn is_overflow_flag(statements: &[Statement], place: &Place) -> bool {
+    let (last, prefix) = match place.projection.split_last() {
+        Option::Some(split) => split,
+        Option::None => return false,
+    };
+    if !matches!(last, ProjectionElem::Field(FieldProjKind::Tuple(2), id) if *id == FieldId::ONE) {
+        return false;
+    }
+    // Look for the last assignment to `tmp`
+    statements.iter().rev().any(|st| match &st.content {
+        RawStatement::Assign(dest, Rvalue::BinaryOp(..)) => {
+            dest.var_id == place.var_id && dest.projection == prefix
+        }
+        _ => false,
+    })
+}
+
+/// Replace the overflow check which terminates a block, if there is one,
+/// with a `goto`. If we eliminated an assertion, return the variable which
+/// holds the result of the checked operation.
+pub fn eliminate_in_block(block: &mut BlockData) -> Option<VarId::Id> {
+    let (var_id, target) = match &block.terminator.content {
+        RawTerminator::Assert {
+            cond: Operand::Move(place) | Operand::Copy(place),
+            expected: false,
+            target,
+        } if is_overflow_flag(&block.statements, place) => (place.var_id, *target),
+        _ => return None,
+    };
+    block.terminator.content = RawTerminator::Goto { target };
+    Some(var_id)
+}
+
+/// Replace the uses `tmp.0` of the results of the checked operations whose
+/// overflow check we eliminated with `tmp`.
+struct UncheckResults {
+    vars: HashSet<VarId::Id>,
+}
+
+impl MutTypeVisitor for UncheckResults {}
+
+impl MutExprVisitor for UncheckResults {
+    fn visit_place(&mut self, p: &mut Place) {
+        if self.vars.contains(&p.var_id) {
+            if let Some(ProjectionElem::Field(FieldProjKind::Tuple(2), id)) = p.projection.first() {
+                if *id == FieldId::ZERO {
+                    p.projection.remove(0);
+                }
+            }
+        }
+    }
+}
+
+impl MutAstVisitor for UncheckResults {}
+
+pub fn transform(funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        let mut vars = HashSet::new();
+        for block in b.body.iter_mut() {
+            if let Some(var_id) = eliminate_in_block(block) {
+                vars.insert(var_id);
+            }
+        }
+        trace!(
+            "# Eliminated {} overflow check(s) in decl: {name}",
+            vars.len()
+        );
+
+        for var_id in &vars {
+            let var = b.locals.get_mut(*var_id).unwrap();
+            if let Ty::Adt(TypeId::Tuple, _, tys, _) = &*var.ty {
+                var.ty = InternedTy::new(tys[0].clone());
+            }
+        }
+        let mut visitor = UncheckResults { vars };
+        for block in b.body.iter_mut() {
+            visitor.visit_block_data(block);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::types::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn block(statements: Vec<RawStatement>, terminator: RawTerminator) -> BlockData {
+        BlockData {
+            statements: statements
+                .into_iter()
+                .map(|st| Statement::new(dummy_meta(), st))
+                .collect(),
+            terminator: Terminator::new(dummy_meta(), terminator),
+            is_loop_header: false,
+        }
+    }
+
+    fn tuple_field(var_id: usize, field_id: usize) -> Place {
+        Place {
+            var_id: VarId::Id::new(var_id),
+            projection: vec![ProjectionElem::Field(
+                FieldProjKind::Tuple(2),
+                FieldId::Id::new(field_id),
+            )],
+        }
+    }
+
+    /// The body of `add_test` in debug mode:
+    /// ```text
+    /// bb0:
+    ///   @3 := copy x@1 + copy y@2;
+    ///   assert(move (@3.1) == false) -> bb1
+    /// bb1:
+    ///   @0 := move (@3.0);
+    ///   return
+    /// ```
+    #[test]
+    fn test_add_test() {
+        let x = Place::new(VarId::Id::new(1));
+        let y = Place::new(VarId::Id::new(2));
+        let add = Rvalue::BinaryOp(BinOp::Add, Operand::Copy(x), Operand::Copy(y));
+        let mut blocks = vec![
+            block(
+                vec![RawStatement::Assign(Place::new(VarId::Id::new(3)), add)],
+                RawTerminator::Assert {
+                    cond: Operand::Move(tuple_field(3, 1)),
+                    expected: false,
+                    target: BlockId::Id::new(1),
+                },
+            ),
+            block(
+                vec![RawStatement::Assign(
+                    Place::new(VarId::Id::new(0)),
+                    Rvalue::Use(Operand::Move(tuple_field(3, 0))),
+                )],
+                RawTerminator::Return,
+            ),
+        ];
+        assert!(eliminate_in_block(&mut blocks[0]) == Some(VarId::Id::new(3)));
+        assert!(eliminate_in_block(&mut blocks[1]).is_none());
+        assert!(blocks
+            .iter()
+            .all(|b| !matches!(b.terminator.content, RawTerminator::Assert { .. })));
+        assert!(matches!(
+            blocks[0].terminator.content,
+            RawTerminator::Goto { target } if target == BlockId::Id::new(1)
+        ));
+    }
+
+    /// The bound checks are not overflow checks:
+    /// ```text
+    /// l := len(a);
+    /// b := copy i < copy l;
+    /// assert(move b == true) -> bb1
+    /// ```
+    #[test]
+    fn test_bound_check() {
+        let b = Place::new(VarId::Id::new(3));
+        let l = Place::new(VarId::Id::new(4));
+        let lt = Rvalue::BinaryOp(
+            BinOp::Lt,
+            Operand::Copy(Place::new(VarId::Id::new(2))),
+            Operand::Copy(l.clone()),
+        );
+        let mut bb = block(
+            vec![
+                RawStatement::Assign(
+                    l,
+                    Rvalue::Len(Place::new(VarId::Id::new(1)), Ty::mk_unit(), None),
+                ),
+                RawStatement::Assign(b.clone(), lt),
+            ],
+            RawTerminator::Assert {
+                cond: Operand::Move(b),
+                expected: true,
+                target: BlockId::Id::new(1),
+            },
+        );
+        assert!(eliminate_in_block(&mut bb).is_none());
+        assert!(matches!(
+            bb.terminator.content,
+            RawTerminator::Assert { .. }
+        ));
+    }
+}
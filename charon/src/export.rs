@@ -1,14 +1,30 @@
+#![allow(dead_code)]
+
 use crate::common::*;
+use crate::def_ids::DefIdMapping;
+use crate::gast::{GFunDecl, GGlobalDecl, ModuleDeclId, TraitDecl, TraitDecls, ROOT_MODULE_ID};
 use crate::llbc_ast;
 use crate::meta::{FileId, FileName};
-use crate::reorder_decls::DeclarationsGroups;
+use crate::names_utils::sort_by_name;
+#[cfg(feature = "rustc")]
+use crate::reorder_decls;
+use crate::reorder_decls::{
+    AnyDeclId, AnyTransId, DeclDependencies, DeclarationsGroups, GDeclarationGroup,
+};
+use crate::schema::SCHEMA_VERSION;
+#[cfg(feature = "rustc")]
+use crate::translate_ctx::TransCtx;
 use crate::types::*;
 use crate::ullbc_ast;
-use crate::ullbc_ast::{FunDeclId, GlobalDeclId};
+use crate::ullbc_ast::{FunDeclId, GlobalDeclId, ModuleDecl, ModuleDecls};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{RootSchema, Schema};
+use schemars::JsonSchema;
 use serde::{Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Serialization wrapper for vectors
 pub struct VecSW<'a, T> {
@@ -30,6 +46,20 @@ impl<'a, T: Serialize> Serialize for VecSW<'a, T> {
     }
 }
 
+impl<'a, T: JsonSchema> JsonSchema for VecSW<'a, T> {
+    fn schema_name() -> String {
+        <Vec<T>>::schema_name()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <Vec<T>>::json_schema(gen)
+    }
+}
+
 // TODO: there is an equivalent definition somewhere
 type DeclarationGroup =
     crate::reorder_decls::DeclarationGroup<TypeDeclId::Id, FunDeclId::Id, GlobalDeclId::Id>;
@@ -38,10 +68,14 @@ type DeclarationGroup =
 type DeclarationsSerializer<'a> = VecSW<'a, DeclarationGroup>;
 
 /// A generic crate, which implements the [Serialize] trait
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(rename = "Crate")]
-struct GCrateSerializer<'a, FD: Serialize + Clone, GD: Serialize + Clone> {
+struct GCrateSerializer<'a, FD: Serialize + Clone + 'a, GD: Serialize + Clone + 'a> {
     name: String,
+    /// The files this file depends on, when the output is split per module
+    /// (see [export_modules]). The field is omitted otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<String>,
     /// The `id_to_file` map is serialized as a vector.
     /// We use this map for the spans: the spans only store the file ids, not
     /// the file names, in order to save space.
@@ -50,130 +84,521 @@ struct GCrateSerializer<'a, FD: Serialize + Clone, GD: Serialize + Clone> {
     types: VecSW<'a, TypeDecl>,
     functions: VecSW<'a, FD>,
     globals: VecSW<'a, GD>,
+    /// The (local) trait declarations
+    traits: VecSW<'a, TraitDecl>,
+    /// The module tree. The root (the crate) is the module with id 0.
+    modules: VecSW<'a, ModuleDecl>,
+    /// The rustc identifiers of the declarations (see [crate::def_ids]). The
+    /// field is omitted if the user didn't ask for them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    def_ids: Option<&'a DefIdMapping>,
 }
 
-/// Export the translated definitions to a JSON file.
-///
-/// This is a generic function, used both for LLBC and ULLBC.
-pub fn gexport<FD: Serialize + Clone, GD: Serialize + Clone>(
-    crate_name: String,
-    id_to_file: &HashMap<FileId::Id, FileName>,
-    ordered_decls: &DeclarationsGroups,
-    type_defs: &TypeDecls,
-    fun_defs: &FunDeclId::Map<FD>,
-    global_defs: &GlobalDeclId::Map<GD>,
-    dest_dir: &Option<PathBuf>,
-    extension: &str,
-) -> Result<()> {
-    // Generate the destination file - we use the crate name for the file name
-    let mut target_filename = dest_dir
-        .as_deref()
-        .map_or_else(PathBuf::new, |d| d.to_path_buf());
-    target_filename.push(format!("{crate_name}.{extension}"));
+/// Compute the JSON schema of the files generated for a given kind of
+/// function bodies.
+fn gschema<T: JsonSchema + Debug + Clone + Serialize + 'static>(kind: &str) -> RootSchema {
+    let gen = SchemaSettings::draft07().into_generator();
+    let mut schema =
+        gen.into_root_schema_for::<GCrateSerializer<'static, GFunDecl<T>, GGlobalDecl<T>>>();
+    schema.schema.metadata().id = Some(format!("urn:charon:{kind}:{SCHEMA_VERSION}"));
+    schema
+}
+
+/// The JSON schema of the `.llbc` files.
+pub fn llbc_schema() -> RootSchema {
+    gschema::<llbc_ast::Statement>("llbc")
+}
+
+/// The JSON schema of the `.ullbc` files.
+pub fn ullbc_schema() -> RootSchema {
+    gschema::<ullbc_ast::BlockId::Vector<ullbc_ast::BlockData>>("ullbc")
+}
+
+/// The format of the generated files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    /// The CBOR format is a lot more compact than JSON, and faster to parse
+    /// for large crates. Note that the serialized data is the same: the
+    /// [crate::id_map::Map] and [crate::id_vector::Vector] are serialized as
+    /// sequences in both cases.
+    Cbor,
+}
+
+/// An error which happened while generating a file
+#[derive(Debug)]
+pub enum SerializeError {
+    /// We couldn't create or write to the file (or its directory)
+    Io(PathBuf, std::io::Error),
+    /// We couldn't encode the crate
+    Format(PathBuf, String),
+}
+
+impl std::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            SerializeError::Io(path, e) => write!(f, "could not write to {path:?}: {e}"),
+            SerializeError::Format(path, e) => write!(f, "could not encode {path:?}: {e}"),
+        }
+    }
+}
+
+/// The declarations of a crate we export. We borrow them from the
+/// translation context, or from a crate we read back (see
+/// [crate::deserialize::GTranslatedCrate]).
+pub struct ExportedCrate<'a, T: Debug + Clone + Serialize> {
+    pub crate_name: String,
+    /// The files this file depends on, when the output is split per module
+    /// (see [export_modules])
+    pub dependencies: Vec<String>,
+    pub id_to_file: &'a HashMap<FileId::Id, FileName>,
+    pub ordered_decls: &'a DeclarationsGroups,
+    pub type_defs: &'a TypeDecls,
+    pub fun_defs: &'a FunDeclId::Map<GFunDecl<T>>,
+    pub global_defs: &'a GlobalDeclId::Map<GGlobalDecl<T>>,
+    pub trait_defs: &'a TraitDecls,
+    pub module_defs: &'a ModuleDecls,
+    /// The rustc identifiers of the declarations, if the user asked for them
+    /// (see [crate::def_ids])
+    pub def_ids: Option<&'a DefIdMapping>,
+}
+
+/// The options of the export (see [gexport])
+pub struct ExportOptions<'a> {
+    /// The directory in which to generate the files. We use the current
+    /// directory if it is not provided.
+    pub dest_dir: &'a Option<PathBuf>,
+    /// Also generate CBOR files (see [OutputFormat::Cbor])
+    pub cbor: bool,
+    /// If provided, generate one file per module (see [export_modules]). We
+    /// need the dependencies of the declarations to compute the dependencies
+    /// between the files.
+    pub split: Option<&'a DeclDependencies>,
+}
 
+/// Serialize a crate to a file.
+pub(crate) fn serialize_crate<T: Debug + Clone + Serialize>(
+    krate: &ExportedCrate<'_, T>,
+    target_filename: &Path,
+    format: OutputFormat,
+) -> std::result::Result<(), SerializeError> {
     trace!("Target file: {:?}", target_filename);
 
     // Transform the map file id -> file into a vector.
     // Sort the vector to make the serialized file as stable as possible.
-    let mut file_ids: Vec<FileId::Id> = id_to_file.keys().copied().collect();
+    let mut file_ids: Vec<FileId::Id> = krate.id_to_file.keys().copied().collect();
     file_ids.sort();
     let id_to_file: Vec<(FileId::Id, FileName)> = file_ids
         .into_iter()
-        .map(|id| (id, id_to_file.get(&id).unwrap().clone()))
+        .map(|id| (id, krate.id_to_file.get(&id).unwrap().clone()))
         .collect();
     let id_to_file = VecSW::new(&id_to_file);
 
     // Serialize
     // Note that we replace the maps with vectors (the declarations contain
     // their ids, so it is easy to reconstruct the maps from there).
-    let types = type_defs.iter().cloned().collect();
-    let funs = fun_defs.iter().cloned().collect();
-    let globals = global_defs.iter().cloned().collect();
+    // We sort the declarations by name, because the ids depend on the order
+    // in which we explore the crate: this makes the output as stable as
+    // possible.
+    let types = sort_by_name(krate.type_defs.iter().map(|d| (&d.name, d.clone())));
+    let funs = sort_by_name(krate.fun_defs.iter().map(|d| (&d.name, d.clone())));
+    let globals = sort_by_name(krate.global_defs.iter().map(|d| (&d.name, d.clone())));
+    let traits = sort_by_name(krate.trait_defs.iter().map(|d| (&d.name, d.clone())));
+    let modules = krate.module_defs.iter().cloned().collect();
     let crate_serializer = GCrateSerializer {
-        name: crate_name,
+        name: krate.crate_name.clone(),
+        dependencies: krate.dependencies.clone(),
         id_to_file,
-        declarations: VecSW::new(ordered_decls),
+        declarations: VecSW::new(krate.ordered_decls),
         types: VecSW::new(&types),
         functions: VecSW::new(&funs),
         globals: VecSW::new(&globals),
+        traits: VecSW::new(&traits),
+        modules: VecSW::new(&modules),
+        def_ids: krate.def_ids,
     };
 
-    // Create the directory, if necessary (note that if the target directory
-    // is not specified, there is no need to create it: otherwise we
-    // couldn't have read the input file in the first place).
-    match dest_dir {
-        Option::None => (),
-        Option::Some(dest_dir) => match std::fs::create_dir_all(dest_dir) {
-            std::result::Result::Ok(()) => (),
-            std::result::Result::Err(_) => {
-                error!("Could not create the directory: {:?}", dest_dir);
-                return Err(());
+    // Write to the file
+    let io_error = |e| SerializeError::Io(target_filename.to_path_buf(), e);
+    let format_error = |e: String| SerializeError::Format(target_filename.to_path_buf(), e);
+    let outfile = File::create(target_filename).map_err(io_error)?;
+    match format {
+        OutputFormat::Json => serde_json::to_writer(&outfile, &crate_serializer).map_err(|e| {
+            if e.is_io() {
+                io_error(e.into())
+            } else {
+                format_error(e.to_string())
             }
-        },
+        })?,
+        OutputFormat::Cbor => {
+            ciborium::ser::into_writer(&crate_serializer, &outfile).map_err(|e| match e {
+                ciborium::ser::Error::Io(e) => io_error(e),
+                ciborium::ser::Error::Value(e) => format_error(e),
+            })?
+        }
     };
 
-    // Write to the file
-    match File::create(target_filename.clone()) {
-        std::io::Result::Ok(outfile) => match serde_json::to_writer(&outfile, &crate_serializer) {
-            std::result::Result::Ok(()) => {
-                // We canonicalize (i.e., make absolute) the path before printing it:
-                // this makes it clearer to the user where to find the file.
-                let path = std::fs::canonicalize(target_filename).unwrap();
-                info!("Generated the file: {}", path.to_str().unwrap());
-                Ok(())
+    // We canonicalize (i.e., make absolute) the path before printing it:
+    // this makes it clearer to the user where to find the file.
+    let path = std::fs::canonicalize(target_filename).map_err(io_error)?;
+    info!("Generated the file: {}", path.to_string_lossy());
+    Ok(())
+}
+
+/// Serialize a crate to a JSON file and, if `cbor` is `true`, to a CBOR file
+/// (with the additional `.cbor` extension).
+fn serialize_crate_formats<T: Debug + Clone + Serialize>(
+    krate: &ExportedCrate<'_, T>,
+    mut target_filename: PathBuf,
+    extension: &str,
+    cbor: bool,
+) -> std::result::Result<(), SerializeError> {
+    serialize_crate(krate, &target_filename, OutputFormat::Json)?;
+
+    if cbor {
+        target_filename.set_extension(format!("{extension}.cbor"));
+        serialize_crate(krate, &target_filename, OutputFormat::Cbor)?;
+    }
+    Ok(())
+}
+
+/// Restrict a declaration group to the declarations which satisfy `keep`.
+/// Return `None` if no declaration of the group satisfies it.
+fn filter_group<Id: Copy>(
+    group: &GDeclarationGroup<Id>,
+    keep: impl Fn(Id) -> bool,
+) -> Option<GDeclarationGroup<Id>> {
+    match group {
+        GDeclarationGroup::NonRec(id) => {
+            if keep(*id) {
+                Some(GDeclarationGroup::NonRec(*id))
+            } else {
+                None
             }
-            std::result::Result::Err(_) => {
-                error!("Could not write to: {:?}", target_filename);
-                Err(())
+        }
+        GDeclarationGroup::Rec(ids) => {
+            // Note that the group remains recursive, even if we keep only one
+            // declaration: the declarations may refer to each other across
+            // the files
+            let ids: Vec<Id> = ids.iter().copied().filter(|id| keep(*id)).collect();
+            if ids.is_empty() {
+                None
+            } else {
+                Some(GDeclarationGroup::Rec(ids))
             }
-        },
-        std::io::Result::Err(_) => {
-            error!("Could not open: {:?}", target_filename);
-            Err(())
         }
     }
 }
 
+/// Compute the name of the file of a module, without the extension: the file
+/// of a module `crate::a::b` is `crate.a.b`. Note that we use the crate name
+/// given by the user, if there is one, for the crate root.
+fn module_file_stem(crate_name: &str, module: &ModuleDecl) -> String {
+    let mut elems = vec![crate_name.to_string()];
+    elems.extend(module.name.name.iter().skip(1).map(|e| e.to_string()));
+    elems.join(".")
+}
+
+/// Export the translated definitions to one file per module, rather than to a
+/// single file. The file of the crate root is the file we generate when we
+/// don't split the output (see [module_file_stem]).
+///
+/// Every file contains the declarations of its module (but not the
+/// declarations of its submodules). The declarations which don't belong to a
+/// local module (the external declarations and the traits) are written in the
+/// file of the crate root. The declarations keep their ids, which are unique
+/// across the files: the declarations of a file can refer to the
+/// declarations of the other files, which we list in the `dependencies` field.
+fn export_modules<T: Debug + Clone + Serialize>(
+    krate: &ExportedCrate<'_, T>,
+    decl_deps: &DeclDependencies,
+    extension: &str,
+    options: &ExportOptions<'_>,
+) -> std::result::Result<(), SerializeError> {
+    let crate_name = &krate.crate_name;
+    let module_defs = krate.module_defs;
+    // Compute the module in which every declaration is written
+    let mut decl_modules: HashMap<AnyTransId, ModuleDeclId::Id> = HashMap::new();
+    for m in module_defs.iter() {
+        let ids = (m.types.iter().map(|id| AnyDeclId::Type(*id)))
+            .chain(m.funs.iter().map(|id| AnyDeclId::Fun(*id)))
+            .chain(m.globals.iter().map(|id| AnyDeclId::Global(*id)));
+        for id in ids {
+            let _ = decl_modules.insert(id, m.def_id);
+        }
+    }
+    let module_of = |id: AnyTransId| decl_modules.get(&id).copied().unwrap_or(ROOT_MODULE_ID);
+
+    for m in module_defs.iter() {
+        let in_module = |id: AnyTransId| module_of(id) == m.def_id;
+
+        let types: TypeDecls = krate
+            .type_defs
+            .iter()
+            .filter(|d| in_module(AnyDeclId::Type(d.def_id)))
+            .map(|d| (d.def_id, d.clone()))
+            .collect();
+        let funs: FunDeclId::Map<GFunDecl<T>> = krate
+            .fun_defs
+            .iter()
+            .filter(|d| in_module(AnyDeclId::Fun(d.def_id)))
+            .map(|d| (d.def_id, d.clone()))
+            .collect();
+        let globals: GlobalDeclId::Map<GGlobalDecl<T>> = krate
+            .global_defs
+            .iter()
+            .filter(|d| in_module(AnyDeclId::Global(d.def_id)))
+            .map(|d| (d.def_id, d.clone()))
+            .collect();
+        let traits: TraitDecls = if m.def_id == ROOT_MODULE_ID {
+            krate
+                .trait_defs
+                .iter()
+                .map(|d| (d.def_id, d.clone()))
+                .collect()
+        } else {
+            TraitDecls::new()
+        };
+        let modules: ModuleDecls = std::iter::once((m.def_id, m.clone())).collect();
+        // The mapping of the identifiers is written in the file of the root
+        let m_def_ids = if m.def_id == ROOT_MODULE_ID {
+            krate.def_ids
+        } else {
+            None
+        };
+
+        let groups: DeclarationsGroups = krate
+            .ordered_decls
+            .iter()
+            .filter_map(|g| match g {
+                DeclarationGroup::Type(g) => {
+                    filter_group(g, |id| in_module(AnyDeclId::Type(id))).map(DeclarationGroup::Type)
+                }
+                DeclarationGroup::Fun(g) => {
+                    filter_group(g, |id| in_module(AnyDeclId::Fun(id))).map(DeclarationGroup::Fun)
+                }
+                DeclarationGroup::Global(g) => {
+                    filter_group(g, |id| in_module(AnyDeclId::Global(id)))
+                        .map(DeclarationGroup::Global)
+                }
+            })
+            .collect();
+
+        // The files this file depends on. We use a [BTreeSet] to list them in
+        // a stable order.
+        let dependencies: BTreeSet<ModuleDeclId::Id> = decl_deps
+            .iter()
+            .filter(|(id, _)| in_module(**id))
+            .flat_map(|(_, deps)| deps.iter().map(|id| module_of(*id)))
+            .filter(|mid| *mid != m.def_id)
+            .collect();
+        let dependencies: Vec<String> = dependencies
+            .into_iter()
+            .map(|mid| {
+                let stem = module_file_stem(crate_name, module_defs.get(mid).unwrap());
+                format!("{stem}.{extension}")
+            })
+            .collect();
+
+        let mut target_filename = options
+            .dest_dir
+            .as_deref()
+            .map_or_else(PathBuf::new, |d| d.to_path_buf());
+        target_filename.push(format!("{}.{extension}", module_file_stem(crate_name, m)));
+        let m_krate = ExportedCrate {
+            crate_name: crate_name.clone(),
+            dependencies,
+            id_to_file: krate.id_to_file,
+            ordered_decls: &groups,
+            type_defs: &types,
+            fun_defs: &funs,
+            global_defs: &globals,
+            trait_defs: &traits,
+            module_defs: &modules,
+            def_ids: m_def_ids,
+        };
+        serialize_crate_formats(&m_krate, target_filename, extension, options.cbor)?;
+    }
+    Ok(())
+}
+
+/// Export the translated definitions to a JSON file and, if `cbor` is `true`,
+/// to a CBOR file (with the additional `.cbor` extension), possibly split per
+/// module (see [ExportOptions]).
+///
+/// This is a generic function, used both for LLBC and ULLBC: `extension` is
+/// the extension of the generated files.
+pub fn gexport<T: Debug + Clone + Serialize>(
+    krate: &ExportedCrate<'_, T>,
+    extension: &str,
+    options: &ExportOptions<'_>,
+) -> std::result::Result<(), SerializeError> {
+    // Create the directory, if necessary (note that if the target directory
+    // is not specified, there is no need to create it: otherwise we
+    // couldn't have read the input file in the first place).
+    if let Option::Some(dest_dir) = options.dest_dir {
+        std::fs::create_dir_all(dest_dir).map_err(|e| SerializeError::Io(dest_dir.clone(), e))?;
+    }
+
+    if let Option::Some(decl_deps) = options.split {
+        return export_modules(krate, decl_deps, extension, options);
+    }
+
+    // Generate the destination file - we use the crate name for the file name
+    let mut target_filename = options
+        .dest_dir
+        .as_deref()
+        .map_or_else(PathBuf::new, |d| d.to_path_buf());
+    target_filename.push(format!("{}.{extension}", krate.crate_name));
+
+    serialize_crate_formats(krate, target_filename, extension, options.cbor)
+}
+
+/// Export the ULLBC definitions of a translation context to a CBOR file.
+#[cfg(feature = "rustc")]
+pub fn write_cbor(ctx: &TransCtx, path: &Path) -> std::result::Result<(), SerializeError> {
+    let ordered_decls = reorder_decls::reorder_declarations(ctx).map_err(|()| {
+        let msg = "could not reorder the declarations".to_string();
+        SerializeError::Format(path.to_path_buf(), msg)
+    })?;
+    let krate = ExportedCrate {
+        crate_name: ctx.crate_info.crate_name.clone(),
+        dependencies: Vec::new(),
+        id_to_file: &ctx.id_to_file,
+        ordered_decls: &ordered_decls,
+        type_defs: &ctx.type_defs,
+        fun_defs: &ctx.fun_defs,
+        global_defs: &ctx.global_defs,
+        trait_defs: &ctx.trait_defs,
+        module_defs: &ctx.module_defs,
+        def_ids: None,
+    };
+    serialize_crate(&krate, path, OutputFormat::Cbor)
+}
+
 /// Export the translated ULLBC definitions to a JSON file.
 pub fn export_ullbc(
-    crate_name: String,
-    id_to_file: &HashMap<FileId::Id, FileName>,
-    ordered_decls: &DeclarationsGroups,
-    type_defs: &TypeDecls,
-    fun_defs: &ullbc_ast::FunDecls,
-    global_defs: &ullbc_ast::GlobalDecls,
-    dest_dir: &Option<PathBuf>,
-) -> Result<()> {
-    gexport(
-        crate_name,
-        id_to_file,
-        ordered_decls,
-        type_defs,
-        fun_defs,
-        global_defs,
-        dest_dir,
-        "ullbc",
-    )
+    krate: &ExportedCrate<'_, ullbc_ast::BlockId::Vector<ullbc_ast::BlockData>>,
+    options: &ExportOptions<'_>,
+) -> std::result::Result<(), SerializeError> {
+    gexport(krate, "ullbc", options)
 }
 
 /// Export the translated LLBC definitions to a JSON file.
 pub fn export_llbc(
-    crate_name: String,
-    id_to_file: &HashMap<FileId::Id, FileName>,
-    ordered_decls: &DeclarationsGroups,
-    type_defs: &TypeDecls,
-    fun_defs: &llbc_ast::FunDecls,
-    global_defs: &llbc_ast::GlobalDecls,
-    dest_dir: &Option<PathBuf>,
-) -> Result<()> {
-    gexport(
-        crate_name,
-        id_to_file,
-        ordered_decls,
-        type_defs,
-        fun_defs,
-        global_defs,
-        dest_dir,
-        "llbc",
-    )
+    krate: &ExportedCrate<'_, llbc_ast::Statement>,
+    options: &ExportOptions<'_>,
+) -> std::result::Result<(), SerializeError> {
+    gexport(krate, "llbc", options)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gast::Var;
+    use crate::id_map::Map;
+    use crate::types::*;
+    use crate::values::*;
+    use serde::Serialize;
+
+    /// Check that the CBOR and the JSON serializations have the same content
+    fn check_cbor_json<T: Serialize>(x: &T) {
+        let json = serde_json::to_value(x).unwrap();
+        let mut cbor: Vec<u8> = Vec::new();
+        ciborium::ser::into_writer(x, &mut cbor).unwrap();
+        let from_cbor: serde_json::Value = ciborium::de::from_reader(cbor.as_slice()).unwrap();
+        assert!(json == from_cbor);
+    }
+
+    #[test]
+    fn test_cbor_map() {
+        let mut ids = VarId::Generator::new();
+        let mut vars: Map<VarId::Id, Var> = Map::new();
+        for (name, ty) in [
+            (Some("x"), Ty::Literal(LiteralTy::Bool)),
+            (None, Ty::Adt(TypeId::Tuple, vec![], vec![], vec![])),
+        ] {
+            let index = ids.fresh_id();
+            let var = Var {
+                index,
+                name: name.map(|s| s.to_string()),
+                ty: ty.into(),
+            };
+            vars.insert(index, var);
+        }
+
+        // The map is serialized as a sequence of pairs
+        let json = serde_json::to_value(&vars).unwrap();
+        assert!(json.as_array().unwrap().len() == 2);
+        assert!(json[0].as_array().unwrap().len() == 2);
+        check_cbor_json(&vars);
+    }
+
+    #[test]
+    fn test_filter_group() {
+        use crate::reorder_decls::GDeclarationGroup::*;
+        let group = Rec(vec![0, 1, 2]);
+        assert!(
+            matches!(super::filter_group(&group, |id| id != 1), Some(Rec(ids)) if ids == vec![0, 2])
+        );
+        assert!(super::filter_group(&group, |id| id > 2).is_none());
+        assert!(matches!(
+            super::filter_group(&NonRec(3), |_| true),
+            Some(NonRec(3))
+        ));
+    }
+
+    #[test]
+    fn test_module_file_stem() {
+        use crate::gast::{ModuleDecl, ModuleDeclId};
+        use crate::names::{Name, PathElem};
+        let module = ModuleDecl {
+            def_id: ModuleDeclId::Id::new(1),
+            name: Name {
+                name: vec![
+                    PathElem::Ident("krate".to_string()),
+                    PathElem::Ident("a".to_string()),
+                    PathElem::Ident("b".to_string()),
+                ],
+            },
+            submodules: vec![],
+            types: vec![],
+            funs: vec![],
+            globals: vec![],
+        };
+        // We use the crate name given by the user
+        assert!(super::module_file_stem("my_crate", &module) == "my_crate.a.b");
+    }
+
+    #[test]
+    fn test_schema() {
+        for (schema, kind) in [
+            (super::llbc_schema(), "llbc"),
+            (super::ullbc_schema(), "ullbc"),
+        ] {
+            let json = serde_json::to_value(&schema).unwrap();
+            assert!(json["$schema"] == "http://json-schema.org/draft-07/schema#");
+            assert!(json["$id"]
+                .as_str()
+                .unwrap()
+                .starts_with(&format!("urn:charon:{kind}:")));
+            // The empty object is not a crate (this also checks that the
+            // schema is valid)
+            assert!(crate::schema::validate(&schema, &serde_json::json!({})).is_err());
+        }
+    }
+
+    #[test]
+    fn test_schema_scalar_value() {
+        let mut gen = schemars::gen::SchemaSettings::draft07().into_generator();
+        let schema = gen.root_schema_for::<ScalarValue>();
+        for v in [
+            ScalarValue::U8(3),
+            ScalarValue::Usize(PointerSize::P64, 1 << 40),
+        ] {
+            let json = serde_json::to_value(v).unwrap();
+            assert!(crate::schema::validate(&schema, &json).is_ok());
+        }
+        let json = serde_json::json!({ "U8": 3 });
+        assert!(crate::schema::validate(&schema, &json).is_err());
+    }
 }
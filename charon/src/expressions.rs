@@ -1,13 +1,15 @@
 //! Implements expressions: paths, operands, rvalues, lvalues
 
 pub use crate::expressions_utils::*;
+use crate::gast::FunDeclId;
 use crate::types::*;
 use crate::values::*;
 use macros::{EnumAsGetters, EnumIsA, EnumToGetters, VariantIndexArity, VariantName};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::vec::Vec;
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Place {
     pub var_id: VarId::Id,
     pub projection: Projection,
@@ -24,7 +26,18 @@ pub type Projection = Vec<ProjectionElem>;
 /// In MIR, downcasts always happen before field projections: in our internal
 /// language, we thus merge downcasts and field projections.
 #[derive(
-    Debug, PartialEq, Eq, Clone, EnumIsA, EnumAsGetters, EnumToGetters, VariantName, Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    EnumIsA,
+    EnumAsGetters,
+    EnumToGetters,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum ProjectionElem {
     /// Dereference a shared/mutable reference.
@@ -52,15 +65,44 @@ pub enum ProjectionElem {
     /// (for pretty printing for instance). We retrieve it through
     /// type-checking.
     Field(FieldProjKind, FieldId::Id),
+    /// Projection of a union field. Contrary to [ProjectionElem::Field], reading
+    /// a union field is unsafe: it reinterprets the bytes of the union as a
+    /// value of the field type.
+    UnionField(TypeDeclId::Id, FieldId::Id),
     /// MIR imposes that the argument to an index projection be a local variable, meaning
     /// that even constant indices into arrays are let-bound as separate variables.
     /// We also keep the type of the array/slice that we index for convenience purposes
     /// (this is not necessary).
     /// We **eliminate** this variant in a micro-pass.
     Index(VarId::Id, ETy),
+    /// Access to an element of an array/slice at a constant offset. This is
+    /// introduced by the slice patterns: matching `[a, b, ..]` reads `a` at
+    /// offset 0 and `b` at offset 1, after a check on the length of the slice.
+    /// If `from_end` is `true`, the offset is counted from the end of the
+    /// slice (with `offset >= 1`): this is used to access the elements which
+    /// come after a `..` in the pattern.
+    ConstantIndex { offset: u64, from_end: bool },
+    /// A subslice of an array/slice. This is introduced by the rest bindings
+    /// of the slice patterns (`[a, rest @ ..]`).
+    /// If `from_end` is `true`, the subslice spans from `from` to
+    /// `len - to` and has a slice type, otherwise it spans from `from` to
+    /// `to` (this case only happens for arrays, and the subslice is an array).
+    Subslice { from: u64, to: u64, from_end: bool },
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIsA, EnumAsGetters, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    EnumIsA,
+    EnumAsGetters,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum FieldProjKind {
     #[serde(rename = "ProjAdt")]
     Adt(TypeDeclId::Id, Option<VariantId::Id>),
@@ -70,9 +112,25 @@ pub enum FieldProjKind {
     /// If we project from a tuple, the projection kind gives the arity of the
     #[serde(rename = "ProjTuple")]
     Tuple(usize),
+    /// The range types are assumed: the field ids index the fields given by
+    /// [crate::assumed::range_field_names]
+    #[serde(rename = "ProjRange")]
+    Range(AssumedTy),
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIsA, EnumAsGetters, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    EnumIsA,
+    EnumAsGetters,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum BorrowKind {
     Shared,
     Mut,
@@ -87,8 +145,34 @@ pub enum BorrowKind {
     Shallow,
 }
 
+/// A cast involving raw pointers. See [UnOp::PtrCast].
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Copy,
+    EnumIsA,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+pub enum PtrCastKind {
+    /// `p as *const T`, where `p` is a raw pointer
+    PtrToPtr,
+    /// `x as *const T`, where `x` is an integer of the given type
+    IntToPtr(IntegerTy),
+    /// `p as usize`, where `p` is a raw pointer. We give the target integer
+    /// type.
+    PtrToInt(IntegerTy),
+}
+
 /// Unary operation
-#[derive(Debug, PartialEq, Eq, Clone, EnumIsA, VariantName, Serialize)]
+#[derive(
+    Debug, PartialEq, Eq, Hash, Clone, EnumIsA, VariantName, Serialize, Deserialize, JsonSchema,
+)]
 pub enum UnOp {
     Not,
     /// This can overflow. In practice, rust introduces an assert before
@@ -97,11 +181,14 @@ pub enum UnOp {
     Neg,
     /// Casts are rvalues in MIR, but we treat them as unops. For now, we
     /// only support for integer to integer, but we can also do from integers/booleans
-    /// to integers/booleans. For now, we don't handle pointer casts.
+    /// to integers/booleans. The pointer casts are handled by [UnOp::PtrCast].
     ///
     /// The first integer type gives the source type, the second one gives
     /// the destination type.
     Cast(IntegerTy, IntegerTy),
+    /// Casts involving raw pointers. The pointed types can be retrieved from
+    /// the types of the operand and of the destination.
+    PtrCast(PtrCastKind),
     /// Coercion from array (i.e., [T; N]) to slice.
     ///
     /// **Remark:** We introduce this unop when translating from MIR, **then transform**
@@ -113,7 +200,19 @@ pub enum UnOp {
 }
 
 /// Binary operations.
-#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIsA, VariantName, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    EnumIsA,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum BinOp {
     BitXor,
     BitAnd,
@@ -142,7 +241,18 @@ pub enum BinOp {
 }
 
 #[derive(
-    Debug, PartialEq, Eq, Clone, EnumIsA, EnumToGetters, EnumAsGetters, VariantName, Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    EnumIsA,
+    EnumToGetters,
+    EnumAsGetters,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum Operand {
     Copy(Place),
@@ -168,7 +278,9 @@ pub enum Operand {
 /// `Identifier` and `Static` case:
 /// Match constant variables. We later desugar those to separate statements,
 /// see [extract_global_assignments.rs].
-#[derive(Debug, PartialEq, Eq, Clone, VariantName, EnumIsA, EnumAsGetters, VariantIndexArity)]
+#[derive(
+    Debug, PartialEq, Eq, Hash, Clone, VariantName, EnumIsA, EnumAsGetters, VariantIndexArity,
+)]
 pub enum OperandConstantValue {
     Literal(Literal),
     ///
@@ -178,6 +290,13 @@ pub enum OperandConstantValue {
     ///
     /// Less frequently: arbitrary ADT values.
     Adt(Option<VariantId::Id>, Vec<OperandConstantValue>),
+    /// A constant array. As for [OperandConstantValue::Adt], this case is
+    /// eliminated in [crate::regularize_constant_adts].
+    ///
+    /// This is also used for the byte strings (`b"hello"`): as for the
+    /// string literals, the operand then has type `&[u8; N]` and the array
+    /// is the value of the reference.
+    Array(Vec<OperandConstantValue>),
     ///
     /// The case when the constant is elsewhere.
     /// The MIR seems to forbid more complex expressions like paths :
@@ -192,10 +311,25 @@ pub enum OperandConstantValue {
 
 /// TODO: we could factor out [Rvalue] and function calls (for LLBC, not ULLBC).
 /// We can also factor out the unops, binops with the function calls.
-#[derive(Debug, Clone, Serialize, EnumToGetters, EnumAsGetters, EnumIsA)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    EnumToGetters,
+    EnumAsGetters,
+    EnumIsA,
+)]
 pub enum Rvalue {
     Use(Operand),
     Ref(Place, BorrowKind),
+    /// Raw borrow (`&raw const p`, `&raw mut p`), introduced for instance by
+    /// casts from references to raw pointers (`x as *const T`).
+    RawPtr(Place, RefKind),
     /// Unary operation (not, neg)
     UnaryOp(UnOp, Operand),
     /// Binary operations (note that we merge "checked" and "unchecked" binops)
@@ -224,6 +358,9 @@ pub enum Rvalue {
     /// Not present in MIR: we introduce it when replacing constant variables
     /// in operands in [extract_global_assignments.rs]
     Global(GlobalDeclId::Id),
+    /// A function coerced to a function pointer (`let f: fn(u32) -> u32 = foo;`).
+    /// We give the identifier of the function and its generic arguments.
+    FnPtr(FunDeclId::Id, Vec<ETy>, Vec<ConstGeneric>),
     /// Length of a memory location. The run-time length of e.g. a vector or a slice is
     /// represented differently (but pretty-prints the same, FIXME).
     /// Should be seen as a function of signature:
@@ -232,22 +369,29 @@ pub enum Rvalue {
     ///
     /// We store the type argument and the const generic (the latter only for arrays).
     ///
-    /// [Len] is introduced by rustc for the bound checks: we **eliminate it
-    /// together with the bounds checks**. Whenever the user writes `x.len()`
-    /// where `x` is a slice or an array, they actually call a non-primitive
-    /// function.
+    /// [Len] is introduced by rustc for the bound checks, which we eliminate
+    /// together with it, and for the slice patterns (`[x, y, ..]`), which check
+    /// the length of the slice. Whenever the user writes `x.len()` where `x`
+    /// is a slice or an array, they actually call a non-primitive function.
     Len(Place, ETy, Option<ConstGeneric>),
 }
 
-#[derive(Debug, Clone, VariantIndexArity, Serialize)]
+#[derive(
+    Debug, PartialEq, Eq, Hash, Clone, VariantIndexArity, Serialize, Deserialize, JsonSchema,
+)]
 pub enum AggregateKind {
     Tuple,
     // TODO: treat Option in a general manner by merging it with the Adt case (we should
     // extract the definitions of the external enumerations - because as they are public,
     // their variants are public)
     Option(VariantId::Id, ETy),
-    // TODO: do we really need this?
-    Range(ETy),
+    /// A range (`a..b`, `a..`, etc.): we give the range type (one of the
+    /// range types of [AssumedTy]) and its type parameters (there are none
+    /// for [AssumedTy::RangeFull]). The operands are the fields.
+    Range(AssumedTy, Vec<ETy>),
+    /// A `PhantomData` value (there are no operands): we give the type
+    /// parameter.
+    PhantomData(ETy),
     Adt(
         TypeDeclId::Id,
         Option<VariantId::Id>,
@@ -255,7 +399,76 @@ pub enum AggregateKind {
         Vec<ETy>,
         Vec<ConstGeneric>,
     ),
+    /// A structure built with the struct update syntax (`Foo { x: 1, ..a }`).
+    /// rustc projects the fields which are not updated from the base
+    /// (`Foo { x: const 1, y: move (a.y) }`): we group them back. The rvalue
+    /// has no other operands.
+    StructUpdate {
+        type_id: TypeDeclId::Id,
+        /// The structure the fields which are not updated come from
+        base: Operand,
+        /// The updated fields, in the order in which they are initialized
+        updated_fields: Vec<(FieldId::Id, Operand)>,
+    },
     // We don't put this with the ADT cas because this is the only assumed type
     // with aggregates.
     Array(ETy, ConstGeneric),
+    /// An array whose elements are all the same (`[x; N]`): we give the type
+    /// of the elements, the repeated element and the length, which may be a
+    /// const generic. As with [AggregateKind::StructUpdate], the rvalue has
+    /// no other operands.
+    Repeat(ETy, Operand, ConstGeneric),
+    /// A union value: we give the union identifier, the field we initialize
+    /// and the generic arguments. There is exactly one operand.
+    Union(
+        TypeDeclId::Id,
+        FieldId::Id,
+        Vec<ErasedRegion>,
+        Vec<ETy>,
+        Vec<ConstGeneric>,
+    ),
+    /// A closure: we give the identifier of the function implementing the
+    /// closure body, the closure kind, and the types of the captured variables.
+    ///
+    /// The closure state is the tuple of its captured variables (the operands
+    /// of the aggregate): this tuple is given as first argument to the closure
+    /// body (by reference if the closure kind is [ClosureKind::Fn] or
+    /// [ClosureKind::FnMut]).
+    Closure(FunDeclId::Id, ClosureKind, Vec<ETy>),
+    /// A generator: we give the identifier of the function implementing the
+    /// generator body, and the types of the captured variables.
+    ///
+    /// As for closures, the generator state is the tuple of its captured
+    /// variables.
+    Generator(FunDeclId::Id, Vec<ETy>),
+    /// A raw pointer built from its parts: we give the type of the pointed
+    /// value and the kind of the pointer (`*const` or `*mut`).
+    ///
+    /// There are two operands: the data pointer and the metadata (`()` for
+    /// the pointers to sized values, the length for the pointers to slices).
+    RawPtr(ETy, RefKind),
+}
+
+/// The kind of a closure, which determines how the closure state is given
+/// to the closure body.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    EnumIsA,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+pub enum ClosureKind {
+    /// The state is borrowed immutably (the closure implements `Fn`)
+    Fn,
+    /// The state is borrowed mutably (the closure implements `FnMut`)
+    FnMut,
+    /// The state is moved (the closure implements `FnOnce`)
+    FnOnce,
 }
@@ -5,12 +5,17 @@ use crate::assumed;
 use crate::expressions::*;
 use crate::formatter::Formatter;
 use crate::gast::{AssumedFunId, Call, FunDeclId, FunId};
+use crate::id_vector::ToUsize;
+use crate::names::Name;
 use crate::types::*;
 use crate::ullbc_ast::GlobalDeclId;
 use crate::values;
 use crate::values::*;
 use macros::make_generic_in_borrows;
-use serde::{Serialize, Serializer};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::vec::Vec;
 
 impl Place {
@@ -39,6 +44,9 @@ impl std::fmt::Display for UnOp {
             UnOp::Not => write!(f, "~"),
             UnOp::Neg => write!(f, "-"),
             UnOp::Cast(src, tgt) => write!(f, "cast<{src},{tgt}>"),
+            UnOp::PtrCast(PtrCastKind::PtrToPtr) => write!(f, "ptr_cast"),
+            UnOp::PtrCast(PtrCastKind::IntToPtr(src)) => write!(f, "ptr_cast<{src},ptr>"),
+            UnOp::PtrCast(PtrCastKind::PtrToInt(tgt)) => write!(f, "ptr_cast<ptr,{tgt}>"),
             UnOp::ArrayToSlice(..) => write!(f, "array_to_slice"),
         }
     }
@@ -109,8 +117,29 @@ impl Place {
                     FieldProjKind::Option(_) => {
                         out = format!("({out}).{field_id}");
                     }
+                    FieldProjKind::Range(aty) => {
+                        let field_name = assumed::range_field_names(*aty)[field_id.to_usize()];
+                        out = format!("({out}).{field_name}");
+                    }
                 },
+                ProjectionElem::UnionField(adt_id, field_id) => {
+                    let field_name = ctx.format_object((*adt_id, None, *field_id));
+                    out = format!("({out} as union).{field_name}");
+                }
                 ProjectionElem::Index(i, _) => out = format!("({out})[{}]", ctx.format_object(*i)),
+                ProjectionElem::ConstantIndex { offset, from_end } => {
+                    let minus = if *from_end { "-" } else { "" };
+                    out = format!("({out})[{minus}{offset}]")
+                }
+                ProjectionElem::Subslice {
+                    from,
+                    to: 0,
+                    from_end: true,
+                } => out = format!("({out})[{from}..]"),
+                ProjectionElem::Subslice { from, to, from_end } => {
+                    let minus = if *from_end { "-" } else { "" };
+                    out = format!("({out})[{from}..{minus}{to}]")
+                }
             }
         }
 
@@ -149,6 +178,10 @@ impl OperandConstantValue {
                 let values: Vec<String> = values.iter().map(|v| v.fmt_with_ctx(ctx)).collect();
                 format!("ConstAdt {} [{}]", variant_id, values.join(", "))
             }
+            OperandConstantValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.fmt_with_ctx(ctx)).collect();
+                format!("ConstArray [{}]", values.join(", "))
+            }
             OperandConstantValue::ConstantId(id) => ctx.format_object(*id),
             OperandConstantValue::StaticId(id) => format!("alloc: &{}", ctx.format_object(*id)),
             OperandConstantValue::Var(id) => format!("const {}", ctx.format_object(*id)),
@@ -212,6 +245,10 @@ impl Rvalue {
                 }
                 BorrowKind::Shallow => format!("&shallow {}", place.fmt_with_ctx(ctx)),
             },
+            Rvalue::RawPtr(place, kind) => match kind {
+                RefKind::Shared => format!("&raw const {}", place.fmt_with_ctx(ctx)),
+                RefKind::Mut => format!("&raw mut {}", place.fmt_with_ctx(ctx)),
+            },
             Rvalue::UnaryOp(unop, x) => {
                 format!("{}({})", unop, x.fmt_with_ctx(ctx))
             }
@@ -251,16 +288,79 @@ impl Rvalue {
                         };
                         format!("{} {{ {} }}", variant, fields.join(", "))
                     }
+                    AggregateKind::StructUpdate {
+                        type_id,
+                        base,
+                        updated_fields,
+                    } => {
+                        assert!(ops.is_empty());
+                        let mut fields: Vec<String> = updated_fields
+                            .iter()
+                            .map(|(field_id, op)| {
+                                let field_name = ctx.format_object((*type_id, None, *field_id));
+                                format!("{}: {}", field_name, op.fmt_with_ctx(ctx))
+                            })
+                            .collect();
+                        fields.push(format!("..{}", base.fmt_with_ctx(ctx)));
+                        format!(
+                            "{} {{ {} }}",
+                            ctx.format_object(*type_id),
+                            fields.join(", ")
+                        )
+                    }
+                    AggregateKind::Union(def_id, field_id, _, _, _) => {
+                        assert!(ops.len() == 1);
+                        let field_name = ctx.format_object((*def_id, None, *field_id));
+                        format!(
+                            "{} {{ {}: {} }}",
+                            ctx.format_object(*def_id),
+                            field_name,
+                            ops_s[0]
+                        )
+                    }
                     AggregateKind::Array(_, _) => {
                         format!("[{}]", ops_s.join(", "))
                     }
-                    AggregateKind::Range(_) => {
-                        format!("@Range[{}]", ops_s.join(", "))
+                    AggregateKind::Repeat(_, op, cg) => {
+                        assert!(ops.is_empty());
+                        format!("[{}; {}]", op.fmt_with_ctx(ctx), cg.fmt_with_ctx(ctx))
+                    }
+                    AggregateKind::Range(aty, _) => {
+                        let fields: Vec<String> = assumed::range_field_names(*aty)
+                            .iter()
+                            .zip(ops_s.iter())
+                            .map(|(name, op)| format!("{name}: {op}"))
+                            .collect();
+                        format!("@{} {{ {} }}", aty.variant_name(), fields.join(", "))
+                    }
+                    AggregateKind::PhantomData(_) => {
+                        assert!(ops.is_empty());
+                        "@PhantomData".to_string()
+                    }
+                    AggregateKind::Closure(fid, kind, _) => {
+                        format!(
+                            "@Closure<{}, {}>({})",
+                            fid.to_pretty_string(),
+                            kind.variant_name(),
+                            ops_s.join(", ")
+                        )
+                    }
+                    AggregateKind::Generator(fid, _) => {
+                        format!(
+                            "@Generator<{}>({})",
+                            fid.to_pretty_string(),
+                            ops_s.join(", ")
+                        )
+                    }
+                    AggregateKind::RawPtr(_, _) => {
+                        assert!(ops.len() == 2);
+                        format!("raw_ptr_from_parts({})", ops_s.join(", "))
                     }
                 }
             }
             Rvalue::Global(gid) => ctx.format_object(*gid),
             Rvalue::Len(place, ..) => format!("len({})", place.fmt_with_ctx(ctx)),
+            Rvalue::FnPtr(fid, _, _) => format!("@FnPtr<{}>", fid.to_pretty_string()),
         }
     }
 
@@ -276,6 +376,18 @@ impl std::fmt::Display for Rvalue {
     }
 }
 
+/// The serialized form of an [OperandConstantValue]: a literal, or a const
+/// generic variable (`{"Var": id}`).
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum SerializedConstantValue {
+    Literal(Literal),
+    Var {
+        #[serde(rename = "Var")]
+        id: ConstGenericVarId::Id,
+    },
+}
+
 impl Serialize for OperandConstantValue {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -284,12 +396,47 @@ impl Serialize for OperandConstantValue {
         match self {
             // [OperandConstantValue] exists only to handle temporary cases inherited from the MIR:
             // for the final (U)LLBC format, we simply export the underlying constant value.
-            OperandConstantValue::Literal(cv) => cv.serialize(serializer),
-            _ => unreachable!("unexpected `{:?}`: `OperandConstantValue` fields other than `ConstantValue` are temporary and should not occur in serialized LLBC", self),
+            // The const generic variables (`N` in `x + N`) can't be eliminated.
+            OperandConstantValue::Literal(cv) => {
+                SerializedConstantValue::Literal(cv.clone()).serialize(serializer)
+            }
+            OperandConstantValue::Var(id) => {
+                SerializedConstantValue::Var { id: *id }.serialize(serializer)
+            }
+            _ => unreachable!("unexpected `{:?}`: `OperandConstantValue` fields other than `ConstantValue` and `Var` are temporary and should not occur in serialized LLBC", self),
         }
     }
 }
 
+/// We only serialize the literals and the const generic variables (see the
+/// serializer), so we only need to read those back.
+impl<'de> Deserialize<'de> for OperandConstantValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match SerializedConstantValue::deserialize(deserializer)? {
+            SerializedConstantValue::Literal(lit) => OperandConstantValue::Literal(lit),
+            SerializedConstantValue::Var { id } => OperandConstantValue::Var(id),
+        })
+    }
+}
+
+impl JsonSchema for OperandConstantValue {
+    fn schema_name() -> String {
+        "OperandConstantValue".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    // We only serialize the literals and the variables (see the serializer)
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        gen.subschema_for::<SerializedConstantValue>()
+    }
+}
+
 // Derive two implementations at once: one which uses shared borrows, and one
 // which uses mutable borrows.
 // Generates the traits: `SharedExprVisitor` and `MutExprVisitor`.
@@ -320,7 +467,11 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
             ProjectionElem::DerefPtrUnique => self.visit_deref_ptr_unique(),
             ProjectionElem::DerefPtrNonNull => self.visit_deref_ptr_non_null(),
             ProjectionElem::Field(proj_kind, fid) => self.visit_projection_field(proj_kind, fid),
+            ProjectionElem::UnionField(adt_id, fid) => {
+                self.visit_projection_union_field(adt_id, fid)
+            }
             ProjectionElem::Index(i, _) => self.visit_var_id(i),
+            ProjectionElem::ConstantIndex { .. } | ProjectionElem::Subslice { .. } => (),
         }
     }
 
@@ -334,6 +485,9 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
     fn visit_deref_ptr_unique(&mut self) {}
     fn visit_deref_ptr_non_null(&mut self) {}
     fn visit_projection_field(&mut self, _: &FieldProjKind, _: &FieldId::Id) {}
+    fn visit_projection_union_field(&mut self, adt_id: &TypeDeclId::Id, _: &FieldId::Id) {
+        self.visit_type_decl_id(adt_id)
+    }
 
     fn default_visit_operand(&mut self, o: &Operand) {
         match o {
@@ -365,6 +519,7 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
         match op {
             Literal(lit) => self.visit_literal(lit),
             Adt(oid, ops) => self.visit_operand_const_adt(oid, ops),
+            Array(ops) => self.visit_operand_const_array(ops),
             ConstantId(id) => self.visit_global_decl_id(id),
             StaticId(id) => self.visit_global_decl_id(id),
             Var(id) => self.visit_const_generic_var_id(id),
@@ -381,16 +536,24 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
         }
     }
 
+    fn visit_operand_const_array(&mut self, ops: &Vec<OperandConstantValue>) {
+        for op in ops {
+            self.visit_operand_constant_value(op)
+        }
+    }
+
     fn default_visit_rvalue(&mut self, rv: &Rvalue) {
         match rv {
             Rvalue::Use(o) => self.visit_use(o),
             Rvalue::Ref(p, bkind) => self.visit_ref(p, bkind),
+            Rvalue::RawPtr(p, kind) => self.visit_raw_ptr(p, kind),
             Rvalue::UnaryOp(op, o1) => self.visit_unary_op(op, o1),
             Rvalue::BinaryOp(op, o1, o2) => self.visit_binary_op(op, o1, o2),
             Rvalue::Discriminant(p) => self.visit_discriminant(p),
             Rvalue::Aggregate(kind, ops) => self.visit_aggregate(kind, ops),
             Rvalue::Global(gid) => self.visit_global(gid),
             Rvalue::Len(p, ty, cg) => self.visit_len(p, ty, cg),
+            Rvalue::FnPtr(fid, tys, cgs) => self.visit_fn_ptr(fid, tys, cgs),
         }
     }
 
@@ -406,6 +569,10 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
         self.visit_place(p)
     }
 
+    fn visit_raw_ptr(&mut self, p: &Place, _: &RefKind) {
+        self.visit_place(p)
+    }
+
     fn visit_unary_op(&mut self, _: &UnOp, o1: &Operand) {
         self.visit_operand(o1)
     }
@@ -433,8 +600,12 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
         match ak {
             Tuple => (),
             Option(_, ty) => self.visit_ty(ty),
-            Range(ty) => self.visit_ty(ty),
-            Adt(adt_id, _, _, tys, cgs) => {
+            Range(_, tys) => {
+                for ty in tys {
+                    self.visit_ty(ty);
+                }
+            }
+            Adt(adt_id, _, _, tys, cgs) | Union(adt_id, _, _, tys, cgs) => {
                 self.visit_type_decl_id(adt_id);
                 for ty in tys {
                     self.visit_ty(ty);
@@ -443,10 +614,33 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
                     self.visit_const_generic(cg);
                 }
             }
+            StructUpdate {
+                type_id,
+                base,
+                updated_fields,
+            } => {
+                self.visit_type_decl_id(type_id);
+                self.visit_operand(base);
+                for (_, op) in updated_fields {
+                    self.visit_operand(op);
+                }
+            }
             Array(ty, cg) => {
                 self.visit_ty(ty);
                 self.visit_const_generic(cg);
             }
+            Repeat(ty, op, cg) => {
+                self.visit_ty(ty);
+                self.visit_operand(op);
+                self.visit_const_generic(cg);
+            }
+            Closure(fid, _, tys) | Generator(fid, tys) => {
+                self.visit_fun_decl_id(fid);
+                for ty in tys {
+                    self.visit_ty(ty);
+                }
+            }
+            RawPtr(ty, _) | PhantomData(ty) => self.visit_ty(ty),
         }
     }
 
@@ -456,6 +650,16 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
         self.visit_place(p)
     }
 
+    fn visit_fn_ptr(&mut self, fid: &FunDeclId::Id, tys: &Vec<ETy>, cgs: &Vec<ConstGeneric>) {
+        self.visit_fun_decl_id(fid);
+        for ty in tys {
+            self.visit_ty(ty);
+        }
+        for cg in cgs {
+            self.visit_const_generic(cg);
+        }
+    }
+
     fn visit_call(&mut self, c: &Call) {
         self.visit_fun_id(&c.func);
         // We ignore the regions which are erased
@@ -475,11 +679,98 @@ pub trait ExprVisitor: crate::types::TypeVisitor {
         match fun_id {
             FunId::Regular(fid) => self.visit_fun_decl_id(fid),
             FunId::Assumed(aid) => self.visit_assumed_fun_id(aid),
+            FunId::Virtual(trait_name, method_name, vtable_index) => {
+                self.visit_virtual_fun_id(trait_name, method_name, vtable_index)
+            }
+            FunId::Ptr(op) => self.visit_operand(op),
         }
     }
 
     fn visit_fun_decl_id(&mut self, fid: &FunDeclId::Id) {}
     fn visit_assumed_fun_id(&mut self, fid: &AssumedFunId) {}
+    fn visit_virtual_fun_id(&mut self, trait_name: &Name, method_name: &str, vtable_index: &usize) {}
 }
 
 } // make_generic_in_borrows
+
+#[cfg(test)]
+mod tests {
+    use crate::expressions::*;
+    use crate::types::{ConstGeneric, ConstGenericVarId, FieldId, Ty, TypeDeclId};
+    use crate::values::VarId;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    fn hash<T: Hash>(x: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Check that the equality and the hash of the places are structural
+    #[test]
+    fn test_place_eq_hash() {
+        let mk_place = || Place {
+            var_id: VarId::Id::new(0),
+            projection: vec![ProjectionElem::Deref],
+        };
+        let p0 = mk_place();
+        let p1 = mk_place();
+        assert_eq!(p0, p1);
+        assert_eq!(hash(&p0), hash(&p1));
+
+        let mut set = HashSet::new();
+        assert!(set.insert(p0));
+        assert!(!set.insert(p1));
+        assert!(set.insert(Place::new(VarId::Id::new(0))));
+        assert_eq!(set.len(), 2);
+    }
+
+    struct CollectVars(Vec<VarId::Id>);
+
+    impl crate::types::SharedTypeVisitor for CollectVars {}
+    impl SharedExprVisitor for CollectVars {
+        fn visit_var_id(&mut self, id: &VarId::Id) {
+            self.0.push(*id)
+        }
+    }
+
+    /// `Foo { x: move b, ..move a }`: the base is formatted and visited
+    #[test]
+    fn test_struct_update() {
+        let rv = Rvalue::Aggregate(
+            AggregateKind::StructUpdate {
+                type_id: TypeDeclId::ZERO,
+                base: Operand::Move(Place::new(VarId::Id::new(1))),
+                updated_fields: vec![(FieldId::ZERO, Operand::Move(Place::new(VarId::Id::new(2))))],
+            },
+            Vec::new(),
+        );
+        let s = rv.fmt_with_ctx(&crate::values::DummyFormatter {});
+        assert!(s.ends_with("{ @field0: move (@2), ..move (@1) }"), "{}", s);
+
+        let mut visitor = CollectVars(Vec::new());
+        visitor.visit_rvalue(&rv);
+        assert_eq!(visitor.0, vec![VarId::Id::new(1), VarId::Id::new(2)]);
+    }
+
+    /// `[copy x; N]`: the repeated operand is formatted and visited
+    #[test]
+    fn test_repeat() {
+        let rv = Rvalue::Aggregate(
+            AggregateKind::Repeat(
+                Ty::mk_unit(),
+                Operand::Copy(Place::new(VarId::Id::new(1))),
+                ConstGeneric::Var(ConstGenericVarId::ZERO),
+            ),
+            Vec::new(),
+        );
+        let s = rv.fmt_with_ctx(&crate::values::DummyFormatter {});
+        assert_eq!(s, "[copy (@1); @Const0]");
+
+        let mut visitor = CollectVars(Vec::new());
+        visitor.visit_rvalue(&rv);
+        assert_eq!(visitor.0, vec![VarId::Id::new(1)]);
+    }
+}
@@ -56,8 +56,8 @@ fn extract_operand_global_var<F: FnMut(ETy) -> VarId::Id>(
 
     let var = match *c {
         OperandConstantValue::Literal(_) | OperandConstantValue::Var(..) => return,
-        OperandConstantValue::Adt(_, _) => {
-            unreachable!("Constant ADTs should have been replaced by now")
+        OperandConstantValue::Adt(_, _) | OperandConstantValue::Array(_) => {
+            unreachable!("Constant ADTs and arrays should have been replaced by now")
         }
         OperandConstantValue::ConstantId(global_id) => {
             let var = make_new_var(ty.clone());
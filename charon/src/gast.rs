@@ -1,34 +1,45 @@
 //! Definitions common to [crate::ullbc_ast] and [crate::llbc_ast]
 #![allow(dead_code)]
 
+use crate::expressions::OperandConstantValue;
 pub use crate::expressions::{Operand, Place};
 pub use crate::gast_utils::*;
 use crate::meta::Meta;
 use crate::names::FunName;
 use crate::names::GlobalName;
+use crate::names::ModuleName;
+use crate::names::TraitName;
 use crate::regions_hierarchy::RegionGroups;
 pub use crate::types::GlobalDeclId;
+pub use crate::types::TraitDeclId;
 use crate::types::*;
+use crate::types_interner::InternedTy;
 use crate::values::*;
 use macros::generate_index_type;
 use macros::{EnumAsGetters, EnumIsA, VariantName};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 // TODO: move this definition
 pub static TAB_INCR: &str = "    ";
 
 generate_index_type!(FunDeclId);
+generate_index_type!(ModuleDeclId);
+
+// The crate root is always the module with id 0
+pub static ROOT_MODULE_ID: ModuleDeclId::Id = ModuleDeclId::ZERO;
 
 /// A variable
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Var {
     /// Unique index identifying the variable
     pub index: VarId::Id,
     /// Variable name - may be `None` if the variable was introduced by Rust
     /// through desugaring.
     pub name: Option<String>,
-    /// The variable type
-    pub ty: ETy,
+    /// The variable type (interned: the locals of a body often share the
+    /// same types)
+    pub ty: InternedTy,
 }
 
 /// A function signature.
@@ -37,7 +48,7 @@ pub struct Var {
 /// We need the functions' signatures *with* the region parameters in order
 /// to correctly abstract those functions (number and signature of the backward
 /// functions) - we only use regions for this purpose.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FunSig {
     pub region_params: RegionVarId::Vector<RegionVar>,
     /// The region parameters contain early bound and late bound parameters.
@@ -54,6 +65,8 @@ pub struct FunSig {
     pub num_early_bound_regions: usize,
     pub type_params: TypeVarId::Vector<TypeVar>,
     pub const_generic_params: ConstGenericVarId::Vector<ConstGenericVar>,
+    /// The where clauses
+    pub predicates: Vec<Predicate>,
     pub inputs: Vec<RTy>,
     pub output: RTy,
     /// The lifetime's hierarchy between the different regions.
@@ -67,7 +80,7 @@ pub struct FunSig {
 /// An expression body.
 /// TODO: arg_count should be stored in GFunDecl below. But then,
 ///       the print is obfuscated and Aeneas may need some refactoring.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GExprBody<T: std::fmt::Debug + Clone + Serialize> {
     pub meta: Meta,
     /// The number of local variables used for the input arguments.
@@ -79,10 +92,82 @@ pub struct GExprBody<T: std::fmt::Debug + Clone + Serialize> {
     /// - the remaining locals, used for the intermediate computations
     pub locals: VarId::Vector<Var>,
     pub body: T,
+    /// The liveness information, given for every block (in the order of the
+    /// blocks). This is only computed for the ULLBC bodies, and only if the
+    /// user asked for it (see [crate::liveness]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub liveness: Option<Vec<BlockLiveness>>,
+}
+
+/// The local variables which are live at the entry and at the exit of a
+/// block.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BlockLiveness {
+    pub live_in: Vec<VarId::Id>,
+    pub live_out: Vec<VarId::Id>,
+}
+
+/// For the methods defined in trait impls: the implemented method.
+/// ```text
+/// impl Iterator for MyList {
+///     type Item = u32;
+///     fn next(&mut self) -> Option<u32> { ... }
+/// }
+/// ```
+/// For `next`, the trait is `Iterator`, the implementing type is `MyList`
+/// and the method name is `"next"`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TraitImplInfo {
+    pub trait_decl_id: TraitDeclId::Id,
+    /// The type for which the trait is implemented (the `Self` type of the
+    /// impl). It may refer to the type parameters of the impl, which are
+    /// also parameters of the function (see [crate::types::FunSig]).
+    pub impl_type: ETy,
+    /// The name of the method in the trait
+    pub method_name: String,
+}
+
+/// How a closure captures a variable.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIsA, VariantName, Serialize, Deserialize, JsonSchema,
+)]
+pub enum CaptureMode {
+    /// The closure state contains a shared borrow of the variable
+    ByRef,
+    /// The closure state contains a mutable (or unique) borrow of the variable
+    ByMutRef,
+    /// The variable is moved (or copied) into the closure state
+    ByValue,
+}
+
+/// A variable captured by a closure. The captures are given in the order of
+/// the fields of the closure state (the first argument of the closure body).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CaptureInfo {
+    /// The captured place, as printed by rustc: for instance `x`, or `x__f`
+    /// if the closure only captures the field `f` of `x`
+    pub name: String,
+    /// The type of the captured place (not the type of the borrow if the
+    /// place is captured by reference)
+    pub ty: ETy,
+    pub mode: CaptureMode,
+}
+
+/// An attribute of a declaration, for instance `#[my_tool::verified]`. We
+/// only keep the attributes which are not interpreted by the compiler: the
+/// tool attributes (see [crate::translate_functions_to_ullbc]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Attribute {
+    /// The path of the attribute: `["my_tool", "verified"]`
+    pub path: Vec<String>,
+    /// The arguments of the attribute, as written in the source code (for
+    /// instance `(x > 0)` for `#[my_tool::requires(x > 0)]`). Is empty if the
+    /// attribute has no arguments.
+    pub args: String,
 }
 
 /// A function definition
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GFunDecl<T: std::fmt::Debug + Clone + Serialize> {
     pub def_id: FunDeclId::Id,
     /// The meta data associated with the declaration.
@@ -95,10 +180,98 @@ pub struct GFunDecl<T: std::fmt::Debug + Clone + Serialize> {
     /// Opaque functions are: external functions, or local functions tagged
     /// as opaque.
     pub body: Option<GExprBody<T>>,
+    /// If the function is a known compiler built-in (like `core::mem::size_of`),
+    /// the kind of built-in. The backends can use this to emit specialized
+    /// rules rather than treating the calls as calls to opaque functions.
+    pub builtin: Option<BuiltinFnKind>,
+    /// The calling convention (ABI) of the function.
+    pub calling_convention: CallingConvention,
+    /// `true` if the function is a `const fn`. The bodies of the `const fn`
+    /// are always translated from the promoted MIR, whatever the MIR level
+    /// we extract (see [crate::get_mir::function_mir_level]).
+    pub is_const_fn: bool,
+    /// `true` if the function is declared `unsafe`.
+    pub is_unsafe: bool,
+    /// `true` if the function (transitively) calls an unsafe function, for
+    /// instance through a safe wrapper which uses an `unsafe` block. This is
+    /// computed once all the functions have been translated (see
+    /// [crate::unsafe_propagation]).
+    pub calls_unsafe: bool,
+    /// If the function is a method defined in an impl block
+    /// (`impl Foo { fn f(&self) { ... } }`), the type for which the block is
+    /// defined. This allows to quickly enumerate the methods of a type.
+    /// The type may be external, in the case of trait impls
+    /// (`impl Trait for Vec<u32>`): it is then either an assumed type or an
+    /// opaque type declaration (see [TypeDecl::local_methods]).
+    /// Is `None` if the function is not a method, or if the type of the impl
+    /// is not an ADT (e.g., `impl Trait for u32`).
+    pub self_type: Option<TypeId>,
+    /// If the function is a method of a trait (see [TraitDecl]) or a method
+    /// defined in a trait impl, the trait.
+    pub trait_id: Option<TraitDeclId::Id>,
+    /// If the function is a method defined in a trait impl, the method it
+    /// implements.
+    pub trait_impl_info: Option<TraitImplInfo>,
+    /// `true` if the function is a provided method of a trait, that is a
+    /// method with a default implementation (see [TraitDecl::default_methods]).
+    pub is_trait_default: bool,
+    /// If the function is a method of a trait impl which overrides the default
+    /// implementation of a (local) trait, the default implementation.
+    pub overrides_default: Option<FunDeclId::Id>,
+    /// If the function is the body of a closure, the captured variables.
+    /// This is empty for the other functions.
+    pub captures: Vec<CaptureInfo>,
+    /// `true` if the function is the `drop` method of an implementation of
+    /// the `Drop` trait. The drops of the values of the implementing type
+    /// call this function (see the `Drop` terminator of the ULLBC).
+    pub implemented_drop: bool,
+    /// The user attributes of the function (see [Attribute]).
+    pub attributes: Vec<Attribute>,
+    /// `true` if the declaration appears explicitly in the crate, `false` if
+    /// we translated it because other declarations refer to it.
+    pub is_root: bool,
+    /// A name-based identifier, which (contrary to the numeric ids) doesn't
+    /// change when we add or remove unrelated declarations. Only computed
+    /// with `--stable-ids` (see [crate::stable_ids]).
+    pub stable_id: Option<String>,
+}
+
+/// A function which is known to the compiler and has guaranteed semantics.
+/// See [crate::assumed::get_builtin_fn_kind_from_name].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIsA, VariantName, Serialize, Deserialize, JsonSchema,
+)]
+pub enum BuiltinFnKind {
+    /// `core::mem::size_of`
+    SizeOf,
+    /// `core::mem::align_of`
+    AlignOf,
+    /// `core::mem::forget`
+    Forget,
+    /// `core::mem::drop`
+    Drop,
+    /// `core::mem::manually_drop::ManuallyDrop::new`
+    ManuallyDrop,
+}
+
+/// The calling convention of a function. The functions declared in `extern`
+/// blocks (FFI functions) typically use the `C` calling convention.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIsA, VariantName, Serialize, Deserialize, JsonSchema,
+)]
+pub enum CallingConvention {
+    /// The (default) Rust calling convention
+    Rust,
+    /// `extern "C"`
+    C,
+    /// `extern "cdecl"`
+    Cdecl,
+    /// `extern "system"`
+    System,
 }
 
 /// A global variable definition, either opaque or transparent.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GGlobalDecl<T: std::fmt::Debug + Clone + Serialize> {
     pub def_id: GlobalDeclId::Id,
     /// The meta data associated with the declaration.
@@ -106,10 +279,117 @@ pub struct GGlobalDecl<T: std::fmt::Debug + Clone + Serialize> {
     pub name: GlobalName,
     pub ty: ETy,
     pub body: Option<GExprBody<T>>,
+    /// If the global is the value given by a trait impl to an associated
+    /// constant (`impl HasId for Foo { const ID: u32 = 0; }`), the trait
+    /// which declares the constant (see [AssocConstDecl]).
+    pub trait_id: Option<TraitDeclId::Id>,
+    /// `true` if the declaration appears explicitly in the crate, `false` if
+    /// we translated it because other declarations refer to it.
+    pub is_root: bool,
+    /// A name-based identifier, which (contrary to the numeric ids) doesn't
+    /// change when we add or remove unrelated declarations. Only computed
+    /// with `--stable-ids` (see [crate::stable_ids]).
+    pub stable_id: Option<String>,
+}
+
+/// A (local) module, and the declarations it contains. The modules form a
+/// tree whose root is the crate ([ROOT_MODULE_ID]).
+///
+/// Note that we only list the declarations which were translated: the
+/// declarations which were ignored (because they are unsupported, etc.) don't
+/// appear here. The methods are listed in the module containing their impl
+/// block.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ModuleDecl {
+    pub def_id: ModuleDeclId::Id,
+    pub name: ModuleName,
+    pub submodules: Vec<ModuleDeclId::Id>,
+    pub types: Vec<TypeDeclId::Id>,
+    pub funs: Vec<FunDeclId::Id>,
+    pub globals: Vec<GlobalDeclId::Id>,
 }
 
+pub type ModuleDecls = ModuleDeclId::Map<ModuleDecl>;
+
+/// An associated constant declared in a trait:
+/// ```text
+/// trait HasId {
+///     const ID: u32;
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AssocConstDecl {
+    pub trait_id: TraitDeclId::Id,
+    pub name: String,
+    pub ty: ETy,
+    /// The default value, if the trait provides one
+    pub default_value: Option<OperandConstantValue>,
+}
+
+/// A trait declaration:
+/// ```text
+/// trait Shape: Named {
+///     fn area(&self) -> u32; // Required method
+///     fn double_area(&self) -> u32 { 2 * self.area() } // Provided method
+/// }
+/// ```
+///
+/// The methods are translated as functions (whose [GFunDecl::trait_id] is the
+/// trait): the required methods don't have a body.
+///
+/// Note that we only translate the declarations of the local traits.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TraitDecl {
+    pub def_id: TraitDeclId::Id,
+    pub meta: Meta,
+    pub name: TraitName,
+    pub region_params: RegionVarId::Vector<RegionVar>,
+    /// The type parameters. The first parameter is the (implicit) `Self`
+    /// type: the methods of the trait refer to it.
+    pub type_params: TypeVarId::Vector<TypeVar>,
+    pub const_generic_params: ConstGenericVarId::Vector<ConstGenericVar>,
+    pub required_methods: Vec<FunDeclId::Id>,
+    /// The provided methods, that is the methods with a default
+    /// implementation. The impls may override them (see
+    /// [GFunDecl::overrides_default]).
+    pub default_methods: Vec<FunDeclId::Id>,
+    /// The parent traits (`Named` in the example above)
+    pub supertrait_bounds: Vec<RTraitRef>,
+    /// The (local) impls of the trait which apply to an infinite set of types
+    /// (see [BlanketImpl]).
+    pub blanket_impls: Vec<BlanketImpl>,
+}
+
+/// An impl of a trait which applies to an infinite set of types: the impls
+/// which are generic over types, and the impls for trait objects.
+/// ```text
+/// impl<T: Debug> Describe for Wrapper<T> { ... }
+/// impl<T: Display> Describe for T { ... }
+/// impl Describe for dyn Display { ... }
+/// ```
+/// The impl is universally quantified over its parameters: it implements the
+/// trait for all the instantiations of [BlanketImpl::impl_type] which satisfy
+/// the bounds.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BlanketImpl {
+    pub meta: Meta,
+    /// The implemented trait
+    pub trait_id: TraitDeclId::Id,
+    pub region_params: RegionVarId::Vector<RegionVar>,
+    pub type_params: TypeVarId::Vector<TypeVar>,
+    pub const_generic_params: ConstGenericVarId::Vector<ConstGenericVar>,
+    /// The type for which the trait is implemented (the `Self` type)
+    pub impl_type: RTy,
+    /// The instantiation of the trait parameters (without `Self`)
+    pub trait_ref: RTraitRef,
+    /// The where clauses of the impl (`T: Debug` in the first example above)
+    pub bounds: Vec<Predicate>,
+}
+
+pub type TraitDecls = TraitDeclId::Map<TraitDecl>;
+
 /// A function identifier. See [crate::ullbc_ast::Terminator]
-#[derive(Debug, Clone, EnumIsA, EnumAsGetters, VariantName, Serialize)]
+#[derive(Debug, Clone, EnumIsA, EnumAsGetters, VariantName, Serialize, Deserialize, JsonSchema)]
 pub enum FunId {
     /// A "regular" function (function local to the crate, external function
     /// not treated as a primitive one).
@@ -118,11 +398,24 @@ pub enum FunId {
     /// `alloc::boxed::Box::new`).
     /// TODO: rename to "Primitive"
     Assumed(AssumedFunId),
+    /// A call through a trait object (virtual dispatch), for instance a call
+    /// to `f` where `f: &dyn Fn(u32) -> u32`.
+    ///
+    /// We store the name of the trait, the name of the method and the index
+    /// of the method in the vtable. The first argument of the call is the
+    /// trait object.
+    Virtual(TraitName, String, usize),
+    /// An indirect call through a function pointer, for instance a call to
+    /// `f` where `f: fn(u32) -> u32`. The operand evaluates to the function
+    /// pointer.
+    Ptr(Operand),
 }
 
 /// An assumed function identifier, identifying a function coming from a
 /// standard library.
-#[derive(Debug, Clone, Copy, EnumIsA, EnumAsGetters, VariantName, Serialize)]
+#[derive(
+    Debug, Clone, Copy, EnumIsA, EnumAsGetters, VariantName, Serialize, Deserialize, JsonSchema,
+)]
 pub enum AssumedFunId {
     /// `core::mem::replace`
     Replace,
@@ -218,7 +511,7 @@ pub enum AssumedFunId {
 }
 
 /// TODO: factor out with [Rvalue]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Call {
     pub func: FunId,
     /// Technically this is useless, but we still keep it because we might
@@ -6,6 +6,7 @@ use crate::formatter::Formatter;
 use crate::gast::*;
 use crate::names::Name;
 use crate::types::*;
+use crate::types_interner::InternedTy;
 use crate::values::*;
 use serde::Serialize;
 use std::cmp::max;
@@ -44,12 +45,18 @@ pub fn make_locals_generator(locals: &mut VarId::Vector<Var>) -> impl FnMut(ETy)
         locals.push_back(Var {
             index: id,
             name: None,
-            ty,
+            ty: InternedTy::new(ty),
         });
         id
     }
 }
 
+impl FunDeclId::Id {
+    pub fn to_pretty_string(&self) -> String {
+        format!("@Fun{self}")
+    }
+}
+
 impl std::string::ToString for Var {
     fn to_string(&self) -> String {
         let id = self.index.to_pretty_string();
@@ -65,11 +72,42 @@ impl std::string::ToString for Var {
 impl VarId::Vector<Var> {
     pub fn fresh_var(&mut self, name: Option<String>, ty: ETy) -> VarId::Id {
         let index = VarId::Id::new(self.len());
-        self.push_back(Var { index, name, ty });
+        self.push_back(Var {
+            index,
+            name,
+            ty: InternedTy::new(ty),
+        });
         index
     }
 }
 
+impl CallingConvention {
+    /// Parse the name of an ABI (as written in `extern "..."`).
+    ///
+    /// The ABIs which are internal to Rust (`rust-call`, which is used by the
+    /// closures, or `rust-intrinsic`, etc.) are mapped to [CallingConvention::Rust].
+    pub fn from_abi_name(name: &str) -> Option<Self> {
+        match name {
+            "Rust" | "rust-call" | "rust-intrinsic" | "platform-intrinsic" => {
+                Some(CallingConvention::Rust)
+            }
+            "C" => Some(CallingConvention::C),
+            "cdecl" => Some(CallingConvention::Cdecl),
+            "system" => Some(CallingConvention::System),
+            _ => None,
+        }
+    }
+
+    pub fn to_abi_name(&self) -> &'static str {
+        match self {
+            CallingConvention::Rust => "Rust",
+            CallingConvention::C => "C",
+            CallingConvention::Cdecl => "cdecl",
+            CallingConvention::System => "system",
+        }
+    }
+}
+
 impl Var {
     /// Substitute the region parameters and type variables and return
     /// the resulting variable
@@ -77,7 +115,7 @@ impl Var {
         Var {
             index: self.index,
             name: self.name.clone(),
-            ty: self.ty.substitute_types(subst, cgsubst),
+            ty: InternedTy::new(self.ty.substitute_types(subst, cgsubst)),
         }
     }
 }
@@ -123,6 +161,10 @@ where
         FunId::Assumed(assumed) => {
             format!("@{}{rt_args}", assumed.variant_name())
         }
+        FunId::Virtual(trait_name, method_name, _) => {
+            format!("@dyn({trait_name}::{method_name}){rt_args}")
+        }
+        FunId::Ptr(op) => format!("({})", op.fmt_with_ctx(ctx)),
     };
 
     format!("{f}({args})")
@@ -206,7 +248,11 @@ impl FunSig {
     {
         // Type parameters
         let params = {
-            let regions: Vec<String> = self.region_params.iter().map(|x| x.to_string()).collect();
+            let regions: Vec<String> = self
+                .region_params
+                .iter()
+                .map(|x| x.lifetime_display_name())
+                .collect();
             let mut types: Vec<String> = self.type_params.iter().map(|x| x.to_string()).collect();
             let mut cgs: Vec<String> = self
                 .const_generic_params
@@ -265,7 +311,11 @@ impl<'a, GD> Formatter<TypeVarId::Id> for FunSigFormatter<'a, GD> {
 
 impl<'a, GD> Formatter<RegionVarId::Id> for FunSigFormatter<'a, GD> {
     fn format_object(&self, id: RegionVarId::Id) -> String {
-        self.sig.region_params.get(id).unwrap().to_string()
+        self.sig
+            .region_params
+            .get(id)
+            .unwrap()
+            .lifetime_display_name()
     }
 }
 
@@ -321,6 +371,14 @@ impl FunSig {
 }
 
 impl<T: Debug + Clone + Serialize> GFunDecl<T> {
+    /// Check if the function has an attribute, given by its path (for
+    /// instance `my_tool::verified`).
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attr| attr.path.join("::") == name)
+    }
+
     /// This is an auxiliary function for printing definitions. One may wonder
     /// why we require a formatter to format, for instance, (type) var ids,
     /// because the function definition already has the information to print
@@ -409,11 +467,17 @@ impl<T: Debug + Clone + Serialize> GFunDecl<T> {
             format!(" -> {}", ret_ty.fmt_with_ctx(sig_ctx))
         };
 
+        // Calling convention
+        let abi = match self.calling_convention {
+            CallingConvention::Rust => "".to_string(),
+            cc => format!("extern \"{}\" ", cc.to_abi_name()),
+        };
+
         // Case disjunction on the presence of a body (transparent/opaque definition)
         match &self.body {
             Option::None => {
                 // Put everything together
-                format!("{tab}fn {name}{params}({args}){ret_ty}")
+                format!("{tab}{abi}fn {name}{params}({args}){ret_ty}")
             }
             Option::Some(body) => {
                 // Body
@@ -421,7 +485,7 @@ impl<T: Debug + Clone + Serialize> GFunDecl<T> {
                 let body = body.fmt_with_ctx(&body_tab, body_ctx);
 
                 // Put everything together
-                format!("{tab}fn {name}{params}({args}){ret_ty} {{\n{body}\n{tab}}}",)
+                format!("{tab}{abi}fn {name}{params}({args}){ret_ty} {{\n{body}\n{tab}}}",)
             }
         }
     }
@@ -563,21 +627,22 @@ impl<'ctx, FD, GD> Formatter<(TypeDeclId::Id, VariantId::Id)> for GAstFormatter<
         let (def_id, variant_id) = id;
         let ctx = self.type_context;
         // The definition may not be available yet, especially if we print-debug
-        // while translating the crate
-        match ctx.get(def_id) {
-            Option::None => format!(
-                "{}::{}",
-                def_id.to_pretty_string(),
-                variant_id.to_pretty_string()
-            ),
-            Option::Some(def) => {
-                let variants = def.kind.as_enum();
+        // while translating the crate. Also, the external enumerations (like
+        // `Result`) are opaque: we don't know the names of their variants.
+        match ctx.get(def_id).map(|def| (def, &def.kind)) {
+            Option::Some((def, TypeDeclKind::Enum(variants))) => {
                 let mut name = def.name.to_string();
                 let variant_name = &variants.get(variant_id).unwrap().name;
                 name.push_str("::");
                 name.push_str(variant_name);
                 name
             }
+            Option::Some((def, _)) => format!("{}::{}", def.name, variant_id.to_pretty_string()),
+            Option::None => format!(
+                "{}::{}",
+                def_id.to_pretty_string(),
+                variant_id.to_pretty_string()
+            ),
         }
     }
 }
@@ -590,8 +655,8 @@ impl<'ctx, FD, GD> Formatter<(TypeDeclId::Id, Option<VariantId::Id>, FieldId::Id
         let (def_id, opt_variant_id, field_id) = id;
         let ctx = self.type_context;
         // The definition may not be available yet, especially if we print-debug
-        // while translating the crate
-        match ctx.get(def_id) {
+        // while translating the crate. Also, the external types are opaque.
+        match ctx.get(def_id).filter(|def| !def.kind.is_opaque()) {
             Option::None => match opt_variant_id {
                 Option::None => format!(
                     "{}::{}",
@@ -618,7 +683,7 @@ impl<'ctx, FD, GD> Formatter<(TypeDeclId::Id, Option<VariantId::Id>, FieldId::Id
                         Option::None => field_id.to_string(),
                     }
                 }
-                (TypeDeclKind::Struct(fields), None) => {
+                (TypeDeclKind::Struct(fields), None) | (TypeDeclKind::Union(fields), None) => {
                     let field = fields.get(field_id).unwrap();
                     match &field.name {
                         Option::Some(name) => name.clone(),
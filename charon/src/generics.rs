@@ -80,8 +80,13 @@ where
 
 /// Function used for sanity checks: check the constraints given by a definition's
 /// generics (lifetime constraints, traits, etc.).
-/// For now we simply check that there are no such constraints...
-fn check_generics(tcx: TyCtxt<'_>, def_id: DefId) {
+/// For now we simply check that there are no such constraints: we return the
+/// first unsupported predicate otherwise.
+///
+/// Note that we translate the where clauses of the functions (see
+/// [crate::translate_ctx::BodyTransCtx::translate_predicates]): this
+/// is only used for the types and the globals.
+fn check_generics(tcx: TyCtxt<'_>, def_id: DefId) -> Result<(), String> {
     // Retrieve the generics and the predicates (where-clauses)
     let _generics = tcx.generics_of(def_id);
     let preds = tcx.predicates_of(def_id);
@@ -102,49 +107,53 @@ fn check_generics(tcx: TyCtxt<'_>, def_id: DefId) {
                 // For now, we check that there are only instances of this trait,
                 // and ignore it.
                 use rustc_middle::ty::{BoundConstness, ImplPolarity};
-                assert!(trait_pred.polarity == ImplPolarity::Positive);
-                // Note sure what this is about
-                assert!(trait_pred.constness == BoundConstness::NotConst);
                 let trait_name = trait_def_id_to_name(tcx, trait_pred.trait_ref.def_id);
                 trace!("{}", trait_name);
-                assert!(
-                    trait_name.equals_ref_name(&assumed::MARKER_SIZED_NAME),
-                    "Unsupported trait: {:?}",
-                    trait_name
-                );
+                // Note sure what the constness is about
+                if trait_pred.polarity != ImplPolarity::Positive
+                    || trait_pred.constness != BoundConstness::NotConst
+                    || !trait_name.equals_ref_name(&assumed::MARKER_SIZED_NAME)
+                {
+                    return Err(format!("{pred:?}"));
+                }
+            }
+            PredicateKind::Clause(Clause::RegionOutlives(_))
+            | PredicateKind::Clause(Clause::TypeOutlives(_)) => {
+                // Those are typically inferred by rustc from the fields (for
+                // instance, a field of type `PhantomData<&'a T>` implies
+                // `T: 'a`): we can ignore them
             }
-            PredicateKind::Clause(Clause::RegionOutlives(_)) => unimplemented!(),
-            PredicateKind::Clause(Clause::TypeOutlives(_)) => unimplemented!(),
-            PredicateKind::Clause(Clause::Projection(_)) => unimplemented!(),
             PredicateKind::Clause(Clause::ConstArgHasType(..)) => {
                 // I don't really understand that one
             }
-            PredicateKind::WellFormed(_) => unimplemented!(),
-            PredicateKind::ObjectSafe(_) => unimplemented!(),
-            PredicateKind::ClosureKind(_, _, _) => unimplemented!(),
-            PredicateKind::Subtype(_) => unimplemented!(),
-            PredicateKind::Coerce(_) => unimplemented!(),
-            PredicateKind::ConstEvaluatable(_) => unimplemented!(),
-            PredicateKind::ConstEquate(_, _) => unimplemented!(),
-            PredicateKind::TypeWellFormedFromEnv(_) => unimplemented!(),
-            PredicateKind::Ambiguous => unimplemented!(),
-            PredicateKind::AliasRelate(..) => unimplemented!(),
+            PredicateKind::ConstEvaluatable(_) => {
+                // Introduced by the const generic expressions (for instance,
+                // `where [u8; N * 2]: Sized`): we can ignore them
+            }
+            PredicateKind::Clause(Clause::Projection(_))
+            | PredicateKind::WellFormed(_)
+            | PredicateKind::ObjectSafe(_)
+            | PredicateKind::ClosureKind(_, _, _)
+            | PredicateKind::Subtype(_)
+            | PredicateKind::Coerce(_)
+            | PredicateKind::ConstEquate(_, _)
+            | PredicateKind::TypeWellFormedFromEnv(_)
+            | PredicateKind::Ambiguous
+            | PredicateKind::AliasRelate(..) => return Err(format!("{pred:?}")),
         }
     }
-}
-
-/// Check a function's generics
-pub(crate) fn check_function_generics(tcx: TyCtxt<'_>, def_id: DefId) {
-    check_generics(tcx, def_id)
+    Ok(())
 }
 
 /// Check a type's generics
-pub(crate) fn check_type_generics(tcx: TyCtxt<'_>, def_id: DefId) {
+pub(crate) fn check_type_generics(tcx: TyCtxt<'_>, def_id: DefId) -> Result<(), String> {
     check_generics(tcx, def_id)
 }
 
 /// Check a global's generics (to refuse them except Sized trait)
-pub(crate) fn check_global_generics(tcx: TyCtxt<'_>, def_id: DefId) {
-    assert!(tcx.generics_of(def_id).params.is_empty());
+pub(crate) fn check_global_generics(tcx: TyCtxt<'_>, def_id: DefId) -> Result<(), String> {
+    if !tcx.generics_of(def_id).params.is_empty() {
+        return Err(format!("{:?}", tcx.generics_of(def_id).params));
+    }
     check_generics(tcx, def_id)
 }
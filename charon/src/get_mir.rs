@@ -2,10 +2,15 @@
 //! Allow to easily load the MIR code generated by a specific pass.
 
 #![allow(dead_code)]
+use crate::export::SerializeError;
+use crate::names::Name;
+use crate::ullbc_ast::{FunDeclId, FunDecls};
+use rustc_ast::AttrKind;
 use rustc_hir::def_id::{DefId, LocalDefId};
-use rustc_middle::mir::Body;
+use rustc_middle::mir::pretty::write_mir_fn;
+use rustc_middle::mir::{Body, Promoted};
 use rustc_middle::ty::TyCtxt;
-use std::cell::Ref;
+use std::path::PathBuf;
 
 /// TODO: maybe we should always target MIR Built, this would make things
 /// simpler. In particular, the MIR optimized is very low level and
@@ -20,6 +25,31 @@ pub enum MirLevel {
     Optimized,
 }
 
+impl MirLevel {
+    /// Parse a MIR level: "built", "promoted" or "optimized".
+    pub fn parse(level: &str) -> Option<MirLevel> {
+        match level {
+            "built" => Some(MirLevel::Built),
+            "promoted" => Some(MirLevel::Promoted),
+            "optimized" => Some(MirLevel::Optimized),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an argument of `--mir-level-for`, of the shape `<path>=<level>`
+/// (for instance `module::f=optimized`).
+pub fn parse_item_mir_level(arg: &str) -> Result<(&str, MirLevel), String> {
+    let (path, level) = match arg.split_once('=') {
+        Some(split) => split,
+        None => return Err(format!("expected `<path>=<level>`, got `{arg}`")),
+    };
+    match MirLevel::parse(level.trim()) {
+        Some(level) => Ok((path.trim(), level)),
+        None => Err(format!("unknown MIR level: `{level}`")),
+    }
+}
+
 /// Indicates if the constants should be extracted in their own identifier,
 /// or if they must be evaluated to a constant value, depending on the
 /// MIR level which we extract.
@@ -41,6 +71,58 @@ pub fn boxes_are_desugared(level: MirLevel) -> bool {
     }
 }
 
+/// The MIR level at which we translate the body of a function, given the
+/// level requested for the crate.
+///
+/// The optimized MIR of a `const fn` is not the code which is evaluated by
+/// the compiler at compile time (it may, for instance, have been simplified
+/// by passes which are not valid in a const context): we always translate
+/// the `const fn` at the [MirLevel::Promoted] level.
+///
+/// Similarly, rustc lowers the generators to state machines before
+/// optimizing their bodies (the `yield`s disappear): we always translate the
+/// generator bodies at the [MirLevel::Built] level.
+pub fn function_mir_level(tcx: TyCtxt<'_>, def_id: DefId, level: MirLevel) -> MirLevel {
+    if tcx.generator_kind(def_id).is_some() {
+        MirLevel::Built
+    } else if tcx.is_const_fn(def_id) {
+        MirLevel::Promoted
+    } else {
+        level
+    }
+}
+
+/// The MIR level of a function, given by a `#[charon::mir_level = "..."]`
+/// attribute. Return `Err` with the attribute span if the level is invalid.
+pub fn attribute_mir_level(
+    tcx: TyCtxt<'_>,
+    def_id: DefId,
+) -> Result<Option<MirLevel>, rustc_span::Span> {
+    let attr = tcx
+        .get_attrs_unchecked(def_id)
+        .iter()
+        .find(|attr| match &attr.kind {
+            AttrKind::Normal(normal) => {
+                let segments: Vec<String> = normal
+                    .item
+                    .path
+                    .segments
+                    .iter()
+                    .map(|seg| seg.ident.to_string())
+                    .collect();
+                segments == ["charon", "mir_level"]
+            }
+            AttrKind::DocComment(..) => false,
+        });
+    match attr {
+        None => Ok(None),
+        Some(attr) => match attr.value_str().and_then(|s| MirLevel::parse(s.as_str())) {
+            Some(level) => Ok(Some(level)),
+            None => Err(attr.span),
+        },
+    }
+}
+
 /// Query the MIR for a function at a specific level
 pub fn get_mir_for_def_id_and_level(
     tcx: TyCtxt<'_>,
@@ -48,15 +130,16 @@ pub fn get_mir_for_def_id_and_level(
     level: MirLevel,
 ) -> &Body<'_> {
     match level {
+        // We copy the bodies rather than leaking the borrows: rustc steals
+        // those bodies to compute the next levels, which it couldn't do if
+        // they were still borrowed.
         MirLevel::Built => {
             let body = tcx.mir_built(def_id);
-            // Rk.: leak is unstable
-            Ref::leak(body.borrow())
+            tcx.arena.alloc(body.borrow().clone())
         }
         MirLevel::Promoted => {
             let (body, _) = tcx.mir_promoted(def_id);
-            // Rk.: leak is unstable
-            Ref::leak(body.borrow())
+            tcx.arena.alloc(body.borrow().clone())
         }
         MirLevel::Optimized => {
             let def_id = DefId {
@@ -67,3 +150,110 @@ pub fn get_mir_for_def_id_and_level(
         }
     }
 }
+
+/// Query the MIR of a promoted constant (see [Promoted]) at a specific level.
+///
+/// The promoted constants are introduced by the promotion pass: there are
+/// none in [MirLevel::Built].
+pub fn get_promoted_mir_for_def_id_and_level(
+    tcx: TyCtxt<'_>,
+    def_id: LocalDefId,
+    promoted: Promoted,
+    level: MirLevel,
+) -> &Body<'_> {
+    match level {
+        MirLevel::Built => unreachable!("no promoted constants in MIR built"),
+        MirLevel::Promoted => {
+            let (_, promoted_bodies) = tcx.mir_promoted(def_id);
+            tcx.arena.alloc(promoted_bodies.borrow()[promoted].clone())
+        }
+        MirLevel::Optimized => &tcx.promoted_mir(def_id.to_def_id())[promoted],
+    }
+}
+
+/// Pretty-print a MIR body with the rustc pretty-printer (`--emit-mir`).
+pub fn mir_body_to_string<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>) -> String {
+    let mut out: Vec<u8> = Vec::new();
+    // Writing to a vector doesn't fail
+    write_mir_fn(tcx, body, &mut |_, _| Ok(()), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The name of the file in which we dump the MIR of a function: the MIR of
+/// `krate::m::f` goes to `krate.m.f.mir` (the first element of the name is
+/// the crate name).
+fn mir_file_name(crate_name: &str, name: &Name) -> String {
+    let path: Vec<String> = name.name.iter().skip(1).map(|e| e.to_string()).collect();
+    format!("{crate_name}.{}.mir", path.join("."))
+}
+
+/// Write the MIR of the translated functions (`--emit-mir`) next to the
+/// generated files: one file per function (see [mir_file_name]).
+pub fn export_mir(
+    crate_name: &str,
+    fun_defs: &FunDecls,
+    mir_dumps: &FunDeclId::Map<String>,
+    dest_dir: &Option<PathBuf>,
+) -> Result<(), SerializeError> {
+    let dest_dir = dest_dir
+        .as_deref()
+        .map_or_else(PathBuf::new, |d| d.to_path_buf());
+    std::fs::create_dir_all(&dest_dir).map_err(|e| SerializeError::Io(dest_dir.clone(), e))?;
+
+    for (id, mir) in mir_dumps.iter_indexed() {
+        let decl = fun_defs.get(*id).unwrap();
+        let target_filename = dest_dir.join(mir_file_name(crate_name, &decl.name));
+        std::fs::write(&target_filename, mir)
+            .map_err(|e| SerializeError::Io(target_filename.clone(), e))?;
+        info!("Generated the file: {}", target_filename.to_str().unwrap());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::names::{Disambiguator, PathElem};
+
+    #[test]
+    fn test_mir_file_name() {
+        let name = Name {
+            name: vec![
+                PathElem::Ident("krate".to_string()),
+                PathElem::Ident("m".to_string()),
+                PathElem::Disambiguator(Disambiguator::Id::new(1)),
+                PathElem::Ident("f".to_string()),
+            ],
+        };
+        assert!(mir_file_name("krate", &name) == "krate.m.1.f.mir");
+    }
+
+    #[test]
+    fn test_parse_item_mir_level() {
+        assert!(matches!(
+            parse_item_mir_level("module::f=optimized"),
+            Ok(("module::f", MirLevel::Optimized))
+        ));
+        assert!(matches!(
+            parse_item_mir_level("g = built"),
+            Ok(("g", MirLevel::Built))
+        ));
+        assert!(parse_item_mir_level("module::f").is_err());
+        assert!(parse_item_mir_level("module::f=fast").is_err());
+    }
+
+    /// We can't create the destination directory if a file has its name
+    #[test]
+    fn test_export_error() {
+        let path = std::env::temp_dir().join(format!("charon_mir_{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let res = export_mir(
+            "test",
+            &FunDecls::new(),
+            &FunDeclId::Map::new(),
+            &Some(path.clone()),
+        );
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(res, Err(SerializeError::Io(p, _)) if p == path));
+    }
+}
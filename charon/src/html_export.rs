@@ -0,0 +1,217 @@
+//! Generate an HTML rendering of the LLBC functions, for debugging purposes.
+//!
+//! Every function is rendered as a `<section>`, whose header links to the
+//! location of the function in the original source. By default, the links
+//! are plain `file://...` links to the source files: the format of the links
+//! can be changed with `--html-link-format`, for instance so that clicking on
+//! a function name opens the corresponding file, at the proper line, in an
+//! editor.
+
+use crate::export::SerializeError;
+use crate::llbc_ast::{FunDecl, FunDecls, GlobalDecls};
+use crate::meta::{FileId, FileName, Span};
+use crate::types::TypeDecls;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The format of the links, if the user doesn't provide one (see
+/// [crate::cli_options::CliOpts::html_link_format])
+pub const DEFAULT_LINK_FORMAT: &str = "file://{path}";
+
+/// Escape the characters which have a special meaning in HTML.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Compute the link to a span, following the format `link_format`. Returns
+/// `None` if the span doesn't refer to a file on the disk (for instance, if it
+/// comes from a macro expansion).
+fn span_href(
+    link_format: &str,
+    id_to_file: &HashMap<FileId::Id, FileName>,
+    span: &Span,
+) -> Option<String> {
+    let path = match id_to_file.get(&span.file_id)? {
+        FileName::Local(path) | FileName::Virtual(path) => path,
+        FileName::NotReal(_) => return None,
+    };
+    // The links need absolute paths
+    let path = if path.is_absolute() {
+        path.clone()
+    } else {
+        std::env::current_dir().ok()?.join(path)
+    };
+    let path = path.display().to_string();
+    // Note that the columns are 0-based in the spans, but 1-based in the links
+    Some(
+        link_format
+            .replace("{path}", &path)
+            .replace("{line}", &span.beg.line.to_string())
+            .replace("{col}", &(span.beg.col + 1).to_string()),
+    )
+}
+
+/// Render a function as a `<section>`.
+fn fun_decl_to_html(
+    link_format: &str,
+    id_to_file: &HashMap<FileId::Id, FileName>,
+    type_defs: &TypeDecls,
+    fun_defs: &FunDecls,
+    global_defs: &GlobalDecls,
+    def: &FunDecl,
+    out: &mut String,
+) {
+    let name = escape(&def.name.to_string());
+    let title = match span_href(link_format, id_to_file, &def.meta.span) {
+        Some(href) => format!("<a href=\"{}\">{}</a>", escape(&href), name),
+        None => name,
+    };
+    let text = def.fmt_with_decls(type_defs, fun_defs, global_defs);
+    // Writing to a string can't fail
+    let _ = writeln!(
+        out,
+        "<section id=\"fun-{}\">\n<h2>{}</h2>\n<pre>{}</pre>\n</section>",
+        def.def_id,
+        title,
+        escape(&text)
+    );
+}
+
+/// Render the functions of a crate to an HTML document.
+fn crate_to_html(
+    crate_name: &str,
+    link_format: &str,
+    id_to_file: &HashMap<FileId::Id, FileName>,
+    type_defs: &TypeDecls,
+    fun_defs: &FunDecls,
+    global_defs: &GlobalDecls,
+) -> String {
+    let crate_name = escape(crate_name);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{crate_name}</title>\n</head>\n<body>\n<h1>{crate_name}</h1>"
+    );
+    for def in fun_defs.iter() {
+        fun_decl_to_html(
+            link_format,
+            id_to_file,
+            type_defs,
+            fun_defs,
+            global_defs,
+            def,
+            &mut out,
+        );
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Export the LLBC functions to an HTML file (`{crate_name}.html`).
+/// `link_format` is the format of the links to the sources (see
+/// [DEFAULT_LINK_FORMAT]).
+pub fn export_html(
+    crate_name: &str,
+    link_format: &str,
+    id_to_file: &HashMap<FileId::Id, FileName>,
+    type_defs: &TypeDecls,
+    fun_defs: &FunDecls,
+    global_defs: &GlobalDecls,
+    dest_dir: &Option<PathBuf>,
+) -> std::result::Result<(), SerializeError> {
+    let mut target_filename = dest_dir
+        .as_deref()
+        .map_or_else(PathBuf::new, Path::to_path_buf);
+    target_filename.push(format!("{crate_name}.html"));
+
+    if let Some(dest_dir) = dest_dir {
+        std::fs::create_dir_all(dest_dir).map_err(|e| SerializeError::Io(dest_dir.clone(), e))?;
+    }
+
+    let html = crate_to_html(
+        crate_name,
+        link_format,
+        id_to_file,
+        type_defs,
+        fun_defs,
+        global_defs,
+    );
+    std::fs::write(&target_filename, html)
+        .map_err(|e| SerializeError::Io(target_filename.clone(), e))?;
+    info!("Generated the file: {}", target_filename.to_string_lossy());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{Loc, LocalFileId};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("&x < 'a > \"b\""),
+            "&amp;x &lt; &#39;a &gt; &quot;b&quot;"
+        );
+    }
+
+    #[test]
+    fn test_span_href() {
+        let local = FileId::Id::LocalId(LocalFileId::Id::new(0));
+        let not_real = FileId::Id::LocalId(LocalFileId::Id::new(1));
+        let mut id_to_file = HashMap::new();
+        let _ = id_to_file.insert(local, FileName::Local(PathBuf::from("/tmp/test.rs")));
+        let _ = id_to_file.insert(not_real, FileName::NotReal("<macro>".to_string()));
+        let span = |file_id| Span {
+            file_id,
+            beg: Loc { line: 3, col: 4 },
+            end: Loc { line: 5, col: 0 },
+        };
+        assert_eq!(
+            span_href(DEFAULT_LINK_FORMAT, &id_to_file, &span(local)),
+            Some("file:///tmp/test.rs".to_string())
+        );
+        assert_eq!(
+            span_href(
+                "vscode://file{path}:{line}:{col}",
+                &id_to_file,
+                &span(local)
+            ),
+            Some("vscode://file/tmp/test.rs:3:5".to_string())
+        );
+        assert_eq!(
+            span_href(DEFAULT_LINK_FORMAT, &id_to_file, &span(not_real)),
+            None
+        );
+    }
+
+    /// We can't create the destination directory if a file has its name
+    #[test]
+    fn test_export_error() {
+        let path = std::env::temp_dir().join(format!("charon_html_{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let res = export_html(
+            "test",
+            DEFAULT_LINK_FORMAT,
+            &HashMap::new(),
+            &TypeDecls::new(),
+            &FunDecls::new(),
+            &GlobalDecls::new(),
+            &Some(path.clone()),
+        );
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(res, Err(SerializeError::Io(p, _)) if p == path));
+    }
+}
@@ -4,7 +4,11 @@
 //! This data-structure is mostly meant to be used with the index types defined
 //! with [macros::generate_index_type]: by using custom index types, we
 //! leverage the type checker to prevent us from mixing them.
-use serde::{Serialize, Serializer};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+pub use std::collections::btree_map::Entry;
 pub use std::collections::btree_map::Iter as IterAll;
 pub use std::collections::btree_map::IterMut as IterAllMut;
 pub use std::collections::BTreeMap;
@@ -34,6 +38,18 @@ impl<Id: std::cmp::Ord, T> Map<Id, T> {
         self.map.get_mut(&id)
     }
 
+    /// Get the entry for an id, to inspect or update it in place with a
+    /// single lookup.
+    pub fn entry(&mut self, id: Id) -> Entry<'_, Id, T> {
+        self.map.entry(id)
+    }
+
+    /// Return the value associated to `id`, after inserting `f()` if there
+    /// is none.
+    pub fn or_insert_with(&mut self, id: Id, f: impl FnOnce() -> T) -> &mut T {
+        self.map.entry(id).or_insert_with(f)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.map.iter().map(|(_, x)| x)
     }
@@ -49,6 +65,39 @@ impl<Id: std::cmp::Ord, T> Map<Id, T> {
     pub fn len(&self) -> usize {
         self.map.len()
     }
+
+    /// Remove the bindings which don't satisfy the predicate. Contrary to
+    /// [crate::id_vector::Vector::retain], the remaining values keep their
+    /// ids.
+    pub fn retain_indexed(&mut self, mut f: impl FnMut(Id, &T) -> bool)
+    where
+        Id: Copy,
+    {
+        self.map.retain(|id, x| f(*id, x))
+    }
+
+    /// Same as [Map::retain_indexed], but consumes the map.
+    pub fn into_filtered(mut self, f: impl FnMut(Id, &T) -> bool) -> Self
+    where
+        Id: Copy,
+    {
+        self.retain_indexed(f);
+        self
+    }
+}
+
+/// The values which can be identified by a stable id (see
+/// [crate::stable_ids]).
+pub trait HasStableId {
+    fn stable_id(&self) -> Option<&str>;
+}
+
+impl<Id: std::cmp::Ord, T: HasStableId> Map<Id, T> {
+    /// Find a value from its stable id. Contrary to [Map::get], this is a
+    /// linear lookup.
+    pub fn lookup_by_stable_id(&self, stable_id: &str) -> Option<&T> {
+        self.iter().find(|x| x.stable_id() == Some(stable_id))
+    }
 }
 
 impl<'a, Id, T> IntoIterator for &'a Map<Id, T>
@@ -79,6 +128,34 @@ impl<Id: Serialize, T: Clone + Serialize> Serialize for Map<Id, T> {
     }
 }
 
+impl<'de, Id: std::cmp::Ord + Deserialize<'de>, T: Clone + Deserialize<'de>> Deserialize<'de>
+    for Map<Id, T>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // A sequence of pairs (see the serializer)
+        let bindings: Vec<(Id, T)> = Vec::deserialize(deserializer)?;
+        Ok(bindings.into_iter().collect())
+    }
+}
+
+impl<Id: JsonSchema, T: Clone + JsonSchema> JsonSchema for Map<Id, T> {
+    fn schema_name() -> String {
+        format!("Map_of_{}", T::schema_name())
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    // A sequence of pairs (see the serializer)
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <Vec<(Id, T)>>::json_schema(gen)
+    }
+}
+
 impl<Id, T> FromIterator<(Id, T)> for Map<Id, T>
 where
     Id: std::cmp::Ord,
@@ -91,3 +168,36 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::values::VarId;
+
+    #[test]
+    fn test_retain_indexed() {
+        let mut m: VarId::Map<u32> = (0..5).map(|x| (VarId::Id::new(x), x as u32 * 10)).collect();
+        // Remove the bindings for the ids 1 and 3: the other ids are unchanged
+        m.retain_indexed(|id, _| id != VarId::Id::new(1) && id != VarId::Id::new(3));
+        assert!(m.len() == 3);
+        assert!(m.get(VarId::Id::new(1)).is_none());
+        assert!(m.get(VarId::Id::new(4)) == Some(&40));
+
+        let m = m.into_filtered(|_, x| *x > 0);
+        let bindings: Vec<(VarId::Id, u32)> = m.iter_indexed().map(|(id, x)| (*id, *x)).collect();
+        assert!(bindings == vec![(VarId::Id::new(2), 20), (VarId::Id::new(4), 40)]);
+    }
+
+    #[test]
+    fn test_map_generator() {
+        let mut gen: VarId::MapGenerator<&str> = VarId::MapGenerator::new();
+        assert!(gen.insert_new("x") == (VarId::Id::new(0), true));
+        assert!(gen.insert_new("y") == (VarId::Id::new(1), true));
+        // Inserting a key twice doesn't allocate a new id
+        assert!(gen.insert_new("x") == (VarId::Id::new(0), false));
+        assert!(gen.insert("y") == VarId::Id::new(1));
+        assert!(gen.insert("z") == VarId::Id::new(2));
+        assert!(gen.get("z") == Some(VarId::Id::new(2)));
+        assert!(gen.get("w").is_none());
+        assert!(gen.counter.fresh_id() == VarId::Id::new(3));
+    }
+}
@@ -8,7 +8,10 @@
 //! Note that this data structure is implemented by using persistent vectors.
 //! This makes the clone operation almost a no-op.
 
-use serde::{Serialize, Serializer};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::iter::{FromIterator, IntoIterator};
 
 pub use std::collections::hash_map::Iter as IterAll;
@@ -119,6 +122,40 @@ where
             index: I::zero(),
         }
     }
+
+    /// Only keep the elements which satisfy the predicate. The predicate
+    /// receives the indices of the elements before the filtering.
+    ///
+    /// The vector remains dense: the surviving elements are shifted to fill
+    /// the holes, which means their indices may change.
+    pub fn retain(&mut self, mut f: impl FnMut(I, &T) -> bool) {
+        let mut index = I::zero();
+        self.vector.retain(|x| {
+            let keep = f(index, x);
+            index.incr();
+            keep
+        });
+    }
+
+    /// Map the elements and filter the ones for which `f` returns `None`. As
+    /// with [Vector::retain], `f` receives the indices of the elements before
+    /// the filtering, and the indices of the surviving elements may change.
+    pub fn filter_map<U: Clone>(self, mut f: impl FnMut(I, T) -> Option<U>) -> Vector<I, U> {
+        let mut index = I::zero();
+        self.vector
+            .into_iter()
+            .filter_map(|x| {
+                let i = index;
+                index.incr();
+                f(i, x)
+            })
+            .collect()
+    }
+
+    /// Return the first element (and its index) which satisfies the predicate.
+    pub fn find(&self, f: impl Fn(&T) -> bool) -> Option<(I, &T)> {
+        self.iter_indexed_values().find(|(_, x)| f(x))
+    }
 }
 
 impl<I, T> Vector<I, T>
@@ -268,3 +305,66 @@ impl<I: ToUsize, T: Clone + Serialize> Serialize for Vector<I, T> {
         seq.end()
     }
 }
+
+impl<'de, I: ToUsize, T: Clone + Deserialize<'de>> Deserialize<'de> for Vector<I, T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v: Vec<T> = Vec::deserialize(deserializer)?;
+        Ok(Vector::from(v))
+    }
+}
+
+impl<I: ToUsize, T: Clone + JsonSchema> JsonSchema for Vector<I, T> {
+    fn schema_name() -> String {
+        format!("Vector_of_{}", T::schema_name())
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <Vec<T>>::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::VarId;
+
+    #[test]
+    fn test_retain() {
+        let mut v: VarId::Vector<u32> = VarId::Vector::new();
+        for x in 0..5 {
+            v.push_back(x * 10);
+        }
+        // Remove the elements at indices 1 and 3
+        v.retain(|id, _| id != VarId::Id::new(1) && id != VarId::Id::new(3));
+        assert!(v.len() == 3);
+        let values: Vec<(VarId::Id, u32)> =
+            v.iter_indexed_values().map(|(id, x)| (id, *x)).collect();
+        assert!(
+            values
+                == vec![
+                    (VarId::Id::new(0), 0),
+                    (VarId::Id::new(1), 20),
+                    (VarId::Id::new(2), 40)
+                ]
+        );
+        assert!(v.find(|x| *x == 40) == Some((VarId::Id::new(2), &40)));
+        assert!(v.find(|x| *x == 10).is_none());
+
+        let v: VarId::Vector<String> = v.filter_map(|id, x| {
+            if x > 0 {
+                Some(format!("{id}:{x}"))
+            } else {
+                None
+            }
+        });
+        // The closure receives the indices before the filtering
+        let values: Vec<&String> = v.iter().collect();
+        assert!(values == vec!["1:20", "2:40"]);
+    }
+}
@@ -148,7 +148,7 @@ impl<'a> MutExprVisitor for Transform<'a> {
     fn visit_rvalue(&mut self, rv: &mut Rvalue) {
         use Rvalue::*;
         match rv {
-            Use(_) | UnaryOp(..) | BinaryOp(..) | Aggregate(..) | Global(..) => {
+            Use(_) | UnaryOp(..) | BinaryOp(..) | Aggregate(..) | Global(..) | FnPtr(..) => {
                 // We don't access places here, only operands
                 self.default_visit_rvalue(rv)
             }
@@ -163,6 +163,7 @@ impl<'a> MutExprVisitor for Transform<'a> {
                     }
                 }
             }
+            RawPtr(p, kind) => self.visit_transform_place(*kind == RefKind::Mut, p),
             Discriminant(p) | Len(p, _, _) => {
                 // We access places, but those places are used to access
                 // elements without mutating them
@@ -200,8 +201,25 @@ impl<'a> MutAstVisitor for Transform<'a> {
             FakeRead(p) => {
                 self.visit_transform_place(false, p);
             }
-            Assign(..) | SetDiscriminant(..) | Drop(..) | Assert(..) | Call(..) | Panic
-            | Return | Break(..) | Continue(..) | Nop | Switch(..) | Loop(..) => {
+            Assign(..)
+            | SetDiscriminant(..)
+            | Drop(..)
+            | StorageLive(..)
+            | StorageDead(..)
+            | Assert(..)
+            | TaggedAssert(..)
+            | Call(..)
+            | Panic
+            | Return
+            | Break(..)
+            | Continue(..)
+            | Nop
+            | MacroExpansion(..)
+            | Switch(..)
+            | Loop(..)
+            | LetElse(..)
+            | IfLet(..)
+            | TryOp { .. } => {
                 // Explore
                 self.default_visit_raw_statement(st)
             }
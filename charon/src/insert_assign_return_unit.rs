@@ -30,7 +30,10 @@ fn transform_st(mut st: Statement) -> Statement {
         RawStatement::FakeRead(p) => RawStatement::FakeRead(p),
         RawStatement::SetDiscriminant(p, vid) => RawStatement::SetDiscriminant(p, vid),
         RawStatement::Drop(p) => RawStatement::Drop(p),
+        RawStatement::StorageLive(vid) => RawStatement::StorageLive(vid),
+        RawStatement::StorageDead(vid) => RawStatement::StorageDead(vid),
         RawStatement::Assert(assert) => RawStatement::Assert(assert),
+        RawStatement::TaggedAssert(id, assert) => RawStatement::TaggedAssert(id, assert),
         RawStatement::Call(call) => RawStatement::Call(call),
         RawStatement::Panic => RawStatement::Panic,
         RawStatement::Break(i) => RawStatement::Break(i),
@@ -58,6 +61,13 @@ fn transform_st(mut st: Statement) -> Statement {
             }
         },
         RawStatement::Loop(loop_body) => RawStatement::Loop(Box::new(transform_st(*loop_body))),
+        RawStatement::LetElse(..)
+        | RawStatement::IfLet(..)
+        | RawStatement::TryOp { .. }
+        | RawStatement::MacroExpansion(_) => {
+            // Those variants are introduced in subsequent passes
+            unreachable!();
+        }
         RawStatement::Sequence(st1, st2) => {
             RawStatement::Sequence(Box::new(transform_st(*st1)), Box::new(transform_st(*st2)))
         }
@@ -10,10 +10,17 @@
 //! a cleaned-up version of MIR) and [`llbc_ast`](llbc_ast) (same as ULLBC, but
 //! we reconstructed the control-flow to have `if ... then ... else ...`,
 //! loops, etc. instead of `GOTO`s).
+//!
+//! The translation from MIR requires the rustc libraries: it is only
+//! available with the `rustc` feature (enabled by default). Without this
+//! feature, the library only contains the ASTs, the (de)serialization code
+//! (see [`deserialize`](deserialize)) and the visitors and formatters, which
+//! is what the tools consuming the generated files need.
 
-#![feature(rustc_private, register_tool)]
+#![cfg_attr(feature = "rustc", feature(rustc_private))]
+#![feature(register_tool)]
 #![feature(box_patterns)]
-#![feature(cell_leak)] // For Ref::leak
+#![cfg_attr(feature = "rustc", feature(cell_leak))] // For Ref::leak
 // For rustdoc: prevents overflows
 #![recursion_limit = "256"]
 
@@ -21,31 +28,67 @@ extern crate hashlink;
 extern crate im;
 extern crate linked_hash_set;
 extern crate log;
+extern crate take_mut;
+
+// The rustc libraries: we only need them to translate the MIR (see the
+// `rustc` feature)
+#[cfg(feature = "rustc")]
 extern crate rustc_abi;
+#[cfg(feature = "rustc")]
 extern crate rustc_ast;
+#[cfg(feature = "rustc")]
 extern crate rustc_borrowck;
+#[cfg(feature = "rustc")]
 extern crate rustc_const_eval;
+#[cfg(feature = "rustc")]
 extern crate rustc_driver;
+#[cfg(feature = "rustc")]
 extern crate rustc_error_messages;
+#[cfg(feature = "rustc")]
 extern crate rustc_errors;
+#[cfg(feature = "rustc")]
 extern crate rustc_hir;
+#[cfg(feature = "rustc")]
 extern crate rustc_index;
+#[cfg(feature = "rustc")]
 extern crate rustc_interface;
+#[cfg(feature = "rustc")]
 extern crate rustc_middle;
+#[cfg(feature = "rustc")]
 extern crate rustc_mir_dataflow;
+#[cfg(feature = "rustc")]
 extern crate rustc_mir_transform;
+#[cfg(feature = "rustc")]
 extern crate rustc_monomorphize;
+#[cfg(feature = "rustc")]
 extern crate rustc_resolve;
+#[cfg(feature = "rustc")]
 extern crate rustc_session;
+#[cfg(feature = "rustc")]
 extern crate rustc_span;
+#[cfg(feature = "rustc")]
 extern crate rustc_target;
-extern crate take_mut;
 
 #[macro_use]
 pub mod common;
 pub mod assumed;
+pub mod call_graph;
+pub mod cfg_analysis;
+pub mod cfg_dot;
+pub mod check_storage;
 pub mod cli_options;
+pub mod constant_propagation;
+pub mod cse;
+pub mod def_ids;
+pub mod deserialize;
+pub mod detect_interior_mutability;
+pub mod detect_loops;
+#[cfg(feature = "rustc")]
+pub mod diagnostics;
+pub mod diff;
+#[cfg(feature = "rustc")]
 pub mod driver;
+pub mod eliminate_asserts;
 pub mod export;
 pub mod expressions;
 pub mod expressions_utils;
@@ -53,22 +96,31 @@ pub mod extract_global_assignments;
 pub mod formatter;
 pub mod gast;
 pub mod gast_utils;
+#[cfg(feature = "rustc")]
 pub mod generics;
+#[cfg(feature = "rustc")]
 pub mod get_mir;
 pub mod graphs;
+pub mod html_export;
 pub mod id_map;
 pub mod id_vector;
 pub mod index_to_function_calls;
 pub mod insert_assign_return_unit;
+pub mod liveness;
 pub mod llbc_ast;
 pub mod llbc_ast_utils;
 pub mod logger;
 pub mod meta;
 pub mod meta_utils;
+pub mod name_matcher;
 pub mod names;
 pub mod names_utils;
+pub mod normalize_projections;
 pub mod ops_to_function_calls;
 pub mod reconstruct_asserts;
+pub mod reconstruct_if_let;
+pub mod reconstruct_let_else;
+pub mod reconstruct_try;
 pub mod regions_hierarchy;
 pub mod regularize_constant_adts;
 pub mod remove_drop_never;
@@ -76,15 +128,35 @@ pub mod remove_dynamic_checks;
 pub mod remove_read_discriminant;
 pub mod remove_unused_locals;
 pub mod reorder_decls;
+pub mod restore_type_aliases;
+pub mod schema;
+pub mod stable_ids;
+pub mod stats;
+pub mod strip_macro_metadata;
+pub mod substitute;
+pub mod transform;
+#[cfg(feature = "rustc")]
 pub mod translate_constants;
+#[cfg(feature = "rustc")]
 pub mod translate_crate_to_ullbc;
+#[cfg(feature = "rustc")]
 pub mod translate_ctx;
+#[cfg(feature = "rustc")]
 pub mod translate_functions_to_ullbc;
+#[cfg(feature = "rustc")]
+pub mod translate_traits;
+#[cfg(feature = "rustc")]
 pub mod translate_types;
+pub mod type_dep_graph;
+pub mod typecheck;
 pub mod types;
+pub mod types_interner;
 pub mod types_utils;
 pub mod ullbc_ast;
 pub mod ullbc_ast_utils;
 pub mod ullbc_to_llbc;
+pub mod unsafe_propagation;
 pub mod values;
 pub mod values_utils;
+pub mod wellformed;
+pub mod workspace;
@@ -0,0 +1,320 @@
+//! Compute the liveness of the local variables in a ULLBC body.
+//!
+//! A variable is live at a given program point if its current value may be
+//! read later on. We perform the standard backward dataflow analysis: the
+//! variables live at the exit of a block are the variables live at the entry
+//! of its successors, and the variables live at the entry of a block are the
+//! variables it reads before (re)defining them, together with the variables
+//! live at its exit that it doesn't define. We iterate until we reach a
+//! fixpoint.
+//!
+//! We consider that a variable is defined when it is entirely overwritten:
+//! writing to a field of a variable counts as a use (the other fields must
+//! be preserved).
+#![allow(dead_code)]
+
+use crate::expressions::*;
+use crate::gast::{iter_function_bodies, iter_global_bodies, BlockLiveness, Call};
+use crate::types::SharedTypeVisitor;
+use crate::ullbc_ast::*;
+use crate::ullbc_to_llbc::get_block_targets;
+use crate::values::*;
+use std::collections::{HashMap, HashSet};
+
+/// The variables live at the entry of a block, and at the exit of a block.
+pub type LiveSets = (HashSet<VarId::Id>, HashSet<VarId::Id>);
+
+/// The variables read and the variables defined by a statement (or a
+/// block).
+struct UseDef {
+    uses: HashSet<VarId::Id>,
+    defs: HashSet<VarId::Id>,
+}
+
+impl UseDef {
+    fn new() -> Self {
+        UseDef {
+            uses: HashSet::new(),
+            defs: HashSet::new(),
+        }
+    }
+
+    fn write_place(&mut self, p: &Place) {
+        if p.projection.is_empty() {
+            self.defs.insert(p.var_id);
+        } else {
+            self.visit_place(p);
+        }
+    }
+
+    fn compute_in_statement(st: &Statement) -> Self {
+        let mut ud = UseDef::new();
+        match &st.content {
+            RawStatement::Assign(p, rv) => {
+                ud.visit_rvalue(rv);
+                ud.write_place(p);
+            }
+            RawStatement::FakeRead(p) | RawStatement::SetDiscriminant(p, _) => ud.visit_place(p),
+            RawStatement::StorageLive(var_id) | RawStatement::StorageDead(var_id) => {
+                ud.defs.insert(*var_id);
+            }
+            RawStatement::Deinit(p) => ud.write_place(p),
+        }
+        ud
+    }
+
+    fn compute_in_terminator(terminator: &Terminator) -> Self {
+        let mut ud = UseDef::new();
+        match &terminator.content {
+            RawTerminator::Switch { discr, .. } => ud.visit_operand(discr),
+            RawTerminator::Drop { place, .. } => ud.visit_place(place),
+            RawTerminator::Call {
+                call: Call {
+                    func, args, dest, ..
+                },
+                ..
+            } => {
+                // The function pointer, in case of an indirect call
+                ud.visit_fun_id(func);
+                for arg in args {
+                    ud.visit_operand(arg);
+                }
+                ud.write_place(dest);
+            }
+            RawTerminator::Assert { cond, .. } => ud.visit_operand(cond),
+            RawTerminator::Yield {
+                value,
+                resume_place,
+                ..
+            } => {
+                ud.visit_operand(value);
+                ud.write_place(resume_place);
+            }
+            // The return variable is read by the caller
+            RawTerminator::Return => {
+                ud.uses.insert(VarId::ZERO);
+            }
+            RawTerminator::Goto { .. }
+            | RawTerminator::Panic
+            | RawTerminator::Unreachable
+            | RawTerminator::GeneratorDrop => (),
+        }
+        ud
+    }
+
+    /// Compute the variables read by a block before being defined, and the
+    /// variables defined by the block.
+    fn compute_in_block(block: &BlockData) -> Self {
+        let mut ud = UseDef::compute_in_terminator(&block.terminator);
+        for st in block.statements.iter().rev() {
+            let st_ud = UseDef::compute_in_statement(st);
+            for var_id in &st_ud.defs {
+                ud.uses.remove(var_id);
+            }
+            ud.uses.extend(st_ud.uses);
+            ud.defs.extend(st_ud.defs);
+        }
+        ud
+    }
+}
+
+impl SharedTypeVisitor for UseDef {}
+
+impl SharedExprVisitor for UseDef {
+    fn visit_var_id(&mut self, id: &VarId::Id) {
+        self.uses.insert(*id);
+    }
+}
+
+/// Compute, for every block of a body, the variables live at the entry of
+/// the block and at the exit of the block.
+pub fn compute_liveness(body: &ExprBody) -> HashMap<BlockId::Id, LiveSets> {
+    let mut use_defs: HashMap<BlockId::Id, UseDef> = HashMap::new();
+    let mut successors: HashMap<BlockId::Id, Vec<BlockId::Id>> = HashMap::new();
+    let mut liveness: HashMap<BlockId::Id, LiveSets> = HashMap::new();
+    for (bid, block) in body.body.iter_indexed_values() {
+        use_defs.insert(bid, UseDef::compute_in_block(block));
+        successors.insert(bid, get_block_targets(body, bid));
+        liveness.insert(bid, (HashSet::new(), HashSet::new()));
+    }
+
+    // We explore the blocks in reverse order: as the information flows
+    // backward, this makes us reach the fixpoint faster.
+    let block_ids: Vec<BlockId::Id> = body
+        .body
+        .iter_indexed_values()
+        .map(|(bid, _)| bid)
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for bid in block_ids.iter().rev() {
+            let live_out: HashSet<VarId::Id> = successors[bid]
+                .iter()
+                .flat_map(|succ| liveness[succ].0.iter().copied())
+                .collect();
+            let ud = &use_defs[bid];
+            let mut live_in: HashSet<VarId::Id> = live_out.difference(&ud.defs).copied().collect();
+            live_in.extend(ud.uses.iter().copied());
+
+            let sets = liveness.get_mut(bid).unwrap();
+            if sets.0 != live_in || sets.1 != live_out {
+                *sets = (live_in, live_out);
+                changed = true;
+            }
+        }
+    }
+
+    liveness
+}
+
+/// Convert the result of [compute_liveness] to the annotations we store in
+/// the bodies. We sort the variables to get a deterministic output.
+fn to_annotations(
+    body: &ExprBody,
+    liveness: &HashMap<BlockId::Id, LiveSets>,
+) -> Vec<BlockLiveness> {
+    let sorted = |vars: &HashSet<VarId::Id>| {
+        let mut vars: Vec<VarId::Id> = vars.iter().copied().collect();
+        vars.sort();
+        vars
+    };
+    body.body
+        .iter_indexed_values()
+        .map(|(bid, _)| {
+            let (live_in, live_out) = &liveness[&bid];
+            BlockLiveness {
+                live_in: sorted(live_in),
+                live_out: sorted(live_out),
+            }
+        })
+        .collect()
+}
+
+/// Compute the liveness information of all the bodies, and store it in the
+/// bodies.
+pub fn annotate(funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (_, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        let liveness = compute_liveness(b);
+        b.liveness = Some(to_annotations(b, &liveness));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::types::*;
+    use crate::ullbc_ast::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn mk_place(var_id: VarId::Id) -> Place {
+        Place {
+            var_id,
+            projection: vec![],
+        }
+    }
+
+    fn assign(dest: VarId::Id, src: VarId::Id) -> Statement {
+        Statement::new(
+            dummy_meta(),
+            RawStatement::Assign(mk_place(dest), Rvalue::Use(Operand::Copy(mk_place(src)))),
+        )
+    }
+
+    fn block(statements: Vec<Statement>, content: RawTerminator) -> BlockData {
+        BlockData {
+            statements,
+            terminator: Terminator::new(dummy_meta(), content),
+            is_loop_header: false,
+        }
+    }
+
+    /// ```text
+    /// fn f(b: bool, x: u32, y: u32) -> u32 {
+    ///   bb0: switch b [true -> bb1, false -> bb2]
+    ///   bb1: @return := copy x; goto bb3
+    ///   bb2: @return := copy y; goto bb3
+    ///   bb3: return
+    /// }
+    /// ```
+    #[test]
+    fn test_branches() {
+        let mut ids = VarId::Generator::new();
+        let ret = ids.fresh_id();
+        let b = ids.fresh_id();
+        let x = ids.fresh_id();
+        let y = ids.fresh_id();
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut locals = VarId::Vector::new();
+        for (index, ty) in [
+            (ret, u32_ty.clone()),
+            (b, Ty::Literal(LiteralTy::Bool)),
+            (x, u32_ty.clone()),
+            (y, u32_ty),
+        ] {
+            locals.push_back(Var {
+                index,
+                name: None,
+                ty: ty.into(),
+            });
+        }
+
+        let mut block_ids = BlockId::Generator::new();
+        let bb0 = block_ids.fresh_id();
+        let bb1 = block_ids.fresh_id();
+        let bb2 = block_ids.fresh_id();
+        let bb3 = block_ids.fresh_id();
+        let mut blocks = BlockId::Vector::new();
+        blocks.push_back(block(
+            vec![],
+            RawTerminator::Switch {
+                discr: Operand::Move(mk_place(b)),
+                targets: SwitchTargets::If(bb1, bb2),
+            },
+        ));
+        blocks.push_back(block(
+            vec![assign(ret, x)],
+            RawTerminator::Goto { target: bb3 },
+        ));
+        blocks.push_back(block(
+            vec![assign(ret, y)],
+            RawTerminator::Goto { target: bb3 },
+        ));
+        blocks.push_back(block(vec![], RawTerminator::Return));
+
+        let body = ExprBody {
+            meta: dummy_meta(),
+            arg_count: 3,
+            locals,
+            body: blocks,
+            liveness: None,
+        };
+        let liveness = super::compute_liveness(&body);
+
+        let set = |vars: Vec<VarId::Id>| vars.into_iter().collect();
+        assert!(liveness[&bb0].0 == set(vec![b, x, y]));
+        assert!(liveness[&bb0].1 == set(vec![x, y]));
+        assert!(liveness[&bb1].0 == set(vec![x]));
+        assert!(liveness[&bb2].0 == set(vec![y]));
+        // `x` and `y` are not live after the join
+        assert!(liveness[&bb1].1 == set(vec![ret]));
+        assert!(liveness[&bb2].1 == set(vec![ret]));
+        assert!(liveness[&bb3].0 == set(vec![ret]));
+        assert!(liveness[&bb3].1.is_empty());
+    }
+}
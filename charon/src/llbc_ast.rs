@@ -14,27 +14,42 @@ use crate::meta::Meta;
 use crate::types::*;
 pub use crate::ullbc_ast::{Call, CtxNames, FunDeclId, GlobalDeclId, Var};
 use crate::values::*;
+use macros::generate_index_type;
 use macros::{EnumAsGetters, EnumIsA, EnumToGetters, VariantIndexArity, VariantName};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Assertion identifier: used to link the assertions of a body to the proof
+// obligations generated by the verification backends (see [tag_assertions]).
+generate_index_type!(AssertionId);
 
 /// Asserts are special constructs introduced by Rust to perform dynamic
 /// checks, to detect out-of-bounds accesses or divisions by zero for
 /// instance. We eliminate the assertions in [crate::remove_dynamic_checks],
 /// then introduce other dynamic checks in [crate::reconstruct_asserts].
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Assert {
     pub cond: Operand,
     pub expected: bool,
 }
 
 /// A raw statement: a statement without meta data.
-#[derive(Debug, Clone, EnumIsA, EnumToGetters, EnumAsGetters, Serialize)]
+#[derive(
+    Debug, Clone, EnumIsA, EnumToGetters, EnumAsGetters, Serialize, Deserialize, JsonSchema,
+)]
 pub enum RawStatement {
     Assign(Place, Rvalue),
     FakeRead(Place),
     SetDiscriminant(Place, VariantId::Id),
     Drop(Place),
+    /// See [crate::ullbc_ast::RawStatement::StorageLive]
+    StorageLive(VarId::Id),
+    /// See [crate::ullbc_ast::RawStatement::StorageDead]
+    StorageDead(VarId::Id),
     Assert(Assert),
+    /// An assertion with a unique identifier, introduced by
+    /// [crate::llbc_ast_utils::tag_assertions].
+    TaggedAssert(AssertionId::Id, Box<Assert>),
     Call(Call),
     /// Panic also handles "unreachable"
     Panic,
@@ -60,15 +75,66 @@ pub enum RawStatement {
     Sequence(Box<Statement>, Box<Statement>),
     Switch(Switch),
     Loop(Box<Statement>),
+    /// A `let ... else { ... }` statement, over an ADT:
+    /// ```text
+    /// let Some(v) = x else { return 0; };
+    /// ```
+    /// If the variant of the place is one of the given variants, we
+    /// continue with the subsequent statements, otherwise we evaluate the
+    /// `else` statement, which diverges (it returns, panics, breaks or
+    /// continues). Note that the bindings of the pattern (`v` above) are
+    /// introduced by the subsequent statements.
+    ///
+    /// This statement is introduced in [crate::reconstruct_let_else].
+    LetElse(Place, Vec<VariantId::Id>, Box<Statement>),
+    /// An `if let ... { ... } else { ... }` statement, over an ADT:
+    /// ```text
+    /// if let Some(v) = x { ... } else { ... }
+    /// ```
+    /// If the variant of the place is one of the given variants, we evaluate
+    /// the first statement, otherwise we evaluate the second one. As for
+    /// [RawStatement::LetElse], the bindings of the pattern are introduced by
+    /// the first statement.
+    ///
+    /// This statement is introduced in [crate::reconstruct_if_let].
+    IfLet(Place, Vec<VariantId::Id>, Box<Statement>, Box<Statement>),
+    /// The `?` operator, which MIR desugars to a call to `Try::branch`
+    /// followed by a match over the resulting `ControlFlow`:
+    /// ```text
+    /// let v = x?;
+    /// ```
+    /// If `Try::branch(scrutinee)` is `Continue`, we store its content in
+    /// `ok_place` and continue with the subsequent statements. Otherwise, we
+    /// store the residual in `err_place` and evaluate `err_return`, which
+    /// diverges (it converts the residual with `FromResidual::from_residual`,
+    /// then returns).
+    ///
+    /// This statement is introduced in [crate::reconstruct_try].
+    TryOp {
+        scrutinee: Operand,
+        ok_place: Place,
+        err_place: Place,
+        err_return: Box<Statement>,
+    },
+    /// A placeholder for statements coming from the expansion of a macro,
+    /// which we removed to make the output more readable. The string is the
+    /// name of the macro.
+    ///
+    /// This statement is introduced in [crate::strip_macro_metadata].
+    MacroExpansion(String),
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Statement {
     pub meta: Meta,
     pub content: RawStatement,
 }
 
-#[derive(Debug, Clone, EnumIsA, EnumToGetters, EnumAsGetters, VariantName, VariantIndexArity)]
+/// Note that the serializer is implemented by hand, but it produces the same
+/// format as the derived one: we can derive the deserializer.
+#[derive(
+    Debug, Clone, EnumIsA, EnumToGetters, EnumAsGetters, VariantName, VariantIndexArity, Deserialize,
+)]
 pub enum Switch {
     /// Gives the `if` block and the `else` block
     If(Operand, Box<Statement>, Box<Statement>),
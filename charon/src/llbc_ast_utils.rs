@@ -1,17 +1,19 @@
 //! Implementations for [crate::llbc_ast]
 
 #![allow(dead_code)]
+use std::collections::HashSet;
 use std::ops::DerefMut;
 
 use crate::common::*;
-use crate::expressions::{MutExprVisitor, Operand, Place, Rvalue};
+use crate::expressions::{MutExprVisitor, Operand, Place, Rvalue, SharedExprVisitor};
 use crate::formatter::Formatter;
 use crate::llbc_ast::{
-    Assert, Call, ExprBody, FunDecl, FunDecls, GlobalDecl, GlobalDecls, RawStatement, Statement,
-    Switch,
+    Assert, AssertionId, Call, ExprBody, FunDecl, FunDecls, GlobalDecl, GlobalDecls, RawStatement,
+    Statement, Switch,
 };
 use crate::meta;
-use crate::meta::Meta;
+use crate::meta::{Meta, Span};
+use crate::schema;
 use crate::types::*;
 use crate::ullbc_ast::{
     fmt_call, CtxNames, FunDeclId, FunNamesFormatter, FunSigFormatter, GAstFormatter, GlobalDeclId,
@@ -19,6 +21,9 @@ use crate::ullbc_ast::{
 };
 use crate::values::*;
 use macros::make_generic_in_borrows;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::ser::SerializeTupleVariant;
 use serde::{Serialize, Serializer};
 use take_mut::take;
@@ -157,6 +162,42 @@ impl Serialize for Switch {
     }
 }
 
+impl JsonSchema for Switch {
+    fn schema_name() -> String {
+        "Switch".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        schema::one_of(vec![
+            schema::tuple_variant(
+                "If",
+                vec![
+                    gen.subschema_for::<Operand>(),
+                    gen.subschema_for::<Statement>(),
+                    gen.subschema_for::<Statement>(),
+                ],
+            ),
+            schema::tuple_variant(
+                "SwitchInt",
+                vec![
+                    gen.subschema_for::<Operand>(),
+                    gen.subschema_for::<IntegerTy>(),
+                    gen.subschema_for::<Vec<(Vec<ScalarValue>, Statement)>>(),
+                    gen.subschema_for::<Statement>(),
+                ],
+            ),
+            schema::tuple_variant(
+                "Match",
+                vec![
+                    gen.subschema_for::<Place>(),
+                    gen.subschema_for::<Vec<(Vec<VariantId::Id>, Statement)>>(),
+                    gen.subschema_for::<Statement>(),
+                ],
+            ),
+        ])
+    }
+}
+
 impl Statement {
     pub fn new(meta: Meta, content: RawStatement) -> Self {
         Statement { meta, content }
@@ -193,12 +234,25 @@ impl Statement {
             RawStatement::Drop(place) => {
                 format!("{}drop {}", tab, place.fmt_with_ctx(ctx))
             }
+            RawStatement::StorageLive(vid) => {
+                format!("{}storage_live({})", tab, ctx.format_object(*vid))
+            }
+            RawStatement::StorageDead(vid) => {
+                format!("{}storage_dead({})", tab, ctx.format_object(*vid))
+            }
             RawStatement::Assert(assert) => format!(
                 "{}assert({} == {})",
                 tab,
                 assert.cond.fmt_with_ctx(ctx),
                 assert.expected,
             ),
+            RawStatement::TaggedAssert(id, assert) => format!(
+                "{}assert@{}({} == {})",
+                tab,
+                id,
+                assert.cond.fmt_with_ctx(ctx),
+                assert.expected,
+            ),
             RawStatement::Call(call) => {
                 let Call {
                     func,
@@ -216,6 +270,7 @@ impl Statement {
             RawStatement::Break(index) => format!("{tab}break {index}"),
             RawStatement::Continue(index) => format!("{tab}continue {index}"),
             RawStatement::Nop => format!("{tab}nop"),
+            RawStatement::MacroExpansion(name) => format!("{tab}{name}!(...)"),
             RawStatement::Sequence(st1, st2) => format!(
                 "{}\n{}",
                 st1.fmt_with_ctx(tab, ctx),
@@ -311,6 +366,50 @@ impl Statement {
                     tab
                 )
             }
+            RawStatement::LetElse(place, variants, else_st) => {
+                let inner_tab = format!("{tab}{TAB_INCR}");
+                let variants: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+                format!(
+                    "{}let {} = {} else {{\n{}\n{}}}",
+                    tab,
+                    variants.join(" | "),
+                    place.fmt_with_ctx(ctx),
+                    else_st.fmt_with_ctx(&inner_tab, ctx),
+                    tab
+                )
+            }
+            RawStatement::IfLet(place, variants, then_st, else_st) => {
+                let inner_tab = format!("{tab}{TAB_INCR}");
+                let variants: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+                format!(
+                    "{}if let {} = {} {{\n{}\n{}}}\n{}else {{\n{}\n{}}}",
+                    tab,
+                    variants.join(" | "),
+                    place.fmt_with_ctx(ctx),
+                    then_st.fmt_with_ctx(&inner_tab, ctx),
+                    tab,
+                    tab,
+                    else_st.fmt_with_ctx(&inner_tab, ctx),
+                    tab
+                )
+            }
+            RawStatement::TryOp {
+                scrutinee,
+                ok_place,
+                err_place,
+                err_return,
+            } => {
+                let inner_tab = format!("{tab}{TAB_INCR}");
+                format!(
+                    "{}{} := {}? else {} => {{\n{}\n{}}}",
+                    tab,
+                    ok_place.fmt_with_ctx(ctx),
+                    scrutinee.fmt_with_ctx(ctx),
+                    err_place.fmt_with_ctx(ctx),
+                    err_return.fmt_with_ctx(&inner_tab, ctx),
+                    tab
+                )
+            }
         }
     }
 }
@@ -540,9 +639,18 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
             RawStatement::Drop(p) => {
                 self.visit_drop(p);
             }
+            RawStatement::StorageLive(vid) => {
+                self.visit_storage_live(vid);
+            }
+            RawStatement::StorageDead(vid) => {
+                self.visit_storage_dead(vid);
+            }
             RawStatement::Assert(a) => {
                 self.visit_assert(a);
             }
+            RawStatement::TaggedAssert(id, a) => {
+                self.visit_tagged_assert(id, a);
+            }
             RawStatement::Call(c) => {
                 self.visit_call(c);
             }
@@ -557,9 +665,20 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
                 self.visit_continue(i);
             }
             RawStatement::Nop => self.visit_nop(),
+            RawStatement::MacroExpansion(name) => self.visit_macro_expansion(name),
             RawStatement::Sequence(st1, st2) => self.visit_sequence(st1, st2),
             RawStatement::Switch(s) => self.visit_switch(s),
             RawStatement::Loop(lp) => self.visit_loop(lp),
+            RawStatement::LetElse(p, vids, else_st) => self.visit_let_else(p, vids, else_st),
+            RawStatement::IfLet(p, vids, then_st, else_st) => {
+                self.visit_if_let(p, vids, then_st, else_st)
+            }
+            RawStatement::TryOp {
+                scrutinee,
+                ok_place,
+                err_place,
+                err_return,
+            } => self.visit_try_op(scrutinee, ok_place, err_place, err_return),
         }
     }
 
@@ -584,15 +703,28 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
         self.visit_place(p);
     }
 
+    fn visit_storage_live(&mut self, vid: &VarId::Id) {
+        self.visit_var_id(vid);
+    }
+
+    fn visit_storage_dead(&mut self, vid: &VarId::Id) {
+        self.visit_var_id(vid);
+    }
+
     fn visit_assert(&mut self, a: &Assert) {
         self.visit_operand(&a.cond);
     }
 
+    fn visit_tagged_assert(&mut self, _: &AssertionId::Id, a: &Assert) {
+        self.visit_assert(a);
+    }
+
     fn visit_panic(&mut self) {}
     fn visit_return(&mut self) {}
     fn visit_break(&mut self, _: &usize) {}
     fn visit_continue(&mut self, _: &usize) {}
     fn visit_nop(&mut self) {}
+    fn visit_macro_expansion(&mut self, _: &String) {}
 
     fn visit_sequence(&mut self, st1: &Statement, st2: &Statement) {
         self.visit_statement(st1);
@@ -656,6 +788,39 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
     fn visit_loop(&mut self, lp: &Statement) {
         self.visit_statement(lp)
     }
+
+    fn visit_let_else(&mut self, scrut: &Place, _: &Vec<VariantId::Id>, else_st: &Statement) {
+        self.visit_place(scrut);
+        self.spawn(&mut |v| v.visit_statement(else_st));
+        self.merge();
+    }
+
+    fn visit_if_let(
+        &mut self,
+        scrut: &Place,
+        _: &Vec<VariantId::Id>,
+        then_st: &Statement,
+        else_st: &Statement,
+    ) {
+        self.visit_place(scrut);
+        self.spawn(&mut |v| v.visit_statement(then_st));
+        self.spawn(&mut |v| v.visit_statement(else_st));
+        self.merge();
+    }
+
+    fn visit_try_op(
+        &mut self,
+        scrutinee: &Operand,
+        ok_place: &Place,
+        err_place: &Place,
+        err_return: &Statement,
+    ) {
+        self.visit_operand(scrutinee);
+        self.visit_place(ok_place);
+        self.visit_place(err_place);
+        self.spawn(&mut |v| v.visit_statement(err_return));
+        self.merge();
+    }
 }
 
 } // make_generic_in_borrows
@@ -718,3 +883,160 @@ impl Statement {
         visitor.visit_statement(self);
     }
 }
+
+/// Helper for [tag_assertions]: collect the identifiers of the assertions
+/// which are already tagged.
+struct CollectAssertionIds {
+    ids: HashSet<AssertionId::Id>,
+}
+
+impl SharedTypeVisitor for CollectAssertionIds {}
+impl SharedExprVisitor for CollectAssertionIds {}
+impl SharedAstVisitor for CollectAssertionIds {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_tagged_assert(&mut self, id: &AssertionId::Id, a: &Assert) {
+        self.ids.insert(*id);
+        self.visit_assert(a)
+    }
+}
+
+/// Helper for [tag_assertions]
+struct TagAssertions {
+    /// The identifiers of the assertions which are already tagged
+    used: HashSet<AssertionId::Id>,
+    generator: AssertionId::Generator,
+    tagged: Vec<(AssertionId::Id, Span)>,
+}
+
+impl MutTypeVisitor for TagAssertions {}
+impl MutExprVisitor for TagAssertions {}
+impl MutAstVisitor for TagAssertions {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        if st.content.is_assert() {
+            let mut id = self.generator.fresh_id();
+            while self.used.contains(&id) {
+                id = self.generator.fresh_id();
+            }
+            self.tagged.push((id, st.meta.span));
+            take(&mut st.content, |content| {
+                RawStatement::TaggedAssert(id, Box::new(content.to_assert()))
+            });
+        } else {
+            self.default_visit_raw_statement(&mut st.content)
+        }
+    }
+}
+
+/// Give a unique identifier to all the assertions in a body, by replacing the
+/// [RawStatement::Assert] statements with [RawStatement::TaggedAssert].
+///
+/// Returns the list of assertion identifiers (in the order in which the
+/// assertions appear in the body), together with the span of the
+/// corresponding assertion. This is useful for the verification backends,
+/// which need to link the proof obligations they generate back to the
+/// original source code.
+///
+/// The assertions which are already tagged are left unchanged (and are not
+/// returned): we only give fresh identifiers to the new assertions.
+pub fn tag_assertions(body: &mut ExprBody) -> Vec<(AssertionId::Id, Span)> {
+    let mut collect = CollectAssertionIds {
+        ids: HashSet::new(),
+    };
+    collect.visit_statement(&body.body);
+
+    let mut visitor = TagAssertions {
+        used: collect.ids,
+        generator: AssertionId::Generator::new(),
+        tagged: Vec::new(),
+    };
+    visitor.visit_statement(&mut body.body);
+    visitor.tagged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::OperandConstantValue;
+    use crate::meta::{FileId, Loc, LocalFileId};
+
+    fn meta(line: usize) -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line, col: 0 },
+            end: Loc { line, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn assert(line: usize) -> Statement {
+        let cond = Operand::Const(
+            Ty::Literal(LiteralTy::Bool),
+            OperandConstantValue::Literal(Literal::Bool(true)),
+        );
+        let assert = Assert {
+            cond,
+            expected: true,
+        };
+        Statement::new(meta(line), RawStatement::Assert(assert))
+    }
+
+    fn tagged_ids(st: &Statement) -> Vec<AssertionId::Id> {
+        match &st.content {
+            RawStatement::TaggedAssert(id, _) => vec![*id],
+            RawStatement::Sequence(st1, st2) => {
+                let mut ids = tagged_ids(st1);
+                ids.extend(tagged_ids(st2));
+                ids
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The assertions get distinct identifiers, and tagging the body again
+    /// leaves the tagged assertions unchanged
+    #[test]
+    fn test_tag_assertions() {
+        let body = chain_statements(
+            vec![assert(1), assert(2)],
+            Statement::new(meta(3), RawStatement::Return),
+        );
+        let mut body = ExprBody {
+            meta: meta(1),
+            arg_count: 0,
+            locals: VarId::Vector::new(),
+            body,
+            liveness: None,
+        };
+
+        let tagged = tag_assertions(&mut body);
+        let ids: Vec<AssertionId::Id> = tagged.iter().map(|(id, _)| *id).collect();
+        assert!(ids == vec![AssertionId::Id::new(0), AssertionId::Id::new(1)]);
+        assert!(tagged[1].1.beg.line == 2);
+        assert!(tagged_ids(&body.body) == ids);
+
+        assert!(tag_assertions(&mut body).is_empty());
+        assert!(tagged_ids(&body.body) == ids);
+
+        // A new assertion doesn't reuse the identifiers of the tagged ones
+        let st = std::mem::replace(&mut body.body, Statement::new(meta(0), RawStatement::Nop));
+        body.body = chain_statements(vec![assert(0)], st);
+        let tagged = tag_assertions(&mut body);
+        assert!(tagged.len() == 1 && tagged[0].0 == AssertionId::Id::new(2));
+    }
+}
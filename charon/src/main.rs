@@ -37,10 +37,10 @@ extern crate rustc_tools_util;
 mod cli_options;
 mod logger;
 
-use cli_options::{CliOpts, CHARON_ARGS};
+use cli_options::{CargoManifest, CliOpts, ConfigFile, CHARON_ARGS};
 use log::trace;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use structopt::StructOpt;
 
@@ -51,21 +51,47 @@ pub fn main() {
     logger::initialize_logger();
 
     // Parse the command-line
-    let options = CliOpts::from_args();
+    let mut options = CliOpts::from_args();
     trace!("Arguments: {:?}", std::env::args());
 
+    // Complete the options with the configuration file, if there is one
+    let current_dir = env::current_dir().expect("invalid current directory");
+    if let Some(config_path) = ConfigFile::find(&current_dir) {
+        trace!("Configuration file: {:?}", config_path);
+        let res = ConfigFile::read(&config_path).and_then(|c| options.merge_config(c));
+        if let Err(msg) = res {
+            eprintln!("Invalid configuration file {:?}: {}", config_path, msg);
+            std::process::exit(1);
+        }
+    }
+
     // Check that the options are meaningful
     assert!(
         !options.lib || options.bin.is_none(),
         "Can't use --lib and --bin at the same time"
     );
 
+    assert!(
+        !options.workspace || (options.bin.is_none() && options.crate_name.is_none()),
+        "Can't use --workspace with --bin or --crate"
+    );
+
     assert!(
         !options.mir_promoted || !options.mir_optimized,
         "Can't use --mir_promoted and --mir_optimized at the same time"
     );
 
-    if let Err(code) = process(&options) {
+    assert!(
+        !options.liveness || options.ullbc,
+        "Can't use --liveness without --ullbc"
+    );
+
+    let res = if options.workspace {
+        process_workspace(&options, &current_dir)
+    } else {
+        process(&options, None)
+    };
+    if let Err(code) = res {
         std::process::exit(code);
     }
 }
@@ -82,7 +108,26 @@ fn path() -> PathBuf {
     path
 }
 
-fn process(options: &CliOpts) -> Result<(), i32> {
+/// Translate the members of a workspace one after the other. Every member
+/// is translated to its own file, named after the crate.
+fn process_workspace(options: &CliOpts, root: &Path) -> Result<(), i32> {
+    let members = match CargoManifest::workspace_members(root) {
+        Ok(members) => members,
+        Err(msg) => {
+            eprintln!("Could not read the workspace members: {}", msg);
+            return Err(1);
+        }
+    };
+    trace!("Workspace members: {:?}", members);
+    for member in &members {
+        process(options, Some(member))?;
+    }
+    Ok(())
+}
+
+/// Translate a crate. If `package` is provided, we translate this package of
+/// the current workspace.
+fn process(options: &CliOpts, package: Option<&str>) -> Result<(), i32> {
     // Compute the arguments of the command to call cargo
     //let cargo_subcommand = "build";
     let cargo_subcommand = "rustc";
@@ -99,6 +144,11 @@ fn process(options: &CliOpts) -> Result<(), i32> {
 
     cmd.arg(cargo_subcommand);
 
+    if let Some(package) = package {
+        cmd.arg("--package");
+        cmd.arg(package);
+    }
+
     if options.lib {
         cmd.arg("--lib");
     }
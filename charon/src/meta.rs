@@ -2,7 +2,8 @@
 
 pub use crate::meta_utils::*;
 use macros::{generate_index_type, EnumAsGetters, EnumIsA};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 generate_index_type!(LocalFileId);
@@ -13,15 +14,28 @@ pub mod FileId {
     use crate::meta::*;
 
     #[derive(
-        Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, EnumIsA, EnumAsGetters, Serialize,
+        Debug,
+        Clone,
+        Copy,
+        Hash,
+        PartialEq,
+        Eq,
+        PartialOrd,
+        Ord,
+        EnumIsA,
+        EnumAsGetters,
+        Serialize,
+        Deserialize,
+        JsonSchema,
     )]
+    #[schemars(rename = "FileId")]
     pub enum Id {
         LocalId(LocalFileId::Id),
         VirtualId(VirtualFileId::Id),
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Loc {
     /// The (1-based) line number.
     pub line: usize,
@@ -30,7 +44,7 @@ pub struct Loc {
 }
 
 /// Span information
-#[derive(Debug, Copy, Clone, Serialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Span {
     pub file_id: FileId::Id,
     pub beg: Loc,
@@ -38,7 +52,7 @@ pub struct Span {
 }
 
 /// Meta information about a piece of code (block, statement, etc.)
-#[derive(Debug, Copy, Clone, Serialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Meta {
     /// The source code span.
     ///
@@ -62,13 +76,29 @@ pub struct Meta {
     pub span: Span,
     /// Where the code actually comes from, in case of macro expansion/inlining/etc.
     pub generated_from_span: Option<Span>,
+    /// `true` if the code comes from the expansion of a macro (`assert!`,
+    /// `println!`, etc.). Note that the desugarings (of the `for` loops, of
+    /// the `?` operator, etc.) are not considered as macro expansions.
+    pub is_from_macro: bool,
+    /// The name of the outermost macro the code comes from, if
+    /// [Self::is_from_macro].
+    pub macro_name: Option<MacroName>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+/// The name of a macro, for instance `assert`.
+///
+/// We leak the names (there are only a few distinct macros in a crate) so
+/// that [Meta] can remain `Copy`. See [MacroName::new].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MacroName(pub(crate) &'static str);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, JsonSchema)]
 pub struct FileInfo {}
 
 /// A filename.
-#[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord, Serialize)]
+#[derive(
+    Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 pub enum FileName {
     /// A remapped path (namely paths into stdlib)
     Virtual(PathBuf),
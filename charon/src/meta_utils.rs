@@ -2,16 +2,26 @@
 #![allow(dead_code)]
 
 use crate::meta::*;
+#[cfg(feature = "rustc")]
 use rustc_hir::def_id::DefId;
+#[cfg(feature = "rustc")]
 use rustc_middle::ty::TyCtxt;
+#[cfg(feature = "rustc")]
 use rustc_session::Session;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::iter::Iterator;
+use std::sync::{Mutex, OnceLock};
 
 /// Retrieve the Rust span from a def id.
 ///
 /// Rem.: we use [TyCtxt::def_span], not [TyCtxt::def_ident_span] to retrieve
 /// the span.
+#[cfg(feature = "rustc")]
 pub fn get_rspan_from_def_id(ctx: TyCtxt, def_id: DefId) -> rustc_span::Span {
     ctx.def_span(def_id)
 }
@@ -53,10 +63,18 @@ pub fn combine_meta(m0: &Meta, m1: &Meta) -> Meta {
 
     // We don't attempt to merge the "generated from" spans: they might
     // come from different files, and even if they come from the same files
-    // they might come from different macros, etc.
+    // they might come from different macros, etc. We only preserve the macro
+    // information if both pieces of code come from the same macro.
+    let macro_name = if m0.macro_name == m1.macro_name {
+        m0.macro_name
+    } else {
+        None
+    };
     Meta {
         span,
         generated_from_span: None,
+        is_from_macro: macro_name.is_some(),
+        macro_name,
     }
 }
 
@@ -71,6 +89,70 @@ pub fn combine_meta_iter<'a, T: Iterator<Item = &'a Meta>>(mut ms: T) -> Meta {
     mc
 }
 
+impl MacroName {
+    /// Intern a macro name: we leak every distinct name once.
+    pub fn new(name: &str) -> MacroName {
+        static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+        let mut names = NAMES
+            .get_or_init(|| Mutex::new(HashSet::new()))
+            .lock()
+            .unwrap();
+        match names.get(name) {
+            Some(name) => MacroName(name),
+            None => {
+                let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+                names.insert(name);
+                MacroName(name)
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl std::fmt::Display for MacroName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for MacroName {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacroName {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Ok(MacroName::new(&name))
+    }
+}
+
+impl JsonSchema for MacroName {
+    fn schema_name() -> String {
+        "MacroName".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    // We serialize the macro names as strings
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(feature = "rustc")]
 pub fn convert_filename(name: &rustc_span::FileName) -> FileName {
     match name {
         rustc_span::FileName::Real(name) => {
@@ -103,6 +185,7 @@ pub fn convert_filename(name: &rustc_span::FileName) -> FileName {
 }
 
 /// Return the filename from a Rust span.
+#[cfg(feature = "rustc")]
 pub fn get_filename_from_rspan(sess: &Session, span: rustc_span::Span) -> FileName {
     // Retrieve the source map, which contains information about the source file:
     // we need it to be able to interpret the span.
@@ -115,6 +198,7 @@ pub fn get_filename_from_rspan(sess: &Session, span: rustc_span::Span) -> FileNa
     convert_filename(&name)
 }
 
+#[cfg(feature = "rustc")]
 pub fn convert_loc(loc: rustc_span::Loc) -> Loc {
     Loc {
         line: loc.line,
@@ -123,6 +207,7 @@ pub fn convert_loc(loc: rustc_span::Loc) -> Loc {
 }
 
 // TODO: remove?
+#[cfg(feature = "rustc")]
 pub fn span_to_string(sess: &Session, span: rustc_span::Span) -> String {
     // Retrieve the source map, which contains information about the source file:
     // we need it to be able to interpret the span.
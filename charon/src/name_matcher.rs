@@ -0,0 +1,159 @@
+//! Patterns to select items by name, for instance to mark them as opaque
+//! (see [crate::cli_options::CliOpts::opaque_modules]).
+//!
+//! The patterns ignore the disambiguators of the names: we only match the
+//! identifiers (see [Name::segments]).
+#![allow(dead_code)]
+
+use crate::names::Name;
+use regex::Regex;
+
+/// A pattern over the item names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameMatcher {
+    /// The name must be exactly this path
+    Exact(Vec<String>),
+    /// The name must start with this path: this selects an item (or a module)
+    /// together with all the items it contains.
+    Prefix(Vec<String>),
+    /// A glob pattern over the path written with `::` separators, like
+    /// `std::collections::*`. `*` matches any sequence of characters
+    /// (including `::`), and `?` matches a single character.
+    Glob(String),
+    /// A regular expression, which must match the whole path (written with
+    /// `::` separators).
+    Regex(String),
+}
+
+impl NameMatcher {
+    /// Parse a pattern given on the command line. `krate` is the name of the
+    /// crate we extract.
+    ///
+    /// - `regex:RE` is a regular expression
+    /// - `a::b::*` selects everything inside the module `a::b`
+    /// - the other patterns which contain `*` or `?` are glob patterns
+    /// - `a::b` selects `a::b` and everything it contains
+    /// - `module` (a single identifier) selects the module of the crate we
+    ///   extract: it is equivalent to `krate::module`
+    pub fn parse(pattern: &str, krate: &str) -> Result<NameMatcher, String> {
+        if let Some(re) = pattern.strip_prefix("regex:") {
+            return match Regex::new(re) {
+                Ok(_) => Ok(NameMatcher::Regex(re.to_string())),
+                Err(err) => Err(format!("invalid regular expression `{re}`: {err}")),
+            };
+        }
+
+        let is_glob = |s: &str| s.contains('*') || s.contains('?');
+        if let Some(prefix) = pattern.strip_suffix("::*") {
+            if !is_glob(prefix) {
+                return Ok(NameMatcher::Prefix(split_path(prefix)));
+            }
+        }
+        if is_glob(pattern) {
+            return Ok(NameMatcher::Glob(pattern.to_string()));
+        }
+
+        let mut path = split_path(pattern);
+        if path.len() == 1 {
+            path.insert(0, krate.to_string());
+        }
+        Ok(NameMatcher::Prefix(path))
+    }
+
+    /// Check if a name matches the pattern.
+    pub fn matches(&self, name: &Name) -> bool {
+        let segments = name.segments();
+        match self {
+            NameMatcher::Exact(path) => {
+                segments.len() == path.len() && segments.iter().zip(path).all(|(s, p)| s == p)
+            }
+            NameMatcher::Prefix(path) => {
+                segments.len() >= path.len() && segments.iter().zip(path).all(|(s, p)| s == p)
+            }
+            NameMatcher::Glob(glob) => matches_regex(&glob_to_regex(glob), &segments),
+            NameMatcher::Regex(re) => matches_regex(&format!("^(?:{re})$"), &segments),
+        }
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split("::").map(|s| s.to_string()).collect()
+}
+
+/// Convert a glob pattern to an (anchored) regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = "^".to_string();
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// The regular expressions are checked when parsing the patterns: we
+/// consider that an invalid expression matches nothing.
+fn matches_regex(re: &str, segments: &[&str]) -> bool {
+    match Regex::new(re) {
+        Ok(re) => re.is_match(&segments.join("::")),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(path: &str) -> Name {
+        Name::from(split_path(path))
+    }
+
+    fn parse(pattern: &str) -> NameMatcher {
+        NameMatcher::parse(pattern, "krate").unwrap()
+    }
+
+    #[test]
+    fn test_prefix() {
+        let m = parse("std::*");
+        assert!(m == NameMatcher::Prefix(vec!["std".to_string()]));
+        assert!(m.matches(&name("std::vec::Vec")));
+        assert!(!m.matches(&name("alloc::vec::Vec")));
+        assert!(!m.matches(&name("stdx::vec::Vec")));
+    }
+
+    #[test]
+    fn test_module_of_the_crate() {
+        let m = parse("hashmap_utils");
+        assert!(m.matches(&name("krate::hashmap_utils::serialize")));
+        assert!(!m.matches(&name("krate::hashmap::HashMap")));
+        assert!(!m.matches(&name("other::hashmap_utils::serialize")));
+    }
+
+    #[test]
+    fn test_exact() {
+        let m = NameMatcher::Exact(split_path("std::vec::Vec"));
+        assert!(m.matches(&name("std::vec::Vec")));
+        assert!(!m.matches(&name("std::vec::Vec::push")));
+    }
+
+    #[test]
+    fn test_glob() {
+        let m = parse("std::collections::*Map");
+        assert!(m == NameMatcher::Glob("std::collections::*Map".to_string()));
+        assert!(m.matches(&name("std::collections::HashMap")));
+        assert!(m.matches(&name("std::collections::btree::BTreeMap")));
+        assert!(!m.matches(&name("std::collections::HashSet")));
+        assert!(parse("std::vec::Ve?").matches(&name("std::vec::Vec")));
+    }
+
+    #[test]
+    fn test_regex() {
+        let m = parse("regex:(std|core)::.*");
+        assert!(m.matches(&name("core::option::Option")));
+        assert!(!m.matches(&name("alloc::vec::Vec")));
+        assert!(NameMatcher::parse("regex:(", "krate").is_err());
+    }
+}
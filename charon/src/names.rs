@@ -4,12 +4,13 @@
 pub use crate::names_utils::*;
 use macros::generate_index_type;
 use macros::EnumIsA;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 generate_index_type!(Disambiguator);
 
 /// See the comments for [Name]
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, EnumIsA)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, EnumIsA)]
 pub enum PathElem {
     Ident(String),
     Disambiguator(Disambiguator::Id),
@@ -50,13 +51,14 @@ pub enum PathElem {
 /// name clashes anyway. Still, we might want to be more precise in the future.
 ///
 /// Also note that the first path element in the name is always the crate name.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Name {
     pub name: Vec<PathElem>,
 }
 
 pub type ModuleName = Name;
 pub type TypeName = Name;
+pub type TraitName = Name;
 pub type ItemName = Name;
 pub type FunName = Name;
 pub type GlobalName = Name;
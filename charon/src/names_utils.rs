@@ -5,11 +5,18 @@
 #![allow(dead_code)]
 
 use crate::names::*;
+#[cfg(feature = "rustc")]
 use rustc_hir::def_id::DefId;
+#[cfg(feature = "rustc")]
 use rustc_hir::definitions::DefPathData;
+#[cfg(feature = "rustc")]
 use rustc_hir::{Item, ItemKind};
+#[cfg(feature = "rustc")]
 use rustc_middle::ty::TyCtxt;
-use serde::{Serialize, Serializer};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashSet;
 
 impl PathElem {
@@ -48,6 +55,17 @@ impl Name {
         self.name.len()
     }
 
+    /// The identifiers of the name, ignoring the disambiguators.
+    pub fn segments(&self) -> Vec<&str> {
+        self.name
+            .iter()
+            .filter_map(|e| match e {
+                PathElem::Ident(s) => Some(s.as_str()),
+                PathElem::Disambiguator(_) => None,
+            })
+            .collect()
+    }
+
     /// Compare the name to a constant array.
     /// This ignores disambiguators.
     ///
@@ -68,6 +86,20 @@ impl Name {
         true
     }
 
+    /// Check if the name matches a path written with `::` separators. A path
+    /// with a single segment (`list_length`) only has to match the last
+    /// identifier of the name, while a longer path
+    /// (`no_nested_borrows::list_length`) has to match the whole name.
+    /// This ignores disambiguators.
+    pub fn matches_path(&self, path: &str) -> bool {
+        let ref_name: Vec<&str> = path.split("::").collect();
+        if ref_name.len() == 1 {
+            self.segments().last() == Some(&path)
+        } else {
+            self.equals_ref_name(&ref_name)
+        }
+    }
+
     /// Compare the name to a constant array.
     /// This ignores disambiguators.
     pub fn equals_ref_name(&self, ref_name: &[&str]) -> bool {
@@ -105,6 +137,18 @@ impl std::fmt::Display for Name {
     }
 }
 
+/// Sort some declarations by name.
+///
+/// The ids of the declarations depend on the order in which we explore the
+/// crate, while their names don't: we use this function to make the
+/// generated files more stable. Note that the sort is stable: the
+/// declarations which have the same name remain in the original order.
+pub fn sort_by_name<'a, T>(decls: impl Iterator<Item = (&'a Name, T)>) -> Vec<T> {
+    let mut decls: Vec<(&'a Name, T)> = decls.collect();
+    decls.sort_by_cached_key(|(name, _)| name.to_string());
+    decls.into_iter().map(|(_, d)| d).collect()
+}
+
 impl Serialize for Name {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -116,7 +160,28 @@ impl Serialize for Name {
     }
 }
 
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = Vec::deserialize(deserializer)?;
+        Ok(Name { name })
+    }
+}
+
+impl JsonSchema for Name {
+    fn schema_name() -> String {
+        "Name".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <Vec<PathElem>>::json_schema(gen)
+    }
+}
+
 /// Retrieve an item name from a `DefId`.
+#[cfg(feature = "rustc")]
 pub fn item_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> ItemName {
     trace!("{:?}", def_id);
 
@@ -238,19 +303,43 @@ pub fn item_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> ItemName {
                         let mut type_name = type_def_id_to_name(tcx, adt_def.did());
                         type_name.name.pop().unwrap().to_string()
                     }
-                    // Builtin cases.
-                    rustc_middle::ty::TyKind::Int(_)
-                    | rustc_middle::ty::TyKind::Uint(_)
-                    | rustc_middle::ty::TyKind::Array(..)
-                    | rustc_middle::ty::TyKind::Slice(_) => {
-                        format!("{ty:?}")
-                    }
-                    _ => unreachable!(),
+                    // Builtin cases (integers, arrays, slices, `str`, raw
+                    // pointers, etc.), and the blanket impls (the type is
+                    // then a type parameter, as in `impl<T> Trait for T`)
+                    _ => format!("{ty:?}"),
                 }));
             }
+            DefPathData::ClosureExpr => {
+                // Closures are anonymous: we identify them with the
+                // disambiguator (there may be several closures in the
+                // same function)
+                name.push(PathElem::Disambiguator(Disambiguator::Id::new(
+                    data.disambiguator as usize,
+                )));
+                name.push(PathElem::Ident("closure".to_string()));
+            }
             DefPathData::ImplTrait => {
-                // TODO: this should work the same as for `Impl`
-                unimplemented!();
+                // `impl Trait` types (in return position) are anonymous: as
+                // for the closures, we identify them with the disambiguator
+                name.push(PathElem::Disambiguator(Disambiguator::Id::new(
+                    data.disambiguator as usize,
+                )));
+                name.push(PathElem::Ident("impl_trait".to_string()));
+            }
+            DefPathData::AnonConst => {
+                // Anonymous constants (like the const generic expressions in
+                // `[u8; N * 2]`): as for the closures, we identify them with
+                // the disambiguator
+                name.push(PathElem::Disambiguator(Disambiguator::Id::new(
+                    data.disambiguator as usize,
+                )));
+                name.push(PathElem::Ident("const".to_string()));
+            }
+            DefPathData::ForeignMod => {
+                // The items declared in an `extern` block live in the
+                // namespace of the enclosing module: we ignore this path
+                // element (note that a module may contain several `extern`
+                // blocks, which are distinguished by their disambiguators).
             }
             DefPathData::MacroNs(symbol) => {
                 assert!(data.disambiguator == 0); // Sanity check
@@ -290,22 +379,27 @@ pub fn item_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> ItemName {
     Name { name }
 }
 
+#[cfg(feature = "rustc")]
 pub fn type_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> TypeName {
     item_def_id_to_name(tcx, def_id)
 }
 
+#[cfg(feature = "rustc")]
 pub fn module_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> ModuleName {
     item_def_id_to_name(tcx, def_id)
 }
 
+#[cfg(feature = "rustc")]
 pub fn function_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> FunName {
     item_def_id_to_name(tcx, def_id)
 }
 
+#[cfg(feature = "rustc")]
 pub fn global_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> GlobalName {
     item_def_id_to_name(tcx, def_id)
 }
 
+#[cfg(feature = "rustc")]
 pub fn trait_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> FunName {
     item_def_id_to_name(tcx, def_id)
 }
@@ -317,34 +411,79 @@ pub fn trait_def_id_to_name(tcx: TyCtxt, def_id: DefId) -> FunName {
 ///
 /// Rk.: this function is only used by [crate::register], and implemented with this
 /// context in mind.
+#[cfg(feature = "rustc")]
 pub fn hir_item_to_name(tcx: TyCtxt, item: &Item) -> Option<HirItemName> {
     let def_id = item.owner_id.to_def_id();
 
     // TODO: calling different functions to retrieve the name is not very
     // satisfying below
     match &item.kind {
-        ItemKind::OpaqueTy(_) => unimplemented!(),
-        ItemKind::Union(_, _) => unimplemented!(),
         ItemKind::ExternCrate(_) => {
             // We ignore this -
             // TODO: investigate when extern crates appear, and why
             Option::None
         }
         ItemKind::Use(_, _) => Option::None,
-        ItemKind::TyAlias(_, _) => {
-            // We ignore the type aliases - it seems they are inlined
-            Option::None
-        }
         ItemKind::Enum(_, _)
         | ItemKind::Struct(_, _)
+        | ItemKind::Union(_, _)
+        | ItemKind::TyAlias(_, _)
+        | ItemKind::OpaqueTy(_)
         | ItemKind::Fn(_, _, _)
         | ItemKind::Impl(_)
         | ItemKind::Mod(_)
         | ItemKind::Const(_, _)
         | ItemKind::Static(_, _, _)
+        | ItemKind::ForeignMod { .. }
+        | ItemKind::Trait(..)
         | ItemKind::Macro(_, _) => Option::Some(item_def_id_to_name(tcx, def_id)),
         _ => {
             unimplemented!("{:?}", item.kind);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `no_nested_borrows::{impl#0}::list_length`
+    fn list_length() -> Name {
+        Name {
+            name: vec![
+                PathElem::Ident("no_nested_borrows".to_string()),
+                PathElem::Disambiguator(Disambiguator::Id::new(0)),
+                PathElem::Ident("list_length".to_string()),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_matches_path() {
+        let name = list_length();
+        assert!(name.matches_path("list_length"));
+        assert!(name.matches_path("no_nested_borrows::list_length"));
+        assert!(!name.matches_path("no_nested_borrows"));
+        assert!(!name.matches_path("length"));
+        assert!(!name.matches_path("other::list_length"));
+    }
+
+    #[test]
+    fn test_sort_by_name() {
+        let mk_name = |path: &[&str]| Name {
+            name: path
+                .iter()
+                .map(|s| PathElem::Ident(s.to_string()))
+                .collect(),
+        };
+        let a = mk_name(&["krate", "a"]);
+        let b = mk_name(&["krate", "b"]);
+        let c = mk_name(&["krate", "m", "c"]);
+        // The result doesn't depend on the order of the input, and the
+        // declarations with the same name remain in the original order
+        let decls = [(&c, 0), (&a, 1), (&b, 2), (&a, 3)];
+        assert!(sort_by_name(decls.iter().copied()) == vec![1, 3, 2, 0]);
+        let decls = [(&b, 2), (&a, 1), (&a, 3), (&c, 0)];
+        assert!(sort_by_name(decls.iter().copied()) == vec![1, 3, 2, 0]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Normalize the projections of the places, by merging the consecutive
+//! projections which can be expressed as a single projection. For instance,
+//! reading the element at offset `k` of the subslice starting at offset `i`
+//! is the same as reading the element at offset `i + k`:
+//! ```text
+//! (*s)[i..][k] ~~> (*s)[i + k]
+//! ```
+//!
+//! We use the types of the places to count the offsets from the beginning of
+//! the arrays (whose length we know), rather than from their end: this gives
+//! a unique representation to the accesses to the elements of an array, and
+//! allows more merges.
+//!
+//! We only merge the projections when the resulting place denotes the same
+//! memory location and has the same type. In particular, we never remove the
+//! dereferences: they follow pointers (`*(*p)` where `p: &&T` goes through
+//! two borrows, and eliding a mutable borrow would change the aliasing). We
+//! don't flatten the field projections either, even when projecting through a
+//! `#[repr(transparent)]` newtype: the field projections are typed by the
+//! ADT they project from (see [crate::expressions::FieldProjKind]), so
+//! `x.0.1` can't be expressed as a single field projection of `x`.
+
+#![allow(dead_code)]
+
+use crate::expressions::{MutExprVisitor, Place, Projection, ProjectionElem};
+use crate::llbc_ast::{CtxNames, FunDecls, GlobalDecls, MutAstVisitor, Var};
+use crate::typecheck::project_ty;
+use crate::types::*;
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+use crate::values::*;
+
+/// Merge two consecutive projections, if possible.
+fn merge_projection_elems(pe1: &ProjectionElem, pe2: &ProjectionElem) -> Option<ProjectionElem> {
+    use ProjectionElem::*;
+    match (pe1, pe2) {
+        // An element of a subslice, counted from the beginning
+        (
+            Subslice { from, .. },
+            ConstantIndex {
+                offset,
+                from_end: false,
+            },
+        ) => Some(ConstantIndex {
+            offset: from + offset,
+            from_end: false,
+        }),
+        // An element of a subslice, counted from the end: the subslice spans
+        // from `from` to `len - to` (slice) or to `to` (array)
+        (
+            Subslice {
+                to, from_end: true, ..
+            },
+            ConstantIndex {
+                offset,
+                from_end: true,
+            },
+        ) => Some(ConstantIndex {
+            offset: to + offset,
+            from_end: true,
+        }),
+        (
+            Subslice {
+                to,
+                from_end: false,
+                ..
+            },
+            ConstantIndex {
+                offset,
+                from_end: true,
+            },
+        ) => Some(ConstantIndex {
+            offset: to - offset,
+            from_end: false,
+        }),
+        // A subslice of a subslice (both of the same kind)
+        (
+            Subslice {
+                from: from1,
+                to: to1,
+                from_end: true,
+            },
+            Subslice {
+                from: from2,
+                to: to2,
+                from_end: true,
+            },
+        ) => Some(Subslice {
+            from: from1 + from2,
+            to: to1 + to2,
+            from_end: true,
+        }),
+        (
+            Subslice {
+                from: from1,
+                from_end: false,
+                ..
+            },
+            Subslice {
+                from: from2,
+                to: to2,
+                from_end: false,
+            },
+        ) => Some(Subslice {
+            from: from1 + from2,
+            to: from1 + to2,
+            from_end: false,
+        }),
+        _ => None,
+    }
+}
+
+/// Count the offset of an element of an array from the beginning of the
+/// array, if we know its length. `ty` is the type of the place we project from.
+fn normalize_projection_elem(ty: Option<&ETy>, pe: &ProjectionElem) -> ProjectionElem {
+    match (pe, ty) {
+        (
+            ProjectionElem::ConstantIndex {
+                offset,
+                from_end: true,
+            },
+            Some(Ty::Adt(TypeId::Assumed(AssumedTy::Array), _, _, cgs)),
+        ) => match cgs.first() {
+            Some(ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(_, len))))
+                if offset <= len =>
+            {
+                ProjectionElem::ConstantIndex {
+                    offset: len - offset,
+                    from_end: false,
+                }
+            }
+            _ => pe.clone(),
+        },
+        _ => pe.clone(),
+    }
+}
+
+/// Normalize the projection of a place (see the module documentation).
+///
+/// We need the local variables and the type declarations to compute the types
+/// of the intermediate places: if we can't compute them (because a place
+/// projects out of an opaque type, for instance) we only merge the projections
+/// which don't depend on the types.
+pub fn normalize_place(place: &Place, vars: &VarId::Vector<Var>, type_defs: &TypeDecls) -> Place {
+    let mut ty: Option<ETy> = vars.get(place.var_id).map(|v| (*v.ty).clone());
+    let mut normalized: Projection = Vec::with_capacity(place.projection.len());
+    for pe in &place.projection {
+        let pe = normalize_projection_elem(ty.as_ref(), pe);
+        ty = ty.and_then(|ty| project_ty(type_defs, &ty, &pe));
+
+        let merged = normalized
+            .last()
+            .and_then(|last| merge_projection_elems(last, &pe));
+        match merged {
+            Some(merged) => *normalized.last_mut().unwrap() = merged,
+            None => normalized.push(pe),
+        }
+    }
+    Place {
+        var_id: place.var_id,
+        projection: normalized,
+    }
+}
+
+struct Transform<'a> {
+    vars: &'a VarId::Vector<Var>,
+    type_defs: &'a TypeDecls,
+}
+
+impl<'a> MutTypeVisitor for Transform<'a> {}
+
+impl<'a> MutExprVisitor for Transform<'a> {
+    fn visit_place(&mut self, p: &mut Place) {
+        *p = normalize_place(p, self.vars, self.type_defs);
+    }
+}
+
+impl<'a> MutAstVisitor for Transform<'a> {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+}
+
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to normalize the projections in decl: {name}\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+        let mut visitor = Transform {
+            vars: &b.locals,
+            type_defs: fmt_ctx.type_context,
+        };
+        visitor.visit_statement(&mut b.body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ProjectionElem::*;
+
+    fn u32_ty() -> ETy {
+        Ty::Literal(LiteralTy::Integer(IntegerTy::U32))
+    }
+
+    fn slice_ty(ty: ETy) -> ETy {
+        Ty::Adt(TypeId::Assumed(AssumedTy::Slice), vec![], vec![ty], vec![])
+    }
+
+    fn array_ty(ty: ETy, len: u64) -> ETy {
+        let len = ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(PointerSize::P64, len)));
+        Ty::Adt(
+            TypeId::Assumed(AssumedTy::Array),
+            vec![],
+            vec![ty],
+            vec![len],
+        )
+    }
+
+    fn ref_ty(ty: ETy, kind: RefKind) -> ETy {
+        Ty::Ref(ErasedRegion::Erased, Box::new(ty), kind)
+    }
+
+    /// The locals of a function with a single input argument of type `ty`
+    fn vars(ty: ETy) -> VarId::Vector<Var> {
+        let mut vars = VarId::Vector::new();
+        for (index, ty) in [(VarId::ZERO, Ty::mk_unit()), (VarId::Id::new(1), ty)] {
+            vars.push_back(Var {
+                index,
+                name: None,
+                ty: ty.into(),
+            });
+        }
+        vars
+    }
+
+    fn place(projection: Vec<ProjectionElem>) -> Place {
+        Place {
+            var_id: VarId::Id::new(1),
+            projection,
+        }
+    }
+
+    /// `(*(*p))[1..][2]` where `p: &mut &[u32]`: the dereferences remain (in
+    /// particular, the one of the mutable reference), but the indexing in the
+    /// subslice becomes `(*(*p))[3]`
+    #[test]
+    fn test_pointer_chain() {
+        let ty = ref_ty(ref_ty(slice_ty(u32_ty()), RefKind::Shared), RefKind::Mut);
+        let p = place(vec![
+            Deref,
+            Deref,
+            Subslice {
+                from: 1,
+                to: 0,
+                from_end: true,
+            },
+            ConstantIndex {
+                offset: 2,
+                from_end: false,
+            },
+        ]);
+        let normalized = normalize_place(&p, &vars(ty), &TypeDecls::new());
+        assert!(normalized.projection.len() < p.projection.len());
+        assert!(
+            normalized
+                == place(vec![
+                    Deref,
+                    Deref,
+                    ConstantIndex {
+                        offset: 3,
+                        from_end: false
+                    }
+                ])
+        );
+    }
+
+    #[test]
+    fn test_subslices() {
+        let vars = vars(array_ty(u32_ty(), 6));
+        let type_defs = TypeDecls::new();
+
+        // `a[1..5][1..3]` where `a: [u32; 6]`: `a[2..4]`
+        let p = place(vec![
+            Subslice {
+                from: 1,
+                to: 5,
+                from_end: false,
+            },
+            Subslice {
+                from: 1,
+                to: 3,
+                from_end: false,
+            },
+        ]);
+        let expected = place(vec![Subslice {
+            from: 2,
+            to: 4,
+            from_end: false,
+        }]);
+        assert!(normalize_place(&p, &vars, &type_defs) == expected);
+
+        // The last element of `a[1..5]` is `a[4]`
+        let p = place(vec![
+            Subslice {
+                from: 1,
+                to: 5,
+                from_end: false,
+            },
+            ConstantIndex {
+                offset: 1,
+                from_end: true,
+            },
+        ]);
+        let expected = place(vec![ConstantIndex {
+            offset: 4,
+            from_end: false,
+        }]);
+        assert!(normalize_place(&p, &vars, &type_defs) == expected);
+    }
+
+    /// We know the length of the arrays: the offsets counted from their end
+    /// are counted from their beginning instead (but not for the slices)
+    #[test]
+    fn test_array_from_end() {
+        let type_defs = TypeDecls::new();
+        let last = place(vec![ConstantIndex {
+            offset: 1,
+            from_end: true,
+        }]);
+        let expected = place(vec![ConstantIndex {
+            offset: 5,
+            from_end: false,
+        }]);
+        let array_vars = vars(array_ty(u32_ty(), 6));
+        assert!(normalize_place(&last, &array_vars, &type_defs) == expected);
+
+        let slice_vars = vars(ref_ty(slice_ty(u32_ty()), RefKind::Shared));
+        let last = place(vec![
+            Deref,
+            ConstantIndex {
+                offset: 1,
+                from_end: true,
+            },
+        ]);
+        assert!(normalize_place(&last, &slice_vars, &type_defs) == last);
+    }
+
+    /// The dereferences are left unchanged, and we don't need the types to
+    /// merge the projections
+    #[test]
+    fn test_unchanged() {
+        let p = place(vec![
+            Deref,
+            DerefBox,
+            Deref,
+            ConstantIndex {
+                offset: 0,
+                from_end: false,
+            },
+        ]);
+        assert!(normalize_place(&p, &VarId::Vector::new(), &TypeDecls::new()) == p);
+    }
+}
@@ -0,0 +1,132 @@
+//! Reconstruct the `if let ... { ... } else { ... }` statements.
+//!
+//! MIR compiles `if let Some(v) = x { ... } else { ... }` to a switch over
+//! the discriminant of `x`, with a single branch for the matched variant, in
+//! which the pattern bindings are introduced, and an `otherwise` branch.
+//! Once the discriminant reads have been merged with the switches
+//! ([crate::remove_read_discriminant]), we get:
+//! ```text
+//! match x {
+//!   1 => {
+//!     v := (x as variant @1).0;
+//!     ...
+//!   },
+//!   _ => {
+//!     ...
+//!   }
+//! }
+//! ```
+//! We transform this to:
+//! ```text
+//! if let 1 = x {
+//!   v := (x as variant @1).0;
+//!   ...
+//! }
+//! else {
+//!   ...
+//! }
+//! ```
+//! We only do so if the branch starts by binding a field of the matched
+//! variant (otherwise, there is no pattern to speak of: the code was
+//! probably written with a `match`). Note that if the `otherwise` branch
+//! diverges, the match is reconstructed as a `let ... else` by
+//! [crate::reconstruct_let_else], which must thus be applied before.
+
+use take_mut::take;
+
+use crate::expressions::{FieldProjKind, MutExprVisitor, Operand, Place, ProjectionElem, Rvalue};
+use crate::llbc_ast::{
+    CtxNames, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, Statement, Switch,
+};
+use crate::types::{MutTypeVisitor, VariantId};
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+
+/// Check if a place is a field of one of the given variants of the
+/// scrutinee.
+fn is_variant_field(scrut: &Place, variants: &[VariantId::Id], p: &Place) -> bool {
+    let n = scrut.projection.len();
+    if p.var_id != scrut.var_id
+        || p.projection.len() != n + 1
+        || p.projection[..n] != scrut.projection[..]
+    {
+        return false;
+    }
+    match &p.projection[n] {
+        ProjectionElem::Field(FieldProjKind::Adt(_, Some(vid)), _)
+        | ProjectionElem::Field(FieldProjKind::Option(vid), _) => variants.contains(vid),
+        _ => false,
+    }
+}
+
+/// Check if a statement binds a field of one of the given variants of the
+/// scrutinee (either by value or by reference). We ignore the `storage_live`
+/// statements which introduce the bindings.
+fn binds_variant_field(scrut: &Place, variants: &[VariantId::Id], st: &Statement) -> bool {
+    let mut st = st;
+    while let RawStatement::Sequence(st1, st2) = &st.content {
+        if st1.content.is_storage_live() {
+            st = st2;
+        } else {
+            st = st1;
+            break;
+        }
+    }
+    match &st.content {
+        RawStatement::Assign(_, Rvalue::Use(Operand::Move(p) | Operand::Copy(p)))
+        | RawStatement::Assign(_, Rvalue::Ref(p, _)) => is_variant_field(scrut, variants, p),
+        _ => false,
+    }
+}
+
+/// Check if a statement is a match with a single branch, which starts by
+/// binding a field of the matched variant.
+fn is_if_let(st: &Statement) -> bool {
+    match &st.content {
+        RawStatement::Switch(Switch::Match(scrut, targets, _)) => {
+            targets.len() == 1 && binds_variant_field(scrut, &targets[0].0, &targets[0].1)
+        }
+        _ => false,
+    }
+}
+
+/// Transform a match which satisfies [is_if_let].
+fn to_if_let(st: Statement) -> Statement {
+    let (p, mut targets, otherwise) = st.content.to_switch().to_match();
+    let (variants, branch) = targets.pop().unwrap();
+    Statement::new(
+        st.meta,
+        RawStatement::IfLet(p, variants, Box::new(branch), otherwise),
+    )
+}
+
+struct Transform {}
+
+impl MutTypeVisitor for Transform {}
+impl MutExprVisitor for Transform {}
+
+impl MutAstVisitor for Transform {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        // Bottom-up
+        self.default_visit_raw_statement(&mut st.content);
+
+        if is_if_let(st) {
+            take(st, to_if_let);
+        }
+    }
+}
+
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to reconstruct the if-let statements in decl: {name}\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+        Transform {}.visit_statement(&mut b.body);
+    }
+}
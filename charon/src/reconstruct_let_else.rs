@@ -0,0 +1,100 @@
+//! Reconstruct the `let ... else { ... }` statements.
+//!
+//! MIR compiles `let Some(v) = x else { return 0; };` to a switch over the
+//! discriminant of `x`, with a single branch for the matched variant and an
+//! `otherwise` branch which diverges. Once the control-flow has been
+//! reconstructed and the discriminant reads merged with the switches
+//! ([crate::remove_read_discriminant]), we get:
+//! ```text
+//! match x {
+//!   1 => {
+//!     v := (x as variant @1).0;
+//!     ...
+//!   },
+//!   _ => {
+//!     @return := 0;
+//!     return
+//!   }
+//! }
+//! ```
+//! We transform this to:
+//! ```text
+//! let 1 = x else {
+//!   @return := 0;
+//!   return
+//! }
+//! v := (x as variant @1).0;
+//! ...
+//! ```
+//! Note that an `if let` whose `else` branch diverges is compiled to the same
+//! MIR as a `let ... else`: we transform it in the same way.
+
+use take_mut::take;
+
+use crate::expressions::MutExprVisitor;
+use crate::llbc_ast::{
+    new_sequence, CtxNames, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, Statement, Switch,
+};
+use crate::types::MutTypeVisitor;
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+use crate::ullbc_to_llbc::is_terminal;
+
+/// Check if a statement is a match with a single branch, and whose
+/// `otherwise` branch diverges.
+fn is_let_else(st: &Statement) -> bool {
+    match &st.content {
+        RawStatement::Switch(Switch::Match(_, targets, otherwise)) => {
+            targets.len() == 1 && is_terminal(otherwise)
+        }
+        _ => false,
+    }
+}
+
+/// Transform a match which satisfies [is_let_else].
+fn to_let_else(st: Statement) -> Statement {
+    let (p, mut targets, otherwise) = st.content.to_switch().to_match();
+    let (variants, branch) = targets.pop().unwrap();
+    let let_else = Statement::new(st.meta, RawStatement::LetElse(p, variants, otherwise));
+    new_sequence(let_else, branch)
+}
+
+struct Transform {}
+
+impl MutTypeVisitor for Transform {}
+impl MutExprVisitor for Transform {}
+
+impl MutAstVisitor for Transform {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        // Bottom-up
+        self.default_visit_raw_statement(&mut st.content);
+
+        if is_let_else(st) {
+            take(st, to_let_else);
+        } else if let RawStatement::Sequence(st1, _) = &st.content {
+            // The transformation may have turned the first statement of the
+            // sequence into a sequence: we need to restore the invariant
+            if st1.content.is_sequence() {
+                take(st, |st| {
+                    let (st1, st2) = st.content.to_sequence();
+                    new_sequence(*st1, *st2)
+                });
+            }
+        }
+    }
+}
+
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to reconstruct the let-else statements in decl: {name}\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+        Transform {}.visit_statement(&mut b.body);
+    }
+}
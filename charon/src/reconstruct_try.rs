@@ -0,0 +1,557 @@
+//! Reconstruct the `?` operator.
+//!
+//! MIR desugars `let v = x?;` to a call to `Try::branch`, followed by a match
+//! over the resulting `ControlFlow`. Once the control-flow has been
+//! reconstructed and the discriminant reads merged with the switches
+//! ([crate::remove_read_discriminant]), we get:
+//! ```text
+//! cf := core::ops::try_trait::Try::branch(move x);
+//! match cf {
+//!   0 => {
+//!     v := move (cf as variant @0).0;
+//!     ...
+//!   },
+//!   1 => {
+//!     r := move (cf as variant @1).0;
+//!     @return := core::ops::try_trait::FromResidual::from_residual(move r);
+//!     return
+//!   }
+//! }
+//! ```
+//! We transform this to:
+//! ```text
+//! v := move x? else r => {
+//!   @return := core::ops::try_trait::FromResidual::from_residual(move r);
+//!   return
+//! }
+//! ...
+//! ```
+//!
+//! If the branches join after the match (for instance, if the `?` is used in
+//! the returned expression: `Ok(x? + 1)`), the `Break` branch doesn't diverge
+//! by itself. If the statements following the match diverge, we append a copy
+//! of them to the `Break` branch (and remove them if the `Continue` branch
+//! diverges).
+//!
+//! Rustc introduces storage statements (and a fake read of `cf`) between the
+//! call and the match, and at the beginning of the branches. We move the storage statements of the
+//! branches before the [RawStatement::TryOp], so that the storage of `v` and
+//! `r` is live when we assign them. The storage statements between the call
+//! and the match are duplicated after the [RawStatement::TryOp] and at the
+//! beginning of `err_return`, while the fake read is removed (`cf` doesn't
+//! exist anymore). Unless we preserve the storage annotations (see the
+//! `--storage-annotations` option), the `StorageDead` statements between the
+//! call and the match are drops of locals, which we treat in the same way.
+//!
+//! This pass is optional (see the `--reconstruct-try` option).
+
+use take_mut::take;
+
+use crate::assumed;
+use crate::expressions::{FieldProjKind, MutExprVisitor, Operand, Place, ProjectionElem, Rvalue};
+use crate::llbc_ast::{
+    chain_statements, CtxNames, FunDeclId, FunDecls, FunId, GlobalDecls, MutAstVisitor,
+    RawStatement, Statement, Switch,
+};
+use crate::meta::combine_meta;
+use crate::types::{FieldId, MutTypeVisitor, VariantId};
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+use crate::ullbc_to_llbc::is_terminal;
+use std::collections::HashSet;
+
+/// Split a sequence into the list of its statements (the sequences may be
+/// nested on both sides).
+fn flatten(mut st: Statement) -> Vec<Statement> {
+    let mut sts = Vec::new();
+    loop {
+        match st.content {
+            RawStatement::Sequence(st1, st2) => {
+                sts.extend(flatten(*st1));
+                st = *st2;
+            }
+            content => {
+                sts.push(Statement::new(st.meta, content));
+                return sts;
+            }
+        }
+    }
+}
+
+/// Rebuild a sequence from a (non-empty) list of statements.
+fn unflatten(mut sts: Vec<Statement>) -> Statement {
+    let last = sts.pop().unwrap();
+    chain_statements(sts, last)
+}
+
+fn is_storage(st: &Statement) -> bool {
+    st.content.is_storage_live() || st.content.is_storage_dead()
+}
+
+/// Check if a statement is a drop of a local (this is how we translate the
+/// `StorageDead` statements by default).
+fn is_drop_local(st: &Statement) -> bool {
+    matches!(&st.content, RawStatement::Drop(p) if p.projection.is_empty())
+}
+
+/// Check if a statement is a call to `Try::branch`.
+fn is_try_branch_call(try_branch_ids: &HashSet<FunDeclId::Id>, st: &Statement) -> bool {
+    match &st.content {
+        RawStatement::Call(call) => match &call.func {
+            FunId::Regular(id) => try_branch_ids.contains(id) && call.args.len() == 1,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Check if a statement is of the shape `dest := move (cf as variant @variant).0`,
+/// and return `dest` if it is the case.
+fn get_variant_field_assign<'a>(
+    cf: &Place,
+    variant: VariantId::Id,
+    st: &'a Statement,
+) -> Option<&'a Place> {
+    let (dest, rv) = match &st.content {
+        RawStatement::Assign(dest, Rvalue::Use(Operand::Move(src) | Operand::Copy(src))) => {
+            (dest, src)
+        }
+        _ => return None,
+    };
+    match rv.projection.as_slice() {
+        [ProjectionElem::Field(FieldProjKind::Adt(_, Some(vid)), fid)]
+            if rv.var_id == cf.var_id
+                && cf.projection.is_empty()
+                && *vid == variant
+                && *fid == FieldId::ZERO =>
+        {
+            Some(dest)
+        }
+        _ => None,
+    }
+}
+
+/// Return the first statement of a sequence which is not a storage statement.
+fn first_non_storage(mut st: &Statement) -> &Statement {
+    while let RawStatement::Sequence(st1, st2) = &st.content {
+        if !is_storage(st1) {
+            return st1;
+        }
+        st = st2;
+    }
+    st
+}
+
+/// Check if a branch of the match starts (after some storage statements) by
+/// reading the field of a variant of `cf`.
+fn branch_reads_variant(cf: &Place, variant: VariantId::Id, branch: &Statement) -> bool {
+    get_variant_field_assign(cf, variant, first_non_storage(branch)).is_some()
+}
+
+/// The position of a branch in a match over a `ControlFlow`: `Some(i)` if it
+/// is the `i`-th target, `None` if it is the `otherwise` branch.
+type BranchPos = Option<usize>;
+
+/// Check if a statement is a match over `cf` whose `Continue` branch reads the
+/// value and continues, and whose `Break` branch reads the residual and
+/// diverges (possibly by executing the statements following the match, if
+/// `next_diverges` is `true`). If it is the case, return the positions of the
+/// `Continue` and `Break` branches.
+fn get_try_match(
+    cf: &Place,
+    st: &Statement,
+    next_diverges: bool,
+) -> Option<(BranchPos, BranchPos)> {
+    let (p, targets, otherwise) = match &st.content {
+        RawStatement::Switch(Switch::Match(p, targets, otherwise)) => (p, targets, otherwise),
+        _ => return None,
+    };
+    if p != cf {
+        return None;
+    }
+
+    let find = |variant| targets.iter().position(|(vids, _)| *vids == vec![variant]);
+    let cont = find(assumed::CONTROL_FLOW_CONTINUE_VARIANT_ID);
+    let brk = find(assumed::CONTROL_FLOW_BREAK_VARIANT_ID);
+    // There mustn't be other branches, and the `otherwise` branch must be
+    // unreachable if it is not one of the two branches
+    let num_found = cont.iter().count() + brk.iter().count();
+    let otherwise_used = cont.is_none() || brk.is_none();
+    if num_found != targets.len()
+        || num_found == 0
+        || (!otherwise_used && !otherwise.content.is_panic())
+    {
+        return None;
+    }
+
+    let branch = |pos: BranchPos| match pos {
+        Some(i) => &targets[i].1,
+        None => otherwise.as_ref(),
+    };
+    let cont_branch = branch(cont);
+    let brk_branch = branch(brk);
+    if branch_reads_variant(cf, assumed::CONTROL_FLOW_CONTINUE_VARIANT_ID, cont_branch)
+        && branch_reads_variant(cf, assumed::CONTROL_FLOW_BREAK_VARIANT_ID, brk_branch)
+        && (next_diverges || is_terminal(brk_branch))
+    {
+        Some((cont, brk))
+    } else {
+        None
+    }
+}
+
+/// Split a branch which satisfies [branch_reads_variant]: return its leading
+/// storage statements, the place in which we store the field of the variant,
+/// and the remaining statements.
+fn split_branch(branch: Statement) -> (Vec<Statement>, Place, Vec<Statement>) {
+    let mut storage = flatten(branch);
+    let pos = storage.iter().position(|st| !is_storage(st)).unwrap();
+    let mut sts = storage.split_off(pos);
+    let (dest, _) = sts.remove(0).content.to_assign();
+    (storage, dest, sts)
+}
+
+/// Transform a sequence whose first statement satisfies [is_try_branch_call],
+/// if it is followed by a match which satisfies [get_try_match] (the
+/// sequence is left unchanged otherwise).
+fn to_try_op(st: Statement) -> Statement {
+    let mut sts = flatten(st);
+    let cf = sts[0].content.as_call().dest.clone();
+
+    // Skip the storage statements, the drops of locals and the fake reads
+    // which follow the call, and check the match
+    let is_fake_read_cf =
+        |st: &Statement| matches!(&st.content, RawStatement::FakeRead(p) if *p == cf);
+    let match_pos = 1 + sts[1..]
+        .iter()
+        .take_while(|st| is_storage(st) || is_drop_local(st) || is_fake_read_cf(st))
+        .count();
+    let next_diverges =
+        sts.len() > match_pos + 1 && is_terminal(&unflatten(sts[match_pos + 1..].to_vec()));
+    let positions = sts
+        .get(match_pos)
+        .and_then(|st| get_try_match(&cf, st, next_diverges));
+    let (cont, brk) = match positions {
+        Some(positions) => positions,
+        None => return unflatten(sts),
+    };
+
+    let next = sts.split_off(match_pos + 1);
+    let match_st = sts.pop().unwrap();
+    let between: Vec<Statement> = sts
+        .split_off(1)
+        .into_iter()
+        .filter(|st| !is_fake_read_cf(st))
+        .collect();
+    let call_st = sts.pop().unwrap();
+
+    // Retrieve the branches
+    let (_, mut targets, otherwise) = match_st.content.to_switch().to_match();
+    let mut otherwise = Some(*otherwise);
+    // We remove the targets in decreasing order of position, so that the
+    // positions remain valid
+    let mut take_branch =
+        |pos: BranchPos, targets: &mut Vec<(Vec<VariantId::Id>, Statement)>| match pos {
+            Some(i) => targets.remove(i).1,
+            None => otherwise.take().unwrap(),
+        };
+    let (cont_branch, brk_branch) = if cont > brk {
+        let cont_branch = take_branch(cont, &mut targets);
+        (cont_branch, take_branch(brk, &mut targets))
+    } else {
+        let brk_branch = take_branch(brk, &mut targets);
+        (take_branch(cont, &mut targets), brk_branch)
+    };
+    let cont_diverges = is_terminal(&cont_branch);
+    let brk_diverges = is_terminal(&brk_branch);
+    let (cont_storage, ok_place, cont_sts) = split_branch(cont_branch);
+    let (brk_storage, err_place, mut brk_sts) = split_branch(brk_branch);
+    if !brk_diverges {
+        brk_sts.extend(next.iter().cloned());
+    }
+    // The statements following the match are dead code if the `Continue`
+    // branch diverges
+    let next = if cont_diverges { Vec::new() } else { next };
+
+    // Build the statement
+    let call = call_st.content.to_call();
+    let scrutinee = call.args.into_iter().next().unwrap();
+    let err_return = unflatten(between.iter().cloned().chain(brk_sts).collect());
+    let try_op = Statement::new(
+        combine_meta(&call_st.meta, &match_st.meta),
+        RawStatement::TryOp {
+            scrutinee,
+            ok_place,
+            err_place,
+            err_return: Box::new(err_return),
+        },
+    );
+    let sts: Vec<Statement> = cont_storage
+        .into_iter()
+        .chain(brk_storage)
+        .chain(std::iter::once(try_op))
+        .chain(between)
+        .chain(cont_sts)
+        .chain(next)
+        .collect();
+    unflatten(sts)
+}
+
+struct Transform<'a> {
+    try_branch_ids: &'a HashSet<FunDeclId::Id>,
+}
+
+impl<'a> MutTypeVisitor for Transform<'a> {}
+impl<'a> MutExprVisitor for Transform<'a> {}
+
+impl<'a> MutAstVisitor for Transform<'a> {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        // Bottom-up
+        self.default_visit_raw_statement(&mut st.content);
+
+        // The sequences are not necessarily nested on the right: the match
+        // over the result of a call to `Try::branch` may be followed by
+        // statements which are not in the same sub-sequence. We thus look
+        // for the calls in the whole (flattened) sequence.
+        if st.content.is_sequence() {
+            let try_branch_ids = self.try_branch_ids;
+            take(st, |st| {
+                let mut sts = flatten(st);
+                // We start with the last call, so that the statements which
+                // follow a call are already transformed when we transform it
+                for i in (0..sts.len()).rev() {
+                    if is_try_branch_call(try_branch_ids, &sts[i]) {
+                        let tail = sts.split_off(i);
+                        sts.extend(flatten(to_try_op(unflatten(tail))));
+                    }
+                }
+                unflatten(sts)
+            });
+        }
+    }
+}
+
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    let try_branch_ids: HashSet<FunDeclId::Id> = funs
+        .iter()
+        .filter(|d| d.name.equals_ref_name(&assumed::TRY_BRANCH_NAME))
+        .map(|d| d.def_id)
+        .collect();
+    if try_branch_ids.is_empty() {
+        return;
+    }
+
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to reconstruct the `?` operators in decl: {name}\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+        Transform {
+            try_branch_ids: &try_branch_ids,
+        }
+        .visit_statement(&mut b.body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llbc_ast::Call;
+    use crate::meta::*;
+    use crate::types::TypeDeclId;
+    use crate::values::VarId;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn st(content: RawStatement) -> Statement {
+        Statement::new(dummy_meta(), content)
+    }
+
+    fn var(id: usize) -> Place {
+        Place::new(VarId::Id::new(id))
+    }
+
+    /// `dest := move (cf as variant @variant).0`
+    fn read_variant(dest: Place, cf: &Place, variant: VariantId::Id) -> Statement {
+        let mut src = cf.clone();
+        src.projection.push(ProjectionElem::Field(
+            FieldProjKind::Adt(TypeDeclId::ZERO, Some(variant)),
+            FieldId::ZERO,
+        ));
+        st(RawStatement::Assign(dest, Rvalue::Use(Operand::Move(src))))
+    }
+
+    /// ```text
+    /// cf := Try::branch(move x);
+    /// storage_dead(x);
+    /// match cf {
+    ///   0 => { storage_live(v); v := move (cf as variant @0).0; @return := move v; return },
+    ///   1 => { storage_live(r); r := move (cf as variant @1).0; @return := move r; return },
+    ///   _ => panic
+    /// }
+    /// ```
+    #[test]
+    fn test_to_try_op() {
+        let (ret, x, cf, v, r) = (var(0), var(1), var(2), var(3), var(4));
+        let call = Call {
+            func: FunId::Regular(FunDeclId::ZERO),
+            region_args: vec![],
+            type_args: vec![],
+            const_generic_args: vec![],
+            args: vec![Operand::Move(x.clone())],
+            dest: cf.clone(),
+        };
+        let branch = |variant, p: &Place| {
+            unflatten(vec![
+                st(RawStatement::StorageLive(p.var_id)),
+                read_variant(p.clone(), &cf, variant),
+                st(RawStatement::Assign(
+                    ret.clone(),
+                    Rvalue::Use(Operand::Move(p.clone())),
+                )),
+                st(RawStatement::Return),
+            ])
+        };
+        let targets = vec![
+            (
+                vec![assumed::CONTROL_FLOW_CONTINUE_VARIANT_ID],
+                branch(assumed::CONTROL_FLOW_CONTINUE_VARIANT_ID, &v),
+            ),
+            (
+                vec![assumed::CONTROL_FLOW_BREAK_VARIANT_ID],
+                branch(assumed::CONTROL_FLOW_BREAK_VARIANT_ID, &r),
+            ),
+        ];
+        let body = unflatten(vec![
+            st(RawStatement::Call(call)),
+            st(RawStatement::StorageDead(x.var_id)),
+            st(RawStatement::Switch(Switch::Match(
+                cf.clone(),
+                targets,
+                Box::new(st(RawStatement::Panic)),
+            ))),
+        ]);
+
+        let mut try_branch_ids = HashSet::new();
+        try_branch_ids.insert(FunDeclId::ZERO);
+        assert!(is_try_branch_call(
+            &try_branch_ids,
+            first_non_storage(&body)
+        ));
+
+        // storage_live(v); storage_live(r); try; storage_dead(x); @return := move v; return
+        let sts = flatten(to_try_op(body));
+        assert!(sts.len() == 6);
+        assert!(sts[0].content.is_storage_live() && sts[1].content.is_storage_live());
+        match &sts[2].content {
+            RawStatement::TryOp {
+                scrutinee,
+                ok_place,
+                err_place,
+                err_return,
+            } => {
+                assert!(*scrutinee.as_move() == x);
+                assert!(*ok_place == v && *err_place == r);
+                // storage_dead(x); @return := move r; return
+                assert!(flatten((**err_return).clone()).len() == 3);
+                assert!(is_terminal(err_return));
+            }
+            _ => panic!(),
+        }
+        assert!(sts[3].content.is_storage_dead() && sts[5].content.is_return());
+    }
+
+    /// ```text
+    /// {
+    ///   cf := Try::branch(move x);
+    ///   @fake_read(cf);
+    ///   match cf {
+    ///     0 => { v := move (cf as variant @0).0; @return := move v },
+    ///     1 => { r := move (cf as variant @1).0; @return := move r },
+    ///   }
+    /// };
+    /// return
+    /// ```
+    #[test]
+    fn test_to_try_op_join() {
+        let (ret, x, cf, v, r) = (var(0), var(1), var(2), var(3), var(4));
+        let call = Call {
+            func: FunId::Regular(FunDeclId::ZERO),
+            region_args: vec![],
+            type_args: vec![],
+            const_generic_args: vec![],
+            args: vec![Operand::Move(x)],
+            dest: cf.clone(),
+        };
+        let branch = |variant, p: &Place| {
+            unflatten(vec![
+                read_variant(p.clone(), &cf, variant),
+                st(RawStatement::Assign(
+                    ret.clone(),
+                    Rvalue::Use(Operand::Move(p.clone())),
+                )),
+            ])
+        };
+        let targets = vec![
+            (
+                vec![assumed::CONTROL_FLOW_CONTINUE_VARIANT_ID],
+                branch(assumed::CONTROL_FLOW_CONTINUE_VARIANT_ID, &v),
+            ),
+            (
+                vec![assumed::CONTROL_FLOW_BREAK_VARIANT_ID],
+                branch(assumed::CONTROL_FLOW_BREAK_VARIANT_ID, &r),
+            ),
+        ];
+        let body = unflatten(vec![
+            st(RawStatement::Call(call)),
+            st(RawStatement::FakeRead(cf.clone())),
+            st(RawStatement::Switch(Switch::Match(
+                cf,
+                targets,
+                Box::new(st(RawStatement::Panic)),
+            ))),
+        ]);
+        // The sequence is nested on the left
+        let mut body = st(RawStatement::Sequence(
+            Box::new(body),
+            Box::new(st(RawStatement::Return)),
+        ));
+
+        let mut try_branch_ids = HashSet::new();
+        try_branch_ids.insert(FunDeclId::ZERO);
+        Transform {
+            try_branch_ids: &try_branch_ids,
+        }
+        .visit_statement(&mut body);
+
+        // try; @return := move v; return
+        let sts = flatten(body);
+        assert!(sts.len() == 3);
+        match &sts[0].content {
+            RawStatement::TryOp { err_return, .. } => {
+                // @return := move r; return
+                assert!(flatten((**err_return).clone()).len() == 2);
+                assert!(is_terminal(err_return));
+            }
+            _ => panic!(),
+        }
+        assert!(sts[1].content.is_assign() && sts[2].content.is_return());
+    }
+}
@@ -7,7 +7,9 @@ use crate::formatter::Formatter;
 use crate::graphs::*;
 use crate::llbc_ast::FunDecls;
 use crate::reorder_decls as rd;
+#[cfg(feature = "rustc")]
 use crate::reorder_decls::{DeclarationGroup, DeclarationsGroups};
+#[cfg(feature = "rustc")]
 use crate::translate_ctx::TransCtx;
 use crate::types as ty;
 use crate::types::*;
@@ -17,7 +19,8 @@ use macros::generate_index_type;
 use petgraph::algo::tarjan_scc;
 use petgraph::graphmap::DiGraphMap;
 use petgraph::Direction;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::iter::FromIterator;
 
@@ -43,7 +46,7 @@ type LifetimeConstraints = DiGraphMap<Region<RegionVarId::Id>, ()>;
 ///
 /// Is used to group regions with the same lifetime together, and express
 /// the lifetime hierarchy between different groups of regions.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RegionGroup {
     /// The region group identifier
     pub id: RegionGroupId::Id,
@@ -338,9 +341,15 @@ fn compute_full_regions_constraints_for_ty(
                     | AssumedTy::PtrUnique
                     | AssumedTy::Str
                     | AssumedTy::PtrNonNull
+                    | AssumedTy::PhantomData
+                    | AssumedTy::UnsafeCell
                     | AssumedTy::Array
                     | AssumedTy::Slice
-                    | AssumedTy::Range,
+                    | AssumedTy::Range
+                    | AssumedTy::RangeFrom
+                    | AssumedTy::RangeTo
+                    | AssumedTy::RangeFull
+                    | AssumedTy::RangeInclusive,
                 ) => {
                     // Explore the types given as parameters
                     for fty in types {
@@ -359,6 +368,10 @@ fn compute_full_regions_constraints_for_ty(
         Ty::Literal(_) | Ty::Never => {
             // Nothing to do
         }
+        Ty::TraitAssoc(..) => {
+            // We don't know the associated type: we can't compute its
+            // constraints
+        }
         Ty::Ref(region, ref_ty, _mutability) => {
             // Add the constraint for the region in the reference
             add_region_constraints(
@@ -392,6 +405,44 @@ fn compute_full_regions_constraints_for_ty(
                 ptr_ty,
             );
         }
+        Ty::TraitObject(traits, region) => {
+            // We treat the lifetime bound of the trait object like the region
+            // of a reference
+            add_region_constraints(
+                updated,
+                acc_constraints,
+                type_def_constraints,
+                *region,
+                &parent_regions,
+            );
+
+            // The types in the trait references must outlive the bound
+            let mut parent_regions = parent_regions.clone();
+            parent_regions.insert(*region);
+            for ty in traits.iter().flat_map(|tr| tr.iter_types()) {
+                compute_full_regions_constraints_for_ty(
+                    updated,
+                    constraints_map,
+                    acc_constraints,
+                    type_def_constraints,
+                    parent_regions.clone(),
+                    ty,
+                );
+            }
+        }
+        Ty::FnPtr(inputs, output) => {
+            // Dive in
+            for ty in inputs.iter().chain(std::iter::once(&**output)) {
+                compute_full_regions_constraints_for_ty(
+                    updated,
+                    constraints_map,
+                    acc_constraints,
+                    type_def_constraints,
+                    parent_regions.clone(),
+                    ty,
+                );
+            }
+        }
         Ty::TypeVar(var_id) => {
             // Add the parent regions in the set of parent regions for the type variable
             match type_def_constraints {
@@ -473,8 +524,13 @@ fn compute_regions_constraints_for_type_decl_group(
     let mut acc_constraints_map: HashMap<TypeDeclId::Id, LifetimeConstraints> =
         HashMap::from_iter(type_ids.iter().map(|id| {
             (*id, {
+                // We add all the regions, in case some of them don't appear
+                // in the fields (for instance if the type is opaque)
                 let mut graph = LifetimeConstraints::new();
                 graph.add_node(Region::Static);
+                for var in types.get(*id).unwrap().region_params.iter() {
+                    graph.add_node(Region::Var(var.index));
+                }
                 graph
             })
         }));
@@ -645,8 +701,13 @@ fn compute_regions_constraints_for_sig(
     types_constraints: &TypesConstraintsMap,
     sig: &FunSig,
 ) -> SCCs<Region<RegionVarId::Id>> {
+    // We add all the regions, in case some of them only appear in types we
+    // don't explore (like the trait objects)
     let mut constraints_graph = LifetimeConstraints::new();
     constraints_graph.add_node(Region::Static);
+    for var in sig.region_params.iter() {
+        constraints_graph.add_node(Region::Var(var.index));
+    }
 
     for input_ty in &sig.inputs {
         compute_regions_constraints_for_ty(types_constraints, &mut constraints_graph, input_ty);
@@ -775,6 +836,7 @@ pub fn types_constraints_map_fmt_with_ctx(
     types_constraints.join("\n")
 }
 
+#[cfg(feature = "rustc")]
 pub fn compute(ctx: &mut TransCtx, ordered_decls: &DeclarationsGroups) {
     // First, compute the regions hierarchy for the types, and compute the types
     // constraints map while doing so. We compute by working on a whole type
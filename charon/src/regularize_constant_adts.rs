@@ -1,11 +1,15 @@
 //! In MIR, compile-time constant ADTs are treated separately.
 //! We don't want to have this distinction / redundancy in (U)LLBC.
 //!
-//! This pass removes all occurrences of [OperandConstantValue::Adt],
-//! and builds regular ADTs ([Rvalue::Aggregate]) instead (as for static values).
+//! This pass removes all occurrences of [OperandConstantValue::Adt] and
+//! [OperandConstantValue::Array], and builds regular ADTs and arrays
+//! ([Rvalue::Aggregate]) instead (as for static values).
 //!
 //! To do so, it recursively translates an operand of the form `const <ADT>`
 //! to `AggregatedAdt`. The recursion happens on the assignment operands.
+//! The constant byte strings and the other constant aggregates behind a shared
+//! reference (`&[1, 2, 3]`) are translated to an aggregated value followed by a
+//! borrow.
 
 use std::iter::zip;
 
@@ -22,10 +26,18 @@ use crate::values::VarId;
 fn make_aggregate_kind(ty: &ETy, var_index: Option<VariantId::Id>) -> AggregateKind {
     let (id, regions, tys, cgs) = ty.as_adt();
     match id {
+        TypeId::Assumed(AssumedTy::Array) => {
+            assert!(var_index.is_none());
+            AggregateKind::Array(tys[0].clone(), cgs[0].clone())
+        }
         TypeId::Tuple => {
             assert!(var_index.is_none());
             AggregateKind::Tuple
         }
+        TypeId::Assumed(AssumedTy::PhantomData) => {
+            assert!(var_index.is_none());
+            AggregateKind::PhantomData(tys[0].clone())
+        }
         TypeId::Adt(decl_id) => {
             let regions = regions.iter().cloned().collect();
             let tys = tys.iter().cloned().collect();
@@ -36,29 +48,66 @@ fn make_aggregate_kind(ty: &ETy, var_index: Option<VariantId::Id>) -> AggregateK
     }
 }
 
-/// If the constant value is a constant ADT, push `Assign::Aggregate` statements
-/// to the vector of statements, that bind new variables to the ADT parts and
-/// the variable assigned to the complete ADT.
+/// If the constant value is a constant ADT (or array), push `Assign::Aggregate`
+/// statements to the vector of statements, that bind new variables to the ADT
+/// parts and the variable assigned to the complete ADT.
 ///
 /// Goes fom e.g. `f(T::A(x, y))` to `let a = T::A(x, y); f(a)`.
 /// The function is recursively called on the aggregate fields (e.g. here x and y).
 fn transform_constant_adt<F: FnMut(ETy) -> VarId::Id>(
+    type_decls: &TypeDecls,
     meta: &Meta,
     nst: &mut Vec<Statement>,
     ty: &ETy,
     val: &OperandConstantValue,
     make_new_var: &mut F,
 ) -> Option<VarId::Id> {
+    // The byte strings (`b"hello"`) are constant arrays whose type is a
+    // shared reference to an array: we bind the array to a new variable, and
+    // borrow it (`let a = [...]; let r = &a;`). Same for the references to
+    // constant tuples.
+    if let (
+        Ty::Ref(_, pointee_ty, RefKind::Shared),
+        OperandConstantValue::Array(_) | OperandConstantValue::Adt(..),
+    ) = (ty, val)
+    {
+        let pointee_id =
+            transform_constant_adt(type_decls, meta, nst, pointee_ty, val, make_new_var).unwrap();
+        let rval = Rvalue::Ref(Place::new(pointee_id), BorrowKind::Shared);
+        let var_id = make_new_var(ty.clone());
+        nst.push(Statement::new(
+            *meta,
+            RawStatement::Assign(Place::new(var_id), rval),
+        ));
+        return Some(var_id);
+    }
+
     // Return early if there is nothing to decompose
     let (variant, fields) = match val {
         OperandConstantValue::Adt(v, f) => (v, f),
+        OperandConstantValue::Array(f) => (&None, f),
         _ => return None,
     };
 
+    // Retrieve the types of the fields
+    let field_tys: Vec<ETy> = match ty.as_adt() {
+        (TypeId::Tuple, _, tys, _) => tys.clone(),
+        (TypeId::Adt(id), _, tys, cgs) => type_decls
+            .get(*id)
+            .unwrap()
+            .get_erased_regions_instantiated_field_types(*variant, tys, cgs),
+        // For the arrays, all the elements have the same type
+        (TypeId::Assumed(AssumedTy::Array), _, tys, _) => vec![tys[0].clone(); fields.len()],
+        (TypeId::Assumed(AssumedTy::PhantomData), _, _, _) => Vec::new(),
+        (TypeId::Assumed(_), _, _, _) => unreachable!(),
+    };
+
     // Translate fields recursively into statements and operands.
-    let ops = zip(ty.as_adt().2, fields)
+    let ops = zip(&field_tys, fields)
         .map(|(f_ty, f_val)| {
-            if let Some(var_id) = transform_constant_adt(meta, nst, f_ty, f_val, make_new_var) {
+            if let Some(var_id) =
+                transform_constant_adt(type_decls, meta, nst, f_ty, f_val, make_new_var)
+            {
                 Operand::Move(Place::new(var_id))
             } else {
                 Operand::Const(f_ty.clone(), f_val.clone())
@@ -77,13 +126,14 @@ fn transform_constant_adt<F: FnMut(ETy) -> VarId::Id>(
 }
 
 fn transform_operand_adt<F: FnMut(ETy) -> VarId::Id>(
+    type_decls: &TypeDecls,
     meta: &Meta,
     nst: &mut Vec<Statement>,
     op: &mut Operand,
     f: &mut F,
 ) {
     if let Operand::Const(ty, val) = op {
-        if let Some(var_id) = transform_constant_adt(meta, nst, ty, val, f) {
+        if let Some(var_id) = transform_constant_adt(type_decls, meta, nst, ty, val, f) {
             // Change the ADT constant operand to a move (of the extracted AST).
             *op = Operand::Move(Place::new(var_id));
         }
@@ -99,7 +149,7 @@ pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut Glob
 
         let mut f = make_locals_generator(&mut b.locals);
         body_transform_operands(&mut b.body, &mut |meta, nst, op| {
-            transform_operand_adt(meta, nst, op, &mut f)
+            transform_operand_adt(fmt_ctx.type_context, meta, nst, op, &mut f)
         });
     }
 }
@@ -1,14 +1,16 @@
 //! The MIR code often contains variables with type `Never`, and we want to get
 //! rid of those. We proceed in two steps. First, we remove the instructions
-//! `drop(v)` where `v` has type `Never` (it can happen - this module does the
-//! filtering). Then, we filter the unused variables ([crate::remove_unused_locals]).
+//! `drop(v)`, `storage_live(v)` and `storage_dead(v)` where `v` has type `Never`
+//! (it can happen - this module does the filtering). Then, we filter the unused
+//! variables ([crate::remove_unused_locals]).
 
 use crate::llbc_ast::{CtxNames, FunDecls, GlobalDecls, RawStatement, Statement, Var};
 use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
 use crate::values::*;
 
-/// Filter the statement by replacing it with `Nop` if it is a `Drop(x)` where
-/// `x` has type `Never`. Otherwise leave it unchanged.
+/// Filter the statement by replacing it with `Nop` if it is a `Drop(x)`,
+/// `StorageLive(x)` or `StorageDead(x)` where `x` has type `Never`. Otherwise
+/// leave it unchanged.
 fn transform_st(locals: &VarId::Vector<Var>, st: &mut Statement) {
     // Shall we filter the statement?
     let filter = match &mut st.content {
@@ -20,6 +22,9 @@ fn transform_st(locals: &VarId::Vector<Var>, st: &mut Statement) {
                 false
             }
         }
+        RawStatement::StorageLive(vid) | RawStatement::StorageDead(vid) => {
+            locals.get(*vid).unwrap().ty.is_never()
+        }
         _ => false,
     };
 
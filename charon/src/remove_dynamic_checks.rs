@@ -110,12 +110,9 @@ impl MutAstVisitor for RemoveDynChecks {
             }
         }
         // Dive in.
-        // Make sure we eliminate all the asserts and all the len
+        // Make sure we eliminate all the asserts. Note that some `len` remain:
+        // the slice patterns check the length of the slices.
         assert!(!s.content.is_assert());
-        if s.content.is_assign() {
-            let (_, rv) = s.content.as_assign();
-            assert!(!rv.is_len());
-        }
         self.default_visit_raw_statement(&mut s.content);
     }
 }
@@ -28,7 +28,10 @@ fn transform_st(st: Statement) -> Statement {
         RawStatement::FakeRead(p) => RawStatement::FakeRead(p),
         RawStatement::SetDiscriminant(p, vid) => RawStatement::SetDiscriminant(p, vid),
         RawStatement::Drop(p) => RawStatement::Drop(p),
+        RawStatement::StorageLive(vid) => RawStatement::StorageLive(vid),
+        RawStatement::StorageDead(vid) => RawStatement::StorageDead(vid),
         RawStatement::Assert(assert) => RawStatement::Assert(assert),
+        RawStatement::TaggedAssert(id, assert) => RawStatement::TaggedAssert(id, assert),
         RawStatement::Call(call) => RawStatement::Call(call),
         RawStatement::Panic => RawStatement::Panic,
         RawStatement::Return => RawStatement::Return,
@@ -57,6 +60,13 @@ fn transform_st(st: Statement) -> Statement {
             RawStatement::Switch(switch)
         }
         RawStatement::Loop(loop_body) => RawStatement::Loop(Box::new(transform_st(*loop_body))),
+        RawStatement::LetElse(..)
+        | RawStatement::IfLet(..)
+        | RawStatement::TryOp { .. }
+        | RawStatement::MacroExpansion(_) => {
+            // Those variants are introduced in subsequent passes
+            unreachable!();
+        }
         RawStatement::Sequence(st1, st2) => {
             if st1.content.is_assign() {
                 let (_, rv) = st1.content.as_assign();
@@ -76,18 +86,25 @@ fn transform_st(st: Statement) -> Statement {
                         RawStatement::Switch(switch) => (st2.meta, switch, None),
                         _ => unreachable!(),
                     };
-                    let (op, int_ty, targets, otherwise) = switch.to_switch_int();
-                    assert!(int_ty.is_isize());
+                    // Note that the switch values are the variant indices (we
+                    // convert the discriminants when translating the switch),
+                    // but the integer type is the type of the discriminant
+                    // (which is not necessarily `isize`).
+                    let (op, _, targets, otherwise) = switch.to_switch_int();
                     // The operand should be a [Move] applied to the variable `dest`
                     let op_p = op.to_move();
                     assert!(op_p.projection.is_empty() && op_p.var_id == dest.var_id);
 
                     let targets = Vec::from_iter(targets.into_iter().map(|(v, e)| {
                         (
-                            Vec::from_iter(
-                                v.into_iter()
-                                    .map(|x| VariantId::Id::new(*x.as_isize() as usize)),
-                            ),
+                            Vec::from_iter(v.into_iter().map(|x| {
+                                let idx = if x.is_int() {
+                                    x.as_int().unwrap() as usize
+                                } else {
+                                    x.as_uint().unwrap() as usize
+                                };
+                                VariantId::Id::new(idx)
+                            })),
                             transform_st(e),
                         )
                     }));
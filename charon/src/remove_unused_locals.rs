@@ -6,9 +6,9 @@
 #![allow(dead_code)]
 
 use crate::expressions::{MutExprVisitor, SharedExprVisitor};
-use crate::id_vector::ToUsize;
 use crate::llbc_ast::{
-    CtxNames, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, SharedAstVisitor, Statement,
+    CtxNames, ExprBody, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, SharedAstVisitor,
+    Statement,
 };
 use crate::meta::combine_meta;
 use crate::types::{MutTypeVisitor, SharedTypeVisitor};
@@ -39,7 +39,9 @@ impl MutAstVisitor for RemoveNops {
                             content: s2.content,
                             meta: combine_meta(&s1.meta, &s2.meta),
                         }
-                    })
+                    });
+                    // The next statement may be a nop as well
+                    self.visit_statement(s)
                 } else {
                     self.default_visit_raw_statement(&mut s.content)
                 }
@@ -92,6 +94,12 @@ impl SharedAstVisitor for ComputeUsedLocals {
     }
 
     fn merge(&mut self) {}
+
+    /// The storage statements are not uses of the local: we remove them
+    /// together with the local (see [remove_unused_locals_in_body]).
+    fn visit_storage_live(&mut self, _vid: &VarId::Id) {}
+
+    fn visit_storage_dead(&mut self, _vid: &VarId::Id) {}
 }
 
 #[derive(Debug, Clone)]
@@ -145,18 +153,14 @@ fn update_locals(
 
     // Filter: only keep the variables which are used, and update
     // their indices so as not to have "holes"
+    let mut locals = old_locals;
+    locals.retain(|_, var| used_locals.contains(&var.index));
     let mut vids_map: HashMap<VarId::Id, VarId::Id> = HashMap::new();
-    let mut locals: VarId::Vector<Var> = VarId::Vector::new();
     let mut var_id_counter = VarId::Generator::new();
-    for mut var in old_locals {
-        if used_locals.contains(&var.index) {
-            let old_id = var.index;
-            let new_id = var_id_counter.fresh_id();
-            var.index = new_id;
-            vids_map.insert(old_id, new_id);
-            assert!(new_id.to_usize() == locals.len());
-            locals.push_back(var);
-        }
+    for var in locals.iter_mut() {
+        let new_id = var_id_counter.fresh_id();
+        vids_map.insert(var.index, new_id);
+        var.index = new_id;
     }
 
     // Check there are no remaining variables with type `Never`
@@ -166,19 +170,33 @@ fn update_locals(
     (locals, vids_map)
 }
 
+/// Remove the unused locals of a body, and update the variable indices.
+pub(crate) fn remove_unused_locals_in_body(b: &mut ExprBody) {
+    take(b, |mut b| {
+        let (locals, vids_map) = update_locals(b.arg_count, b.locals, &b.body);
+        b.locals = locals;
+        trace!("vids_maps: {:?}", vids_map);
+        // Remove the storage statements of the locals we removed
+        b.body.transform(&mut |st| {
+            if let RawStatement::StorageLive(vid) | RawStatement::StorageDead(vid) = &st.content {
+                if !vids_map.contains_key(vid) {
+                    *st = Statement::new(st.meta, RawStatement::Nop);
+                }
+            }
+            vec![]
+        });
+        UpdateUsedLocals::update_statement(vids_map, &mut b.body);
+        b
+    });
+}
+
 pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
     for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
         trace!(
             "# About to remove unused locals in decl: {name}:\n{}",
             b.fmt_with_ctx_names(fmt_ctx)
         );
-        take(b, |mut b| {
-            let (locals, vids_map) = update_locals(b.arg_count, b.locals, &b.body);
-            b.locals = locals;
-            trace!("vids_maps: {:?}", vids_map);
-            UpdateUsedLocals::update_statement(vids_map, &mut b.body);
-            b
-        });
+        remove_unused_locals_in_body(b);
         trace!(
             "# After removing unused locals of: {name}:\n{}",
             b.fmt_with_ctx_names(fmt_ctx)
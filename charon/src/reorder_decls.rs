@@ -1,20 +1,30 @@
 use crate::common::*;
 use crate::expressions::SharedExprVisitor;
 use crate::gast::{FunDeclId, GlobalDeclId};
+#[cfg(feature = "rustc")]
 use crate::graphs::*;
+use crate::schema;
+#[cfg(feature = "rustc")]
 use crate::translate_ctx::TransCtx;
-use crate::types::{SharedTypeVisitor, TypeDeclId, TypeDeclKind};
-use crate::ullbc_ast::{ExprBody, SharedAstVisitor};
+use crate::types::{ConstGenericExpr, SharedTypeVisitor, TypeDeclId};
+#[cfg(feature = "rustc")]
+use crate::ullbc_ast::ExprBody;
+use crate::ullbc_ast::SharedAstVisitor;
 use hashlink::linked_hash_map::LinkedHashMap;
 use linked_hash_set::LinkedHashSet;
 use macros::EnumAsGetters;
 use macros::EnumIsA;
 use macros::{VariantIndexArity, VariantName};
+#[cfg(feature = "rustc")]
 use petgraph::algo::tarjan_scc;
 use petgraph::graphmap::DiGraphMap;
+#[cfg(feature = "rustc")]
 use rustc_hir::def_id::DefId;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::ser::SerializeTupleVariant;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{Debug, Display, Error, Formatter};
 use std::vec::Vec;
 
@@ -39,6 +49,7 @@ pub enum DeclarationGroup<TypeId: Copy, FunId: Copy, GlobalId: Copy> {
     Global(GDeclarationGroup<GlobalId>),
 }
 
+#[cfg(feature = "rustc")]
 impl<TypeId: Copy, FunId: Copy, GlobalId: Copy> DeclarationGroup<TypeId, FunId, GlobalId> {
     fn make_type_group<'a>(is_rec: bool, gr: impl Iterator<Item = TypeId>) -> Self {
         let gr: Vec<TypeId> = gr.collect();
@@ -144,6 +155,44 @@ impl<Id: Copy + Serialize> Serialize for GDeclarationGroup<Id> {
     }
 }
 
+/// The serialized form of a [GDeclarationGroup]: the variants are serialized
+/// as tuple variants (see the serializer), hence the 1-tuples.
+#[derive(Deserialize)]
+enum SerializedGDeclarationGroup<Id> {
+    NonRec((Id,)),
+    Rec((Vec<Id>,)),
+}
+
+impl<'de, Id: Copy + Deserialize<'de>> Deserialize<'de> for GDeclarationGroup<Id> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let group = match SerializedGDeclarationGroup::deserialize(deserializer)? {
+            SerializedGDeclarationGroup::NonRec((id,)) => GDeclarationGroup::NonRec(id),
+            SerializedGDeclarationGroup::Rec((ids,)) => GDeclarationGroup::Rec(ids),
+        };
+        Ok(group)
+    }
+}
+
+impl<Id: Copy + JsonSchema> JsonSchema for GDeclarationGroup<Id> {
+    fn schema_name() -> String {
+        format!("GDeclarationGroup_for_{}", Id::schema_name())
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        schema::one_of(vec![
+            schema::tuple_variant("NonRec", vec![gen.subschema_for::<Id>()]),
+            schema::tuple_variant("Rec", vec![gen.subschema_for::<Vec<Id>>()]),
+        ])
+    }
+}
+
 /// We use the [Debug] trait instead of [Display] for the identifiers, because
 /// the rustc [DefId] doesn't implement [Display]...
 impl<TypeId: Copy + Debug, FunId: Copy + Debug, GlobalId: Copy + Debug> Display
@@ -192,6 +241,60 @@ impl<TypeId: Copy + Serialize, FunId: Copy + Serialize, GlobalId: Copy + Seriali
     }
 }
 
+/// See [SerializedGDeclarationGroup]
+#[derive(Deserialize)]
+enum SerializedDeclarationGroup<TypeId: Copy, FunId: Copy, GlobalId: Copy> {
+    Type((GDeclarationGroup<TypeId>,)),
+    Fun((GDeclarationGroup<FunId>,)),
+    Global((GDeclarationGroup<GlobalId>,)),
+}
+
+impl<'de, TypeId, FunId, GlobalId> Deserialize<'de> for DeclarationGroup<TypeId, FunId, GlobalId>
+where
+    TypeId: Copy + Deserialize<'de>,
+    FunId: Copy + Deserialize<'de>,
+    GlobalId: Copy + Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let group = match SerializedDeclarationGroup::deserialize(deserializer)? {
+            SerializedDeclarationGroup::Type((g,)) => DeclarationGroup::Type(g),
+            SerializedDeclarationGroup::Fun((g,)) => DeclarationGroup::Fun(g),
+            SerializedDeclarationGroup::Global((g,)) => DeclarationGroup::Global(g),
+        };
+        Ok(group)
+    }
+}
+
+impl<TypeId: Copy + JsonSchema, FunId: Copy + JsonSchema, GlobalId: Copy + JsonSchema> JsonSchema
+    for DeclarationGroup<TypeId, FunId, GlobalId>
+{
+    fn schema_name() -> String {
+        "DeclarationGroup".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        schema::one_of(vec![
+            schema::tuple_variant(
+                "Type",
+                vec![gen.subschema_for::<GDeclarationGroup<TypeId>>()],
+            ),
+            schema::tuple_variant("Fun", vec![gen.subschema_for::<GDeclarationGroup<FunId>>()]),
+            schema::tuple_variant(
+                "Global",
+                vec![gen.subschema_for::<GDeclarationGroup<GlobalId>>()],
+            ),
+        ])
+    }
+}
+
+#[cfg(feature = "rustc")]
 pub type AnyRustId = AnyDeclId<DefId, DefId, DefId>;
 pub type AnyTransId = AnyDeclId<TypeDeclId::Id, FunDeclId::Id, GlobalDeclId::Id>;
 
@@ -204,6 +307,7 @@ pub struct Deps {
 }
 
 impl Deps {
+    #[cfg(feature = "rustc")]
     fn new() -> Self {
         Deps {
             dgraph: DiGraphMap::new(),
@@ -212,11 +316,13 @@ impl Deps {
         }
     }
 
+    #[cfg(feature = "rustc")]
     fn set_current_id(&mut self, id: AnyTransId) {
         self.insert_node(id);
         self.current_id = Option::Some(id);
     }
 
+    #[cfg(feature = "rustc")]
     fn unset_current_id(&mut self) {
         self.current_id = Option::None;
     }
@@ -250,6 +356,14 @@ impl SharedTypeVisitor for Deps {
         let id = AnyDeclId::Global(*id);
         self.insert_edge(id);
     }
+
+    fn visit_const_generic_expr(&mut self, e: &ConstGenericExpr) {
+        // The default visitor ignores the function calls
+        if let ConstGenericExpr::FunCall(fid, _) = e {
+            self.insert_edge(AnyDeclId::Fun(*fid));
+        }
+        self.default_visit_const_generic_expr(e);
+    }
 }
 
 impl SharedExprVisitor for Deps {
@@ -261,13 +375,14 @@ impl SharedExprVisitor for Deps {
 
 impl SharedAstVisitor for Deps {}
 
+#[cfg(feature = "rustc")]
 impl Deps {
     fn visit_body(&mut self, body: &Option<ExprBody>) {
         match &body {
             Option::None => (),
             Option::Some(body) => {
                 for v in &body.locals {
-                    self.visit_ty(&v.ty);
+                    self.visit_ty(&*v.ty);
                 }
                 for block in &body.body {
                     self.visit_block_data(block);
@@ -277,19 +392,21 @@ impl Deps {
     }
 }
 
-pub fn reorder_declarations(ctx: &TransCtx) -> Result<DeclarationsGroups> {
-    trace!();
+/// The declarations every declaration (directly) refers to.
+pub type DeclDependencies = LinkedHashMap<AnyTransId, LinkedHashSet<AnyTransId>>;
 
-    // Step 1: explore the declarations to build the graph
+/// Explore the declarations to build the dependency graph.
+#[cfg(feature = "rustc")]
+fn compute_deps(ctx: &TransCtx) -> Deps {
     let mut graph = Deps::new();
     for id in &ctx.all_ids {
         graph.set_current_id(*id);
         match id {
             AnyTransId::Type(id) => {
                 let d = ctx.type_defs.get(*id).unwrap();
-                use TypeDeclKind::*;
+                use crate::types::TypeDeclKind::*;
                 match &d.kind {
-                    Struct(fields) => {
+                    Struct(fields) | Union(fields) => {
                         for f in fields {
                             graph.visit_ty(&f.ty)
                         }
@@ -301,6 +418,12 @@ pub fn reorder_declarations(ctx: &TransCtx) -> Result<DeclarationsGroups> {
                             }
                         }
                     }
+                    ImplTrait(traits) => {
+                        for ty in traits.iter().flat_map(|tr| tr.iter_types()) {
+                            graph.visit_ty(ty);
+                        }
+                    }
+                    Alias(ty) => graph.visit_ty(ty),
                     Opaque => (),
                 }
             }
@@ -325,7 +448,21 @@ pub fn reorder_declarations(ctx: &TransCtx) -> Result<DeclarationsGroups> {
         }
         graph.unset_current_id();
     }
+    graph
+}
 
+/// Compute the declarations every declaration (directly) refers to.
+#[cfg(feature = "rustc")]
+pub fn compute_dependencies(ctx: &TransCtx) -> DeclDependencies {
+    compute_deps(ctx).graph
+}
+
+#[cfg(feature = "rustc")]
+pub fn reorder_declarations(ctx: &TransCtx) -> Result<DeclarationsGroups> {
+    trace!();
+
+    // Step 1: explore the declarations to build the graph
+    let graph = compute_deps(ctx);
     trace!("Graph: {:?}", &graph.dgraph);
 
     // Step 2: Apply Tarjan's SCC (Strongly Connected Components) algorithm
@@ -409,7 +546,7 @@ mod tests {
             1 => vec![0, 3],
             _ => vec![],
         };
-        let reordered = crate::reorder_decls::reorder_sccs(get_deps, &ids, &sccs);
+        let reordered = crate::graphs::reorder_sccs(get_deps, &ids, &sccs);
 
         assert!(reordered.sccs == vec![vec![3, 4, 5], vec![0], vec![1, 2],]);
         assert!(reordered.scc_deps[0] == im::OrdSet::from(vec![]));
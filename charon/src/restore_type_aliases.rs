@@ -0,0 +1,226 @@
+//! Restore the type aliases (`--preserve-type-aliases`).
+//!
+//! rustc expands the type aliases: after `type MyVec = Vec<u32>`, the
+//! signature of `fn f() -> MyVec` is `fn f() -> Vec<u32>`. With
+//! `--preserve-type-aliases`, we translate the aliases to declarations of
+//! kind [TypeDeclKind::Alias], and this pass replaces the types which are
+//! exactly the expansion of an alias with a reference to the alias.
+//!
+//! We only restore the aliases which have no generic parameters and whose
+//! expansion has no regions: we don't need to match the parameters of the
+//! alias, and the expansion can be compared with the types which still have
+//! regions. If several aliases have the same expansion, we use the first one.
+//! We don't restore the aliases of `()`: some passes check if the functions
+//! return `()` (see [crate::insert_assign_return_unit]).
+//!
+//! We only restore the aliases in the fields of the type declarations and in
+//! the function signatures. The types in the bodies stay expanded: the passes
+//! which inspect them (for instance, to compute the type of a projection)
+//! don't have to unfold the aliases.
+
+use crate::types::*;
+use crate::ullbc_ast::FunDecls;
+
+/// The aliases we restore: their expansion, and their identifier.
+type Aliases = Vec<(ETy, TypeDeclId::Id)>;
+
+fn collect_aliases(type_defs: &TypeDecls) -> Aliases {
+    type_defs
+        .iter()
+        .filter_map(|d| match &d.kind {
+            TypeDeclKind::Alias(ty)
+                if d.region_params.is_empty()
+                    && d.type_params.is_empty()
+                    && d.const_generic_params.is_empty()
+                    && !ty.contains_regions()
+                    && !ty.is_unit() =>
+            {
+                Some((ty.erase_regions(), d.def_id))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replace the types (and their sub-types) which are the expansion of an
+/// alias.
+fn restore_in_ty<R: Copy + Eq>(aliases: &Aliases, ty: &mut Ty<R>) {
+    if !ty.contains_regions() {
+        let ety = ty.erase_regions();
+        if let Some((_, id)) = aliases.iter().find(|(expansion, _)| *expansion == ety) {
+            *ty = Ty::Adt(TypeId::Adt(*id), Vec::new(), Vec::new(), Vec::new());
+            return;
+        }
+    }
+    match ty {
+        Ty::Adt(_, _, tys, _) => {
+            for ty in tys {
+                restore_in_ty(aliases, ty);
+            }
+        }
+        Ty::Ref(_, ty, _) | Ty::RawPtr(ty, _) => restore_in_ty(aliases, ty),
+        Ty::FnPtr(inputs, output) => {
+            for ty in inputs {
+                restore_in_ty(aliases, ty);
+            }
+            restore_in_ty(aliases, output);
+        }
+        Ty::TypeVar(_) | Ty::Literal(_) | Ty::Never | Ty::TraitObject(..) | Ty::TraitAssoc(..) => {}
+    }
+}
+
+/// Replace the aliases with their expansion (recursively). This is the
+/// inverse of [restore_in_ty], for the analyses which need to compare the
+/// types.
+pub fn expand_aliases(type_defs: &TypeDecls, ty: &ETy) -> ETy {
+    match ty {
+        Ty::Adt(TypeId::Adt(id), ..) => match type_defs.get(*id).map(|d| &d.kind) {
+            Some(TypeDeclKind::Alias(expansion)) => {
+                expand_aliases(type_defs, &expansion.erase_regions())
+            }
+            _ => expand_aliases_in_args(type_defs, ty),
+        },
+        _ => expand_aliases_in_args(type_defs, ty),
+    }
+}
+
+fn expand_aliases_in_args(type_defs: &TypeDecls, ty: &ETy) -> ETy {
+    let expand_all = |tys: &Vec<ETy>| -> Vec<ETy> {
+        tys.iter().map(|ty| expand_aliases(type_defs, ty)).collect()
+    };
+    match ty {
+        Ty::Adt(id, regions, tys, cgs) => {
+            Ty::Adt(id.clone(), regions.clone(), expand_all(tys), cgs.clone())
+        }
+        Ty::Ref(r, ty, kind) => Ty::Ref(*r, Box::new(expand_aliases(type_defs, ty)), *kind),
+        Ty::RawPtr(ty, kind) => Ty::RawPtr(Box::new(expand_aliases(type_defs, ty)), *kind),
+        Ty::FnPtr(inputs, output) => Ty::FnPtr(
+            expand_all(inputs),
+            Box::new(expand_aliases(type_defs, output)),
+        ),
+        Ty::TypeVar(_) | Ty::Literal(_) | Ty::Never | Ty::TraitObject(..) | Ty::TraitAssoc(..) => {
+            ty.clone()
+        }
+    }
+}
+
+pub fn transform(type_defs: &mut TypeDecls, funs: &mut FunDecls) {
+    let aliases = collect_aliases(type_defs);
+    if aliases.is_empty() {
+        return;
+    }
+
+    for d in type_defs.iter_mut() {
+        let fields: Vec<&mut Field> = match &mut d.kind {
+            TypeDeclKind::Struct(fields) | TypeDeclKind::Union(fields) => {
+                fields.iter_mut().collect()
+            }
+            TypeDeclKind::Enum(variants) => variants
+                .iter_mut()
+                .flat_map(|v| v.fields.iter_mut())
+                .collect(),
+            TypeDeclKind::ImplTrait(_) | TypeDeclKind::Alias(_) | TypeDeclKind::Opaque => {
+                Vec::new()
+            }
+        };
+        for f in fields {
+            restore_in_ty(&aliases, &mut f.ty);
+        }
+    }
+
+    for d in funs.iter_mut() {
+        for ty in d.signature.inputs.iter_mut() {
+            restore_in_ty(&aliases, ty);
+        }
+        restore_in_ty(&aliases, &mut d.signature.output);
+        trace!(
+            "# Restored the type aliases in the signature of: {}",
+            d.name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::*;
+    use crate::names::*;
+    use crate::regions_hierarchy::RegionGroups;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn alias_decl(def_id: TypeDeclId::Id, name: &str, ty: RTy) -> TypeDecl {
+        TypeDecl {
+            def_id,
+            meta: dummy_meta(),
+            name: Name {
+                name: vec![PathElem::Ident(name.to_string())],
+            },
+            region_params: RegionVarId::Vector::new(),
+            type_params: TypeVarId::Vector::new(),
+            const_generic_params: ConstGenericVarId::Vector::new(),
+            kind: TypeDeclKind::Alias(ty),
+            repr: ReprConfig::rust(),
+            is_root: true,
+            stable_id: None,
+            local_methods: Vec::new(),
+            variance: Vec::new(),
+            is_recursive: false,
+            regions_hierarchy: RegionGroups::new(),
+        }
+    }
+
+    fn u32_ty<R: Clone + Eq>() -> Ty<R> {
+        Ty::Literal(LiteralTy::Integer(IntegerTy::U32))
+    }
+
+    fn vec_ty<R: Clone + Eq>(ty: Ty<R>) -> Ty<R> {
+        Ty::Adt(
+            TypeId::Assumed(AssumedTy::Vec),
+            Vec::new(),
+            vec![ty],
+            Vec::new(),
+        )
+    }
+
+    /// `type MyVec = Vec<u32>`: `&'a mut Vec<u32>` becomes `&'a mut MyVec`,
+    /// and expanding it gives back the original type (with erased regions).
+    #[test]
+    fn test_restore_and_expand() {
+        let my_vec = TypeDeclId::ZERO;
+        let mut type_defs = TypeDecls::new();
+        type_defs.insert(my_vec, alias_decl(my_vec, "MyVec", vec_ty(u32_ty())));
+        let aliases = collect_aliases(&type_defs);
+        assert!(aliases == vec![(vec_ty(u32_ty()), my_vec)]);
+
+        let r = Region::Var(RegionVarId::ZERO);
+        let mut ty: RTy = Ty::Ref(r, Box::new(vec_ty(u32_ty())), RefKind::Mut);
+        restore_in_ty(&aliases, &mut ty);
+        let alias_ty = Ty::Adt(TypeId::Adt(my_vec), Vec::new(), Vec::new(), Vec::new());
+        assert!(ty == Ty::Ref(r, Box::new(alias_ty), RefKind::Mut));
+
+        let expected: ETy = Ty::Ref(
+            ErasedRegion::Erased,
+            Box::new(vec_ty(u32_ty())),
+            RefKind::Mut,
+        );
+        assert!(expand_aliases(&type_defs, &ty.erase_regions()) == expected);
+
+        // The other types are left unchanged
+        let mut other: RTy = vec_ty(Ty::Literal(LiteralTy::Bool));
+        restore_in_ty(&aliases, &mut other);
+        assert!(other == vec_ty(Ty::Literal(LiteralTy::Bool)));
+    }
+}
@@ -0,0 +1,79 @@
+//! Utilities for the JSON schema of the generated files.
+//!
+//! Most of the types derive [schemars::JsonSchema], which follows the
+//! (derived) serializers. The types which implement [serde::Serialize] by hand
+//! must implement [schemars::JsonSchema] by hand as well: we provide some
+//! helpers here to describe the shapes serde generates.
+//! See [crate::export::llbc_schema] for the schema of the generated files.
+
+#![allow(dead_code)]
+
+use schemars::schema::{
+    ArrayValidation, InstanceType, ObjectValidation, RootSchema, Schema, SchemaObject, SingleOrVec,
+    SubschemaValidation,
+};
+
+/// The version of the schema, which we use in its `$id`. The format of the
+/// generated files changes with the version of Charon.
+pub const SCHEMA_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The schema of a variant with one field, which serde serializes as
+/// `{ "Variant": field }`.
+pub fn newtype_variant(name: &str, field: Schema) -> Schema {
+    let mut object = ObjectValidation::default();
+    let _ = object.properties.insert(name.to_string(), field);
+    let _ = object.required.insert(name.to_string());
+    object.additional_properties = Some(Box::new(Schema::Bool(false)));
+    SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(object)),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// The schema of a variant serialized with
+/// [serde::Serializer::serialize_tuple_variant]: `{ "Variant": [field0, ...] }`
+/// (note that the fields are in an array even if there is only one field).
+pub fn tuple_variant(name: &str, fields: Vec<Schema>) -> Schema {
+    let len = fields.len() as u32;
+    let array = SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(SingleOrVec::Vec(fields)),
+            min_items: Some(len),
+            max_items: Some(len),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    newtype_variant(name, array.into())
+}
+
+/// The schema of an enumeration, given the schemas of its variants.
+pub fn one_of(variants: Vec<Schema>) -> Schema {
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            one_of: Some(variants),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Validate a JSON value against a schema (we use the draft 7 of the JSON
+/// schema specification). Return the list of errors, if there are.
+pub fn validate(schema: &RootSchema, instance: &serde_json::Value) -> Result<(), Vec<String>> {
+    let schema = serde_json::to_value(schema).unwrap();
+    // Note that compiling the schema checks that it is valid
+    let compiled = jsonschema::JSONSchema::options()
+        .with_draft(jsonschema::Draft::Draft7)
+        .compile(&schema)
+        .map_err(|e| vec![format!("invalid schema: {e}")])?;
+    compiled.validate(instance).map_err(|errors| {
+        errors
+            .map(|e| format!("{}: {}", e.instance_path, e))
+            .collect()
+    })
+}
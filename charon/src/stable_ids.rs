@@ -0,0 +1,194 @@
+//! Compute stable, name-based ids for the declarations.
+//!
+//! The numeric ids ([crate::types::TypeDeclId], [crate::gast::FunDeclId],
+//! etc.) are attributed in the order in which we explore the crate: adding
+//! or removing a declaration may shift the ids of all the declarations which
+//! follow it. This is an issue for the tools which store information about
+//! the declarations across several versions of a crate. The stable ids are
+//! computed from the names of the declarations and their generic parameters,
+//! for instance: `core::option::Option<T>` or `crate::Foo::#1::bar<T, N>`
+//! (where `#1` is a disambiguator).
+//!
+//! The stable ids are only computed with `--stable-ids`.
+#![allow(dead_code)]
+
+use crate::gast::{GFunDecl, GGlobalDecl};
+use crate::id_map::HasStableId;
+use crate::names::{Name, PathElem};
+use crate::types::{ConstGenericVar, ConstGenericVarId, TypeDecl, TypeDecls, TypeVar, TypeVarId};
+use crate::ullbc_ast::{FunDecls, GlobalDecls};
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Compute the stable id of a declaration from its name and generic
+/// parameters.
+pub fn compute_stable_id(
+    name: &Name,
+    type_params: &TypeVarId::Vector<TypeVar>,
+    const_generic_params: &ConstGenericVarId::Vector<ConstGenericVar>,
+) -> String {
+    let path = name
+        .name
+        .iter()
+        .map(|elem| match elem {
+            PathElem::Ident(s) => s.clone(),
+            PathElem::Disambiguator(d) => format!("#{d}"),
+        })
+        .collect::<Vec<String>>()
+        .join("::");
+    let params: Vec<&str> = type_params
+        .iter()
+        .map(|v| v.name.as_str())
+        .chain(const_generic_params.iter().map(|v| v.name.as_str()))
+        .collect();
+    if params.is_empty() {
+        path
+    } else {
+        format!("{path}<{}>", params.join(", "))
+    }
+}
+
+impl<T: Debug + Clone + Serialize> HasStableId for GFunDecl<T> {
+    fn stable_id(&self) -> Option<&str> {
+        self.stable_id.as_deref()
+    }
+}
+
+impl<T: Debug + Clone + Serialize> HasStableId for GGlobalDecl<T> {
+    fn stable_id(&self) -> Option<&str> {
+        self.stable_id.as_deref()
+    }
+}
+
+impl HasStableId for TypeDecl {
+    fn stable_id(&self) -> Option<&str> {
+        self.stable_id.as_deref()
+    }
+}
+
+/// Fill the stable ids of the declarations. We do it on the ULLBC
+/// declarations: they are preserved when translating to LLBC.
+pub fn transform(type_defs: &mut TypeDecls, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for def in type_defs.iter_mut() {
+        def.stable_id = Some(compute_stable_id(
+            &def.name,
+            &def.type_params,
+            &def.const_generic_params,
+        ));
+    }
+    for def in funs.iter_mut() {
+        def.stable_id = Some(compute_stable_id(
+            &def.name,
+            &def.signature.type_params,
+            &def.signature.const_generic_params,
+        ));
+    }
+    for def in globals.iter_mut() {
+        def.stable_id = Some(compute_stable_id(
+            &def.name,
+            &TypeVarId::Vector::new(),
+            &ConstGenericVarId::Vector::new(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gast::*;
+    use crate::meta::*;
+    use crate::names::Disambiguator;
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::types::*;
+    use crate::ullbc_ast::FunDecl;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    /// An opaque function `fn name<T>()`
+    fn mk_fun(id: usize, name: Name) -> FunDecl {
+        let mut type_params = TypeVarId::Vector::new();
+        type_params.push_back(TypeVar {
+            index: TypeVarId::ZERO,
+            name: "T".to_string(),
+        });
+        FunDecl {
+            def_id: FunDeclId::Id::new(id),
+            meta: dummy_meta(),
+            name,
+            signature: FunSig {
+                region_params: RegionVarId::Vector::new(),
+                num_early_bound_regions: 0,
+                type_params,
+                const_generic_params: ConstGenericVarId::Vector::new(),
+                predicates: Vec::new(),
+                inputs: Vec::new(),
+                output: Ty::mk_unit(),
+                regions_hierarchy: RegionGroups::new(),
+            },
+            body: None,
+            builtin: None,
+            calling_convention: CallingConvention::Rust,
+            is_const_fn: false,
+            is_unsafe: false,
+            calls_unsafe: false,
+            self_type: None,
+            trait_id: None,
+            trait_impl_info: None,
+            is_trait_default: false,
+            overrides_default: None,
+            captures: Vec::new(),
+            implemented_drop: false,
+            attributes: Vec::new(),
+            is_root: true,
+            stable_id: None,
+        }
+    }
+
+    fn mk_funs(funs: Vec<FunDecl>) -> FunDecls {
+        let mut funs: FunDecls = funs.into_iter().map(|d| (d.def_id, d)).collect();
+        transform(&mut TypeDecls::new(), &mut funs, &mut GlobalDecls::new());
+        funs
+    }
+
+    #[test]
+    fn test_disambiguator() {
+        let name = Name {
+            name: vec![
+                PathElem::Ident("test".to_string()),
+                PathElem::Ident("Foo".to_string()),
+                PathElem::Disambiguator(Disambiguator::Id::new(1)),
+                PathElem::Ident("f".to_string()),
+            ],
+        };
+        let funs = mk_funs(vec![mk_fun(0, name)]);
+        let f = funs.get(FunDeclId::ZERO).unwrap();
+        assert!(f.stable_id() == Some("test::Foo::#1::f<T>"));
+    }
+
+    /// Adding a function shifts the numeric id of `f`, but not its stable id
+    #[test]
+    fn test_stable_under_insertion() {
+        let f = Name::from(vec!["test".to_string(), "f".to_string()]);
+        let g = Name::from(vec!["test".to_string(), "g".to_string()]);
+        let before = mk_funs(vec![mk_fun(0, f.clone())]);
+        let after = mk_funs(vec![mk_fun(0, g), mk_fun(1, f)]);
+
+        let f_before = before.lookup_by_stable_id("test::f<T>").unwrap();
+        let f_after = after.lookup_by_stable_id("test::f<T>").unwrap();
+        assert!(f_before.def_id != f_after.def_id);
+        assert!(f_before.stable_id == f_after.stable_id);
+        assert!(after.lookup_by_stable_id("test::h<T>").is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! Compute some statistics about the translated functions (number of blocks,
+//! statements, etc.). This is useful to identify the functions which are
+//! expensive to translate or to analyze.
+
+use crate::export::SerializeError;
+use crate::expressions::SharedExprVisitor;
+use crate::names::Name;
+use crate::types::SharedTypeVisitor;
+use crate::ullbc_ast::{BlockData, FunDecls, SharedAstVisitor, Statement, Terminator};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The statistics of a function. The counts are 0 if the function doesn't
+/// have a body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunStats {
+    pub name: Name,
+    pub block_count: usize,
+    pub statement_count: usize,
+    pub local_count: usize,
+    pub terminator_count: usize,
+}
+
+/// Count the blocks, statements and terminators of a body.
+#[derive(Default)]
+struct Counter {
+    blocks: usize,
+    statements: usize,
+    terminators: usize,
+}
+
+impl SharedTypeVisitor for Counter {}
+impl SharedExprVisitor for Counter {}
+
+impl SharedAstVisitor for Counter {
+    fn visit_block_data(&mut self, block: &BlockData) {
+        self.blocks += 1;
+        for st in &block.statements {
+            self.visit_statement(st);
+        }
+        self.visit_terminator(&block.terminator);
+    }
+
+    fn visit_statement(&mut self, st: &Statement) {
+        self.statements += 1;
+        self.visit_meta(&st.meta);
+        self.visit_raw_statement(&st.content);
+    }
+
+    fn visit_terminator(&mut self, st: &Terminator) {
+        self.terminators += 1;
+        self.visit_meta(&st.meta);
+        self.visit_raw_terminator(&st.content);
+    }
+}
+
+/// Compute the statistics of the functions, in the order of their identifiers.
+pub fn compute_stats(fun_defs: &FunDecls) -> Vec<FunStats> {
+    fun_defs
+        .iter()
+        .map(|decl| {
+            let mut counter = Counter::default();
+            let local_count = match &decl.body {
+                Option::None => 0,
+                Option::Some(body) => {
+                    for block in body.body.iter() {
+                        counter.visit_block_data(block);
+                    }
+                    body.locals.len()
+                }
+            };
+            FunStats {
+                name: decl.name.clone(),
+                block_count: counter.blocks,
+                statement_count: counter.statements,
+                local_count,
+                terminator_count: counter.terminators,
+            }
+        })
+        .collect()
+}
+
+/// Print the statistics as a table, sorted by decreasing number of blocks.
+pub fn print_stats_table(stats: &[FunStats], out: &mut impl Write) -> std::io::Result<()> {
+    let mut stats: Vec<&FunStats> = stats.iter().collect();
+    stats.sort_by(|s0, s1| s1.block_count.cmp(&s0.block_count));
+
+    let names: Vec<String> = stats.iter().map(|s| s.name.to_string()).collect();
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0).max(8);
+    writeln!(
+        out,
+        "{:width$}  {:>6}  {:>10}  {:>6}  {:>11}",
+        "function", "blocks", "statements", "locals", "terminators"
+    )?;
+    for (name, s) in names.iter().zip(stats) {
+        writeln!(
+            out,
+            "{:width$}  {:>6}  {:>10}  {:>6}  {:>11}",
+            name, s.block_count, s.statement_count, s.local_count, s.terminator_count
+        )?;
+    }
+    Ok(())
+}
+
+/// Serialize the statistics to a JSON file (`CRATE.stats.json`), next to the
+/// generated files.
+pub fn export_stats(
+    crate_name: &str,
+    stats: &[FunStats],
+    dest_dir: &Option<PathBuf>,
+) -> std::result::Result<(), SerializeError> {
+    let mut target_filename = dest_dir
+        .as_deref()
+        .map_or_else(PathBuf::new, Path::to_path_buf);
+    target_filename.push(format!("{crate_name}.stats.json"));
+
+    if let Some(dest_dir) = dest_dir {
+        std::fs::create_dir_all(dest_dir).map_err(|e| SerializeError::Io(dest_dir.clone(), e))?;
+    }
+
+    let json = serde_json::to_string(stats)
+        .map_err(|e| SerializeError::Format(target_filename.clone(), e.to_string()))?;
+    std::fs::write(&target_filename, json)
+        .map_err(|e| SerializeError::Io(target_filename.clone(), e))?;
+    info!("Generated the file: {}", target_filename.to_string_lossy());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::names::PathElem;
+    use crate::ullbc_ast::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn block(statements: Vec<Statement>, content: RawTerminator) -> BlockData {
+        BlockData {
+            statements,
+            terminator: Terminator::new(dummy_meta(), content),
+            is_loop_header: false,
+        }
+    }
+
+    #[test]
+    fn test_counter() {
+        let x = VarId::Id::new(1);
+        let fake_read = Statement::new(dummy_meta(), RawStatement::FakeRead(Place::new(x)));
+        let mut counter = Counter::default();
+        counter.visit_block_data(&block(
+            vec![fake_read.clone(), fake_read],
+            RawTerminator::Goto {
+                target: BlockId::Id::new(1),
+            },
+        ));
+        counter.visit_block_data(&block(vec![], RawTerminator::Return));
+        assert!(counter.blocks == 2 && counter.statements == 2 && counter.terminators == 2);
+    }
+
+    #[test]
+    fn test_print_stats_table() {
+        let mk_stats = |name: &str, block_count| FunStats {
+            name: Name {
+                name: vec![PathElem::Ident(name.to_string())],
+            },
+            block_count,
+            statement_count: 0,
+            local_count: 0,
+            terminator_count: block_count,
+        };
+        let stats = vec![mk_stats("small", 1), mk_stats("big", 10)];
+        let mut out = Vec::new();
+        print_stats_table(&stats, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines.len() == 3);
+        // The functions are sorted by decreasing number of blocks
+        assert!(lines[1].starts_with("big") && lines[2].starts_with("small"));
+    }
+
+    /// We can't create the destination directory if a file has its name
+    #[test]
+    fn test_export_error() {
+        let path = std::env::temp_dir().join(format!("charon_stats_{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let res = export_stats("test", &[], &Some(path.clone()));
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(res, Err(SerializeError::Io(p, _)) if p == path));
+    }
+}
@@ -0,0 +1,180 @@
+//! Replace the statements coming from macro expansions with placeholders
+//! (`--strip-macro-metadata`).
+//!
+//! Macros like `assert!` or `println!` expand to several statements, which
+//! clutter the output. We replace the statements whose meta information
+//! says they come from a macro expansion (see [crate::meta::Meta::macro_name])
+//! with a [RawStatement::MacroExpansion] placeholder, and merge the
+//! consecutive placeholders for the same macro. For instance, we transform:
+//! ```text
+//! tmp := copy x == const 1; // Written by the user
+//! assert(move tmp == true); // From `assert!`
+//! return;
+//! ```
+//! to:
+//! ```text
+//! tmp := copy x == const 1;
+//! assert!(...);
+//! return;
+//! ```
+//! We don't replace the `return`, `break` and `continue` statements, which
+//! give the control-flow of the function. Note that this transformation
+//! loses information: it is only meant to make the output more readable.
+//!
+//! This pass must be applied last, because the other passes don't handle
+//! the placeholders.
+
+use take_mut::take;
+
+use crate::expressions::MutExprVisitor;
+use crate::llbc_ast::{CtxNames, FunDecls, GlobalDecls, MutAstVisitor, RawStatement, Statement};
+use crate::types::MutTypeVisitor;
+use crate::ullbc_ast::{iter_function_bodies, iter_global_bodies};
+
+/// Return the name of the macro the statement comes from, if we should
+/// replace it with a placeholder.
+fn strip_macro_name(st: &Statement) -> Option<String> {
+    match &st.content {
+        RawStatement::Sequence(..)
+        | RawStatement::Return
+        | RawStatement::Break(_)
+        | RawStatement::Continue(_)
+        | RawStatement::Nop
+        | RawStatement::MacroExpansion(_) => None,
+        _ => st.meta.macro_name.map(|name| name.to_string()),
+    }
+}
+
+/// Check if `st1; st2` starts with two placeholders for the same macro.
+fn merge_placeholders(st1: &Statement, st2: &Statement) -> bool {
+    let st2 = match &st2.content {
+        RawStatement::Sequence(st2, _) => st2,
+        _ => st2,
+    };
+    match (&st1.content, &st2.content) {
+        (RawStatement::MacroExpansion(name1), RawStatement::MacroExpansion(name2)) => {
+            name1 == name2
+        }
+        _ => false,
+    }
+}
+
+struct Transform {}
+
+impl MutTypeVisitor for Transform {}
+impl MutExprVisitor for Transform {}
+
+impl MutAstVisitor for Transform {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &mut Statement) {
+        // Bottom-up
+        self.default_visit_raw_statement(&mut st.content);
+
+        if let Some(name) = strip_macro_name(st) {
+            st.content = RawStatement::MacroExpansion(name);
+        } else if let RawStatement::Sequence(st1, st2) = &st.content {
+            if merge_placeholders(st1, st2) {
+                // Only keep the second placeholder
+                take(st, |st| {
+                    let (_, st2) = st.content.to_sequence();
+                    *st2
+                });
+            }
+        }
+    }
+}
+
+pub fn transform(fmt_ctx: &CtxNames<'_>, funs: &mut FunDecls, globals: &mut GlobalDecls) {
+    for (name, b) in iter_function_bodies(funs).chain(iter_global_bodies(globals)) {
+        trace!(
+            "# About to strip the macro expansions in decl: {name}\n{}",
+            b.fmt_with_ctx_names(fmt_ctx)
+        );
+        Transform {}.visit_statement(&mut b.body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::llbc_ast::Assert;
+    use crate::meta::*;
+    use crate::types::*;
+    use crate::values::*;
+
+    fn meta(macro_name: Option<&str>) -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: macro_name.is_some(),
+            macro_name: macro_name.map(MacroName::new),
+        }
+    }
+
+    fn seq(sts: Vec<Statement>) -> Statement {
+        sts.into_iter()
+            .rev()
+            .reduce(|st2, st1| {
+                Statement::new(
+                    meta(None),
+                    RawStatement::Sequence(Box::new(st1), Box::new(st2)),
+                )
+            })
+            .unwrap()
+    }
+
+    fn contents(st: &Statement) -> Vec<&RawStatement> {
+        match &st.content {
+            RawStatement::Sequence(st1, st2) => {
+                let mut sts = vec![&st1.content];
+                sts.extend(contents(st2));
+                sts
+            }
+            content => vec![content],
+        }
+    }
+
+    /// `assert!(x == 1)`, followed by `println!()` and a return
+    #[test]
+    fn test_strip_assert() {
+        let x = Place::new(VarId::Id::new(1));
+        let tmp = Place::new(VarId::Id::new(2));
+        let one = Operand::Const(
+            Ty::Literal(LiteralTy::Integer(IntegerTy::U32)),
+            OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(1))),
+        );
+        let cmp = Rvalue::BinaryOp(BinOp::Eq, Operand::Copy(x.clone()), one);
+        let assert = Assert {
+            cond: Operand::Move(tmp.clone()),
+            expected: true,
+        };
+        let mut body = seq(vec![
+            Statement::new(meta(None), RawStatement::Assign(tmp, cmp)),
+            Statement::new(meta(Some("assert")), RawStatement::Assert(assert)),
+            Statement::new(meta(Some("println")), RawStatement::Nop),
+            Statement::new(meta(Some("println")), RawStatement::FakeRead(x.clone())),
+            Statement::new(meta(Some("println")), RawStatement::FakeRead(x)),
+            Statement::new(meta(Some("assert")), RawStatement::Return),
+        ]);
+        Transform {}.visit_statement(&mut body);
+
+        let sts = contents(&body);
+        assert!(sts.len() == 5);
+        assert!(sts[0].is_assign());
+        assert!(matches!(sts[1], RawStatement::MacroExpansion(name) if name == "assert"));
+        assert!(sts[2].is_nop());
+        assert!(matches!(sts[3], RawStatement::MacroExpansion(name) if name == "println"));
+        assert!(sts[4].is_return());
+    }
+}
@@ -0,0 +1,283 @@
+//! Substitute the generic parameters (types, regions and const generics) in
+//! the translated types and function bodies. This is useful for the tools
+//! which need to specialize the functions (for instance, to monomorphize
+//! them).
+//!
+//! The variables for which the substitution doesn't give a value are left
+//! unchanged.
+#![allow(dead_code)]
+
+use crate::expressions::*;
+use crate::gast::{Call, FunDeclId};
+use crate::llbc_ast::{ExprBody, MutAstVisitor};
+use crate::types::*;
+use crate::types_interner::InternedTy;
+use std::collections::HashMap;
+
+/// A substitution for the generic parameters.
+#[derive(Debug, Clone, Default)]
+pub struct GenericSubst {
+    pub type_subst: HashMap<TypeVarId::Id, ETy>,
+    /// The erased types have no region variables: this substitution is only
+    /// used for the types which still have regions (see [substitute_rty]).
+    pub region_subst: HashMap<RegionVarId::Id, ErasedRegion>,
+    pub const_subst: HashMap<ConstGenericVarId::Id, ConstGeneric>,
+}
+
+impl GenericSubst {
+    fn subst_type_var(&self, id: &TypeVarId::Id) -> ETy {
+        match self.type_subst.get(id) {
+            Some(ty) => ty.clone(),
+            None => Ty::TypeVar(*id),
+        }
+    }
+
+    fn subst_const_generic_var(&self, id: &ConstGenericVarId::Id) -> ConstGeneric {
+        match self.const_subst.get(id) {
+            Some(cg) => cg.clone(),
+            None => ConstGeneric::Var(*id),
+        }
+    }
+}
+
+/// Substitute the generic parameters in an erased type.
+pub fn substitute_ty(ty: &ETy, subst: &GenericSubst) -> ETy {
+    ty.substitute(&|r| *r, &|id| subst.subst_type_var(id), &|id| {
+        subst.subst_const_generic_var(id)
+    })
+}
+
+/// Substitute the generic parameters in a type with regions (for instance,
+/// a type of a function signature) and erase the regions.
+pub fn substitute_rty(ty: &RTy, subst: &GenericSubst) -> ETy {
+    ty.substitute(
+        &|r| match r {
+            Region::Var(id) => *subst.region_subst.get(id).unwrap_or(&ErasedRegion::Erased),
+            _ => ErasedRegion::Erased,
+        },
+        &|id| subst.subst_type_var(id),
+        &|id| subst.subst_const_generic_var(id),
+    )
+}
+
+pub fn substitute_const_generic(cg: &ConstGeneric, subst: &GenericSubst) -> ConstGeneric {
+    cg.substitute(&|id| subst.subst_const_generic_var(id))
+}
+
+/// Substitute the generic parameters in a function body: in the types of
+/// the local variables and in the statements.
+pub fn substitute_fun_body(body: &ExprBody, subst: &GenericSubst) -> ExprBody {
+    let mut body = body.clone();
+    for var in body.locals.iter_mut() {
+        var.ty = InternedTy::new(substitute_ty(&var.ty, subst));
+    }
+    let mut visitor = Substitute { subst };
+    visitor.visit_statement(&mut body.body);
+    body
+}
+
+/// The type visitors are generic over the regions: we can't substitute the
+/// types in [MutTypeVisitor::visit_ty]. Instead, we substitute the types at
+/// the places where the expressions and the statements contain types.
+struct Substitute<'a> {
+    subst: &'a GenericSubst,
+}
+
+impl<'a> Substitute<'a> {
+    fn substitute_tys(&self, tys: &mut [ETy]) {
+        for ty in tys.iter_mut() {
+            *ty = substitute_ty(ty, self.subst);
+        }
+    }
+
+    fn substitute_cgs(&self, cgs: &mut [ConstGeneric]) {
+        for cg in cgs.iter_mut() {
+            *cg = substitute_const_generic(cg, self.subst);
+        }
+    }
+}
+
+impl<'a> MutTypeVisitor for Substitute<'a> {
+    fn visit_const_generic(&mut self, cg: &mut ConstGeneric) {
+        *cg = substitute_const_generic(cg, self.subst);
+    }
+}
+
+impl<'a> MutExprVisitor for Substitute<'a> {
+    fn visit_operand_const(&mut self, ty: &mut ETy, op: &mut OperandConstantValue) {
+        *ty = substitute_ty(ty, self.subst);
+        self.visit_operand_constant_value(op);
+    }
+
+    fn visit_aggregate_kind(&mut self, ak: &mut AggregateKind) {
+        use AggregateKind::*;
+        match ak {
+            Tuple => (),
+            Option(_, ty) | PhantomData(ty) | RawPtr(ty, _) => *ty = substitute_ty(ty, self.subst),
+            Range(_, tys) | Closure(_, _, tys) | Generator(_, tys) => self.substitute_tys(tys),
+            Adt(_, _, _, tys, cgs) | Union(_, _, _, tys, cgs) => {
+                self.substitute_tys(tys);
+                self.substitute_cgs(cgs);
+            }
+            StructUpdate {
+                base,
+                updated_fields,
+                ..
+            } => {
+                self.visit_operand(base);
+                for (_, op) in updated_fields {
+                    self.visit_operand(op);
+                }
+            }
+            Array(ty, cg) => {
+                *ty = substitute_ty(ty, self.subst);
+                self.visit_const_generic(cg);
+            }
+            Repeat(ty, op, cg) => {
+                *ty = substitute_ty(ty, self.subst);
+                self.visit_operand(op);
+                self.visit_const_generic(cg);
+            }
+        }
+    }
+
+    fn visit_len(&mut self, p: &mut Place, ty: &mut ETy, cg: &mut Option<ConstGeneric>) {
+        *ty = substitute_ty(ty, self.subst);
+        if let Some(cg) = cg {
+            self.visit_const_generic(cg);
+        }
+        self.visit_place(p)
+    }
+
+    fn visit_fn_ptr(
+        &mut self,
+        _: &mut FunDeclId::Id,
+        tys: &mut Vec<ETy>,
+        cgs: &mut Vec<ConstGeneric>,
+    ) {
+        self.substitute_tys(tys);
+        self.substitute_cgs(cgs);
+    }
+
+    fn visit_call(&mut self, c: &mut Call) {
+        self.substitute_tys(&mut c.type_args);
+        self.substitute_cgs(&mut c.const_generic_args);
+        for o in &mut c.args {
+            self.visit_operand(o);
+        }
+        self.visit_place(&mut c.dest);
+    }
+}
+
+impl<'a> MutAstVisitor for Substitute<'a> {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llbc_ast::{RawStatement, Statement};
+    use crate::meta::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn u32_ty() -> ETy {
+        Ty::Literal(LiteralTy::Integer(IntegerTy::U32))
+    }
+
+    /// `T := u32`, `N := 3`
+    fn mk_subst() -> GenericSubst {
+        let mut subst = GenericSubst::default();
+        subst.type_subst.insert(TypeVarId::ZERO, u32_ty());
+        let three = Literal::Scalar(ScalarValue::Usize(PointerSize::P64, 3));
+        subst
+            .const_subst
+            .insert(ConstGenericVarId::ZERO, ConstGeneric::Value(three));
+        subst
+    }
+
+    #[test]
+    fn test_substitute_type_var() {
+        let subst = mk_subst();
+        assert!(substitute_ty(&Ty::TypeVar(TypeVarId::ZERO), &subst) == u32_ty());
+
+        // `[T; N]` becomes `[u32; 3]`, and the other variables are unchanged
+        let array = |ty, cg| {
+            Ty::Adt(
+                TypeId::Assumed(AssumedTy::Array),
+                vec![],
+                vec![ty],
+                vec![cg],
+            )
+        };
+        let ty = array(
+            Ty::TypeVar(TypeVarId::ZERO),
+            ConstGeneric::Var(ConstGenericVarId::ZERO),
+        );
+        let three = ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(PointerSize::P64, 3)));
+        assert!(substitute_ty(&ty, &subst) == array(u32_ty(), three));
+        let other = Ty::TypeVar(TypeVarId::ONE);
+        assert!(substitute_ty(&other, &subst) == other);
+    }
+
+    /// ```text
+    /// fn f<T>(x: T) -> Option<T> {
+    ///   ret := Some(move x);
+    ///   return;
+    /// }
+    /// ```
+    #[test]
+    fn test_substitute_fun_body() {
+        let t = Ty::TypeVar(TypeVarId::ZERO);
+        let option =
+            |ty: ETy| Ty::Adt(TypeId::Assumed(AssumedTy::Option), vec![], vec![ty], vec![]);
+        let mut locals = VarId::Vector::new();
+        let ret = locals.fresh_var(None, option(t.clone()));
+        let x = locals.fresh_var(Some("x".to_string()), t.clone());
+        let some = Rvalue::Aggregate(
+            AggregateKind::Option(VariantId::ONE, t),
+            vec![Operand::Move(Place::new(x))],
+        );
+        let assign = RawStatement::Assign(Place::new(ret), some);
+        let body = RawStatement::Sequence(
+            Box::new(Statement::new(dummy_meta(), assign)),
+            Box::new(Statement::new(dummy_meta(), RawStatement::Return)),
+        );
+        let body = ExprBody {
+            meta: dummy_meta(),
+            arg_count: 1,
+            locals,
+            body: Statement::new(dummy_meta(), body),
+            liveness: None,
+        };
+
+        let body = substitute_fun_body(&body, &mk_subst());
+        assert!(*body.locals.get(ret).unwrap().ty == option(u32_ty()));
+        assert!(*body.locals.get(x).unwrap().ty == u32_ty());
+        let kind = match &body.body.content {
+            RawStatement::Sequence(assign, _) => match &assign.content {
+                RawStatement::Assign(_, Rvalue::Aggregate(kind, _)) => kind,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert!(*kind == AggregateKind::Option(VariantId::ONE, u32_ty()));
+    }
+}
@@ -0,0 +1,468 @@
+//! A uniform interface for the micro-passes which transform the LLBC
+//! declarations ([Transform]), and a pipeline to run them in order
+//! ([TransformPipeline]).
+//!
+//! The users of the library can insert their own transformations in the
+//! pipeline, and register hooks to inspect the declarations before or after
+//! a given transformation (for instance, to dump the bodies when debugging a
+//! micro-pass).
+
+#![allow(dead_code)]
+
+use crate::constant_propagation;
+use crate::cse;
+use crate::index_to_function_calls;
+use crate::insert_assign_return_unit;
+use crate::llbc_ast::{CtxNames, FunDeclId, FunDecls, GlobalDeclId, GlobalDecls};
+use crate::normalize_projections;
+use crate::ops_to_function_calls;
+use crate::reconstruct_asserts;
+use crate::reconstruct_if_let;
+use crate::reconstruct_let_else;
+use crate::reconstruct_try;
+use crate::remove_drop_never;
+use crate::remove_dynamic_checks;
+use crate::remove_read_discriminant;
+use crate::remove_unused_locals;
+use crate::strip_macro_metadata;
+use crate::types::TypeDecls;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+/// The declarations the transformations operate on.
+pub struct TranslatedCrate<'a> {
+    /// The transformations don't modify the type declarations.
+    pub type_defs: &'a TypeDecls,
+    pub fun_defs: FunDecls,
+    pub global_defs: GlobalDecls,
+    /// The names of the functions and globals, used for pretty-printing
+    /// purposes (see [CtxNames]). The transformations don't change them.
+    fun_names: FunDeclId::Map<String>,
+    global_names: GlobalDeclId::Map<String>,
+}
+
+impl<'a> TranslatedCrate<'a> {
+    pub fn new(type_defs: &'a TypeDecls, fun_defs: FunDecls, global_defs: GlobalDecls) -> Self {
+        let fun_names =
+            FunDeclId::Map::from_iter(fun_defs.iter().map(|d| (d.def_id, d.name.to_string())));
+        let global_names = GlobalDeclId::Map::from_iter(
+            global_defs.iter().map(|d| (d.def_id, d.name.to_string())),
+        );
+        TranslatedCrate {
+            type_defs,
+            fun_defs,
+            global_defs,
+            fun_names,
+            global_names,
+        }
+    }
+
+    /// Retrieve the (transformed) function and global declarations.
+    pub fn into_decls(self) -> (FunDecls, GlobalDecls) {
+        (self.fun_defs, self.global_defs)
+    }
+}
+
+/// An error raised by a transformation.
+#[derive(Debug)]
+pub struct TransformError {
+    /// The name of the transformation
+    pub transform: String,
+    pub msg: String,
+}
+
+impl std::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.transform, self.msg)
+    }
+}
+
+/// A transformation of the LLBC declarations.
+pub trait Transform {
+    /// The name of the transformation, which identifies it when registering
+    /// hooks (see [TransformPipeline::before]).
+    fn name(&self) -> &str;
+
+    fn run(&self, ctx: &mut TranslatedCrate<'_>) -> Result<(), TransformError>;
+}
+
+/// The signature of the micro-passes (see for instance
+/// [crate::remove_unused_locals::transform]).
+pub type TransformFn = fn(&CtxNames<'_>, &mut FunDecls, &mut GlobalDecls);
+
+/// A transformation implemented by a micro-pass function.
+pub struct FunTransform {
+    name: &'static str,
+    fun: TransformFn,
+}
+
+impl FunTransform {
+    pub fn new(name: &'static str, fun: TransformFn) -> Self {
+        FunTransform { name, fun }
+    }
+}
+
+impl Transform for FunTransform {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn run(&self, ctx: &mut TranslatedCrate<'_>) -> Result<(), TransformError> {
+        let fmt_ctx = CtxNames::new(ctx.type_defs, &ctx.fun_names, &ctx.global_names);
+        (self.fun)(&fmt_ctx, &mut ctx.fun_defs, &mut ctx.global_defs);
+        Ok(())
+    }
+}
+
+/// A callback called before or after a transformation. The hooks can only
+/// inspect the declarations: the modifications should be implemented as
+/// transformations.
+pub type TransformHook = Box<dyn Fn(&TranslatedCrate<'_>)>;
+
+/// A sequence of transformations, together with the hooks to call around
+/// them.
+#[derive(Default)]
+pub struct TransformPipeline {
+    transforms: Vec<Box<dyn Transform>>,
+    before: HashMap<String, Vec<TransformHook>>,
+    after: HashMap<String, Vec<TransformHook>>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a transformation at the end of the pipeline.
+    pub fn push(&mut self, transform: Box<dyn Transform>) {
+        self.transforms.push(transform);
+    }
+
+    /// The names of the transformations, in the order in which we run them.
+    pub fn names(&self) -> Vec<&str> {
+        self.transforms.iter().map(|t| t.name()).collect()
+    }
+
+    /// Register a hook to call before every run of the transformation called
+    /// `name`. The hooks are called in the order in which they were
+    /// registered.
+    pub fn before(&mut self, name: &str, hook: TransformHook) {
+        self.before.entry(name.to_string()).or_default().push(hook);
+    }
+
+    /// Register a hook to call after every run of the transformation called
+    /// `name`.
+    pub fn after(&mut self, name: &str, hook: TransformHook) {
+        self.after.entry(name.to_string()).or_default().push(hook);
+    }
+
+    /// Run the transformations in order. We stop at the first error.
+    pub fn run(&self, ctx: &mut TranslatedCrate<'_>) -> Result<(), TransformError> {
+        for transform in &self.transforms {
+            let name = transform.name();
+            trace!("# Running the transformation: {name}");
+            for hook in self.before.get(name).into_iter().flatten() {
+                hook(ctx);
+            }
+            transform.run(ctx)?;
+            for hook in self.after.get(name).into_iter().flatten() {
+                hook(ctx);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The micro-passes we apply to the LLBC, in order. The normalization of the
+/// projections, the constant propagation, the reconstruction of the `?`
+/// operators, the common subexpression elimination and the removal of the
+/// macro expansions are optional.
+pub fn llbc_pipeline(
+    normalize_projections: bool,
+    constant_propagation: bool,
+    reconstruct_try: bool,
+    cse: bool,
+    strip_macro_metadata: bool,
+) -> TransformPipeline {
+    let mut pipeline = TransformPipeline::new();
+    let mut push = |name: &'static str, fun: TransformFn| {
+        pipeline.push(Box::new(FunTransform::new(name, fun)))
+    };
+
+    // # Micro-pass: remove the dynamic checks for array/slice bounds
+    // and division by zero.
+    // **WARNING**: this pass uses the fact that the dynamic checks
+    // introduced by Rustc use a special "assert" construct. Because of
+    // this, it must happen *before* the [reconstruct_asserts] pass.
+    // See the comments in [crate::remove_dynamic_checks].
+    push("remove_dynamic_checks", remove_dynamic_checks::transform);
+
+    // # Micro-pass: reconstruct the asserts
+    push("reconstruct_asserts", reconstruct_asserts::transform);
+
+    // # Micro-pass: replace some unops/binops with function calls
+    // (introduces: ArrayToSlice, etc.)
+    push("ops_to_function_calls", ops_to_function_calls::transform);
+
+    // # Micro-pass: replace the arrays/slices index operations with function
+    // calls.
+    // (introduces: ArrayIndexShared, ArrayIndexMut, etc.)
+    push(
+        "index_to_function_calls",
+        index_to_function_calls::transform,
+    );
+
+    // # Micro-pass: merge the consecutive projections of the places which
+    // can be expressed as a single projection (for instance, the indexing
+    // in a subslice).
+    if normalize_projections {
+        push("normalize_projections", normalize_projections::transform);
+    }
+
+    // # Micro-pass: Remove the discriminant reads (merge them with the switches)
+    push(
+        "remove_read_discriminant",
+        remove_read_discriminant::transform,
+    );
+
+    // # Micro-pass: add the missing assignments to the return value.
+    // When the function return type is unit, the generated MIR doesn't
+    // set the return value to `()`. This can be a concern: in the case
+    // of Aeneas, it means the return variable contains ⊥ upon returning.
+    // For this reason, when the function has return type unit, we insert
+    // an extra assignment just before returning.
+    // This also applies to globals (for checking or executing code before
+    // the main or at compile-time).
+    push(
+        "insert_assign_return_unit",
+        insert_assign_return_unit::transform,
+    );
+
+    // # Micro-pass: remove the drops of locals whose type is `Never` (`!`). This
+    // is in preparation of the next transformation.
+    push("remove_drop_never", remove_drop_never::transform);
+
+    // # Micro-pass: propagate the constants assigned to temporaries to the
+    // place where those temporaries are used. This leaves unused
+    // temporaries, which are filtered by the next pass.
+    if constant_propagation {
+        push("constant_propagation", constant_propagation::transform);
+    }
+
+    // # Micro-pass: share the computations which appear several times in a
+    // sequence of statements. This introduces temporaries, which may end up
+    // unused and are filtered by the next pass.
+    if cse {
+        push("cse", cse::transform);
+    }
+
+    // # Micro-pass: remove the locals which are never used. After doing so, we
+    // check that there are no remaining locals with type `Never`.
+    push("remove_unused_locals", remove_unused_locals::transform);
+
+    // # Micro-pass: reconstruct the `?` operators. This must happen after
+    // [remove_read_discriminant] (which introduces the matches), and before
+    // [reconstruct_let_else]: the matches introduced by `?` have the shape of
+    // a `let ... else`.
+    if reconstruct_try {
+        push("reconstruct_try", reconstruct_try::transform);
+    }
+
+    // # Micro-pass: reconstruct the `let ... else { ... }` statements.
+    // This must happen after [remove_read_discriminant] (which introduces
+    // the matches). We do this last because the previous passes don't
+    // handle the [crate::llbc_ast::RawStatement::LetElse] statements.
+    push("reconstruct_let_else", reconstruct_let_else::transform);
+
+    // # Micro-pass: reconstruct the `if let ... { ... } else { ... }`
+    // statements. This must happen after [reconstruct_let_else]: the
+    // matches whose `otherwise` branch diverges are `let ... else`.
+    push("reconstruct_if_let", reconstruct_if_let::transform);
+
+    // # Micro-pass: replace the statements coming from macro expansions with
+    // placeholders. This must happen last: the other passes don't handle the
+    // [crate::llbc_ast::RawStatement::MacroExpansion] statements.
+    if strip_macro_metadata {
+        push("strip_macro_metadata", strip_macro_metadata::transform);
+    }
+
+    pipeline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gast::{CallingConvention, FunSig};
+    use crate::llbc_ast::{ExprBody, FunDecl, RawStatement, Statement};
+    use crate::meta::*;
+    use crate::names::*;
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::types::*;
+    use crate::values::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    /// ```text
+    /// fn f(x: u32) {
+    ///   let unused: u32;
+    ///   return;
+    /// }
+    /// ```
+    fn mk_funs() -> FunDecls {
+        let unit_ty = Ty::mk_unit();
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut locals = VarId::Vector::new();
+        locals.fresh_var(None, unit_ty);
+        locals.fresh_var(Some("x".to_string()), u32_ty.clone());
+        locals.fresh_var(Some("unused".to_string()), u32_ty);
+
+        let decl = FunDecl {
+            def_id: FunDeclId::ZERO,
+            meta: dummy_meta(),
+            name: Name {
+                name: vec![PathElem::Ident("f".to_string())],
+            },
+            signature: FunSig {
+                region_params: RegionVarId::Vector::new(),
+                num_early_bound_regions: 0,
+                type_params: TypeVarId::Vector::new(),
+                const_generic_params: ConstGenericVarId::Vector::new(),
+                predicates: Vec::new(),
+                inputs: vec![Ty::Literal(LiteralTy::Integer(IntegerTy::U32))],
+                output: Ty::mk_unit(),
+                regions_hierarchy: RegionGroups::new(),
+            },
+            body: Some(ExprBody {
+                meta: dummy_meta(),
+                arg_count: 1,
+                locals,
+                body: Statement::new(dummy_meta(), RawStatement::Return),
+                liveness: None,
+            }),
+            builtin: None,
+            calling_convention: CallingConvention::Rust,
+            is_const_fn: false,
+            is_unsafe: false,
+            calls_unsafe: false,
+            self_type: None,
+            trait_id: None,
+            trait_impl_info: None,
+            is_trait_default: false,
+            overrides_default: None,
+            captures: Vec::new(),
+            implemented_drop: false,
+            attributes: Vec::new(),
+            is_root: true,
+            stable_id: None,
+        };
+        let mut funs = FunDecls::new();
+        funs.insert(FunDeclId::ZERO, decl);
+        funs
+    }
+
+    fn run(pipeline: &TransformPipeline) -> serde_json::Value {
+        let type_defs = TypeDecls::new();
+        let mut krate = TranslatedCrate::new(&type_defs, mk_funs(), GlobalDecls::new());
+        pipeline.run(&mut krate).unwrap();
+        let (funs, _) = krate.into_decls();
+        serde_json::to_value(&funs).unwrap()
+    }
+
+    fn mk_pipeline(order: &[&'static str]) -> TransformPipeline {
+        let mut pipeline = TransformPipeline::new();
+        for name in order {
+            let fun: TransformFn = match *name {
+                "insert_assign_return_unit" => insert_assign_return_unit::transform,
+                "remove_unused_locals" => remove_unused_locals::transform,
+                _ => unreachable!(),
+            };
+            pipeline.push(Box::new(FunTransform::new(name, fun)));
+        }
+        pipeline
+    }
+
+    #[test]
+    fn test_noop_hooks() {
+        let order = ["insert_assign_return_unit", "remove_unused_locals"];
+        let expected = run(&mk_pipeline(&order));
+
+        let calls = Rc::new(Cell::new(0));
+        let mut pipeline = mk_pipeline(&order);
+        for name in order {
+            let before_calls = calls.clone();
+            pipeline.before(
+                name,
+                Box::new(move |_| before_calls.set(before_calls.get() + 1)),
+            );
+            let after_calls = calls.clone();
+            pipeline.after(
+                name,
+                Box::new(move |_| after_calls.set(after_calls.get() + 1)),
+            );
+        }
+        assert!(run(&pipeline) == expected);
+        assert!(calls.get() == 4);
+    }
+
+    #[test]
+    fn test_swap_passes() {
+        let pipeline = mk_pipeline(&["insert_assign_return_unit", "remove_unused_locals"]);
+        let swapped = mk_pipeline(&["remove_unused_locals", "insert_assign_return_unit"]);
+        let output = run(&pipeline);
+        assert!(output == run(&swapped));
+
+        // Check that the passes did something: the unused local was removed,
+        // and we assign the return value
+        let body = &output[0][1]["body"];
+        assert!(body["locals"].as_array().unwrap().len() == 2);
+        assert!(body["body"]["content"]["Sequence"].is_array());
+    }
+
+    #[test]
+    fn test_llbc_pipeline() {
+        let pipeline = llbc_pipeline(true, true, true, true, true);
+        let names = pipeline.names();
+        let pos = |name| names.iter().position(|n| *n == name).unwrap();
+        assert!(pos("index_to_function_calls") < pos("normalize_projections"));
+        assert!(pos("constant_propagation") < pos("cse"));
+        assert!(pos("cse") < pos("remove_unused_locals"));
+        assert!(pos("remove_dynamic_checks") < pos("reconstruct_asserts"));
+        assert!(pos("remove_read_discriminant") < pos("reconstruct_try"));
+        assert!(pos("reconstruct_try") < pos("reconstruct_let_else"));
+        assert!(pos("reconstruct_let_else") < pos("reconstruct_if_let"));
+        assert!(names.last() == Some(&"strip_macro_metadata"));
+        let without_try = llbc_pipeline(true, true, false, true, true);
+        assert!(!without_try.names().contains(&"reconstruct_try"));
+        let without_cse = llbc_pipeline(true, true, true, false, true);
+        assert!(!without_cse.names().contains(&"cse"));
+        let without_strip = llbc_pipeline(true, true, true, true, false);
+        assert!(!without_strip.names().contains(&"strip_macro_metadata"));
+
+        // The optional passes are off by default
+        let default = llbc_pipeline(false, false, false, false, false);
+        for name in [
+            "normalize_projections",
+            "constant_propagation",
+            "reconstruct_try",
+            "cse",
+            "strip_macro_metadata",
+        ] {
+            assert!(!default.names().contains(&name));
+        }
+        assert!(default.names().contains(&"remove_unused_locals"));
+    }
+}
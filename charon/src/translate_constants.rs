@@ -1,67 +1,125 @@
 //! Functions to translate constants to LLBC.
 #![allow(dead_code)]
-use crate::common::*;
 use crate::expressions as e;
 use crate::get_mir::extract_constants_at_top_level;
 use crate::translate_ctx::*;
+use crate::translate_functions_to_ullbc::translate_binaryop_kind;
 use crate::types as ty;
 use crate::values as v;
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir;
 use rustc_middle::ty as mir_ty;
-use rustc_middle::ty::{ConstKind, Ty, TyKind};
+use rustc_middle::ty::{ConstKind, Ty, TyKind, TypeVisitableExt};
 use std::iter::zip;
 
 /// Translate a typed constant value (either a bool, a char or an integer).
+///
+/// We need the size of the pointers on the target to convert the `isize` and
+/// `usize` values. Return `None` if the type is not a literal type, or if the
+/// scalar doesn't have the size of the type.
 fn translate_constant_integer_like_value(
+    ptr_size: v::PointerSize,
     ty: &ty::ETy,
     scalar: &mir::interpret::Scalar,
-) -> v::Literal {
+) -> Option<v::Literal> {
     trace!();
     // The documentation explicitly says not to match on a scalar.
     // We match on the type and convert the value following this,
     // by calling the appropriate `to_*` method.
-    match ty {
-        ty::Ty::Literal(ty::LiteralTy::Bool) => v::Literal::Bool(scalar.to_bool().unwrap()),
-        ty::Ty::Literal(ty::LiteralTy::Char) => v::Literal::Char(scalar.to_char().unwrap()),
+    Some(match ty {
+        ty::Ty::Literal(ty::LiteralTy::Bool) => v::Literal::Bool(scalar.to_bool().ok()?),
+        ty::Ty::Literal(ty::LiteralTy::Char) => v::Literal::Char(scalar.to_char().ok()?),
         ty::Ty::Literal(ty::LiteralTy::Integer(i)) => v::Literal::Scalar(match i {
-            ty::IntegerTy::Isize => {
-                // This is a bit annoying: there is no
-                // `to_isize`. For now, we make the hypothesis
-                // that isize is an int64
-                assert!(std::mem::size_of::<isize>() == 8);
-                v::ScalarValue::Isize(scalar.to_i64().unwrap())
-            }
-            ty::IntegerTy::Usize => {
-                // Same as above for usize.
-                assert!(std::mem::size_of::<usize>() == 8);
-                v::ScalarValue::Usize(scalar.to_u64().unwrap())
-            }
-            ty::IntegerTy::I8 => v::ScalarValue::I8(scalar.to_i8().unwrap()),
-            ty::IntegerTy::U8 => v::ScalarValue::U8(scalar.to_u8().unwrap()),
-            ty::IntegerTy::I16 => v::ScalarValue::I16(scalar.to_i16().unwrap()),
-            ty::IntegerTy::U16 => v::ScalarValue::U16(scalar.to_u16().unwrap()),
-            ty::IntegerTy::I32 => v::ScalarValue::I32(scalar.to_i32().unwrap()),
-            ty::IntegerTy::U32 => v::ScalarValue::U32(scalar.to_u32().unwrap()),
-            ty::IntegerTy::I64 => v::ScalarValue::I64(scalar.to_i64().unwrap()),
-            ty::IntegerTy::U64 => v::ScalarValue::U64(scalar.to_u64().unwrap()),
-            ty::IntegerTy::I128 => v::ScalarValue::I128(scalar.to_i128().unwrap()),
-            ty::IntegerTy::U128 => v::ScalarValue::U128(scalar.to_u128().unwrap()),
+            // There is no `to_isize` which doesn't require the data layout:
+            // we dispatch on the pointer size ourselves.
+            ty::IntegerTy::Isize => match ptr_size {
+                v::PointerSize::P32 => {
+                    v::ScalarValue::Isize(ptr_size, scalar.to_i32().ok()? as i64)
+                }
+                v::PointerSize::P64 => v::ScalarValue::Isize(ptr_size, scalar.to_i64().ok()?),
+            },
+            ty::IntegerTy::Usize => match ptr_size {
+                v::PointerSize::P32 => {
+                    v::ScalarValue::Usize(ptr_size, scalar.to_u32().ok()? as u64)
+                }
+                v::PointerSize::P64 => v::ScalarValue::Usize(ptr_size, scalar.to_u64().ok()?),
+            },
+            ty::IntegerTy::I8 => v::ScalarValue::I8(scalar.to_i8().ok()?),
+            ty::IntegerTy::U8 => v::ScalarValue::U8(scalar.to_u8().ok()?),
+            ty::IntegerTy::I16 => v::ScalarValue::I16(scalar.to_i16().ok()?),
+            ty::IntegerTy::U16 => v::ScalarValue::U16(scalar.to_u16().ok()?),
+            ty::IntegerTy::I32 => v::ScalarValue::I32(scalar.to_i32().ok()?),
+            ty::IntegerTy::U32 => v::ScalarValue::U32(scalar.to_u32().ok()?),
+            ty::IntegerTy::I64 => v::ScalarValue::I64(scalar.to_i64().ok()?),
+            ty::IntegerTy::U64 => v::ScalarValue::U64(scalar.to_u64().ok()?),
+            ty::IntegerTy::I128 => v::ScalarValue::I128(scalar.to_i128().ok()?),
+            ty::IntegerTy::U128 => v::ScalarValue::U128(scalar.to_u128().ok()?),
         }),
-        _ => {
-            // The remaining types should not be used for constants,
-            // or should have been filtered by the caller.
-            error!("unexpected type: {:?}", ty);
-            unreachable!();
+        // The remaining types should not be used for constants, or should
+        // have been filtered by the caller.
+        _ => return None,
+    })
+}
+
+/// If a type is an array of bytes (`[u8; N]`) of known length, return this
+/// length.
+fn byte_array_len(ty: &ty::ETy) -> Option<usize> {
+    match ty {
+        ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::Array), _, tys, cgs) => {
+            match (&tys[0], &cgs[0]) {
+                (
+                    ty::Ty::Literal(ty::LiteralTy::Integer(ty::IntegerTy::U8)),
+                    ty::ConstGeneric::Value(v::Literal::Scalar(v::ScalarValue::Usize(_, len))),
+                ) => Some(*len as usize),
+                _ => None,
+            }
         }
+        _ => None,
     }
 }
 
+/// Return `true` if the type is an array or a tuple: we can read the constant
+/// values of those types from their allocation, see
+/// [BodyTransCtx::translate_constant_reference_value].
+fn is_constant_aggregate(ty: &ty::ETy) -> bool {
+    matches!(
+        ty,
+        ty::Ty::Adt(
+            ty::TypeId::Tuple | ty::TypeId::Assumed(ty::AssumedTy::Array),
+            ..
+        )
+    )
+}
+
+/// Read an array of bytes from an allocation (this is used for the byte
+/// strings, like `b"hello"`).
+fn translate_byte_array(
+    alloc: &mir::interpret::Allocation,
+    offset: usize,
+    len: usize,
+) -> e::OperandConstantValue {
+    let bytes = alloc.inspect_with_uninit_and_ptr_outside_interpreter(offset..offset + len);
+    let bytes = bytes
+        .iter()
+        .map(|b| e::OperandConstantValue::Literal(v::Literal::Scalar(v::ScalarValue::U8(*b))))
+        .collect();
+    e::OperandConstantValue::Array(bytes)
+}
+
 impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
+    /// Report an unsupported constant
+    fn unsupported_constant<T>(&mut self, kind: String) -> TransResult<T> {
+        Err(Box::new(TranslationError::UnsupportedConstant {
+            span: self.def_meta(),
+            kind,
+        }))
+    }
+
     /// Translate the type of a [mir::interpret::ConstValue::Scalar] value :
-    /// Either a bool, a char, an integer, an enumeration ADT, an empty tuple or a static reference.
-    fn translate_constant_scalar_type(&mut self, ty: &TyKind) -> ty::ETy {
-        match ty {
+    /// Either a bool, a char, an integer, an enumeration ADT, an empty tuple, a static reference
+    /// or a raw pointer.
+    fn translate_constant_scalar_type(&mut self, ty: &TyKind<'tcx>) -> TransResult<ty::ETy> {
+        Ok(match ty {
             TyKind::Bool => ty::Ty::Literal(ty::LiteralTy::Bool),
             TyKind::Char => ty::Ty::Literal(ty::LiteralTy::Char),
             TyKind::Int(int_ty) => match int_ty {
@@ -103,23 +161,34 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             TyKind::Ref(region, ref_ty, mir::Mutability::Not) => match region.kind() {
                 mir_ty::RegionKind::ReErased => ty::Ty::Ref(
                     ty::ErasedRegion::Erased,
-                    Box::new(self.translate_constant_scalar_type(ref_ty.kind())),
+                    Box::new(self.translate_constant_scalar_type(ref_ty.kind())?),
                     ty::RefKind::Shared,
                 ),
-                _ => unreachable!(),
+                _ => {
+                    return self.unsupported_constant(format!("reference with region: {region:?}"))
+                }
             },
-            TyKind::Float(_) => {
-                // We don't support floating point numbers:
-                // this should have been detected and eliminated before.
-                unreachable!();
-            }
-            _ => {
-                // The remaining types should not be used for constants, or
-                // should have been filtered by the caller.
-                error!("unexpected type: {:?}", ty);
-                unreachable!();
+            // Raw pointers (the null pointer, for instance)
+            TyKind::RawPtr(ty_and_mut) => {
+                let ty = self.translate_ety(&ty_and_mut.ty)?;
+                let kind = match ty_and_mut.mutbl {
+                    mir::Mutability::Not => ty::RefKind::Shared,
+                    mir::Mutability::Mut => ty::RefKind::Mut,
+                };
+                ty::Ty::RawPtr(Box::new(ty), kind)
             }
-        }
+            // The string literals (behind a shared reference)
+            TyKind::Str => ty::Ty::Adt(
+                ty::TypeId::Assumed(ty::AssumedTy::Str),
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+            ),
+            // We don't support floating point numbers. The remaining types
+            // should not be used for constants, or should have been filtered
+            // by the caller.
+            _ => return self.unsupported_constant(format!("scalar of type {ty:?}")),
+        })
     }
 
     /// Translate a parameter substitution.
@@ -128,7 +197,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     fn translate_subst_with_erased_regions(
         &mut self,
         substs: &rustc_middle::ty::List<rustc_middle::ty::Ty<'tcx>>,
-    ) -> Result<Vec<ty::ETy>> {
+    ) -> TransResult<Vec<ty::ETy>> {
         let mut t_args_tys = Vec::new();
 
         for param in substs.iter() {
@@ -139,29 +208,28 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
 
     /// Translate the type of a [mir::interpret::ConstValue::ByRef] value.
     /// Currently, it should be a tuple.
-    fn translate_constant_reference_type(&mut self, ty: &TyKind<'tcx>) -> ty::ETy {
+    fn translate_constant_reference_type(&mut self, ty: &TyKind<'tcx>) -> TransResult<ty::ETy> {
         // Match on the type to destructure
         match ty {
             TyKind::Tuple(substs) => {
                 // Here, the substitution only contains types (no regions)
-                let type_params = self.translate_subst_with_erased_regions(substs).unwrap();
+                let type_params = self.translate_subst_with_erased_regions(substs)?;
                 trace!("{:?}", type_params);
                 let field_tys = type_params.into_iter().collect();
-                ty::Ty::Adt(ty::TypeId::Tuple, Vec::new(), field_tys, Vec::new())
-            }
-            TyKind::Adt(_, _) => {
-                // Following tests, it seems rustc doesn't introduce constants
-                // references when initializing ADTs, only when initializing tuples.
-                // Anyway, our `OperandConstantValue` handles all cases so updating
-                // the code to handle ADTs in a general manner wouldn't be a
-                // problem.
-                unreachable!("unexpected ADT type: {:?}", ty);
-            }
-            _ => {
-                // The remaining types should not be used for constants, or
-                // should have been filtered by the caller.
-                unreachable!("unexpected type: {:?}", ty);
+                Ok(ty::Ty::Adt(
+                    ty::TypeId::Tuple,
+                    Vec::new(),
+                    field_tys,
+                    Vec::new(),
+                ))
             }
+            // Following tests, it seems rustc doesn't introduce constants
+            // references when initializing ADTs, only when initializing tuples.
+            // Anyway, our `OperandConstantValue` handles all cases so updating
+            // the code to handle ADTs in a general manner wouldn't be a
+            // problem. The remaining types should not be used for constants,
+            // or should have been filtered by the caller.
+            _ => self.unsupported_constant(format!("reference of type {ty:?}")),
         }
     }
 
@@ -169,8 +237,9 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     fn translate_constant_scalar_value(
         &mut self,
         llbc_ty: &ty::ETy,
+        mir_ty: &Ty<'tcx>,
         scalar: &mir::interpret::Scalar,
-    ) -> e::OperandConstantValue {
+    ) -> TransResult<e::OperandConstantValue> {
         trace!("{:?}", scalar);
 
         // The documentation explicitly says not to match on a scalar.
@@ -182,15 +251,30 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             ty::Ty::Literal(ty::LiteralTy::Bool)
             | ty::Ty::Literal(ty::LiteralTy::Char)
             | ty::Ty::Literal(ty::LiteralTy::Integer(_)) => {
-                let v = translate_constant_integer_like_value(llbc_ty, scalar);
-                e::OperandConstantValue::Literal(v)
+                match translate_constant_integer_like_value(
+                    self.t_ctx.pointer_size,
+                    llbc_ty,
+                    scalar,
+                ) {
+                    Option::Some(v) => Ok(e::OperandConstantValue::Literal(v)),
+                    Option::None => {
+                        self.unsupported_constant(format!("{scalar:?} of type {llbc_ty:?}"))
+                    }
+                }
             }
             ty::Ty::Adt(ty::TypeId::Adt(id), region_tys, field_tys, cgs) => {
                 assert!(region_tys.is_empty());
                 assert!(field_tys.is_empty());
                 assert!(cgs.is_empty());
 
-                let def = self.t_ctx.type_defs.get(*id).unwrap();
+                let def = match self.t_ctx.type_defs.get(*id) {
+                    Option::Some(def) => def,
+                    Option::None => {
+                        return self.unsupported_constant(format!(
+                            "{scalar:?} of an untranslated type: {mir_ty:?}"
+                        ))
+                    }
+                };
 
                 // Check that there is only one variant, with no fields
                 // and no parameters. Construct the value at the same time.
@@ -200,49 +284,105 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                         assert!(variants.len() == 1);
                         Option::Some(ty::VariantId::ZERO)
                     }
-                    ty::TypeDeclKind::Struct(_) => Option::None,
-                    ty::TypeDeclKind::Opaque => {
-                        unreachable!("Can't analyze a constant value built from an opaque type")
+                    ty::TypeDeclKind::Struct(_) | ty::TypeDeclKind::Union(..) => Option::None,
+                    ty::TypeDeclKind::ImplTrait(_)
+                    | ty::TypeDeclKind::Alias(_)
+                    | ty::TypeDeclKind::Opaque => {
+                        // We can't analyze a constant value built from an
+                        // opaque type
+                        return self.unsupported_constant(format!(
+                            "{scalar:?} of an opaque type: {mir_ty:?}"
+                        ));
                     }
                 };
-                e::OperandConstantValue::Adt(variant_id, Vec::new())
+                Ok(e::OperandConstantValue::Adt(variant_id, Vec::new()))
             }
             ty::Ty::Adt(ty::TypeId::Tuple, region_tys, field_tys, cgs) => {
                 assert!(region_tys.is_empty());
                 assert!(field_tys.is_empty());
                 assert!(cgs.is_empty());
-                e::OperandConstantValue::Adt(Option::None, Vec::new())
+                Ok(e::OperandConstantValue::Adt(Option::None, Vec::new()))
             }
-            ty::Ty::Ref(ty::ErasedRegion::Erased, _, ty::RefKind::Shared) => match scalar {
+            ty::Ty::Ref(ty::ErasedRegion::Erased, pointee, ty::RefKind::Shared) => match scalar {
                 mir::interpret::Scalar::Ptr(p, _) => {
-                    match self.t_ctx.tcx.global_alloc(p.provenance) {
+                    let (alloc_id, offset) = p.into_parts();
+                    match self.t_ctx.tcx.global_alloc(alloc_id) {
                         mir::interpret::GlobalAlloc::Static(s) => {
                             let id = self.translate_global_decl_id(s);
-                            e::OperandConstantValue::StaticId(id)
+                            Ok(e::OperandConstantValue::StaticId(id))
                         }
-                        _ => unreachable!(
-                            "Expected static pointer, got {:?}",
-                            self.t_ctx.tcx.global_alloc(p.provenance)
-                        ),
+                        // The byte strings (`b"hello"`, of type `&[u8; 5]`):
+                        // as for the string literals, the value of the
+                        // reference is the (constant) array
+                        mir::interpret::GlobalAlloc::Memory(alloc)
+                            if byte_array_len(pointee).is_some() =>
+                        {
+                            let len = byte_array_len(pointee).unwrap();
+                            Ok(translate_byte_array(
+                                alloc.inner(),
+                                offset.bytes_usize(),
+                                len,
+                            ))
+                        }
+                        // The references to the other constant arrays and
+                        // tuples (`&[1, 2, 3]`, for instance): we read the
+                        // value from the allocation. As for the byte
+                        // strings, the value of the reference is the
+                        // (constant) aggregate.
+                        mir::interpret::GlobalAlloc::Memory(alloc)
+                            if is_constant_aggregate(pointee) =>
+                        {
+                            let mir_pointee = match mir_ty.kind() {
+                                TyKind::Ref(_, mir_pointee, _) => mir_pointee,
+                                _ => {
+                                    return self.unsupported_constant(format!(
+                                        "{scalar:?} of type {mir_ty:?}"
+                                    ))
+                                }
+                            };
+                            let value = mir::interpret::ConstValue::ByRef { alloc, offset };
+                            self.translate_const_value(pointee, mir_pointee, &value)
+                        }
+                        // The remaining allocations (vtables, function
+                        // pointers, or memory we can't represent
+                        // symbolically): we report them rather than panic
+                        alloc => self.unsupported_constant(format!(
+                            "pointer to {alloc:?}, of type {mir_ty:?}"
+                        )),
                     }
                 }
-                _ => unreachable!("Expected static pointer, got {:?}", scalar),
+                _ => self.unsupported_constant(format!("{scalar:?} of type {mir_ty:?}")),
             },
-            _ => {
-                // The remaining types should not be used for constants
-                unreachable!("unexpected type: {:?}, for scalar: {:?}", llbc_ty, scalar);
-            }
+            ty::Ty::RawPtr(_, _) => match scalar {
+                // The raw pointers built from integers (for instance, the
+                // null pointer): we retrieve the address.
+                mir::interpret::Scalar::Int(_) => {
+                    let addr = match self.t_ctx.pointer_size {
+                        v::PointerSize::P32 => scalar.to_u32().map(|addr| addr as u64),
+                        v::PointerSize::P64 => scalar.to_u64(),
+                    };
+                    match addr {
+                        Ok(addr) => Ok(e::OperandConstantValue::Literal(v::Literal::RawPtr(addr))),
+                        Err(_) => self.unsupported_constant(format!("raw pointer: {scalar:?}")),
+                    }
+                }
+                mir::interpret::Scalar::Ptr(_, _) => {
+                    self.unsupported_constant(format!("raw pointer to an allocation: {scalar:?}"))
+                }
+            },
+            // The remaining types should not be used for constants
+            _ => self.unsupported_constant(format!("{scalar:?} of type {llbc_ty:?}")),
         }
     }
 
     /// Translate a constant typed by [translate_constant_reference_type].
-    /// This should always be a tuple.
+    /// This should always be a tuple or an array.
     fn translate_constant_reference_value(
         &mut self,
         llbc_ty: &ty::ETy,
         mir_ty: &Ty<'tcx>, // TODO: remove?
         value: &mir::interpret::ConstValue<'tcx>,
-    ) -> e::OperandConstantValue {
+    ) -> TransResult<e::OperandConstantValue> {
         trace!();
 
         let tcx = self.t_ctx.tcx;
@@ -258,9 +398,10 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             value: cvalue,
         };
 
-        let dc = tcx
-            .try_destructure_mir_constant(param_env_and_const)
-            .unwrap();
+        let dc = match tcx.try_destructure_mir_constant(param_env_and_const) {
+            Option::Some(dc) => dc,
+            Option::None => return self.unsupported_constant(format!("{value:?}: {mir_ty:?}")),
+        };
         trace!("{:?}", dc);
 
         // Iterate over the fields
@@ -273,20 +414,25 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             .fields
             .iter()
             .map(|f| self.translate_constant_kind(f))
-            .collect();
+            .collect::<TransResult<_>>()?;
 
-        // Sanity check
+        // Sanity check, and build the value at the same time
+        let fields_values: Vec<e::OperandConstantValue> =
+            fields.iter().map(|f| f.1.clone()).collect();
         match llbc_ty {
             ty::Ty::Adt(ty::TypeId::Tuple, regions, fields_tys, cgs) => {
                 assert!(regions.is_empty());
                 assert!(zip(&fields, fields_tys).all(|(f, ty)| &f.0 == ty));
                 assert!(cgs.is_empty());
+                Ok(e::OperandConstantValue::Adt(Option::None, fields_values))
             }
-            _ => unreachable!("Expected a tuple, got {:?}", mir_ty),
-        };
-
-        let fields: Vec<e::OperandConstantValue> = fields.into_iter().map(|f| f.1).collect();
-        e::OperandConstantValue::Adt(Option::None, fields)
+            ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::Array), regions, tys, _) => {
+                assert!(regions.is_empty());
+                assert!(fields.iter().all(|f| f.0 == tys[0]));
+                Ok(e::OperandConstantValue::Array(fields_values))
+            }
+            _ => self.unsupported_constant(format!("{value:?}: {mir_ty:?}")),
+        }
     }
 
     /// Translate a [mir::interpret::ConstValue]
@@ -295,20 +441,60 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         llbc_ty: &ty::ETy,
         mir_ty: &Ty<'tcx>, // TODO: remove?
         val: &mir::interpret::ConstValue<'tcx>,
-    ) -> e::OperandConstantValue {
+    ) -> TransResult<e::OperandConstantValue> {
         trace!("{:?}", val);
         match val {
             mir::interpret::ConstValue::Scalar(scalar) => {
-                self.translate_constant_scalar_value(llbc_ty, scalar)
+                self.translate_constant_scalar_value(llbc_ty, mir_ty, scalar)
+            }
+            // The arrays of bytes: we directly read the content of the
+            // allocation, rather than destructuring the constant byte by
+            // byte
+            mir::interpret::ConstValue::ByRef { alloc, offset }
+                if byte_array_len(llbc_ty).is_some() =>
+            {
+                let len = byte_array_len(llbc_ty).unwrap();
+                Ok(translate_byte_array(
+                    alloc.inner(),
+                    offset.bytes_usize(),
+                    len,
+                ))
             }
             mir::interpret::ConstValue::ByRef { .. } => {
                 self.translate_constant_reference_value(llbc_ty, mir_ty, val)
             }
-            mir::interpret::ConstValue::Slice { .. } => unimplemented!(),
+            mir::interpret::ConstValue::Slice { data, start, end } => match llbc_ty {
+                // The string literals: we retrieve the bytes of the string
+                // from the allocation
+                ty::Ty::Ref(_, pointee, ty::RefKind::Shared)
+                    if matches!(
+                        **pointee,
+                        ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::Str), ..)
+                    ) =>
+                {
+                    let bytes = data
+                        .inner()
+                        .inspect_with_uninit_and_ptr_outside_interpreter(*start..*end);
+                    match std::str::from_utf8(bytes) {
+                        Ok(s) => Ok(e::OperandConstantValue::Literal(v::Literal::Str(
+                            s.to_string(),
+                        ))),
+                        Err(_) => self.unsupported_constant(format!("{val:?}")),
+                    }
+                }
+                _ => self.unsupported_constant(format!("{val:?}")),
+            },
             mir::interpret::ConstValue::ZeroSized { .. } => {
-                // Should be unit
-                assert!(llbc_ty.is_unit());
-                e::OperandConstantValue::Adt(None, Vec::new())
+                // Should be unit, or `PhantomData` (which is a structure
+                // without fields)
+                assert!(
+                    llbc_ty.is_unit()
+                        || matches!(
+                            llbc_ty,
+                            ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::PhantomData), ..)
+                        )
+                );
+                Ok(e::OperandConstantValue::Adt(None, Vec::new()))
             }
         }
     }
@@ -319,128 +505,325 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         rid: DefId,
         mir_ty: &mir_ty::Ty<'tcx>,
-    ) -> (ty::ETy, e::OperandConstantValue) {
+    ) -> TransResult<(ty::ETy, e::OperandConstantValue)> {
         // Sanity check
-        assert!(extract_constants_at_top_level(self.t_ctx.mir_level));
+        assert!(extract_constants_at_top_level(self.mir_level));
 
         // Lookup the constant identifier and refer to it.
         let id = self.translate_global_decl_id(rid);
-        let ty = self.translate_ety(mir_ty).unwrap();
-        (ty, e::OperandConstantValue::ConstantId(id))
+        let ty = self.translate_ety(mir_ty)?;
+        Ok((ty, e::OperandConstantValue::ConstantId(id)))
+    }
+
+    /// If a constant is an associated constant of a trait (`<Foo as HasId>::ID`),
+    /// retrieve the constant given by the trait impl (`Foo::ID`).
+    fn resolve_assoc_const(
+        &mut self,
+        ucv: &rustc_middle::mir::UnevaluatedConst<'tcx>,
+    ) -> TransResult<DefId> {
+        let tcx = self.t_ctx.tcx;
+        if tcx.trait_of_item(ucv.def).is_none() {
+            return Ok(ucv.def);
+        }
+        let param_env = tcx.param_env(self.def_id);
+        match mir_ty::Instance::resolve(tcx, param_env, ucv.def, ucv.substs) {
+            Ok(Some(instance)) if instance.def_id() != ucv.def => Ok(instance.def_id()),
+            // The constant is not given by an impl: either we don't know the
+            // `Self` type, or the impl uses the default value of the trait
+            _ => Err(Box::new(TranslationError::UnsupportedConstant {
+                span: self.def_meta(),
+                kind: format!("unresolved associated constant: {:?}", ucv.def),
+            })),
+        }
+    }
+
+    /// Try to evaluate a constant whose value is fully known (see
+    /// [CrateInfo::inline_const_eval]). We don't evaluate the promoted
+    /// constants, which are references to anonymous constants.
+    fn try_eval_const_unevaluated(
+        &mut self,
+        mir_ty: &mir_ty::Ty<'tcx>,
+        ucv: &rustc_middle::mir::UnevaluatedConst<'tcx>,
+    ) -> Option<(ty::ETy, e::OperandConstantValue)> {
+        if ucv.promoted.is_some() || ucv.substs.has_param() {
+            return None;
+        }
+        let tcx = self.t_ctx.tcx;
+        let param_env = tcx.param_env(self.def_id);
+        let cv = tcx.const_eval_resolve(param_env, *ucv, None).ok()?;
+        let llbc_ty = self.translate_ety(mir_ty).ok()?;
+        // We may not support the evaluated value (if it is given by a
+        // pointer to its memory, for instance): we then use the reference
+        let v = self.translate_const_value(&llbc_ty, mir_ty, &cv).ok()?;
+        Some((llbc_ty, v))
     }
 
     fn translate_const_kind_unevaluated(
         &mut self,
         mir_ty: &mir_ty::Ty<'tcx>,
         ucv: &rustc_middle::mir::UnevaluatedConst<'tcx>,
-    ) -> (ty::ETy, e::OperandConstantValue) {
+    ) -> TransResult<(ty::ETy, e::OperandConstantValue)> {
+        if self.t_ctx.crate_info.inline_const_eval {
+            if let Some(evaluated) = self.try_eval_const_unevaluated(mir_ty, ucv) {
+                return Ok(evaluated);
+            }
+        }
+
         // Two cases:
         // - if we extract the constants at top level, we lookup the constant
         //   identifier and refer to it
         // - otherwise, we evaluate the constant and insert it in place
-        if extract_constants_at_top_level(self.t_ctx.mir_level) {
-            self.translate_constant_id_as_top_level(ucv.def, mir_ty)
+        if extract_constants_at_top_level(self.mir_level) {
+            // The promoted constants (`&[1, 2, 3]` in a function body, for
+            // instance) are not items: they are identified by the body in
+            // which they appear and their index in this body.
+            if let Option::Some(promoted) = ucv.promoted {
+                let mir_level = self.mir_level;
+                let id = self
+                    .t_ctx
+                    .translate_promoted_decl_id(ucv.def, promoted, mir_level)?;
+                let ty = self.translate_ety(mir_ty)?;
+                return Ok((ty, e::OperandConstantValue::ConstantId(id)));
+            }
+            let rid = self.resolve_assoc_const(ucv)?;
+            self.translate_constant_id_as_top_level(rid, mir_ty)
         } else {
             // Evaluate the constant.
             // We need a param_env: we use the function def id as a dummy id...
             let tcx = self.t_ctx.tcx;
             let param_env = tcx.param_env(self.def_id);
-            let cv = tcx.const_eval_resolve(param_env, *ucv, None).unwrap();
-            let llbc_ty = self.translate_ety(mir_ty).unwrap();
-            let v = self.translate_const_value(&llbc_ty, mir_ty, &cv);
-            (llbc_ty, v)
+            let cv = match tcx.const_eval_resolve(param_env, *ucv, None) {
+                Ok(cv) => cv,
+                Err(_) => return self.unsupported_constant(format!("{ucv:?}: {mir_ty:?}")),
+            };
+            let llbc_ty = self.translate_ety(mir_ty)?;
+            let v = self.translate_const_value(&llbc_ty, mir_ty, &cv)?;
+            Ok((llbc_ty, v))
+        }
+    }
+
+    /// Translate a [mir_ty::ValTree].
+    ///
+    /// The leaves are the scalar values. The branches are the aggregated values
+    /// (structures, tuples, arrays...), whose children are the values of the
+    /// fields. For the enumerations, the first child is a leaf giving the
+    /// variant index.
+    fn translate_valtree(
+        &mut self,
+        mir_ty: &Ty<'tcx>,
+        valtree: mir_ty::ValTree<'tcx>,
+    ) -> TransResult<e::OperandConstantValue> {
+        trace!("{:?}: {:?}", mir_ty, valtree);
+        let tcx = self.t_ctx.tcx;
+        match (mir_ty.kind(), valtree) {
+            (TyKind::Int(_) | TyKind::Uint(_) | TyKind::Bool, mir_ty::ValTree::Leaf(v)) => {
+                let ty = self.translate_ety(mir_ty)?;
+                let ptr_size = self.t_ctx.pointer_size;
+                let lit = match ty.as_literal() {
+                    ty::LiteralTy::Integer(int_ty) => {
+                        let sv = if int_ty.is_signed() {
+                            v.try_to_int(v.size())
+                                .ok()
+                                .and_then(|v| v::ScalarValue::from_int(ptr_size, *int_ty, v).ok())
+                        } else {
+                            v.try_to_uint(v.size())
+                                .ok()
+                                .and_then(|v| v::ScalarValue::from_uint(ptr_size, *int_ty, v).ok())
+                        };
+                        sv.map(v::Literal::Scalar)
+                    }
+                    ty::LiteralTy::Bool => v.try_to_bool().ok().map(v::Literal::Bool),
+                    ty::LiteralTy::Char => None,
+                };
+                match lit {
+                    Option::Some(lit) => Ok(e::OperandConstantValue::Literal(lit)),
+                    Option::None => self.unsupported_constant(format!("{valtree:?}: {mir_ty:?}")),
+                }
+            }
+            (TyKind::Tuple(tys), mir_ty::ValTree::Branch(children)) => {
+                assert!(tys.len() == children.len());
+                let fields = zip(tys.iter(), children.iter())
+                    .map(|(ty, c)| self.translate_valtree(&ty, *c))
+                    .collect::<TransResult<_>>()?;
+                Ok(e::OperandConstantValue::Adt(Option::None, fields))
+            }
+            (TyKind::Array(ty, _), mir_ty::ValTree::Branch(children)) => {
+                let elems = children
+                    .iter()
+                    .map(|c| self.translate_valtree(ty, *c))
+                    .collect::<TransResult<_>>()?;
+                Ok(e::OperandConstantValue::Array(elems))
+            }
+            (TyKind::Adt(adt_def, substs), mir_ty::ValTree::Branch(children)) => {
+                // Retrieve the variant: for the enumerations, it is given
+                // by the first child.
+                let (variant_id, variant, children) = if adt_def.is_enum() {
+                    let (index, children) = match children.split_first() {
+                        Option::Some((mir_ty::ValTree::Leaf(discr), children)) => {
+                            match discr.try_to_uint(discr.size()) {
+                                Ok(index) => (index as usize, children),
+                                Err(_) => {
+                                    return self
+                                        .unsupported_constant(format!("{valtree:?}: {mir_ty:?}"))
+                                }
+                            }
+                        }
+                        _ => return self.unsupported_constant(format!("{valtree:?}: {mir_ty:?}")),
+                    };
+                    let variant_id = ty::VariantId::Id::new(index);
+                    let variant = adt_def.variant(rustc_target::abi::VariantIdx::from_usize(index));
+                    (Option::Some(variant_id), variant, children)
+                } else if adt_def.is_struct() {
+                    (Option::None, adt_def.non_enum_variant(), children)
+                } else {
+                    return self.unsupported_constant(format!("{valtree:?}: {mir_ty:?}"));
+                };
+
+                assert!(variant.fields.len() == children.len());
+                let fields = zip(variant.fields.iter(), children.iter())
+                    .map(|(field, c)| self.translate_valtree(&field.ty(tcx, substs), *c))
+                    .collect::<TransResult<_>>()?;
+                Ok(e::OperandConstantValue::Adt(variant_id, fields))
+            }
+            _ => self.unsupported_constant(format!("{valtree:?}: {mir_ty:?}")),
         }
     }
 
     pub(crate) fn translate_const_kind(
         &mut self,
         constant: rustc_middle::ty::Const<'tcx>,
-    ) -> (ty::ETy, e::OperandConstantValue) {
+    ) -> TransResult<(ty::ETy, e::OperandConstantValue)> {
         match constant.kind() {
             ConstKind::Value(v) => {
-                // The value is a [ValTree].
-                // For now, we only imlement support for a limited subset of the cases -
-                // there are many cases for which I don't know in which situations they
-                // happen.
-
-                // We only support integers and scalars
-                let ty = self.translate_ety(&constant.ty()).unwrap();
-                let v = match v {
-                    mir_ty::ValTree::Leaf(v) => match ty.as_literal() {
-                        ty::LiteralTy::Integer(int_ty) => {
-                            if int_ty.is_signed() {
-                                let v = v.try_to_int(v.size()).unwrap();
-                                v::Literal::Scalar(v::ScalarValue::from_int(*int_ty, v).unwrap())
-                            } else {
-                                let v = v.try_to_uint(v.size()).unwrap();
-                                v::Literal::Scalar(v::ScalarValue::from_uint(*int_ty, v).unwrap())
-                            }
-                        }
-                        ty::LiteralTy::Bool => {
-                            let v = v.try_to_bool().unwrap();
-                            v::Literal::Bool(v)
-                        }
-                        ty::LiteralTy::Char => unimplemented!(),
-                    },
-                    mir_ty::ValTree::Branch(_) => {
-                        // In practice I don't know when this is used
-                        unimplemented!()
-                    }
-                };
-                (ty, e::OperandConstantValue::Literal(v))
+                // The value is a [ValTree]
+                let ty = self.translate_ety(&constant.ty())?;
+                let v = self.translate_valtree(&constant.ty(), v)?;
+                Ok((ty, v))
             }
             ConstKind::Expr(_) => {
-                unimplemented!();
+                // Const expressions should only appear in the types, and
+                // are handled in [translate_const_kind_as_const_generic]
+                self.unsupported_constant(format!("{constant:?}"))
             }
             ConstKind::Unevaluated(ucv) => {
+                // If the user asked for it, try to evaluate the constant
+                if self.t_ctx.crate_info.inline_const_eval && !constant.has_param() {
+                    let param_env = self.t_ctx.tcx.param_env(self.def_id);
+                    let evaluated = constant.eval(self.t_ctx.tcx, param_env);
+                    if let ConstKind::Value(_) = evaluated.kind() {
+                        return self.translate_const_kind(evaluated);
+                    }
+                }
                 // Two cases:
                 // - if we extract the constants at top level, we lookup the constant
                 //   identifier and refer to it
                 // - otherwise, we evaluate the constant and insert it in place
-                if extract_constants_at_top_level(self.t_ctx.mir_level) {
+                if extract_constants_at_top_level(self.mir_level) {
                     self.translate_constant_id_as_top_level(ucv.def, &constant.ty())
                 } else {
                     // TODO: we can't call [translate_const_kind_unevaluated]:
                     // the types don't match.
                     // We could use [TyCtxt.const_eval_resolve_for_typeck]
                     // to get a [ValTree]
-                    unimplemented!();
+                    self.unsupported_constant(format!("{constant:?}"))
                 }
             }
             ConstKind::Param(cp) => {
-                let ty = self.translate_ety(&constant.ty()).unwrap();
-                let cg_id = self.const_generic_vars_map.get(&cp.index).unwrap();
-                (ty, e::OperandConstantValue::Var(*cg_id))
+                let ty = self.translate_ety(&constant.ty())?;
+                match self.const_generic_vars_map.get(&cp.index) {
+                    Option::Some(cg_id) => Ok((ty, e::OperandConstantValue::Var(*cg_id))),
+                    Option::None => self.unsupported_constant(format!("{constant:?}")),
+                }
             }
             ConstKind::Infer(_)
             | ConstKind::Bound(_, _)
             | ConstKind::Placeholder(_)
-            | ConstKind::Error(_) => {
-                unreachable!("Unexpected: {:?}", constant);
-            }
+            | ConstKind::Error(_) => self.unsupported_constant(format!("{constant:?}")),
         }
     }
 
     pub(crate) fn translate_const_kind_as_const_generic(
         &mut self,
         constant: rustc_middle::ty::Const<'tcx>,
-    ) -> ty::ConstGeneric {
-        let (ty, c) = self.translate_const_kind(constant);
-        assert!(ty.is_literal());
-        match c {
+    ) -> TransResult<ty::ConstGeneric> {
+        // The const expressions may be hidden behind anonymous constants (this
+        // is typically the case in the function bodies): we expand them (the
+        // anonymous constants depend on the generics, so we can't translate
+        // them to globals)
+        let constant = self.t_ctx.tcx.expand_abstract_consts(constant);
+
+        // Const expressions are not values: we treat them separately
+        if let ConstKind::Expr(expr) = constant.kind() {
+            let expr = self.translate_const_expr(&expr)?;
+            return Ok(ty::ConstGeneric::Expr(Box::new(expr)));
+        }
+
+        let (ty, c) = self.translate_const_kind(constant)?;
+        if !ty.is_literal() {
+            return self.unsupported_constant(format!("const generic of type {ty:?}"));
+        }
+        Ok(match c {
             e::OperandConstantValue::Literal(v) => ty::ConstGeneric::Value(v),
-            e::OperandConstantValue::Adt(..) => unreachable!(),
             e::OperandConstantValue::ConstantId(v) => ty::ConstGeneric::Global(v),
-            e::OperandConstantValue::StaticId(_) => unreachable!(),
             e::OperandConstantValue::Var(v) => ty::ConstGeneric::Var(v),
-        }
+            e::OperandConstantValue::Adt(..)
+            | e::OperandConstantValue::Array(..)
+            | e::OperandConstantValue::StaticId(_) => {
+                return self.unsupported_constant(format!("const generic: {c:?}"))
+            }
+        })
+    }
+
+    /// Translate a const generic expression, like `N + 1`.
+    ///
+    /// Those expressions appear in the types when the `generic_const_exprs`
+    /// feature is activated.
+    fn translate_const_expr(
+        &mut self,
+        expr: &mir_ty::Expr<'tcx>,
+    ) -> TransResult<ty::ConstGenericExpr> {
+        Ok(match expr {
+            mir_ty::Expr::Binop(op, c0, c1) => {
+                let op = translate_binaryop_kind(*op);
+                let c0 = self.translate_const_kind_as_const_generic(*c0)?;
+                let c1 = self.translate_const_kind_as_const_generic(*c1)?;
+                ty::ConstGenericExpr::BinaryOp(op, c0, c1)
+            }
+            mir_ty::Expr::UnOp(op, c) => {
+                let op = match op {
+                    mir::UnOp::Not => e::UnOp::Not,
+                    mir::UnOp::Neg => e::UnOp::Neg,
+                };
+                let c = self.translate_const_kind_as_const_generic(*c)?;
+                ty::ConstGenericExpr::UnaryOp(op, c)
+            }
+            mir_ty::Expr::FunctionCall(f, args) => {
+                // The function is a zero-sized constant whose type gives
+                // the function definition
+                let rust_id = match f.ty().kind() {
+                    TyKind::FnDef(rust_id, _) => *rust_id,
+                    _ => return self.unsupported_constant(format!("function call: {f:?}")),
+                };
+                let fid = self.translate_fun_decl_id(rust_id);
+                let args = args
+                    .iter()
+                    .map(|c| self.translate_const_kind_as_const_generic(c))
+                    .collect::<TransResult<_>>()?;
+                ty::ConstGenericExpr::FunCall(fid, args)
+            }
+            mir_ty::Expr::Cast(_, c, mir_ty) => {
+                let c = self.translate_const_kind_as_const_generic(*c)?;
+                let ty = self.translate_ety(mir_ty)?;
+                ty::ConstGenericExpr::Cast(c, *ty.as_literal())
+            }
+        })
     }
 
     /// Translate a constant which may not be yet evaluated.
     pub(crate) fn translate_constant_kind(
         &mut self,
         constant: &rustc_middle::mir::ConstantKind<'tcx>,
-    ) -> (ty::ETy, e::OperandConstantValue) {
+    ) -> TransResult<(ty::ETy, e::OperandConstantValue)> {
         trace!("{:?}", constant);
 
         match constant {
@@ -465,17 +848,17 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         ty: &Ty<'tcx>,
         val: &mir::interpret::ConstValue<'tcx>,
-    ) -> (ty::ETy, e::OperandConstantValue) {
-        let llbc_ty = self.translate_ety(ty).unwrap();
-        let im_val = self.translate_const_value(&llbc_ty, ty, val);
-        (llbc_ty, im_val)
+    ) -> TransResult<(ty::ETy, e::OperandConstantValue)> {
+        let llbc_ty = self.translate_ety(ty)?;
+        let im_val = self.translate_const_value(&llbc_ty, ty, val)?;
+        Ok((llbc_ty, im_val))
     }
 
     /// Translate a constant which may not be yet evaluated.
     pub(crate) fn translate_operand_constant(
         &mut self,
         constant: &mir::Constant<'tcx>,
-    ) -> (ty::ETy, e::OperandConstantValue) {
+    ) -> TransResult<(ty::ETy, e::OperandConstantValue)> {
         trace!("{:?}", constant);
         use std::ops::Deref;
         let constant = &constant.deref();
@@ -483,3 +866,32 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         self.translate_constant_kind(&constant.literal)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::types as ty;
+    use crate::values as v;
+    use rustc_middle::mir::interpret::Scalar;
+
+    /// On a 32-bit target, the `usize` constants are 32-bit wide.
+    #[test]
+    fn test_usize_32bits() {
+        let usize_ty = ty::Ty::Literal(ty::LiteralTy::Integer(ty::IntegerTy::Usize));
+        let scalar: Scalar = Scalar::from_u32(u32::MAX);
+        let lit =
+            super::translate_constant_integer_like_value(v::PointerSize::P32, &usize_ty, &scalar);
+        let sv = v::ScalarValue::Usize(v::PointerSize::P32, u32::MAX as u64);
+        assert!(lit == Some(v::Literal::Scalar(sv)));
+
+        // The scalar is too small for a 64-bit `usize`: we don't panic
+        let lit =
+            super::translate_constant_integer_like_value(v::PointerSize::P64, &usize_ty, &scalar);
+        assert!(lit.is_none());
+        assert!(serde_json::to_string(&sv).unwrap() == "{\"Usize\":[\"P32\",\"4294967295\"]}");
+
+        // The value doesn't fit in a 32-bit `usize`
+        let v = u32::MAX as u128 + 1;
+        assert!(v::ScalarValue::from_uint(v::PointerSize::P32, ty::IntegerTy::Usize, v).is_err());
+        assert!(v::ScalarValue::from_uint(v::PointerSize::P64, ty::IntegerTy::Usize, v).is_ok());
+    }
+}
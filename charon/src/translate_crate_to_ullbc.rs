@@ -1,32 +1,56 @@
+use crate::diagnostics::PrintingSink;
 use crate::get_mir::{extract_constants_at_top_level, MirLevel};
 use crate::meta;
-use crate::names::{hir_item_to_name, item_def_id_to_name};
+use crate::names::{hir_item_to_name, item_def_id_to_name, module_def_id_to_name};
 use crate::reorder_decls as rd;
 use crate::translate_ctx::*;
 use crate::translate_functions_to_ullbc;
 use crate::types as ty;
+use crate::types_interner::TypeInterner;
 use crate::ullbc_ast as ast;
+use crate::values::PointerSize;
 use linked_hash_set::LinkedHashSet;
-use rustc_hir::{Defaultness, ImplItem, ImplItemKind, Item, ItemKind};
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, CRATE_DEF_ID};
+use rustc_hir::{
+    Defaultness, ForeignItemKind, ImplItem, ImplItemKind, Item, ItemKind, OpaqueTyOrigin,
+    TraitItemKind,
+};
 use rustc_middle::ty::TyCtxt;
 use rustc_session::Session;
 use std::collections::HashMap;
 
 impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     fn register_local_hir_impl_item(&mut self, _top_item: bool, impl_item: &ImplItem) {
-        // TODO: make a proper error message
-        assert!(impl_item.defaultness == Defaultness::Final);
+        let def_id = impl_item.owner_id.to_def_id();
+
+        // We don't support the specialization (`default fn`, etc.)
+        if impl_item.defaultness != Defaultness::Final {
+            self.push_unsupported_item(def_id, "specializable impl item (`default`)");
+            return;
+        }
 
         // Match on the impl item kind
         match &impl_item.kind {
-            ImplItemKind::Const(_, _) => unimplemented!(),
+            ImplItemKind::Const(_, _) => {
+                // We translate the associated constants as globals. If the
+                // constant is the value of a trait constant, we link it to
+                // the trait when translating it.
+                if extract_constants_at_top_level(self.mir_level) {
+                    let id = self.translate_global_decl_id(def_id);
+                    self.root_ids.insert(rd::AnyDeclId::Global(id));
+                } else {
+                    // Avoid registering globals in optimized MIR (they will be inlined)
+                }
+            }
             ImplItemKind::Type(_) => {
-                // Note sure what to do with associated types yet
-                unimplemented!();
+                // Nothing to translate: we use the associated types of the
+                // impls when normalizing the types which refer to them (see
+                // [crate::translate_types])
             }
             ImplItemKind::Fn(_, _) => {
-                let local_id = impl_item.owner_id.to_def_id().as_local().unwrap();
-                let _ = self.translate_fun_decl_id(local_id.to_def_id());
+                let id = self.translate_fun_decl_id(def_id);
+                self.root_ids.insert(rd::AnyDeclId::Fun(id));
             }
         }
     }
@@ -70,19 +94,35 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         let def_id = item.owner_id.to_def_id();
         match &item.kind {
             ItemKind::TyAlias(_, _) => {
-                // We ignore the type aliases - it seems they are inlined
+                // The type aliases are expanded by rustc: we only translate
+                // them if we need to restore them afterwards (see
+                // [crate::restore_type_aliases])
+                if self.crate_info.preserve_type_aliases {
+                    let id = self.translate_type_decl_id(def_id);
+                    self.root_ids.insert(rd::AnyDeclId::Type(id));
+                }
             }
-            ItemKind::OpaqueTy(_) => unimplemented!(),
-            ItemKind::Union(_, _) => unimplemented!(),
-            ItemKind::Enum(_, _) | ItemKind::Struct(_, _) => {
-                let _ = self.translate_type_decl_id(def_id);
+            ItemKind::OpaqueTy(opaque_ty) => match opaque_ty.origin {
+                // We only support `impl Trait` in return position, including
+                // the `impl Future` returned by the `async` functions
+                OpaqueTyOrigin::FnReturn(_) | OpaqueTyOrigin::AsyncFn(_) => {
+                    let id = self.translate_type_decl_id(def_id);
+                    self.root_ids.insert(rd::AnyDeclId::Type(id));
+                }
+                OpaqueTyOrigin::TyAlias { .. } => self.push_unsupported_item(def_id, "opaque type"),
+            },
+            ItemKind::Enum(_, _) | ItemKind::Struct(_, _) | ItemKind::Union(_, _) => {
+                let id = self.translate_type_decl_id(def_id);
+                self.root_ids.insert(rd::AnyDeclId::Type(id));
             }
             ItemKind::Fn(_, _, _) => {
-                let _ = self.translate_fun_decl_id(def_id);
+                let id = self.translate_fun_decl_id(def_id);
+                self.root_ids.insert(rd::AnyDeclId::Fun(id));
             }
             ItemKind::Const(_, _) | ItemKind::Static(_, _, _) => {
                 if extract_constants_at_top_level(self.mir_level) {
-                    let _ = self.translate_global_decl_id(def_id);
+                    let id = self.translate_global_decl_id(def_id);
+                    self.root_ids.insert(rd::AnyDeclId::Global(id));
                 } else {
                     // Avoid registering globals in optimized MIR (they will be inlined)
                 }
@@ -103,6 +143,54 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                     self.register_local_hir_impl_item(false, impl_item);
                 }
             }
+            ItemKind::Trait(_, _, _, _, items) => {
+                trace!("trait");
+                let hir_map = self.tcx.hir();
+                for trait_item_ref in *items {
+                    let trait_item = hir_map.trait_item(trait_item_ref.id);
+                    let item_def_id = trait_item.owner_id.to_def_id();
+                    match &trait_item.kind {
+                        TraitItemKind::Const(_, default) => {
+                            self.translate_assoc_const_decl(def_id, item_def_id, default.is_some())
+                        }
+                        TraitItemKind::Fn(_, _) => {
+                            let id = self.translate_fun_decl_id(item_def_id);
+                            self.root_ids.insert(rd::AnyDeclId::Fun(id));
+                        }
+                        TraitItemKind::Type(_, _) => {
+                            self.push_unsupported_item(item_def_id, "associated type")
+                        }
+                    }
+                }
+                self.translate_trait(def_id);
+            }
+            ItemKind::ForeignMod { abi, items } => {
+                trace!("extern block");
+                // We register the functions declared in the block: they are
+                // translated as opaque functions (we check the calling
+                // convention when translating them).
+                if ast::CallingConvention::from_abi_name(abi.name()).is_none() {
+                    self.push_unsupported_item(def_id, &format!("extern \"{}\" block", abi.name()));
+                    return;
+                }
+                let hir_map = self.tcx.hir();
+                for foreign_item_ref in *items {
+                    let foreign_item = hir_map.foreign_item(foreign_item_ref.id);
+                    let item_def_id = foreign_item.owner_id.to_def_id();
+                    match &foreign_item.kind {
+                        ForeignItemKind::Fn(_, _, _) => {
+                            let id = self.translate_fun_decl_id(item_def_id);
+                            self.root_ids.insert(rd::AnyDeclId::Fun(id));
+                        }
+                        ForeignItemKind::Static(_, _) => {
+                            self.push_unsupported_item(item_def_id, "extern static")
+                        }
+                        ForeignItemKind::Type => {
+                            self.push_unsupported_item(item_def_id, "extern type")
+                        }
+                    }
+                }
+            }
             ItemKind::Use(_, _) => {
                 // Ignore
             }
@@ -124,6 +212,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                     trace!("Ignoring module [{}] because marked as opaque", module_name);
                 } else {
                     trace!("Diving into module [{}]", module_name);
+                    let _ = self.module_id_map.insert(def_id);
                     let hir_map = self.tcx.hir();
                     for item_id in module.item_ids {
                         // Lookup and register the item
@@ -133,25 +222,128 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                 }
             }
             _ => {
-                unimplemented!("{:?}", item.kind);
+                let kind = item.kind.descr();
+                self.push_unsupported_item(def_id, kind);
             }
         }
     }
+
+    /// Compute the module tree, once all the declarations have been translated:
+    /// we attach every local declaration to the module in which it is defined.
+    fn translate_modules(&mut self) {
+        let tcx = self.tcx;
+        for (rid, id) in self.module_id_map.map.iter() {
+            let name = module_def_id_to_name(tcx, *rid);
+            self.module_defs.insert(
+                *id,
+                ast::ModuleDecl {
+                    def_id: *id,
+                    name,
+                    submodules: Vec::new(),
+                    types: Vec::new(),
+                    funs: Vec::new(),
+                    globals: Vec::new(),
+                },
+            );
+        }
+
+        // Lookup the module in which a local definition is defined. Note that
+        // the module may not have been registered, if it is opaque.
+        let module_id_map = &self.module_id_map;
+        let parent_module = |rid: &DefId| {
+            let local_id = rid.as_local()?;
+            let module_rid = tcx.parent_module_from_def_id(local_id).to_def_id();
+            module_id_map.map.get(&module_rid).copied()
+        };
+
+        // The map generators are ordered by Rust ids: the declarations are
+        // listed in a stable order.
+        for (rid, id) in self.module_id_map.map.iter() {
+            if *rid == CRATE_DEF_ID.to_def_id() {
+                continue;
+            }
+            if let Option::Some(parent_id) = parent_module(rid) {
+                let parent = self.module_defs.get_mut(parent_id).unwrap();
+                parent.submodules.push(*id);
+            }
+        }
+        for (rid, id) in self.type_id_map.map.iter() {
+            if let (Option::Some(module_id), Option::Some(_)) =
+                (parent_module(rid), self.type_defs.get(*id))
+            {
+                let module = self.module_defs.get_mut(module_id).unwrap();
+                module.types.push(*id);
+            }
+        }
+        for (rid, id) in self.fun_id_map.map.iter() {
+            if let (Option::Some(module_id), Option::Some(_)) =
+                (parent_module(rid), self.fun_defs.get(*id))
+            {
+                let module = self.module_defs.get_mut(module_id).unwrap();
+                module.funs.push(*id);
+            }
+        }
+        for (rid, id) in self.global_id_map.map.iter() {
+            if let (Option::Some(module_id), Option::Some(_)) =
+                (parent_module(rid), self.global_defs.get(*id))
+            {
+                let module = self.module_defs.get_mut(module_id).unwrap();
+                module.globals.push(*id);
+            }
+        }
+    }
+
+    /// Record that we ignored an item because we don't support it
+    pub(crate) fn push_unsupported_item(&mut self, def_id: DefId, kind: &str) {
+        let span = self.translate_meta_from_rid(def_id);
+        self.push_error(TranslationError::UnsupportedItem {
+            span,
+            kind: kind.to_string(),
+        });
+    }
+}
+
+/// Lookup a local function (or method) from its path, given as a string
+/// (`module::f`). The path may or may not start with the name of the crate.
+pub fn find_local_function(tcx: TyCtxt, crate_name: &str, path: &str) -> Option<DefId> {
+    let mut ref_name: Vec<&str> = path.split("::").collect();
+    if ref_name[0] != crate_name {
+        ref_name.insert(0, crate_name);
+    }
+    tcx.hir()
+        .body_owners()
+        .map(|id| id.to_def_id())
+        .filter(|id| matches!(tcx.def_kind(*id), DefKind::Fn | DefKind::AssocFn))
+        .find(|id| item_def_id_to_name(tcx, *id).equals_ref_name(&ref_name))
 }
 
-/// Translate all the declarations in the crate.
+/// Translate all the declarations in the crate (or only the function given in
+/// [CrateInfo::function], and the declarations it refers to).
+///
+/// We don't stop at the first unsupported declaration: we return the errors
+/// we encountered together with the (partial) translation.
 pub fn translate<'tcx, 'ctx>(
     crate_info: CrateInfo,
     sess: &'ctx Session,
     tcx: TyCtxt<'tcx>,
     mir_level: MirLevel,
-) -> TransCtx<'tcx, 'ctx> {
+    per_item_mir_level: HashMap<DefId, MirLevel>,
+) -> (TransCtx<'tcx, 'ctx>, Vec<TranslationError>) {
+    // We report an unsupported pointer size once the context is initialized
+    // (we need it to translate the span of the error, see below)
+    let pointer_bits = tcx.data_layout.pointer_size.bits();
+    let pointer_size = PointerSize::from_bits(pointer_bits);
+
     let mut ctx = TransCtx {
         sess,
         tcx,
         mir_level,
+        per_item_mir_level,
+        mir_bodies: HashMap::new(),
+        pointer_size: pointer_size.unwrap_or(PointerSize::P64),
         crate_info,
         all_ids: LinkedHashSet::new(),
+        root_ids: LinkedHashSet::new(),
         stack: LinkedHashSet::new(),
         file_to_id: HashMap::new(),
         id_to_file: HashMap::new(),
@@ -162,9 +354,37 @@ pub fn translate<'tcx, 'ctx>(
         fun_id_map: ast::FunDeclId::MapGenerator::new(),
         fun_defs: ast::FunDeclId::Map::new(),
         global_id_map: ast::GlobalDeclId::MapGenerator::new(),
+        promoted_id_map: HashMap::new(),
         global_defs: ast::GlobalDeclId::Map::new(),
+        trait_id_map: ast::TraitDeclId::MapGenerator::new(),
+        trait_defs: ast::TraitDeclId::Map::new(),
+        assoc_consts: Vec::new(),
+        generator_decls: ast::FunDeclId::Map::new(),
+        mir_dumps: ast::FunDeclId::Map::new(),
+        module_id_map: ast::ModuleDeclId::MapGenerator::new(),
+        module_defs: ast::ModuleDeclId::Map::new(),
+        errors: Vec::new(),
+        diag_sink: Box::new(PrintingSink::new(sess)),
+        type_interner: TypeInterner::new(),
     };
 
+    // Register the crate root first: it is the root of the module tree
+    // (see [ast::ROOT_MODULE_ID]).
+    let _ = ctx.module_id_map.insert(CRATE_DEF_ID.to_def_id());
+
+    // We can't translate the integers of a target whose pointer size we don't
+    // support: we don't translate anything
+    if pointer_size.is_none() {
+        let span = tcx.def_span(CRATE_DEF_ID.to_def_id());
+        let span = ctx.translate_meta_from_rspan(span);
+        ctx.push_error(TranslationError::UnsupportedItem {
+            span,
+            kind: format!("target with {pointer_bits}-bit pointers"),
+        });
+        let errors = std::mem::take(&mut ctx.errors);
+        return (ctx, errors);
+    }
+
     // First push all the items in the stack of items to translate.
     //
     // The way rustc works is as follows:
@@ -172,15 +392,23 @@ pub fn translate<'tcx, 'ctx>(
     //   explores all the files from there (typically listed through statements
     //   of the form "mod MODULE_NAME")
     // - the other files in the crate are Module items in the HIR graph
-    let hir = tcx.hir();
-    for item_id in hir.items() {
-        let item_id = item_id.hir_id();
-        let node = hir.find(item_id).unwrap();
-        let item = match node {
-            rustc_hir::Node::Item(item) => item,
-            _ => unreachable!(),
-        };
-        ctx.register_local_hir_item(true, item);
+    //
+    // If the user asked for a single function, we only push this function:
+    // its dependencies are pushed as we translate it.
+    if let Option::Some(def_id) = ctx.crate_info.function {
+        let id = ctx.translate_fun_decl_id(def_id);
+        ctx.root_ids.insert(rd::AnyDeclId::Fun(id));
+    } else {
+        let hir = tcx.hir();
+        for item_id in hir.items() {
+            let item = hir.item(item_id);
+            ctx.register_local_hir_item(true, item);
+        }
+    }
+
+    // The constants we evaluate steal the MIR bodies we may still need
+    if ctx.crate_info.inline_const_eval {
+        ctx.preload_mir_bodies();
     }
 
     // Translate.
@@ -192,6 +420,17 @@ pub fn translate<'tcx, 'ctx>(
     // Note that the order in which we translate the definitions doesn't matter:
     // we never need to lookup a translated definition, and only use the map
     // from Rust ids to translated ids.
+    //
+    // We deliberately translate the declarations sequentially, and don't
+    // translate the bodies in parallel (for instance by pre-registering the
+    // ids, then using one [BodyTransCtx] per thread and merging the results):
+    // - the [TyCtxt] can't be shared between threads: it is `!Sync` unless
+    //   rustc is built with the parallel compiler, which is not the case of
+    //   the nightly toolchains we use. Every body translation needs it.
+    // - we can't pre-register all the ids: the set of ids to translate is
+    //   only known once the bodies which refer to them have been translated.
+    // The rustc queries we perform (MIR, types, constant evaluation, etc.)
+    // dominate the translation time anyway.
     while let Some(id) = ctx.stack.pop_front() {
         match id {
             rd::AnyDeclId::Type(id) => ctx.translate_type(id),
@@ -200,6 +439,13 @@ pub fn translate<'tcx, 'ctx>(
         }
     }
 
+    // Link the opaque types to the methods the crate defines for them
+    ctx.record_opaque_types_methods();
+
+    // Compute the module tree
+    ctx.translate_modules();
+
     // Return the context
-    ctx
+    let errors = std::mem::take(&mut ctx.errors);
+    (ctx, errors)
 }
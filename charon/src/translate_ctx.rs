@@ -1,34 +1,64 @@
 //! The translation contexts.
 
 #![allow(dead_code)]
+use crate::cfg_dot;
+use crate::diagnostics::{DiagLevel, DiagSink, Diagnostic};
 use crate::formatter::Formatter;
-use crate::get_mir::MirLevel;
+use crate::get_mir::{
+    attribute_mir_level, function_mir_level, get_mir_for_def_id_and_level,
+    get_promoted_mir_for_def_id_and_level, MirLevel,
+};
 use crate::meta;
-use crate::meta::{FileId, FileName, LocalFileId, Meta, VirtualFileId};
+use crate::meta::{FileId, FileName, LocalFileId, MacroName, Meta, VirtualFileId};
+use crate::name_matcher::NameMatcher;
 use crate::names::Name;
-use crate::reorder_decls::{AnyRustId, AnyTransId};
+use crate::reorder_decls::{compute_dependencies, AnyRustId, AnyTransId};
 use crate::types as ty;
 use crate::types::LiteralTy;
+use crate::types_interner::TypeInterner;
 use crate::ullbc_ast as ast;
 use crate::values as v;
 use linked_hash_set::LinkedHashSet;
-use rustc_hir::def_id::DefId;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId};
 use rustc_index::IndexVec;
 use rustc_middle::mir;
 use rustc_middle::mir::BasicBlock;
 use rustc_middle::mir::{SourceInfo, SourceScope, SourceScopeData};
 use rustc_middle::ty::TyCtxt;
 use rustc_session::Session;
+use rustc_span::hygiene::ExpnKind;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
 
 pub struct CrateInfo {
     pub crate_name: String,
-    pub opaque_mods: HashSet<String>,
+    /// The patterns selecting the items we consider as opaque (see
+    /// [crate::cli_options::CliOpts::opaque_modules]).
+    pub opaque_mods: Vec<NameMatcher>,
+    /// If the user asked to translate a single function (see
+    /// [crate::cli_options::CliOpts::function]): the function, which is the only
+    /// root of the translation.
+    pub function: Option<DefId>,
+    /// If `true`, we try to evaluate the constants whose value is fully known
+    /// (see [crate::cli_options::CliOpts::inline_const_eval]).
+    pub inline_const_eval: bool,
+    /// If `true`, we translate the type aliases (see
+    /// [crate::cli_options::CliOpts::preserve_type_aliases]).
+    pub preserve_type_aliases: bool,
+    /// If `true`, we save the MIR of the translated functions (see
+    /// [crate::cli_options::CliOpts::emit_mir]).
+    pub emit_mir: bool,
+    /// If `true`, we translate the `StorageLive` statements (see
+    /// [crate::cli_options::CliOpts::storage_annotations]).
+    pub storage_annotations: bool,
 }
 
 impl CrateInfo {
     pub(crate) fn is_opaque_decl(&self, name: &Name) -> bool {
-        name.is_in_modules(&self.crate_name, &self.opaque_mods)
+        self.opaque_mods.iter().any(|m| m.matches(name))
     }
 
     fn is_transparent_decl(&self, name: &Name) -> bool {
@@ -36,7 +66,76 @@ impl CrateInfo {
     }
 }
 
+/// An error encountered during the translation.
+///
+/// Those errors signal that the input uses a feature which we don't support
+/// yet: when we encounter one, we record it and move on to the remaining
+/// declarations, so that the translation can produce a partial result.
+#[derive(Debug, Clone)]
+pub enum TranslationError {
+    UnsupportedItem { span: Meta, kind: String },
+    UnsupportedType { span: Meta, ty: String },
+    UnsupportedConstant { span: Meta, kind: String },
+    UnsupportedTerminator { span: Meta, kind: String },
+    UnsupportedStatement { span: Meta, kind: String },
+    UnsupportedExpression { span: Meta, kind: String },
+}
+
+pub type TransResult<T> = std::result::Result<T, Box<TranslationError>>;
+
+impl TranslationError {
+    pub fn meta(&self) -> &Meta {
+        match self {
+            TranslationError::UnsupportedItem { span, .. }
+            | TranslationError::UnsupportedType { span, .. }
+            | TranslationError::UnsupportedConstant { span, .. }
+            | TranslationError::UnsupportedTerminator { span, .. }
+            | TranslationError::UnsupportedStatement { span, .. }
+            | TranslationError::UnsupportedExpression { span, .. } => span,
+        }
+    }
+}
+
+impl std::fmt::Display for TranslationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranslationError::UnsupportedItem { kind, .. } => {
+                write!(f, "Unsupported item: {kind}")
+            }
+            TranslationError::UnsupportedType { ty, .. } => write!(f, "Unsupported type: {ty}"),
+            TranslationError::UnsupportedConstant { kind, .. } => {
+                write!(f, "Unsupported constant: {kind}")
+            }
+            TranslationError::UnsupportedTerminator { kind, .. } => {
+                write!(f, "Unsupported terminator: {kind}")
+            }
+            TranslationError::UnsupportedStatement { kind, .. } => {
+                write!(f, "Unsupported statement: {kind}")
+            }
+            TranslationError::UnsupportedExpression { kind, .. } => {
+                write!(f, "Unsupported expression: {kind}")
+            }
+        }
+    }
+}
+
 /// Translation context containing the top-level definitions.
+///
+/// We always translate the crate from scratch: we don't restore the
+/// declarations from a cache of the previous translations (fingerprinted
+/// with rustc's `StableHashingContext`). Even though the declarations can be
+/// read back (see [crate::deserialize]), such a cache would be unsound here:
+/// - the declaration ids (`TypeDeclId`, `FunDeclId`, etc.) are generated in
+///   the order in which we explore the crate, and thus are not stable across
+///   runs: a cached declaration may refer to ids which are now invalid, or
+///   which now designate other declarations.
+/// - we discover the declarations to translate while translating the bodies
+///   which refer to them: restoring a body from the cache would skip the
+///   registration of its dependencies.
+/// - the fingerprint of an item doesn't cover what its translation depends
+///   on (the signatures and layouts of the other items, the opaque modules,
+///   the MIR level, etc.): we would need rustc's dependency tracking, which
+///   is only available to the queries of the compiler itself.
 pub struct TransCtx<'tcx, 'ctx> {
     /// The compiler session
     pub sess: &'ctx Session,
@@ -44,10 +143,24 @@ pub struct TransCtx<'tcx, 'ctx> {
     pub tcx: TyCtxt<'tcx>,
     /// The level at which to extract the MIR
     pub mir_level: MirLevel,
+    /// The levels at which to extract the MIR of specific functions, given
+    /// with `--mir-level-for`. We also cache there the levels computed by
+    /// [Self::function_mir_level].
+    pub per_item_mir_level: HashMap<DefId, MirLevel>,
+    /// The MIR bodies of the local definitions and of their promoted
+    /// constants (see [Self::get_mir]).
+    pub mir_bodies: HashMap<(LocalDefId, Option<mir::Promoted>), &'tcx mir::Body<'tcx>>,
+    /// The size of the pointers on the target (this is also the size of the
+    /// `isize` and `usize` integers)
+    pub pointer_size: v::PointerSize,
     ///
     pub crate_info: CrateInfo,
     /// All the ids
     pub all_ids: LinkedHashSet<AnyTransId>,
+    /// The ids of the declarations which appear explicitly in the crate (we
+    /// register them while exploring the crate), as opposed to the
+    /// declarations we translate because other declarations refer to them.
+    pub root_ids: LinkedHashSet<AnyTransId>,
     /// The declarations we came accross and which we haven't translated yet
     pub stack: LinkedHashSet<AnyRustId>,
     /// File names to ids and vice-versa
@@ -65,21 +178,52 @@ pub struct TransCtx<'tcx, 'ctx> {
     pub fun_defs: ast::FunDecls,
     /// The map from Rust global ids to translated global ids
     pub global_id_map: ast::GlobalDeclId::MapGenerator<DefId>,
+    /// The map from the promoted constants (identified by the body in which
+    /// they appear and their index) to the globals we translate them to. The
+    /// ids are generated with the counter of [Self::global_id_map].
+    pub promoted_id_map: HashMap<(DefId, mir::Promoted), ast::GlobalDeclId::Id>,
     /// The translated global definitions
     pub global_defs: ast::GlobalDecls,
+    /// The map from Rust trait ids to translated trait ids
+    pub trait_id_map: ast::TraitDeclId::MapGenerator<DefId>,
+    /// The translated (local) trait declarations
+    pub trait_defs: ast::TraitDecls,
+    /// The associated constants declared in the (local) traits
+    pub assoc_consts: Vec<ast::AssocConstDecl>,
+    /// The generators, indexed by the functions implementing their bodies
+    pub generator_decls: ast::GeneratorDecls,
+    /// The MIR of the translated functions, pretty-printed by rustc (only
+    /// with `--emit-mir`, see [crate::get_mir::export_mir])
+    pub mir_dumps: ast::FunDeclId::Map<String>,
+    /// The map from Rust module ids to translated module ids
+    pub module_id_map: ast::ModuleDeclId::MapGenerator<DefId>,
+    /// The module tree of the crate. We compute it once all the declarations
+    /// have been translated.
+    pub module_defs: ast::ModuleDecls,
+    /// The (non-fatal) errors we encountered so far
+    pub errors: Vec<TranslationError>,
+    /// Where we emit the warnings and notes (see [crate::diagnostics])
+    pub diag_sink: Box<dyn DiagSink + 'ctx>,
+    /// The table in which we intern the types of the local variables (see
+    /// [crate::types_interner])
+    pub type_interner: TypeInterner,
 }
 
 /// A translation context for type/global/function bodies.
 /// Simply augments the [TransCtx] with local variables.
 ///
-/// TODO: use other collections than `im::OrdMap`? (we don't need a O(1) clone
-/// operation).
+/// We use `im::OrdMap` for the maps because it has a O(1) clone operation,
+/// which makes the snapshots cheap (see [BodyTransCtx::snapshot]).
 /// TODO: remove the borrow for the TransCtx, or make it a mutable borrow.
 pub(crate) struct BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     /// This is used in very specific situations.
     pub def_id: DefId,
     /// The translation context containing the top-level definitions/ids.
     pub t_ctx: &'ctx mut TransCtx<'tcx, 'ctx1>,
+    /// The MIR level at which we translate the body. This is generally
+    /// [TransCtx::mir_level], but some functions (like the `const fn`) are
+    /// translated at a specific level.
+    pub mir_level: MirLevel,
     /// Region counter
     pub regions_counter: ty::RegionVarId::Generator,
     /// The regions - TODO: rename to region_vars
@@ -119,6 +263,29 @@ pub(crate) struct BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     pub blocks_map: im::OrdMap<BasicBlock, ast::BlockId::Id>,
 }
 
+/// A snapshot of the local state of a [BodyTransCtx], to roll back a
+/// speculative translation (see [BodyTransCtx::snapshot]).
+#[derive(Clone)]
+pub(crate) struct BodyTransCtxSnapshot<'tcx> {
+    regions_counter: ty::RegionVarId::Generator,
+    region_vars: ty::RegionVarId::Vector<ty::RegionVar>,
+    region_vars_map: im::OrdMap<rustc_middle::ty::RegionKind<'tcx>, ty::RegionVarId::Id>,
+    type_vars_counter: ty::TypeVarId::Generator,
+    type_vars: ty::TypeVarId::Vector<ty::TypeVar>,
+    type_vars_map: im::OrdMap<u32, ty::TypeVarId::Id>,
+    vars_counter: v::VarId::Generator,
+    vars: v::VarId::Vector<ast::Var>,
+    vars_map: im::OrdMap<u32, v::VarId::Id>,
+    const_generic_counter: ty::ConstGenericVarId::Generator,
+    const_generic_vars: ty::ConstGenericVarId::Vector<ty::ConstGenericVar>,
+    const_generic_vars_map: im::OrdMap<u32, ty::ConstGenericVarId::Id>,
+    blocks_counter: ast::BlockId::Generator,
+    blocks: im::OrdMap<ast::BlockId::Id, ast::BlockData>,
+    blocks_map: im::OrdMap<BasicBlock, ast::BlockId::Id>,
+    /// The number of errors of the [TransCtx] at the time of the snapshot
+    errors_len: usize,
+}
+
 impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     /// Register the file containing a definition (rem.: we register the
     /// file containing the definition itself, not its def ident).
@@ -136,19 +303,20 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     /// Register a file if it is a "real" file and was not already registered
     fn register_file(&mut self, filename: FileName) -> FileId::Id {
         // Lookup the file if it was already registered
-        match self.file_to_id.get(&filename) {
-            Option::Some(id) => *id,
-            Option::None => {
+        match self.file_to_id.entry(filename) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
                 // Generate the fresh id
-                let id = match &filename {
+                // The "not real" files (the macro expansions, etc.) don't
+                // exist on the disk: we number them like the virtual files
+                let id = match entry.key() {
                     FileName::Local(_) => FileId::Id::LocalId(self.real_file_counter.fresh_id()),
-                    FileName::Virtual(_) => {
+                    FileName::Virtual(_) | FileName::NotReal(_) => {
                         FileId::Id::VirtualId(self.virtual_file_counter.fresh_id())
                     }
-                    FileName::NotReal(_) => unimplemented!(),
                 };
-                self.file_to_id.insert(filename.clone(), id);
-                self.id_to_file.insert(id, filename);
+                self.id_to_file.insert(id, entry.key().clone());
+                entry.insert(id);
                 id
             }
         }
@@ -172,13 +340,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         // to the file where the macro is defined, not the file where it is used.
         let (beg, end) = source_map.is_valid_span(rspan).unwrap();
         let filename = meta::convert_filename(&beg.file.name);
-        let file_id = match &filename {
-            FileName::NotReal(_) => {
-                // For now we forbid not real filenames
-                unimplemented!();
-            }
-            FileName::Virtual(_) | FileName::Local(_) => self.register_file(filename),
-        };
+        let file_id = self.register_file(filename);
 
         let beg = meta::convert_loc(beg);
         let end = meta::convert_loc(end);
@@ -187,6 +349,20 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         meta::Span { file_id, beg, end }
     }
 
+    /// Retrieve the name of the outermost macro a piece of code comes from,
+    /// if it comes from a macro expansion. We ignore the other kinds of
+    /// expansions (desugarings, etc.).
+    fn translate_macro_name(rspan: rustc_span::Span) -> Option<MacroName> {
+        // The backtrace starts with the innermost expansion
+        rspan
+            .macro_backtrace()
+            .filter_map(|expn| match expn.kind {
+                ExpnKind::Macro(_, name) => Some(MacroName::new(name.as_str())),
+                _ => None,
+            })
+            .last()
+    }
+
     /// Compute meta data from a Rust source scope
     pub fn translate_meta_from_source_info(
         &mut self,
@@ -196,6 +372,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         // Translate the span
         let mut scope_data = source_scopes.get(source_info.scope).unwrap();
         let span = self.translate_span(scope_data.span);
+        let macro_name = Self::translate_macro_name(source_info.span);
 
         // Lookup the top-most inlined parent scope.
         if scope_data.inlined_parent_scope.is_some() {
@@ -209,11 +386,15 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
             Meta {
                 span: parent_span,
                 generated_from_span: Some(span),
+                is_from_macro: macro_name.is_some(),
+                macro_name,
             }
         } else {
             Meta {
                 span,
                 generated_from_span: None,
+                is_from_macro: macro_name.is_some(),
+                macro_name,
             }
         }
     }
@@ -221,10 +402,121 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     pub(crate) fn translate_meta_from_rspan(&mut self, rspan: rustc_span::Span) -> Meta {
         // Translate teh span
         let span = self.translate_span(rspan);
+        let macro_name = Self::translate_macro_name(rspan);
 
         Meta {
             span,
             generated_from_span: None,
+            is_from_macro: macro_name.is_some(),
+            macro_name,
+        }
+    }
+
+    /// Record a translation error, and continue.
+    pub(crate) fn push_error(&mut self, error: TranslationError) {
+        let span = &error.meta().span;
+        let file = self.id_to_file.get(&span.file_id).unwrap();
+        error!("{:?}:{}:{}: {}", file, span.beg.line, span.beg.col, error);
+        self.errors.push(error);
+    }
+
+    /// Emit a warning (see [crate::diagnostics]).
+    pub(crate) fn emit_warning(
+        &mut self,
+        rspan: rustc_span::Span,
+        message: String,
+        note: Option<String>,
+    ) {
+        let meta = self.translate_meta_from_rspan(rspan);
+        self.diag_sink.emit(Diagnostic {
+            level: DiagLevel::Warning,
+            message,
+            span: Some(meta),
+            note,
+            rust_span: Some(rspan),
+        });
+    }
+
+    /// The MIR level at which we translate the body of a function. By order
+    /// of priority:
+    /// - the level given on the command line (`--mir-level-for`)
+    /// - the level given by a `#[charon::mir_level = "..."]` attribute
+    /// - the default level (see [crate::get_mir::function_mir_level])
+    pub(crate) fn function_mir_level(&mut self, def_id: DefId) -> MirLevel {
+        if let Some(level) = self.per_item_mir_level.get(&def_id) {
+            return *level;
+        }
+        let level = match attribute_mir_level(self.tcx, def_id) {
+            Ok(Some(level)) => level,
+            Ok(None) => function_mir_level(self.tcx, def_id, self.mir_level),
+            Err(span) => {
+                self.emit_warning(
+                    span,
+                    "Unknown MIR level: we use the default level".to_string(),
+                    Some(
+                        "the MIR levels are \"built\", \"promoted\" and \"optimized\"".to_string(),
+                    ),
+                );
+                function_mir_level(self.tcx, def_id, self.mir_level)
+            }
+        };
+        // Don't emit the warnings twice
+        self.per_item_mir_level.insert(def_id, level);
+        level
+    }
+
+    /// Query the MIR of a local definition, or of one of its promoted
+    /// constants, at a specific level.
+    ///
+    /// We keep a copy of the bodies: rustc steals the MIR bodies when it
+    /// computes the bodies of the next level, which it does in particular
+    /// when evaluating a constant (see [Self::preload_mir_bodies]).
+    pub(crate) fn get_mir(
+        &mut self,
+        def_id: LocalDefId,
+        promoted: Option<mir::Promoted>,
+        level: MirLevel,
+    ) -> &'tcx mir::Body<'tcx> {
+        let tcx = self.tcx;
+        self.mir_bodies
+            .entry((def_id, promoted))
+            .or_insert_with(|| match promoted {
+                None => get_mir_for_def_id_and_level(tcx, def_id, level),
+                Some(promoted) => {
+                    get_promoted_mir_for_def_id_and_level(tcx, def_id, promoted, level)
+                }
+            })
+    }
+
+    /// Load the MIR bodies of the local functions and globals, before we
+    /// evaluate any constant (see [CrateInfo::inline_const_eval]).
+    ///
+    /// Evaluating a constant forces rustc to compute the optimized MIR of the
+    /// constant and of the functions it calls, and the MIR of the lower
+    /// levels is stolen in the process: we wouldn't be able to query it
+    /// anymore when translating those definitions.
+    pub(crate) fn preload_mir_bodies(&mut self) {
+        let tcx = self.tcx;
+        for def_id in tcx.hir().body_owners() {
+            let level = match tcx.def_kind(def_id) {
+                DefKind::Fn | DefKind::AssocFn => self.function_mir_level(def_id.to_def_id()),
+                DefKind::Const | DefKind::AssocConst | DefKind::Static(_) => self.mir_level,
+                _ => continue,
+            };
+            match level {
+                // The optimized MIR is never stolen
+                MirLevel::Optimized => (),
+                MirLevel::Built => {
+                    let _ = self.get_mir(def_id, None, level);
+                }
+                MirLevel::Promoted => {
+                    let _ = self.get_mir(def_id, None, level);
+                    let promoted = tcx.mir_promoted(def_id).1.borrow().indices();
+                    for promoted in promoted {
+                        let _ = self.get_mir(def_id, Some(promoted), level);
+                    }
+                }
+            }
         }
     }
 
@@ -237,6 +529,90 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         !self.id_is_opaque(id)
     }
 
+    /// Remove the opaque declarations from the translated declarations
+    /// (`--strip-opaque`). We keep the opaque declarations which the
+    /// transparent declarations (transitively) refer to, so that the output
+    /// doesn't refer to missing declarations: we remove the unused items of
+    /// the opaque modules. The remaining declarations keep their ids.
+    pub(crate) fn strip_opaque(&mut self) {
+        let deps = compute_dependencies(self);
+
+        // The roots: the transparent declarations, and the methods of the
+        // traits (which refer to them)
+        let info = &self.crate_info;
+        let mut stack: Vec<AnyTransId> = Vec::new();
+        stack.extend(
+            self.type_defs
+                .iter()
+                .filter(|d| info.is_transparent_decl(&d.name))
+                .map(|d| AnyTransId::Type(d.def_id)),
+        );
+        stack.extend(
+            self.fun_defs
+                .iter()
+                .filter(|d| info.is_transparent_decl(&d.name))
+                .map(|d| AnyTransId::Fun(d.def_id)),
+        );
+        stack.extend(
+            self.global_defs
+                .iter()
+                .filter(|d| info.is_transparent_decl(&d.name))
+                .map(|d| AnyTransId::Global(d.def_id)),
+        );
+        for d in self.trait_defs.iter() {
+            let methods = d.required_methods.iter().chain(d.default_methods.iter());
+            stack.extend(methods.map(|id| AnyTransId::Fun(*id)));
+        }
+
+        // Compute the declarations reachable from the roots
+        let mut kept: HashSet<AnyTransId> = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if !kept.insert(id) {
+                continue;
+            }
+            if let Some(ids) = deps.get(&id) {
+                stack.extend(ids.iter().copied());
+            }
+            // The overridden default methods are not in the dependencies
+            if let AnyTransId::Fun(fid) = id {
+                let overridden = self.fun_defs.get(fid).and_then(|d| d.overrides_default);
+                stack.extend(overridden.map(AnyTransId::Fun));
+            }
+        }
+
+        let count = self.all_ids.len();
+        self.type_defs
+            .retain_indexed(|id, _| kept.contains(&AnyTransId::Type(id)));
+        self.fun_defs
+            .retain_indexed(|id, _| kept.contains(&AnyTransId::Fun(id)));
+        self.global_defs
+            .retain_indexed(|id, _| kept.contains(&AnyTransId::Global(id)));
+        self.generator_decls
+            .retain_indexed(|id, _| kept.contains(&AnyTransId::Fun(id)));
+        self.mir_dumps
+            .retain_indexed(|id, _| kept.contains(&AnyTransId::Fun(id)));
+        self.all_ids = self
+            .all_ids
+            .iter()
+            .copied()
+            .filter(|id| kept.contains(id))
+            .collect();
+        let count = count - self.all_ids.len();
+        trace!("# Stripped {count} opaque declaration(s)");
+        self.root_ids = self
+            .root_ids
+            .iter()
+            .copied()
+            .filter(|id| kept.contains(id))
+            .collect();
+        for m in self.module_defs.iter_mut() {
+            m.types.retain(|id| kept.contains(&AnyTransId::Type(*id)));
+            m.funs.retain(|id| kept.contains(&AnyTransId::Fun(*id)));
+            m.globals
+                .retain(|id| kept.contains(&AnyTransId::Global(*id)));
+        }
+    }
+
     pub(crate) fn push_id(&mut self, _rust_id: DefId, id: AnyRustId, trans_id: AnyTransId) {
         // Add the id to the stack of declarations to translate
         self.stack.insert(id);
@@ -244,31 +620,33 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     }
 
     pub(crate) fn register_type_decl_id(&mut self, id: DefId) -> ty::TypeDeclId::Id {
-        match self.type_id_map.get(id) {
-            Option::Some(id) => id,
-            Option::None => {
-                let rid = AnyRustId::Type(id);
-                let trans_id = self.type_id_map.insert(id);
-                self.push_id(id, rid, AnyTransId::Type(trans_id));
-                trans_id
-            }
+        let (trans_id, fresh) = self.type_id_map.insert_new(id);
+        if fresh {
+            let rid = AnyRustId::Type(id);
+            self.push_id(id, rid, AnyTransId::Type(trans_id));
         }
+        trans_id
     }
 
     pub(crate) fn translate_type_decl_id(&mut self, id: DefId) -> ty::TypeDeclId::Id {
         self.register_type_decl_id(id)
     }
 
+    /// Check if a registered type declaration is a union.
+    pub(crate) fn type_decl_is_union(&self, id: ty::TypeDeclId::Id) -> bool {
+        self.type_id_map
+            .map
+            .iter()
+            .any(|(rid, tid)| *tid == id && self.tcx.adt_def(*rid).is_union())
+    }
+
     pub(crate) fn register_fun_decl_id(&mut self, id: DefId) -> ast::FunDeclId::Id {
-        match self.fun_id_map.get(id) {
-            Option::Some(id) => id,
-            Option::None => {
-                let rid = AnyRustId::Fun(id);
-                let trans_id = self.fun_id_map.insert(id);
-                self.push_id(id, rid, AnyTransId::Fun(trans_id));
-                trans_id
-            }
+        let (trans_id, fresh) = self.fun_id_map.insert_new(id);
+        if fresh {
+            let rid = AnyRustId::Fun(id);
+            self.push_id(id, rid, AnyTransId::Fun(trans_id));
         }
+        trans_id
     }
 
     pub(crate) fn translate_fun_decl_id(&mut self, id: DefId) -> ast::FunDeclId::Id {
@@ -276,19 +654,67 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     }
 
     pub(crate) fn register_global_decl_id(&mut self, id: DefId) -> ty::GlobalDeclId::Id {
-        match self.global_id_map.get(id) {
+        let (trans_id, fresh) = self.global_id_map.insert_new(id);
+        if fresh {
+            let rid = AnyRustId::Global(id);
+            self.push_id(id, rid, AnyTransId::Global(trans_id));
+        }
+        trans_id
+    }
+
+    pub(crate) fn translate_global_decl_id(&mut self, id: DefId) -> ast::GlobalDeclId::Id {
+        self.register_global_decl_id(id)
+    }
+
+    /// Note that we only translate the declarations of the local traits (see
+    /// [Self::translate_trait]): the identifiers of the other traits are only
+    /// used to link the associated constants, types and methods to their
+    /// traits.
+    pub(crate) fn translate_trait_decl_id(&mut self, id: DefId) -> ast::TraitDeclId::Id {
+        self.trait_id_map.insert(id)
+    }
+
+    /// Lookup a translated function by name (see [Name::matches_path]: the
+    /// name can be the simple name of the function, like `list_length`, or
+    /// its full path). If several functions match, we return the first one.
+    pub fn find_fun_by_name(&self, name: &str) -> Option<ast::FunDeclId::Id> {
+        self.fun_defs
+            .iter()
+            .find(|d| d.name.matches_path(name))
+            .map(|d| d.def_id)
+    }
+
+    /// Same as [Self::find_fun_by_name], but panics if there is no such
+    /// function.
+    pub fn expect_fun_by_name(&self, name: &str) -> ast::FunDeclId::Id {
+        match self.find_fun_by_name(name) {
             Option::Some(id) => id,
             Option::None => {
-                let rid = AnyRustId::Global(id);
-                let trans_id = self.global_id_map.insert(id);
-                self.push_id(id, rid, AnyTransId::Global(trans_id));
-                trans_id
+                let names: Vec<String> = self.fun_defs.iter().map(|d| d.name.to_string()).collect();
+                panic!(
+                    "Could not find a function named `{name}` among the translated functions: {}",
+                    names.join(", ")
+                )
             }
         }
     }
 
-    pub(crate) fn translate_global_decl_id(&mut self, id: DefId) -> ast::GlobalDeclId::Id {
-        self.register_global_decl_id(id)
+    /// Lookup a translated type by name (see [Self::find_fun_by_name]).
+    pub fn find_type_by_name(&self, name: &str) -> Option<ty::TypeDeclId::Id> {
+        self.type_defs
+            .iter()
+            .find(|d| d.name.matches_path(name))
+            .map(|d| d.def_id)
+    }
+
+    /// Dump the control-flow graph of a translated function to a GraphViz
+    /// file (see [crate::cfg_dot]). Useful for debugging.
+    pub fn dump_cfg(&self, fun_id: ast::FunDeclId::Id, path: &Path) -> std::io::Result<()> {
+        let decl = self.fun_defs.get(fun_id).unwrap();
+        let fun_ctx = ast::FunDeclsFormatter::new(&self.fun_defs);
+        let global_ctx = ast::GlobalDeclsFormatter::new(&self.global_defs);
+        let mut file = File::create(path)?;
+        cfg_dot::write_cfg_dot(decl, &self.type_defs, &fun_ctx, &global_ctx, &mut file)
     }
 }
 
@@ -297,6 +723,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     pub(crate) fn new(def_id: DefId, t_ctx: &'ctx mut TransCtx<'tcx, 'ctx1>) -> Self {
         BodyTransCtx {
             def_id,
+            mir_level: t_ctx.mir_level,
             t_ctx,
             regions_counter: ty::RegionVarId::Generator::new(),
             region_vars: ty::RegionVarId::Vector::new(),
@@ -316,6 +743,74 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         }
     }
 
+    /// Save the local state of the context (the counters, the variables, the
+    /// blocks, etc.), to attempt a translation which we may want to undo with
+    /// [Self::rollback].
+    ///
+    /// The errors pushed during the speculative translation are discarded by
+    /// the rollback, but the rest of the state of the [TransCtx] is not saved:
+    /// the declarations registered in the meantime are still translated.
+    pub(crate) fn snapshot(&self) -> BodyTransCtxSnapshot<'tcx> {
+        BodyTransCtxSnapshot {
+            regions_counter: self.regions_counter,
+            region_vars: self.region_vars.clone(),
+            region_vars_map: self.region_vars_map.clone(),
+            type_vars_counter: self.type_vars_counter,
+            type_vars: self.type_vars.clone(),
+            type_vars_map: self.type_vars_map.clone(),
+            vars_counter: self.vars_counter,
+            vars: self.vars.clone(),
+            vars_map: self.vars_map.clone(),
+            const_generic_counter: self.const_generic_counter,
+            const_generic_vars: self.const_generic_vars.clone(),
+            const_generic_vars_map: self.const_generic_vars_map.clone(),
+            blocks_counter: self.blocks_counter,
+            blocks: self.blocks.clone(),
+            blocks_map: self.blocks_map.clone(),
+            errors_len: self.t_ctx.errors.len(),
+        }
+    }
+
+    /// Restore the local state saved by [Self::snapshot]. The identifiers
+    /// generated since the snapshot are forgotten, and will be generated
+    /// again, and the errors pushed since the snapshot are discarded.
+    pub(crate) fn rollback(&mut self, snapshot: BodyTransCtxSnapshot<'tcx>) {
+        let BodyTransCtxSnapshot {
+            regions_counter,
+            region_vars,
+            region_vars_map,
+            type_vars_counter,
+            type_vars,
+            type_vars_map,
+            vars_counter,
+            vars,
+            vars_map,
+            const_generic_counter,
+            const_generic_vars,
+            const_generic_vars_map,
+            blocks_counter,
+            blocks,
+            blocks_map,
+            errors_len,
+        } = snapshot;
+        self.regions_counter = regions_counter;
+        self.region_vars = region_vars;
+        self.region_vars_map = region_vars_map;
+        self.type_vars_counter = type_vars_counter;
+        self.type_vars = type_vars;
+        self.type_vars_map = type_vars_map;
+        self.vars_counter = vars_counter;
+        self.vars = vars;
+        self.vars_map = vars_map;
+        self.const_generic_counter = const_generic_counter;
+        self.const_generic_vars = const_generic_vars;
+        self.const_generic_vars_map = const_generic_vars_map;
+        self.blocks_counter = blocks_counter;
+        self.blocks = blocks;
+        self.blocks_map = blocks_map;
+        self.t_ctx.errors.truncate(errors_len);
+    }
+
     pub(crate) fn translate_meta_from_rid(&mut self, def_id: DefId) -> Meta {
         self.t_ctx.translate_meta_from_rid(def_id)
     }
@@ -324,6 +819,12 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         self.t_ctx.translate_meta_from_rspan(rspan)
     }
 
+    /// The meta information of the definition we are currently translating.
+    /// We use it to report errors when we don't have more precise information.
+    pub(crate) fn def_meta(&mut self) -> Meta {
+        self.t_ctx.translate_meta_from_rid(self.def_id)
+    }
+
     pub(crate) fn get_local(&self, local: &mir::Local) -> Option<v::VarId::Id> {
         self.vars_map.get(&local.as_u32()).copied()
     }
@@ -401,7 +902,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         let var = ast::Var {
             index: var_id,
             name,
-            ty,
+            ty: self.t_ctx.type_interner.intern(ty),
         };
         self.vars.insert(var_id, var);
         self.vars_map.insert(rid, var_id);
@@ -415,6 +916,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             index: var_id,
             name,
             ty,
+            default: None,
         };
         self.const_generic_vars.insert(var_id, var);
         self.const_generic_vars_map.insert(rid, var_id);
@@ -458,7 +960,7 @@ impl<'tcx, 'ctx, 'ctx1> Formatter<v::VarId::Id> for BodyTransCtx<'tcx, 'ctx, 'ct
 impl<'tcx, 'ctx, 'ctx1> Formatter<ty::RegionVarId::Id> for BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     fn format_object(&self, id: ty::RegionVarId::Id) -> String {
         let v = self.region_vars.get(id).unwrap();
-        v.to_string()
+        v.lifetime_display_name()
     }
 }
 
@@ -509,88 +1011,17 @@ impl<'tcx, 'ctx, 'ctx1> Formatter<&ty::Ty<ty::ErasedRegion>> for BodyTransCtx<'t
     }
 }
 
-/// Auxiliary definition used to format definitions.
-pub(crate) struct TypeDeclFormatter<'a> {
-    pub type_defs: &'a ty::TypeDecls,
-    pub global_defs: &'a ast::GlobalDecls,
-    /// The region parameters of the definition we are printing (needed to
-    /// correctly pretty print region var ids)
-    pub region_params: &'a ty::RegionVarId::Vector<ty::RegionVar>,
-    /// The type parameters of the definition we are printing (needed to
-    /// correctly pretty print type var ids)
-    pub type_params: &'a ty::TypeVarId::Vector<ty::TypeVar>,
-    /// The const generic parameters of the definition we are printing (needed to
-    /// correctly pretty print type var ids)
-    pub const_generic_params: &'a ty::ConstGenericVarId::Vector<ty::ConstGenericVar>,
-}
-
-impl<'a> Formatter<ty::RegionVarId::Id> for TypeDeclFormatter<'a> {
-    fn format_object(&self, id: ty::RegionVarId::Id) -> String {
-        // Lookup the region parameter
-        let v = self.region_params.get(id).unwrap();
-        // Format
-        v.to_string()
-    }
-}
-
-impl<'a> Formatter<ty::ConstGenericVarId::Id> for TypeDeclFormatter<'a> {
-    fn format_object(&self, id: ty::ConstGenericVarId::Id) -> String {
-        // Lookup the region parameter
-        let v = self.const_generic_params.get(id).unwrap();
-        // Format
-        v.to_string()
-    }
-}
-
-impl<'a> Formatter<ty::TypeVarId::Id> for TypeDeclFormatter<'a> {
-    fn format_object(&self, id: ty::TypeVarId::Id) -> String {
-        // Lookup the type parameter
-        let v = self.type_params.get(id).unwrap();
-        // Format
-        v.to_string()
-    }
-}
-
-impl<'a> Formatter<&ty::Region<ty::RegionVarId::Id>> for TypeDeclFormatter<'a> {
-    fn format_object(&self, r: &ty::Region<ty::RegionVarId::Id>) -> String {
-        r.fmt_with_ctx(self)
-    }
-}
-
-impl<'a> Formatter<&ty::ErasedRegion> for TypeDeclFormatter<'a> {
-    fn format_object(&self, _: &ty::ErasedRegion) -> String {
-        "".to_owned()
-    }
-}
-
-impl<'a> Formatter<&ty::TypeDecl> for TypeDeclFormatter<'a> {
-    fn format_object(&self, def: &ty::TypeDecl) -> String {
-        def.fmt_with_ctx(self)
-    }
-}
-
-impl<'a> Formatter<ty::TypeDeclId::Id> for TypeDeclFormatter<'a> {
-    fn format_object(&self, id: ty::TypeDeclId::Id) -> String {
-        self.type_defs.format_object(id)
-    }
-}
-
-impl<'a> Formatter<ty::GlobalDeclId::Id> for TypeDeclFormatter<'a> {
-    fn format_object(&self, id: ty::GlobalDeclId::Id) -> String {
-        self.global_defs.format_object(id)
-    }
-}
-
 impl<'tcx, 'ctx, 'ctx1> Formatter<&ty::TypeDecl> for BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     fn format_object(&self, def: &ty::TypeDecl) -> String {
         // Create a type def formatter (which will take care of the
         // type parameters)
-        let formatter = TypeDeclFormatter {
+        let formatter = ty::TypeDeclFormatter {
             type_defs: &self.t_ctx.type_defs,
             global_defs: &self.t_ctx.global_defs,
             region_params: &def.region_params,
             type_params: &def.type_params,
             const_generic_params: &def.const_generic_params,
+            show_variance: false,
         };
         formatter.format_object(def)
     }
@@ -9,11 +9,14 @@ use crate::common::*;
 use crate::expressions as e;
 use crate::formatter::Formatter;
 use crate::generics;
-use crate::get_mir::{boxes_are_desugared, get_mir_for_def_id_and_level};
-use crate::id_vector;
+use crate::get_mir::{boxes_are_desugared, mir_body_to_string, MirLevel};
+use crate::id_vector::{self, ToUsize};
+use crate::meta::Meta;
 use crate::names::global_def_id_to_name;
-use crate::names::{function_def_id_to_name, type_def_id_to_name};
+use crate::names::{function_def_id_to_name, trait_def_id_to_name, type_def_id_to_name};
+use crate::names::{item_def_id_to_name, Disambiguator, Name, PathElem};
 use crate::regions_hierarchy::RegionGroups;
+use crate::reorder_decls::AnyTransId;
 use crate::translate_ctx::*;
 use crate::translate_types;
 use crate::types as ty;
@@ -21,20 +24,20 @@ use crate::types::{FieldId, VariantId};
 use crate::ullbc_ast as ast;
 use crate::values as v;
 use crate::values::{Literal, ScalarValue};
+use crate::wellformed;
 use core::convert::*;
-use log::warn;
 use rustc_abi::FieldIdx;
+use rustc_ast::{AttrArgs, AttrArgsEq, AttrKind};
 use rustc_hir::def_id::{DefId, LocalDefId};
 use rustc_middle::mir;
 use rustc_middle::mir::{
     BasicBlock, Body, Operand, Place, PlaceElem, Statement, StatementKind, Terminator,
-    TerminatorKind, START_BLOCK,
+    TerminatorKind, UnwindAction, START_BLOCK,
 };
 use rustc_middle::ty as mir_ty;
 use rustc_middle::ty::adjustment::PointerCast;
-use rustc_middle::ty::{TyCtxt, TyKind};
-use rustc_span::Span;
-use std::iter::FromIterator;
+use rustc_middle::ty::{TyCtxt, TyKind, TypeVisitableExt};
+use std::collections::HashMap;
 use translate_types::{translate_erased_region, translate_region_name};
 
 fn translate_variant_id(id: rustc_target::abi::VariantIdx) -> VariantId::Id {
@@ -59,13 +62,36 @@ fn translate_borrow_kind(borrow_kind: mir::BorrowKind) -> e::BorrowKind {
             }
         }
         mir::BorrowKind::Unique => {
-            unimplemented!();
+            // Unique immutable borrows are introduced by the closures which
+            // mutate the target of a captured mutable reference (`*x += 1`
+            // where `x: &mut u32`): the closure state then contains a unique
+            // borrow of `x`, through which we mutate `*x`. We treat it as a
+            // mutable borrow.
+            e::BorrowKind::Mut
         }
         mir::BorrowKind::Shallow => e::BorrowKind::Shallow,
     }
 }
 
-fn translate_binaryop_kind(binop: mir::BinOp) -> e::BinOp {
+fn translate_closure_kind(kind: mir_ty::ClosureKind) -> e::ClosureKind {
+    match kind {
+        mir_ty::ClosureKind::Fn => e::ClosureKind::Fn,
+        mir_ty::ClosureKind::FnMut => e::ClosureKind::FnMut,
+        mir_ty::ClosureKind::FnOnce => e::ClosureKind::FnOnce,
+    }
+}
+
+fn translate_capture_mode(capture: mir_ty::UpvarCapture) -> ast::CaptureMode {
+    match capture {
+        mir_ty::UpvarCapture::ByValue => ast::CaptureMode::ByValue,
+        mir_ty::UpvarCapture::ByRef(mir_ty::BorrowKind::ImmBorrow) => ast::CaptureMode::ByRef,
+        mir_ty::UpvarCapture::ByRef(
+            mir_ty::BorrowKind::UniqueImmBorrow | mir_ty::BorrowKind::MutBorrow,
+        ) => ast::CaptureMode::ByMutRef,
+    }
+}
+
+pub(crate) fn translate_binaryop_kind(binop: mir::BinOp) -> e::BinOp {
     use mir::BinOp;
     match binop {
         BinOp::BitXor => e::BinOp::BitXor,
@@ -98,6 +124,76 @@ fn translate_unaryop_kind(binop: mir::UnOp) -> e::UnOp {
     }
 }
 
+/// Detect the struct update syntax (`Foo { x: 1, ..a }`) in the operands of a
+/// structure aggregate: rustc projects the fields which are not updated from
+/// the base (`Foo { x: const 1, y: move (a.y) }`, see
+/// [e::AggregateKind::StructUpdate]). We only group the fields if some of them
+/// (but not all) are the same field of the same base place.
+fn translate_struct_update(
+    type_id: ty::TypeDeclId::Id,
+    operands: &[e::Operand],
+) -> Option<e::AggregateKind> {
+    let mut base: Option<e::Place> = None;
+    let mut moved = false;
+    let mut updated_fields = Vec::new();
+    for (i, op) in operands.iter().enumerate() {
+        let field_id = FieldId::Id::new(i);
+        let from_base = match op {
+            e::Operand::Copy(p) | e::Operand::Move(p) => match p.projection.split_last() {
+                Some((e::ProjectionElem::Field(e::FieldProjKind::Adt(id, None), fid), prefix))
+                    if *id == type_id && *fid == field_id =>
+                {
+                    let prefix = e::Place {
+                        var_id: p.var_id,
+                        projection: prefix.to_vec(),
+                    };
+                    match &base {
+                        None => {
+                            base = Some(prefix);
+                            true
+                        }
+                        Some(base) => *base == prefix,
+                    }
+                }
+                _ => false,
+            },
+            e::Operand::Const(..) => false,
+        };
+        if from_base {
+            moved |= op.is_move();
+        } else {
+            updated_fields.push((field_id, op.clone()));
+        }
+    }
+
+    let base = base?;
+    if updated_fields.is_empty() {
+        return None;
+    }
+    let base = if moved {
+        e::Operand::Move(base)
+    } else {
+        e::Operand::Copy(base)
+    };
+    Some(e::AggregateKind::StructUpdate {
+        type_id,
+        base,
+        updated_fields,
+    })
+}
+
+/// Detect the array literals whose elements are all the same constant
+/// (`[0, 0, 0]`): we translate them to [e::AggregateKind::Repeat], like
+/// `[0; 3]`. We don't collapse the arrays of places, nor the arrays with
+/// less than two elements.
+fn collapse_array_operands(operands: &[e::Operand]) -> Option<e::Operand> {
+    let (first, rest) = operands.split_first()?;
+    if rest.is_empty() || !first.is_const() || rest.iter().any(|op| op != first) {
+        return None;
+    }
+    Some(first.clone())
+}
+
 /// Build an uninterpreted constant from a MIR constant identifier.
 fn rid_as_unevaluated_constant<'tcx>(id: DefId) -> rustc_middle::mir::UnevaluatedConst<'tcx> {
     let p = mir_ty::List::empty();
@@ -108,51 +204,56 @@ fn rid_as_unevaluated_constant<'tcx>(id: DefId) -> rustc_middle::mir::Unevaluate
 /// parameters substitution.
 /// The `Operand` comes from a `TerminatorKind::Call`.
 /// Only supports calls to top-level functions (which are considered as constants
-/// by rustc); doesn't support closures for now.
+/// by rustc): return `None` for the other operands (closures, unevaluated
+/// constants, etc.).
 fn get_function_from_operand<'tcx>(
     func: &Operand<'tcx>,
-) -> (DefId, &'tcx rustc_middle::ty::subst::InternalSubsts<'tcx>) {
+) -> Option<(DefId, &'tcx rustc_middle::ty::subst::InternalSubsts<'tcx>)> {
     trace!("func: {:?}", func);
 
     use std::ops::Deref;
     // Match on the func operand: it should be a constant as we don't support
     // closures for now.
-    match func {
+    let c_ty = match func {
         mir::Operand::Constant(c) => {
             trace!("Operand::Constant: {:?}", c);
-            let c = c.deref();
-            match &c.literal {
-                mir::ConstantKind::Ty(c) => {
-                    // The type of the constant should be a FnDef, allowing
-                    // us to retrieve the function's identifier and instantiation.
-                    let c_ty = c.ty();
-                    assert!(c_ty.is_fn());
-                    match c_ty.kind() {
-                        mir_ty::TyKind::FnDef(def_id, subst) => (*def_id, subst),
-                        _ => {
-                            unreachable!();
-                        }
-                    }
-                }
+            match &c.deref().literal {
+                // The type of the constant should be a FnDef, allowing
+                // us to retrieve the function's identifier and instantiation.
+                mir::ConstantKind::Ty(c) => c.ty(),
                 mir::ConstantKind::Val(cv, c_ty) => {
                     trace!("cv: {:?}, ty: {:?}", cv, c_ty);
-                    // Same as for the `Ty` case above
-                    assert!(c_ty.is_fn());
-                    match c_ty.kind() {
-                        mir_ty::TyKind::FnDef(def_id, subst) => (*def_id, subst),
-                        _ => {
-                            unreachable!();
-                        }
-                    }
-                }
-                mir::ConstantKind::Unevaluated(_, _) => {
-                    unimplemented!();
+                    *c_ty
                 }
+                mir::ConstantKind::Unevaluated(_, _) => return None,
             }
         }
-        mir::Operand::Move(_place) | mir::Operand::Copy(_place) => {
-            unimplemented!();
-        }
+        mir::Operand::Move(_place) | mir::Operand::Copy(_place) => return None,
+    };
+    match c_ty.kind() {
+        mir_ty::TyKind::FnDef(def_id, subst) => Some((*def_id, subst)),
+        _ => None,
+    }
+}
+
+/// Check if a call to a trait method is dispatched through a trait object
+/// (i.e., the `Self` type is `dyn Trait`), in which case we return the index
+/// of the method in the vtable.
+fn get_virtual_call_index<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    caller_def_id: DefId,
+    def_id: DefId,
+    substs: &'tcx rustc_middle::ty::subst::InternalSubsts<'tcx>,
+) -> Option<usize> {
+    // Only trait methods can be called through a vtable
+    tcx.trait_of_item(def_id)?;
+    let param_env = tcx.param_env(caller_def_id);
+    match mir_ty::Instance::resolve(tcx, param_env, def_id, substs) {
+        Ok(Some(instance)) => match instance.def {
+            mir_ty::InstanceDef::Virtual(_, vtable_index) => Some(vtable_index),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
@@ -224,7 +325,7 @@ fn translate_primitive_function_call(
     args: Vec<e::Operand>,
     dest: e::Place,
     target: ast::BlockId::Id,
-) -> Result<ast::RawTerminator> {
+) -> TransResult<ast::RawTerminator> {
     trace!("- def_id: {:?}", def_id,);
 
     // Translate the function name
@@ -338,7 +439,7 @@ fn translate_box_deref(
     args: Vec<e::Operand>,
     dest: e::Place,
     target: ast::BlockId::Id,
-) -> Result<ast::RawTerminator> {
+) -> TransResult<ast::RawTerminator> {
     // Check the arguments
     assert!(region_args.is_empty());
     assert!(type_args.len() == 1);
@@ -378,7 +479,7 @@ fn translate_vec_index(
     args: Vec<e::Operand>,
     dest: e::Place,
     target: ast::BlockId::Id,
-) -> Result<ast::RawTerminator> {
+) -> TransResult<ast::RawTerminator> {
     // Check the arguments
     assert!(region_args.is_empty());
     assert!(type_args.len() == 1);
@@ -424,20 +525,25 @@ pub(crate) fn check_impl_item(impl_item: &rustc_hir::Impl<'_>) {
     assert!(impl_item.defaultness == Defaultness::Final);
     // Note sure what this is about
     assert!(impl_item.constness == Constness::NotConst);
-    assert!(impl_item.of_trait.is_none()); // We don't support traits for now
 }
 
 impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     /// Translate a function's local variables by adding them in the environment.
-    fn translate_body_locals(&mut self, body: &Body<'tcx>) -> Result<()> {
+    fn translate_body_locals(&mut self, body: &Body<'tcx>) -> TransResult<()> {
         // First, retrieve the debug info - we want to retrieve the names
         // of the variables (which otherwise are just referenced with indices).
         // This is mostly to generate a clean and readable translation later on.
-        // It seems the only way of linking the locals to the debug info is
-        // through the spans.
-        let mut span_to_var_name: im::OrdMap<Span, String> = im::OrdMap::new();
+        // We link the locals to the debug info through the places of the
+        // debug info: linking them through the spans is not precise enough
+        // for the desugared code (in the `async` functions for instance, all
+        // the locals introduced for an `.await` share the same span).
+        let mut local_to_var_name: HashMap<mir::Local, String> = HashMap::new();
         for info in &body.var_debug_info {
-            span_to_var_name.insert(info.source_info.span, info.name.to_ident_string());
+            if let mir::VarDebugInfoContents::Place(place) = &info.value {
+                if place.projection.is_empty() && info.references == 0 {
+                    local_to_var_name.insert(place.local, info.name.to_ident_string());
+                }
+            }
         }
 
         // Translate the parameters
@@ -449,8 +555,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             );
 
             // Find the name of the variable
-            let span = var.source_info.span;
-            let name: Option<String> = span_to_var_name.get(&span).cloned();
+            let name: Option<String> = local_to_var_name.get(&index).cloned();
 
             // Translate the type
             let ty = self.translate_ety(&var.ty)?;
@@ -466,7 +571,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     ///
     /// The local variables should already have been translated and inserted in
     /// the context.
-    fn translate_transparent_expression_body(&mut self, body: &Body<'tcx>) -> Result<()> {
+    fn translate_transparent_expression_body(&mut self, body: &Body<'tcx>) -> TransResult<()> {
         trace!();
 
         let id = self.translate_basic_block(body, START_BLOCK)?;
@@ -479,7 +584,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         body: &Body<'tcx>,
         block_id: BasicBlock,
-    ) -> Result<ast::BlockId::Id> {
+    ) -> TransResult<ast::BlockId::Id> {
         // Check if the block has already been translated
         if let Some(id) = self.blocks_map.get(&block_id) {
             return Ok(*id);
@@ -501,14 +606,24 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             }
         }
 
-        // Translate the terminator
+        // Translate the terminator. The calls to `from_raw_parts` become an
+        // assignment followed by a goto.
         let terminator = block.terminator();
-        let terminator = self.translate_terminator(body, terminator)?;
+        let terminator = match self.translate_ptr_from_raw_parts(body, terminator)? {
+            Some((statement, terminator)) => {
+                statements.push(statement);
+                terminator
+            }
+            None => self.translate_terminator(body, &block.statements, terminator)?,
+        };
 
         // Insert the block in the translated blocks
+        // The loop headers are computed once the body is translated (see
+        // [crate::detect_loops])
         let block = ast::BlockData {
             statements,
             terminator,
+            is_loop_header: false,
         };
 
         self.push_block(nid, block);
@@ -517,17 +632,20 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     }
 
     /// Translate a place and return its type
-    fn translate_place_with_type(&mut self, place: &Place<'tcx>) -> (e::Place, ty::ETy) {
+    fn translate_place_with_type(
+        &mut self,
+        place: &Place<'tcx>,
+    ) -> TransResult<(e::Place, ty::ETy)> {
         let var_id = self.get_local(&place.local).unwrap();
         let var = self.get_var_from_id(var_id).unwrap();
-        let (projection, ty) = self.translate_projection(var.ty.clone(), place.projection);
+        let (projection, ty) = self.translate_projection((*var.ty).clone(), place.projection)?;
 
-        (e::Place { var_id, projection }, ty)
+        Ok((e::Place { var_id, projection }, ty))
     }
 
     /// Translate a place
-    fn translate_place(&mut self, place: &Place<'tcx>) -> e::Place {
-        self.translate_place_with_type(place).0
+    fn translate_place(&mut self, place: &Place<'tcx>) -> TransResult<e::Place> {
+        Ok(self.translate_place_with_type(place)?.0)
     }
 
     /// Translate a projection
@@ -544,7 +662,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         var_ty: ty::ETy,
         rprojection: &rustc_middle::ty::List<PlaceElem<'tcx>>,
-    ) -> (e::Projection, ty::ETy) {
+    ) -> TransResult<(e::Projection, ty::ETy)> {
         trace!("- projection: {:?}\n- var_ty: {:?}", rprojection, var_ty);
 
         // We need to track the type of the value we look at, while exploring the path.
@@ -570,7 +688,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                         }
                         ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::Box), regions, tys, cgs) => {
                             // This case only happens in some MIR levels
-                            assert!(!boxes_are_desugared(self.t_ctx.mir_level));
+                            assert!(!boxes_are_desugared(self.mir_level));
                             assert!(regions.is_empty());
                             assert!(tys.len() == 1);
                             assert!(cgs.is_empty());
@@ -592,10 +710,15 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                     // same time.
                     let proj_elem = match path_type {
                         ty::Ty::Adt(ty::TypeId::Adt(type_id), _regions, _tys, _cgs) => {
-                            path_type = self.translate_ety(&field_ty).unwrap();
+                            path_type = self.translate_ety(&field_ty)?;
 
-                            let proj_kind = e::FieldProjKind::Adt(type_id, downcast_id);
-                            e::ProjectionElem::Field(proj_kind, field_id)
+                            if self.t_ctx.type_decl_is_union(type_id) {
+                                assert!(downcast_id.is_none());
+                                e::ProjectionElem::UnionField(type_id, field_id)
+                            } else {
+                                let proj_kind = e::FieldProjKind::Adt(type_id, downcast_id);
+                                e::ProjectionElem::Field(proj_kind, field_id)
+                            }
                         }
                         ty::Ty::Adt(ty::TypeId::Tuple, regions, tys, cgs) => {
                             assert!(regions.is_empty());
@@ -623,6 +746,17 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                             let proj_kind = e::FieldProjKind::Option(variant_id);
                             e::ProjectionElem::Field(proj_kind, field_id)
                         }
+                        ty::Ty::Adt(ty::TypeId::Assumed(aty), regions, _tys, cgs)
+                            if aty.is_range_type() =>
+                        {
+                            assert!(regions.is_empty());
+                            assert!(cgs.is_empty());
+                            assert!(downcast_id.is_none());
+                            assert!(field_id.to_usize() < assumed::range_field_names(aty).len());
+                            path_type = self.translate_ety(&field_ty)?;
+                            let proj_kind = e::FieldProjKind::Range(aty);
+                            e::ProjectionElem::Field(proj_kind, field_id)
+                        }
                         ty::Ty::Adt(ty::TypeId::Assumed(aty), regions, tys, cgs)
                             if aty == ty::AssumedTy::Box
                                 || aty == ty::AssumedTy::PtrUnique
@@ -635,7 +769,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                             // (x:std::ptr::Unique<T>).0: std::ptr::NonNull<T>
                             // (x:std::ptr::NonNull<T>).0: *const T // raw pointer
                             // ```
-                            assert!(!aty.is_box() || boxes_are_desugared(self.t_ctx.mir_level));
+                            assert!(!aty.is_box() || boxes_are_desugared(self.mir_level));
 
                             // Some more sanity checks
                             assert!(regions.is_empty());
@@ -704,24 +838,62 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                         unreachable!("ProjectionElem::Index, path_type:\n{:?}", path_type)
                     }
                 },
+                // The constant indices and the subslices are introduced by
+                // the slice patterns (`[a, b, rest @ ..]`). Note that the
+                // length of the slice is checked beforehand, which is why we
+                // don't need to keep `min_length`.
                 mir::ProjectionElem::ConstantIndex {
-                    offset: _,
+                    offset,
                     min_length: _,
-                    from_end: _,
-                } => {
-                    // This doesn't seem to occur in MIR built
-                    unimplemented!();
-                }
-                mir::ProjectionElem::Subslice {
-                    from: _,
-                    to: _,
-                    from_end: _,
-                } => {
-                    // This doesn't seem to occur in MIR built
-                    unimplemented!();
+                    from_end,
+                } => match &path_type {
+                    ty::Ty::Adt(
+                        ty::TypeId::Assumed(ty::AssumedTy::Array | ty::AssumedTy::Slice),
+                        _,
+                        tys,
+                        _,
+                    ) => {
+                        assert!(tys.len() == 1);
+                        projection.push(e::ProjectionElem::ConstantIndex { offset, from_end });
+                        path_type = tys[0].clone();
+                    }
+                    _ => {
+                        unreachable!("ProjectionElem::ConstantIndex, path_type:\n{:?}", path_type)
+                    }
+                },
+                mir::ProjectionElem::Subslice { from, to, from_end } => {
+                    // The type of the subslice: slices give slices, and
+                    // arrays give arrays (whose length is statically known)
+                    path_type = match (&path_type, from_end) {
+                        (ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::Slice), ..), true) => {
+                            path_type.clone()
+                        }
+                        (
+                            ty::Ty::Adt(ty::TypeId::Assumed(ty::AssumedTy::Array), regions, tys, _),
+                            false,
+                        ) => {
+                            let cg = ty::ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(
+                                self.t_ctx.pointer_size,
+                                to - from,
+                            )));
+                            ty::Ty::Adt(
+                                ty::TypeId::Assumed(ty::AssumedTy::Array),
+                                regions.clone(),
+                                tys.clone(),
+                                vec![cg],
+                            )
+                        }
+                        _ => {
+                            unreachable!("ProjectionElem::Subslice, path_type:\n{:?}", path_type)
+                        }
+                    };
+                    projection.push(e::ProjectionElem::Subslice { from, to, from_end });
                 }
                 mir::ProjectionElem::OpaqueCast(_) => {
-                    unimplemented!();
+                    return Err(Box::new(TranslationError::UnsupportedExpression {
+                        span: self.def_meta(),
+                        kind: "cast of a place to an opaque type".to_string(),
+                    }));
                 }
                 mir::ProjectionElem::Downcast(_, variant_id) => {
                     // Downcast an enum to a specific variant.
@@ -739,35 +911,35 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             }
         }
 
-        (projection, path_type)
+        Ok((projection, path_type))
     }
 
     /// Translate an operand with its type
     fn translate_operand_with_type(
         &mut self,
         operand: &mir::Operand<'tcx>,
-    ) -> (e::Operand, ty::ETy) {
+    ) -> TransResult<(e::Operand, ty::ETy)> {
         trace!();
         match operand {
             Operand::Copy(place) => {
-                let (p, ty) = self.translate_place_with_type(place);
-                (e::Operand::Copy(p), ty)
+                let (p, ty) = self.translate_place_with_type(place)?;
+                Ok((e::Operand::Copy(p), ty))
             }
             Operand::Move(place) => {
-                let (p, ty) = self.translate_place_with_type(place);
-                (e::Operand::Move(p), ty)
+                let (p, ty) = self.translate_place_with_type(place)?;
+                Ok((e::Operand::Move(p), ty))
             }
             Operand::Constant(constant) => {
-                let (ty, constant) = self.translate_operand_constant(constant);
-                (e::Operand::Const(ty.clone(), constant), ty)
+                let (ty, constant) = self.translate_operand_constant(constant)?;
+                Ok((e::Operand::Const(ty.clone(), constant), ty))
             }
         }
     }
 
     /// Translate an operand
-    fn translate_operand(&mut self, operand: &mir::Operand<'tcx>) -> e::Operand {
+    fn translate_operand(&mut self, operand: &mir::Operand<'tcx>) -> TransResult<e::Operand> {
         trace!();
-        self.translate_operand_with_type(operand).0
+        Ok(self.translate_operand_with_type(operand)?.0)
     }
 
     /// Translate an operand which should be `move b.0` where `b` is a box (such
@@ -815,45 +987,42 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     }
 
     /// Translate an rvalue
-    fn translate_rvalue(&mut self, rvalue: &mir::Rvalue<'tcx>) -> e::Rvalue {
+    fn translate_rvalue(&mut self, rvalue: &mir::Rvalue<'tcx>) -> TransResult<e::Rvalue> {
         use std::ops::Deref;
-        match rvalue {
-            mir::Rvalue::Use(operand) => e::Rvalue::Use(self.translate_operand(operand)),
+        Ok(match rvalue {
+            mir::Rvalue::Use(operand) => e::Rvalue::Use(self.translate_operand(operand)?),
             mir::Rvalue::CopyForDeref(place) => {
                 // According to the documentation, it seems to be an optimisation
                 // for drop elaboration. We treat it as a regular copy.
-                let place = self.translate_place(place);
+                let place = self.translate_place(place)?;
                 e::Rvalue::Use(e::Operand::Copy(place))
             }
             mir::Rvalue::Repeat(operand, cnst) => {
-                let c = self.translate_const_kind_as_const_generic(*cnst);
-                // We are effectively desugaring the repeat in Charon, turning it into an array literal
-                // where the operand is repeated `cnst` times.
-                // TODO: allow having other kinds of const generics, and desugar later to a function call
-                let cv = *c.as_value().as_scalar().as_usize();
-                let (operand, t) = self.translate_operand_with_type(operand);
-                let mut operands = Vec::with_capacity(cv as usize);
-                for _ in 0..cv {
-                    operands.push(operand.clone());
-                }
-                // We *have* to desugar here; we don't have enough context (the destination place, the
-                // lifetime variable) to translate this into a built-in function call. This is why we
-                // don't have a ArrayRepeat AssumedFunId.
-                e::Rvalue::Aggregate(e::AggregateKind::Array(t, c), operands)
+                // We don't desugar the repeat to an array literal where the
+                // operand is repeated `cnst` times: the length may be a const
+                // generic (`[0u8; N]`).
+                let c = self.translate_const_kind_as_const_generic(*cnst)?;
+                let (operand, t) = self.translate_operand_with_type(operand)?;
+                e::Rvalue::Aggregate(e::AggregateKind::Repeat(t, operand, c), Vec::new())
             }
             mir::Rvalue::Ref(_region, borrow_kind, place) => {
-                let place = self.translate_place(place);
+                let place = self.translate_place(place)?;
                 let borrow_kind = translate_borrow_kind(*borrow_kind);
                 e::Rvalue::Ref(place, borrow_kind)
             }
             mir::Rvalue::ThreadLocalRef(_) => {
                 unreachable!();
             }
-            mir::Rvalue::AddressOf(_, _) => {
-                unreachable!();
+            mir::Rvalue::AddressOf(mtbl, place) => {
+                let place = self.translate_place(place)?;
+                let kind = match mtbl {
+                    rustc_ast::Mutability::Not => ty::RefKind::Shared,
+                    rustc_ast::Mutability::Mut => ty::RefKind::Mut,
+                };
+                e::Rvalue::RawPtr(place, kind)
             }
             mir::Rvalue::Len(place) => {
-                let (place, ty) = self.translate_place_with_type(place);
+                let (place, ty) = self.translate_place_with_type(place)?;
                 let cg = match &ty {
                     ty::Ty::Adt(
                         ty::TypeId::Assumed(aty @ (ty::AssumedTy::Array | ty::AssumedTy::Slice)),
@@ -873,14 +1042,21 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             }
             mir::Rvalue::Cast(cast_kind, operand, tgt_ty) => {
                 trace!("Rvalue::Cast: {:?}", rvalue);
-                // Put aside the pointer casts (which we don't support), I think
-                // casts should only be from integers/booleans to integer/booleans.
+                // Put aside the pointer casts, I think casts should only be from
+                // integers/booleans to integer/booleans.
 
                 // Translate the target type
-                let tgt_ty = self.translate_ety(tgt_ty).unwrap();
+                let tgt_ty = self.translate_ety(tgt_ty)?;
+
+                // A function coerced to a function pointer: we can't translate
+                // the operand, whose type is a function item type
+                if let rustc_middle::mir::CastKind::Pointer(PointerCast::ReifyFnPointer) = cast_kind
+                {
+                    return self.translate_reify_fn_pointer(operand);
+                }
 
                 // Translate the operand
-                let (op, src_ty) = self.translate_operand_with_type(operand);
+                let (op, src_ty) = self.translate_operand_with_type(operand)?;
 
                 match (cast_kind, &src_ty, &tgt_ty) {
                     (rustc_middle::mir::CastKind::IntToInt, _, _) => {
@@ -890,6 +1066,31 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
 
                         e::Rvalue::UnaryOp(e::UnOp::Cast(src_ty, tgt_ty), op)
                     }
+                    (
+                        rustc_middle::mir::CastKind::PtrToPtr
+                        | rustc_middle::mir::CastKind::Pointer(PointerCast::MutToConstPointer),
+                        ty::Ty::RawPtr(..),
+                        ty::Ty::RawPtr(..),
+                    )
+                    | (
+                        rustc_middle::mir::CastKind::FnPtrToPtr,
+                        ty::Ty::FnPtr(..),
+                        ty::Ty::RawPtr(..),
+                    ) => e::Rvalue::UnaryOp(e::UnOp::PtrCast(e::PtrCastKind::PtrToPtr), op),
+                    (
+                        rustc_middle::mir::CastKind::PointerFromExposedAddress,
+                        ty::Ty::Literal(ty::LiteralTy::Integer(src_ty)),
+                        ty::Ty::RawPtr(..),
+                    ) => {
+                        e::Rvalue::UnaryOp(e::UnOp::PtrCast(e::PtrCastKind::IntToPtr(*src_ty)), op)
+                    }
+                    (
+                        rustc_middle::mir::CastKind::PointerExposeAddress,
+                        ty::Ty::RawPtr(..),
+                        ty::Ty::Literal(ty::LiteralTy::Integer(tgt_ty)),
+                    ) => {
+                        e::Rvalue::UnaryOp(e::UnOp::PtrCast(e::PtrCastKind::PtrToInt(*tgt_ty)), op)
+                    }
                     (
                         rustc_middle::mir::CastKind::Pointer(PointerCast::Unsize),
                         ty::Ty::Ref(_, t1, kind1),
@@ -934,8 +1135,8 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 let (left, right) = operands.deref();
                 e::Rvalue::BinaryOp(
                     translate_binaryop_kind(*binop),
-                    self.translate_operand(left),
-                    self.translate_operand(right),
+                    self.translate_operand(left)?,
+                    self.translate_operand(right)?,
                 )
             }
             mir::Rvalue::NullaryOp(nullop, _ty) => {
@@ -946,10 +1147,10 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             }
             mir::Rvalue::UnaryOp(unop, operand) => e::Rvalue::UnaryOp(
                 translate_unaryop_kind(*unop),
-                self.translate_operand(operand),
+                self.translate_operand(operand)?,
             ),
             mir::Rvalue::Discriminant(place) => {
-                e::Rvalue::Discriminant(self.translate_place(place))
+                e::Rvalue::Discriminant(self.translate_place(place)?)
             }
             mir::Rvalue::Aggregate(aggregate_kind, operands) => {
                 // It seems this instruction is not present in certain passes:
@@ -972,15 +1173,24 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 let operands_t: Vec<e::Operand> = operands
                     .iter()
                     .map(|op| self.translate_operand(op))
-                    .collect();
+                    .collect::<TransResult<_>>()?;
 
                 match aggregate_kind.deref() {
                     mir::AggregateKind::Array(ty) => {
-                        let t_ty = self.translate_ety(&ty).unwrap();
+                        let t_ty = self.translate_ety(ty)?;
                         let cg = ty::ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(
+                            self.t_ctx.pointer_size,
                             operands_t.len() as u64,
                         )));
-                        e::Rvalue::Aggregate(e::AggregateKind::Array(t_ty, cg), operands_t)
+                        match collapse_array_operands(&operands_t) {
+                            Option::Some(op) => e::Rvalue::Aggregate(
+                                e::AggregateKind::Repeat(t_ty, op, cg),
+                                Vec::new(),
+                            ),
+                            Option::None => {
+                                e::Rvalue::Aggregate(e::AggregateKind::Array(t_ty, cg), operands_t)
+                            }
+                        }
                     }
                     mir::AggregateKind::Tuple => {
                         e::Rvalue::Aggregate(e::AggregateKind::Tuple, operands_t)
@@ -994,53 +1204,68 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                     ) => {
                         trace!("{:?}", rvalue);
 
-                        // Not sure what those two parameters are used for, so
-                        // panicking if they are not none (to catch a use case).
-                        // I'm not even sure that "field_index" is a proper name:
-                        // the documentation seems outdated (it says the 4th parameter
-                        // is a field index, while it makes more sense for it to be
-                        // the 5th, and I don't know how I should use it anyway).
+                        // Not sure what the user annotation is used for, so
+                        // panicking if it is not none (to catch a use case).
+                        // The field index is only used for the unions: it gives
+                        // the field which is initialized (we check this below).
                         assert!(user_annotation.is_none());
-                        assert!(field_index.is_none());
 
                         // Translate the substitution
-                        let (region_params, mut type_params, cg_params) = self
-                            .translate_subst_generic_args_in_body(None, substs)
-                            .unwrap();
-
-                        if adt_id.is_local() {
-                            assert!(!self.t_ctx.id_is_opaque(*adt_id));
-
-                            // Local ADT: translate the id
-                            let id_t = self.translate_type_decl_id(*adt_id);
+                        let (region_params, mut type_params, cg_params) =
+                            self.translate_subst_generic_args_in_body(None, substs)?;
+
+                        // The external ADTs which don't have a primitive
+                        // support (`Ordering`, `Result`, etc.) are translated
+                        // like the local ADTs
+                        if let ty::TypeId::Adt(id_t) = self.translate_type_id(*adt_id) {
+                            if adt_id.is_local() {
+                                assert!(!self.t_ctx.id_is_opaque(*adt_id));
+                            }
 
                             let kind = self.t_ctx.tcx.adt_def(adt_id).adt_kind();
-                            let variant_id = match kind {
-                                rustc_middle::ty::AdtKind::Struct => Option::None,
+                            if kind == rustc_middle::ty::AdtKind::Struct {
+                                if let Some(akind) = translate_struct_update(id_t, &operands_t) {
+                                    return Ok(e::Rvalue::Aggregate(akind, Vec::new()));
+                                }
+                            }
+                            let akind = match kind {
+                                rustc_middle::ty::AdtKind::Struct => {
+                                    assert!(field_index.is_none());
+                                    e::AggregateKind::Adt(
+                                        id_t,
+                                        Option::None,
+                                        region_params,
+                                        type_params,
+                                        cg_params,
+                                    )
+                                }
                                 rustc_middle::ty::AdtKind::Enum => {
+                                    assert!(field_index.is_none());
                                     let variant_id = translate_variant_id(*variant_idx);
-                                    Some(variant_id)
+                                    e::AggregateKind::Adt(
+                                        id_t,
+                                        Some(variant_id),
+                                        region_params,
+                                        type_params,
+                                        cg_params,
+                                    )
                                 }
                                 rustc_middle::ty::AdtKind::Union => {
-                                    unimplemented!();
+                                    let field_id = translate_field_id(field_index.unwrap());
+                                    e::AggregateKind::Union(
+                                        id_t,
+                                        field_id,
+                                        region_params,
+                                        type_params,
+                                        cg_params,
+                                    )
                                 }
                             };
 
-                            let akind = e::AggregateKind::Adt(
-                                id_t,
-                                variant_id,
-                                region_params,
-                                type_params,
-                                cg_params,
-                            );
-
                             e::Rvalue::Aggregate(akind, operands_t)
                         } else {
-                            // External ADT.
-                            // Can be `Option`
-                            // TODO: treat all external ADTs in a consistant manner.
-                            // For instance, we can access the variants of any external
-                            // enumeration marked as `public`.
+                            // External ADT with a primitive support.
+                            // Can be `Option`, a range or `PhantomData`
                             let name = type_def_id_to_name(self.t_ctx.tcx, *adt_id);
                             if name.equals_ref_name(&assumed::OPTION_NAME) {
                                 // Sanity checks
@@ -1063,13 +1288,25 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                                 );
 
                                 e::Rvalue::Aggregate(akind, operands_t)
-                            } else if name.equals_ref_name(&assumed::RANGE_NAME) {
+                            } else if let Some(aty) = assumed::get_type_id_from_name(&name)
+                                .filter(|aty| aty.is_range_type())
+                            {
                                 // Sanity checks
                                 assert!(region_params.is_empty());
                                 // Ranges are parametric over the type of indices
-                                assert!(type_params.len() == 1);
+                                // (except `RangeFull`, which has no fields)
+                                assert!(type_params.len() <= 1);
+                                assert!(operands_t.len() == assumed::range_field_names(aty).len());
                                 e::Rvalue::Aggregate(
-                                    e::AggregateKind::Range(type_params.pop().unwrap()),
+                                    e::AggregateKind::Range(aty, type_params),
+                                    operands_t,
+                                )
+                            } else if name.equals_ref_name(&assumed::PHANTOM_DATA_NAME) {
+                                // `PhantomData {}` (`PhantomData` is usually
+                                // a constant)
+                                assert!(operands_t.is_empty());
+                                e::Rvalue::Aggregate(
+                                    e::AggregateKind::PhantomData(type_params.pop().unwrap()),
                                     operands_t,
                                 )
                             } else {
@@ -1077,28 +1314,63 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                             }
                         }
                     }
-                    mir::AggregateKind::Closure(_def_id, _subst) => {
-                        unimplemented!();
+                    mir::AggregateKind::Closure(def_id, substs) => {
+                        trace!(
+                            "Closure:\n\n- def_id: {:?}\n\n- substs: {:?}",
+                            def_id,
+                            substs
+                        );
+
+                        // The closure body is translated as a separate function
+                        let fid = self.translate_fun_decl_id(*def_id);
+                        let closure = substs.as_closure();
+                        let kind = translate_closure_kind(closure.kind());
+                        let upvar_tys = closure
+                            .upvar_tys()
+                            .map(|ty| self.translate_ety(&ty))
+                            .collect::<TransResult<_>>()?;
+
+                        let akind = e::AggregateKind::Closure(fid, kind, upvar_tys);
+                        e::Rvalue::Aggregate(akind, operands_t)
                     }
-                    mir::AggregateKind::Generator(_def_id, _subst, _movability) => {
-                        unimplemented!();
+                    mir::AggregateKind::Generator(def_id, substs, _movability) => {
+                        trace!(
+                            "Generator:\n\n- def_id: {:?}\n\n- substs: {:?}",
+                            def_id,
+                            substs
+                        );
+
+                        // Similarly to closures, the generator body is translated
+                        // as a separate function
+                        let fid = self.translate_fun_decl_id(*def_id);
+                        let upvar_tys = substs
+                            .as_generator()
+                            .upvar_tys()
+                            .map(|ty| self.translate_ety(&ty))
+                            .collect::<TransResult<_>>()?;
+
+                        let akind = e::AggregateKind::Generator(fid, upvar_tys);
+                        e::Rvalue::Aggregate(akind, operands_t)
                     }
                 }
             }
             mir::Rvalue::ShallowInitBox(_, _) => {
-                unimplemented!();
+                return Err(Box::new(TranslationError::UnsupportedExpression {
+                    span: self.def_meta(),
+                    kind: "shallow initialization of a box".to_string(),
+                }));
             }
-        }
+        })
     }
 
     /// Translate a statement
     ///
-    /// We return an option, because we ignore some statements (`Nop`, `StorageLive`...)
+    /// We return an option, because we ignore some statements (`Nop`, `Retag`...)
     fn translate_statement(
         &mut self,
         body: &Body<'tcx>,
         statement: &Statement<'tcx>,
-    ) -> Result<Option<ast::Statement>> {
+    ) -> TransResult<Option<ast::Statement>> {
         trace!("About to translate statement (MIR) {:?}", statement);
 
         use std::ops::Deref;
@@ -1106,21 +1378,21 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         let t_statement: Option<ast::RawStatement> = match &statement.kind {
             StatementKind::Assign(assign) => {
                 let (place, rvalue) = assign.deref();
-                let t_place = self.translate_place(place);
-                let t_rvalue = self.translate_rvalue(rvalue);
+                let t_place = self.translate_place(place)?;
+                let t_rvalue = self.translate_rvalue(rvalue)?;
 
                 Some(ast::RawStatement::Assign(t_place, t_rvalue))
             }
             StatementKind::FakeRead(info) => {
                 let (_read_cause, place) = info.deref();
-                let t_place = self.translate_place(place);
+                let t_place = self.translate_place(place)?;
 
                 Some(ast::RawStatement::FakeRead(t_place))
             }
             StatementKind::PlaceMention(place) => {
                 // Simply accesses a place. Introduced for instance in place
                 // of `let _ = ...`. We desugar it to a fake read.
-                let t_place = self.translate_place(place);
+                let t_place = self.translate_place(place)?;
 
                 Some(ast::RawStatement::FakeRead(t_place))
             }
@@ -1128,13 +1400,19 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 place,
                 variant_index,
             } => {
-                let t_place = self.translate_place(place);
+                let t_place = self.translate_place(place)?;
                 let variant_id = translate_variant_id(*variant_index);
                 Some(ast::RawStatement::SetDiscriminant(t_place, variant_id))
             }
-            StatementKind::StorageLive(_) => {
-                // We ignore StorageLive
-                None
+            StatementKind::StorageLive(local) => {
+                // We ignore StorageLive, unless the user asked for the storage
+                // annotations
+                if self.t_ctx.crate_info.storage_annotations {
+                    let var_id = self.get_local(local).unwrap();
+                    Some(ast::RawStatement::StorageLive(var_id))
+                } else {
+                    None
+                }
             }
             StatementKind::StorageDead(local) => {
                 let var_id = self.get_local(local).unwrap();
@@ -1153,18 +1431,30 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 None
             }
             StatementKind::Coverage(_) => {
-                unimplemented!();
+                return Err(Box::new(TranslationError::UnsupportedStatement {
+                    span: self.t_ctx.translate_meta_from_source_info(
+                        &body.source_scopes,
+                        statement.source_info,
+                    ),
+                    kind: "coverage instrumentation".to_string(),
+                }));
             }
             StatementKind::Nop => {
                 // We ignore this statement
                 None
             }
             StatementKind::Deinit(place) => {
-                let t_place = self.translate_place(place);
+                let t_place = self.translate_place(place)?;
                 Some(ast::RawStatement::Deinit(t_place))
             }
-            StatementKind::Intrinsic(_) => {
-                unimplemented!();
+            StatementKind::Intrinsic(intrinsic) => {
+                return Err(Box::new(TranslationError::UnsupportedStatement {
+                    span: self.t_ctx.translate_meta_from_source_info(
+                        &body.source_scopes,
+                        statement.source_info,
+                    ),
+                    kind: format!("intrinsic {intrinsic:?}"),
+                }));
             }
             StatementKind::ConstEvalCounter => {
                 // See the doc: only used in the interpreter, to check that
@@ -1188,11 +1478,16 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     }
 
     /// Translate a terminator
+    /// Translate a terminator.
+    ///
+    /// We need the statements of the block to retrieve the enumeration whose
+    /// discriminant is read before a switch (see [Self::get_switch_discriminants]).
     fn translate_terminator(
         &mut self,
         body: &Body<'tcx>,
+        statements: &[Statement<'tcx>],
         terminator: &Terminator<'tcx>,
-    ) -> Result<ast::Terminator> {
+    ) -> TransResult<ast::Terminator> {
         trace!("About to translate terminator (MIR) {:?}", terminator);
 
         // Compute the meta information beforehand (we might need it to introduce
@@ -1208,31 +1503,52 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 ast::RawTerminator::Goto { target }
             }
             TerminatorKind::SwitchInt { discr, targets } => {
+                // If we switch over the discriminant of an enumeration,
+                // retrieve the discriminants of its variants
+                let variants = self.get_switch_discriminants(body, statements, discr);
+
                 // Translate the operand which gives the discriminant
-                let (discr, discr_ty) = self.translate_operand_with_type(discr);
+                let (discr, discr_ty) = self.translate_operand_with_type(discr)?;
 
                 // Translate the switch targets
-                let targets = self.translate_switch_targets(body, &discr_ty, targets)?;
+                let targets =
+                    self.translate_switch_targets(body, &discr_ty, targets, variants.as_ref())?;
 
                 ast::RawTerminator::Switch { discr, targets }
             }
             TerminatorKind::Resume => {
-                // This is used to correctly unwind. We shouldn't get there: if
-                // we panic, the state gets stuck.
-                unreachable!();
+                // This is used to correctly unwind. We only reach the cleanup
+                // blocks through the unwinding paths of the drops (see
+                // below): at the end of the cleanup, the state gets stuck.
+                ast::RawTerminator::Panic
             }
             TerminatorKind::Return => ast::RawTerminator::Return,
             TerminatorKind::Unreachable => ast::RawTerminator::Unreachable,
-            TerminatorKind::Terminate => unimplemented!(),
+            TerminatorKind::Terminate => {
+                // We only find those in the cleanup blocks: we abort because
+                // we panicked while unwinding
+                ast::RawTerminator::Panic
+            }
             TerminatorKind::Drop {
                 place,
                 target,
-                unwind: _, // We consider that panic is an error, and don't model unwinding
+                unwind,
                 replace: _,
-            } => ast::RawTerminator::Drop {
-                place: self.translate_place(place),
-                target: self.translate_basic_block(body, *target)?,
-            },
+            } => {
+                // The drop may call a user-defined `drop` method, which may
+                // panic: we keep track of the cleanup block
+                let unwind = match unwind {
+                    UnwindAction::Cleanup(bid) => Some(self.translate_basic_block(body, *bid)?),
+                    UnwindAction::Continue
+                    | UnwindAction::Unreachable
+                    | UnwindAction::Terminate => None,
+                };
+                ast::RawTerminator::Drop {
+                    place: self.translate_place(place)?,
+                    target: self.translate_basic_block(body, *target)?,
+                    unwind,
+                }
+            }
             TerminatorKind::Call {
                 func,
                 args,
@@ -1252,7 +1568,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 target,
                 unwind: _, // We consider that panic is an error, and don't model unwinding
             } => {
-                let cond = self.translate_operand(cond);
+                let cond = self.translate_operand(cond)?;
                 let target = self.translate_basic_block(body, *target)?;
                 ast::RawTerminator::Assert {
                     cond,
@@ -1261,16 +1577,26 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 }
             }
             TerminatorKind::Yield {
-                value: _,
-                resume: _,
-                resume_arg: _,
-                drop: _,
+                value,
+                resume,
+                resume_arg,
+                drop,
             } => {
-                unimplemented!();
-            }
-            TerminatorKind::GeneratorDrop => {
-                unimplemented!();
+                let value = self.translate_operand(value)?;
+                let resume = self.translate_basic_block(body, *resume)?;
+                let resume_place = self.translate_place(resume_arg)?;
+                let drop_target = match drop {
+                    Option::Some(drop) => Option::Some(self.translate_basic_block(body, *drop)?),
+                    Option::None => Option::None,
+                };
+                ast::RawTerminator::Yield {
+                    value,
+                    resume,
+                    resume_place,
+                    drop_target,
+                }
             }
+            TerminatorKind::GeneratorDrop => ast::RawTerminator::GeneratorDrop,
             TerminatorKind::FalseEdge {
                 real_target,
                 imaginary_target,
@@ -1309,12 +1635,54 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     }
 
     /// Translate switch targets
+    /// If the operand of a switch is the discriminant of an enumeration,
+    /// read by the last statement of the block which assigns it, return the
+    /// map from the discriminants of the variants (as given by the switch
+    /// values) to the variant indices.
+    ///
+    /// The discriminants may be given by the user (`enum E { A = 3, B = 5 }`),
+    /// and may be negative (as with `core::cmp::Ordering`).
+    fn get_switch_discriminants(
+        &self,
+        body: &Body<'tcx>,
+        statements: &[Statement<'tcx>],
+        discr: &Operand<'tcx>,
+    ) -> Option<HashMap<u128, VariantId::Id>> {
+        use std::ops::Deref;
+        let local = discr.place()?.as_local()?;
+        let rvalue = statements.iter().rev().find_map(|st| match &st.kind {
+            StatementKind::Assign(assign) if assign.deref().0.as_local() == Some(local) => {
+                Some(&assign.deref().1)
+            }
+            _ => None,
+        })?;
+        let place = match rvalue {
+            mir::Rvalue::Discriminant(place) => place,
+            _ => return None,
+        };
+        let tcx = self.t_ctx.tcx;
+        match place.ty(body, tcx).ty.kind() {
+            TyKind::Adt(adt, _) if adt.is_enum() => Some(
+                adt.discriminants(tcx)
+                    .map(|(variant_idx, discr)| (discr.val, translate_variant_id(variant_idx)))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Translate the targets of a switch.
+    ///
+    /// If we switch over the discriminant of an enumeration, `variants` maps
+    /// the discriminants to the variant indices: the translated switch values
+    /// are the variant indices (see [crate::remove_read_discriminant]).
     fn translate_switch_targets(
         &mut self,
         body: &Body<'tcx>,
         switch_ty: &ty::ETy,
         targets: &mir::SwitchTargets,
-    ) -> Result<ast::SwitchTargets> {
+        variants: Option<&HashMap<u128, VariantId::Id>>,
+    ) -> TransResult<ast::SwitchTargets> {
         trace!("targets: {:?}", targets);
         let targets_vec: Vec<(u128, BasicBlock)> = targets.iter().map(|(v, b)| (v, b)).collect();
 
@@ -1338,9 +1706,13 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 // Convert all the test values to the proper values.
                 let mut targets_map: Vec<(v::ScalarValue, ast::BlockId::Id)> = Vec::new();
                 for (v, tgt) in targets_vec {
+                    let v = match variants {
+                        Some(variants) => variants.get(&v).unwrap().to_usize() as u128,
+                        None => v,
+                    };
                     // We need to reinterpret the bytes (`v as i128` is not correct)
                     let raw: [u8; 16] = v.to_le_bytes();
-                    let v = v::ScalarValue::from_le_bytes(*int_ty, raw);
+                    let v = v::ScalarValue::from_le_bytes(self.t_ctx.pointer_size, *int_ty, raw);
                     let tgt = self.translate_basic_block(body, tgt)?;
                     targets_map.push((v, tgt));
                 }
@@ -1359,6 +1731,90 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         }
     }
 
+    /// Translate the coercion of a function to a function pointer
+    /// (`CastKind::Pointer(ReifyFnPointer)`). The operand is a constant whose
+    /// type is the function item type (`FnDef`).
+    fn translate_reify_fn_pointer(&mut self, operand: &Operand<'tcx>) -> TransResult<e::Rvalue> {
+        let tcx = self.t_ctx.tcx;
+        let (def_id, substs) = match get_function_from_operand(operand) {
+            Option::Some(f) => f,
+            Option::None => {
+                return Err(Box::new(TranslationError::UnsupportedConstant {
+                    span: self.def_meta(),
+                    kind: format!("function pointer: {operand:?}"),
+                }))
+            }
+        };
+
+        // We don't support pointers to trait methods (we would need to resolve
+        // the method) nor to primitive functions (they are not translated)
+        let name = function_def_id_to_name(tcx, def_id);
+        let (_, type_args, const_generic_args) =
+            self.translate_subst_generic_args_in_body(None, substs)?;
+        let is_prim =
+            !def_id.is_local() && assumed::get_fun_id_from_name(&name, &type_args).is_some();
+        if tcx.trait_of_item(def_id).is_some() || is_prim {
+            return Err(Box::new(TranslationError::UnsupportedConstant {
+                span: self.def_meta(),
+                kind: format!("function pointer to {name}"),
+            }));
+        }
+
+        let fid = self.translate_fun_decl_id(def_id);
+        Ok(e::Rvalue::FnPtr(fid, type_args, const_generic_args))
+    }
+
+    /// Translate a call to `core::ptr::from_raw_parts` (or `from_raw_parts_mut`)
+    /// to the assignment of a raw pointer aggregate, followed by a goto.
+    /// Return `None` if the terminator is not such a call.
+    ///
+    /// The recent versions of rustc directly introduce raw pointer aggregates
+    /// (`AggregateKind::RawPtr`) in the MIR, as the bodies of those functions:
+    /// the generated code is the same.
+    fn translate_ptr_from_raw_parts(
+        &mut self,
+        body: &mir::Body<'tcx>,
+        terminator: &Terminator<'tcx>,
+    ) -> TransResult<Option<(ast::Statement, ast::Terminator)>> {
+        let (func, args, destination, target) = match &terminator.kind {
+            TerminatorKind::Call {
+                func: func @ Operand::Constant(_),
+                args,
+                destination,
+                target: Some(target),
+                ..
+            } => (func, args, destination, *target),
+            _ => return Ok(None),
+        };
+        let (def_id, substs) = match get_function_from_operand(func) {
+            Option::Some(f) => f,
+            Option::None => return Ok(None),
+        };
+        let name = function_def_id_to_name(self.t_ctx.tcx, def_id);
+        let kind = if name.equals_ref_name(&assumed::PTR_FROM_RAW_PARTS_NAME) {
+            ty::RefKind::Shared
+        } else if name.equals_ref_name(&assumed::PTR_FROM_RAW_PARTS_MUT_NAME) {
+            ty::RefKind::Mut
+        } else {
+            return Ok(None);
+        };
+        // The operands are the data pointer and the metadata
+        assert!(args.len() == 2);
+
+        let meta = self
+            .t_ctx
+            .translate_meta_from_source_info(&body.source_scopes, terminator.source_info);
+        let pointee_ty = self.translate_ety(&substs.type_at(0))?;
+        let operands = self.translate_arguments(None, args)?;
+        let dest = self.translate_place(destination)?;
+        let target = self.translate_basic_block(body, target)?;
+
+        let rvalue = e::Rvalue::Aggregate(e::AggregateKind::RawPtr(pointee_ty, kind), operands);
+        let statement = ast::Statement::new(meta, ast::RawStatement::Assign(dest, rvalue));
+        let terminator = ast::Terminator::new(meta, ast::RawTerminator::Goto { target });
+        Ok(Some((statement, terminator)))
+    }
+
     /// Translate a function call statement.
     /// Note that `body` is the body of the function being translated, not of the
     /// function referenced in the function call: we need it in order to translate
@@ -1370,7 +1826,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         args: &Vec<Operand<'tcx>>,
         destination: &Place<'tcx>,
         target: &Option<BasicBlock>,
-    ) -> Result<ast::RawTerminator> {
+    ) -> TransResult<ast::RawTerminator> {
         trace!();
 
         // Translate the function operand - should be a constant: we don't
@@ -1379,8 +1835,43 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
 
         let tcx = self.t_ctx.tcx;
 
+        // Indirect call through a function pointer
+        if let Operand::Move(_) | Operand::Copy(_) = func {
+            let next_block = match target {
+                Option::Some(target) => *target,
+                Option::None => {
+                    return Err(Box::new(TranslationError::UnsupportedTerminator {
+                        span: self.def_meta(),
+                        kind: "call to a diverging function pointer".to_string(),
+                    }))
+                }
+            };
+            let lval = self.translate_place(destination)?;
+            let next_block = self.translate_basic_block(body, next_block)?;
+            let call = ast::Call {
+                func: ast::FunId::Ptr(self.translate_operand(func)?),
+                region_args: vec![],
+                type_args: vec![],
+                const_generic_args: vec![],
+                args: self.translate_arguments(None, args)?,
+                dest: lval,
+            };
+            return Ok(ast::RawTerminator::Call {
+                call,
+                target: next_block,
+            });
+        }
+
         // Retrieve the function's identifier and instantiation
-        let (def_id, substs) = get_function_from_operand(func);
+        let (def_id, substs) = match get_function_from_operand(func) {
+            Option::Some(f) => f,
+            Option::None => {
+                return Err(Box::new(TranslationError::UnsupportedTerminator {
+                    span: self.def_meta(),
+                    kind: format!("call to {func:?}"),
+                }))
+            }
+        };
 
         // Translate the name to check if is is `core::panicking::panic`
         let name = function_def_id_to_name(tcx, def_id);
@@ -1400,7 +1891,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             let next_block = target.unwrap();
 
             // Translate the target
-            let lval = self.translate_place(destination);
+            let lval = self.translate_place(destination)?;
             let next_block = self.translate_basic_block(body, next_block)?;
 
             // There is something annoying: when going to MIR, the rust compiler
@@ -1465,7 +1956,27 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                     self.translate_subst_generic_args_in_body(used_type_args, substs)?;
 
                 // Translate the arguments
-                let args = self.translate_arguments(used_args, args);
+                let args = self.translate_arguments(used_args, args)?;
+
+                // Check if the call is dispatched through a trait object
+                if let Some(vtable_index) = get_virtual_call_index(tcx, self.def_id, def_id, substs)
+                {
+                    let trait_id = tcx.trait_of_item(def_id).unwrap();
+                    let trait_name = trait_def_id_to_name(tcx, trait_id);
+                    let method_name = tcx.item_name(def_id).to_string();
+                    let call = ast::Call {
+                        func: ast::FunId::Virtual(trait_name, method_name, vtable_index),
+                        region_args,
+                        type_args,
+                        const_generic_args,
+                        args,
+                        dest: lval,
+                    };
+                    return Ok(ast::RawTerminator::Call {
+                        call,
+                        target: next_block,
+                    });
+                }
 
                 // Check if the function is considered primitive: primitive
                 // functions benefit from special treatment.
@@ -1526,7 +2037,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         used_args: Option<Vec<bool>>,
         substs: &rustc_middle::ty::subst::InternalSubsts<'tcx>,
-    ) -> Result<(Vec<ty::ErasedRegion>, Vec<ty::ETy>, Vec<ty::ConstGeneric>)> {
+    ) -> TransResult<(Vec<ty::ErasedRegion>, Vec<ty::ETy>, Vec<ty::ConstGeneric>)> {
         let substs: Vec<rustc_middle::ty::subst::GenericArg<'tcx>> = match used_args {
             Option::None => substs.iter().collect(),
             Option::Some(used_args) => {
@@ -1553,7 +2064,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                     t_args_regions.push(translate_erased_region(region.kind()));
                 }
                 rustc_middle::ty::subst::GenericArgKind::Const(c) => {
-                    let t_cg = self.translate_const_kind_as_const_generic(c);
+                    let t_cg = self.translate_const_kind_as_const_generic(c)?;
                     t_args_cgs.push(t_cg);
                 }
             }
@@ -1568,7 +2079,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     fn translate_subst_in_body(
         &mut self,
         substs: &rustc_middle::ty::List<rustc_middle::ty::Ty<'tcx>>,
-    ) -> Result<Vec<ty::ETy>> {
+    ) -> TransResult<Vec<ty::ETy>> {
         let mut t_args_tys = Vec::new();
 
         for param in substs.iter() {
@@ -1583,7 +2094,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         used_args: Option<Vec<bool>>,
         args: &Vec<Operand<'tcx>>,
-    ) -> Vec<e::Operand> {
+    ) -> TransResult<Vec<e::Operand>> {
         let args: Vec<&Operand<'tcx>> = match used_args {
             Option::None => args.iter().collect(),
             Option::Some(used_args) => {
@@ -1608,18 +2119,24 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             }
 
             // Translate
-            let op = self.translate_operand(arg);
+            let op = self.translate_operand(arg)?;
             t_args.push(op);
         }
 
-        t_args
+        Ok(t_args)
     }
 
-    fn translate_body(mut self, local_id: LocalDefId, arg_count: usize) -> Result<ast::ExprBody> {
-        let tcx = self.t_ctx.tcx;
-
-        let body = get_mir_for_def_id_and_level(tcx, local_id, self.t_ctx.mir_level);
+    fn translate_body(self, local_id: LocalDefId, arg_count: usize) -> TransResult<ast::ExprBody> {
+        let body = self.t_ctx.get_mir(local_id, None, self.mir_level);
+        self.translate_mir_body(body, arg_count)
+    }
 
+    /// Translate a MIR body (see [Self::translate_body]).
+    fn translate_mir_body(
+        mut self,
+        body: &Body<'tcx>,
+        arg_count: usize,
+    ) -> TransResult<ast::ExprBody> {
         // Compute the meta information
         let meta = self.translate_meta_from_rspan(body.span);
 
@@ -1635,7 +2152,6 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         // We clone things while we could move them...
         let mut blocks = ast::BlockId::Vector::new();
         for (id, block) in self.blocks {
-            use crate::id_vector::ToUsize;
             // Sanity check to make sure we don't mess with the indices
             assert!(id.to_usize() == blocks.len());
             blocks.push_back(block);
@@ -1647,6 +2163,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             arg_count,
             locals: self.vars,
             body: blocks,
+            liveness: None,
         })
     }
 }
@@ -1658,11 +2175,51 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     fn translate_function_signature<'ctx1>(
         &'ctx1 mut self,
         def_id: DefId,
-    ) -> (BodyTransCtx<'tcx, 'ctx1, 'ctx>, ast::FunSig) {
+    ) -> TransResult<(BodyTransCtx<'tcx, 'ctx1, 'ctx>, ast::FunSig)> {
         let tcx = self.tcx;
 
-        // Retrieve the function signature, which includes the lifetimes
-        let signature = tcx.fn_sig(def_id).subst_identity();
+        // Retrieve the function signature, which includes the lifetimes, together
+        // with the "normal" substitution (which lists the function's parameters).
+        // Closures are special: we can't call [TyCtxt::fn_sig] on them, their
+        // substitution contains additional synthetic parameters, and the inputs
+        // of their signature are tupled.
+        let fun_type = tcx.type_of(def_id).subst_identity();
+        let (substs, signature, closure) = match fun_type.kind() {
+            TyKind::FnDef(_def_id, substs_ref) => {
+                let signature = tcx.fn_sig(def_id).subst_identity();
+                (substs_ref.to_vec(), signature, None)
+            }
+            TyKind::Closure(_def_id, substs_ref) => {
+                let closure = substs_ref.as_closure();
+                (
+                    closure.parent_substs().to_vec(),
+                    closure.sig(),
+                    Some(closure),
+                )
+            }
+            TyKind::Generator(_def_id, substs_ref, _movability) => {
+                // Before the MIR state transformation (which happens after the
+                // promoted MIR), the generator body receives the generator state
+                // and the resume argument, and returns the generator's return
+                // value.
+                let generator = substs_ref.as_generator();
+                let signature = tcx.mk_fn_sig(
+                    [fun_type, generator.resume_ty()],
+                    generator.return_ty(),
+                    false,
+                    rustc_hir::Unsafety::Normal,
+                    rustc_target::spec::abi::Abi::Rust,
+                );
+                (
+                    generator.parent_substs().to_vec(),
+                    mir_ty::Binder::dummy(signature),
+                    None,
+                )
+            }
+            _ => {
+                unreachable!()
+            }
+        };
 
         // Instantiate the signature's bound region variables (the signature
         // is wrapped in a [`Binder`](rustc_middle::ty::Binder). This is inspired by
@@ -1698,21 +2255,10 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         // We need a body translation context to keep track of all the variables
         let mut bt_ctx = BodyTransCtx::new(def_id, self);
 
-        // **Sanity checks on the HIR**
-        generics::check_function_generics(tcx, def_id);
-
         // Start by translating the "normal" substitution (which lists the function's
         // parameters). As written above, this substitution contains all the type
         // variables, and the early-bound regions, but not the late-bound ones.
         // TODO: we do something similar in `translate_function`
-        let fun_type = tcx.type_of(def_id).subst_identity();
-        let substs = match fun_type.kind() {
-            TyKind::FnDef(_def_id, substs_ref) => substs_ref,
-            _ => {
-                unreachable!()
-            }
-        };
-
         for param in substs.iter() {
             match param.unpack() {
                 rustc_middle::ty::subst::GenericArgKind::Type(param_ty) => {
@@ -1733,7 +2279,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                 rustc_middle::ty::subst::GenericArgKind::Const(c) => {
                     // The type should be primitive, meaning it shouldn't contain
                     // variables, etc. (we could use an empty context).
-                    let ty = bt_ctx.translate_ety(&c.ty()).unwrap();
+                    let ty = bt_ctx.translate_ety(&c.ty())?;
                     let ty = ty.to_literal();
                     match c.kind() {
                         rustc_middle::ty::ConstKind::Param(cp) => {
@@ -1776,13 +2322,52 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
 
         // Now that we instantiated all the binders and introduced identifiers for
         // all the variables, we can translate the function's signature.
-        let inputs: Vec<ty::RTy> = Vec::from_iter(
-            signature
+        let inputs: Vec<ty::RTy> = match closure {
+            None => signature
                 .inputs()
                 .iter()
-                .map(|ty| bt_ctx.translate_sig_ty(ty).unwrap()),
-        );
-        let output = bt_ctx.translate_sig_ty(&signature.output()).unwrap();
+                .map(|ty| bt_ctx.translate_sig_ty(ty))
+                .collect::<TransResult<Vec<_>>>()?,
+            Some(closure) => {
+                // The closure body receives the closure state as first argument,
+                // then the (untupled) closure arguments.
+                // The regions of the types of the captured variables are
+                // erased: we introduce one region for all of them.
+                if fun_type.has_erased_regions() {
+                    bt_ctx.push_region(mir_ty::RegionKind::ReErased, None);
+                }
+                let state_ty = bt_ctx.translate_sig_ty(&fun_type)?;
+                let state_ty = match closure.kind() {
+                    mir_ty::ClosureKind::FnOnce => state_ty,
+                    kind => {
+                        // The state is borrowed: we introduce a region for the borrow
+                        let region = mir_ty::RegionKind::ReFree(mir_ty::FreeRegion {
+                            scope: def_id,
+                            bound_region: mir_ty::BoundRegionKind::BrEnv,
+                        });
+                        let name = translate_region_name(&region);
+                        let rid = bt_ctx.push_region(region, name);
+                        let ref_kind = if kind == mir_ty::ClosureKind::Fn {
+                            ty::RefKind::Shared
+                        } else {
+                            ty::RefKind::Mut
+                        };
+                        ty::Ty::Ref(ty::Region::Var(rid), Box::new(state_ty), ref_kind)
+                    }
+                };
+
+                let args = match signature.inputs()[0].kind() {
+                    TyKind::Tuple(args) => args,
+                    _ => unreachable!(),
+                };
+                let mut inputs = vec![state_ty];
+                for ty in args.iter() {
+                    inputs.push(bt_ctx.translate_sig_ty(&ty)?);
+                }
+                inputs
+            }
+        };
+        let output = bt_ctx.translate_sig_ty(&signature.output())?;
 
         trace!(
             "# Input variables types:\n{}",
@@ -1790,17 +2375,144 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         );
         trace!("# Output variable type:\n{}", bt_ctx.format_object(&output));
 
+        // Translate the where clauses. If we can't translate them, we simply
+        // ignore them: they are not necessary to use the function.
+        let predicates = match bt_ctx.translate_predicates(def_id) {
+            Ok(predicates) => predicates,
+            Err(err) => {
+                bt_ctx.t_ctx.push_error(*err);
+                Vec::new()
+            }
+        };
+
         let sig = ast::FunSig {
             region_params: bt_ctx.region_vars.clone(),
             num_early_bound_regions: late_bound_regions.len(),
             regions_hierarchy: RegionGroups::new(), // Hierarchy not yet computed
             type_params: bt_ctx.type_vars.clone(),
             const_generic_params: bt_ctx.const_generic_vars.clone(),
+            predicates,
             inputs,
             output,
         };
 
-        (bt_ctx, sig)
+        Ok((bt_ctx, sig))
+    }
+
+    /// If a function is a method defined in an impl block, retrieve the type
+    /// for which the block is defined (if it is an ADT) and, for the trait
+    /// impls, the implemented trait. If the function is a method of a trait,
+    /// retrieve the trait.
+    fn translate_method_impl_info(
+        &mut self,
+        rust_id: DefId,
+    ) -> (Option<ty::TypeId>, Option<ast::TraitDeclId::Id>) {
+        if let Option::Some(trait_rid) = self.tcx.trait_of_item(rust_id) {
+            return (
+                Option::None,
+                Option::Some(self.translate_trait_decl_id(trait_rid)),
+            );
+        }
+        let impl_id = match self.tcx.impl_of_method(rust_id) {
+            Option::Some(impl_id) => impl_id,
+            Option::None => return (Option::None, Option::None),
+        };
+        let self_type = match self.tcx.type_of(impl_id).subst_identity().kind() {
+            // The type may be external (in the case of trait impls), in which
+            // case it is either assumed (`Vec`, `Box`, etc.) or opaque
+            TyKind::Adt(adt, _) => Option::Some(self.translate_type_id(adt.did())),
+            _ => Option::None,
+        };
+        let trait_id = self
+            .tcx
+            .trait_id_of_impl(impl_id)
+            .map(|trait_rid| self.translate_trait_decl_id(trait_rid));
+        (self_type, trait_id)
+    }
+
+    /// Retrieve the user attributes of a declaration (see [ast::Attribute]).
+    /// The compiler attributes (`#[inline]`, `#[allow(...)]`, the doc
+    /// comments, etc.) are single identifiers: we only keep the tool
+    /// attributes (`#[my_tool::verified]`), except those of the tools known
+    /// by the compiler (`#[rustfmt::skip]`, `#[clippy::...]`, etc.).
+    /// Note that the attribute macros (like `#[no_panic]`, when implemented
+    /// as a procedural macro) have been expanded at this point: only the
+    /// inert attributes remain.
+    fn translate_attributes(&self, rust_id: DefId) -> Vec<ast::Attribute> {
+        const BUILTIN_TOOLS: [&str; 5] = ["rustfmt", "clippy", "rustdoc", "miri", "rust_analyzer"];
+        let source_map = self.sess.source_map();
+        self.tcx
+            .get_attrs_unchecked(rust_id)
+            .iter()
+            .filter_map(|attr| match &attr.kind {
+                AttrKind::Normal(normal) => {
+                    let item = &normal.item;
+                    let path: Vec<String> = item
+                        .path
+                        .segments
+                        .iter()
+                        .map(|seg| seg.ident.to_string())
+                        .collect();
+                    if path.len() < 2 || BUILTIN_TOOLS.contains(&path[0].as_str()) {
+                        return Option::None;
+                    }
+                    // Once lowered to HIR, the value of a key-value attribute
+                    // (`#[my_tool::level = "high"]`) is a literal, which
+                    // [AttrArgs::span] doesn't support
+                    let span = match &item.args {
+                        AttrArgs::Eq(eq_span, AttrArgsEq::Hir(lit)) => Some(eq_span.to(lit.span)),
+                        args => args.span(),
+                    };
+                    let args = span
+                        .and_then(|span| source_map.span_to_snippet(span).ok())
+                        .unwrap_or_default();
+                    Option::Some(ast::Attribute { path, args })
+                }
+                AttrKind::DocComment(..) => Option::None,
+            })
+            .collect()
+    }
+
+    /// Record the methods defined in the crate for the opaque types (see
+    /// [ty::TypeDecl::local_methods]). This must be done once all the
+    /// declarations have been translated.
+    pub(crate) fn record_opaque_types_methods(&mut self) {
+        for decl in self.fun_defs.iter() {
+            if let Option::Some(ty::TypeId::Adt(id)) = decl.self_type {
+                let type_decl = self.type_defs.get_mut(id).unwrap();
+                if type_decl.kind.is_opaque() {
+                    type_decl.local_methods.push(decl.def_id);
+                }
+            }
+        }
+    }
+
+    /// Detect the compiler built-ins (see [ast::BuiltinFnKind]). We rely on
+    /// the diagnostic items and the lang items of the compiler whenever we
+    /// can, and only use the names for the remaining built-ins. A function
+    /// of the current crate is never a built-in, even if it has the same name
+    /// as one (in a crate named `core` for instance).
+    fn get_builtin_fn_kind(&self, rust_id: DefId, name: &Name) -> Option<ast::BuiltinFnKind> {
+        let tcx = self.tcx;
+        if rust_id.is_local() {
+            return Option::None;
+        }
+        if let Some(item) = tcx.get_diagnostic_name(rust_id) {
+            if let Some(kind) = assumed::get_builtin_fn_kind_from_diagnostic_item(item.as_str()) {
+                return Option::Some(kind);
+            }
+        }
+        // `ManuallyDrop::new` is an inherent method of a lang item
+        if let Some(impl_id) = tcx.impl_of_method(rust_id) {
+            if let TyKind::Adt(adt, _) = tcx.type_of(impl_id).subst_identity().kind() {
+                if Some(adt.did()) == tcx.lang_items().manually_drop()
+                    && tcx.item_name(rust_id).as_str() == "new"
+                {
+                    return Option::Some(ast::BuiltinFnKind::ManuallyDrop);
+                }
+            }
+        }
+        assumed::get_builtin_fn_kind_from_name(name)
     }
 
     /// Translate one function.
@@ -1813,25 +2525,242 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         let meta = self.translate_meta_from_rid(rust_id);
 
         // Translate the function name
-        let name = function_def_id_to_name(self.tcx, rust_id);
+        let tcx = self.tcx;
+        let name = function_def_id_to_name(tcx, rust_id);
+
+        // Check if this is a compiler built-in
+        let builtin = self.get_builtin_fn_kind(rust_id, &name);
+
+        // If the function is a method, retrieve the type and the trait it
+        // is associated with
+        let (self_type, trait_id) = self.translate_method_impl_info(rust_id);
+
+        // Retrieve the calling convention. Note that the closures and the
+        // generators always use the Rust calling convention.
+        let calling_convention = match tcx.type_of(rust_id).subst_identity().kind() {
+            TyKind::FnDef(..) => {
+                let abi = tcx.fn_sig(rust_id).skip_binder().abi();
+                match ast::CallingConvention::from_abi_name(abi.name()) {
+                    Option::Some(cc) => cc,
+                    Option::None => {
+                        let kind = format!("function with ABI \"{}\"", abi.name());
+                        self.push_unsupported_item(rust_id, &kind);
+                        ast::CallingConvention::Rust
+                    }
+                }
+            }
+            _ => ast::CallingConvention::Rust,
+        };
+
+        // Check if the function is declared `unsafe` (the closures and the
+        // generators can't be)
+        let is_unsafe = match tcx.type_of(rust_id).subst_identity().kind() {
+            TyKind::FnDef(..) => {
+                tcx.fn_sig(rust_id).skip_binder().unsafety() == rustc_hir::Unsafety::Unsafe
+            }
+            _ => false,
+        };
+
+        // If the function is a method of a trait impl which overrides the
+        // default implementation of a local trait, retrieve the default
+        // implementation
+        let overrides_default = tcx
+            .opt_associated_item(rust_id)
+            .filter(|item| item.container == mir_ty::AssocItemContainer::ImplContainer)
+            .and_then(|item| item.trait_item_def_id)
+            .filter(|trait_item_id| {
+                trait_item_id.is_local()
+                    && tcx
+                        .associated_item(*trait_item_id)
+                        .defaultness(tcx)
+                        .has_value()
+            })
+            .map(|trait_item_id| self.translate_fun_decl_id(trait_item_id));
+
+        // Check if the function is the `drop` method of a `Drop` impl
+        let implemented_drop = tcx
+            .impl_of_method(rust_id)
+            .and_then(|impl_id| tcx.trait_id_of_impl(impl_id))
+            .map_or(false, |trait_rid| {
+                tcx.lang_items().drop_trait() == Some(trait_rid)
+            });
+
+        // Retrieve the user attributes
+        let attributes = self.translate_attributes(rust_id);
+
+        // The `const fn` are translated at a specific MIR level, and the user
+        // may choose the level of some functions
+        let is_const_fn = tcx.is_const_fn(rust_id);
+        let mir_level = self.function_mir_level(rust_id);
+
+        // Check if the function is a method of a trait, and if it has a default
+        // implementation
+        let is_trait_method = tcx.trait_of_item(rust_id).is_some();
+        let is_trait_default =
+            is_trait_method && tcx.associated_item(rust_id).defaultness(tcx).has_value();
+        let is_required_method = is_trait_method && !is_trait_default;
 
         // Translate the function signature and initialize the body translation context
         // at the same time (the signature gives us the region and type parameters,
         // that we put in the translation context).
         trace!("Translating function signature");
-        let (bt_ctx, signature) = self.translate_function_signature(rust_id);
+        let (mut bt_ctx, signature) = match self.translate_function_signature(rust_id) {
+            Ok(res) => res,
+            Err(err) => {
+                // We can't register a function without its signature: we
+                // record the error (this will make the translation fail) and
+                // move on to the remaining declarations
+                self.push_error(*err);
+                return;
+            }
+        };
+        bt_ctx.mir_level = mir_level;
+
+        // If the function is the body of a generator, retrieve the generator
+        // types now (translating the body consumes the body translation
+        // context). We report the errors once the body is translated.
+        let generator = match tcx.type_of(rust_id).subst_identity().kind() {
+            TyKind::Generator(_, substs, _) => {
+                let generator = substs.as_generator();
+                let mut translate_types = || -> TransResult<_> {
+                    let upvar_tys = generator
+                        .upvar_tys()
+                        .map(|ty| bt_ctx.translate_ety(&ty))
+                        .collect::<TransResult<Vec<ty::ETy>>>()?;
+                    let resume_ty = bt_ctx.translate_ety(&generator.resume_ty())?;
+                    let yield_ty = bt_ctx.translate_ety(&generator.yield_ty())?;
+                    let return_ty = bt_ctx.translate_ety(&generator.return_ty())?;
+                    Ok((upvar_tys, resume_ty, yield_ty, return_ty))
+                };
+                Some(translate_types())
+            }
+            _ => None,
+        };
+
+        // If the function is a method defined in a trait impl, retrieve the
+        // implementing type. We translate it now because the type may refer
+        // to the type parameters of the impl, which are registered in the
+        // body translation context. We report the errors once the body is
+        // translated (translating the body consumes the context).
+        let impl_type = tcx.impl_of_method(rust_id).and_then(|impl_id| {
+            tcx.trait_id_of_impl(impl_id)?;
+            let impl_ty = tcx.type_of(impl_id).subst_identity();
+            Some(bt_ctx.translate_ety(&impl_ty))
+        });
+
+        // If the function is the body of a closure, retrieve the captured
+        // variables. As for the implementing type above, we report the errors
+        // once the body is translated.
+        let is_closure = tcx.type_of(rust_id).subst_identity().is_closure();
+        let captures: TransResult<Vec<ast::CaptureInfo>> = match rust_id.as_local() {
+            Some(local_id) if is_closure => tcx
+                .closure_captures(local_id)
+                .iter()
+                .map(|captured| -> TransResult<ast::CaptureInfo> {
+                    Ok(ast::CaptureInfo {
+                        name: captured.to_symbol().to_string(),
+                        ty: bt_ctx.translate_ety(&captured.place.ty())?,
+                        mode: translate_capture_mode(captured.info.capture_kind),
+                    })
+                })
+                .collect(),
+            _ => Ok(Vec::new()),
+        };
 
-        // Check if the type is opaque or transparent
-        let body = if !is_transparent || !rust_id.is_local() {
+        // Check if the type is opaque or transparent. Note that the functions
+        // declared in `extern` blocks and the required methods of the traits
+        // don't have bodies.
+        let body = if !is_transparent
+            || !rust_id.is_local()
+            || tcx.is_foreign_item(rust_id)
+            || is_required_method
+        {
             Option::None
         } else {
-            Option::Some(
-                bt_ctx
-                    .translate_body(rust_id.expect_local(), signature.inputs.len())
-                    .unwrap(),
-            )
+            match bt_ctx.translate_body(rust_id.expect_local(), signature.inputs.len()) {
+                Ok(body) => Option::Some(body),
+                Err(err) => {
+                    // We couldn't translate the body: we record the error,
+                    // and make the function opaque
+                    self.push_error(*err);
+                    Option::None
+                }
+            }
         };
 
+        // Save the MIR we translated (`--emit-mir`)
+        if self.crate_info.emit_mir && body.is_some() {
+            let mir = self.get_mir(rust_id.expect_local(), None, mir_level);
+            self.mir_dumps.insert(def_id, mir_body_to_string(tcx, mir));
+        }
+
+        // Sanity check: the block structure of the body must be well-formed
+        if cfg!(debug_assertions) {
+            if let Some(Err(errors)) = body.as_ref().map(wellformed::check_body_wellformed) {
+                for err in &errors {
+                    error!("In {}: {}", name, err);
+                }
+                panic!("The ULLBC body of {} is ill-formed", name);
+            }
+        }
+
+        // Build the trait impl information
+        let trait_impl_info = match (trait_id, impl_type) {
+            (Some(trait_decl_id), Some(Ok(impl_type))) => Some(ast::TraitImplInfo {
+                trait_decl_id,
+                impl_type,
+                method_name: tcx.item_name(rust_id).to_string(),
+            }),
+            (_, Some(Err(err))) => {
+                self.push_error(*err);
+                None
+            }
+            _ => None,
+        };
+
+        let captures = match captures {
+            Ok(captures) => captures,
+            Err(err) => {
+                self.push_error(*err);
+                Vec::new()
+            }
+        };
+
+        // Save the generator information
+        let generator = match generator {
+            Some(Ok(types)) => Some(types),
+            Some(Err(err)) => {
+                self.push_error(*err);
+                None
+            }
+            None => None,
+        };
+        if let Some((upvar_tys, resume_ty, yield_ty, return_ty)) = generator {
+            let suspension_points = body
+                .as_ref()
+                .map_or_else(Vec::new, |body| body.suspension_points());
+            let state_type = self.translate_generator_state(
+                rust_id,
+                meta,
+                &name,
+                &signature,
+                body.as_ref(),
+                &suspension_points,
+            );
+            self.generator_decls.insert(
+                def_id,
+                ast::GeneratorDecl {
+                    def_id,
+                    state_type,
+                    upvar_tys,
+                    resume_ty,
+                    yield_ty,
+                    return_ty,
+                    suspension_points,
+                },
+            );
+        }
+
         // Save the new function
         self.fun_defs.insert(
             def_id,
@@ -1841,10 +2770,112 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                 name,
                 signature,
                 body,
+                builtin,
+                calling_convention,
+                is_const_fn,
+                is_unsafe,
+                // Computed later (see [crate::unsafe_propagation])
+                calls_unsafe: false,
+                self_type,
+                trait_id,
+                trait_impl_info,
+                is_trait_default,
+                overrides_default,
+                captures,
+                implemented_drop,
+                attributes,
+                is_root: self.root_ids.contains(&AnyTransId::Fun(def_id)),
+                stable_id: None,
             },
         );
     }
 
+    /// Generate the enumeration of the states of a generator (see
+    /// [ast::GeneratorDecl::state_type]).
+    ///
+    /// Rustc doesn't give us a declaration for this type: we generate one
+    /// with a fresh identifier, named after the generator (we add a `State`
+    /// path element). It has the same type parameters as the generator body.
+    fn translate_generator_state(
+        &mut self,
+        rust_id: DefId,
+        meta: Meta,
+        name: &Name,
+        signature: &ast::FunSig,
+        body: Option<&ast::ExprBody>,
+        suspension_points: &[ast::SuspensionPoint],
+    ) -> ty::TypeDeclId::Id {
+        // The generator body doesn't define a type: its id is free in the
+        // map of the type ids
+        let (trans_id, _) = self.type_id_map.insert_new(rust_id);
+        self.all_ids.insert(AnyTransId::Type(trans_id));
+
+        let unit_variant = |name: &str| ty::Variant {
+            meta,
+            name: name.to_string(),
+            fields: ty::FieldId::Vector::new(),
+        };
+        let kind = match body {
+            None => ty::TypeDeclKind::Opaque,
+            Some(body) => {
+                let mut variants = ty::VariantId::Vector::new();
+                variants.push_back(unit_variant("Unresumed"));
+                variants.push_back(unit_variant("Returned"));
+                variants.push_back(unit_variant("Poisoned"));
+                for (i, point) in suspension_points.iter().enumerate() {
+                    let meta = body.body.get(point.block).unwrap().terminator.meta;
+                    let fields = point
+                        .live_vars
+                        .iter()
+                        .map(|var_id| {
+                            let var = body.locals.get(*var_id).unwrap();
+                            ty::Field {
+                                meta,
+                                name: var.name.clone(),
+                                ty: var.ty.substitute(
+                                    &|_| ty::Region::Static,
+                                    &|tid| ty::Ty::TypeVar(*tid),
+                                    &|cgid| ty::ConstGeneric::Var(*cgid),
+                                ),
+                                // Computed later (see [crate::detect_interior_mutability])
+                                interior_mut: false,
+                            }
+                        })
+                        .collect();
+                    variants.push_back(ty::Variant {
+                        meta,
+                        name: format!("Suspend{i}"),
+                        fields,
+                    });
+                }
+                ty::TypeDeclKind::Enum(variants)
+            }
+        };
+
+        let mut name = name.clone();
+        name.name.push(PathElem::Ident("State".to_string()));
+        let type_def = ty::TypeDecl {
+            def_id: trans_id,
+            meta,
+            name,
+            region_params: ty::RegionVarId::Vector::new(),
+            type_params: signature.type_params.clone(),
+            const_generic_params: signature.const_generic_params.clone(),
+            kind,
+            repr: ty::ReprConfig::rust(),
+            is_root: false,
+            stable_id: None,
+            local_methods: Vec::new(),
+            // Rustc doesn't compute the variances of the generators
+            variance: Vec::new(),
+            is_recursive: false,
+            regions_hierarchy: RegionGroups::new(),
+        };
+        trace!("{} -> {}", trans_id.to_string(), type_def.to_string());
+        self.type_defs.insert(trans_id, type_def);
+        trans_id
+    }
+
     /// Generate an expression body from a typed constant value.
     fn global_generate_assignment_body(
         &mut self,
@@ -1860,7 +2891,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         let var = ast::Var {
             index: v::VarId::ZERO,
             name: None,
-            ty: ty.clone(),
+            ty: self.type_interner.intern(ty.clone()),
         };
         // # Instructions
         // ret := const (ty, val)
@@ -1874,12 +2905,14 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                 ),
             )],
             terminator: ast::Terminator::new(meta, ast::RawTerminator::Return),
+            is_loop_header: false,
         };
         ast::ExprBody {
             meta,
             arg_count: 0,
             locals: id_vector::Vector::from(vec![var]),
             body: id_vector::Vector::from(vec![block]),
+            liveness: None,
         }
     }
 
@@ -1892,6 +2925,14 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         // Translate the global name
         let name = global_def_id_to_name(self.tcx, rust_id);
 
+        // If the global is the value given by a trait impl to an associated
+        // constant, retrieve the trait which declares the constant
+        let trait_id = self
+            .tcx
+            .opt_associated_item(rust_id)
+            .and_then(|item| item.trait_item_def_id)
+            .map(|trait_item_id| self.translate_trait_decl_id(self.tcx.parent(trait_item_id)));
+
         // Compute the meta information
         let meta = self.translate_meta_from_rid(rust_id);
         let is_transparent = self.id_is_transparent(rust_id);
@@ -1900,14 +2941,26 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
 
         trace!("Translating global type");
         let mir_ty = bt_ctx.t_ctx.tcx.type_of(rust_id).subst_identity();
-        let g_ty = bt_ctx.translate_ety(&mir_ty).unwrap();
+        let g_ty = match bt_ctx.translate_ety(&mir_ty) {
+            Ok(ty) => ty,
+            Err(err) => {
+                bt_ctx.t_ctx.push_error(*err);
+                return;
+            }
+        };
 
         let body = match (rust_id.is_local(), is_transparent) {
             // It's a local and opaque global: we do not give it a body.
             (true, false) => Option::None,
 
             // It's a local and transparent global: we extract its body as for functions.
-            (true, true) => Option::Some(bt_ctx.translate_body(rust_id.expect_local(), 0).unwrap()),
+            (true, true) => match bt_ctx.translate_body(rust_id.expect_local(), 0) {
+                Ok(body) => Option::Some(body),
+                Err(err) => {
+                    self.push_error(*err);
+                    Option::None
+                }
+            },
 
             // It is an external global.
             // The fact that it is listed among the declarations to extract means that
@@ -1929,15 +2982,25 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                     std::result::Result::Ok(c) => {
                         // Evaluate the constant
                         // We need a param_env: we use the expression def id as a dummy id...
-                        let (ty, val) = bt_ctx.translate_evaluated_operand_constant(&mir_ty, &c);
-                        Option::Some(
-                            bt_ctx
-                                .t_ctx
-                                .global_generate_assignment_body(ty, rust_id, val),
-                        )
+                        match bt_ctx.translate_evaluated_operand_constant(&mir_ty, &c) {
+                            Ok((ty, val)) => Option::Some(
+                                bt_ctx
+                                    .t_ctx
+                                    .global_generate_assignment_body(ty, rust_id, val),
+                            ),
+                            Err(err) => {
+                                bt_ctx.t_ctx.push_error(*err);
+                                Option::None
+                            }
+                        }
                     }
                     std::result::Result::Err(e) => {
-                        warn!("Did not evaluate {:?}: {:?}", rust_id, e);
+                        let span = bt_ctx.t_ctx.tcx.def_span(rust_id);
+                        bt_ctx.t_ctx.emit_warning(
+                            span,
+                            format!("Could not evaluate the constant: {e:?}"),
+                            Some("The global is translated without a body".to_string()),
+                        );
                         Option::None
                     }
                 }
@@ -1953,7 +3016,148 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                 name,
                 ty: g_ty,
                 body,
+                trait_id,
+                is_root: self.root_ids.contains(&AnyTransId::Global(def_id)),
+                stable_id: None,
+            },
+        );
+    }
+
+    /// Translate a promoted constant (see [mir::Promoted]) to an anonymous
+    /// global, and return its identifier.
+    ///
+    /// Contrary to the other declarations, we don't push the promoted
+    /// constants on the stack of declarations to translate: they are not
+    /// items, and we can only retrieve their bodies through the body in which
+    /// they appear, so we translate them immediately. `mir_level` is the
+    /// level of this body.
+    pub(crate) fn translate_promoted_decl_id(
+        &mut self,
+        rust_id: DefId,
+        promoted: mir::Promoted,
+        mir_level: MirLevel,
+    ) -> TransResult<ast::GlobalDeclId::Id> {
+        if let Option::Some(id) = self.promoted_id_map.get(&(rust_id, promoted)) {
+            return Ok(*id);
+        }
+        trace!(
+            "About to translate promoted:\n{:?}, {:?}",
+            rust_id,
+            promoted
+        );
+
+        let tcx = self.tcx;
+        let body = self.get_mir(rust_id.expect_local(), Some(promoted), mir_level);
+        let meta = self.translate_meta_from_rspan(body.span);
+
+        // The name is the name of the enclosing definition, followed by the
+        // index of the promoted constant: `f::promoted::0`
+        let mut name = item_def_id_to_name(tcx, rust_id);
+        name.name.push(PathElem::Ident("promoted".to_string()));
+        name.name
+            .push(PathElem::Disambiguator(Disambiguator::Id::new(
+                promoted.as_usize(),
+            )));
+
+        // Translate the type before generating an identifier: if we fail,
+        // we don't want to leave a dangling identifier
+        let mut bt_ctx = BodyTransCtx::new(rust_id, self);
+        bt_ctx.mir_level = mir_level;
+        let g_ty = bt_ctx.translate_ety(&body.local_decls[mir::RETURN_PLACE].ty)?;
+
+        let def_id = bt_ctx.t_ctx.global_id_map.counter.fresh_id();
+        bt_ctx
+            .t_ctx
+            .promoted_id_map
+            .insert((rust_id, promoted), def_id);
+        bt_ctx.t_ctx.all_ids.insert(AnyTransId::Global(def_id));
+
+        let body = match bt_ctx.translate_mir_body(body, 0) {
+            Ok(body) => Option::Some(body),
+            Err(err) => {
+                self.push_error(*err);
+                Option::None
+            }
+        };
+
+        self.global_defs.insert(
+            def_id,
+            ast::GlobalDecl {
+                def_id,
+                meta,
+                name,
+                ty: g_ty,
+                body,
+                trait_id: Option::None,
+                is_root: false,
+                stable_id: None,
             },
         );
+        Ok(def_id)
+    }
+
+    /// Translate an associated constant declared in a trait.
+    ///
+    /// `has_default`: `true` if the trait gives a default value to the
+    /// constant.
+    pub(crate) fn translate_assoc_const_decl(
+        &mut self,
+        trait_rid: DefId,
+        rust_id: DefId,
+        has_default: bool,
+    ) {
+        trace!("About to translate associated constant:\n{:?}", rust_id);
+
+        let trait_id = self.translate_trait_decl_id(trait_rid);
+        let name = self.tcx.item_name(rust_id).to_ident_string();
+
+        let mut bt_ctx = BodyTransCtx::new(rust_id, self);
+        let mir_ty = bt_ctx.t_ctx.tcx.type_of(rust_id).subst_identity();
+        // We don't have type variables in scope: the type must not refer to
+        // `Self` or to the parameters of the trait
+        if mir_ty.has_param() {
+            bt_ctx
+                .t_ctx
+                .push_unsupported_item(rust_id, "associated constant with a generic type");
+            return;
+        }
+        let ty = match bt_ctx.translate_ety(&mir_ty) {
+            Ok(ty) => ty,
+            Err(err) => {
+                bt_ctx.t_ctx.push_error(*err);
+                return;
+            }
+        };
+
+        // Evaluate the default value, if there is one
+        let default_value = if has_default {
+            match bt_ctx.t_ctx.tcx.const_eval_poly(rust_id) {
+                std::result::Result::Ok(c) => {
+                    match bt_ctx.translate_evaluated_operand_constant(&mir_ty, &c) {
+                        Ok((_, val)) => Option::Some(val),
+                        Err(err) => {
+                            bt_ctx.t_ctx.push_error(*err);
+                            return;
+                        }
+                    }
+                }
+                std::result::Result::Err(_) => {
+                    bt_ctx.t_ctx.push_unsupported_item(
+                        rust_id,
+                        "associated constant with a generic default value",
+                    );
+                    return;
+                }
+            }
+        } else {
+            Option::None
+        };
+
+        self.assoc_consts.push(ast::AssocConstDecl {
+            trait_id,
+            name,
+            ty,
+            default_value,
+        });
     }
 }
@@ -0,0 +1,216 @@
+//! Translate the trait declarations.
+//!
+//! The methods of the traits are translated as functions (see
+//! [crate::translate_functions_to_ullbc]): the trait declarations only list
+//! them.
+use crate::assumed;
+use crate::names::trait_def_id_to_name;
+use crate::translate_ctx::*;
+use crate::translate_types::translate_non_erased_region;
+use crate::types as ty;
+use crate::ullbc_ast as ast;
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::{AssocKind, Clause, GenericParamDefKind, PredicateKind, TyCtxt, TyKind};
+
+/// Check if an impl applies to an infinite set of types (see
+/// [ast::BlanketImpl]): either it is generic over types, or it implements the
+/// trait for a trait object.
+fn is_blanket_impl(tcx: TyCtxt<'_>, impl_id: DefId) -> bool {
+    let has_type_params = tcx
+        .generics_of(impl_id)
+        .params
+        .iter()
+        .any(|param| matches!(param.kind, GenericParamDefKind::Type { .. }));
+    let self_ty = tcx.type_of(impl_id).subst_identity();
+    has_type_params || matches!(self_ty.kind(), TyKind::Dynamic(..))
+}
+
+impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
+    /// Translate the parent traits of a trait (`trait Shape: Named + Clone`).
+    ///
+    /// We ignore the `Sized` bound and the lifetime bounds. The associated
+    /// type constraints (`trait Counter: Iterator<Item = u32>`) are attached
+    /// to the parent trait which defines them.
+    fn translate_supertrait_bounds(&mut self, def_id: DefId) -> TransResult<Vec<ty::RTraitRef>> {
+        let tcx = self.t_ctx.tcx;
+        let region_vars_map = self.region_vars_map.clone();
+        let region_translator = |r: &rustc_middle::ty::RegionKind<'tcx>| {
+            translate_non_erased_region(&region_vars_map, *r)
+        };
+
+        let mut traits: Vec<(DefId, ty::RTraitRef)> = vec![];
+        let mut assoc_types: Vec<(DefId, String, ty::RTy)> = vec![];
+        for (pred, _span) in tcx.super_predicates_of(def_id).predicates {
+            let pred_kind = pred.kind();
+            // We don't support higher-ranked bounds for now
+            if !pred_kind.bound_vars().is_empty() {
+                return Err(Box::new(TranslationError::UnsupportedType {
+                    span: self.def_meta(),
+                    ty: format!("{pred:?}"),
+                }));
+            }
+            match pred_kind.skip_binder() {
+                PredicateKind::Clause(Clause::Trait(trait_pred)) => {
+                    let trait_id = trait_pred.trait_ref.def_id;
+                    let trait_name = trait_def_id_to_name(tcx, trait_id);
+                    if trait_name.equals_ref_name(&assumed::MARKER_SIZED_NAME) {
+                        continue;
+                    }
+                    // The first parameter is the `Self` type
+                    let substs = tcx.mk_substs(&trait_pred.trait_ref.substs[1..]);
+                    let trait_ref =
+                        self.translate_trait_ref(&region_translator, trait_id, &substs)?;
+                    traits.push((trait_id, trait_ref));
+                }
+                PredicateKind::Clause(Clause::Projection(proj)) => {
+                    let assoc_id = proj.projection_ty.def_id;
+                    let ty = match proj.term.ty() {
+                        Option::Some(ty) => self.translate_ty(&region_translator, &ty)?,
+                        Option::None => {
+                            return Err(Box::new(TranslationError::UnsupportedType {
+                                span: self.def_meta(),
+                                ty: format!("{pred:?}"),
+                            }))
+                        }
+                    };
+                    let name = tcx.item_name(assoc_id).to_ident_string();
+                    assoc_types.push((tcx.parent(assoc_id), name, ty));
+                }
+                PredicateKind::Clause(Clause::TypeOutlives(_))
+                | PredicateKind::Clause(Clause::RegionOutlives(_)) => {
+                    // We ignore the lifetime bounds (`trait Shape: 'static`)
+                }
+                _ => {
+                    return Err(Box::new(TranslationError::UnsupportedType {
+                        span: self.def_meta(),
+                        ty: format!("{pred:?}"),
+                    }))
+                }
+            }
+        }
+
+        // Attach the associated types to the traits which define them (or to
+        // the first trait, if the trait is a parent of a parent trait)
+        for (trait_id, name, ty) in assoc_types {
+            let pos = traits
+                .iter()
+                .position(|(id, _)| *id == trait_id)
+                .unwrap_or(0);
+            traits[pos].1.assoc_types.push((name, ty));
+        }
+
+        Ok(traits.into_iter().map(|(_, tr)| tr).collect())
+    }
+
+    /// Translate an impl of a local trait which applies to an infinite set of
+    /// types (see [ast::BlanketImpl]). The generic parameters of the impl must
+    /// have been pushed in the context.
+    fn translate_blanket_impl(
+        &mut self,
+        impl_id: DefId,
+        trait_id: ast::TraitDeclId::Id,
+    ) -> TransResult<ast::BlanketImpl> {
+        let tcx = self.t_ctx.tcx;
+        let region_vars_map = self.region_vars_map.clone();
+        let region_translator = |r: &rustc_middle::ty::RegionKind<'tcx>| {
+            translate_non_erased_region(&region_vars_map, *r)
+        };
+
+        let impl_trait_ref = tcx.impl_trait_ref(impl_id).unwrap().subst_identity();
+        let impl_type = self.translate_ty(&region_translator, &impl_trait_ref.self_ty())?;
+        // The first parameter is the `Self` type
+        let substs = tcx.mk_substs(&impl_trait_ref.substs[1..]);
+        let trait_ref =
+            self.translate_trait_ref(&region_translator, impl_trait_ref.def_id, &substs)?;
+        let bounds = self.translate_predicates(impl_id)?;
+
+        Ok(ast::BlanketImpl {
+            meta: self.t_ctx.translate_meta_from_rid(impl_id),
+            trait_id,
+            region_params: self.region_vars.clone(),
+            type_params: self.type_vars.clone(),
+            const_generic_params: self.const_generic_vars.clone(),
+            impl_type,
+            trait_ref,
+            bounds,
+        })
+    }
+}
+
+impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
+    /// Translate a (local) trait declaration.
+    ///
+    /// The methods must have been registered (see
+    /// [crate::translate_crate_to_ullbc]): we only retrieve their identifiers.
+    pub(crate) fn translate_trait(&mut self, rust_id: DefId) {
+        trace!("About to translate trait:\n{:?}", rust_id);
+        let def_id = self.translate_trait_decl_id(rust_id);
+        let tcx = self.tcx;
+        let meta = self.translate_meta_from_rid(rust_id);
+        let name = trait_def_id_to_name(tcx, rust_id);
+
+        // List the methods, in the order in which they are defined
+        let mut required_methods = Vec::new();
+        let mut default_methods = Vec::new();
+        for item in tcx.associated_items(rust_id).in_definition_order() {
+            if item.kind != AssocKind::Fn {
+                continue;
+            }
+            let fun_id = self.translate_fun_decl_id(item.def_id);
+            if item.defaultness(tcx).has_value() {
+                default_methods.push(fun_id);
+            } else {
+                required_methods.push(fun_id);
+            }
+        }
+
+        // Translate the generics. Note that the first type parameter is `Self`.
+        let substs = rustc_middle::ty::subst::InternalSubsts::identity_for_item(tcx, rust_id);
+        let mut bt_ctx = BodyTransCtx::new(rust_id, self);
+        bt_ctx.push_generic_params(substs);
+
+        let supertrait_bounds = match bt_ctx.translate_supertrait_bounds(rust_id) {
+            Ok(bounds) => bounds,
+            Err(err) => {
+                // The parent traits are not necessary to use the trait: we
+                // record the error and ignore them
+                bt_ctx.t_ctx.push_error(*err);
+                Vec::new()
+            }
+        };
+
+        let region_params = bt_ctx.region_vars.clone();
+        let type_params = bt_ctx.type_vars.clone();
+        let const_generic_params = bt_ctx.const_generic_vars.clone();
+
+        // The local impls of the trait which apply to an infinite set of types
+        let impl_ids: Vec<DefId> = tcx
+            .all_impls(rust_id)
+            .filter(|impl_id| impl_id.is_local() && is_blanket_impl(tcx, *impl_id))
+            .collect();
+        let mut blanket_impls = Vec::new();
+        for impl_id in impl_ids {
+            let substs = rustc_middle::ty::subst::InternalSubsts::identity_for_item(tcx, impl_id);
+            let mut bt_ctx = BodyTransCtx::new(impl_id, self);
+            bt_ctx.push_generic_params(substs);
+            match bt_ctx.translate_blanket_impl(impl_id, def_id) {
+                Ok(blanket_impl) => blanket_impls.push(blanket_impl),
+                Err(err) => self.push_error(*err),
+            }
+        }
+
+        let trait_decl = ast::TraitDecl {
+            def_id,
+            meta,
+            name,
+            region_params,
+            type_params,
+            const_generic_params,
+            required_methods,
+            default_methods,
+            supertrait_bounds,
+            blanket_impls,
+        };
+        self.trait_defs.insert(def_id, trait_decl);
+    }
+}
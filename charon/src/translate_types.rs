@@ -1,15 +1,49 @@
 use crate::assumed;
-use crate::common::*;
 use crate::generics;
-use crate::names::type_def_id_to_name;
+use crate::names::{trait_def_id_to_name, type_def_id_to_name};
 use crate::regions_hierarchy::RegionGroups;
+use crate::reorder_decls::AnyTransId;
 use crate::translate_ctx::*;
 use crate::types as ty;
 use crate::types::ConstGeneric;
 use core::convert::*;
+use rustc_abi::{Integer, IntegerType};
+use rustc_hir::def::DefKind;
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir::Mutability;
-use rustc_middle::ty::{Ty, TyKind};
+use rustc_middle::ty::{
+    AliasKind, AliasTy, Clause, ExistentialPredicate, PredicateKind, Ty, TyKind, TypeVisitableExt,
+};
+
+/// A translated predicate, before we attach the associated type constraints
+/// to the trait bounds (see [BodyTransCtx::translate_predicates]).
+enum TranslatedPredicate {
+    /// The trait, and the predicate
+    Trait(DefId, ty::Predicate),
+    /// `<T as Trait>::Name = Ty`: the trait, `T`, the name and `Ty`
+    AssocType(DefId, ty::RTy, String, ty::RTy),
+}
+
+/// Translate the integer type used to represent the discriminant of an
+/// enumeration (as given by `#[repr(u8)]`, etc.).
+fn translate_integer_type(int: IntegerType) -> ty::IntegerTy {
+    match int {
+        IntegerType::Pointer(true) => ty::IntegerTy::Isize,
+        IntegerType::Pointer(false) => ty::IntegerTy::Usize,
+        IntegerType::Fixed(int, signed) => match (int, signed) {
+            (Integer::I8, true) => ty::IntegerTy::I8,
+            (Integer::I16, true) => ty::IntegerTy::I16,
+            (Integer::I32, true) => ty::IntegerTy::I32,
+            (Integer::I64, true) => ty::IntegerTy::I64,
+            (Integer::I128, true) => ty::IntegerTy::I128,
+            (Integer::I8, false) => ty::IntegerTy::U8,
+            (Integer::I16, false) => ty::IntegerTy::U16,
+            (Integer::I32, false) => ty::IntegerTy::U32,
+            (Integer::I64, false) => ty::IntegerTy::U64,
+            (Integer::I128, false) => ty::IntegerTy::U128,
+        },
+    }
+}
 
 pub fn translate_region_name(region: &rustc_middle::ty::RegionKind<'_>) -> Option<String> {
     // Compute the region name
@@ -43,7 +77,13 @@ pub fn translate_non_erased_region<'tcx>(
     region: rustc_middle::ty::RegionKind<'tcx>,
 ) -> ty::Region<ty::RegionVarId::Id> {
     match region {
-        rustc_middle::ty::RegionKind::ReErased => unreachable!(),
+        // The erased regions only appear in the signatures of the closures,
+        // in which case we introduce a region for them (see
+        // [crate::translate_functions_to_ullbc]).
+        rustc_middle::ty::RegionKind::ReErased => {
+            let rid = region_params.get(&region).unwrap_or_else(|| unreachable!());
+            ty::Region::Var(*rid)
+        }
         rustc_middle::ty::RegionKind::ReStatic => ty::Region::Static,
         _ => {
             let rid = region_params.get(&region).unwrap();
@@ -55,10 +95,14 @@ pub fn translate_non_erased_region<'tcx>(
 /// Translate a region which is expected to be erased.
 ///
 /// The regions are expected to be erased inside the function bodies (i.e.:
-/// we believe MIR uses regions only in the function signatures).
+/// we believe MIR uses regions only in the function signatures). The only
+/// exception is the `'static` region, which appears in the types of the
+/// constants (string literals, promoted constants, etc.): we erase it.
 pub fn translate_erased_region(region: rustc_middle::ty::RegionKind<'_>) -> ty::ErasedRegion {
     match region {
-        rustc_middle::ty::RegionKind::ReErased => ty::ErasedRegion::Erased,
+        rustc_middle::ty::RegionKind::ReErased | rustc_middle::ty::RegionKind::ReStatic => {
+            ty::ErasedRegion::Erased
+        }
         _ => {
             unreachable!();
         }
@@ -66,6 +110,14 @@ pub fn translate_erased_region(region: rustc_middle::ty::RegionKind<'_>) -> ty::
 }
 
 impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
+    /// Report an unsupported type
+    fn unsupported_ty<T>(&mut self, ty_kind: &TyKind<'tcx>) -> TransResult<T> {
+        Err(Box::new(TranslationError::UnsupportedType {
+            span: self.def_meta(),
+            ty: format!("{ty_kind:?}"),
+        }))
+    }
+
     /// Translate a Ty.
     ///
     /// Typically used in this module to translate the fields of a structure/
@@ -78,7 +130,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         region_translator: &dyn Fn(&rustc_middle::ty::RegionKind<'tcx>) -> R,
         ty: &Ty<'tcx>,
-    ) -> Result<ty::Ty<R>>
+    ) -> TransResult<ty::Ty<R>>
     where
         R: Clone + Eq,
     {
@@ -93,7 +145,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         region_translator: &dyn Fn(&rustc_middle::ty::RegionKind<'tcx>) -> R,
         ty_kind: &TyKind<'tcx>,
-    ) -> Result<ty::Ty<R>>
+    ) -> TransResult<ty::Ty<R>>
     where
         R: Clone + Eq,
     {
@@ -109,13 +161,25 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             ))),
             TyKind::Float(_) => {
                 trace!("Float");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
             TyKind::Never => Ok(ty::Ty::Never),
 
-            TyKind::Alias(_, _) => {
-                unimplemented!();
+            TyKind::Alias(AliasKind::Opaque, alias_ty) => {
+                trace!("Alias(Opaque)");
+
+                // An `impl Trait` type: we translate it to a reference to the
+                // opaque type declaration (see [ty::TypeDeclKind::ImplTrait])
+                let (regions, params, cgs) =
+                    self.translate_substs(region_translator, None, &alias_ty.substs)?;
+                let id = ty::TypeId::Adt(self.translate_type_decl_id(alias_ty.def_id));
+                Ok(ty::Ty::Adt(id, regions, params, cgs))
+            }
+            TyKind::Alias(AliasKind::Projection, alias_ty) => {
+                trace!("Alias(Projection)");
+                self.translate_projection_ty(region_translator, ty_kind, alias_ty)
             }
+            TyKind::Alias(_, _) => self.unsupported_ty(ty_kind),
 
             TyKind::Adt(adt, substs) => {
                 let adt_did = adt.did();
@@ -148,7 +212,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             TyKind::Array(ty, const_param) => {
                 trace!("Array");
 
-                let c = self.translate_const_kind_as_const_generic(*const_param);
+                let c = self.translate_const_kind_as_const_generic(*const_param)?;
                 let tys = vec![self.translate_ty(region_translator, ty)?];
                 let cgs = vec![c];
                 let id = ty::TypeId::Assumed(ty::AssumedTy::Array);
@@ -198,9 +262,21 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 ))
             }
 
-            TyKind::FnPtr(_) => {
+            TyKind::FnPtr(sig) => {
                 trace!("FnPtr");
-                unimplemented!();
+
+                // We don't support higher-ranked function pointers for now
+                // (i.e., `for<'a> fn(&'a u32)`)
+                if !sig.bound_vars().is_empty() {
+                    return self.unsupported_ty(ty_kind);
+                }
+                let sig = sig.skip_binder();
+                let mut inputs = vec![];
+                for input in sig.inputs() {
+                    inputs.push(self.translate_ty(region_translator, input)?);
+                }
+                let output = self.translate_ty(region_translator, &sig.output())?;
+                Ok(ty::Ty::FnPtr(inputs, Box::new(output)))
             }
             TyKind::Param(param) => {
                 // A type parameter, for example `T` in `fn f<T>(x : T) {}`.
@@ -221,47 +297,125 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
 
             TyKind::Foreign(_) => {
                 trace!("Foreign");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
             TyKind::Infer(_) => {
                 trace!("Infer");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
 
             TyKind::FnDef(_, _) => {
                 trace!("FnDef");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
 
-            TyKind::Dynamic(_, _, _) => {
+            TyKind::Dynamic(preds, region, _) => {
                 trace!("Dynamic");
-                unimplemented!();
+
+                // The principal trait (if there is one) comes first, followed
+                // by the auto traits (`Send`, `Sync`, etc.). The associated type
+                // constraints (`Output = u32` in `dyn Fn(u32) -> u32`) are
+                // attached to the principal trait.
+                let mut traits = vec![];
+                let mut assoc_types = vec![];
+                for pred in preds.iter() {
+                    // We don't support higher-ranked trait objects for now
+                    // (i.e., `dyn for<'a> Fn(&'a u32)`)
+                    if !pred.bound_vars().is_empty() {
+                        return self.unsupported_ty(ty_kind);
+                    }
+                    match pred.skip_binder() {
+                        ExistentialPredicate::Trait(trait_ref) => {
+                            let trait_ref = self.translate_trait_ref(
+                                region_translator,
+                                trait_ref.def_id,
+                                &trait_ref.substs,
+                            )?;
+                            traits.insert(0, trait_ref);
+                        }
+                        ExistentialPredicate::Projection(proj) => {
+                            let name = self.t_ctx.tcx.item_name(proj.def_id).to_string();
+                            let ty = match proj.term.ty() {
+                                Option::Some(ty) => self.translate_ty(region_translator, &ty)?,
+                                Option::None => return self.unsupported_ty(ty_kind),
+                            };
+                            assoc_types.push((name, ty));
+                        }
+                        ExistentialPredicate::AutoTrait(def_id) => {
+                            let substs = rustc_middle::ty::List::empty();
+                            traits.push(self.translate_trait_ref(
+                                region_translator,
+                                def_id,
+                                &substs,
+                            )?);
+                        }
+                    }
+                }
+                if !assoc_types.is_empty() {
+                    traits[0].assoc_types = assoc_types;
+                }
+
+                let region = region_translator(region);
+                Ok(ty::Ty::TraitObject(traits, region))
             }
-            TyKind::Closure(_, _) => {
+            TyKind::Closure(_, substs) => {
                 trace!("Closure");
-                unimplemented!();
+
+                // We represent the closure state with the tuple of its
+                // captured variables (see [crate::expressions::AggregateKind::Closure])
+                let mut params = vec![];
+                for param in substs.as_closure().upvar_tys() {
+                    let param_ty = self.translate_ty(region_translator, &param)?;
+                    params.push(param_ty);
+                }
+
+                Ok(ty::Ty::Adt(
+                    ty::TypeId::Tuple,
+                    Vec::new(),
+                    params,
+                    Vec::new(),
+                ))
             }
 
-            TyKind::Generator(_, _, _) | TyKind::GeneratorWitness(_) => {
+            TyKind::Generator(_, substs, _) => {
                 trace!("Generator");
-                unimplemented!();
+
+                // Similarly to closures, we represent the generator state with
+                // the tuple of its captured variables (see
+                // [crate::expressions::AggregateKind::Generator])
+                let mut params = vec![];
+                for param in substs.as_generator().upvar_tys() {
+                    let param_ty = self.translate_ty(region_translator, &param)?;
+                    params.push(param_ty);
+                }
+
+                Ok(ty::Ty::Adt(
+                    ty::TypeId::Tuple,
+                    Vec::new(),
+                    params,
+                    Vec::new(),
+                ))
+            }
+            TyKind::GeneratorWitness(_) => {
+                trace!("GeneratorWitness");
+                self.unsupported_ty(ty_kind)
             }
 
             TyKind::Error(_) => {
                 trace!("Error");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
             TyKind::Bound(_, _) => {
                 trace!("Bound");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
             TyKind::Placeholder(_) => {
                 trace!("PlaceHolder");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
             TyKind::GeneratorWitnessMIR(..) => {
                 trace!("GeneratorWitnessMIR");
-                unimplemented!();
+                self.unsupported_ty(ty_kind)
             }
         }
     }
@@ -269,7 +423,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
     /// Translate a signature type, where the regions are not erased and use region
     /// variable ids.
     /// Simply calls [`translate_ty`](translate_ty)
-    pub(crate) fn translate_sig_ty(&mut self, ty: &Ty<'tcx>) -> Result<ty::RTy> {
+    pub(crate) fn translate_sig_ty(&mut self, ty: &Ty<'tcx>) -> TransResult<ty::RTy> {
         // Borrowing issues: we have to clone the region vars map.
         // This shouldn't cost us too much. In case of performance issues,
         // we can turn the map into an im::map
@@ -277,9 +431,54 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         self.translate_ty(&|r| translate_non_erased_region(&region_vars_map, *r), ty)
     }
 
+    /// Translate an associated type projection, like `<T as Iterator>::Item`.
+    ///
+    /// We first try to normalize the projection: this succeeds if the type is
+    /// concrete (we then use the associated type given by its impl), or if the
+    /// bounds of the current definition give the value of the associated type
+    /// (`T: Iterator<Item = u32>`). Otherwise, if the projection is on a type
+    /// parameter, we translate it to [ty::Ty::TraitAssoc].
+    fn translate_projection_ty<R>(
+        &mut self,
+        region_translator: &dyn Fn(&rustc_middle::ty::RegionKind<'tcx>) -> R,
+        ty_kind: &TyKind<'tcx>,
+        alias_ty: &AliasTy<'tcx>,
+    ) -> TransResult<ty::Ty<R>>
+    where
+        R: Clone + Eq,
+    {
+        let tcx = self.t_ctx.tcx;
+        let ty = tcx.mk_alias(AliasKind::Projection, *alias_ty);
+        let param_env = tcx.param_env(self.def_id);
+        if let Ok(normalized) = tcx.try_normalize_erasing_regions(param_env, ty) {
+            let is_projection =
+                matches!(normalized.kind(), TyKind::Alias(AliasKind::Projection, _));
+            // The normalization erases the regions: we can't use the result
+            // if this made us lose region information (in the signatures)
+            let lost_regions = normalized.has_erased_regions() && !ty.has_erased_regions();
+            if !is_projection && !lost_regions {
+                return self.translate_ty(region_translator, &normalized);
+            }
+        }
+
+        // We can't resolve the projection: we only support the projections
+        // on a type parameter, for traits without parameters (besides `Self`)
+        match alias_ty.self_ty().kind() {
+            TyKind::Param(param) if alias_ty.substs.len() == 1 => {
+                let trait_id = self
+                    .t_ctx
+                    .translate_trait_decl_id(alias_ty.trait_def_id(tcx));
+                let var_id = *self.type_vars_map.get(&param.index).unwrap();
+                let name = tcx.item_name(alias_ty.def_id).to_ident_string();
+                Ok(ty::Ty::TraitAssoc(trait_id, var_id, name))
+            }
+            _ => self.unsupported_ty(ty_kind),
+        }
+    }
+
     /// Translate a type where the regions are erased
     /// Simply calls [translate_ty]
-    pub(crate) fn translate_ety(&mut self, ty: &Ty<'tcx>) -> Result<ty::ETy> {
+    pub(crate) fn translate_ety(&mut self, ty: &Ty<'tcx>) -> TransResult<ty::ETy> {
         self.translate_ty(&|r| translate_erased_region(*r), ty)
     }
 
@@ -288,7 +487,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         region_translator: &dyn Fn(&rustc_middle::ty::RegionKind<'tcx>) -> R,
         used_params: Option<Vec<bool>>,
         substs: &rustc_middle::ty::subst::SubstsRef<'tcx>,
-    ) -> Result<(Vec<R>, Vec<ty::Ty<R>>, Vec<ConstGeneric>)>
+    ) -> TransResult<(Vec<R>, Vec<ty::Ty<R>>, Vec<ConstGeneric>)>
     where
         R: Clone + Eq,
     {
@@ -320,7 +519,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
 
         let mut regions: Vec<R> = vec![];
         let mut params = vec![];
-        let cgs = vec![];
+        let mut cgs = vec![];
         for (param, param_i) in substs.iter() {
             trace!("Adt: param {}: {:?}", param_i, param);
             match param.unpack() {
@@ -331,38 +530,325 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 rustc_middle::ty::subst::GenericArgKind::Lifetime(region) => {
                     regions.push(region_translator(&region));
                 }
-                rustc_middle::ty::subst::GenericArgKind::Const(_) => {
-                    unimplemented!();
+                rustc_middle::ty::subst::GenericArgKind::Const(c) => {
+                    cgs.push(self.translate_const_kind_as_const_generic(c)?);
                 }
             }
         }
 
-        Result::Ok((regions, params, cgs))
+        Ok((regions, params, cgs))
     }
 
-    /// Translate a type def id
-    pub(crate) fn translate_type_id(&mut self, def_id: DefId) -> ty::TypeId {
+    /// Introduce the parameters of an identity substitution (see
+    /// [rustc_middle::ty::subst::InternalSubsts::identity_for_item]) in the
+    /// context.
+    pub(crate) fn push_generic_params(&mut self, substs: rustc_middle::ty::subst::SubstsRef<'tcx>) {
+        for p in substs.iter() {
+            match p.unpack() {
+                rustc_middle::ty::subst::GenericArgKind::Type(param_ty) => {
+                    // The type should be a Param:
+                    match param_ty.kind() {
+                        rustc_middle::ty::TyKind::Param(param_ty) => {
+                            let _ =
+                                self.push_type_var(param_ty.index, param_ty.name.to_ident_string());
+                        }
+                        _ => {
+                            panic!("Inconsistent state");
+                        }
+                    }
+                }
+                rustc_middle::ty::subst::GenericArgKind::Lifetime(region) => {
+                    let name = translate_region_name(&region);
+                    let _ = self.push_region(*region, name);
+                }
+                rustc_middle::ty::subst::GenericArgKind::Const(c) => {
+                    // The type should be primitive, meaning it shouldn't contain variables,
+                    // non-primitive adts, etc. As a result, we can use an empty context.
+                    let ty = self.translate_ety(&c.ty()).unwrap();
+                    let ty = ty.to_literal();
+                    match c.kind() {
+                        rustc_middle::ty::ConstKind::Param(cp) => {
+                            self.push_const_generic_var(cp.index, ty, cp.name.to_ident_string());
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Translate the default values of the const generic parameters of a
+    /// type declaration, once its generic parameters have been introduced in
+    /// the context (see [Self::push_generic_params]).
+    pub(crate) fn translate_const_generic_defaults(&mut self, def_id: DefId) {
+        let tcx = self.t_ctx.tcx;
+        for param in &tcx.generics_of(def_id).params {
+            if let rustc_middle::ty::GenericParamDefKind::Const {
+                has_default: true, ..
+            } = param.kind
+            {
+                // The default may refer to the other parameters of the
+                // declaration, which have already been introduced
+                let default = tcx.const_param_default(param.def_id).subst_identity();
+                let var_id = *self.const_generic_vars_map.get(&param.index).unwrap();
+                match self.translate_const_kind_as_const_generic(default) {
+                    Ok(default) => {
+                        self.const_generic_vars.get_mut(var_id).unwrap().default = Some(default)
+                    }
+                    Err(err) => self.t_ctx.push_error(*err),
+                }
+            }
+        }
+    }
+
+    /// Translate a reference to a trait, appearing for instance in a trait
+    /// object (`dyn Trait<T>`).
+    ///
+    /// Note that the substitution doesn't contain the `Self` type.
+    pub(crate) fn translate_trait_ref<R>(
+        &mut self,
+        region_translator: &dyn Fn(&rustc_middle::ty::RegionKind<'tcx>) -> R,
+        def_id: DefId,
+        substs: &rustc_middle::ty::subst::SubstsRef<'tcx>,
+    ) -> TransResult<ty::TraitRef<R>>
+    where
+        R: Clone + Eq,
+    {
         trace!("{:?}", def_id);
 
-        if def_id.is_local() {
-            ty::TypeId::Adt(self.translate_type_decl_id(def_id))
-        } else {
-            // Non-local: check if the type has primitive support
+        let trait_name = trait_def_id_to_name(self.t_ctx.tcx, def_id);
+        // The regions are not relevant here: the lifetime bound of the trait
+        // object is recorded in [ty::Ty::TraitObject].
+        let (_, types, const_generics) = self.translate_substs(region_translator, None, substs)?;
+        Ok(ty::TraitRef {
+            trait_name,
+            types,
+            const_generics,
+            assoc_types: Vec::new(),
+        })
+    }
 
-            // Retrieve the type name
-            let name = type_def_id_to_name(self.t_ctx.tcx, def_id);
+    /// Translate the bounds of an `impl Trait` type (in return position).
+    ///
+    /// The associated type constraints (`Item = u32` in `impl Iterator<Item = u32>`)
+    /// are attached to the trait which defines them.
+    fn translate_impl_trait_bounds(&mut self, def_id: DefId) -> TransResult<Vec<ty::RTraitRef>> {
+        trace!("{:?}", def_id);
+        let tcx = self.t_ctx.tcx;
+        let region_vars_map = self.region_vars_map.clone();
+        let region_translator = |r: &rustc_middle::ty::RegionKind<'tcx>| {
+            translate_non_erased_region(&region_vars_map, *r)
+        };
 
-            match assumed::get_type_id_from_name(&name) {
-                Option::Some(id) => {
-                    // The type has primitive support
-                    ty::TypeId::Assumed(id)
+        let mut traits: Vec<(DefId, ty::RTraitRef)> = vec![];
+        let mut assoc_types: Vec<(DefId, String, ty::RTy)> = vec![];
+        for (pred, _span) in tcx.explicit_item_bounds(def_id).skip_binder() {
+            let pred_kind = pred.kind();
+            // We don't support higher-ranked bounds for now
+            if !pred_kind.bound_vars().is_empty() {
+                return Err(Box::new(TranslationError::UnsupportedType {
+                    span: self.def_meta(),
+                    ty: format!("{pred:?}"),
+                }));
+            }
+            match pred_kind.skip_binder() {
+                PredicateKind::Clause(Clause::Trait(trait_pred)) => {
+                    let trait_id = trait_pred.trait_ref.def_id;
+                    let trait_name = trait_def_id_to_name(tcx, trait_id);
+                    if trait_name.equals_ref_name(&assumed::MARKER_SIZED_NAME) {
+                        continue;
+                    }
+                    // The first parameter is the `Self` type
+                    let substs = tcx.mk_substs(&trait_pred.trait_ref.substs[1..]);
+                    let trait_ref =
+                        self.translate_trait_ref(&region_translator, trait_id, &substs)?;
+                    traits.push((trait_id, trait_ref));
+                }
+                PredicateKind::Clause(Clause::Projection(proj)) => {
+                    let assoc_id = proj.projection_ty.def_id;
+                    let ty = match proj.term.ty() {
+                        Option::Some(ty) => self.translate_ty(&region_translator, &ty)?,
+                        Option::None => {
+                            return Err(Box::new(TranslationError::UnsupportedType {
+                                span: self.def_meta(),
+                                ty: format!("{pred:?}"),
+                            }))
+                        }
+                    };
+                    let name = tcx.item_name(assoc_id).to_string();
+                    assoc_types.push((tcx.parent(assoc_id), name, ty));
+                }
+                PredicateKind::Clause(Clause::TypeOutlives(_)) => {
+                    // We ignore the lifetime bounds (`impl Trait + 'a`)
+                }
+                _ => {
+                    return Err(Box::new(TranslationError::UnsupportedType {
+                        span: self.def_meta(),
+                        ty: format!("{pred:?}"),
+                    }))
+                }
+            }
+        }
+
+        // Attach the associated types to the traits. If the trait which defines
+        // an associated type is not in the list (it can be a parent trait), we
+        // attach it to the first trait.
+        for (trait_id, name, ty) in assoc_types {
+            let pos = traits
+                .iter()
+                .position(|(id, _)| *id == trait_id)
+                .unwrap_or(0);
+            traits[pos].1.assoc_types.push((name, ty));
+        }
+
+        Ok(traits.into_iter().map(|(_, tr)| tr).collect())
+    }
+
+    /// Translate the predicates (where clauses) of a definition.
+    ///
+    /// We ignore the `Sized` bounds (they are implicit) and the lifetime
+    /// bounds. The associated type constraints (`Item = u32` in
+    /// `T: Iterator<Item = u32>`) are attached to the bound on the trait
+    /// which defines the associated type or, if the trait is a parent trait
+    /// (for instance `FnOnce`, which defines the `Output` of `Fn`), to the
+    /// last bound on the same type.
+    ///
+    /// Note that this must be called once all the region parameters have been
+    /// introduced: the regions bound by the predicates are numbered after them.
+    pub(crate) fn translate_predicates(
+        &mut self,
+        def_id: DefId,
+    ) -> TransResult<Vec<ty::Predicate>> {
+        let tcx = self.t_ctx.tcx;
+        let mut preds: Vec<(DefId, ty::Predicate)> = vec![];
+        let mut assoc_types: Vec<(DefId, ty::RTy, String, ty::RTy)> = vec![];
+        for (pred, _span) in tcx.predicates_of(def_id).predicates {
+            // Introduce identifiers for the regions bound by the predicate.
+            // We don't introduce region parameters: the bound regions are
+            // only in scope in the predicate.
+            let (pred_kind, bound_regions) =
+                generics::replace_late_bound_regions(tcx, pred.kind(), def_id);
+            let snapshot = self.snapshot();
+            let mut binders: Vec<ty::RegionVar> = vec![];
+            for (_, region) in &bound_regions {
+                let index = ty::RegionVarId::Id::new(self.region_vars.len() + binders.len());
+                let name = translate_region_name(region);
+                self.region_vars_map.insert(**region, index);
+                binders.push(ty::RegionVar { index, name });
+            }
+
+            let res = self.translate_predicate_kind(&pred_kind);
+            self.rollback(snapshot);
+            match res? {
+                Option::None => (),
+                Option::Some(TranslatedPredicate::Trait(trait_id, pred)) => {
+                    let pred = if binders.is_empty() {
+                        pred
+                    } else {
+                        ty::Predicate::HigherRanked {
+                            binders,
+                            pred: Box::new(pred),
+                        }
+                    };
+                    preds.push((trait_id, pred))
                 }
+                Option::Some(TranslatedPredicate::AssocType(trait_id, self_ty, name, ty)) => {
+                    assoc_types.push((trait_id, self_ty, name, ty))
+                }
+            }
+        }
+
+        // Attach the associated types to the trait bounds
+        for (trait_id, self_ty, name, ty) in assoc_types {
+            let pos = preds
+                .iter()
+                .position(|(id, pred)| *id == trait_id && pred.self_ty() == &self_ty)
+                .or_else(|| {
+                    preds
+                        .iter()
+                        .rposition(|(_, pred)| pred.self_ty() == &self_ty)
+                });
+            match pos {
+                Option::Some(pos) => preds[pos].1.trait_ref_mut().assoc_types.push((name, ty)),
                 Option::None => {
-                    // The type is external
-                    ty::TypeId::Adt(self.translate_type_decl_id(def_id))
+                    return Err(Box::new(TranslationError::UnsupportedType {
+                        span: self.def_meta(),
+                        ty: format!("associated type constraint: {name}"),
+                    }))
                 }
             }
         }
+
+        Ok(preds.into_iter().map(|(_, pred)| pred).collect())
+    }
+
+    /// Translate a predicate, once its bound regions have been introduced
+    /// (see [Self::translate_predicates]).
+    fn translate_predicate_kind(
+        &mut self,
+        pred_kind: &PredicateKind<'tcx>,
+    ) -> TransResult<Option<TranslatedPredicate>> {
+        let tcx = self.t_ctx.tcx;
+        let region_vars_map = self.region_vars_map.clone();
+        let region_translator = |r: &rustc_middle::ty::RegionKind<'tcx>| {
+            translate_non_erased_region(&region_vars_map, *r)
+        };
+        match pred_kind {
+            PredicateKind::Clause(Clause::Trait(trait_pred)) => {
+                use rustc_middle::ty::ImplPolarity;
+                assert!(trait_pred.polarity == ImplPolarity::Positive);
+                let trait_id = trait_pred.trait_ref.def_id;
+                let trait_name = trait_def_id_to_name(tcx, trait_id);
+                if trait_name.equals_ref_name(&assumed::MARKER_SIZED_NAME) {
+                    return Ok(None);
+                }
+                let self_ty = self.translate_ty(&region_translator, &trait_pred.self_ty())?;
+                // The first parameter is the `Self` type
+                let substs = tcx.mk_substs(&trait_pred.trait_ref.substs[1..]);
+                let trait_ref = self.translate_trait_ref(&region_translator, trait_id, &substs)?;
+                Ok(Some(TranslatedPredicate::Trait(
+                    trait_id,
+                    ty::Predicate::Trait(self_ty, trait_ref),
+                )))
+            }
+            PredicateKind::Clause(Clause::Projection(proj)) => {
+                let assoc_id = proj.projection_ty.def_id;
+                let self_ty =
+                    self.translate_ty(&region_translator, &proj.projection_ty.self_ty())?;
+                let ty = match proj.term.ty() {
+                    Option::Some(ty) => self.translate_ty(&region_translator, &ty)?,
+                    Option::None => {
+                        return Err(Box::new(TranslationError::UnsupportedType {
+                            span: self.def_meta(),
+                            ty: format!("{pred_kind:?}"),
+                        }))
+                    }
+                };
+                let name = tcx.item_name(assoc_id).to_ident_string();
+                Ok(Some(TranslatedPredicate::AssocType(
+                    tcx.parent(assoc_id),
+                    self_ty,
+                    name,
+                    ty,
+                )))
+            }
+            // We ignore the lifetime bounds, and the predicates which only
+            // make sense for the type checker
+            PredicateKind::Clause(Clause::RegionOutlives(_))
+            | PredicateKind::Clause(Clause::TypeOutlives(_))
+            | PredicateKind::Clause(Clause::ConstArgHasType(..))
+            | PredicateKind::ConstEvaluatable(_)
+            | PredicateKind::WellFormed(_) => Ok(None),
+            _ => Err(Box::new(TranslationError::UnsupportedType {
+                span: self.def_meta(),
+                ty: format!("{pred_kind:?}"),
+            })),
+        }
+    }
+
+    /// Translate a type def id
+    pub(crate) fn translate_type_id(&mut self, def_id: DefId) -> ty::TypeId {
+        self.t_ctx.translate_type_id(def_id)
     }
 
     /// Translate one local type definition which has not been flagged as opaque.
@@ -370,7 +856,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
         &mut self,
         trans_id: ty::TypeDeclId::Id,
         substs: &rustc_middle::ty::subst::SubstsRef<'tcx>,
-    ) -> ty::TypeDeclKind {
+    ) -> TransResult<ty::TypeDeclKind> {
         trace!("{}", trans_id);
 
         // Retrieve the definition
@@ -394,7 +880,7 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                 let ty = field_def.ty(self.t_ctx.tcx, substs);
 
                 // Translate the field type
-                let ty = self.translate_sig_ty(&ty).unwrap();
+                let ty = self.translate_sig_ty(&ty)?;
 
                 // Retrieve the field name.
                 // Note that the only way to check if the user wrote the name or
@@ -429,6 +915,8 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
                     meta,
                     name: field_name.clone(),
                     ty,
+                    // Computed later (see [crate::detect_interior_mutability])
+                    interior_mut: false,
                 };
                 fields.push(field);
 
@@ -454,16 +942,65 @@ impl<'tcx, 'ctx, 'ctx1> BodyTransCtx<'tcx, 'ctx, 'ctx1> {
             rustc_middle::ty::AdtKind::Enum => {
                 ty::TypeDeclKind::Enum(ty::VariantId::Vector::from(variants))
             }
-            rustc_middle::ty::AdtKind::Union => {
-                unimplemented!();
-            }
+            rustc_middle::ty::AdtKind::Union => ty::TypeDeclKind::Union(variants[0].fields.clone()),
         };
 
-        type_def_kind
+        Ok(type_def_kind)
     }
 }
 
 impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
+    /// Translate a type def id
+    pub(crate) fn translate_type_id(&mut self, def_id: DefId) -> ty::TypeId {
+        trace!("{:?}", def_id);
+
+        if def_id.is_local() {
+            ty::TypeId::Adt(self.translate_type_decl_id(def_id))
+        } else {
+            // Non-local: check if the type has primitive support
+
+            // Retrieve the type name
+            let name = type_def_id_to_name(self.tcx, def_id);
+
+            match assumed::get_type_id_from_name(&name) {
+                Option::Some(id) => {
+                    // The type has primitive support
+                    ty::TypeId::Assumed(id)
+                }
+                Option::None => {
+                    // The type is external
+                    ty::TypeId::Adt(self.translate_type_decl_id(def_id))
+                }
+            }
+        }
+    }
+
+    /// Translate the `#[repr(...)]` attributes of a type definition.
+    ///
+    /// Only the ADTs can have such attributes: we return the default
+    /// representation for the other types (opaque types, etc.).
+    fn translate_repr(&self, id: DefId) -> ty::ReprConfig {
+        match self.tcx.def_kind(id) {
+            DefKind::Struct | DefKind::Enum | DefKind::Union => (),
+            _ => return ty::ReprConfig::rust(),
+        }
+        let repr = self.tcx.adt_def(id).repr();
+        let kind = if repr.c() {
+            ty::ReprKind::C
+        } else if repr.transparent() {
+            ty::ReprKind::Transparent
+        } else if let Some(int) = repr.int {
+            ty::ReprKind::Int(translate_integer_type(int))
+        } else {
+            ty::ReprKind::Rust
+        };
+        ty::ReprConfig {
+            kind,
+            packed: repr.pack.map(|a| a.bytes()),
+            align: repr.align.map(|a| a.bytes()),
+        }
+    }
+
     /// Auxiliary helper.
     ///
     /// Translate the generics of a type definition.
@@ -473,6 +1010,43 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
     ///
     /// Rem.: this seems simpler in [crate::translate_functions_to_ullbc].
     /// TODO: compare and simplify/factorize?
+    /// Translate the variance of the generic parameters of a type.
+    ///
+    /// Rustc lists the variances in the order of the identity substitution:
+    /// we reorder them so that the region parameters come first, then the
+    /// type parameters, then the const generic parameters (see
+    /// [ty::TypeDecl::variance]).
+    fn translate_variance(
+        &self,
+        def_id: DefId,
+        substs: rustc_middle::ty::subst::SubstsRef<'tcx>,
+    ) -> Vec<ty::Variance> {
+        match self.tcx.def_kind(def_id) {
+            DefKind::Struct | DefKind::Enum | DefKind::Union | DefKind::OpaqueTy => (),
+            // Rustc doesn't compute the variances of the other types (for
+            // instance, the foreign types, which can't have parameters)
+            _ => return Vec::new(),
+        }
+        let variances = self.tcx.variances_of(def_id);
+        let mut regions = Vec::new();
+        let mut types = Vec::new();
+        let mut cgs = Vec::new();
+        for (p, v) in substs.iter().zip(variances.iter()) {
+            let v = match v {
+                rustc_middle::ty::Variance::Covariant => ty::Variance::Covariant,
+                rustc_middle::ty::Variance::Contravariant => ty::Variance::Contravariant,
+                rustc_middle::ty::Variance::Invariant => ty::Variance::Invariant,
+                rustc_middle::ty::Variance::Bivariant => ty::Variance::Bivariant,
+            };
+            match p.unpack() {
+                rustc_middle::ty::subst::GenericArgKind::Lifetime(_) => regions.push(v),
+                rustc_middle::ty::subst::GenericArgKind::Type(_) => types.push(v),
+                rustc_middle::ty::subst::GenericArgKind::Const(_) => cgs.push(v),
+            }
+        }
+        regions.into_iter().chain(types).chain(cgs).collect()
+    }
+
     fn translate_type_generics<'ctx1>(
         &'ctx1 mut self,
         def_id: DefId,
@@ -480,51 +1054,13 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         BodyTransCtx<'tcx, 'ctx1, 'ctx>,
         rustc_middle::ty::subst::SubstsRef<'tcx>,
     ) {
-        // Check the generics
-        generics::check_type_generics(self.tcx, def_id);
-
         // Use a dummy substitution to instantiate the type parameters
         let substs = rustc_middle::ty::subst::InternalSubsts::identity_for_item(self.tcx, def_id);
 
         // Initialize the body translation context
         let mut bt_ctx = BodyTransCtx::new(def_id, self);
-
-        for p in substs.iter() {
-            match p.unpack() {
-                rustc_middle::ty::subst::GenericArgKind::Type(param_ty) => {
-                    // The type should be a Param:
-                    match param_ty.kind() {
-                        rustc_middle::ty::TyKind::Param(param_ty) => {
-                            let _ = bt_ctx
-                                .push_type_var(param_ty.index, param_ty.name.to_ident_string());
-                        }
-                        _ => {
-                            panic!("Inconsistent state");
-                        }
-                    }
-                }
-                rustc_middle::ty::subst::GenericArgKind::Lifetime(region) => {
-                    let name = translate_region_name(&region);
-                    let _ = bt_ctx.push_region(*region, name);
-                }
-                rustc_middle::ty::subst::GenericArgKind::Const(c) => {
-                    // The type should be primitive, meaning it shouldn't contain variables,
-                    // non-primitive adts, etc. As a result, we can use an empty context.
-                    let ty = bt_ctx.translate_ety(&c.ty()).unwrap();
-                    let ty = ty.to_literal();
-                    match c.kind() {
-                        rustc_middle::ty::ConstKind::Param(cp) => {
-                            let _ = bt_ctx.push_const_generic_var(
-                                cp.index,
-                                ty,
-                                cp.name.to_ident_string(),
-                            );
-                        }
-                        _ => unreachable!(),
-                    }
-                }
-            }
-        }
+        bt_ctx.push_generic_params(substs);
+        bt_ctx.translate_const_generic_defaults(def_id);
 
         // Sanity check: region names are pairwise distinct (this caused trouble
         // when generating names for the backward functinos in Aeneas): at some
@@ -537,7 +1073,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
                 let name = &r.name;
                 if name.is_some() {
                     let name = name.as_ref().unwrap();
-                    assert!(s.contains(name));
+                    assert!(!s.contains(name));
                     s.insert(name.clone());
                 }
             }
@@ -555,7 +1091,7 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         let trans_id = self.translate_type_decl_id(id);
         let is_transparent = self.id_is_transparent(id);
 
-        // Check and translate the generics
+        // Translate the generics
         // TODO: use the body trans context as input, and don't return anything.
         let (mut bt_ctx, substs) = self.translate_type_generics(id);
 
@@ -566,8 +1102,35 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
             // - external types
             // - local types flagged as opaque
             ty::TypeDeclKind::Opaque
+        } else if let Err(pred) = generics::check_type_generics(bt_ctx.t_ctx.tcx, id) {
+            // We don't support the where clauses of the types: we record the
+            // error, and make the type opaque
+            let span = bt_ctx.def_meta();
+            bt_ctx.t_ctx.push_error(TranslationError::UnsupportedType {
+                span,
+                ty: format!("where clause: {pred}"),
+            });
+            ty::TypeDeclKind::Opaque
         } else {
-            bt_ctx.translate_transparent_type(trans_id, &substs)
+            let translated = match bt_ctx.t_ctx.tcx.def_kind(id) {
+                DefKind::OpaqueTy => bt_ctx
+                    .translate_impl_trait_bounds(id)
+                    .map(ty::TypeDeclKind::ImplTrait),
+                DefKind::TyAlias => {
+                    let ty = bt_ctx.t_ctx.tcx.type_of(id).subst_identity();
+                    bt_ctx.translate_sig_ty(&ty).map(ty::TypeDeclKind::Alias)
+                }
+                _ => bt_ctx.translate_transparent_type(trans_id, &substs),
+            };
+            match translated {
+                Ok(kind) => kind,
+                Err(err) => {
+                    // We couldn't translate the type: we record the error,
+                    // and make the type opaque
+                    bt_ctx.t_ctx.push_error(*err);
+                    ty::TypeDeclKind::Opaque
+                }
+            }
         };
 
         // Register the type
@@ -579,6 +1142,10 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
         // Translate the span information
         let meta = bt_ctx.translate_meta_from_rid(id);
 
+        let repr = bt_ctx.t_ctx.translate_repr(id);
+        let variance = bt_ctx.t_ctx.translate_variance(id, substs);
+        let is_root = bt_ctx.t_ctx.root_ids.contains(&AnyTransId::Type(trans_id));
+
         let type_def = ty::TypeDecl {
             def_id: trans_id,
             meta,
@@ -587,6 +1154,12 @@ impl<'tcx, 'ctx> TransCtx<'tcx, 'ctx> {
             type_params,
             const_generic_params,
             kind,
+            repr,
+            is_root,
+            stable_id: None,
+            local_methods: Vec::new(),
+            variance,
+            is_recursive: false,
             regions_hierarchy: RegionGroups::new(),
         };
 
@@ -0,0 +1,287 @@
+//! Compute the dependency graph of the translated type declarations.
+//!
+//! This is meant to be used by the tools consuming the translated crate which
+//! need to process the types in topological order (i.e., a type after the
+//! types it references).
+#![allow(dead_code)]
+
+use crate::types::*;
+use petgraph::algo::tarjan_scc;
+use petgraph::graphmap::DiGraphMap;
+use std::collections::{BTreeSet, HashSet};
+
+pub struct TypeDepGraph {
+    /// For every type, the set of types it directly references in its fields
+    /// and variants.
+    pub deps: TypeDeclId::Map<BTreeSet<TypeDeclId::Id>>,
+    /// The strongly connected components of the graph, in topological order
+    sccs: Vec<Vec<TypeDeclId::Id>>,
+    /// The types which are (mutually) recursive
+    recursive: HashSet<TypeDeclId::Id>,
+}
+
+/// Collect the types referenced by a type declaration
+struct TypeDeclIdsCollector {
+    ids: BTreeSet<TypeDeclId::Id>,
+}
+
+impl SharedTypeVisitor for TypeDeclIdsCollector {
+    fn visit_type_decl_id(&mut self, id: &TypeDeclId::Id) {
+        self.ids.insert(*id);
+    }
+}
+
+impl TypeDeclIdsCollector {
+    fn visit_fields(&mut self, fields: &FieldId::Vector<Field>) {
+        for f in fields {
+            self.visit_ty(&f.ty);
+        }
+    }
+
+    fn visit_type_decl(&mut self, decl: &TypeDecl) {
+        match &decl.kind {
+            TypeDeclKind::Struct(fields) | TypeDeclKind::Union(fields) => self.visit_fields(fields),
+            TypeDeclKind::Enum(variants) => {
+                for v in variants {
+                    self.visit_fields(&v.fields);
+                }
+            }
+            TypeDeclKind::ImplTrait(traits) => {
+                for tr in traits {
+                    self.visit_trait_ref(tr);
+                }
+            }
+            TypeDeclKind::Alias(ty) => self.visit_ty(ty),
+            TypeDeclKind::Opaque => (),
+        }
+    }
+}
+
+impl TypeDepGraph {
+    /// Build the graph from the dependencies of every type.
+    pub fn new(deps: TypeDeclId::Map<BTreeSet<TypeDeclId::Id>>) -> Self {
+        let mut graph: DiGraphMap<TypeDeclId::Id, ()> = DiGraphMap::new();
+        for (id, ids) in deps.iter_indexed() {
+            graph.add_node(*id);
+            for dep in ids {
+                graph.add_edge(*id, *dep, ());
+            }
+        }
+
+        // [tarjan_scc] returns the components in reverse topological order:
+        // as the edges go from a type to its dependencies, the dependencies
+        // come first.
+        let sccs = tarjan_scc(&graph);
+        let mut recursive = HashSet::new();
+        for scc in &sccs {
+            if scc.len() > 1 || graph.contains_edge(scc[0], scc[0]) {
+                recursive.extend(scc.iter().copied());
+            }
+        }
+
+        TypeDepGraph {
+            deps,
+            sccs,
+            recursive,
+        }
+    }
+
+    /// Return the types directly referenced by a type.
+    pub fn get_deps(&self, id: TypeDeclId::Id) -> Option<&BTreeSet<TypeDeclId::Id>> {
+        self.deps.get(id)
+    }
+
+    /// Order the types so that a type comes after the types it references.
+    ///
+    /// A type which references itself (like a list) doesn't prevent us from
+    /// ordering the declarations, but mutually recursive types do: in this
+    /// case, we return one of the groups of mutually recursive types.
+    pub fn topological_order(&self) -> Result<Vec<TypeDeclId::Id>, Vec<TypeDeclId::Id>> {
+        let mut order = Vec::new();
+        for scc in &self.sccs {
+            if scc.len() > 1 {
+                let mut group = scc.clone();
+                group.sort();
+                return Err(group);
+            }
+            order.push(scc[0]);
+        }
+        Ok(order)
+    }
+
+    /// Return `true` if the type is recursive, or part of a group of mutually
+    /// recursive types.
+    pub fn is_recursive(&self, id: TypeDeclId::Id) -> bool {
+        self.recursive.contains(&id)
+    }
+}
+
+/// Build the dependency graph of a set of translated types.
+pub fn build_type_dep_graph(type_defs: &TypeDecls) -> TypeDepGraph {
+    let mut deps = TypeDeclId::Map::new();
+    for (id, decl) in type_defs.iter_indexed() {
+        let mut collector = TypeDeclIdsCollector {
+            ids: BTreeSet::new(),
+        };
+        collector.visit_type_decl(decl);
+        deps.insert(*id, collector.ids);
+    }
+    TypeDepGraph::new(deps)
+}
+
+/// Mark the recursive types (see [TypeDecl::is_recursive]).
+pub fn compute_recursive_types(type_defs: &mut TypeDecls) {
+    let graph = build_type_dep_graph(type_defs);
+    for d in type_defs.iter_mut() {
+        d.is_recursive = graph.is_recursive(d.def_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::meta::*;
+    use crate::names::*;
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::types::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn field(ty: RTy) -> Field {
+        Field {
+            meta: dummy_meta(),
+            name: None,
+            ty,
+            interior_mut: false,
+        }
+    }
+
+    fn adt(id: TypeDeclId::Id, tys: Vec<RTy>) -> RTy {
+        Ty::Adt(TypeId::Adt(id), Vec::new(), tys, Vec::new())
+    }
+
+    fn decl(def_id: TypeDeclId::Id, name: &str, kind: TypeDeclKind) -> TypeDecl {
+        TypeDecl {
+            def_id,
+            meta: dummy_meta(),
+            name: Name {
+                name: vec![PathElem::Ident(name.to_string())],
+            },
+            region_params: RegionVarId::Vector::new(),
+            type_params: TypeVarId::Vector::new(),
+            const_generic_params: ConstGenericVarId::Vector::new(),
+            kind,
+            repr: ReprConfig::rust(),
+            is_root: true,
+            stable_id: None,
+            local_methods: Vec::new(),
+            variance: Vec::new(),
+            is_recursive: false,
+            regions_hierarchy: RegionGroups::new(),
+        }
+    }
+
+    /// ```text
+    /// struct Pair { x: u32, y: u32 }
+    /// enum List { Cons(Pair, Box<List>), Nil }
+    /// struct Even { odd: Option<Box<Odd>> }
+    /// struct Odd { even: Box<Even> }
+    /// ```
+    #[test]
+    fn test_list_pair() {
+        let mut gen = TypeDeclId::Generator::new();
+        let pair = gen.fresh_id();
+        let list = gen.fresh_id();
+        let even = gen.fresh_id();
+        let odd = gen.fresh_id();
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let boxed = |ty: RTy| {
+            Ty::Adt(
+                TypeId::Assumed(AssumedTy::Box),
+                Vec::new(),
+                vec![ty],
+                Vec::new(),
+            )
+        };
+
+        let mut type_defs = TypeDecls::new();
+        type_defs.insert(
+            pair,
+            decl(
+                pair,
+                "Pair",
+                TypeDeclKind::Struct(vec![field(u32_ty.clone()), field(u32_ty)].into()),
+            ),
+        );
+        let variants = vec![
+            Variant {
+                meta: dummy_meta(),
+                name: "Cons".to_string(),
+                fields: vec![
+                    field(adt(pair, Vec::new())),
+                    field(boxed(adt(list, Vec::new()))),
+                ]
+                .into(),
+            },
+            Variant {
+                meta: dummy_meta(),
+                name: "Nil".to_string(),
+                fields: FieldId::Vector::new(),
+            },
+        ];
+        type_defs.insert(
+            list,
+            decl(list, "List", TypeDeclKind::Enum(variants.into())),
+        );
+
+        // Without the mutually recursive types
+        let graph = super::build_type_dep_graph(&type_defs);
+        assert!(graph.is_recursive(list));
+        assert!(!graph.is_recursive(pair));
+        assert!(graph.get_deps(list).unwrap() == &vec![pair, list].into_iter().collect());
+        assert!(graph.topological_order() == Ok(vec![pair, list]));
+        super::compute_recursive_types(&mut type_defs);
+        assert!(type_defs.get(list).unwrap().is_recursive);
+        assert!(!type_defs.get(pair).unwrap().is_recursive);
+
+        // With the mutually recursive types
+        let option = |ty: RTy| {
+            Ty::Adt(
+                TypeId::Assumed(AssumedTy::Option),
+                Vec::new(),
+                vec![ty],
+                Vec::new(),
+            )
+        };
+        type_defs.insert(
+            even,
+            decl(
+                even,
+                "Even",
+                TypeDeclKind::Struct(vec![field(option(boxed(adt(odd, Vec::new()))))].into()),
+            ),
+        );
+        type_defs.insert(
+            odd,
+            decl(
+                odd,
+                "Odd",
+                TypeDeclKind::Struct(vec![field(boxed(adt(even, Vec::new())))].into()),
+            ),
+        );
+        let graph = super::build_type_dep_graph(&type_defs);
+        assert!(graph.is_recursive(even) && graph.is_recursive(odd));
+        assert!(graph.topological_order() == Err(vec![even, odd]));
+    }
+}
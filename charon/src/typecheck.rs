@@ -0,0 +1,706 @@
+//! Type-check the LLBC bodies.
+//!
+//! The translation and the micro-passes manipulate the bodies in many ways,
+//! and it is easy to introduce inconsistencies (for instance, by replacing an
+//! operand with a constant of the wrong type). This pass checks that:
+//! - the right-hand side of an assignment has the type of the assigned place
+//! - the arguments given to a (non-assumed) function have the types expected
+//!   by its signature, and the destination has the type of the output
+//! - the discriminants of the switches have an integer or boolean type
+//!
+//! We are conservative: whenever we can't compute the type of a place or of an
+//! rvalue (for instance, because it projects out of a type we don't know, or
+//! because the rvalue is a pointer cast), we skip the check.
+
+use crate::expressions::*;
+use crate::formatter::Formatter;
+use crate::id_vector::ToUsize;
+use crate::llbc_ast::*;
+use crate::meta::Meta;
+use crate::restore_type_aliases::expand_aliases;
+use crate::types::*;
+use crate::values::*;
+
+/// The kind of type error we found.
+#[derive(Debug, Clone)]
+pub enum TypeCheckErrorKind {
+    /// The right-hand side of an assignment doesn't have the type of the
+    /// assigned place.
+    Assign {
+        place: Place,
+        expected: ETy,
+        found: ETy,
+    },
+    /// The argument of a call (we give its index) doesn't have the type
+    /// expected by the callee.
+    CallArg {
+        index: usize,
+        expected: ETy,
+        found: ETy,
+    },
+    /// The destination of a call doesn't have the type of the output of the
+    /// callee.
+    CallDest { expected: ETy, found: ETy },
+    /// The number of arguments given to a function doesn't match its
+    /// signature.
+    CallArity { expected: usize, found: usize },
+    /// The discriminant of an `if` is not a boolean.
+    IfDiscr(ETy),
+    /// The discriminant of a switch over integers is not an integer or a
+    /// boolean.
+    SwitchIntDiscr(ETy),
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeCheckError {
+    /// The meta information of the statement in which we found the error
+    pub meta: Meta,
+    pub kind: TypeCheckErrorKind,
+}
+
+impl TypeCheckError {
+    pub fn fmt_with_ctx<'a, C>(&'a self, ctx: &C) -> String
+    where
+        C: Formatter<VarId::Id>
+            + Formatter<TypeVarId::Id>
+            + Formatter<&'a ErasedRegion>
+            + Formatter<TypeDeclId::Id>
+            + Formatter<ConstGenericVarId::Id>
+            + Formatter<GlobalDeclId::Id>
+            + Formatter<(TypeDeclId::Id, Option<VariantId::Id>, FieldId::Id)>,
+    {
+        let line = self.meta.span.beg.line;
+        let msg = match &self.kind {
+            TypeCheckErrorKind::Assign {
+                place,
+                expected,
+                found,
+            } => format!(
+                "assignment to {}: expected {}, found {}",
+                place.fmt_with_ctx(ctx),
+                expected.fmt_with_ctx(ctx),
+                found.fmt_with_ctx(ctx)
+            ),
+            TypeCheckErrorKind::CallArg {
+                index,
+                expected,
+                found,
+            } => format!(
+                "argument {index} of call: expected {}, found {}",
+                expected.fmt_with_ctx(ctx),
+                found.fmt_with_ctx(ctx)
+            ),
+            TypeCheckErrorKind::CallDest { expected, found } => format!(
+                "destination of call: expected {}, found {}",
+                expected.fmt_with_ctx(ctx),
+                found.fmt_with_ctx(ctx)
+            ),
+            TypeCheckErrorKind::CallArity { expected, found } => {
+                format!("call: expected {expected} arguments, found {found}")
+            }
+            TypeCheckErrorKind::IfDiscr(ty) => format!(
+                "discriminant of if: expected bool, found {}",
+                ty.fmt_with_ctx(ctx)
+            ),
+            TypeCheckErrorKind::SwitchIntDiscr(ty) => format!(
+                "discriminant of switch: expected an integer or a boolean, found {}",
+                ty.fmt_with_ctx(ctx)
+            ),
+        };
+        format!("line {line}: {msg}")
+    }
+}
+
+struct TypeChecker<'a> {
+    type_defs: &'a TypeDecls,
+    fun_defs: &'a FunDecls,
+    locals: &'a VarId::Vector<Var>,
+    /// The meta information of the statement we are currently exploring
+    meta: Meta,
+    /// The first error we found
+    error: Option<TypeCheckError>,
+}
+
+/// Return `true` if a value of type `found` can be used where a value of
+/// type `expected` is expected. We don't check subtyping: the regions are
+/// erased anyway. The only coercion we allow is the one from `!`.
+///
+/// As we can't resolve the associated types (see [Ty::TraitAssoc]), we
+/// accept any type where one of them appears.
+fn compatible(expected: &ETy, found: &ETy) -> bool {
+    found.is_never()
+        || expected.is_never()
+        || expected.contains_trait_assoc()
+        || found.contains_trait_assoc()
+        || expected == found
+}
+
+/// Check if a type refers to one of the opaque `impl Trait` types (see
+/// [TypeDeclKind::ImplTrait]): we don't know the types they hide, and accept
+/// any type where one of them appears.
+struct ImplTraitFinder<'a> {
+    type_defs: &'a TypeDecls,
+    found: bool,
+}
+
+impl<'a> SharedTypeVisitor for ImplTraitFinder<'a> {
+    fn visit_type_decl_id(&mut self, id: &TypeDeclId::Id) {
+        if let Some(def) = self.type_defs.get(*id) {
+            self.found |= def.kind.is_impl_trait();
+        }
+    }
+}
+
+fn contains_impl_trait(type_defs: &TypeDecls, ty: &ETy) -> bool {
+    let mut finder = ImplTraitFinder {
+        type_defs,
+        found: false,
+    };
+    finder.visit_ty(ty);
+    finder.found
+}
+
+/// Substitute the type parameters and the const generic parameters in a type
+/// coming from a declaration, and erase its regions.
+///
+/// Return `None` if the number of arguments doesn't match the number of
+/// parameters.
+fn instantiate(
+    ty: &RTy,
+    type_params: &TypeVarId::Vector<TypeVar>,
+    cg_params: &ConstGenericVarId::Vector<ConstGenericVar>,
+    type_args: &[ETy],
+    cg_args: &[ConstGeneric],
+) -> Option<ETy> {
+    if type_params.len() != type_args.len() || cg_params.len() != cg_args.len() {
+        return None;
+    }
+    let tsubst = make_type_subst(type_params.iter().map(|p| p.index), type_args.iter());
+    let cgsubst = make_cg_subst(cg_params.iter().map(|p| p.index), cg_args.iter());
+    Some(ty.erase_regions_substitute_types(&tsubst, &cgsubst))
+}
+
+/// Compute the type of the fields of an ADT.
+fn adt_field_ty(
+    type_defs: &TypeDecls,
+    id: TypeDeclId::Id,
+    variant_id: Option<VariantId::Id>,
+    field_id: FieldId::Id,
+    type_args: &[ETy],
+    cg_args: &[ConstGeneric],
+) -> Option<ETy> {
+    let def = type_defs.get(id)?;
+    let fields = match (&def.kind, variant_id) {
+        (TypeDeclKind::Struct(fields), None) => fields,
+        (TypeDeclKind::Union(fields), None) => fields,
+        (TypeDeclKind::Enum(variants), Some(variant_id)) => &variants.get(variant_id)?.fields,
+        _ => return None,
+    };
+    instantiate(
+        &fields.get(field_id)?.ty,
+        &def.type_params,
+        &def.const_generic_params,
+        type_args,
+        cg_args,
+    )
+}
+
+/// Compute the type of a projected place, from the type of the place we
+/// project from (see [ProjectionElem]).
+///
+/// Return `None` if the projection doesn't apply to the type, or projects out
+/// of a type we don't know.
+pub(crate) fn project_ty(type_defs: &TypeDecls, ty: &ETy, pe: &ProjectionElem) -> Option<ETy> {
+    match (pe, ty) {
+        (ProjectionElem::Deref, Ty::Ref(_, ty, _))
+        | (ProjectionElem::Deref, Ty::RawPtr(ty, _))
+        | (ProjectionElem::DerefRawPtr, Ty::RawPtr(ty, _)) => Some((**ty).clone()),
+        (ProjectionElem::DerefBox, Ty::Adt(TypeId::Assumed(AssumedTy::Box), _, tys, _)) => {
+            tys.first().cloned()
+        }
+        (
+            ProjectionElem::Field(FieldProjKind::Tuple(_), field_id),
+            Ty::Adt(TypeId::Tuple, _, tys, _),
+        ) => tys.get(field_id.to_usize()).cloned(),
+        (
+            ProjectionElem::Field(FieldProjKind::Option(_), _),
+            Ty::Adt(TypeId::Assumed(AssumedTy::Option), _, tys, _),
+        ) => tys.first().cloned(),
+        (
+            ProjectionElem::Field(FieldProjKind::Range(aty), field_id),
+            Ty::Adt(TypeId::Assumed(ty_aty), _, tys, _),
+        ) if aty == ty_aty => {
+            // The `exhausted` field of `RangeInclusive` is a boolean
+            if *aty == AssumedTy::RangeInclusive && field_id.to_usize() == 2 {
+                Some(Ty::Literal(LiteralTy::Bool))
+            } else {
+                tys.first().cloned()
+            }
+        }
+        (
+            ProjectionElem::Field(FieldProjKind::Adt(id, variant_id), field_id),
+            Ty::Adt(TypeId::Adt(ty_id), _, tys, cgs),
+        ) if id == ty_id => adt_field_ty(type_defs, *id, *variant_id, *field_id, tys, cgs),
+        (ProjectionElem::UnionField(id, field_id), Ty::Adt(TypeId::Adt(ty_id), _, tys, cgs))
+            if id == ty_id =>
+        {
+            adt_field_ty(type_defs, *id, None, *field_id, tys, cgs)
+        }
+        (
+            ProjectionElem::Index(..),
+            Ty::Adt(TypeId::Assumed(AssumedTy::Array | AssumedTy::Slice), _, tys, _),
+        ) => tys.first().cloned(),
+        (
+            ProjectionElem::ConstantIndex { .. },
+            Ty::Adt(TypeId::Assumed(AssumedTy::Array | AssumedTy::Slice), _, tys, _),
+        ) => tys.first().cloned(),
+        (
+            ProjectionElem::Subslice { from_end: true, .. },
+            Ty::Adt(TypeId::Assumed(AssumedTy::Slice), ..),
+        ) => Some(ty.clone()),
+        (
+            ProjectionElem::Subslice {
+                from,
+                to,
+                from_end: false,
+            },
+            Ty::Adt(TypeId::Assumed(AssumedTy::Array), regions, tys, cgs),
+        ) => {
+            let len = match cgs.first()? {
+                ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(size, _))) => {
+                    ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(*size, to - from)))
+                }
+                _ => return None,
+            };
+            Some(Ty::Adt(
+                TypeId::Assumed(AssumedTy::Array),
+                regions.clone(),
+                tys.clone(),
+                vec![len],
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl<'a> TypeChecker<'a> {
+    /// Check that the types are [compatible], once the type aliases are
+    /// expanded (see [crate::restore_type_aliases]).
+    fn compatible(&self, expected: &ETy, found: &ETy) -> bool {
+        if contains_impl_trait(self.type_defs, expected)
+            || contains_impl_trait(self.type_defs, found)
+        {
+            return true;
+        }
+        compatible(
+            &expand_aliases(self.type_defs, expected),
+            &expand_aliases(self.type_defs, found),
+        )
+    }
+
+    fn report(&mut self, kind: TypeCheckErrorKind) {
+        if self.error.is_none() {
+            self.error = Some(TypeCheckError {
+                meta: self.meta,
+                kind,
+            });
+        }
+    }
+
+    fn place_ty(&self, p: &Place) -> Option<ETy> {
+        let mut ty = (*self.locals.get(p.var_id)?.ty).clone();
+        for pe in &p.projection {
+            ty = project_ty(self.type_defs, &ty, pe)?;
+        }
+        Some(ty)
+    }
+
+    fn operand_ty(&self, op: &Operand) -> Option<ETy> {
+        match op {
+            Operand::Copy(p) | Operand::Move(p) => self.place_ty(p),
+            Operand::Const(ty, _) => Some(ty.clone()),
+        }
+    }
+
+    fn rvalue_ty(&self, rv: &Rvalue) -> Option<ETy> {
+        match rv {
+            Rvalue::Use(op) => self.operand_ty(op),
+            Rvalue::Ref(p, kind) => {
+                let kind = match kind {
+                    BorrowKind::Mut | BorrowKind::TwoPhaseMut => RefKind::Mut,
+                    BorrowKind::Shared | BorrowKind::Shallow => RefKind::Shared,
+                };
+                Some(Ty::Ref(
+                    ErasedRegion::Erased,
+                    Box::new(self.place_ty(p)?),
+                    kind,
+                ))
+            }
+            Rvalue::RawPtr(p, kind) => Some(Ty::RawPtr(Box::new(self.place_ty(p)?), *kind)),
+            Rvalue::UnaryOp(UnOp::Not | UnOp::Neg, op) => self.operand_ty(op),
+            Rvalue::UnaryOp(UnOp::Cast(_, tgt), _) => Some(Ty::Literal(LiteralTy::Integer(*tgt))),
+            Rvalue::BinaryOp(binop, op, _) => match binop {
+                BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+                    Some(Ty::Literal(LiteralTy::Bool))
+                }
+                _ => self.operand_ty(op),
+            },
+            Rvalue::Aggregate(kind, ops) => match kind {
+                AggregateKind::Tuple => {
+                    let tys: Option<Vec<ETy>> = ops.iter().map(|op| self.operand_ty(op)).collect();
+                    Some(Ty::Adt(TypeId::Tuple, Vec::new(), tys?, Vec::new()))
+                }
+                AggregateKind::Option(_, ty) => Some(Ty::Adt(
+                    TypeId::Assumed(AssumedTy::Option),
+                    Vec::new(),
+                    vec![ty.clone()],
+                    Vec::new(),
+                )),
+                AggregateKind::Adt(id, _, regions, tys, cgs)
+                | AggregateKind::Union(id, _, regions, tys, cgs) => Some(Ty::Adt(
+                    TypeId::Adt(*id),
+                    regions.clone(),
+                    tys.clone(),
+                    cgs.clone(),
+                )),
+                AggregateKind::PhantomData(ty) => Some(Ty::Adt(
+                    TypeId::Assumed(AssumedTy::PhantomData),
+                    Vec::new(),
+                    vec![ty.clone()],
+                    Vec::new(),
+                )),
+                AggregateKind::Array(ty, cg) | AggregateKind::Repeat(ty, _, cg) => Some(Ty::Adt(
+                    TypeId::Assumed(AssumedTy::Array),
+                    Vec::new(),
+                    vec![ty.clone()],
+                    vec![cg.clone()],
+                )),
+                AggregateKind::Range(aty, tys) => Some(Ty::Adt(
+                    TypeId::Assumed(*aty),
+                    Vec::new(),
+                    tys.clone(),
+                    Vec::new(),
+                )),
+                AggregateKind::RawPtr(ty, kind) => Some(Ty::RawPtr(Box::new(ty.clone()), *kind)),
+                AggregateKind::StructUpdate { base, .. } => self.operand_ty(base),
+                _ => None,
+            },
+            Rvalue::Len(..) => Some(Ty::Literal(LiteralTy::Integer(IntegerTy::Usize))),
+            _ => None,
+        }
+    }
+
+    fn check_call_args(&mut self, call: &Call, inputs: Vec<ETy>, output: ETy) {
+        if inputs.len() != call.args.len() {
+            self.report(TypeCheckErrorKind::CallArity {
+                expected: inputs.len(),
+                found: call.args.len(),
+            });
+            return;
+        }
+        for (index, (expected, arg)) in inputs.into_iter().zip(call.args.iter()).enumerate() {
+            if let Some(found) = self.operand_ty(arg) {
+                if !self.compatible(&expected, &found) {
+                    self.report(TypeCheckErrorKind::CallArg {
+                        index,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+        if let Some(found) = self.place_ty(&call.dest) {
+            if !self.compatible(&found, &output) {
+                self.report(TypeCheckErrorKind::CallDest {
+                    expected: output,
+                    found,
+                });
+            }
+        }
+    }
+}
+
+impl<'a> SharedTypeVisitor for TypeChecker<'a> {}
+
+impl<'a> SharedExprVisitor for TypeChecker<'a> {
+    fn visit_call(&mut self, call: &Call) {
+        match &call.func {
+            FunId::Regular(id) => {
+                let fun_defs = self.fun_defs;
+                let sig = match fun_defs.get(*id) {
+                    Option::Some(decl) => &decl.signature,
+                    Option::None => return,
+                };
+                let inst = |ty: &RTy| {
+                    instantiate(
+                        ty,
+                        &sig.type_params,
+                        &sig.const_generic_params,
+                        &call.type_args,
+                        &call.const_generic_args,
+                    )
+                };
+                let inputs: Option<Vec<ETy>> = sig.inputs.iter().map(inst).collect();
+                if let (Some(inputs), Some(output)) = (inputs, inst(&sig.output)) {
+                    self.check_call_args(call, inputs, output);
+                }
+            }
+            FunId::Ptr(op) => {
+                if let Some(Ty::FnPtr(inputs, output)) = self.operand_ty(op) {
+                    self.check_call_args(call, inputs, *output);
+                }
+            }
+            // We don't have the signatures of the assumed functions at hand,
+            // and the calls through trait objects take the object as
+            // first argument.
+            FunId::Assumed(_) | FunId::Virtual(..) => (),
+        }
+    }
+}
+
+impl<'a> SharedAstVisitor for TypeChecker<'a> {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &Statement) {
+        self.meta = st.meta;
+        self.visit_raw_statement(&st.content)
+    }
+
+    fn visit_assign(&mut self, p: &Place, rv: &Rvalue) {
+        if let (Some(expected), Some(found)) = (self.place_ty(p), self.rvalue_ty(rv)) {
+            if !self.compatible(&expected, &found) {
+                self.report(TypeCheckErrorKind::Assign {
+                    place: p.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+        self.visit_place(p);
+        self.visit_rvalue(rv)
+    }
+
+    fn visit_switch(&mut self, s: &Switch) {
+        match s {
+            Switch::If(discr, _, _) => {
+                if let Some(ty) = self.operand_ty(discr) {
+                    if ty != Ty::Literal(LiteralTy::Bool) {
+                        self.report(TypeCheckErrorKind::IfDiscr(ty));
+                    }
+                }
+            }
+            Switch::SwitchInt(discr, _, _, _) => {
+                if let Some(ty) = self.operand_ty(discr) {
+                    if !matches!(
+                        ty,
+                        Ty::Literal(LiteralTy::Integer(_)) | Ty::Literal(LiteralTy::Bool)
+                    ) {
+                        self.report(TypeCheckErrorKind::SwitchIntDiscr(ty));
+                    }
+                }
+            }
+            Switch::Match(..) => (),
+        }
+        self.default_visit_switch(s)
+    }
+}
+
+/// Type-check a body. We need the function declarations to retrieve the
+/// signatures of the callees.
+pub fn typecheck_body(
+    body: &ExprBody,
+    type_defs: &TypeDecls,
+    fun_defs: &FunDecls,
+) -> Result<(), TypeCheckError> {
+    let mut checker = TypeChecker {
+        type_defs,
+        fun_defs,
+        locals: &body.locals,
+        meta: body.meta,
+        error: None,
+    };
+    checker.visit_statement(&body.body);
+    match checker.error {
+        Option::None => Ok(()),
+        Option::Some(err) => Err(err),
+    }
+}
+
+/// Type-check all the bodies, and report the errors.
+///
+/// Return `true` if all the bodies are well-typed.
+pub fn check(fmt_ctx: &CtxNames<'_>, funs: &FunDecls, globals: &GlobalDecls) -> bool {
+    let type_defs = fmt_ctx.type_context;
+    let fun_ctx = FunNamesFormatter::new(fmt_ctx.fun_context);
+    let global_ctx = GlobalNamesFormatter::new(fmt_ctx.global_context);
+    let mut ok = true;
+
+    for decl in funs.iter() {
+        if let Some(body) = &decl.body {
+            if let Err(err) = typecheck_body(body, type_defs, funs) {
+                let ctx = GAstFormatter::new(
+                    type_defs,
+                    &fun_ctx,
+                    &global_ctx,
+                    Some(&decl.signature.type_params),
+                    Some(&body.locals),
+                    Some(&decl.signature.const_generic_params),
+                );
+                error!(
+                    "Type error in function {}: {}",
+                    decl.name,
+                    err.fmt_with_ctx(&ctx)
+                );
+                ok = false;
+            }
+        }
+    }
+
+    for decl in globals.iter() {
+        if let Some(body) = &decl.body {
+            if let Err(err) = typecheck_body(body, type_defs, funs) {
+                let ctx = GAstFormatter::new(
+                    type_defs,
+                    &fun_ctx,
+                    &global_ctx,
+                    None,
+                    Some(&body.locals),
+                    None,
+                );
+                error!(
+                    "Type error in global {}: {}",
+                    decl.name,
+                    err.fmt_with_ctx(&ctx)
+                );
+                ok = false;
+            }
+        }
+    }
+
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeCheckErrorKind;
+    use crate::expressions::*;
+    use crate::llbc_ast::*;
+    use crate::llbc_ast_utils::chain_statements;
+    use crate::meta::*;
+    use crate::types::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn mk_place(var_id: VarId::Id) -> Place {
+        Place {
+            var_id,
+            projection: vec![],
+        }
+    }
+
+    /// ```text
+    /// fn f(x: u32, b: bool) -> u32 {
+    ///   @return := copy x + const 1;
+    ///   if move b { @return := <rhs> } else { nop }
+    ///   return;
+    /// }
+    /// ```
+    fn mk_body(rhs: Operand) -> ExprBody {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut ids = VarId::Generator::new();
+        let ret = ids.fresh_id();
+        let x = ids.fresh_id();
+        let b = ids.fresh_id();
+        let mut locals = VarId::Vector::new();
+        for (index, ty) in [
+            (ret, u32_ty.clone()),
+            (x, u32_ty.clone()),
+            (b, Ty::Literal(LiteralTy::Bool)),
+        ] {
+            locals.push_back(Var {
+                index,
+                name: None,
+                ty: ty.into(),
+            });
+        }
+
+        let meta = dummy_meta();
+        let one = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(1)));
+        let body = chain_statements(
+            vec![
+                Statement::new(
+                    meta,
+                    RawStatement::Assign(
+                        mk_place(ret),
+                        Rvalue::BinaryOp(
+                            BinOp::Add,
+                            Operand::Copy(mk_place(x)),
+                            Operand::Const(u32_ty, one),
+                        ),
+                    ),
+                ),
+                Statement::new(
+                    meta,
+                    RawStatement::Switch(Switch::If(
+                        Operand::Move(mk_place(b)),
+                        Box::new(Statement::new(
+                            meta,
+                            RawStatement::Assign(mk_place(ret), Rvalue::Use(rhs)),
+                        )),
+                        Box::new(Statement::new(meta, RawStatement::Nop)),
+                    )),
+                ),
+            ],
+            Statement::new(meta, RawStatement::Return),
+        );
+        ExprBody {
+            meta,
+            arg_count: 2,
+            locals,
+            body,
+            liveness: None,
+        }
+    }
+
+    #[test]
+    fn test_assign() {
+        let type_defs = TypeDecls::new();
+        let fun_defs = FunDecls::new();
+        let x = mk_place(VarId::Id::new(1));
+        let b = mk_place(VarId::Id::new(2));
+
+        let body = mk_body(Operand::Copy(x));
+        assert!(super::typecheck_body(&body, &type_defs, &fun_defs).is_ok());
+
+        let body = mk_body(Operand::Copy(b));
+        match super::typecheck_body(&body, &type_defs, &fun_defs) {
+            Err(err) => match err.kind {
+                TypeCheckErrorKind::Assign {
+                    expected, found, ..
+                } => {
+                    assert!(expected == Ty::Literal(LiteralTy::Integer(IntegerTy::U32)));
+                    assert!(found == Ty::Literal(LiteralTy::Bool));
+                }
+                _ => panic!(),
+            },
+            Ok(()) => panic!(),
+        }
+    }
+}
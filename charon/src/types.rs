@@ -1,14 +1,17 @@
 #![allow(dead_code)]
 
+use crate::expressions::{BinOp, UnOp};
+use crate::gast::FunDeclId;
 use crate::meta::Meta;
-use crate::names::TypeName;
+use crate::names::{TraitName, TypeName};
 use crate::regions_hierarchy::RegionGroups;
 pub use crate::types_utils::*;
 use crate::values::Literal;
 use macros::{
     generate_index_type, EnumAsGetters, EnumIsA, EnumToGetters, VariantIndexArity, VariantName,
 };
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 pub type FieldName = String;
 
@@ -24,11 +27,12 @@ generate_index_type!(FieldId);
 generate_index_type!(RegionVarId);
 generate_index_type!(ConstGenericVarId);
 generate_index_type!(GlobalDeclId);
+generate_index_type!(TraitDeclId);
 
 /// Type variable.
 /// We make sure not to mix variables and type variables by having two distinct
 /// definitions.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TypeVar {
     /// Unique index identifying the variable
     pub index: TypeVarId::Id,
@@ -37,7 +41,7 @@ pub struct TypeVar {
 }
 
 /// Region variable.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RegionVar {
     /// Unique index identifying the variable
     pub index: RegionVarId::Id,
@@ -46,7 +50,7 @@ pub struct RegionVar {
 }
 
 /// Const Generic Variable
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConstGenericVar {
     /// Unique index identifying the variable
     pub index: ConstGenericVarId::Id,
@@ -54,13 +58,29 @@ pub struct ConstGenericVar {
     pub name: String,
     /// Type of the const generic
     pub ty: LiteralTy,
+    /// Default value, if there is one (`struct Ring<const N: usize = 8>`).
+    /// Note that the instantiations of the type which omit the parameter
+    /// refer to the default value: rustc fills the substitutions for us.
+    pub default: Option<ConstGeneric>,
 }
 
 /// Region as used in a function's signatures (in which case we use region variable
 /// ids) and in symbolic variables and projections (in which case we use region
 /// ids).
 #[derive(
-    Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, EnumIsA, EnumAsGetters, Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Hash,
+    PartialOrd,
+    Ord,
+    EnumIsA,
+    EnumAsGetters,
+    Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum Region<Rid: Copy + Eq> {
     /// Static region
@@ -71,7 +91,7 @@ pub enum Region<Rid: Copy + Eq> {
 
 /// The type of erased regions. See [`Ty`](Ty) for more explanations.
 /// We could use `()`, but having a dedicated type makes things more explicit.
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, JsonSchema)]
 pub enum ErasedRegion {
     Erased,
 }
@@ -89,7 +109,7 @@ pub enum ErasedRegion {
 ///
 /// A type can only be an ADT (structure or enumeration), as type aliases are
 /// inlined in MIR.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TypeDecl {
     pub def_id: TypeDeclId::Id,
     /// Meta information associated with the type.
@@ -100,6 +120,32 @@ pub struct TypeDecl {
     pub const_generic_params: ConstGenericVarId::Vector<ConstGenericVar>,
     /// The type kind: enum, struct, or opaque.
     pub kind: TypeDeclKind,
+    /// The layout attributes (`#[repr(...)]`) of the type.
+    pub repr: ReprConfig,
+    /// `true` if the declaration appears explicitly in the crate, `false` if
+    /// we translated it because other declarations refer to it.
+    pub is_root: bool,
+    /// A name-based identifier, which (contrary to the numeric ids) doesn't
+    /// change when we add or remove unrelated declarations. Only computed
+    /// with `--stable-ids` (see [crate::stable_ids]).
+    pub stable_id: Option<String>,
+    /// For the opaque types (in particular, the external types): the methods
+    /// defined for this type in the translated crate. For the external types,
+    /// they come from trait impls (`impl Trait for Foo { ... }`). We need this
+    /// because we don't translate the impl blocks of the opaque types. Is
+    /// empty for the other types.
+    pub local_methods: Vec<FunDeclId::Id>,
+    /// The variance of the generic parameters, as computed by rustc.
+    ///
+    /// The parameters are listed in the following order: the region
+    /// parameters, then the type parameters, then the const generic
+    /// parameters.
+    pub variance: Vec<Variance>,
+    /// `true` if the type references itself, directly (like a list) or
+    /// through other types (in case of mutually recursive types). We
+    /// initialize it to `false`, then compute it once the whole crate has
+    /// been translated (see [crate::type_dep_graph]).
+    pub is_recursive: bool,
     /// The lifetime's hierarchy between the different regions.
     /// We initialize it to a dummy value, then compute it once the whole crate
     /// has been translated.
@@ -108,31 +154,102 @@ pub struct TypeDecl {
     pub regions_hierarchy: RegionGroups,
 }
 
-#[derive(Debug, Clone, EnumIsA, EnumAsGetters, Serialize)]
+/// The variance of a generic parameter: if `T` is covariant in `'a` and
+/// `'a: 'b`, then `T<'a>` is a subtype of `T<'b>`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum Variance {
+    Covariant,
+    Contravariant,
+    Invariant,
+    /// The parameter is not used (we can only find such parameters in
+    /// declarations which are not checked by rustc, for instance in the
+    /// `impl Trait` types)
+    Bivariant,
+}
+
+#[derive(Debug, Clone, EnumIsA, EnumAsGetters, Serialize, Deserialize, JsonSchema)]
 pub enum TypeDeclKind {
     Struct(FieldId::Vector<Field>),
     Enum(VariantId::Vector<Variant>),
+    /// A union: all the fields share the same memory location, and reading
+    /// a field reinterprets the bytes of the union (which is unsafe).
+    Union(FieldId::Vector<Field>),
+    /// A type introduced by `impl Trait` in return position, for instance:
+    /// `fn evens(n: u32) -> impl Iterator<Item = u32>`, or by an `async`
+    /// function (which returns an `impl Future<Output = T>`).
+    ///
+    /// We don't know the underlying type, but we know the traits it
+    /// implements (we ignore the `Sized` bound).
+    ImplTrait(Vec<RTraitRef>),
+    /// A type alias (`type MyVec = Vec<u32>`): we give its expansion. We only
+    /// translate the aliases with `--preserve-type-aliases` (rustc expands
+    /// them), see [crate::restore_type_aliases].
+    Alias(RTy),
     /// An opaque type.
     ///
     /// Either a local type marked as opaque, or an external type.
     Opaque,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// The representation given by a `repr` attribute.
+#[derive(
+    Debug, PartialEq, Eq, Copy, Clone, EnumIsA, VariantName, Serialize, Deserialize, JsonSchema,
+)]
+pub enum ReprKind {
+    /// No attribute: the layout is unspecified
+    Rust,
+    /// `#[repr(C)]`
+    C,
+    /// `#[repr(transparent)]`
+    Transparent,
+    /// The representation of the discriminant of an enumeration, for
+    /// instance: `#[repr(u8)]`
+    Int(IntegerTy),
+}
+
+/// The layout attributes of a type declaration: they are necessary to compute
+/// the field offsets.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReprConfig {
+    pub kind: ReprKind,
+    /// `#[repr(packed(N))]`: the maximum alignment of the fields, in bytes
+    pub packed: Option<u64>,
+    /// `#[repr(align(N))]`: the minimum alignment of the type, in bytes
+    pub align: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Variant {
     pub meta: Meta,
     pub name: String,
     pub fields: FieldId::Vector<Field>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Field {
     pub meta: Meta,
     pub name: Option<String>,
     pub ty: RTy,
+    /// `true` if the field (transitively) contains an [AssumedTy::UnsafeCell],
+    /// which is not behind a reference or a pointer: the field can then be
+    /// mutated through a shared reference. This is computed once all the
+    /// types have been translated (see [crate::detect_interior_mutability]).
+    pub interior_mut: bool,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, EnumIsA, VariantName, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    EnumIsA,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum IntegerTy {
     Isize,
     I8,
@@ -148,7 +265,19 @@ pub enum IntegerTy {
     U128,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, VariantName, EnumIsA, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Copy,
+    VariantName,
+    EnumIsA,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum RefKind {
     Mut,
     Shared,
@@ -157,7 +286,19 @@ pub enum RefKind {
 /// Type identifier.
 ///
 /// Allows us to factorize the code for assumed types, adts and tuples
-#[derive(Debug, PartialEq, Eq, Clone, VariantName, EnumAsGetters, EnumIsA, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    VariantName,
+    EnumAsGetters,
+    EnumIsA,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum TypeId {
     /// A "regular" ADT type.
     ///
@@ -182,6 +323,7 @@ pub type TypeDecls = TypeDeclId::Map<TypeDecl>;
     Debug,
     PartialEq,
     Eq,
+    Hash,
     Clone,
     Copy,
     VariantName,
@@ -189,6 +331,8 @@ pub type TypeDecls = TypeDeclId::Map<TypeDecl>;
     EnumAsGetters,
     VariantIndexArity,
     Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum LiteralTy {
     Integer(IntegerTy),
@@ -198,7 +342,18 @@ pub enum LiteralTy {
 
 /// Const Generic Values. Either a primitive value, or a variable corresponding to a primitve value
 #[derive(
-    Debug, PartialEq, Eq, Clone, VariantName, EnumIsA, EnumAsGetters, VariantIndexArity, Serialize,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    VariantName,
+    EnumIsA,
+    EnumAsGetters,
+    VariantIndexArity,
+    Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum ConstGeneric {
     /// A global constant
@@ -207,6 +362,35 @@ pub enum ConstGeneric {
     Var(ConstGenericVarId::Id),
     /// A concrete value
     Value(Literal),
+    /// A const generic expression, like `N + 1`
+    Expr(Box<ConstGenericExpr>),
+}
+
+/// Const generic expressions.
+///
+/// Those appear in the types when using the `generic_const_exprs` feature,
+/// for instance: `[u8; N * 2]`.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    VariantName,
+    EnumIsA,
+    EnumAsGetters,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+pub enum ConstGenericExpr {
+    BinaryOp(BinOp, ConstGeneric, ConstGeneric),
+    /// Only [UnOp::Not] and [UnOp::Neg] can appear here
+    UnaryOp(UnOp, ConstGeneric),
+    /// A call to a `const fn`
+    FunCall(FunDeclId::Id, Vec<ConstGeneric>),
+    /// A cast, like in `N as usize`
+    Cast(ConstGeneric, LiteralTy),
 }
 
 /// A type.
@@ -221,6 +405,7 @@ pub enum ConstGeneric {
     Debug,
     PartialEq,
     Eq,
+    Hash,
     Clone,
     VariantName,
     EnumIsA,
@@ -228,6 +413,8 @@ pub enum ConstGeneric {
     EnumToGetters,
     VariantIndexArity,
     Serialize,
+    Deserialize,
+    JsonSchema,
 )]
 pub enum Ty<R>
 where
@@ -284,6 +471,45 @@ where
     /// TODO: maybe we should simply deactivate support for optimized code: who
     /// wants to verify this?
     RawPtr(Box<Ty<R>>, RefKind),
+    /// A trait object (`dyn Trait`).
+    ///
+    /// We give the list of traits (the principal trait first, if there is
+    /// one, followed by the auto traits like `Send`), and the lifetime bound
+    /// of the trait object.
+    TraitObject(Vec<TraitRef<R>>, R),
+    /// A function pointer type (`fn(u32) -> u32`): we give the types of the
+    /// inputs and the type of the output.
+    ///
+    /// We don't support higher-ranked function pointers (`for<'a> fn(&'a u32)`)
+    /// for now.
+    FnPtr(Vec<Ty<R>>, Box<Ty<R>>),
+    /// An associated type of a trait, projected from a type parameter, for
+    /// instance `T::Item` where `T: Iterator`: we give the trait which
+    /// defines the associated type, the type parameter and the name of the
+    /// associated type.
+    ///
+    /// When the projection can be resolved (because the type is concrete,
+    /// or because the bounds of the type parameter give its value, as in
+    /// `T: Iterator<Item = u32>`), we directly use the resolved type instead.
+    TraitAssoc(TraitDeclId::Id, TypeVarId::Id, String),
+}
+
+/// A reference to a trait, as found in trait objects.
+///
+/// We don't translate the trait declarations yet: we identify the trait
+/// with its name.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TraitRef<R>
+where
+    R: Clone + std::cmp::Eq,
+{
+    pub trait_name: TraitName,
+    /// The type parameters, without the (implicit) `Self` type
+    pub types: Vec<Ty<R>>,
+    pub const_generics: Vec<ConstGeneric>,
+    /// The constraints on the associated types. For instance, `dyn Fn(u32) -> u32`
+    /// refers to the trait `Fn<(u32,)>` and constrains `Output` to be `u32`.
+    pub assoc_types: Vec<(String, Ty<R>)>,
 }
 
 /// Type with *R*egions.
@@ -298,6 +524,29 @@ pub type RTy = Ty<Region<RegionVarId::Id>>;
 /// Used in function bodies, "general" value types, etc.
 pub type ETy = Ty<ErasedRegion>;
 
+/// Trait reference with *R*egions (see [RTy]).
+pub type RTraitRef = TraitRef<Region<RegionVarId::Id>>;
+
+/// A predicate (i.e., a where clause) of a function signature.
+///
+/// We ignore the `Sized` bounds (they are implicit) and the lifetime bounds.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum Predicate {
+    /// `T: Trait<...>`: the type which implements the trait, and the trait.
+    /// The associated type constraints (`Item = u32` in
+    /// `T: Iterator<Item = u32>`) are attached to the trait reference.
+    Trait(RTy, RTraitRef),
+    /// A predicate which binds regions, like `for<'a> F: Fn(&'a u32)`.
+    ///
+    /// The bound regions are numbered after the region parameters of the
+    /// signature: if the function has `n` region parameters, the bound
+    /// regions are identified by `n`, `n + 1`, etc.
+    HigherRanked {
+        binders: Vec<RegionVar>,
+        pred: Box<Predicate>,
+    },
+}
+
 /// Assumed types identifiers.
 ///
 /// WARNING: for now, all the assumed types are covariant in the generic
@@ -308,7 +557,20 @@ pub type ETy = Ty<ErasedRegion>;
 /// TODO: update to not hardcode the types (except `Box` maybe) and be more
 /// modular.
 /// TODO: move to assumed.rs?
-#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumIsA, EnumAsGetters, VariantName, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Copy,
+    EnumIsA,
+    EnumAsGetters,
+    VariantName,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum AssumedTy {
     /// Boxes have a special treatment: we translate them as identity.
     Box,
@@ -316,13 +578,37 @@ pub enum AssumedTy {
     Vec,
     /// Comes from the standard library
     Option,
-    /// Comes from the standard library
+    /// `core::ops::Range`: `a..b`
     Range,
+    /// `core::ops::RangeFrom`: `a..`
+    RangeFrom,
+    /// `core::ops::RangeTo`: `..b`
+    RangeTo,
+    /// `core::ops::RangeFull`: `..` (it has no type parameter)
+    RangeFull,
+    /// `core::ops::RangeInclusive`: `a..=b`. The values of this type are
+    /// built with `RangeInclusive::new` (its fields are private).
+    RangeInclusive,
     /// Comes from the standard library. See the comments for [Ty::RawPtr]
     /// as to why we have this here.
     PtrUnique,
     /// Same comments as for [AssumedTy::PtrUnique]
     PtrNonNull,
+    /// `core::marker::PhantomData`.
+    ///
+    /// The fields of this type are zero-sized: they don't change the layout
+    /// of the structures, but we preserve them because they influence the
+    /// variance of the parameters and the drop check.
+    PhantomData,
+    /// `core::cell::UnsafeCell`, the basis of the interior mutability (`Cell`,
+    /// `RefCell`, `Mutex`, etc. are built on top of it): the content of an
+    /// `UnsafeCell` can be mutated through a shared reference. Note that,
+    /// contrary to the other assumed types, `UnsafeCell` is invariant in its
+    /// parameter.
+    ///
+    /// The fields which contain an `UnsafeCell` are flagged (see
+    /// [Field::interior_mut]).
+    UnsafeCell,
     /// Primitive type
     Array,
     /// Primitive type
@@ -0,0 +1,184 @@
+//! Interning of the types of the local variables ([crate::gast::Var::ty]).
+//!
+//! The locals of a body often share the same types: during the translation,
+//! we store every distinct type once in the [TypeInterner] of the translation
+//! context, and the variables refer to it with an [InternedTy], which is a
+//! shared pointer.
+//!
+//! This is only a first step: we don't intern the other types of the ASTs
+//! (the types of the fields, the signatures, the substitutions, the types in
+//! the operands and the rvalues, etc.), which are still stored by value, and
+//! an [InternedTy] is a reference-counted pointer rather than an index into
+//! an arena. We haven't measured the memory usage of the translation yet.
+//!
+//! An [InternedTy] can be dereferenced without any context (when formatting
+//! or serializing the ASTs for instance). The types created outside of the
+//! translation (by the micro-passes, or when reading a generated file) are
+//! not shared, but compare equal to the interned types they are equal to.
+#![allow(dead_code)]
+
+use crate::types::ETy;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// An erased type, shared by all the locals which have this type if it was
+/// created by a [TypeInterner].
+///
+/// The equality is the structural equality of the types: we only compare the
+/// pointers to take a shortcut.
+#[derive(Clone)]
+pub struct InternedTy(Arc<ETy>);
+
+/// The table in which we store the interned types. It is owned by the
+/// translation context ([crate::translate_ctx::TransCtx::type_interner]).
+#[derive(Default)]
+pub struct TypeInterner {
+    types: HashSet<InternedTy>,
+}
+
+impl TypeInterner {
+    pub fn new() -> Self {
+        TypeInterner {
+            types: HashSet::new(),
+        }
+    }
+
+    /// Intern a type. If the type was already interned, we return a pointer
+    /// to the same type as before.
+    pub fn intern(&mut self, ty: ETy) -> InternedTy {
+        if let Some(ity) = self.types.get(&ty) {
+            return ity.clone();
+        }
+        let ity = InternedTy::new(ty);
+        self.types.insert(ity.clone());
+        ity
+    }
+
+    /// The number of distinct types interned so far.
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Whether no type has been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+impl InternedTy {
+    /// Wrap a type, without sharing it (see [TypeInterner::intern]).
+    pub fn new(ty: ETy) -> Self {
+        InternedTy(Arc::new(ty))
+    }
+
+    /// Whether two types were produced by the same call to
+    /// [TypeInterner::intern] (and thus share the same memory).
+    pub fn ptr_eq(&self, other: &InternedTy) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl From<ETy> for InternedTy {
+    fn from(ty: ETy) -> Self {
+        InternedTy::new(ty)
+    }
+}
+
+impl std::ops::Deref for InternedTy {
+    type Target = ETy;
+
+    fn deref(&self) -> &ETy {
+        &self.0
+    }
+}
+
+/// We need this to lookup the interned types from an [ETy] (see
+/// [TypeInterner::intern]): the equality and the hash of an [InternedTy] are
+/// the ones of the type it points to.
+impl Borrow<ETy> for InternedTy {
+    fn borrow(&self) -> &ETy {
+        &self.0
+    }
+}
+
+impl PartialEq for InternedTy {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl Eq for InternedTy {}
+
+impl Hash for InternedTy {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self.0).hash(state)
+    }
+}
+
+impl std::fmt::Debug for InternedTy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// We serialize the type itself: the generated files are the same as
+/// without interning.
+impl Serialize for InternedTy {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InternedTy {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(InternedTy::new(ETy::deserialize(deserializer)?))
+    }
+}
+
+impl JsonSchema for InternedTy {
+    fn schema_name() -> String {
+        <ETy>::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <ETy>::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{IntegerTy, LiteralTy, Ty};
+
+    #[test]
+    fn test_intern_same_type() {
+        let u32_ty = || Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let mut interner = TypeInterner::new();
+        let ty0 = interner.intern(u32_ty());
+        let ty1 = interner.intern(u32_ty());
+        let bool_ty = interner.intern(Ty::Literal(LiteralTy::Bool));
+        // The equal types are stored once
+        assert!(ty0.ptr_eq(&ty1));
+        assert!(ty0 == ty1);
+        assert!(interner.len() == 2);
+        assert!(ty0 != bool_ty && !ty0.ptr_eq(&bool_ty));
+        assert!(*ty0 == u32_ty());
+
+        // A type which is not interned is equal to the interned type, but
+        // is not shared
+        let ty2 = InternedTy::new(u32_ty());
+        assert!(ty2 == ty0 && !ty2.ptr_eq(&ty0));
+    }
+}
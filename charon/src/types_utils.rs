@@ -5,9 +5,10 @@ use crate::assumed::get_name_from_type_id;
 use crate::formatter::Formatter;
 use crate::types::*;
 use crate::ullbc_ast::GlobalDeclId;
-use crate::values::Literal;
+use crate::values::{Literal, PointerSize};
 use im::{HashMap, OrdSet};
 use macros::make_generic_in_borrows;
+#[cfg(feature = "rustc")]
 use rustc_middle::ty::{IntTy, UintTy};
 use std::iter::FromIterator;
 use std::iter::Iterator;
@@ -36,6 +37,26 @@ impl ConstGeneric {
             ConstGeneric::Var(id) => id.substitute(cgsubst),
             ConstGeneric::Value(v) => ConstGeneric::Value(v.clone()),
             ConstGeneric::Global(id) => ConstGeneric::Global(*id),
+            ConstGeneric::Expr(e) => ConstGeneric::Expr(Box::new(e.substitute(cgsubst))),
+        }
+    }
+}
+
+impl ConstGenericExpr {
+    pub fn substitute(
+        &self,
+        cgsubst: &dyn Fn(&ConstGenericVarId::Id) -> ConstGeneric,
+    ) -> ConstGenericExpr {
+        use ConstGenericExpr::*;
+        match self {
+            BinaryOp(op, cg0, cg1) => {
+                BinaryOp(*op, cg0.substitute(cgsubst), cg1.substitute(cgsubst))
+            }
+            UnaryOp(op, cg) => UnaryOp(op.clone(), cg.substitute(cgsubst)),
+            FunCall(fid, args) => {
+                FunCall(*fid, args.iter().map(|cg| cg.substitute(cgsubst)).collect())
+            }
+            Cast(cg, ty) => Cast(cg.substitute(cgsubst), *ty),
         }
     }
 }
@@ -99,6 +120,27 @@ impl std::string::ToString for TypeVar {
     }
 }
 
+impl RegionVar {
+    /// Return the name of the region if it has one, otherwise generate a
+    /// synthetic (but stable) name from its index, like `'r0`.
+    ///
+    /// This is more readable than the default printing of anonymous regions
+    /// (for instance, elided lifetimes), which simply shows the region id.
+    pub fn lifetime_display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.to_string(),
+            None => format!("'r{}", self.index),
+        }
+    }
+}
+
+impl RegionVarId::Vector<RegionVar> {
+    /// List the region variables with their (optional) user-provided names.
+    pub fn lifetime_names(&self) -> Vec<(RegionVarId::Id, Option<String>)> {
+        self.iter().map(|r| (r.index, r.name.clone())).collect()
+    }
+}
+
 impl std::string::ToString for RegionVar {
     fn to_string(&self) -> String {
         let id = self.index.to_pretty_string();
@@ -111,7 +153,15 @@ impl std::string::ToString for RegionVar {
 
 impl std::string::ToString for ConstGenericVar {
     fn to_string(&self) -> String {
-        format!("const {} : {}", self.name, self.ty.to_string())
+        match &self.default {
+            None => format!("const {} : {}", self.name, self.ty.to_string()),
+            Some(default) => format!(
+                "const {} : {} = {}",
+                self.name,
+                self.ty.to_string(),
+                default.fmt_with_ctx(&DummyFormatter {})
+            ),
+        }
     }
 }
 
@@ -121,13 +171,14 @@ impl TypeDecl {
     pub fn get_fields(&self, variant_id: Option<VariantId::Id>) -> &FieldId::Vector<Field> {
         match &self.kind {
             TypeDeclKind::Enum(variants) => &variants.get(variant_id.unwrap()).unwrap().fields,
-            TypeDeclKind::Struct(fields) => {
+            TypeDeclKind::Struct(fields) | TypeDeclKind::Union(fields) => {
                 assert!(variant_id.is_none());
                 fields
             }
-            TypeDeclKind::Opaque => {
+            TypeDeclKind::ImplTrait(_) | TypeDeclKind::Opaque => {
                 unreachable!("Opaque type")
             }
+            TypeDeclKind::Alias(_) => unreachable!("Type alias"),
         }
     }
 
@@ -148,7 +199,7 @@ impl TypeDecl {
         let ty_subst = make_type_subst(self.type_params.iter().map(|x| x.index), inst_types.iter());
 
         match &self.kind {
-            TypeDeclKind::Struct(fields) => {
+            TypeDeclKind::Struct(fields) | TypeDeclKind::Union(fields) => {
                 Option::Some(VariantId::Vector::from(vec![FieldId::Vector::from_iter(
                     fields
                         .iter()
@@ -164,7 +215,9 @@ impl TypeDecl {
                     )
                 })))
             }
-            TypeDeclKind::Opaque => Option::None,
+            TypeDeclKind::ImplTrait(_) | TypeDeclKind::Alias(_) | TypeDeclKind::Opaque => {
+                Option::None
+            }
         }
     }
 
@@ -228,7 +281,7 @@ impl TypeDecl {
             + Formatter<ConstGenericVarId::Id>,
     {
         let params = TypeDecl::fmt_params(&self.region_params, &self.type_params);
-        match &self.kind {
+        let decl = match &self.kind {
             TypeDeclKind::Struct(fields) => {
                 if !fields.is_empty() {
                     let fields: Vec<String> = fields
@@ -249,7 +302,55 @@ impl TypeDecl {
                 let variants = variants.join("\n");
                 format!("enum {}{} =\n{}\n", self.name, params, variants)
             }
+            TypeDeclKind::Union(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|f| format!("\n  {}", f.fmt_with_ctx(ctx)))
+                    .collect();
+                let fields = fields.join(",");
+                format!("union {}{} = {{{}\n}}", self.name, params, fields)
+            }
+            TypeDeclKind::ImplTrait(traits) => {
+                let traits: Vec<String> = traits.iter().map(|tr| tr.fmt_with_ctx(ctx)).collect();
+                format!(
+                    "opaque type {}{} : impl {}",
+                    self.name,
+                    params,
+                    traits.join(" + ")
+                )
+            }
+            TypeDeclKind::Alias(ty) => {
+                format!("type {}{} = {}", self.name, params, ty.fmt_with_ctx(ctx))
+            }
             TypeDeclKind::Opaque => format!("opaque type {}{}", self.name, params),
+        };
+        if self.repr.is_default() {
+            decl
+        } else {
+            format!("#[repr({})]\n{}", self.repr.to_string(), decl)
+        }
+    }
+
+    /// Format the variance of the generic parameters, as an attribute:
+    /// `#[variance('a: +, T: o)]` (we use the notations of rustc: `+` for
+    /// covariant, `-` for contravariant, `o` for invariant and `*` for
+    /// bivariant).
+    ///
+    /// Return an empty string if the declaration has no parameters.
+    pub fn fmt_variance(&self) -> String {
+        let regions = self.region_params.iter().map(|r| r.lifetime_display_name());
+        let type_params = self.type_params.iter().map(|p| p.to_string());
+        let cgs = self.const_generic_params.iter().map(|p| p.name.clone());
+        let params: Vec<String> = regions
+            .chain(type_params)
+            .chain(cgs)
+            .zip(self.variance.iter())
+            .map(|(p, v)| format!("{p}: {v}"))
+            .collect();
+        if params.is_empty() {
+            "".to_string()
+        } else {
+            format!("#[variance({})]", params.join(", "))
         }
     }
 
@@ -258,7 +359,7 @@ impl TypeDecl {
         type_params: &TypeVarId::Vector<TypeVar>,
     ) -> String {
         if region_params.len() + type_params.len() > 0 {
-            let regions = region_params.iter().map(|r| r.to_string());
+            let regions = region_params.iter().map(|r| r.lifetime_display_name());
             let type_params = type_params.iter().map(|p| p.to_string());
             let params: Vec<String> = regions.chain(type_params).collect();
             format!("<{}>", params.join(", "))
@@ -268,12 +369,58 @@ impl TypeDecl {
     }
 }
 
+impl std::fmt::Display for Variance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            Variance::Covariant => write!(f, "+"),
+            Variance::Contravariant => write!(f, "-"),
+            Variance::Invariant => write!(f, "o"),
+            Variance::Bivariant => write!(f, "*"),
+        }
+    }
+}
+
 impl std::string::ToString for TypeDecl {
     fn to_string(&self) -> String {
         self.fmt_with_ctx(&IncompleteFormatter { def: self })
     }
 }
 
+impl ReprConfig {
+    /// The representation of the types without `#[repr(...)]` attributes
+    pub fn rust() -> Self {
+        ReprConfig {
+            kind: ReprKind::Rust,
+            packed: None,
+            align: None,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == ReprConfig::rust()
+    }
+}
+
+/// Print the content of the attribute, for instance: `C, align(8)`
+impl std::string::ToString for ReprConfig {
+    fn to_string(&self) -> String {
+        let mut attrs = Vec::new();
+        match self.kind {
+            ReprKind::Rust => (),
+            ReprKind::C => attrs.push("C".to_string()),
+            ReprKind::Transparent => attrs.push("transparent".to_string()),
+            ReprKind::Int(ty) => attrs.push(ty.to_string()),
+        }
+        if let Some(n) = self.packed {
+            attrs.push(format!("packed({n})"));
+        }
+        if let Some(n) = self.align {
+            attrs.push(format!("align({n})"));
+        }
+        attrs.join(", ")
+    }
+}
+
 impl Variant {
     pub fn fmt_with_ctx<'a, T>(&'a self, ctx: &'a T) -> String
     where
@@ -286,7 +433,19 @@ impl Variant {
     {
         let fields: Vec<String> = self.fields.iter().map(|f| f.fmt_with_ctx(ctx)).collect();
         let fields = fields.join(", ");
-        format!("{}({})", self.name, fields)
+        if self.has_named_fields() {
+            // Struct-like variant: `Variant { x: u32, y: u64 }`
+            format!("{} {{ {} }}", self.name, fields)
+        } else {
+            format!("{}({})", self.name, fields)
+        }
+    }
+
+    /// Return `true` if the variant is struct-like (i.e., its fields have
+    /// names, like in `Variant { x: u32, y: u64 }`). Note that the fields of
+    /// a variant are either all named, or all unnamed.
+    pub fn has_named_fields(&self) -> bool {
+        self.fields.iter().any(|f| f.name.is_some())
     }
 }
 
@@ -320,6 +479,7 @@ impl std::string::ToString for Field {
 }
 
 impl IntegerTy {
+    #[cfg(feature = "rustc")]
     pub fn rust_int_ty_to_integer_ty(ty: IntTy) -> IntegerTy {
         match ty {
             IntTy::Isize => IntegerTy::Isize,
@@ -331,6 +491,7 @@ impl IntegerTy {
         }
     }
 
+    #[cfg(feature = "rustc")]
     pub fn rust_uint_ty_to_integer_ty(ty: UintTy) -> IntegerTy {
         match ty {
             UintTy::Usize => IntegerTy::Usize,
@@ -358,17 +519,18 @@ impl IntegerTy {
         !(self.is_signed())
     }
 
-    /// Return the size (in bytes) of an integer of the proper type
-    pub fn size(&self) -> usize {
+    /// Return the size (in bytes) of an integer of the proper type. The size
+    /// of `isize` and `usize` is the size of the pointers on the target.
+    pub fn size(&self, ptr_size: PointerSize) -> usize {
         use std::mem::size_of;
         match self {
-            IntegerTy::Isize => size_of::<isize>(),
+            IntegerTy::Isize => ptr_size.bytes(),
             IntegerTy::I8 => size_of::<i8>(),
             IntegerTy::I16 => size_of::<i16>(),
             IntegerTy::I32 => size_of::<i32>(),
             IntegerTy::I64 => size_of::<i64>(),
             IntegerTy::I128 => size_of::<i128>(),
-            IntegerTy::Usize => size_of::<isize>(),
+            IntegerTy::Usize => ptr_size.bytes(),
             IntegerTy::U8 => size_of::<u8>(),
             IntegerTy::U16 => size_of::<u16>(),
             IntegerTy::U32 => size_of::<u32>(),
@@ -378,6 +540,21 @@ impl IntegerTy {
     }
 }
 
+impl AssumedTy {
+    /// `true` for [AssumedTy::Range] and the other range types (`RangeFrom`,
+    /// etc.)
+    pub fn is_range_type(&self) -> bool {
+        matches!(
+            self,
+            AssumedTy::Range
+                | AssumedTy::RangeFrom
+                | AssumedTy::RangeTo
+                | AssumedTy::RangeFull
+                | AssumedTy::RangeInclusive
+        )
+    }
+}
+
 impl TypeVarId::Id {
     pub fn to_pretty_string(&self) -> String {
         format!("@T{self}")
@@ -450,6 +627,7 @@ impl std::fmt::Display for IntegerTy {
 }
 
 // IntTy is not defined in the current crate
+#[cfg(feature = "rustc")]
 pub fn intty_to_string(ty: IntTy) -> String {
     match ty {
         IntTy::Isize => "isize".to_string(),
@@ -462,6 +640,7 @@ pub fn intty_to_string(ty: IntTy) -> String {
 }
 
 // UintTy is not defined in the current crate
+#[cfg(feature = "rustc")]
 fn uintty_to_string(ty: UintTy) -> String {
     match ty {
         UintTy::Usize => "usize".to_string(),
@@ -495,6 +674,43 @@ impl ConstGeneric {
             ConstGeneric::Var(id) => ctx.format_object(*id),
             ConstGeneric::Value(v) => v.to_string(),
             ConstGeneric::Global(id) => ctx.format_object(*id),
+            ConstGeneric::Expr(e) => e.fmt_with_ctx(ctx),
+        }
+    }
+
+    /// Format a const generic appearing as the operand of a binary operation:
+    /// we add parentheses around the nested binary operations (`(N + 1) * 2`).
+    fn fmt_as_operand_with_ctx<T>(&self, ctx: &T) -> String
+    where
+        T: Formatter<ConstGenericVarId::Id> + Formatter<GlobalDeclId::Id>,
+    {
+        match self {
+            ConstGeneric::Expr(e) if e.is_binary_op() => format!("({})", e.fmt_with_ctx(ctx)),
+            _ => self.fmt_with_ctx(ctx),
+        }
+    }
+}
+
+impl ConstGenericExpr {
+    pub fn fmt_with_ctx<T>(&self, ctx: &T) -> String
+    where
+        T: Formatter<ConstGenericVarId::Id> + Formatter<GlobalDeclId::Id>,
+    {
+        use ConstGenericExpr::*;
+        match self {
+            BinaryOp(op, cg0, cg1) => format!(
+                "{} {} {}",
+                cg0.fmt_as_operand_with_ctx(ctx),
+                op,
+                cg1.fmt_as_operand_with_ctx(ctx)
+            ),
+            UnaryOp(op, cg) => format!("{}({})", op, cg.fmt_with_ctx(ctx)),
+            FunCall(fid, args) => {
+                // We don't have a function formatter at hand: we print the id
+                let args: Vec<String> = args.iter().map(|cg| cg.fmt_with_ctx(ctx)).collect();
+                format!("{}({})", fid.to_pretty_string(), args.join(", "))
+            }
+            Cast(cg, ty) => format!("({} as {})", cg.fmt_with_ctx(ctx), ty.to_string()),
         }
     }
 }
@@ -594,6 +810,17 @@ where
                 RefKind::Mut => format!("*const {}", ty.fmt_with_ctx(ctx)),
                 RefKind::Shared => format!("*mut {}", ty.fmt_with_ctx(ctx)),
             },
+            Ty::TraitObject(traits, r) => {
+                let mut bounds: Vec<String> =
+                    traits.iter().map(|tr| tr.fmt_with_ctx(ctx)).collect();
+                bounds.push(ctx.format_object(r));
+                format!("dyn ({})", bounds.join(" + "))
+            }
+            Ty::FnPtr(inputs, output) => {
+                let inputs: Vec<String> = inputs.iter().map(|ty| ty.fmt_with_ctx(ctx)).collect();
+                format!("fn({}) -> {}", inputs.join(", "), output.fmt_with_ctx(ctx))
+            }
+            Ty::TraitAssoc(_, vid, name) => format!("{}::{}", ctx.format_object(*vid), name),
         }
     }
 
@@ -660,6 +887,17 @@ impl<Rid: Copy + Eq + Ord + std::hash::Hash> Ty<Region<Rid>> {
             Ty::Adt(_, regions, tys, _) => regions
                 .iter()
                 .any(|r| r.contains_var(rset) || tys.iter().any(|x| x.contains_region_var(rset))),
+            Ty::TraitObject(traits, r) => {
+                r.contains_var(rset)
+                    || traits
+                        .iter()
+                        .any(|tr| tr.iter_types().any(|x| x.contains_region_var(rset)))
+            }
+            Ty::FnPtr(inputs, output) => {
+                inputs.iter().any(|x| x.contains_region_var(rset))
+                    || output.contains_region_var(rset)
+            }
+            Ty::TraitAssoc(..) => false,
         }
     }
 }
@@ -821,6 +1059,26 @@ where
             Ty::RawPtr(ty, kind) => {
                 Ty::RawPtr(Box::new(ty.substitute(rsubst, tsubst, cgsubst)), *kind)
             }
+            Ty::TraitObject(traits, r) => Ty::TraitObject(
+                traits
+                    .iter()
+                    .map(|tr| tr.substitute(rsubst, tsubst, cgsubst))
+                    .collect(),
+                rsubst(r),
+            ),
+            Ty::FnPtr(inputs, output) => Ty::FnPtr(
+                inputs
+                    .iter()
+                    .map(|ty| ty.substitute(rsubst, tsubst, cgsubst))
+                    .collect(),
+                Box::new(output.substitute(rsubst, tsubst, cgsubst)),
+            ),
+            Ty::TraitAssoc(trait_id, vid, name) => match tsubst(vid) {
+                Ty::TypeVar(vid) => Ty::TraitAssoc(*trait_id, vid, name.clone()),
+                // We don't have access to the trait implementations: we can't
+                // resolve the projection, and leave it unchanged.
+                _ => Ty::TraitAssoc(*trait_id, *vid, name.clone()),
+            },
         }
     }
 
@@ -871,6 +1129,11 @@ where
             Ty::Adt(_, regions, tys, _) => {
                 !regions.is_empty() || tys.iter().any(|x| x.contains_variables())
             }
+            Ty::TraitObject(_, _) => true, // Always contains a region identifier
+            Ty::FnPtr(inputs, output) => {
+                inputs.iter().any(|x| x.contains_variables()) || output.contains_variables()
+            }
+            Ty::TraitAssoc(..) => true,
         }
     }
 
@@ -884,6 +1147,97 @@ where
             Ty::Adt(_, regions, tys, _) => {
                 !regions.is_empty() || tys.iter().any(|x| x.contains_regions())
             }
+            Ty::TraitObject(_, _) => true,
+            Ty::FnPtr(inputs, output) => {
+                inputs.iter().any(|x| x.contains_regions()) || output.contains_regions()
+            }
+            Ty::TraitAssoc(..) => false,
+        }
+    }
+}
+
+impl Predicate {
+    /// The type the predicate applies to (`T` in `T: Trait`)
+    pub fn self_ty(&self) -> &RTy {
+        match self {
+            Predicate::Trait(ty, _) => ty,
+            Predicate::HigherRanked { pred, .. } => pred.self_ty(),
+        }
+    }
+
+    pub fn trait_ref_mut(&mut self) -> &mut RTraitRef {
+        match self {
+            Predicate::Trait(_, tr) => tr,
+            Predicate::HigherRanked { pred, .. } => pred.trait_ref_mut(),
+        }
+    }
+}
+
+impl<R> TraitRef<R>
+where
+    R: Clone + Eq,
+{
+    /// Iterate over the types appearing in the trait reference
+    pub fn iter_types(&self) -> impl Iterator<Item = &Ty<R>> {
+        self.types
+            .iter()
+            .chain(self.assoc_types.iter().map(|(_, ty)| ty))
+    }
+
+    pub fn fmt_with_ctx<'a, 'b, T>(&'a self, ctx: &'b T) -> String
+    where
+        R: 'a,
+        T: Formatter<ConstGenericVarId::Id>
+            + Formatter<TypeVarId::Id>
+            + Formatter<TypeDeclId::Id>
+            + Formatter<GlobalDeclId::Id>
+            + Formatter<&'a R>,
+    {
+        let mut params: Vec<String> = self.types.iter().map(|ty| ty.fmt_with_ctx(ctx)).collect();
+        params.extend(self.const_generics.iter().map(|cg| cg.fmt_with_ctx(ctx)));
+        params.extend(
+            self.assoc_types
+                .iter()
+                .map(|(name, ty)| format!("{name} = {}", ty.fmt_with_ctx(ctx))),
+        );
+        if params.is_empty() {
+            self.trait_name.to_string()
+        } else {
+            format!("{}<{}>", self.trait_name, params.join(", "))
+        }
+    }
+}
+
+impl<R> TraitRef<R>
+where
+    R: Copy + Clone + Eq,
+{
+    pub fn substitute<R1>(
+        &self,
+        rsubst: &dyn Fn(&R) -> R1,
+        tsubst: &dyn Fn(&TypeVarId::Id) -> Ty<R1>,
+        cgsubst: &dyn Fn(&ConstGenericVarId::Id) -> ConstGeneric,
+    ) -> TraitRef<R1>
+    where
+        R1: Clone + Eq,
+    {
+        TraitRef {
+            trait_name: self.trait_name.clone(),
+            types: self
+                .types
+                .iter()
+                .map(|ty| ty.substitute(rsubst, tsubst, cgsubst))
+                .collect(),
+            const_generics: self
+                .const_generics
+                .iter()
+                .map(|cg| cg.substitute(cgsubst))
+                .collect(),
+            assoc_types: self
+                .assoc_types
+                .iter()
+                .map(|(name, ty)| (name.clone(), ty.substitute(rsubst, tsubst, cgsubst)))
+                .collect(),
         }
     }
 }
@@ -982,7 +1336,7 @@ impl Formatter<TypeVarId::Id> for TypeDecl {
 impl Formatter<RegionVarId::Id> for TypeDecl {
     fn format_object(&self, id: RegionVarId::Id) -> String {
         let var = self.region_params.get(id).unwrap();
-        var.to_string()
+        var.lifetime_display_name()
     }
 }
 
@@ -1019,13 +1373,116 @@ impl Formatter<TypeDeclId::Id> for TypeDecls {
     }
 }
 
+/// Auxiliary definition used to format definitions.
+pub struct TypeDeclFormatter<'a> {
+    pub type_defs: &'a TypeDecls,
+    pub global_defs: &'a dyn Formatter<GlobalDeclId::Id>,
+    /// The region parameters of the definition we are printing (needed to
+    /// correctly pretty print region var ids)
+    pub region_params: &'a RegionVarId::Vector<RegionVar>,
+    /// The type parameters of the definition we are printing (needed to
+    /// correctly pretty print type var ids)
+    pub type_params: &'a TypeVarId::Vector<TypeVar>,
+    /// The const generic parameters of the definition we are printing (needed to
+    /// correctly pretty print type var ids)
+    pub const_generic_params: &'a ConstGenericVarId::Vector<ConstGenericVar>,
+    /// If `true`, print the variance of the generic parameters (see
+    /// [TypeDecl::fmt_variance])
+    pub show_variance: bool,
+}
+
+impl<'a> Formatter<RegionVarId::Id> for TypeDeclFormatter<'a> {
+    fn format_object(&self, id: RegionVarId::Id) -> String {
+        // Lookup the region parameter
+        let v = self.region_params.get(id).unwrap();
+        // Format
+        v.lifetime_display_name()
+    }
+}
+
+impl<'a> Formatter<ConstGenericVarId::Id> for TypeDeclFormatter<'a> {
+    fn format_object(&self, id: ConstGenericVarId::Id) -> String {
+        // Lookup the region parameter
+        let v = self.const_generic_params.get(id).unwrap();
+        // Format
+        v.to_string()
+    }
+}
+
+impl<'a> Formatter<TypeVarId::Id> for TypeDeclFormatter<'a> {
+    fn format_object(&self, id: TypeVarId::Id) -> String {
+        // Lookup the type parameter
+        let v = self.type_params.get(id).unwrap();
+        // Format
+        v.to_string()
+    }
+}
+
+impl<'a> Formatter<&Region<RegionVarId::Id>> for TypeDeclFormatter<'a> {
+    fn format_object(&self, r: &Region<RegionVarId::Id>) -> String {
+        r.fmt_with_ctx(self)
+    }
+}
+
+impl<'a> Formatter<&ErasedRegion> for TypeDeclFormatter<'a> {
+    fn format_object(&self, _: &ErasedRegion) -> String {
+        "".to_owned()
+    }
+}
+
+impl<'a> Formatter<&TypeDecl> for TypeDeclFormatter<'a> {
+    fn format_object(&self, def: &TypeDecl) -> String {
+        let decl = def.fmt_with_ctx(self);
+        let variance = def.fmt_variance();
+        if self.show_variance && !variance.is_empty() {
+            format!("{variance}\n{decl}")
+        } else {
+            decl
+        }
+    }
+}
+
+impl<'a> Formatter<TypeDeclId::Id> for TypeDeclFormatter<'a> {
+    fn format_object(&self, id: TypeDeclId::Id) -> String {
+        self.type_defs.format_object(id)
+    }
+}
+
+impl<'a> Formatter<GlobalDeclId::Id> for TypeDeclFormatter<'a> {
+    fn format_object(&self, id: GlobalDeclId::Id) -> String {
+        self.global_defs.format_object(id)
+    }
+}
+
 impl<R: Clone + std::cmp::Eq> Ty<R> {
     pub fn contains_never(&self) -> bool {
         match self {
             Ty::Never => true,
             Ty::Adt(_, _, tys, _) => tys.iter().any(|ty| ty.contains_never()),
-            Ty::TypeVar(_) | Ty::Literal(_) => false,
+            Ty::TypeVar(_) | Ty::Literal(_) | Ty::TraitAssoc(..) => false,
             Ty::Ref(_, ty, _) | Ty::RawPtr(ty, _) => ty.contains_never(),
+            Ty::TraitObject(traits, _) => traits
+                .iter()
+                .any(|tr| tr.iter_types().any(|ty| ty.contains_never())),
+            // A function pointer which never returns is a valid value
+            Ty::FnPtr(inputs, _) => inputs.iter().any(|ty| ty.contains_never()),
+        }
+    }
+
+    /// Returns `true` if the type contains an associated type we couldn't
+    /// resolve (see [Ty::TraitAssoc])
+    pub fn contains_trait_assoc(&self) -> bool {
+        match self {
+            Ty::TraitAssoc(..) => true,
+            Ty::Adt(_, _, tys, _) => tys.iter().any(|ty| ty.contains_trait_assoc()),
+            Ty::TypeVar(_) | Ty::Literal(_) | Ty::Never => false,
+            Ty::Ref(_, ty, _) | Ty::RawPtr(ty, _) => ty.contains_trait_assoc(),
+            Ty::TraitObject(traits, _) => traits
+                .iter()
+                .any(|tr| tr.iter_types().any(|ty| ty.contains_trait_assoc())),
+            Ty::FnPtr(inputs, output) => {
+                inputs.iter().any(|ty| ty.contains_trait_assoc()) || output.contains_trait_assoc()
+            }
         }
     }
 }
@@ -1051,6 +1508,9 @@ pub trait TypeVisitor {
             Never => self.visit_ty_never(),
             Ref(r, ty, rk) => self.visit_ty_ref(r, ty, rk),
             RawPtr(ty, rk) => self.visit_ty_raw_ptr(ty, rk),
+            TraitObject(traits, r) => self.visit_ty_trait_object(traits, r),
+            FnPtr(inputs, output) => self.visit_ty_fn_ptr(inputs, output),
+            TraitAssoc(trait_id, vid, name) => self.visit_ty_trait_assoc(trait_id, vid, name),
         }
     }
 
@@ -1089,6 +1549,40 @@ pub trait TypeVisitor {
         self.visit_ty(ty);
     }
 
+    fn visit_ty_trait_object<R: Clone + std::cmp::Eq>(
+        &mut self,
+        traits: &Vec<TraitRef<R>>,
+        _r: &R,
+    ) {
+        // We ignore the region
+        for tr in traits {
+            self.visit_trait_ref(tr);
+        }
+    }
+
+    fn visit_ty_fn_ptr<R: Clone + std::cmp::Eq>(&mut self, inputs: &Vec<Ty<R>>, output: &Box<Ty<R>>) {
+        for ty in inputs {
+            self.visit_ty(ty);
+        }
+        self.visit_ty(output);
+    }
+
+    fn visit_ty_trait_assoc(&mut self, _trait_id: &TraitDeclId::Id, vid: &TypeVarId::Id, _name: &str) {
+        self.visit_type_var_id(vid);
+    }
+
+    fn visit_trait_ref<R: Clone + std::cmp::Eq>(&mut self, tr: &TraitRef<R>) {
+        for ty in &tr.types {
+            self.visit_ty(ty);
+        }
+        for cg in &tr.const_generics {
+            self.visit_const_generic(cg);
+        }
+        for (_, ty) in &tr.assoc_types {
+            self.visit_ty(ty);
+        }
+    }
+
     fn visit_type_id(&mut self, id: &TypeId) {
         use TypeId::*;
         match id {
@@ -1108,6 +1602,30 @@ pub trait TypeVisitor {
             Global(id) => self.visit_global_decl_id(id),
             Var(id) => self.visit_const_generic_var_id(id),
             Value(lit) => self.visit_literal(lit),
+            Expr(e) => self.visit_const_generic_expr(e),
+        }
+    }
+
+    fn visit_const_generic_expr(&mut self, e: &ConstGenericExpr) {
+        self.default_visit_const_generic_expr(e)
+    }
+
+    fn default_visit_const_generic_expr(&mut self, e: &ConstGenericExpr) {
+        use ConstGenericExpr::*;
+        match e {
+            BinaryOp(_, cg0, cg1) => {
+                self.visit_const_generic(cg0);
+                self.visit_const_generic(cg1);
+            }
+            UnaryOp(_, cg) | Cast(cg, _) => self.visit_const_generic(cg),
+            FunCall(_, args) => {
+                // We ignore the function id: the function ids are explored
+                // by the expression visitor (we would otherwise get naming
+                // collisions)
+                for cg in args {
+                    self.visit_const_generic(cg);
+                }
+            }
         }
     }
 
@@ -1119,3 +1637,68 @@ pub trait TypeVisitor {
 }
 
 } // make_generic_in_borrows
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mk_region_params() -> RegionVarId::Vector<RegionVar> {
+        let mut regions = RegionVarId::Vector::new();
+        regions.push_back(RegionVar {
+            index: RegionVarId::Id::new(0),
+            name: Some("'a".to_string()),
+        });
+        regions.push_back(RegionVar {
+            index: RegionVarId::Id::new(1),
+            name: None,
+        });
+        regions
+    }
+
+    #[test]
+    fn test_lifetime_names() {
+        let regions = mk_region_params();
+        // The named regions keep their names, the anonymous regions get a
+        // synthetic name derived from their index
+        let names: Vec<String> = regions.iter().map(|r| r.lifetime_display_name()).collect();
+        assert!(names == vec!["'a".to_string(), "'r1".to_string()]);
+        assert!(
+            regions.lifetime_names()
+                == vec![
+                    (RegionVarId::Id::new(0), Some("'a".to_string())),
+                    (RegionVarId::Id::new(1), None)
+                ]
+        );
+    }
+
+    #[test]
+    fn test_format_regions() {
+        let region_params = mk_region_params();
+        let fmt = TypeDeclFormatter {
+            type_defs: &TypeDeclId::Map::new(),
+            global_defs: &DummyFormatter {},
+            region_params: &region_params,
+            type_params: &TypeVarId::Vector::new(),
+            const_generic_params: &ConstGenericVarId::Vector::new(),
+            show_variance: false,
+        };
+        fn u32_ty<R: Clone + Eq>() -> Box<Ty<R>> {
+            Box::new(Ty::Literal(LiteralTy::Integer(IntegerTy::U32)))
+        }
+        let named: RTy = Ty::Ref(
+            Region::Var(RegionVarId::Id::new(0)),
+            u32_ty(),
+            RefKind::Shared,
+        );
+        let anonymous: RTy = Ty::Ref(
+            Region::Var(RegionVarId::Id::new(1)),
+            u32_ty(),
+            RefKind::Shared,
+        );
+        let erased: ETy = Ty::Ref(ErasedRegion::Erased, u32_ty(), RefKind::Shared);
+        assert!(named.fmt_with_ctx(&fmt) == "&'a (u32)");
+        assert!(anonymous.fmt_with_ctx(&fmt) == "&'r1 (u32)");
+        // The erased regions are not printed
+        assert!(erased.fmt_with_ctx(&fmt) == "& (u32)");
+    }
+}
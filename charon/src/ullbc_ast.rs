@@ -11,7 +11,8 @@ pub use crate::ullbc_ast_utils::*;
 use crate::values::*;
 use macros::generate_index_type;
 use macros::{EnumAsGetters, EnumIsA, VariantIndexArity, VariantName};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 // Block identifier. Similar to rust's `BasicBlock`.
 generate_index_type!(BlockId);
@@ -28,24 +29,41 @@ pub type GlobalDecl = GGlobalDecl<BlockId::Vector<BlockData>>;
 pub type GlobalDecls = GlobalDeclId::Map<GlobalDecl>;
 
 /// A raw statement: a statement without meta data.
-#[derive(Debug, Clone, EnumIsA, EnumAsGetters, VariantName, Serialize)]
+#[derive(Debug, Clone, EnumIsA, EnumAsGetters, VariantName, Serialize, Deserialize, JsonSchema)]
 pub enum RawStatement {
     Assign(Place, Rvalue),
     FakeRead(Place),
     SetDiscriminant(Place, VariantId::Id),
-    /// We translate this to [crate::llbc_ast::RawStatement::Drop] in LLBC
+    /// The storage of the local becomes valid (this marks the beginning of
+    /// its lexical lifetime). We only generate those statements if the user
+    /// asked for them (see [crate::cli_options::CliOpts::storage_annotations]).
+    StorageLive(VarId::Id),
+    /// The storage of the local becomes invalid (this marks the end of its
+    /// lexical lifetime). We translate this to
+    /// [crate::llbc_ast::RawStatement::Drop] in LLBC, unless the user asked for
+    /// the storage annotations.
     StorageDead(VarId::Id),
     /// We translate this to [crate::llbc_ast::RawStatement::Drop] in LLBC
     Deinit(Place),
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Statement {
     pub meta: Meta,
     pub content: RawStatement,
 }
 
-#[derive(Debug, Clone, EnumIsA, EnumAsGetters, VariantName, VariantIndexArity, Serialize)]
+#[derive(
+    Debug,
+    Clone,
+    EnumIsA,
+    EnumAsGetters,
+    VariantName,
+    VariantIndexArity,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum SwitchTargets {
     /// Gives the `if` block and the `else` block
     If(BlockId::Id, BlockId::Id),
@@ -56,7 +74,7 @@ pub enum SwitchTargets {
 }
 
 /// A raw terminator: a terminator without meta data.
-#[derive(Debug, Clone, EnumIsA, EnumAsGetters, Serialize)]
+#[derive(Debug, Clone, EnumIsA, EnumAsGetters, Serialize, Deserialize, JsonSchema)]
 pub enum RawTerminator {
     Goto {
         target: BlockId::Id,
@@ -68,9 +86,13 @@ pub enum RawTerminator {
     Panic,
     Return,
     Unreachable,
+    /// Drop a value. If the type of the value has a custom `Drop` impl (see
+    /// [crate::gast::GFunDecl::implemented_drop]) the drop calls the `drop`
+    /// method, which may panic: `unwind` is the cleanup block we then go to.
     Drop {
         place: Place,
         target: BlockId::Id,
+        unwind: Option<BlockId::Id>,
     },
     /// Function call.
     /// For now, we only accept calls to top-level functions.
@@ -83,16 +105,90 @@ pub enum RawTerminator {
         expected: bool,
         target: BlockId::Id,
     },
+    /// Suspend the execution of a generator (this only appears in the bodies
+    /// of generators, for instance the bodies of `async` blocks).
+    Yield {
+        /// The yielded value
+        value: Operand,
+        /// The block to jump to when the generator is resumed
+        resume: BlockId::Id,
+        /// The place in which to store the resume argument
+        resume_place: Place,
+        /// The block to jump to if the generator is dropped while suspended
+        drop_target: Option<BlockId::Id>,
+    },
+    /// Return from a generator which gets dropped while suspended.
+    GeneratorDrop,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Terminator {
     pub meta: Meta,
     pub content: RawTerminator,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BlockData {
     pub statements: Vec<Statement>,
     pub terminator: Terminator,
+    /// `true` if the block is the header of a loop (see [crate::detect_loops]).
+    pub is_loop_header: bool,
+}
+
+/// A suspension point of a generator, i.e., a [RawTerminator::Yield].
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SuspensionPoint {
+    /// The block ending with the `yield`
+    pub block: BlockId::Id,
+    /// The block from which the execution continues upon resumption
+    pub resume: BlockId::Id,
+    /// The block from which the execution continues if the generator is
+    /// dropped while suspended at this point
+    pub drop_target: Option<BlockId::Id>,
+    /// The local variables which are live across this point: they are saved
+    /// in the generator state while the generator is suspended (see
+    /// [GeneratorDecl::state_type]). Sorted by identifier.
+    pub live_vars: Vec<VarId::Id>,
 }
+
+/// A generator (for instance, the desugaring of an `async` block).
+///
+/// The body of the generator is translated as a regular function (see
+/// [GFunDecl]) which receives the generator state and the resume argument,
+/// and returns the generator's return value. We view this function as a
+/// state machine: the generator is either unresumed (we start from the
+/// entry block upon resumption), returned, poisoned (it panicked) or
+/// suspended at one of its suspension points (we continue from the
+/// corresponding `resume` block).
+///
+/// An `async` function returns the generator its body is desugared to (as an
+/// `impl Future`, see [crate::types::TypeDeclKind::ImplTrait]). Polling the
+/// future resumes the generator with the task context (the resume argument
+/// is a `core::future::ResumeTy`): the generator yields `()` while the future
+/// is pending, and returns the output of the future. Each `.await` in the body
+/// is itself a loop which polls the awaited future, and yields while this
+/// future is pending.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GeneratorDecl {
+    /// The function implementing the generator body
+    pub def_id: FunDeclId::Id,
+    /// The enumeration of the states of the generator: `Unresumed`,
+    /// `Returned` and `Poisoned`, then one variant `Suspend{i}` per
+    /// suspension point, whose fields are the variables live across this
+    /// point. The regions are erased by rustc in the bodies: we use
+    /// `'static` in the fields. The enumeration is opaque if the generator
+    /// body is.
+    pub state_type: TypeDeclId::Id,
+    /// The types of the captured variables (the generator state is the
+    /// tuple of those variables)
+    pub upvar_tys: Vec<ETy>,
+    pub resume_ty: ETy,
+    pub yield_ty: ETy,
+    pub return_ty: ETy,
+    /// The suspension points, in the order of the block identifiers.
+    /// Following rustc, the state "suspended at point i" is numbered `i + 3`,
+    /// the first three states being: unresumed, returned, poisoned.
+    pub suspension_points: Vec<SuspensionPoint>,
+}
+
+pub type GeneratorDecls = FunDeclId::Map<GeneratorDecl>;
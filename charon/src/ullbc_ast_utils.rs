@@ -4,6 +4,7 @@
 use crate::expressions::*;
 use crate::formatter::Formatter;
 pub use crate::gast_utils::*;
+use crate::liveness::compute_liveness;
 use crate::meta::Meta;
 use crate::types::*;
 use crate::ullbc_ast::*;
@@ -50,6 +51,7 @@ impl Statement {
             RawStatement::SetDiscriminant(place, variant_id) => {
                 RawStatement::SetDiscriminant(place.substitute(subst), *variant_id)
             }
+            RawStatement::StorageLive(var_id) => RawStatement::StorageLive(*var_id),
             RawStatement::StorageDead(var_id) => RawStatement::StorageDead(*var_id),
             RawStatement::Deinit(place) => RawStatement::Deinit(place.substitute(subst)),
         };
@@ -74,9 +76,14 @@ impl Terminator {
             RawTerminator::Panic => RawTerminator::Panic,
             RawTerminator::Return => RawTerminator::Return,
             RawTerminator::Unreachable => RawTerminator::Unreachable,
-            RawTerminator::Drop { place, target } => RawTerminator::Drop {
+            RawTerminator::Drop {
+                place,
+                target,
+                unwind,
+            } => RawTerminator::Drop {
                 place: place.substitute(subst),
                 target: *target,
+                unwind: *unwind,
             },
             RawTerminator::Call { call, target } => {
                 let Call {
@@ -115,6 +122,18 @@ impl Terminator {
                 expected: *expected,
                 target: *target,
             },
+            RawTerminator::Yield {
+                value,
+                resume,
+                resume_place,
+                drop_target,
+            } => RawTerminator::Yield {
+                value: value.substitute(subst),
+                resume: *resume,
+                resume_place: resume_place.substitute(subst),
+                drop_target: *drop_target,
+            },
+            RawTerminator::GeneratorDrop => RawTerminator::GeneratorDrop,
         };
 
         Terminator::new(self.meta, terminator)
@@ -133,6 +152,7 @@ impl BlockData {
         BlockData {
             statements,
             terminator,
+            is_loop_header: self.is_loop_header,
         }
     }
 }
@@ -163,6 +183,9 @@ impl Statement {
                 place.fmt_with_ctx(ctx),
                 variant_id
             ),
+            RawStatement::StorageLive(vid) => {
+                format!("@storage_live({})", vid.to_pretty_string())
+            }
             RawStatement::StorageDead(vid) => {
                 format!("@storage_dead({})", vid.to_pretty_string())
             }
@@ -209,8 +232,16 @@ impl Terminator {
             RawTerminator::Panic => "panic".to_string(),
             RawTerminator::Return => "return".to_string(),
             RawTerminator::Unreachable => "unreachable".to_string(),
-            RawTerminator::Drop { place, target } => {
-                format!("drop {} -> bb{}", place.fmt_with_ctx(ctx), target)
+            RawTerminator::Drop {
+                place,
+                target,
+                unwind,
+            } => {
+                let unwind = match unwind {
+                    Option::Some(unwind) => format!(", unwind: bb{unwind}"),
+                    Option::None => "".to_string(),
+                };
+                format!("drop {} -> bb{}{}", place.fmt_with_ctx(ctx), target, unwind)
             }
             RawTerminator::Call { call, target } => {
                 let Call {
@@ -235,6 +266,25 @@ impl Terminator {
                 expected,
                 target
             ),
+            RawTerminator::Yield {
+                value,
+                resume,
+                resume_place,
+                drop_target,
+            } => {
+                let drop_target = match drop_target {
+                    Option::Some(drop_target) => format!(", drop: bb{drop_target}"),
+                    Option::None => "".to_string(),
+                };
+                format!(
+                    "{} := yield {} -> bb{}{}",
+                    resume_place.fmt_with_ctx(ctx),
+                    value.fmt_with_ctx(ctx),
+                    resume,
+                    drop_target
+                )
+            }
+            RawTerminator::GeneratorDrop => "generator_drop".to_string(),
         }
     }
 }
@@ -329,6 +379,48 @@ impl ExprBody {
     pub fn fmt_with_ctx_names(&self, ctx: &CtxNames<'_>) -> String {
         self.fmt_with_names(ctx.type_context, ctx.fun_context, ctx.global_context)
     }
+
+    /// Collect the suspension points (i.e., the `yield`s) of a generator
+    /// body, in the order of the block identifiers.
+    ///
+    /// The variables live across a suspension point are the variables live
+    /// at the exit of the block ending with the `yield`, except the resume
+    /// place (it is overwritten upon resumption) and the first argument (the
+    /// generator itself).
+    pub fn suspension_points(&self) -> Vec<SuspensionPoint> {
+        let liveness = compute_liveness(self);
+        let generator_var = VarId::Id::new(1);
+        self.body
+            .iter_indexed_values()
+            .filter_map(|(block, data)| match &data.terminator.content {
+                RawTerminator::Yield {
+                    resume,
+                    resume_place,
+                    drop_target,
+                    ..
+                } => {
+                    let mut live_vars: Vec<VarId::Id> = liveness[&block]
+                        .1
+                        .iter()
+                        .copied()
+                        .filter(|v| {
+                            *v != generator_var
+                                && !(*v == resume_place.var_id
+                                    && resume_place.projection.is_empty())
+                        })
+                        .collect();
+                    live_vars.sort();
+                    Some(SuspensionPoint {
+                        block,
+                        resume: *resume,
+                        drop_target: *drop_target,
+                        live_vars,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 pub(crate) struct FunDeclsFormatter<'ctx> {
@@ -396,8 +488,12 @@ impl<'ctx> Formatter<GlobalDeclId::Id> for GlobalDeclsFormatter<'ctx> {
 
 impl Formatter<GlobalDeclId::Id> for GlobalDecls {
     fn format_object(&self, id: GlobalDeclId::Id) -> String {
-        let d = self.get(id).unwrap();
-        d.name.to_string()
+        // The definition may not be available yet, especially if we print-debug
+        // while translating the crate
+        match self.get(id) {
+            Option::None => id.to_pretty_string(),
+            Option::Some(d) => d.name.to_string(),
+        }
     }
 }
 
@@ -505,6 +601,158 @@ impl GlobalDecl {
     }
 }
 
+/// Format a terminator as pseudo-code: contrary to [Terminator::fmt_with_ctx],
+/// we make the jumps explicit (`goto bbN`).
+fn pretty_fmt_terminator<'a, C>(terminator: &'a Terminator, ctx: &C) -> String
+where
+    C: Formatter<VarId::Id>
+        + Formatter<TypeVarId::Id>
+        + Formatter<&'a ErasedRegion>
+        + Formatter<TypeDeclId::Id>
+        + Formatter<ConstGenericVarId::Id>
+        + Formatter<FunDeclId::Id>
+        + Formatter<GlobalDeclId::Id>
+        + Formatter<(TypeDeclId::Id, VariantId::Id)>
+        + Formatter<(TypeDeclId::Id, Option<VariantId::Id>, FieldId::Id)>,
+{
+    match &terminator.content {
+        RawTerminator::Switch { discr, targets } => match targets {
+            SwitchTargets::If(true_block, false_block) => format!(
+                "if {} {{ goto bb{true_block} }} else {{ goto bb{false_block} }}",
+                discr.fmt_with_ctx(ctx),
+            ),
+            SwitchTargets::SwitchInt(_, maps, otherwise) => {
+                let mut maps: Vec<String> = maps
+                    .iter()
+                    .map(|(v, bid)| format!("{} => goto bb{bid}", v.to_string()))
+                    .collect();
+                maps.push(format!("_ => goto bb{otherwise}"));
+                format!(
+                    "switch {} {{ {} }}",
+                    discr.fmt_with_ctx(ctx),
+                    maps.join(", ")
+                )
+            }
+        },
+        RawTerminator::Drop {
+            place,
+            target,
+            unwind,
+        } => {
+            let unwind = match unwind {
+                Option::Some(unwind) => format!(" (unwind: bb{unwind})"),
+                Option::None => "".to_string(),
+            };
+            format!("drop {}; goto bb{target}{unwind}", place.fmt_with_ctx(ctx))
+        }
+        RawTerminator::Call { call, target } => {
+            let call_str = fmt_call(
+                ctx,
+                &call.func,
+                &call.region_args,
+                &call.type_args,
+                &call.const_generic_args,
+                &call.args,
+            );
+            format!(
+                "{} := {call_str}; goto bb{target}",
+                call.dest.fmt_with_ctx(ctx)
+            )
+        }
+        RawTerminator::Assert {
+            cond,
+            expected,
+            target,
+        } => format!(
+            "assert({} == {expected}); goto bb{target}",
+            cond.fmt_with_ctx(ctx)
+        ),
+        _ => terminator.fmt_with_ctx(ctx),
+    }
+}
+
+/// Print a function as indented pseudo-code: the signature on the first
+/// line, then every block with its label, its statements and its
+/// terminator. We use the names of the variables, types, etc. given by
+/// the context.
+pub fn pretty_print(
+    decl: &FunDecl,
+    fmt_ctx: &CtxNames<'_>,
+    w: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let fun_ctx = FunNamesFormatter::new(fmt_ctx.fun_context);
+    let global_ctx = GlobalNamesFormatter::new(fmt_ctx.global_context);
+    let sig_ctx = FunSigFormatter {
+        ty_ctx: fmt_ctx.type_context,
+        global_ctx: &global_ctx,
+        sig: &decl.signature,
+    };
+    let locals = decl.body.as_ref().map(|body| &body.locals);
+    let ctx = GAstFormatter::new(
+        fmt_ctx.type_context,
+        &fun_ctx,
+        &global_ctx,
+        Some(&decl.signature.type_params),
+        locals,
+        Some(&decl.signature.const_generic_params),
+    );
+
+    // The signature: we use the names of the input variables if we have
+    // them
+    let sig = &decl.signature;
+    let mut params: Vec<String> = sig
+        .region_params
+        .iter()
+        .map(|r| r.lifetime_display_name())
+        .collect();
+    params.extend(sig.type_params.iter().map(|x| x.to_string()));
+    params.extend(sig.const_generic_params.iter().map(|x| x.to_string()));
+    let params = if params.is_empty() {
+        "".to_string()
+    } else {
+        format!("<{}>", params.join(", "))
+    };
+    let args: Vec<String> = sig
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            // The input variables start at index 1
+            let id = VarId::Id::new(i + 1);
+            let name = match locals {
+                Some(locals) => locals.get(id).unwrap().to_string(),
+                None => id.to_pretty_string(),
+            };
+            format!("{name}: {}", ty.fmt_with_ctx(&sig_ctx))
+        })
+        .collect();
+    let ret_ty = if sig.output.is_unit() {
+        "".to_string()
+    } else {
+        format!(" -> {}", sig.output.fmt_with_ctx(&sig_ctx))
+    };
+    let sig = format!("fn {}{params}({}){ret_ty}", decl.name, args.join(", "));
+
+    let body = match &decl.body {
+        Option::None => return writeln!(w, "{sig}"),
+        Option::Some(body) => body,
+    };
+    writeln!(w, "{sig} {{")?;
+    let stmt_tab = format!("{TAB_INCR}{TAB_INCR}");
+    for (bid, block) in body.body.iter_indexed_values() {
+        writeln!(w, "{TAB_INCR}bb{bid}:")?;
+        for st in &block.statements {
+            writeln!(w, "{stmt_tab}{};", st.fmt_with_ctx(&ctx))?;
+        }
+        writeln!(
+            w,
+            "{stmt_tab}{};",
+            pretty_fmt_terminator(&block.terminator, &ctx)
+        )?;
+    }
+    writeln!(w, "}}")
+}
+
 impl BlockData {
     /// Visit the operands in an rvalue and generate statements.
     /// Used below in [BlockData::transform_operands].
@@ -521,12 +769,18 @@ impl BlockData {
                 f(meta, nst, o1);
                 f(meta, nst, o2);
             }
+            Rvalue::Aggregate(AggregateKind::Repeat(_, op, _), _) => f(meta, nst, op),
             Rvalue::Aggregate(_, ops) => {
                 for op in ops {
                     f(meta, nst, op);
                 }
             }
-            Rvalue::Global(_) | Rvalue::Discriminant(_) | Rvalue::Ref(_, _) | Rvalue::Len(..) => {
+            Rvalue::Global(_)
+            | Rvalue::Discriminant(_)
+            | Rvalue::Ref(_, _)
+            | Rvalue::RawPtr(_, _)
+            | Rvalue::Len(..)
+            | Rvalue::FnPtr(..) => {
                 // No operands: nothing to do
             }
         }
@@ -549,6 +803,7 @@ impl BlockData {
                 }
                 RawStatement::FakeRead(_)
                 | RawStatement::SetDiscriminant(_, _)
+                | RawStatement::StorageLive(_)
                 | RawStatement::StorageDead(_)
                 | RawStatement::Deinit(_) => {
                     // No operands: nothing to do
@@ -576,13 +831,23 @@ impl BlockData {
             } => {
                 f(meta, &mut nst, cond);
             }
+            RawTerminator::Yield {
+                value,
+                resume: _,
+                resume_place: _,
+                drop_target: _,
+            } => {
+                f(meta, &mut nst, value);
+            }
             RawTerminator::Panic
             | RawTerminator::Return
             | RawTerminator::Unreachable
+            | RawTerminator::GeneratorDrop
             | RawTerminator::Goto { target: _ }
             | RawTerminator::Drop {
                 place: _,
                 target: _,
+                unwind: _,
             } => {
                 // Nothing to do
             }
@@ -650,6 +915,7 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
             Assign(p, rv) => self.visit_assign(p, rv),
             FakeRead(p) => self.visit_fake_read(p),
             SetDiscriminant(p, vid) => self.visit_set_discriminant(p, vid),
+            StorageLive(vid) => self.visit_storage_live(vid),
             StorageDead(vid) => self.visit_storage_dead(vid),
             Deinit(p) => self.visit_deinit(p),
         }
@@ -668,6 +934,10 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
         self.visit_place(p);
     }
 
+    fn visit_storage_live(&mut self, vid: &VarId::Id) {
+        self.visit_var_id(vid);
+    }
+
     fn visit_storage_dead(&mut self, vid: &VarId::Id) {
         self.visit_var_id(vid);
     }
@@ -693,8 +963,12 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
             Panic => self.visit_panic(),
             Return => self.visit_return(),
             Unreachable => self.visit_unreachable(),
-            Drop { place, target } => {
-                self.visit_drop(place, target);
+            Drop {
+                place,
+                target,
+                unwind,
+            } => {
+                self.visit_drop(place, target, unwind);
             }
             Call { call, target } => {
                 self.visit_call_statement(call, target);
@@ -706,6 +980,15 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
             } => {
                 self.visit_assert(cond, expected, target);
             }
+            Yield {
+                value,
+                resume,
+                resume_place,
+                drop_target,
+            } => {
+                self.visit_yield(value, resume, resume_place, drop_target);
+            }
+            GeneratorDrop => self.visit_generator_drop(),
         }
     }
 
@@ -728,9 +1011,12 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
 
     fn visit_unreachable(&mut self) {}
 
-    fn visit_drop(&mut self, place: &Place, target: &BlockId::Id) {
+    fn visit_drop(&mut self, place: &Place, target: &BlockId::Id, unwind: &Option<BlockId::Id>) {
         self.visit_place(place);
         self.visit_block_id(target);
+        if let Some(unwind) = unwind {
+            self.visit_block_id(unwind);
+        }
     }
 
     fn visit_call_statement(&mut self, call: &Call, target: &BlockId::Id) {
@@ -743,6 +1029,23 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
         self.visit_block_id(target);
     }
 
+    fn visit_yield(
+        &mut self,
+        value: &Operand,
+        resume: &BlockId::Id,
+        resume_place: &Place,
+        drop_target: &Option<BlockId::Id>,
+    ) {
+        self.visit_operand(value);
+        self.visit_block_id(resume);
+        self.visit_place(resume_place);
+        if let Some(drop_target) = drop_target {
+            self.visit_block_id(drop_target);
+        }
+    }
+
+    fn visit_generator_drop(&mut self) {}
+
     fn visit_block_id(&mut self, id: &BlockId::Id) {}
 
     fn visit_switch_targets(&mut self, targets: &SwitchTargets) {
@@ -774,3 +1077,153 @@ pub trait AstVisitor: crate::expressions::ExprVisitor {
 }
 
 } // make_generic_in_borrows
+
+#[cfg(test)]
+mod tests {
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::names::*;
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::types::*;
+    use crate::ullbc_ast::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn mk_place(var_id: VarId::Id) -> Place {
+        Place {
+            var_id,
+            projection: vec![],
+        }
+    }
+
+    fn block(statements: Vec<Statement>, content: RawTerminator) -> BlockData {
+        BlockData {
+            statements,
+            terminator: Terminator::new(dummy_meta(), content),
+            is_loop_header: false,
+        }
+    }
+
+    /// ```text
+    /// fn choose(b: bool, x: u32) -> u32 {
+    ///   bb0: switch b [true -> bb1, false -> bb2]
+    ///   bb1: @return := copy x; return
+    ///   bb2: @return := const 0; return
+    /// }
+    /// ```
+    #[test]
+    fn test_pretty_print() {
+        let u32_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U32));
+        let bool_ty = Ty::Literal(LiteralTy::Bool);
+        let mut locals = VarId::Vector::new();
+        let ret = locals.fresh_var(None, u32_ty.clone());
+        let b = locals.fresh_var(Some("b".to_string()), bool_ty);
+        let x = locals.fresh_var(Some("x".to_string()), u32_ty.clone());
+
+        let mut block_ids = BlockId::Generator::new();
+        let bb0 = block_ids.fresh_id();
+        let bb1 = block_ids.fresh_id();
+        let bb2 = block_ids.fresh_id();
+        let assign = |rhs| {
+            Statement::new(
+                dummy_meta(),
+                RawStatement::Assign(mk_place(ret), Rvalue::Use(rhs)),
+            )
+        };
+        let zero = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(0)));
+        let mut blocks = BlockId::Vector::new();
+        blocks.push_back(block(
+            vec![],
+            RawTerminator::Switch {
+                discr: Operand::Copy(mk_place(b)),
+                targets: SwitchTargets::If(bb1, bb2),
+            },
+        ));
+        blocks.push_back(block(
+            vec![assign(Operand::Copy(mk_place(x)))],
+            RawTerminator::Return,
+        ));
+        blocks.push_back(block(
+            vec![assign(Operand::Const(u32_ty, zero))],
+            RawTerminator::Return,
+        ));
+
+        let decl = FunDecl {
+            def_id: FunDeclId::ZERO,
+            meta: dummy_meta(),
+            name: Name {
+                name: vec![PathElem::Ident("choose".to_string())],
+            },
+            signature: FunSig {
+                region_params: RegionVarId::Vector::new(),
+                num_early_bound_regions: 0,
+                type_params: TypeVarId::Vector::new(),
+                const_generic_params: ConstGenericVarId::Vector::new(),
+                predicates: Vec::new(),
+                inputs: vec![
+                    Ty::Literal(LiteralTy::Bool),
+                    Ty::Literal(LiteralTy::Integer(IntegerTy::U32)),
+                ],
+                output: Ty::Literal(LiteralTy::Integer(IntegerTy::U32)),
+                regions_hierarchy: RegionGroups::new(),
+            },
+            body: Some(ExprBody {
+                meta: dummy_meta(),
+                arg_count: 2,
+                locals,
+                body: blocks,
+                liveness: None,
+            }),
+            builtin: None,
+            calling_convention: CallingConvention::Rust,
+            is_const_fn: false,
+            is_unsafe: false,
+            calls_unsafe: false,
+            self_type: None,
+            trait_id: None,
+            trait_impl_info: None,
+            is_trait_default: false,
+            overrides_default: None,
+            captures: Vec::new(),
+            implemented_drop: false,
+            attributes: Vec::new(),
+            is_root: true,
+            stable_id: None,
+        };
+
+        let type_defs = TypeDecls::new();
+        let fun_names = FunDeclId::Map::new();
+        let global_names = GlobalDeclId::Map::new();
+        let fmt_ctx = CtxNames::new(&type_defs, &fun_names, &global_names);
+        let mut out: Vec<u8> = Vec::new();
+        super::pretty_print(&decl, &fmt_ctx, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0] == "fn choose(b@1: bool, x@2: u32) -> u32 {");
+        assert!(lines[1] == format!("    bb{bb0}:"));
+        assert!(
+            lines[2]
+                == format!("        if copy (b@1) {{ goto bb{bb1} }} else {{ goto bb{bb2} }};")
+        );
+        assert!(lines[3] == format!("    bb{bb1}:"));
+        assert!(lines[4] == "        @0 := copy (x@2);");
+        assert!(lines[5] == "        return;");
+        assert!(lines.last() == Some(&"}"));
+        // The header, the three blocks and the closing brace
+        assert!(lines.len() == 10);
+    }
+}
@@ -20,6 +20,8 @@
 //! only be performed by terminators -, meaning that MIR graphs don't have that
 //! many nodes and edges).
 
+use crate::detect_loops;
+use crate::detect_loops::Cfg;
 use crate::expressions::Place;
 use crate::llbc_ast as tgt;
 use crate::meta::{combine_meta, Meta};
@@ -31,7 +33,6 @@ use hashlink::linked_hash_map::LinkedHashMap;
 use im::Vector;
 use petgraph::algo::floyd_warshall::floyd_warshall;
 use petgraph::algo::toposort;
-use petgraph::graphmap::DiGraphMap;
 use petgraph::Direction;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -39,24 +40,42 @@ use std::iter::FromIterator;
 
 pub type Defs = (tgt::FunDecls, tgt::GlobalDecls);
 
-/// Control-Flow Graph
-type Cfg = DiGraphMap<src::BlockId::Id, ()>;
+/// Compute the blocks which are reachable from the entry block, without going
+/// through the unwinding paths.
+fn get_reachable_blocks(body: &src::ExprBody) -> HashSet<src::BlockId::Id> {
+    let mut reachable = HashSet::new();
+    let mut stack = vec![src::BlockId::ZERO];
+    while let Some(block_id) = stack.pop() {
+        if reachable.insert(block_id) {
+            stack.extend(get_block_targets(body, block_id));
+        }
+    }
+    reachable
+}
 
 /// Small utility
 struct BlockInfo<'a> {
     no_code_duplication: bool,
+    storage_annotations: bool,
     cfg: &'a CfgInfo,
     body: &'a src::ExprBody,
     exits_info: &'a ExitInfo,
     explored: &'a mut HashSet<src::BlockId::Id>,
 }
 
-fn get_block_targets(body: &src::ExprBody, block_id: src::BlockId::Id) -> Vec<src::BlockId::Id> {
+pub(crate) fn get_block_targets(
+    body: &src::ExprBody,
+    block_id: src::BlockId::Id,
+) -> Vec<src::BlockId::Id> {
     let block = body.body.get(block_id).unwrap();
 
     match &block.terminator.content {
         src::RawTerminator::Goto { target }
-        | src::RawTerminator::Drop { place: _, target }
+        | src::RawTerminator::Drop {
+            place: _,
+            target,
+            unwind: _,
+        }
         | src::RawTerminator::Call { call: _, target }
         | src::RawTerminator::Assert {
             cond: _,
@@ -66,9 +85,20 @@ fn get_block_targets(body: &src::ExprBody, block_id: src::BlockId::Id) -> Vec<sr
             vec![*target]
         }
         src::RawTerminator::Switch { discr: _, targets } => targets.get_targets(),
+        src::RawTerminator::Yield {
+            value: _,
+            resume,
+            resume_place: _,
+            drop_target,
+        } => {
+            let mut targets = vec![*resume];
+            targets.extend(drop_target.iter().copied());
+            targets
+        }
         src::RawTerminator::Panic
         | src::RawTerminator::Unreachable
-        | src::RawTerminator::Return => {
+        | src::RawTerminator::Return
+        | src::RawTerminator::GeneratorDrop => {
             vec![]
         }
     }
@@ -80,8 +110,10 @@ struct CfgPartialInfo {
     pub cfg: Cfg,
     /// The CFG where all the backward edges have been removed
     pub cfg_no_be: Cfg,
-    /// We consider the destination of the backward edges to be loop entries and
-    /// store them here.
+    /// The loop entries: the blocks marked as loop headers (see
+    /// [crate::detect_loops]), which are the destinations of the backward
+    /// edges. If the body was not annotated, we compute the loop headers
+    /// ourselves.
     pub loop_entries: HashSet<src::BlockId::Id>,
     /// The backward edges
     pub backward_edges: HashSet<(src::BlockId::Id, src::BlockId::Id)>,
@@ -132,6 +164,21 @@ fn build_cfg_partial_info(body: &src::ExprBody) -> CfgPartialInfo {
         src::BlockId::ZERO,
     );
 
+    // The loop entries: if no block is marked as a loop header, the body may
+    // not have been annotated by [crate::detect_loops]
+    let headers: HashSet<src::BlockId::Id> = body
+        .body
+        .iter_indexed_values()
+        .filter(|(_, block)| block.is_loop_header)
+        .map(|(block_id, _)| block_id)
+        .collect();
+    if headers.is_empty() {
+        cfg.loop_entries
+            .extend(detect_loops::loop_headers(&detect_loops::build_cfg(body)));
+    } else {
+        cfg.loop_entries.extend(headers);
+    }
+
     cfg
 }
 
@@ -173,7 +220,11 @@ fn build_cfg_partial_info_edges(
         // We need to check if it is a backward edge before inserting it in the
         // CFG without backward edges and exploring it
         if ancestors.contains(tgt) {
-            // This is a backward edge
+            // This is a backward edge: its destination is a loop header (this
+            // holds because the MIR control-flow graphs are reducible). We
+            // don't rely on the annotations of the blocks, which may be
+            // missing: the destinations of the backward edges are loop
+            // entries.
             cfg.loop_entries.insert(*tgt);
             cfg.backward_edges.insert((block_id, *tgt));
         } else {
@@ -1398,6 +1449,122 @@ fn translate_child_block(
     }
 }
 
+/// If a block is a trivial block (a block without statements, which simply
+/// gotos another block) that we can skip, return the block it goes to.
+///
+/// Note that translating the block we return is the same as translating
+/// the input block: we stop at the blocks which are loop entries, and at the
+/// gotos which are breaks, continues or go to an exit block.
+fn get_trivial_goto_target(
+    info: &BlockInfo<'_>,
+    parent_loops: &Vector<src::BlockId::Id>,
+    switch_exit_blocks: &im::HashSet<src::BlockId::Id>,
+    block_id: src::BlockId::Id,
+) -> Option<src::BlockId::Id> {
+    if info.cfg.loop_entries.contains(&block_id) {
+        return None;
+    }
+    let block = info.body.body.get(block_id).unwrap();
+    match &block.terminator.content {
+        src::RawTerminator::Goto { target }
+            if block.statements.is_empty()
+                && matches!(
+                    get_goto_kind(info.exits_info, parent_loops, switch_exit_blocks, *target),
+                    GotoKind::Goto
+                ) =>
+        {
+            Some(*target)
+        }
+        _ => None,
+    }
+}
+
+/// Compute the trivial blocks we can skip when translating the branches of
+/// a switch (see [skip_trivial_gotos]).
+///
+/// Depending on the MIR we use, the branches of a switch which are grouped
+/// together (with or-patterns like `E::V1 | E::V2 => ...`) may go to different
+/// trivial blocks, which all go to the same block. Skipping those trivial blocks
+/// allows us to detect that the branches are grouped together.
+///
+/// We only skip the blocks which can only be reached from the switch, through
+/// its branches and the trivial blocks we skip: the blocks we skip are marked
+/// as explored, and must not be reached (and translated) from somewhere else.
+/// We don't skip anything if going to a branch is itself a break, a continue or
+/// a goto to an exit block.
+fn compute_skippable_gotos(
+    info: &BlockInfo<'_>,
+    parent_loops: &Vector<src::BlockId::Id>,
+    switch_exit_blocks: &im::HashSet<src::BlockId::Id>,
+    switch_id: src::BlockId::Id,
+    targets: &[src::BlockId::Id],
+    otherwise: src::BlockId::Id,
+) -> HashSet<src::BlockId::Id> {
+    // Follow the chains of trivial blocks starting at the branches
+    let mut skippable = HashSet::new();
+    for block_id in targets {
+        if !matches!(
+            get_goto_kind(info.exits_info, parent_loops, switch_exit_blocks, *block_id),
+            GotoKind::Goto
+        ) {
+            continue;
+        }
+        let mut block_id = *block_id;
+        while let Some(target) =
+            get_trivial_goto_target(info, parent_loops, switch_exit_blocks, block_id)
+        {
+            if !skippable.insert(block_id) {
+                break;
+            }
+            block_id = target;
+        }
+    }
+    // The otherwise branch is translated on its own
+    skippable.remove(&otherwise);
+
+    // Remove the blocks which have predecessors outside of the switch, until
+    // we reach a fixed point
+    loop {
+        let outside: Vec<src::BlockId::Id> = skippable
+            .iter()
+            .copied()
+            .filter(|block_id| {
+                info.cfg
+                    .cfg
+                    .neighbors_directed(*block_id, Direction::Incoming)
+                    .any(|pred| pred != switch_id && !skippable.contains(&pred))
+            })
+            .collect();
+        if outside.is_empty() {
+            return skippable;
+        }
+        for block_id in outside {
+            skippable.remove(&block_id);
+        }
+    }
+}
+
+/// Follow the chain of skippable trivial blocks (see [compute_skippable_gotos])
+/// starting at a switch branch.
+///
+/// The blocks we skip are marked as explored: their only content is the goto
+/// to the block we return, which is translated instead.
+fn skip_trivial_gotos(
+    info: &mut BlockInfo<'_>,
+    skippable: &HashSet<src::BlockId::Id>,
+    block_id: src::BlockId::Id,
+) -> src::BlockId::Id {
+    let mut block_id = block_id;
+    while skippable.contains(&block_id) {
+        info.explored.insert(block_id);
+        match &info.body.body.get(block_id).unwrap().terminator.content {
+            src::RawTerminator::Goto { target } => block_id = *target,
+            _ => unreachable!(),
+        }
+    }
+    block_id
+}
+
 fn opt_statement_to_nop_if_none(meta: Meta, opt_st: Option<tgt::Statement>) -> tgt::Statement {
     match opt_st {
         Some(st) => st,
@@ -1405,7 +1572,7 @@ fn opt_statement_to_nop_if_none(meta: Meta, opt_st: Option<tgt::Statement>) -> t
     }
 }
 
-fn translate_statement(st: &src::Statement) -> Option<tgt::Statement> {
+fn translate_statement(storage_annotations: bool, st: &src::Statement) -> Option<tgt::Statement> {
     let src_meta = st.meta;
     let st = match &st.content {
         src::RawStatement::Assign(place, rvalue) => {
@@ -1415,10 +1582,15 @@ fn translate_statement(st: &src::Statement) -> Option<tgt::Statement> {
         src::RawStatement::SetDiscriminant(place, variant_id) => {
             tgt::RawStatement::SetDiscriminant(place.clone(), *variant_id)
         }
+        src::RawStatement::StorageLive(var_id) => tgt::RawStatement::StorageLive(*var_id),
         src::RawStatement::StorageDead(var_id) => {
-            // We translate a StorageDead as a drop
-            let place = Place::new(*var_id);
-            tgt::RawStatement::Drop(place)
+            if storage_annotations {
+                tgt::RawStatement::StorageDead(*var_id)
+            } else {
+                // We translate a StorageDead as a drop
+                let place = Place::new(*var_id);
+                tgt::RawStatement::Drop(place)
+            }
         }
         src::RawStatement::Deinit(place) => {
             // We translate a deinit as a drop
@@ -1432,6 +1604,7 @@ fn translate_terminator(
     info: &mut BlockInfo<'_>,
     parent_loops: Vector<src::BlockId::Id>,
     switch_exit_blocks: &im::HashSet<src::BlockId::Id>,
+    block_id: src::BlockId::Id,
     terminator: &src::Terminator,
 ) -> Option<tgt::Statement> {
     let src_meta = terminator.meta;
@@ -1443,6 +1616,10 @@ fn translate_terminator(
         src::RawTerminator::Return => {
             Some(tgt::Statement::new(src_meta, tgt::RawStatement::Return))
         }
+        src::RawTerminator::Yield { .. } | src::RawTerminator::GeneratorDrop => {
+            // The driver rejects the generators when we generate LLBC
+            unreachable!("Generators are only supported in ULLBC (use --ullbc)");
+        }
         src::RawTerminator::Goto { target } => translate_child_block(
             info,
             parent_loops,
@@ -1450,7 +1627,11 @@ fn translate_terminator(
             terminator.meta,
             *target,
         ),
-        src::RawTerminator::Drop { place, target } => {
+        src::RawTerminator::Drop {
+            place,
+            target,
+            unwind: _,
+        } => {
             let opt_child = translate_child_block(
                 info,
                 parent_loops,
@@ -1532,9 +1713,8 @@ fn translate_terminator(
                     // translated as one of the branches of the switch.
                     //
                     // Rk.: note there may be intermediate gotos depending
-                    // on the MIR we use. Typically, we manage to detect the
-                    // grouped branches with Optimized MIR, but not with Promoted
-                    // MIR. See the comment in "tests/src/matches.rs".
+                    // on the MIR we use (typically with Promoted MIR - see the
+                    // comment in "tests/src/matches.rs"): we skip those.
 
                     // We link block ids to:
                     // - vector of matched integer values
@@ -1544,8 +1724,19 @@ fn translate_terminator(
                         (Vec<v::ScalarValue>, tgt::Statement),
                     > = LinkedHashMap::new();
 
+                    let skippable = compute_skippable_gotos(
+                        info,
+                        &parent_loops,
+                        switch_exit_blocks,
+                        block_id,
+                        &targets.iter().map(|(_, bid)| *bid).collect::<Vec<_>>(),
+                        *otherwise,
+                    );
+
                     // Translate the children expressions
                     for (v, bid) in targets.iter() {
+                        let bid = &skip_trivial_gotos(info, &skippable, *bid);
+
                         // Check if the block has already been translated:
                         // if yes, it means we need to group branches
                         if branches.contains_key(bid) {
@@ -1620,7 +1811,9 @@ fn combine_expressions(
     }
 }
 
-fn is_terminal(exp: &tgt::Statement) -> bool {
+/// Return `true` if evaluating the statement never leads to the statement
+/// which follows it (see [is_terminal_explore]).
+pub(crate) fn is_terminal(exp: &tgt::Statement) -> bool {
     is_terminal_explore(0, exp)
 }
 
@@ -1635,9 +1828,15 @@ fn is_terminal_explore(num_loops: usize, st: &tgt::Statement) -> bool {
         | tgt::RawStatement::FakeRead(_)
         | tgt::RawStatement::SetDiscriminant(_, _)
         | tgt::RawStatement::Drop(_)
+        | tgt::RawStatement::StorageLive(_)
+        | tgt::RawStatement::StorageDead(_)
         | tgt::RawStatement::Assert(_)
+        | tgt::RawStatement::TaggedAssert(_, _)
         | tgt::RawStatement::Call(_)
-        | tgt::RawStatement::Nop => false,
+        | tgt::RawStatement::LetElse(..)
+        | tgt::RawStatement::TryOp { .. }
+        | tgt::RawStatement::Nop
+        | tgt::RawStatement::MacroExpansion(_) => false,
         tgt::RawStatement::Panic | tgt::RawStatement::Return => true,
         tgt::RawStatement::Break(index) => *index >= num_loops,
         tgt::RawStatement::Continue(_index) => true,
@@ -1653,6 +1852,9 @@ fn is_terminal_explore(num_loops: usize, st: &tgt::Statement) -> bool {
             .iter()
             .all(|tgt_st| is_terminal_explore(num_loops, tgt_st)),
         tgt::RawStatement::Loop(loop_st) => is_terminal_explore(num_loops + 1, loop_st),
+        tgt::RawStatement::IfLet(_, _, then_st, else_st) => {
+            is_terminal_explore(num_loops, then_st) && is_terminal_explore(num_loops, else_st)
+        }
     }
 }
 
@@ -1719,11 +1921,21 @@ fn translate_block(
     // Note that this terminator is an option: we might ignore it
     // (if it is an exit).
 
-    let terminator =
-        translate_terminator(info, nparent_loops, &nswitch_exit_blocks, &block.terminator);
+    let terminator = translate_terminator(
+        info,
+        nparent_loops,
+        &nswitch_exit_blocks,
+        block_id,
+        &block.terminator,
+    );
 
     // Translate the statements inside the block
-    let statements = Vec::from_iter(block.statements.iter().filter_map(translate_statement));
+    let statements = Vec::from_iter(
+        block
+            .statements
+            .iter()
+            .filter_map(|st| translate_statement(info.storage_annotations, st)),
+    );
 
     // We do different things if this is a loop, a switch (which is not
     // a loop) or something else.
@@ -1775,7 +1987,11 @@ fn translate_block(
     }
 }
 
-fn translate_body(no_code_duplication: bool, src_body: &src::ExprBody) -> tgt::ExprBody {
+fn translate_body(
+    no_code_duplication: bool,
+    storage_annotations: bool,
+    src_body: &src::ExprBody,
+) -> tgt::ExprBody {
     // Explore the function body to create the control-flow graph without backward
     // edges, and identify the loop entries (which are destinations of backward edges).
     let cfg_info = build_cfg_partial_info(src_body);
@@ -1794,6 +2010,7 @@ fn translate_body(no_code_duplication: bool, src_body: &src::ExprBody) -> tgt::E
     let mut explored = HashSet::new();
     let mut info = BlockInfo {
         no_code_duplication,
+        storage_annotations,
         cfg: &cfg_info,
         body: src_body,
         exits_info: &exits_info,
@@ -1807,8 +2024,10 @@ fn translate_body(no_code_duplication: bool, src_body: &src::ExprBody) -> tgt::E
     )
     .unwrap();
 
-    // Sanity: check that we translated all the blocks
-    for (bid, _) in src_body.body.iter_indexed_values() {
+    // Sanity: check that we translated all the blocks. We ignore the cleanup
+    // blocks, which are only reachable through the unwinding paths of the
+    // drops: we don't model unwinding in LLBC.
+    for bid in get_reachable_blocks(src_body) {
         assert!(explored.contains(&bid));
     }
 
@@ -1817,12 +2036,14 @@ fn translate_body(no_code_duplication: bool, src_body: &src::ExprBody) -> tgt::E
         arg_count: src_body.arg_count,
         locals: src_body.locals.clone(),
         body: stmt,
+        liveness: None,
     }
 }
 
 /// `type_defs`, `global_defs`: those parameters are used for pretty-printing purposes
 fn translate_function(
     no_code_duplication: bool,
+    storage_annotations: bool,
     type_defs: &TypeDecls,
     src_defs: &src::FunDecls,
     src_def_id: FunDeclId::Id,
@@ -1845,12 +2066,28 @@ fn translate_function(
         body: src_def
             .body
             .as_ref()
-            .map(|b| translate_body(no_code_duplication, b)),
+            .map(|b| translate_body(no_code_duplication, storage_annotations, b)),
+        builtin: src_def.builtin,
+        calling_convention: src_def.calling_convention,
+        is_const_fn: src_def.is_const_fn,
+        is_unsafe: src_def.is_unsafe,
+        calls_unsafe: src_def.calls_unsafe,
+        self_type: src_def.self_type.clone(),
+        trait_id: src_def.trait_id,
+        trait_impl_info: src_def.trait_impl_info.clone(),
+        is_trait_default: src_def.is_trait_default,
+        overrides_default: src_def.overrides_default,
+        captures: src_def.captures.clone(),
+        implemented_drop: src_def.implemented_drop,
+        attributes: src_def.attributes.clone(),
+        is_root: src_def.is_root,
+        stable_id: src_def.stable_id.clone(),
     }
 }
 
 fn translate_global(
     no_code_duplication: bool,
+    storage_annotations: bool,
     type_defs: &TypeDecls,
     global_defs: &src::GlobalDecls,
     global_id: GlobalDeclId::Id,
@@ -1872,7 +2109,10 @@ fn translate_global(
         body: src_def
             .body
             .as_ref()
-            .map(|b| translate_body(no_code_duplication, b)),
+            .map(|b| translate_body(no_code_duplication, storage_annotations, b)),
+        trait_id: src_def.trait_id,
+        is_root: src_def.is_root,
+        stable_id: src_def.stable_id.clone(),
     }
 }
 
@@ -1882,8 +2122,12 @@ fn translate_global(
 /// can be a sign that the reconstruction is of poor quality, but sometimes
 /// code duplication is necessary, in the presence of "fused" match branches for
 /// instance).
+///
+/// `storage_annotations`: if true, preserve the `StorageDead` statements
+/// (otherwise, we translate them to drops).
 pub fn translate_functions(
     no_code_duplication: bool,
+    storage_annotations: bool,
     type_defs: &TypeDecls,
     src_funs: &src::FunDecls,
     src_globals: &src::GlobalDecls,
@@ -1897,6 +2141,7 @@ pub fn translate_functions(
             *fun_id,
             translate_function(
                 no_code_duplication,
+                storage_annotations,
                 type_defs,
                 src_funs,
                 *fun_id,
@@ -1909,6 +2154,7 @@ pub fn translate_functions(
             *global_id,
             translate_global(
                 no_code_duplication,
+                storage_annotations,
                 type_defs,
                 src_globals,
                 *global_id,
@@ -1936,3 +2182,72 @@ pub fn translate_functions(
 
     (tgt_funs, tgt_globals)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::{Operand, OperandConstantValue};
+    use crate::meta::{FileId, Loc, LocalFileId, Span};
+    use crate::types::{LiteralTy, Ty};
+    use crate::values::Literal;
+
+    fn meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn block(content: src::RawTerminator) -> src::BlockData {
+        src::BlockData {
+            statements: vec![],
+            terminator: src::Terminator {
+                meta: meta(),
+                content,
+            },
+            is_loop_header: false,
+        }
+    }
+
+    /// A loop whose header was not annotated by [crate::detect_loops]:
+    /// ```text
+    /// bb0: goto bb1
+    /// bb1: if true { goto bb2 } else { goto bb3 }
+    /// bb2: goto bb1
+    /// bb3: return
+    /// ```
+    #[test]
+    fn test_unannotated_loop() {
+        let id = src::BlockId::Id::new;
+        let discr = Operand::Const(
+            Ty::Literal(LiteralTy::Bool),
+            OperandConstantValue::Literal(Literal::Bool(true)),
+        );
+        let mut blocks = src::BlockId::Vector::new();
+        blocks.push_back(block(src::RawTerminator::Goto { target: id(1) }));
+        blocks.push_back(block(src::RawTerminator::Switch {
+            discr,
+            targets: src::SwitchTargets::If(id(2), id(3)),
+        }));
+        blocks.push_back(block(src::RawTerminator::Goto { target: id(1) }));
+        blocks.push_back(block(src::RawTerminator::Return));
+        let body = src::ExprBody {
+            meta: meta(),
+            arg_count: 0,
+            locals: v::VarId::Vector::new(),
+            body: blocks,
+            liveness: None,
+        };
+
+        let cfg = build_cfg_partial_info(&body);
+        assert!(cfg.loop_entries == HashSet::from([id(1)]));
+        assert!(cfg.backward_edges == HashSet::from([(id(2), id(1))]));
+    }
+}
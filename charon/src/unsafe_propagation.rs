@@ -0,0 +1,86 @@
+//! Compute the functions which (transitively) call an unsafe function.
+//!
+//! A function which is not declared `unsafe` may still call unsafe functions,
+//! inside `unsafe` blocks: the tools which need to identify the boundaries of
+//! the unsafe code can use [crate::gast::GFunDecl::calls_unsafe] to find the
+//! safe wrappers around unsafe code.
+
+use crate::call_graph::{build_call_graph, CallGraph};
+use crate::ullbc_ast::{FunDeclId, FunDecls};
+use std::collections::{HashMap, HashSet};
+
+/// Compute the set of functions which transitively call one of the functions
+/// in `unsafe_funs`.
+///
+/// We explore the call graph backwards, starting from the unsafe functions.
+/// Note that an unsafe function belongs to the set only if it itself calls an
+/// unsafe function.
+pub fn compute_unsafe_callers(
+    graph: &CallGraph,
+    unsafe_funs: &HashSet<FunDeclId::Id>,
+) -> HashSet<FunDeclId::Id> {
+    // Compute the callers of every function
+    let mut callers: HashMap<FunDeclId::Id, Vec<FunDeclId::Id>> = HashMap::new();
+    for (caller, callees) in graph.callees.iter_indexed() {
+        for callee in callees {
+            callers.entry(*callee).or_default().push(*caller);
+        }
+    }
+
+    let mut unsafe_callers = HashSet::new();
+    let mut stack: Vec<FunDeclId::Id> = unsafe_funs.iter().copied().collect();
+    while let Some(id) = stack.pop() {
+        for caller in callers.get(&id).map_or(&[][..], |v| v.as_slice()) {
+            if unsafe_callers.insert(*caller) {
+                stack.push(*caller);
+            }
+        }
+    }
+    unsafe_callers
+}
+
+/// Set the [crate::gast::GFunDecl::calls_unsafe] flag of the functions.
+pub fn transform(funs: &mut FunDecls) {
+    let graph = build_call_graph(funs);
+    let unsafe_funs: HashSet<FunDeclId::Id> = funs
+        .iter()
+        .filter(|d| d.is_unsafe)
+        .map(|d| d.def_id)
+        .collect();
+    let unsafe_callers = compute_unsafe_callers(&graph, &unsafe_funs);
+    for d in funs.iter_mut() {
+        d.calls_unsafe = unsafe_callers.contains(&d.def_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ```text
+    /// 0 -> 1 -> 2 (unsafe) -> 3 (unsafe)
+    /// 4 -> 0
+    /// 5 -> 1
+    /// 6
+    /// ```
+    #[test]
+    fn test_unsafe_callers() {
+        let mut gen = FunDeclId::Generator::new();
+        let ids: Vec<FunDeclId::Id> = (0..7).map(|_| gen.fresh_id()).collect();
+        let calls: Vec<Vec<usize>> =
+            vec![vec![1], vec![2], vec![3], vec![], vec![0], vec![1], vec![]];
+        let mut callees = FunDeclId::Map::new();
+        for (i, tgts) in calls.iter().enumerate() {
+            callees.insert(ids[i], tgts.iter().map(|j| ids[*j]).collect());
+        }
+        let graph = CallGraph::new(callees);
+
+        let unsafe_funs: HashSet<FunDeclId::Id> = [ids[2], ids[3]].iter().copied().collect();
+        let unsafe_callers = compute_unsafe_callers(&graph, &unsafe_funs);
+        let expected: HashSet<FunDeclId::Id> = [ids[0], ids[1], ids[2], ids[4], ids[5]]
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(unsafe_callers, expected);
+    }
+}
@@ -6,7 +6,8 @@ pub use crate::values_utils::DummyFormatter; // Don't understand why we need to
 pub use crate::values_utils::*;
 use core::hash::Hash;
 use macros::{generate_index_type, EnumAsGetters, EnumIsA, VariantIndexArity, VariantName};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 // We need to manipulate a lot of indices for the types, variables, definitions,
 // etc. In order not to confuse them, we define an index type for every one of
@@ -19,11 +20,37 @@ generate_index_type!(VarId);
 /// A primitive value.
 ///
 /// Those are for instance used for the constant operands [crate::expressions::Operand::Const]
-#[derive(Debug, PartialEq, Eq, Clone, VariantName, EnumIsA, EnumAsGetters, Serialize)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    VariantName,
+    EnumIsA,
+    EnumAsGetters,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
 pub enum Literal {
     Scalar(ScalarValue),
     Bool(bool),
     Char(char),
+    /// A raw pointer constant, given by its address. This is mostly used for
+    /// the null pointer (`std::ptr::null()`), whose address is 0.
+    RawPtr(u64),
+    /// A string literal. Its type is `&'static str`: the literal is the
+    /// value of the reference, not of the (unsized) string.
+    Str(String),
+}
+
+/// The size of the pointers on the target, which is also the size of the
+/// `isize` and `usize` integers.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum PointerSize {
+    P32,
+    P64,
 }
 
 /// It might be a good idea to use a structure:
@@ -38,15 +65,16 @@ pub enum Literal {
     Debug, PartialEq, Eq, Copy, Clone, EnumIsA, EnumAsGetters, VariantName, VariantIndexArity, Hash,
 )]
 pub enum ScalarValue {
-    /// Using i64 to be safe
-    Isize(i64),
+    /// We use an i64 to store the value, and record the size of the pointers
+    /// on the target (the value always fits in this size).
+    Isize(PointerSize, i64),
     I8(i8),
     I16(i16),
     I32(i32),
     I64(i64),
     I128(i128),
-    /// Using u64 to be safe
-    Usize(u64),
+    /// Same as for [ScalarValue::Isize]
+    Usize(PointerSize, u64),
     U8(u8),
     U16(u16),
     U32(u32),
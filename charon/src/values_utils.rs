@@ -3,10 +3,15 @@
 #![allow(dead_code)]
 
 use crate::formatter::Formatter;
+use crate::schema;
 use crate::types::*;
 use crate::ullbc_ast::GlobalDeclId;
 use crate::values::*;
-use serde::{Serialize, Serializer};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::ser::SerializeTupleVariant;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 impl VarId::Id {
     pub fn to_pretty_string(&self) -> String {
@@ -82,16 +87,43 @@ impl Formatter<&Region<RegionVarId::Id>> for DummyFormatter {
     }
 }
 
+impl PointerSize {
+    /// Convert a number of bits to a pointer size. Return `None` if we don't
+    /// support this size.
+    pub fn from_bits(bits: u64) -> Option<PointerSize> {
+        match bits {
+            32 => Some(PointerSize::P32),
+            64 => Some(PointerSize::P64),
+            _ => None,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            PointerSize::P32 => 32,
+            PointerSize::P64 => 64,
+        }
+    }
+
+    /// The size in bytes
+    pub fn bytes(&self) -> usize {
+        match self {
+            PointerSize::P32 => 4,
+            PointerSize::P64 => 8,
+        }
+    }
+}
+
 impl ScalarValue {
     pub fn get_integer_ty(&self) -> IntegerTy {
         match self {
-            ScalarValue::Isize(_) => IntegerTy::Isize,
+            ScalarValue::Isize(..) => IntegerTy::Isize,
             ScalarValue::I8(_) => IntegerTy::I8,
             ScalarValue::I16(_) => IntegerTy::I16,
             ScalarValue::I32(_) => IntegerTy::I32,
             ScalarValue::I64(_) => IntegerTy::I64,
             ScalarValue::I128(_) => IntegerTy::I128,
-            ScalarValue::Usize(_) => IntegerTy::Usize,
+            ScalarValue::Usize(..) => IntegerTy::Usize,
             ScalarValue::U8(_) => IntegerTy::U8,
             ScalarValue::U16(_) => IntegerTy::U16,
             ScalarValue::U32(_) => IntegerTy::U32,
@@ -103,7 +135,7 @@ impl ScalarValue {
     pub fn is_int(&self) -> bool {
         matches!(
             self,
-            ScalarValue::Isize(_)
+            ScalarValue::Isize(..)
                 | ScalarValue::I8(_)
                 | ScalarValue::I16(_)
                 | ScalarValue::I32(_)
@@ -115,7 +147,7 @@ impl ScalarValue {
     pub fn is_uint(&self) -> bool {
         matches!(
             self,
-            ScalarValue::Usize(_)
+            ScalarValue::Usize(..)
                 | ScalarValue::U8(_)
                 | ScalarValue::U16(_)
                 | ScalarValue::U32(_)
@@ -129,7 +161,7 @@ impl ScalarValue {
     /// of course).
     pub fn as_uint(&self) -> ScalarResult<u128> {
         match self {
-            ScalarValue::Usize(v) => Ok(*v as u128),
+            ScalarValue::Usize(_, v) => Ok(*v as u128),
             ScalarValue::U8(v) => Ok(*v as u128),
             ScalarValue::U16(v) => Ok(*v as u128),
             ScalarValue::U32(v) => Ok(*v as u128),
@@ -139,9 +171,13 @@ impl ScalarValue {
         }
     }
 
-    pub fn uint_is_in_bounds(ty: IntegerTy, v: u128) -> bool {
+    /// The pointer size is used for the `usize` integers.
+    pub fn uint_is_in_bounds(ptr_size: PointerSize, ty: IntegerTy, v: u128) -> bool {
         match ty {
-            IntegerTy::Usize => v <= (usize::MAX as u128),
+            IntegerTy::Usize => match ptr_size {
+                PointerSize::P32 => v <= (u32::MAX as u128),
+                PointerSize::P64 => v <= (u64::MAX as u128),
+            },
             IntegerTy::U8 => v <= (u8::MAX as u128),
             IntegerTy::U16 => v <= (u16::MAX as u128),
             IntegerTy::U32 => v <= (u32::MAX as u128),
@@ -151,9 +187,9 @@ impl ScalarValue {
         }
     }
 
-    pub fn from_unchecked_uint(ty: IntegerTy, v: u128) -> ScalarValue {
+    pub fn from_unchecked_uint(ptr_size: PointerSize, ty: IntegerTy, v: u128) -> ScalarValue {
         match ty {
-            IntegerTy::Usize => ScalarValue::Usize(v as u64),
+            IntegerTy::Usize => ScalarValue::Usize(ptr_size, v as u64),
             IntegerTy::U8 => ScalarValue::U8(v as u8),
             IntegerTy::U16 => ScalarValue::U16(v as u16),
             IntegerTy::U32 => ScalarValue::U32(v as u32),
@@ -163,12 +199,12 @@ impl ScalarValue {
         }
     }
 
-    pub fn from_uint(ty: IntegerTy, v: u128) -> ScalarResult<ScalarValue> {
-        if !ScalarValue::uint_is_in_bounds(ty, v) {
+    pub fn from_uint(ptr_size: PointerSize, ty: IntegerTy, v: u128) -> ScalarResult<ScalarValue> {
+        if !ScalarValue::uint_is_in_bounds(ptr_size, ty, v) {
             trace!("Not in bounds for {:?}: {}", ty, v);
             Err(ScalarError::OutOfBounds)
         } else {
-            Ok(ScalarValue::from_unchecked_uint(ty, v))
+            Ok(ScalarValue::from_unchecked_uint(ptr_size, ty, v))
         }
     }
 
@@ -177,7 +213,7 @@ impl ScalarValue {
     /// of course).
     pub fn as_int(&self) -> ScalarResult<i128> {
         match self {
-            ScalarValue::Isize(v) => Ok(*v as i128),
+            ScalarValue::Isize(_, v) => Ok(*v as i128),
             ScalarValue::I8(v) => Ok(*v as i128),
             ScalarValue::I16(v) => Ok(*v as i128),
             ScalarValue::I32(v) => Ok(*v as i128),
@@ -187,9 +223,13 @@ impl ScalarValue {
         }
     }
 
-    pub fn int_is_in_bounds(ty: IntegerTy, v: i128) -> bool {
+    /// The pointer size is used for the `isize` integers.
+    pub fn int_is_in_bounds(ptr_size: PointerSize, ty: IntegerTy, v: i128) -> bool {
         match ty {
-            IntegerTy::Isize => v >= (isize::MIN as i128) && v <= (isize::MAX as i128),
+            IntegerTy::Isize => match ptr_size {
+                PointerSize::P32 => v >= (i32::MIN as i128) && v <= (i32::MAX as i128),
+                PointerSize::P64 => v >= (i64::MIN as i128) && v <= (i64::MAX as i128),
+            },
             IntegerTy::I8 => v >= (i8::MIN as i128) && v <= (i8::MAX as i128),
             IntegerTy::I16 => v >= (i16::MIN as i128) && v <= (i16::MAX as i128),
             IntegerTy::I32 => v >= (i32::MIN as i128) && v <= (i32::MAX as i128),
@@ -199,9 +239,9 @@ impl ScalarValue {
         }
     }
 
-    pub fn from_unchecked_int(ty: IntegerTy, v: i128) -> ScalarValue {
+    pub fn from_unchecked_int(ptr_size: PointerSize, ty: IntegerTy, v: i128) -> ScalarValue {
         match ty {
-            IntegerTy::Isize => ScalarValue::Isize(v as i64),
+            IntegerTy::Isize => ScalarValue::Isize(ptr_size, v as i64),
             IntegerTy::I8 => ScalarValue::I8(v as i8),
             IntegerTy::I16 => ScalarValue::I16(v as i16),
             IntegerTy::I32 => ScalarValue::I32(v as i32),
@@ -211,13 +251,20 @@ impl ScalarValue {
         }
     }
 
-    pub fn from_le_bytes(ty: IntegerTy, b: [u8; 16]) -> ScalarValue {
+    /// The pointer size is used for the `isize` and `usize` integers.
+    pub fn from_le_bytes(ptr_size: PointerSize, ty: IntegerTy, b: [u8; 16]) -> ScalarValue {
         use std::convert::TryInto;
         match ty {
-            IntegerTy::Isize => {
-                let b: [u8; 8] = b[0..8].try_into().unwrap();
-                ScalarValue::Isize(i64::from_le_bytes(b))
-            }
+            IntegerTy::Isize => match ptr_size {
+                PointerSize::P32 => {
+                    let b: [u8; 4] = b[0..4].try_into().unwrap();
+                    ScalarValue::Isize(ptr_size, i32::from_le_bytes(b) as i64)
+                }
+                PointerSize::P64 => {
+                    let b: [u8; 8] = b[0..8].try_into().unwrap();
+                    ScalarValue::Isize(ptr_size, i64::from_le_bytes(b))
+                }
+            },
             IntegerTy::I8 => {
                 let b: [u8; 1] = b[0..1].try_into().unwrap();
                 ScalarValue::I8(i8::from_le_bytes(b))
@@ -238,10 +285,16 @@ impl ScalarValue {
                 let b: [u8; 16] = b[0..16].try_into().unwrap();
                 ScalarValue::I128(i128::from_le_bytes(b))
             }
-            IntegerTy::Usize => {
-                let b: [u8; 8] = b[0..8].try_into().unwrap();
-                ScalarValue::Usize(u64::from_le_bytes(b))
-            }
+            IntegerTy::Usize => match ptr_size {
+                PointerSize::P32 => {
+                    let b: [u8; 4] = b[0..4].try_into().unwrap();
+                    ScalarValue::Usize(ptr_size, u32::from_le_bytes(b) as u64)
+                }
+                PointerSize::P64 => {
+                    let b: [u8; 8] = b[0..8].try_into().unwrap();
+                    ScalarValue::Usize(ptr_size, u64::from_le_bytes(b))
+                }
+            },
             IntegerTy::U8 => {
                 let b: [u8; 1] = b[0..1].try_into().unwrap();
                 ScalarValue::U8(u8::from_le_bytes(b))
@@ -268,11 +321,11 @@ impl ScalarValue {
     /// **Warning**: most constants are stored as u128 by rustc. When converting
     /// to i128, it is not correct to do `v as i128`, we must reinterpret the
     /// bits (see [ScalarValue::from_le_bytes]).
-    pub fn from_int(ty: IntegerTy, v: i128) -> ScalarResult<ScalarValue> {
-        if !ScalarValue::int_is_in_bounds(ty, v) {
+    pub fn from_int(ptr_size: PointerSize, ty: IntegerTy, v: i128) -> ScalarResult<ScalarValue> {
+        if !ScalarValue::int_is_in_bounds(ptr_size, ty, v) {
             Err(ScalarError::OutOfBounds)
         } else {
-            Ok(ScalarValue::from_unchecked_int(ty, v))
+            Ok(ScalarValue::from_unchecked_int(ptr_size, ty, v))
         }
     }
 }
@@ -280,13 +333,13 @@ impl ScalarValue {
 impl std::fmt::Display for ScalarValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         match self {
-            ScalarValue::Isize(v) => write!(f, "{v} : isize"),
+            ScalarValue::Isize(_, v) => write!(f, "{v} : isize"),
             ScalarValue::I8(v) => write!(f, "{v} : i8"),
             ScalarValue::I16(v) => write!(f, "{v} : i16"),
             ScalarValue::I32(v) => write!(f, "{v} : i32"),
             ScalarValue::I64(v) => write!(f, "{v} : i64"),
             ScalarValue::I128(v) => write!(f, "{v} : i128"),
-            ScalarValue::Usize(v) => write!(f, "{v} : usize"),
+            ScalarValue::Usize(_, v) => write!(f, "{v} : usize"),
             ScalarValue::U8(v) => write!(f, "{v} : u8"),
             ScalarValue::U16(v) => write!(f, "{v} : u16"),
             ScalarValue::U32(v) => write!(f, "{v} : u32"),
@@ -302,6 +355,8 @@ impl std::fmt::Display for Literal {
             Literal::Scalar(v) => write!(f, "{v}"),
             Literal::Bool(v) => write!(f, "{v}"),
             Literal::Char(v) => write!(f, "{v}"),
+            Literal::RawPtr(addr) => write!(f, "{addr:#x} as ptr"),
+            Literal::Str(s) => write!(f, "{s:?}"),
         }
     }
 }
@@ -313,21 +368,110 @@ impl Serialize for ScalarValue {
     {
         let enum_name = "ScalarValue";
         let variant_name = self.variant_name();
-        let (variant_index, _variant_arity) = self.variant_index_arity();
+        let (variant_index, variant_arity) = self.variant_index_arity();
         let v = match self {
-            ScalarValue::Isize(i) => i.to_string(),
+            ScalarValue::Isize(_, i) => i.to_string(),
             ScalarValue::I8(i) => i.to_string(),
             ScalarValue::I16(i) => i.to_string(),
             ScalarValue::I32(i) => i.to_string(),
             ScalarValue::I64(i) => i.to_string(),
             ScalarValue::I128(i) => i.to_string(),
-            ScalarValue::Usize(i) => i.to_string(),
+            ScalarValue::Usize(_, i) => i.to_string(),
             ScalarValue::U8(i) => i.to_string(),
             ScalarValue::U16(i) => i.to_string(),
             ScalarValue::U32(i) => i.to_string(),
             ScalarValue::U64(i) => i.to_string(),
             ScalarValue::U128(i) => i.to_string(),
         };
-        serializer.serialize_newtype_variant(enum_name, variant_index, variant_name, &v)
+        match self {
+            // For `isize` and `usize`, we also serialize the pointer size
+            ScalarValue::Isize(ptr_size, _) | ScalarValue::Usize(ptr_size, _) => {
+                let mut vs = serializer.serialize_tuple_variant(
+                    enum_name,
+                    variant_index,
+                    variant_name,
+                    variant_arity,
+                )?;
+                vs.serialize_field(ptr_size)?;
+                vs.serialize_field(&v)?;
+                vs.end()
+            }
+            _ => serializer.serialize_newtype_variant(enum_name, variant_index, variant_name, &v),
+        }
+    }
+}
+
+/// The serialized form of a [ScalarValue]: the values are stored as strings
+/// (see the serializer).
+#[derive(Deserialize)]
+#[serde(rename = "ScalarValue")]
+enum SerializedScalarValue {
+    Isize(PointerSize, String),
+    I8(String),
+    I16(String),
+    I32(String),
+    I64(String),
+    I128(String),
+    Usize(PointerSize, String),
+    U8(String),
+    U16(String),
+    U32(String),
+    U64(String),
+    U128(String),
+}
+
+impl<'de> Deserialize<'de> for ScalarValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        fn parse<T: std::str::FromStr, E: Error>(v: &str) -> std::result::Result<T, E> {
+            v.parse()
+                .map_err(|_| E::custom(format!("invalid scalar value: {v}")))
+        }
+        let v = match SerializedScalarValue::deserialize(deserializer)? {
+            SerializedScalarValue::Isize(ptr_size, v) => ScalarValue::Isize(ptr_size, parse(&v)?),
+            SerializedScalarValue::I8(v) => ScalarValue::I8(parse(&v)?),
+            SerializedScalarValue::I16(v) => ScalarValue::I16(parse(&v)?),
+            SerializedScalarValue::I32(v) => ScalarValue::I32(parse(&v)?),
+            SerializedScalarValue::I64(v) => ScalarValue::I64(parse(&v)?),
+            SerializedScalarValue::I128(v) => ScalarValue::I128(parse(&v)?),
+            SerializedScalarValue::Usize(ptr_size, v) => ScalarValue::Usize(ptr_size, parse(&v)?),
+            SerializedScalarValue::U8(v) => ScalarValue::U8(parse(&v)?),
+            SerializedScalarValue::U16(v) => ScalarValue::U16(parse(&v)?),
+            SerializedScalarValue::U32(v) => ScalarValue::U32(parse(&v)?),
+            SerializedScalarValue::U64(v) => ScalarValue::U64(parse(&v)?),
+            SerializedScalarValue::U128(v) => ScalarValue::U128(parse(&v)?),
+        };
+        Ok(v)
+    }
+}
+
+impl JsonSchema for ScalarValue {
+    fn schema_name() -> String {
+        "ScalarValue".to_string()
+    }
+
+    // The values are serialized as strings (see the serializer)
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut variants: Vec<Schema> = ["Isize", "Usize"]
+            .iter()
+            .map(|name| {
+                schema::tuple_variant(
+                    name,
+                    vec![
+                        gen.subschema_for::<PointerSize>(),
+                        gen.subschema_for::<String>(),
+                    ],
+                )
+            })
+            .collect();
+        for name in [
+            "I8", "I16", "I32", "I64", "I128", "U8", "U16", "U32", "U64", "U128",
+        ] {
+            variants.push(schema::newtype_variant(name, gen.subschema_for::<String>()));
+        }
+        schema::one_of(variants)
     }
 }
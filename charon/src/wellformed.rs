@@ -0,0 +1,202 @@
+//! Check that the block structure of the ULLBC bodies is well-formed:
+//! - the entry block is the block 0 (in particular, the body is not empty)
+//! - the terminators only jump to blocks which exist in the body
+//! - all the blocks are reachable from the entry block
+//!
+//! Note that we don't need to check that the blocks end with a terminator:
+//! this is enforced by [BlockData].
+//!
+//! We perform the check in debug builds, after translating every body (see
+//! [crate::translate_functions_to_ullbc]).
+
+use crate::ullbc_ast::*;
+use crate::ullbc_to_llbc::get_block_targets;
+use std::collections::HashSet;
+
+/// A violation of the invariants listed in the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WellformednessError {
+    /// The body has no blocks: the entry block 0 is missing
+    MissingEntryBlock,
+    /// The terminator of `block` jumps to `target`, which doesn't exist
+    InvalidTarget {
+        block: BlockId::Id,
+        target: BlockId::Id,
+    },
+    /// The block can't be reached from the entry block
+    UnreachableBlock(BlockId::Id),
+}
+
+impl std::fmt::Display for WellformednessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WellformednessError::MissingEntryBlock => write!(f, "the entry block is missing"),
+            WellformednessError::InvalidTarget { block, target } => {
+                write!(f, "bb{block} jumps to bb{target}, which doesn't exist")
+            }
+            WellformednessError::UnreachableBlock(block) => write!(f, "bb{block} is unreachable"),
+        }
+    }
+}
+
+/// The successors of a block. Contrary to [get_block_targets], we take the
+/// cleanup blocks of the drops into account.
+fn block_successors(body: &ExprBody, block_id: BlockId::Id) -> Vec<BlockId::Id> {
+    let mut targets = get_block_targets(body, block_id);
+    if let RawTerminator::Drop {
+        unwind: Some(unwind),
+        ..
+    } = &body.body.get(block_id).unwrap().terminator.content
+    {
+        targets.push(*unwind);
+    }
+    targets
+}
+
+/// Check the block structure of a body.
+pub fn check_body_wellformed(body: &ExprBody) -> Result<(), Vec<WellformednessError>> {
+    if body.body.is_empty() {
+        return Err(vec![WellformednessError::MissingEntryBlock]);
+    }
+
+    let mut errors = Vec::new();
+    for (block, _) in body.body.iter_indexed_values() {
+        for target in block_successors(body, block) {
+            if body.body.get(target).is_none() {
+                errors.push(WellformednessError::InvalidTarget { block, target });
+            }
+        }
+    }
+
+    // Explore the body from the entry block, ignoring the invalid targets
+    let mut reachable = HashSet::new();
+    let mut stack = vec![BlockId::ZERO];
+    while let Some(block) = stack.pop() {
+        if body.body.get(block).is_some() && reachable.insert(block) {
+            stack.extend(block_successors(body, block));
+        }
+    }
+    for (block, _) in body.body.iter_indexed_values() {
+        if !reachable.contains(&block) {
+            errors.push(WellformednessError::UnreachableBlock(block));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::meta::*;
+    use crate::types::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn block(content: RawTerminator) -> BlockData {
+        BlockData {
+            statements: vec![],
+            terminator: Terminator::new(dummy_meta(), content),
+            is_loop_header: false,
+        }
+    }
+
+    fn mk_body(blocks: Vec<BlockData>) -> ExprBody {
+        let mut locals = VarId::Vector::new();
+        locals.fresh_var(None, Ty::Literal(LiteralTy::Integer(IntegerTy::U32)));
+        locals.fresh_var(Some("b".to_string()), Ty::Literal(LiteralTy::Bool));
+        ExprBody {
+            meta: dummy_meta(),
+            arg_count: 1,
+            locals,
+            body: blocks.into_iter().collect(),
+            liveness: None,
+        }
+    }
+
+    fn switch(then_block: usize, else_block: usize) -> RawTerminator {
+        RawTerminator::Switch {
+            discr: Operand::Copy(Place::new(VarId::Id::new(1))),
+            targets: SwitchTargets::If(BlockId::Id::new(then_block), BlockId::Id::new(else_block)),
+        }
+    }
+
+    fn goto(target: usize) -> RawTerminator {
+        RawTerminator::Goto {
+            target: BlockId::Id::new(target),
+        }
+    }
+
+    /// The shape of `list_length`:
+    /// ```text
+    /// bb0: goto bb1
+    /// bb1: switch [cons -> bb2, nil -> bb3] // loop header
+    /// bb2: goto bb1
+    /// bb3: return
+    /// ```
+    #[test]
+    fn test_list_length() {
+        let body = mk_body(vec![
+            block(goto(1)),
+            block(switch(2, 3)),
+            block(goto(1)),
+            block(RawTerminator::Return),
+        ]);
+        assert!(check_body_wellformed(&body) == Ok(()));
+    }
+
+    #[test]
+    fn test_cleanup_blocks_are_reachable() {
+        let drop = RawTerminator::Drop {
+            place: Place::new(VarId::Id::new(1)),
+            target: BlockId::Id::new(1),
+            unwind: Some(BlockId::Id::new(2)),
+        };
+        let body = mk_body(vec![
+            block(drop),
+            block(RawTerminator::Return),
+            block(RawTerminator::Panic),
+        ]);
+        assert!(check_body_wellformed(&body) == Ok(()));
+    }
+
+    #[test]
+    fn test_ill_formed() {
+        assert!(
+            check_body_wellformed(&mk_body(vec![]))
+                == Err(vec![WellformednessError::MissingEntryBlock])
+        );
+
+        let body = mk_body(vec![
+            block(switch(1, 4)),
+            block(RawTerminator::Return),
+            block(RawTerminator::Return),
+        ]);
+        let errors = vec![
+            WellformednessError::InvalidTarget {
+                block: BlockId::ZERO,
+                target: BlockId::Id::new(4),
+            },
+            WellformednessError::UnreachableBlock(BlockId::Id::new(2)),
+        ];
+        assert!(check_body_wellformed(&body) == Err(errors));
+    }
+}
@@ -0,0 +1,256 @@
+//! Read back the files generated for the crates of a workspace (see
+//! [crate::cli_options::CliOpts::workspace]), and resolve the references
+//! between the crates.
+//!
+//! Every crate is translated separately: when a crate `b` calls a function
+//! `a::f` defined in another crate `a` of the workspace, the file of `b`
+//! contains an opaque declaration for `a::f`, with an id local to `b`. We
+//! link this declaration to the declaration of `a::f` in the file of `a` (we
+//! identify the declarations by their names).
+#![allow(dead_code)]
+
+use crate::deserialize::{deserialize_crate, DeserializeError, TranslatedCrate};
+use crate::names::Name;
+use crate::ullbc_ast::FunDeclId;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub type CrateName = String;
+
+/// A function declaration, identified by the index of its crate in
+/// [WorkspaceTranslation::crates] and its id in this crate.
+pub type WorkspaceFunId = (usize, FunDeclId::Id);
+
+pub struct WorkspaceTranslation {
+    pub crates: Vec<(CrateName, TranslatedCrate)>,
+    /// Maps the opaque declarations of the functions defined in other crates
+    /// of the workspace to their definitions.
+    pub fun_refs: HashMap<WorkspaceFunId, WorkspaceFunId>,
+}
+
+impl WorkspaceTranslation {
+    /// Group translated crates, and resolve the references between them.
+    pub fn new(crates: Vec<(CrateName, TranslatedCrate)>) -> Self {
+        let crate_ids: HashMap<&str, usize> = crates
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect();
+        let funs_by_name: Vec<HashMap<&Name, FunDeclId::Id>> = crates
+            .iter()
+            .map(|(_, c)| c.fun_defs.iter().map(|d| (&d.name, d.def_id)).collect())
+            .collect();
+
+        let mut fun_refs = HashMap::new();
+        for (i, (_, krate)) in crates.iter().enumerate() {
+            for decl in krate.fun_defs.iter().filter(|d| d.body.is_none()) {
+                // The first element of a name is the name of the crate
+                let def_crate = decl
+                    .name
+                    .segments()
+                    .first()
+                    .and_then(|name| crate_ids.get(name).copied());
+                match def_crate {
+                    Some(j) if j != i => {
+                        if let Some(def_id) = funs_by_name[j].get(&decl.name) {
+                            fun_refs.insert((i, decl.def_id), (j, *def_id));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        WorkspaceTranslation { crates, fun_refs }
+    }
+
+    /// Read the `.llbc` files of the given crates from a directory.
+    pub fn load(dir: &Path, crate_names: &[CrateName]) -> Result<Self, DeserializeError> {
+        let crates = crate_names
+            .iter()
+            .map(|name| {
+                let krate = deserialize_crate(&dir.join(format!("{name}.llbc")))?;
+                Ok((name.clone(), krate))
+            })
+            .collect::<Result<_, DeserializeError>>()?;
+        Ok(WorkspaceTranslation::new(crates))
+    }
+
+    /// The index of a crate in [Self::crates].
+    pub fn crate_index(&self, name: &str) -> Option<usize> {
+        self.crates.iter().position(|(n, _)| n == name)
+    }
+
+    /// Follow the references to the functions defined in other crates: we
+    /// return the declaration which defines the function.
+    pub fn resolve_fun(&self, krate: usize, id: FunDeclId::Id) -> WorkspaceFunId {
+        self.fun_refs
+            .get(&(krate, id))
+            .copied()
+            .unwrap_or((krate, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expressions::*;
+    use crate::gast::*;
+    use crate::llbc_ast::{ExprBody, FunDecl, RawStatement, Statement};
+    use crate::meta::*;
+    use crate::names::PathElem;
+    use crate::regions_hierarchy::RegionGroups;
+    use crate::types::*;
+    use crate::values::*;
+
+    fn dummy_meta() -> Meta {
+        let span = Span {
+            file_id: FileId::Id::LocalId(LocalFileId::ZERO),
+            beg: Loc { line: 1, col: 0 },
+            end: Loc { line: 1, col: 0 },
+        };
+        Meta {
+            span,
+            generated_from_span: None,
+            is_from_macro: false,
+            macro_name: None,
+        }
+    }
+
+    fn name(elems: &[&str]) -> Name {
+        Name {
+            name: elems
+                .iter()
+                .map(|s| PathElem::Ident(s.to_string()))
+                .collect(),
+        }
+    }
+
+    /// A function `fn() -> ()`, whose body (if there is one) is `statement;
+    /// return`.
+    fn mk_fun(id: usize, path: &[&str], statement: Option<RawStatement>) -> FunDecl {
+        let mut locals = VarId::Vector::new();
+        locals.fresh_var(None, Ty::mk_unit());
+        let body = statement.map(|st| {
+            let st = RawStatement::Sequence(
+                Box::new(Statement::new(dummy_meta(), st)),
+                Box::new(Statement::new(dummy_meta(), RawStatement::Return)),
+            );
+            ExprBody {
+                meta: dummy_meta(),
+                arg_count: 0,
+                locals,
+                body: Statement::new(dummy_meta(), st),
+                liveness: None,
+            }
+        });
+        FunDecl {
+            def_id: FunDeclId::Id::new(id),
+            meta: dummy_meta(),
+            name: name(path),
+            signature: FunSig {
+                region_params: RegionVarId::Vector::new(),
+                num_early_bound_regions: 0,
+                type_params: TypeVarId::Vector::new(),
+                const_generic_params: ConstGenericVarId::Vector::new(),
+                predicates: Vec::new(),
+                inputs: Vec::new(),
+                output: Ty::mk_unit(),
+                regions_hierarchy: RegionGroups::new(),
+            },
+            body,
+            builtin: None,
+            calling_convention: CallingConvention::Rust,
+            is_const_fn: false,
+            is_unsafe: false,
+            calls_unsafe: false,
+            self_type: None,
+            trait_id: None,
+            trait_impl_info: None,
+            is_trait_default: false,
+            overrides_default: None,
+            captures: Vec::new(),
+            implemented_drop: false,
+            attributes: Vec::new(),
+            is_root: true,
+            stable_id: None,
+        }
+    }
+
+    fn call(id: usize) -> RawStatement {
+        RawStatement::Call(Call {
+            func: FunId::Regular(FunDeclId::Id::new(id)),
+            region_args: Vec::new(),
+            type_args: Vec::new(),
+            const_generic_args: Vec::new(),
+            args: Vec::new(),
+            dest: Place::new(VarId::ZERO),
+        })
+    }
+
+    fn mk_crate(name: &str, funs: Vec<FunDecl>) -> (CrateName, TranslatedCrate) {
+        let krate = TranslatedCrate {
+            name: name.to_string(),
+            dependencies: vec![],
+            file_to_id: HashMap::new(),
+            id_to_file: HashMap::new(),
+            ordered_decls: vec![],
+            type_defs: TypeDecls::new(),
+            fun_defs: funs.into_iter().map(|d| (d.def_id, d)).collect(),
+            global_defs: GlobalDeclId::Map::new(),
+            trait_defs: TraitDecls::new(),
+            module_defs: ModuleDecls::new(),
+            def_ids: None,
+        };
+        (name.to_string(), krate)
+    }
+
+    /// ```text
+    /// // crate a
+    /// fn helper() {}   // @Fun0
+    /// pub fn f() {}    // @Fun1
+    ///
+    /// // crate b
+    /// a::f             // @Fun0 (opaque)
+    /// fn g() { a::f() } // @Fun1
+    /// ```
+    #[test]
+    fn test_cross_crate_call() {
+        let a = mk_crate(
+            "a",
+            vec![
+                mk_fun(0, &["a", "helper"], Some(RawStatement::Nop)),
+                mk_fun(1, &["a", "f"], Some(RawStatement::Nop)),
+            ],
+        );
+        let b = mk_crate(
+            "b",
+            vec![
+                mk_fun(0, &["a", "f"], None),
+                mk_fun(1, &["b", "g"], Some(call(0))),
+            ],
+        );
+        let ws = WorkspaceTranslation::new(vec![a, b]);
+        let (a_id, b_id) = (ws.crate_index("a").unwrap(), ws.crate_index("b").unwrap());
+        let g_id = FunDeclId::Id::new(1);
+
+        // Retrieve the function called by `g`
+        let (_, b) = &ws.crates[b_id];
+        let g = b.fun_defs.get(g_id).unwrap();
+        let callee = match &g.body.as_ref().unwrap().body.content {
+            RawStatement::Sequence(st, _) => match &st.content {
+                RawStatement::Call(Call {
+                    func: FunId::Regular(id),
+                    ..
+                }) => *id,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert!(ws.resolve_fun(b_id, callee) == (a_id, FunDeclId::Id::new(1)));
+
+        // The functions defined in the crate are not modified
+        assert!(ws.resolve_fun(b_id, g_id) == (b_id, g_id));
+        assert!(ws.fun_refs.len() == 1);
+    }
+}
@@ -0,0 +1,1563 @@
+//! Translate the files of the `tests` crate (next to this crate) and check the
+//! generated files, by reading them back with [charon_lib::deserialize].
+//!
+//! Every test runs Charon on one file of `tests/src`, with the options it needs,
+//! and writes the output in its own directory (so that the tests can run in
+//! parallel). We only inspect the text printed by Charon when we check the
+//! pretty-printers.
+//!
+//! Those tests need the `tests` crate: they can't run if we only have the
+//! sources of Charon (this is the case of the Nix derivation, see `flake.nix`).
+
+#![cfg_attr(feature = "rustc", feature(rustc_private))]
+
+// Needed to link with the rustc libraries used by [charon_lib]
+#[cfg(feature = "rustc")]
+extern crate rustc_driver;
+
+use assert_cmd::Command;
+use charon_lib::deserialize::{
+    deserialize_crate, deserialize_ullbc_crate, TranslatedCrate, UllbcTranslatedCrate,
+};
+use charon_lib::expressions::*;
+use charon_lib::gast::*;
+use charon_lib::llbc_ast;
+use charon_lib::llbc_ast::{RawStatement, SharedAstVisitor};
+use charon_lib::meta::{MacroName, Meta};
+use charon_lib::names::Name;
+use charon_lib::stats::FunStats;
+use charon_lib::types::*;
+use charon_lib::ullbc_ast::RawTerminator;
+use charon_lib::values::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+/// The output of a run of Charon
+struct Output {
+    /// The directory containing the generated files
+    dir: PathBuf,
+    /// What Charon printed (the bodies, with `--pretty`)
+    stdout: String,
+}
+
+impl Output {
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    fn llbc(&self, krate: &str) -> TranslatedCrate {
+        deserialize_crate(&self.path(&format!("{krate}.llbc"))).unwrap()
+    }
+
+    fn ullbc(&self, krate: &str) -> UllbcTranslatedCrate {
+        deserialize_ullbc_crate(&self.path(&format!("{krate}.ullbc"))).unwrap()
+    }
+
+    /// The lines printed by `--pretty` for a function, from its signature to
+    /// the first empty line.
+    fn pretty_fun(&self, name: &str) -> Vec<&str> {
+        let header = Regex::new(&format!("^fn {}[<(]", regex::escape(name))).unwrap();
+        let lines: Vec<&str> = self
+            .stdout
+            .lines()
+            .skip_while(|l| !header.is_match(l))
+            .take_while(|l| !l.is_empty())
+            .collect();
+        assert!(!lines.is_empty(), "function not printed: {}", name);
+        lines
+    }
+}
+
+/// Run Charon on `tests/src/{krate}.rs`, with some options and environment
+/// variables. `dest` is the name of the output directory, which must be
+/// unique among the tests.
+fn charon_with_env(krate: &str, dest: &str, options: &[&str], env: &[(&str, &str)]) -> Output {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(dest);
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut cmd = Command::cargo_bin("charon").unwrap();
+    cmd.current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests"))
+        .args(["--crate", krate, "--input", &format!("src/{krate}.rs")])
+        .arg("--dest")
+        .arg(&dir)
+        .args(options)
+        .envs(env.iter().copied());
+    let output = cmd.assert().success().get_output().clone();
+    Output {
+        dir,
+        stdout: String::from_utf8(output.stdout).unwrap(),
+    }
+}
+
+fn charon(krate: &str, dest: &str, options: &[&str]) -> Output {
+    charon_with_env(krate, dest, options, &[])
+}
+
+/// Translate a crate to LLBC and read the generated file
+fn llbc(krate: &str, dest: &str, options: &[&str]) -> TranslatedCrate {
+    charon(krate, dest, options).llbc(krate)
+}
+
+/// Translate a crate to ULLBC and read the generated file
+fn ullbc(krate: &str, dest: &str, options: &[&str]) -> UllbcTranslatedCrate {
+    let mut options = options.to_vec();
+    options.push("--ullbc");
+    charon(krate, dest, &options).ullbc(krate)
+}
+
+/// Lookup a function by its path (see [charon_lib::names::Name::matches_path])
+fn fun<'a, T: Debug + Clone + Serialize>(
+    krate: &'a charon_lib::deserialize::GTranslatedCrate<T>,
+    path: &str,
+) -> &'a GFunDecl<T> {
+    let mut funs = krate.fun_defs.iter().filter(|d| d.name.matches_path(path));
+    let d = funs
+        .next()
+        .unwrap_or_else(|| panic!("function not found: {}", path));
+    assert!(funs.next().is_none(), "ambiguous function: {}", path);
+    d
+}
+
+fn type_decl<'a, T: Debug + Clone + Serialize>(
+    krate: &'a charon_lib::deserialize::GTranslatedCrate<T>,
+    path: &str,
+) -> &'a TypeDecl {
+    krate
+        .type_defs
+        .iter()
+        .find(|d| d.name.matches_path(path))
+        .unwrap_or_else(|| panic!("type not found: {}", path))
+}
+
+fn trait_decl<'a, T: Debug + Clone + Serialize>(
+    krate: &'a charon_lib::deserialize::GTranslatedCrate<T>,
+    path: &str,
+) -> &'a TraitDecl {
+    krate
+        .trait_defs
+        .iter()
+        .find(|d| d.name.matches_path(path))
+        .unwrap_or_else(|| panic!("trait not found: {}", path))
+}
+
+/// The fields of a structure
+fn fields(d: &TypeDecl) -> Vec<&Field> {
+    match &d.kind {
+        TypeDeclKind::Struct(fields) => fields.iter().collect(),
+        _ => panic!("not a structure: {}", d.name),
+    }
+}
+
+fn field<'a>(d: &'a TypeDecl, name: &str) -> &'a Field {
+    fields(d)
+        .into_iter()
+        .find(|f| f.name.as_deref() == Some(name))
+        .unwrap_or_else(|| panic!("field not found: {}", name))
+}
+
+fn adt_ty<R: Clone + Eq>(id: TypeId, tys: Vec<Ty<R>>) -> Ty<R> {
+    Ty::Adt(id, Vec::new(), tys, Vec::new())
+}
+
+fn u32_ty<R: Clone + Eq>() -> Ty<R> {
+    Ty::Literal(LiteralTy::Integer(IntegerTy::U32))
+}
+
+fn u32_const(x: u32) -> OperandConstantValue {
+    OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U32(x)))
+}
+
+fn var_place(id: usize) -> Place {
+    Place {
+        var_id: VarId::Id::new(id),
+        projection: Vec::new(),
+    }
+}
+
+/// Everything we find in LLBC bodies
+#[derive(Default)]
+struct Contents {
+    /// The statements, including the nested ones
+    statements: Vec<RawStatement>,
+    metas: Vec<Meta>,
+    rvalues: Vec<Rvalue>,
+    places: Vec<Place>,
+    /// The constant values, including the values nested in other values
+    constants: Vec<OperandConstantValue>,
+}
+
+impl Contents {
+    fn of_body(body: &llbc_ast::ExprBody) -> Self {
+        let mut contents = Contents::default();
+        contents.visit_statement(&body.body);
+        contents
+    }
+
+    /// The contents of the body of a function
+    fn of_fun(krate: &TranslatedCrate, path: &str) -> Self {
+        Contents::of_body(fun(krate, path).body.as_ref().unwrap())
+    }
+
+    /// The contents of all the bodies of the crate (functions and globals)
+    fn of_crate(krate: &TranslatedCrate) -> Self {
+        let mut contents = Contents::default();
+        let fun_bodies = krate.fun_defs.iter().filter_map(|d| d.body.as_ref());
+        let global_bodies = krate.global_defs.iter().filter_map(|d| d.body.as_ref());
+        for body in fun_bodies.chain(global_bodies) {
+            contents.visit_statement(&body.body);
+        }
+        contents
+    }
+
+    fn aggregates(&self) -> Vec<(&AggregateKind, &Vec<Operand>)> {
+        self.rvalues
+            .iter()
+            .filter_map(|rv| match rv {
+                Rvalue::Aggregate(kind, ops) => Some((kind, ops)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn binary_ops(&self, op: BinOp) -> usize {
+        self.rvalues
+            .iter()
+            .filter(|rv| matches!(rv, Rvalue::BinaryOp(o, _, _) if *o == op))
+            .count()
+    }
+
+    fn count(&self, f: impl Fn(&RawStatement) -> bool) -> usize {
+        self.statements.iter().filter(|st| f(st)).count()
+    }
+
+    fn projection_elems(&self) -> Vec<&ProjectionElem> {
+        self.places
+            .iter()
+            .flat_map(|p| p.projection.iter())
+            .collect()
+    }
+}
+
+impl SharedTypeVisitor for Contents {}
+
+impl SharedExprVisitor for Contents {
+    fn visit_place(&mut self, p: &Place) {
+        self.places.push(p.clone())
+    }
+
+    fn visit_rvalue(&mut self, rv: &Rvalue) {
+        self.rvalues.push(rv.clone());
+        self.default_visit_rvalue(rv)
+    }
+
+    fn visit_operand_constant_value(&mut self, cv: &OperandConstantValue) {
+        self.constants.push(cv.clone());
+        match cv {
+            OperandConstantValue::Adt(_, cvs) | OperandConstantValue::Array(cvs) => {
+                for cv in cvs {
+                    self.visit_operand_constant_value(cv)
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl SharedAstVisitor for Contents {
+    fn spawn(&mut self, visitor: &mut dyn FnMut(&mut Self)) {
+        visitor(self)
+    }
+
+    fn merge(&mut self) {}
+
+    fn visit_statement(&mut self, st: &llbc_ast::Statement) {
+        self.statements.push(st.content.clone());
+        self.metas.push(st.meta);
+        self.visit_raw_statement(&st.content)
+    }
+}
+
+/// The functions declared in `extern` blocks are exported with their calling
+/// convention
+#[test]
+fn extern_c() {
+    let krate = llbc("extern_c", "extern_c", &[]);
+    assert!(krate
+        .fun_defs
+        .iter()
+        .any(|d| d.calling_convention == CallingConvention::C));
+}
+
+/// The values of the associated constants are linked to their trait
+#[test]
+fn assoc_consts() {
+    let krate = llbc("assoc_consts", "assoc_consts", &[]);
+    let has_id = trait_decl(&krate, "HasId").def_id;
+    let ids: Vec<_> = krate
+        .global_defs
+        .iter()
+        .filter(|d| d.name.matches_path("ID"))
+        .collect();
+    assert!(ids.len() == 2);
+    assert!(ids.iter().all(|d| d.trait_id == Some(has_id)));
+    let name_len = krate
+        .global_defs
+        .iter()
+        .find(|d| d.name.matches_path("NAME_LEN"))
+        .unwrap();
+    assert!(name_len.trait_id.is_none());
+}
+
+/// The `#[repr(...)]` attributes are exported
+#[test]
+fn repr() {
+    let krate = llbc("repr", "repr", &[]);
+    let repr = |name| type_decl(&krate, name).repr;
+    let c = ReprConfig {
+        kind: ReprKind::C,
+        packed: None,
+        align: None,
+    };
+    assert!(repr("Foo") == c);
+    assert!(repr("Packed").packed.is_some());
+    assert!(repr("Aligned").align.is_some());
+    assert!(repr("Tag").kind == ReprKind::Int(IntegerTy::U8));
+    assert!(repr("Wrapper").kind == ReprKind::Transparent);
+}
+
+/// The associated types which can't be resolved are exported as projections
+#[test]
+fn assoc_types() {
+    let krate = llbc("assoc_types", "assoc_types", &[]);
+    assert!(krate.fun_defs.iter().any(|d| {
+        let sig = &d.signature;
+        sig.inputs.iter().chain([&sig.output]).any(|ty| match ty {
+            Ty::TraitAssoc(..) => true,
+            Ty::Adt(_, _, tys, _) => tys.iter().any(|ty| ty.is_trait_assoc()),
+            _ => false,
+        })
+    }));
+}
+
+/// The `const fn` have a body, including when we extract the promoted or the
+/// optimized MIR
+#[test]
+fn const_fn() {
+    let krate = llbc("const_fn", "const_fn", &[]);
+    assert!(krate.fun_defs.iter().any(|d| d.is_const_fn));
+    for (dest, level) in [
+        ("const_fn_prom", "--mir_promoted"),
+        ("const_fn_opt", "--mir_optimized"),
+    ] {
+        let krate = llbc("const_fn", dest, &[level]);
+        assert!(krate
+            .fun_defs
+            .iter()
+            .filter(|d| d.is_const_fn)
+            .all(|d| d.body.is_some()));
+    }
+}
+
+/// The two methods of `Counter` are linked to it, but not the function which
+/// is not a method
+#[test]
+fn methods() {
+    let krate = llbc("methods", "methods", &[]);
+    let counter = TypeId::Adt(type_decl(&krate, "Counter").def_id);
+    let methods = krate
+        .fun_defs
+        .iter()
+        .filter(|d| d.self_type.as_ref() == Some(&counter))
+        .count();
+    assert!(methods == 2);
+    assert!(
+        krate
+            .fun_defs
+            .iter()
+            .filter(|d| d.self_type.is_none())
+            .count()
+            == 1
+    );
+}
+
+/// The slice patterns are translated to constant indices and subslices
+#[test]
+fn slice_patterns() {
+    let out = charon("slice_patterns", "slice_patterns", &["--pretty"]);
+    let krate = out.llbc("slice_patterns");
+    let contents = Contents::of_crate(&krate);
+    let elems = contents.projection_elems();
+    for elem in [
+        ProjectionElem::ConstantIndex {
+            offset: 1,
+            from_end: false,
+        },
+        ProjectionElem::ConstantIndex {
+            offset: 1,
+            from_end: true,
+        },
+        ProjectionElem::Subslice {
+            from: 2,
+            to: 0,
+            from_end: true,
+        },
+        ProjectionElem::Subslice {
+            from: 1,
+            to: 4,
+            from_end: false,
+        },
+    ] {
+        assert!(elems.contains(&&elem), "not found: {:?}", elem);
+    }
+    let strip_first = out.pretty_fun("slice_patterns::strip_first");
+    assert!(strip_first.iter().any(|l| l.contains("[1..]")));
+}
+
+/// The module tree: the crate contains `outer`, which contains `inner`
+#[test]
+fn modules() {
+    let krate = llbc("modules", "modules", &[]);
+    let module = |name: &[&str]| {
+        krate
+            .module_defs
+            .iter()
+            .find(|m| m.name.equals_ref_name(name))
+            .unwrap_or_else(|| panic!("module not found: {:?}", name))
+    };
+    let root = module(&["modules"]);
+    let outer = module(&["modules", "outer"]);
+    let inner = module(&["modules", "outer", "inner"]);
+    assert!(root.submodules == vec![outer.def_id]);
+    assert!(outer.submodules == vec![inner.def_id]);
+    assert!(outer.types == vec![type_decl(&krate, "Foo").def_id]);
+    assert!(outer.funs.is_empty());
+    assert!(inner.submodules.is_empty() && inner.types.is_empty());
+    assert!(inner.funs.len() == 1 && inner.globals.len() == 1);
+}
+
+/// The `let ... else { ... }` statements are reconstructed
+#[test]
+fn let_else() {
+    let krate = llbc("let_else", "let_else", &[]);
+    let contents = Contents::of_crate(&krate);
+    assert!(contents.count(|st| st.is_let_else()) > 0);
+}
+
+/// Only the two functions of the crate are root declarations (not the
+/// external type `Ordering`)
+#[test]
+fn root_items() {
+    let krate = llbc("root_items", "root_items", &[]);
+    let roots = krate.fun_defs.iter().filter(|d| d.is_root).count()
+        + krate.type_defs.iter().filter(|d| d.is_root).count()
+        + krate.global_defs.iter().filter(|d| d.is_root).count();
+    assert!(roots == 2);
+    assert!(!type_decl(&krate, "Ordering").is_root);
+}
+
+/// The promoted constants are translated to globals (there are promoted
+/// constants only starting from the promoted MIR)
+#[test]
+fn promoted() {
+    let krate = llbc("promoted", "promoted", &["--mir_promoted"]);
+    assert!(krate.global_defs.iter().any(|d| d.name.equals_ref_name(&[
+        "promoted",
+        "get_slice",
+        "promoted"
+    ])));
+}
+
+/// The string literals are translated
+#[test]
+fn str_literals() {
+    let krate = llbc("str_literals", "str_literals", &[]);
+    let contents = Contents::of_crate(&krate);
+    for s in ["hello, world", "hi"] {
+        let lit = OperandConstantValue::Literal(Literal::Str(s.to_string()));
+        assert!(contents.constants.contains(&lit), "not found: {}", s);
+    }
+}
+
+/// The bytes of the byte strings are translated
+#[test]
+fn byte_strings() {
+    let krate = llbc("byte_strings", "byte_strings", &[]);
+    let contents = Contents::of_crate(&krate);
+    for b in [97, 222, 173, 190, 239, 104] {
+        let lit = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U8(b)));
+        assert!(contents.constants.contains(&lit), "not found: {}", b);
+    }
+}
+
+/// The `if let ... { ... } else { ... }` statements are reconstructed
+#[test]
+fn if_let() {
+    let krate = llbc("if_let", "if_let", &[]);
+    let contents = Contents::of_crate(&krate);
+    assert!(contents.count(|st| st.is_if_let()) > 0);
+}
+
+/// The variance of the type parameters
+#[test]
+fn variance() {
+    use Variance::*;
+    let krate = llbc("variance", "variance", &[]);
+    let variance = |name| type_decl(&krate, name).variance.clone();
+    assert!(variance("Cov") == vec![Covariant]);
+    assert!(variance("Inv") == vec![Invariant]);
+    assert!(variance("Contra") == vec![Contravariant]);
+    assert!(variance("Mixed") == vec![Covariant, Invariant]);
+}
+
+/// The `PhantomData` fields are preserved
+#[test]
+fn phantom_data() {
+    let krate = llbc("phantom_data", "phantom_data", &[]);
+    let marker = krate
+        .type_defs
+        .iter()
+        .filter(|d| d.kind.is_struct())
+        .flat_map(fields)
+        .find(|f| f.name.as_deref() == Some("_marker"))
+        .unwrap();
+    match &marker.ty {
+        Ty::Adt(TypeId::Assumed(AssumedTy::PhantomData), regions, tys, _) => {
+            assert!(regions.is_empty());
+            assert!(matches!(tys.as_slice(), [Ty::Ref(..)]));
+        }
+        ty => panic!("unexpected type: {:?}", ty),
+    }
+}
+
+/// The unsafe functions, and the functions which call them
+#[test]
+fn unsafe_fns() {
+    let krate = llbc("unsafe_fns", "unsafe_fns", &[]);
+    let count = |is_unsafe, calls_unsafe| {
+        krate
+            .fun_defs
+            .iter()
+            .filter(|d| d.is_unsafe == is_unsafe && d.calls_unsafe == calls_unsafe)
+            .count()
+    };
+    assert!(count(true, false) >= 1);
+    assert!(count(false, true) >= 2);
+    assert!(count(false, false) >= 1);
+}
+
+/// The trait declarations: `Shape` has one required method, one provided
+/// method, and `Named` as parent trait. The methods of the trait impls are
+/// linked to the implemented traits.
+#[test]
+fn traits() {
+    let krate = llbc("traits", "traits", &[]);
+    let shape = trait_decl(&krate, "Shape");
+    assert!(shape
+        .type_params
+        .iter()
+        .map(|v| v.name.as_str())
+        .eq(["Self"]));
+    assert!(shape.required_methods.len() == 1);
+    assert!(shape.default_methods.len() == 1);
+    assert!(shape.supertrait_bounds.len() == 1);
+    assert!(shape.supertrait_bounds[0]
+        .trait_name
+        .equals_ref_name(&["traits", "Named"]));
+    let square = TypeId::Adt(type_decl(&krate, "Square").def_id);
+    let area = fun(&krate, "traits::Square::area");
+    assert!(area.self_type == Some(square));
+    assert!(area.trait_id == Some(shape.def_id));
+}
+
+/// `UnsafeCell` is an assumed type, and the fields which contain an
+/// `UnsafeCell` (which is not behind a reference) are flagged
+#[test]
+fn interior_mut() {
+    let krate = llbc("interior_mut", "interior_mut", &[]);
+    let my_cell = type_decl(&krate, "MyCell");
+    let v = field(my_cell, "v");
+    let unsafe_cell = TypeId::Assumed(AssumedTy::UnsafeCell);
+    assert!(v.ty == adt_ty(unsafe_cell, vec![u32_ty()]));
+    assert!(v.interior_mut);
+    let cell = field(type_decl(&krate, "Wrapper"), "cell");
+    assert!(cell.ty == adt_ty(TypeId::Adt(my_cell.def_id), Vec::new()));
+    assert!(cell.interior_mut);
+    let x = field(type_decl(&krate, "Wrapper"), "x");
+    assert!(x.ty == u32_ty() && !x.interior_mut);
+    let r = field(type_decl(&krate, "Shared"), "r");
+    assert!(r.ty.is_ref() && !r.interior_mut);
+    let count = field(type_decl(&krate, "Counter"), "count");
+    assert!(matches!(
+        &count.ty,
+        Ty::Adt(TypeId::Adt(_), _, tys, _) if tys == &vec![u32_ty()]
+    ));
+    assert!(count.interior_mut);
+}
+
+/// With `--storage-annotations`, the scoped variables are bracketed by
+/// `StorageLive` and `StorageDead` statements. By default, we translate the
+/// `StorageDead` statements to drops.
+#[test]
+fn storage() {
+    let krate = llbc("storage", "storage", &[]);
+    let contents = Contents::of_fun(&krate, "scoped");
+    assert!(contents.count(|st| st.is_storage_live() || st.is_storage_dead()) == 0);
+    assert!(contents.count(|st| st.is_drop()) > 0);
+
+    let krate = llbc("storage", "storage_annotations", &["--storage-annotations"]);
+    let contents = Contents::of_fun(&krate, "scoped");
+    let lives = contents.statements.iter().filter_map(|st| match st {
+        RawStatement::StorageLive(v) => Some(*v),
+        _ => None,
+    });
+    let live_and_dead = lives.filter(|v| {
+        contents
+            .statements
+            .iter()
+            .any(|st| matches!(st, RawStatement::StorageDead(v1) if v1 == v))
+    });
+    assert!(live_and_dead.count() > 0);
+}
+
+/// `binpow` has exactly one loop header, and we reconstruct the loop
+#[test]
+fn loop_headers() {
+    let krate = ullbc("loop_headers", "loop_headers_ullbc", &[]);
+    let body = fun(&krate, "binpow").body.as_ref().unwrap();
+    assert!(body.body.iter().filter(|b| b.is_loop_header).count() == 1);
+    let krate = llbc("loop_headers", "loop_headers", &[]);
+    let contents = Contents::of_fun(&krate, "binpow");
+    assert!(contents.count(|st| st.is_loop()) == 1);
+}
+
+/// The `?` operators are reconstructed: there are 4 of them
+#[test]
+fn try_op() {
+    let krate = llbc("try_op", "try_op", &["--reconstruct-try"]);
+    let contents = Contents::of_crate(&krate);
+    assert!(contents.count(|st| matches!(st, RawStatement::TryOp { .. })) == 4);
+}
+
+/// The calls to `from_raw_parts` are translated to raw pointer aggregates:
+/// there are 3 of them, one of which builds a `*mut [u32]`
+#[test]
+fn raw_ptr() {
+    let krate = llbc("raw_ptr", "raw_ptr", &[]);
+    let contents = Contents::of_crate(&krate);
+    let raw_ptrs: Vec<_> = contents
+        .aggregates()
+        .into_iter()
+        .filter_map(|(kind, _)| match kind {
+            AggregateKind::RawPtr(ty, kind) => Some((ty, kind)),
+            _ => None,
+        })
+        .collect();
+    assert!(raw_ptrs.len() == 3);
+    let slice = adt_ty(TypeId::Assumed(AssumedTy::Slice), vec![u32_ty()]);
+    assert!(raw_ptrs.contains(&(&slice, &RefKind::Mut)));
+}
+
+/// `Counter::drop` is marked as a custom drop, and the drop of `_y` in
+/// `drop_two` unwinds to the cleanup block which drops `_x`
+#[test]
+fn drop_impl() {
+    let krate = ullbc("drop_impl", "drop_impl", &[]);
+    assert!(krate.fun_defs.iter().filter(|d| d.implemented_drop).count() == 1);
+    let blocks = &fun(&krate, "drop_two").body.as_ref().unwrap().body;
+    let unwind = blocks
+        .iter()
+        .find_map(|b| match &b.terminator.content {
+            RawTerminator::Drop { place, unwind, .. } if *place == var_place(2) => *unwind,
+            _ => None,
+        })
+        .unwrap();
+    assert!(matches!(
+        &blocks.get(unwind).unwrap().terminator.content,
+        RawTerminator::Drop { place, .. } if *place == var_place(1)
+    ));
+}
+
+/// `MyList::next` records the method of `Iterator` it implements, while
+/// `MyList::len` doesn't
+#[test]
+fn trait_impls() {
+    let krate = llbc("trait_impls", "trait_impls", &[]);
+    let my_list = TypeId::Adt(type_decl(&krate, "MyList").def_id);
+    let infos: Vec<_> = krate
+        .fun_defs
+        .iter()
+        .filter_map(|d| d.trait_impl_info.as_ref())
+        .collect();
+    assert!(infos.len() == 1);
+    assert!(infos[0].impl_type == adt_ty(my_list, Vec::new()));
+    assert!(infos[0].method_name == "next");
+    assert!(fun(&krate, "trait_impls::MyList::next")
+        .trait_impl_info
+        .is_some());
+}
+
+/// `Greet` has one required method and two default methods, and
+/// `Person::greet` overrides exactly one of them
+#[test]
+fn default_methods() {
+    let krate = llbc("default_methods", "default_methods", &[]);
+    let greet = trait_decl(&krate, "Greet");
+    assert!(greet.required_methods.len() == 1);
+    assert!(greet.default_methods.len() == 2);
+    assert!(krate.fun_defs.iter().filter(|d| d.is_trait_default).count() == 2);
+    let overrides: Vec<_> = krate
+        .fun_defs
+        .iter()
+        .filter_map(|d| d.overrides_default)
+        .collect();
+    assert!(overrides.len() == 1);
+    assert!(greet.default_methods.contains(&overrides[0]));
+}
+
+/// The variables captured by the closures: `mixed_capturing` captures `v` by
+/// value and `x` by shared reference. The closures are built with aggregates
+/// which give their kind and the types of their captured variables, and their
+/// bodies receive the state (borrowed, unless the closure is `FnOnce`) as
+/// first argument.
+#[test]
+fn closures() {
+    let krate = llbc("closures", "closures", &[]);
+    let vec = adt_ty(TypeId::Assumed(AssumedTy::Vec), vec![u32_ty()]);
+    let mixed = krate
+        .fun_defs
+        .iter()
+        .find(|d| d.captures.len() == 2 && d.captures[0].name == "v" && d.captures[1].name == "x")
+        .unwrap();
+    assert!(mixed.captures[0].ty == vec && mixed.captures[0].mode == CaptureMode::ByValue);
+    assert!(mixed.captures[1].ty == u32_ty() && mixed.captures[1].mode == CaptureMode::ByRef);
+    let by_mut_ref = krate
+        .fun_defs
+        .iter()
+        .flat_map(|d| d.captures.iter())
+        .filter(|c| c.mode == CaptureMode::ByMutRef)
+        .count();
+    // `mut_capturing` and `map_option` capture a variable by mutable reference
+    assert!(by_mut_ref == 2);
+
+    // The closures built in a function: their kind, the types of their
+    // captured variables, and the first input of their body
+    let closures = |name| -> Vec<(ClosureKind, Vec<ETy>, RTy)> {
+        Contents::of_fun(&krate, name)
+            .aggregates()
+            .into_iter()
+            .filter_map(|(kind, ops)| match kind {
+                AggregateKind::Closure(fid, kind, upvar_tys) => {
+                    assert!(ops.len() == upvar_tys.len());
+                    let body = krate.fun_defs.iter().find(|d| d.def_id == *fid).unwrap();
+                    Some((*kind, upvar_tys.clone(), body.signature.inputs[0].clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    };
+    // The first input is the tuple of the captured variables, borrowed if the
+    // closure is `Fn` or `FnMut`
+    let is_state = |kind: ClosureKind, upvar_tys: &Vec<ETy>, input: &RTy| {
+        let (state, ref_kind) = match input {
+            Ty::Ref(_, state, ref_kind) => (&**state, Some(*ref_kind)),
+            _ => (input, None),
+        };
+        let expected_ref_kind = match kind {
+            ClosureKind::Fn => Some(RefKind::Shared),
+            ClosureKind::FnMut => Some(RefKind::Mut),
+            ClosureKind::FnOnce => None,
+        };
+        ref_kind == expected_ref_kind
+            && matches!(state, Ty::Adt(TypeId::Tuple, _, tys, _) if tys.len() == upvar_tys.len())
+    };
+    let u32_ref = Ty::Ref(ErasedRegion::Erased, Box::new(u32_ty()), RefKind::Shared);
+    let expected: [(&str, &[ClosureKind]); 6] = [
+        ("shared_capturing", &[ClosureKind::Fn]),
+        ("mut_capturing", &[ClosureKind::FnMut]),
+        ("move_capturing", &[ClosureKind::Fn]),
+        ("mixed_capturing", &[ClosureKind::FnOnce]),
+        // `map` and `filter` take `FnMut` closures
+        ("map_filter", &[ClosureKind::FnMut, ClosureKind::FnMut]),
+        // `Option::map` takes a `FnOnce` closure
+        ("map_option", &[ClosureKind::FnOnce]),
+    ];
+    for (name, kinds) in expected {
+        let closures = closures(name);
+        assert!(closures.len() == kinds.len(), "{}", name);
+        for ((kind, upvar_tys, input), expected_kind) in closures.iter().zip(kinds) {
+            assert!(kind == expected_kind, "{}", name);
+            assert!(is_state(*kind, upvar_tys, input), "{}", name);
+        }
+    }
+    // The closures of `map_filter` capture `y` by shared reference
+    assert!(closures("map_filter")
+        .iter()
+        .all(|(_, upvar_tys, _)| *upvar_tys == [u32_ref.clone()]));
+    assert!(closures("move_capturing")[0].1 == [u32_ty()]);
+}
+
+/// `add_twice` computes the addition once
+#[test]
+fn cse() {
+    let krate = llbc("cse", "cse", &["--cse"]);
+    let contents = Contents::of_crate(&krate);
+    assert!(contents.binary_ops(BinOp::Add) == 1);
+    assert!(contents.binary_ops(BinOp::Mul) == 1);
+}
+
+/// The optional micro-passes preserve the typing of the bodies
+#[test]
+fn typecheck() {
+    let opts = ["--typecheck", "--cse", "--constant-propagation"];
+    charon("no_nested_borrows", "typecheck", &opts);
+    charon("cse", "typecheck_cse", &opts);
+}
+
+/// Propagating the constants removes the locals to which they are assigned
+/// (in `test2` for instance: `let y = 44; let z = x + y;`)
+#[test]
+fn constant_propagation() {
+    let locals =
+        |krate: &TranslatedCrate, path: &str| fun(krate, path).body.as_ref().unwrap().locals.len();
+    let total_locals = |krate: &TranslatedCrate| {
+        krate
+            .fun_defs
+            .iter()
+            .filter_map(|d| d.body.as_ref())
+            .map(|b| b.locals.len())
+            .sum::<usize>()
+    };
+
+    let opts = ["--no-code-duplication"];
+    let before = llbc("no_nested_borrows", "no_constant_propagation", &opts);
+    let opts = ["--no-code-duplication", "--constant-propagation"];
+    let after = llbc("no_nested_borrows", "constant_propagation", &opts);
+
+    let test2 = "no_nested_borrows::test2";
+    assert!(locals(&after, test2) < locals(&before, test2));
+    assert!(total_locals(&after) < total_locals(&before));
+    // The functions without constants are left unchanged
+    let add_test = "no_nested_borrows::add_test";
+    assert!(locals(&after, add_test) == locals(&before, add_test));
+}
+
+/// The range types are assumed types
+#[test]
+fn ranges() {
+    let krate = llbc("ranges", "ranges", &[]);
+    let contents = Contents::of_crate(&krate);
+    let usize_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::Usize));
+    let kinds: Vec<_> = contents.aggregates().into_iter().map(|(k, _)| k).collect();
+    assert!(kinds.contains(&&AggregateKind::Range(AssumedTy::Range, vec![usize_ty])));
+    assert!(kinds.contains(&&AggregateKind::Range(AssumedTy::RangeFull, Vec::new())));
+    let range_inclusive = TypeId::Assumed(AssumedTy::RangeInclusive);
+    assert!(krate
+        .fun_defs
+        .iter()
+        .any(|d| matches!(&d.signature.output, Ty::Adt(id, ..) if *id == range_inclusive)));
+    assert!(contents.projection_elems().into_iter().any(|e| matches!(
+        e,
+        ProjectionElem::Field(FieldProjKind::Range(AssumedTy::Range), _)
+    )));
+}
+
+/// The const generic expressions of `split` are preserved
+#[test]
+fn const_generic_exprs() {
+    let krate = llbc("const_generic_exprs", "const_generic_exprs", &[]);
+    let split = fun(&krate, "split");
+    let is_mul_n = |cg: &ConstGeneric| match cg {
+        ConstGeneric::Expr(e) => matches!(
+            e.as_ref(),
+            ConstGenericExpr::BinaryOp(BinOp::Mul, ConstGeneric::Var(v), _) if v.is_zero()
+        ),
+        _ => false,
+    };
+    assert!(split
+        .signature
+        .inputs
+        .iter()
+        .any(|ty| matches!(ty, Ty::Adt(_, _, _, cgs) if cgs.iter().any(is_mul_n))));
+}
+
+/// The stable ids are computed from the names and the generic parameters
+#[test]
+fn stable_ids() {
+    let krate = llbc("stable_ids", "stable_ids", &["--stable-ids"]);
+    let wrapper = type_decl(&krate, "Wrapper");
+    assert!(wrapper.stable_id.as_deref() == Some("stable_ids::Wrapper<T, N>"));
+    let first = fun(&krate, "first");
+    assert!(first.stable_id.as_deref() == Some("stable_ids::first<T, N>"));
+}
+
+/// The statements generated by `assert!` are replaced with a placeholder,
+/// while the addition written by the user is preserved
+#[test]
+fn macros() {
+    let krate = llbc("macros", "macros", &["--strip-macro-metadata"]);
+    let contents = Contents::of_fun(&krate, "assert_one");
+    assert!(contents
+        .metas
+        .iter()
+        .any(|m| m.is_from_macro && m.macro_name == Some(MacroName::new("assert"))));
+    assert!(
+        contents.count(|st| matches!(st, RawStatement::MacroExpansion(m) if m == "assert")) > 0
+    );
+    assert!(contents.binary_ops(BinOp::Add) >= 1);
+}
+
+/// The methods implemented for external types refer to those types, and the
+/// opaque type `Duration` records its method
+#[test]
+fn external_impls() {
+    let krate = llbc("external_impls", "external_impls", &[]);
+    let vec = TypeId::Assumed(AssumedTy::Vec);
+    assert!(krate
+        .fun_defs
+        .iter()
+        .any(|d| d.self_type.as_ref() == Some(&vec)));
+    let duration = type_decl(&krate, "Duration");
+    assert!(duration.kind.is_opaque());
+    assert!(!duration.local_methods.is_empty());
+    let duration = TypeId::Adt(duration.def_id);
+    assert!(krate
+        .fun_defs
+        .iter()
+        .any(|d| d.self_type.as_ref() == Some(&duration)));
+}
+
+/// The tool attributes are recorded, with their arguments
+#[test]
+fn attributes() {
+    let krate = llbc("attributes", "attributes", &[]);
+    let attribute = |path: &[&str], args: &str| Attribute {
+        path: path.iter().map(|s| s.to_string()).collect(),
+        args: args.to_string(),
+    };
+    let attributes: Vec<_> = krate.fun_defs.iter().map(|d| &d.attributes).collect();
+    assert!(attributes.contains(&&vec![attribute(&["my_tool", "verified"], "")]));
+    assert!(attributes.contains(&&vec![attribute(&["my_tool", "requires"], "(x > 0)")]));
+}
+
+/// `--eliminate-asserts` removes the overflow check of `add_test` from the
+/// (built) MIR, and the LLBC passes accept the resulting bodies
+#[test]
+fn eliminate_asserts() {
+    let overflow_checks = [("RUSTFLAGS", "-C overflow-checks=on")];
+    let has_assert = |krate: &UllbcTranslatedCrate| {
+        let blocks = &fun(krate, "add_test").body.as_ref().unwrap().body;
+        blocks.iter().any(|b| b.terminator.content.is_assert())
+    };
+    let run = |dest, options: &[&str]| {
+        charon_with_env("no_nested_borrows", dest, options, &overflow_checks)
+    };
+    let before = run("eliminate_asserts_before", &["--ullbc"]).ullbc("no_nested_borrows");
+    assert!(has_assert(&before));
+    let after = run(
+        "eliminate_asserts_after",
+        &["--ullbc", "--eliminate-asserts"],
+    )
+    .ullbc("no_nested_borrows");
+    assert!(!has_assert(&after));
+    let krate = run("eliminate_asserts", &["--eliminate-asserts"]).llbc("no_nested_borrows");
+    let contents = Contents::of_fun(&krate, "add_test");
+    assert!(contents.count(|st| st.is_assert()) == 0);
+}
+
+/// The default value of the const generic of `Ring` is recorded, and used by
+/// the instantiations which omit the parameter
+#[test]
+fn const_generic_defaults() {
+    let krate = llbc("const_generic_defaults", "const_generic_defaults", &[]);
+    let ring = type_decl(&krate, "Ring");
+    let n = ring.const_generic_params.iter().next().unwrap();
+    assert!(n.name == "N" && n.ty == LiteralTy::Integer(IntegerTy::Usize));
+    assert!(matches!(
+        &n.default,
+        Some(ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(
+            _,
+            8
+        ))))
+    ));
+    // The size of a ring, or of a reference to a ring
+    let size = |ty: &RTy| {
+        let ty = match ty {
+            Ty::Ref(_, ty, _) => ty.as_ref(),
+            ty => ty,
+        };
+        match ty {
+            Ty::Adt(TypeId::Adt(id), _, _, cgs) if *id == ring.def_id => match cgs.as_slice() {
+                [ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(_, n)))] => *n,
+                _ => panic!("unexpected const generics: {:?}", cgs),
+            },
+            _ => panic!("unexpected type: {:?}", ty),
+        }
+    };
+    assert!(size(&fun(&krate, "first_explicit").signature.inputs[0]) == 4);
+    assert!(size(&fun(&krate, "first_default").signature.inputs[0]) == 8);
+    assert!(size(&fun(&krate, "new_default").signature.output) == 8);
+}
+
+/// The struct updates record their base (`..a`, where `a` is the first
+/// argument): the remaining fields of `Foo` are copied, while the mutable
+/// borrow of `Refs` is moved
+#[test]
+fn struct_update() {
+    let krate = llbc("struct_update", "struct_update", &[]);
+    let base = |path| {
+        let contents = Contents::of_fun(&krate, path);
+        contents
+            .aggregates()
+            .into_iter()
+            .find_map(|(kind, _)| match kind {
+                AggregateKind::StructUpdate { base, .. } => Some(base.clone()),
+                _ => None,
+            })
+            .unwrap()
+    };
+    assert!(base("update") == Operand::Copy(var_place(1)));
+    assert!(base("update_refs") == Operand::Move(var_place(1)));
+}
+
+/// The uses of `S` are replaced with its value
+#[test]
+fn const_eval() {
+    let krate = llbc("const_eval", "const_eval", &["--inline-const-eval"]);
+    let s = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U64(49)));
+    for path in ["get_s", "add_s"] {
+        assert!(Contents::of_fun(&krate, path).constants.contains(&s));
+    }
+}
+
+/// `--emit-def-ids` records the rustc identifiers of the declarations, which
+/// are omitted otherwise
+#[test]
+fn def_ids() {
+    let krate = llbc("no_nested_borrows", "def_ids", &["--emit-def-ids"]);
+    let def_ids = krate.def_ids.unwrap();
+    assert!(def_ids.fun_ids.len() == krate.fun_defs.iter().count());
+    let krate = llbc("no_nested_borrows", "no_def_ids", &[]);
+    assert!(krate.def_ids.is_none());
+}
+
+/// The fields of the struct-like variants keep their names, both in the type
+/// declaration and in the aggregates
+#[test]
+fn struct_variants() {
+    let out = charon("struct_variants", "struct_variants", &["--pretty"]);
+    let krate = out.llbc("struct_variants");
+    let variants = type_decl(&krate, "E").kind.as_enum();
+    let variant = variants.iter().find(|v| v.name == "Variant").unwrap();
+    let names: Vec<_> = variant.fields.iter().map(|f| f.name.as_deref()).collect();
+    assert!(names == vec![Some("x"), Some("y")]);
+    let aggregate =
+        Regex::new(r"struct_variants::E::Variant \{ x: [a-z]* \([^)]*\), y: [a-z]* \([^)]*\) \}")
+            .unwrap();
+    assert!(aggregate.is_match(&out.stdout));
+}
+
+/// The references to the constant arrays are translated to the aggregated
+/// arrays, followed by a borrow
+#[test]
+fn const_refs() {
+    let krate = llbc("const_refs", "const_refs", &["--inline-const-eval"]);
+    let contents = Contents::of_fun(&krate, "sum_a");
+    let elems: Vec<_> = (1..=3)
+        .map(|x| Operand::Const(u32_ty(), u32_const(x)))
+        .collect();
+    assert!(contents
+        .aggregates()
+        .into_iter()
+        .any(|(kind, ops)| matches!(kind, AggregateKind::Array(..)) && *ops == elems));
+    assert!(contents.rvalues.iter().any(|rv| rv.is_ref()));
+}
+
+/// The functions annotated with `#[charon::mir_level = "optimized"]` or given
+/// with `--mir-level-for` are translated from the optimized MIR (the constant
+/// propagation computed the sum), while the other functions are translated at
+/// the level of the crate
+#[test]
+fn mir_levels() {
+    let krate = llbc(
+        "mir_levels",
+        "mir_levels",
+        &["--mir-level-for", "sum_cli=optimized"],
+    );
+    let computed = |path| {
+        Contents::of_fun(&krate, path)
+            .constants
+            .contains(&u32_const(3))
+    };
+    assert!(computed("sum_attribute"));
+    assert!(computed("sum_cli"));
+    assert!(!computed("sum_default"));
+}
+
+/// `--strip-opaque` removes the unused items of the opaque modules, but keeps
+/// the opaque function used by `call_used`
+#[test]
+fn strip_opaque() {
+    let krate = llbc(
+        "strip_opaque",
+        "strip_opaque",
+        &["--opaque=utils", "--strip-opaque"],
+    );
+    let has_fun = |path| krate.fun_defs.iter().any(|d| d.name.matches_path(path));
+    assert!(has_fun("used"));
+    assert!(!has_fun("unused_helper"));
+    assert!(!krate
+        .type_defs
+        .iter()
+        .any(|d| d.name.matches_path("UnusedType")));
+}
+
+/// The generic impls and the impls for trait objects are recorded in the trait
+/// declarations, with their bounds, while the impl for `u32` isn't
+#[test]
+fn blanket_impls() {
+    let krate = llbc("blanket_impls", "blanket_impls", &[]);
+    let count: usize = krate.trait_defs.iter().map(|d| d.blanket_impls.len()).sum();
+    assert!(count == 3);
+    let bounded_by = |trait_path, bound| {
+        trait_decl(&krate, trait_path)
+            .blanket_impls
+            .iter()
+            .any(|i| {
+                i.bounds.iter().any(|p| match p {
+                    Predicate::Trait(_, trait_ref) => trait_ref.trait_name.matches_path(bound),
+                    _ => false,
+                })
+            })
+    };
+    assert!(bounded_by("Describe", "Debug"));
+    assert!(bounded_by("Named", "Display"));
+}
+
+/// The repeated arrays are translated to `[x; N]` aggregates, also when the
+/// length is a const generic, and the array literals whose elements are the
+/// same constant are collapsed
+#[test]
+fn array_repeat() {
+    let krate = llbc("array_repeat", "array_repeat", &[]);
+    let repeat = |contents: &Contents| {
+        contents
+            .aggregates()
+            .into_iter()
+            .find_map(|(kind, _)| match kind {
+                AggregateKind::Repeat(ty, op, len) => Some((ty.clone(), op.clone(), len.clone())),
+                _ => None,
+            })
+            .unwrap()
+    };
+    let is_len = |cg: &ConstGeneric, len| matches!(cg, ConstGeneric::Value(Literal::Scalar(ScalarValue::Usize(_, n))) if *n == len);
+    let u8_ty = Ty::Literal(LiteralTy::Integer(IntegerTy::U8));
+    let zero = OperandConstantValue::Literal(Literal::Scalar(ScalarValue::U8(0)));
+    let (ty, op, len) = repeat(&Contents::of_fun(&krate, "zeroes"));
+    assert!(ty == u8_ty && op == Operand::Const(u8_ty, zero));
+    assert!(len == ConstGeneric::Var(ConstGenericVarId::ZERO));
+    let (ty, op, len) = repeat(&Contents::of_fun(&krate, "ones"));
+    assert!(ty == u32_ty() && op == Operand::Const(u32_ty(), u32_const(1)));
+    assert!(is_len(&len, 3));
+    // `x` is copied to a temporary, which is moved into the array
+    let contents = Contents::of_fun(&krate, "repeat_var");
+    let (ty, op, len) = repeat(&contents);
+    assert!(ty == u32_ty() && is_len(&len, 4));
+    let copy_x = Rvalue::Use(Operand::Copy(var_place(1)));
+    assert!(contents.statements.iter().any(|st| match (st, &op) {
+        (RawStatement::Assign(p, rv), Operand::Move(tmp)) => p == tmp && *rv == copy_x,
+        _ => false,
+    }));
+}
+
+/// The type aliases are translated, and they appear in the signatures and in
+/// the fields instead of their expansion
+#[test]
+fn type_aliases() {
+    let krate = llbc("type_aliases", "type_aliases", &["--preserve-type-aliases"]);
+    let my_vec = type_decl(&krate, "MyVec");
+    assert!(my_vec.kind.is_alias());
+    let my_vec = adt_ty(TypeId::Adt(my_vec.def_id), Vec::new());
+    assert!(fun(&krate, "make_vec").signature.output == my_vec);
+    assert!(matches!(
+        &fun(&krate, "push").signature.inputs[0],
+        Ty::Ref(_, ty, RefKind::Mut) if **ty == my_vec
+    ));
+}
+
+/// The states of the generator of `yield_ints` (the generators are only
+/// supported in ULLBC). The state enumeration has 5 variants, and only `x` is
+/// saved at the first `yield`. The `async` function `add_ones` returns an
+/// `impl Future<Output = u32>`, and its generator polls the awaited futures.
+#[test]
+fn generators() {
+    let krate = ullbc("generators", "generators", &[]);
+    let state = type_decl(&krate, "generators::yield_ints::closure::State");
+    assert!(state.region_params.is_empty());
+    let variants = state.kind.as_enum();
+    let names: Vec<_> = variants.iter().map(|v| v.name.as_str()).collect();
+    assert!(names == ["Unresumed", "Returned", "Poisoned", "Suspend0", "Suspend1"]);
+    let suspend0: Vec<_> = variants.iter().nth(3).unwrap().fields.iter().collect();
+    assert!(suspend0.len() == 1);
+    assert!(suspend0[0].name.as_deref() == Some("x"));
+    assert!(suspend0[0].ty == u32_ty() && !suspend0[0].interior_mut);
+    assert!(variants.iter().nth(4).unwrap().fields.iter().count() == 0);
+
+    // The `async` function returns the generator, as an opaque type which
+    // implements `Future`
+    let future = match &fun(&krate, "add_ones").signature.output {
+        Ty::Adt(TypeId::Adt(id), ..) => krate.type_defs.get(*id).unwrap(),
+        ty => panic!("unexpected output type: {:?}", ty),
+    };
+    let bounds = future.kind.as_impl_trait();
+    assert!(bounds.len() == 1);
+    assert!(bounds[0]
+        .trait_name
+        .matches_path("core::future::future::Future"));
+    assert!(bounds[0].assoc_types == [("Output".to_string(), u32_ty())]);
+
+    // Each `.await` polls the awaited future, and yields while it is pending
+    let blocks = &fun(&krate, "generators::add_ones::closure")
+        .body
+        .as_ref()
+        .unwrap()
+        .body;
+    let poll = fun(&krate, "core::future::future::Future::poll").def_id;
+    let calls_poll = |t: &RawTerminator| match t {
+        RawTerminator::Call { call, .. } => matches!(call.func, FunId::Regular(id) if id == poll),
+        _ => false,
+    };
+    let polls = blocks
+        .iter()
+        .filter(|b| calls_poll(&b.terminator.content))
+        .count();
+    let yields = blocks
+        .iter()
+        .filter(|b| matches!(&b.terminator.content, RawTerminator::Yield { .. }))
+        .count();
+    assert!(polls == 2 && yields == 2);
+    let state = type_decl(&krate, "generators::add_ones::closure::State");
+    let variants = state.kind.as_enum();
+    assert!(variants.iter().count() == 5);
+    assert!(variants
+        .iter()
+        .any(|v| v.fields.iter().any(|f| f.ty == u32_ty())));
+
+    // We report a translation error if we try to generate LLBC
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("generators_llbc");
+    let output = Command::cargo_bin("charon")
+        .unwrap()
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests"))
+        .args(["--crate", "generators", "--input", "src/generators.rs"])
+        .arg("--dest")
+        .arg(&dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Unsupported item: generator"), "{}", stderr);
+    assert!(!stderr.contains("not yet implemented"), "{}", stderr);
+}
+
+/// We don't support the floating-point numbers: by default, the translation
+/// fails. With `--errors-as-warnings`, we still generate the (partial) crate,
+/// in which the unsupported declarations are opaque.
+#[test]
+fn unsupported() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("unsupported_fail");
+    let output = Command::cargo_bin("charon")
+        .unwrap()
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests"))
+        .args(["--crate", "unsupported", "--input", "src/unsupported.rs"])
+        .arg("--dest")
+        .arg(&dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Unsupported type: f32"), "{}", stderr);
+    assert!(!dir.join("unsupported.llbc").exists());
+
+    let krate = llbc("unsupported", "unsupported", &["--errors-as-warnings"]);
+    assert!(type_decl(&krate, "Point").kind.is_opaque());
+    assert!(fun(&krate, "half").body.is_none());
+    assert!(fun(&krate, "double").body.is_some());
+    assert!(fun(&krate, "assume_unchecked").body.is_some());
+
+    let krate = llbc(
+        "unsupported",
+        "unsupported_opt",
+        &["--errors-as-warnings", "--mir_optimized"],
+    );
+    assert!(fun(&krate, "assume_unchecked").body.is_none());
+    assert!(fun(&krate, "double").body.is_some());
+}
+
+/// The MIR of `max` is dumped next to the generated file. It has 5 blocks.
+#[test]
+fn emit_mir() {
+    let out = charon("emit_mir", "emit_mir", &["--emit-mir"]);
+    let mir = std::fs::read_to_string(out.path("emit_mir.max.mir")).unwrap();
+    assert!(mir.lines().any(|l| l.starts_with("fn max(")));
+    let block = Regex::new(r"^    bb[0-9]*: \{$").unwrap();
+    assert!(mir.lines().filter(|l| block.is_match(l)).count() == 5);
+}
+
+/// `List` is marked as recursive, but not `Pair`. We use `--function` to only
+/// translate the types used by one function.
+#[test]
+fn recursive_types() {
+    let krate = llbc(
+        "no_nested_borrows",
+        "recursive_types_list",
+        &["--function", "list_length"],
+    );
+    assert!(type_decl(&krate, "List").is_recursive);
+    assert!(krate.type_defs.iter().filter(|d| d.is_recursive).count() == 1);
+    let krate = llbc(
+        "no_nested_borrows",
+        "recursive_types_pair",
+        &["--function", "new_pair1"],
+    );
+    assert!(!type_decl(&krate, "Pair").is_recursive);
+    assert!(!krate.type_defs.iter().any(|d| d.is_recursive));
+}
+
+/// The statistics: the number of blocks of `binpow` must match its body and
+/// the number of blocks printed by the pretty-printer, and `list_length` must
+/// have more blocks than `neg_test`
+#[test]
+fn stats() {
+    let read_stats = |out: &Output, krate: &str| -> Vec<FunStats> {
+        let file = std::fs::File::open(out.path(&format!("{krate}.stats.json"))).unwrap();
+        serde_json::from_reader(file).unwrap()
+    };
+    let block_count = |stats: &[FunStats], path| {
+        stats
+            .iter()
+            .find(|s| s.name.matches_path(path))
+            .unwrap()
+            .block_count
+    };
+
+    let out = charon(
+        "loop_headers",
+        "stats_loop_headers",
+        &["--ullbc", "--pretty", "--stats"],
+    );
+    let stats = read_stats(&out, "loop_headers");
+    let krate = out.ullbc("loop_headers");
+    let blocks = &fun(&krate, "binpow").body.as_ref().unwrap().body;
+    assert!(block_count(&stats, "binpow") == blocks.iter().count());
+    let label = Regex::new("^    bb[0-9]*:$").unwrap();
+    let printed = out.pretty_fun("loop_headers::binpow");
+    assert!(block_count(&stats, "binpow") == printed.iter().filter(|l| label.is_match(l)).count());
+
+    let out = charon(
+        "no_nested_borrows",
+        "stats_no_nested_borrows",
+        &["--ullbc", "--stats"],
+    );
+    let stats = read_stats(&out, "no_nested_borrows");
+    assert!(block_count(&stats, "list_length") > block_count(&stats, "neg_test"));
+}
+
+/// The output split per module: the crate root and the `geometry` module must
+/// be written in two different files, each containing only its own items
+#[test]
+fn split_output() {
+    let out = charon("split_output", "split_output", &["--split-output"]);
+    let files = std::fs::read_dir(&out.dir)
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension() == Some("llbc".as_ref()))
+        .count();
+    assert!(files == 2);
+    let has_fun =
+        |krate: &TranslatedCrate, path| krate.fun_defs.iter().any(|d| d.name.matches_path(path));
+    let has_type =
+        |krate: &TranslatedCrate, path| krate.type_defs.iter().any(|d| d.name.matches_path(path));
+
+    let root = out.llbc("split_output");
+    assert!(has_fun(&root, "norm1"));
+    assert!(!has_fun(&root, "origin") && !has_type(&root, "Point"));
+    assert!(root.dependencies == ["split_output.geometry.llbc"]);
+
+    let geometry = out.llbc("split_output.geometry");
+    assert!(has_fun(&geometry, "origin") && has_type(&geometry, "Point"));
+    assert!(!has_fun(&geometry, "norm1"));
+    assert!(geometry.dependencies.is_empty());
+}
+
+/// Translating the same crate twice produces the same files
+#[test]
+fn deterministic() {
+    let read = |dest| {
+        let out = charon("no_nested_borrows", dest, &[]);
+        std::fs::read(out.path("no_nested_borrows.llbc")).unwrap()
+    };
+    assert!(read("deterministic_1") == read("deterministic_2"));
+}
+
+/// The declarations are serialized sorted by name, whatever the order in
+/// which we explore the crate
+#[test]
+fn sorted_declarations() {
+    #[derive(Deserialize)]
+    struct Decl {
+        name: Name,
+    }
+    #[derive(Deserialize)]
+    struct Decls {
+        types: Vec<Decl>,
+        functions: Vec<Decl>,
+    }
+
+    let out = charon("no_nested_borrows", "sorted_declarations", &[]);
+    let file = std::fs::File::open(out.path("no_nested_borrows.llbc")).unwrap();
+    let mut deserializer = serde_json::Deserializer::from_reader(std::io::BufReader::new(file));
+    deserializer.disable_recursion_limit();
+    let decls = Decls::deserialize(&mut deserializer).unwrap();
+    for decls in [decls.types, decls.functions] {
+        let names: Vec<String> = decls.iter().map(|d| d.name.to_string()).collect();
+        assert!(names.len() > 1);
+        assert!(
+            names.windows(2).all(|w| w[0] <= w[1]),
+            "not sorted: {:?}",
+            names
+        );
+    }
+}
+
+/// `--function` only translates the function and its dependencies
+#[test]
+fn function() {
+    let krate = llbc(
+        "no_nested_borrows",
+        "function",
+        &["--function", "list_length"],
+    );
+    let funs: Vec<_> = krate.fun_defs.iter().collect();
+    assert!(funs.len() == 1);
+    assert!(funs[0]
+        .name
+        .equals_ref_name(&["no_nested_borrows", "list_length"]));
+    let types: Vec<_> = krate.type_defs.iter().collect();
+    assert!(types.len() == 1);
+    assert!(types[0]
+        .name
+        .equals_ref_name(&["no_nested_borrows", "List"]));
+}
+
+/// The HTML output: every function must be rendered as a section whose
+/// header links to the source file
+#[test]
+fn html() {
+    let out = charon("no_nested_borrows", "html", &["--html"]);
+    let html = std::fs::read_to_string(out.path("no_nested_borrows.html")).unwrap();
+    let sections = html.matches("<section").count();
+    assert!(sections > 0);
+    assert!(sections <= html.matches(r#"<h2><a href="file:///"#).count());
+    let link = Regex::new(r#"href="file:///[^"]*/no_nested_borrows.rs""#).unwrap();
+    assert!(link.is_match(&html));
+}
+
+/// The output of the pretty-printer: the blocks of `list_length` must be
+/// labeled, and the jumps explicit
+#[test]
+fn pretty() {
+    let out = charon("no_nested_borrows", "pretty", &["--ullbc", "--pretty"]);
+    let lines = out.pretty_fun("no_nested_borrows::list_length");
+    let signature = Regex::new(r"^fn no_nested_borrows::list_length<.*>\(l@1: &").unwrap();
+    assert!(signature.is_match(lines[0]));
+    assert!(lines.iter().take(5).any(|l| *l == "    bb0:"));
+    assert!(lines.iter().take(13).any(|l| l.contains("goto bb")));
+}
+
+/// The compiler built-ins are detected from their def ids, not from their
+/// names only
+#[test]
+fn builtins() {
+    let krate = llbc("builtins", "builtins", &[]);
+    let builtin = |path: &str| fun(&krate, path).builtin;
+    assert!(builtin("core::mem::size_of") == Some(BuiltinFnKind::SizeOf));
+    assert!(builtin("core::mem::align_of") == Some(BuiltinFnKind::AlignOf));
+    assert!(builtin("core::mem::forget") == Some(BuiltinFnKind::Forget));
+    assert!(builtin("core::mem::drop") == Some(BuiltinFnKind::Drop));
+    assert!(
+        builtin("core::mem::manually_drop::ManuallyDrop::new") == Some(BuiltinFnKind::ManuallyDrop)
+    );
+    assert!(builtin("builtins::mem::size_of").is_none());
+    assert!(builtin("builtins::use_builtins").is_none());
+}
+
+/// The binders of the higher-ranked trait bounds are preserved
+#[test]
+fn hrtb() {
+    let krate = llbc("hrtb", "hrtb", &[]);
+    let call_twice = fun(&krate, "call_twice");
+    assert!(call_twice.signature.predicates.iter().any(|p| matches!(
+        p,
+        Predicate::HigherRanked { binders, .. } if binders[0].index.is_zero()
+    )));
+
+    // The regions bound by the predicates are introduced in a snapshot of the
+    // context, which is rolled back: they don't leak in the signatures, and
+    // the binders of the different predicates reuse the same ids
+    for name in ["call_twice", "apply_ref"] {
+        let sig = &fun(&krate, name).signature;
+        assert!(sig.region_params.is_empty());
+        assert!(sig.predicates.iter().all(|p| match p {
+            Predicate::HigherRanked { binders, .. } =>
+                binders.len() == 1 && binders[0].index.is_zero(),
+            _ => true,
+        }));
+    }
+}
+
+/// The branches of the or-patterns are grouped in a single switch branch,
+/// including when the switches are nested
+#[test]
+fn or_patterns() {
+    // The values of the branches of the switches (the otherwise branches
+    // excluded)
+    let branches = |krate: &TranslatedCrate, name| -> Vec<Vec<String>> {
+        let contents = Contents::of_fun(krate, name);
+        contents
+            .statements
+            .iter()
+            .filter_map(|st| match st {
+                RawStatement::Switch(llbc_ast::Switch::SwitchInt(_, _, branches, _)) => Some(
+                    branches
+                        .iter()
+                        .map(|(vs, _)| vs.iter().map(|v| v.to_string()).collect())
+                        .collect::<Vec<_>>(),
+                ),
+                RawStatement::Switch(llbc_ast::Switch::Match(_, branches, _)) => Some(
+                    branches
+                        .iter()
+                        .map(|(vs, _)| vs.iter().map(|v| v.to_string()).collect())
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    };
+    for (suffix, opts) in [("", &[][..]), ("_opt", &["--mir_optimized"][..])] {
+        let krate = llbc(
+            "matches",
+            &format!("matches{}", suffix),
+            &[&["--no-code-duplication"], opts].concat(),
+        );
+        assert!(branches(&krate, "test_or_int") == [["1 : u32", "2 : u32"]]);
+
+        let krate = llbc(
+            "matches_duplicate",
+            &format!("matches_duplicate{}", suffix),
+            opts,
+        );
+        assert!(branches(&krate, "test_nested_or") == [["0", "1"], ["0 : u32", "1 : u32"]]);
+    }
+}
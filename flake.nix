@@ -52,6 +52,19 @@
           in craneLibWithExt.buildPackage {
             src = ./charon;
             inherit cargoArtifacts;
+            # The integration tests translate the files of the `tests` crate,
+            # which is not part of the sources: we only run the unit tests
+            cargoTestExtraArgs = "--lib --bins";
+          };
+        # Check that the library builds and that its unit tests pass without
+        # the rustc libraries (see the `rustc` feature of the Charon crate)
+        charon-no-rustc =
+          let cargoArtifacts = craneLibNoExt.buildDepsOnly { src = ./charon; };
+          in craneLibNoExt.buildPackage {
+            pname = "charon-no-rustc";
+            src = ./charon;
+            inherit cargoArtifacts;
+            cargoExtraArgs = "--lib --bin charon-schema --no-default-features";
           };
         tests =
           let cargoArtifacts = craneLibNoExt.buildDepsOnly { src = ./tests; };
@@ -132,7 +145,7 @@
           inherit charon charon-ml;
           default = charon;
         };
-        checks = { inherit tests tests-polonius charon-ml-tests; };
+        checks = { inherit tests tests-polonius charon-ml-tests charon-no-rustc; };
         hydraJobs = {
           inherit charon tests tests-polonius charon-ml charon-ml-tests;
         };
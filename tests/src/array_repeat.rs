@@ -0,0 +1,19 @@
+//! Exercise the translation of the arrays with repeated elements: they are
+//! translated to `[x; N]` aggregates, rather than to `N` copies of `x`.
+
+pub fn zeroes<const N: usize>() -> [u8; N] {
+    [0u8; N]
+}
+
+pub fn zeroes_3() -> [u8; 3] {
+    [0u8; 3]
+}
+
+/// The array literal is collapsed to `[1; 3]`
+pub fn ones() -> [u32; 3] {
+    [1, 1, 1]
+}
+
+pub fn repeat_var(x: u32) -> [u32; 4] {
+    [x; 4]
+}
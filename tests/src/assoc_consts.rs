@@ -0,0 +1,33 @@
+//! Exercise the translation of associated constants.
+#![allow(dead_code)]
+
+trait HasId {
+    const ID: u32;
+}
+
+struct Foo {
+    x: u32,
+}
+
+struct Bar;
+
+impl HasId for Foo {
+    const ID: u32 = 0;
+}
+
+impl HasId for Bar {
+    const ID: u32 = 1;
+}
+
+/// An associated constant in an inherent impl
+impl Bar {
+    const NAME_LEN: usize = 3;
+}
+
+fn get_ids() -> u32 {
+    Foo::ID + Bar::ID
+}
+
+fn get_name_len() -> usize {
+    Bar::NAME_LEN
+}
@@ -0,0 +1,18 @@
+//! Exercise the translation of the associated types.
+#![allow(dead_code)]
+
+/// `I::Item` can't be resolved: it is translated to an associated type
+/// projection. The signature of `Iterator::next` also uses `Self::Item`.
+fn first<I: Iterator>(mut it: I) -> Option<I::Item> {
+    it.next()
+}
+
+/// The bound gives the value of the associated type
+fn first_u32<I: Iterator<Item = u32>>(mut it: I) -> Option<I::Item> {
+    it.next()
+}
+
+/// The type is concrete: we use the associated type given by the impl
+fn range_start(r: std::ops::Range<u32>) -> <std::ops::Range<u32> as Iterator>::Item {
+    r.start
+}
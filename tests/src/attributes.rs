@@ -0,0 +1,16 @@
+//! The user attributes of the functions
+#![feature(register_tool)]
+#![register_tool(my_tool)]
+#![allow(dead_code)]
+
+#[my_tool::verified]
+pub fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+/// The compiler attributes and the doc comments are ignored
+#[inline]
+#[my_tool::requires(x > 0)]
+pub fn decr(x: u32) -> u32 {
+    x - 1
+}
@@ -0,0 +1,41 @@
+//! Exercise the translation of the impls which apply to an infinite set of
+//! types: the generic impls, and the impls for trait objects
+#![allow(dead_code)]
+use std::fmt::{Debug, Display};
+
+pub trait Describe {
+    fn describe(&self) -> u32;
+}
+
+pub struct Wrapper<T> {
+    pub x: T,
+}
+
+impl<T: Debug> Describe for Wrapper<T> {
+    fn describe(&self) -> u32 {
+        0
+    }
+}
+
+impl Describe for dyn Display {
+    fn describe(&self) -> u32 {
+        1
+    }
+}
+
+/// Not a blanket impl: it applies to a single type
+impl Describe for u32 {
+    fn describe(&self) -> u32 {
+        *self
+    }
+}
+
+pub trait Named {
+    fn name(&self) -> u32;
+}
+
+impl<T: Display> Named for T {
+    fn name(&self) -> u32 {
+        2
+    }
+}
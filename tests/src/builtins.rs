@@ -0,0 +1,18 @@
+//! Exercise the detection of the compiler built-ins.
+#![allow(dead_code)]
+
+use std::mem::ManuallyDrop;
+
+/// A local function with the name of a built-in is not a built-in
+mod mem {
+    pub fn size_of<T>() -> usize {
+        0
+    }
+}
+
+fn use_builtins(x: Vec<u32>, y: Vec<u32>) -> usize {
+    std::mem::forget(x);
+    let y = ManuallyDrop::new(y);
+    drop(y);
+    std::mem::size_of::<u32>() + std::mem::align_of::<u64>() + mem::size_of::<u32>()
+}
@@ -0,0 +1,15 @@
+//! Exercise the translation of the byte literals and byte strings.
+
+pub const MAGIC: &[u8; 4] = b"\xDE\xAD\xBE\xEF";
+
+pub fn byte() -> u8 {
+    b'a'
+}
+
+pub fn hello() -> [u8; 5] {
+    *b"hello"
+}
+
+pub fn first_magic_byte() -> u8 {
+    MAGIC[0]
+}
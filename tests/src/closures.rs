@@ -0,0 +1,48 @@
+//! Exercise the translation of closures.
+//!
+//! For now we only test the creation of closures, which we may give to
+//! higher-order functions: calling a closure goes through the `Fn*` traits,
+//! which we don't support yet.
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+fn non_capturing() {
+    let f = |x: u32| x + 1;
+}
+
+fn shared_capturing(y: &u32) {
+    let f = |x: u32| x + *y;
+}
+
+fn mut_capturing(y: &mut u32) {
+    let mut f = |x: u32| *y += x;
+}
+
+fn move_capturing(y: u32) {
+    let f = move |x: u32| x + y;
+}
+
+fn mixed_capturing(x: u32, v: Vec<u32>) {
+    let f = || {
+        // Moves `v`: it is captured by value
+        let w = v;
+        // Only reads `x`: it is captured by shared reference
+        w.len() as u32 + x
+    };
+}
+
+/// Closures given to the iterator adapters
+fn map_filter(v: Vec<u32>, y: u32) -> Vec<u32> {
+    v.into_iter()
+        .map(|x| x + y)
+        .filter(|x| *x > y)
+        .collect()
+}
+
+/// A mutably capturing closure given to a higher-order function
+fn map_option(x: Option<u32>, count: &mut u32) -> Option<u32> {
+    x.map(|x| {
+        *count += 1;
+        x + 1
+    })
+}
@@ -0,0 +1,18 @@
+//! Exercise the evaluation of the constants at translation time
+//! (`--inline-const-eval`)
+#![allow(dead_code)]
+
+pub const fn square(x: u64) -> u64 {
+    x * x
+}
+
+pub const S: u64 = square(7);
+
+/// With `--inline-const-eval`, `S` is replaced with its value
+pub fn get_s() -> u64 {
+    S
+}
+
+pub fn add_s(x: u64) -> u64 {
+    x + S
+}
@@ -0,0 +1,6 @@
+//! Exercise the translation of the `const fn`, which are always translated
+//! from the promoted MIR.
+
+pub const fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
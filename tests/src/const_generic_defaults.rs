@@ -0,0 +1,20 @@
+//! Exercise the translation of the default values of the const generics
+#![allow(dead_code)]
+
+pub struct Ring<const N: usize = 8> {
+    pub buf: [u8; N],
+}
+
+/// The parameter is given explicitly
+pub fn first_explicit(r: &Ring<4>) -> u8 {
+    r.buf[0]
+}
+
+/// The parameter is omitted: `Ring` is `Ring<8>`
+pub fn first_default(r: &Ring) -> u8 {
+    r.buf[0]
+}
+
+pub fn new_default() -> Ring {
+    Ring { buf: [0; 8] }
+}
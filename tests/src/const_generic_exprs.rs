@@ -0,0 +1,39 @@
+//! Exercise the translation of const generic expressions
+#![allow(dead_code)]
+#![allow(incomplete_features)]
+#![feature(generic_const_exprs)]
+
+pub struct DoubleArray<const N: usize>
+where
+    [u8; N * 2]: Sized,
+{
+    pub data: [u8; N * 2],
+}
+
+pub const fn incr(n: usize) -> usize {
+    n + 1
+}
+
+pub struct IncrArray<const N: usize>
+where
+    [u8; incr(N)]: Sized,
+{
+    pub data: [u8; incr(N)],
+}
+
+pub fn double_array_len<const N: usize>(x: &DoubleArray<N>) -> usize
+where
+    [u8; N * 2]: Sized,
+{
+    x.data.len()
+}
+
+pub fn split<T: Copy, const N: usize>(arr: [T; N * 2]) -> ([T; N], [T; N])
+where
+    [T; N * 2]: Sized,
+{
+    (
+        core::array::from_fn(|i| arr[i]),
+        core::array::from_fn(|i| arr[N + i]),
+    )
+}
@@ -0,0 +1,14 @@
+//! Exercise the translation of the constant references to arrays and tuples.
+//! When evaluated (see `--inline-const-eval`), their values are pointers to
+//! memory allocations (rather than to statics).
+
+pub const A: &[u32; 3] = &[1, 2, 3];
+pub const P: &(u32, u32) = &(4, 5);
+
+pub fn sum_a() -> u32 {
+    A[0] + A[1] + A[2]
+}
+
+pub fn sum_p() -> u32 {
+    P.0 + P.1
+}
@@ -75,6 +75,19 @@ const Q1: i32 = 5;
 const Q2: i32 = Q1;
 const Q3: i32 = add(Q2, 3);
 
+// Aggregated constants
+
+const PAIR: (u32, u32) = (1, 2);
+const ARR: [u8; 4] = [0, 1, 2, 3];
+
+fn sum_pair() -> u32 {
+    PAIR.0 + PAIR.1
+}
+
+fn get_arr() -> [u8; 4] {
+    ARR
+}
+
 // Statiques
 
 static S1: u32 = 6;
@@ -0,0 +1,8 @@
+//! Check the common subexpression elimination (`--cse`)
+#![allow(dead_code)]
+
+pub fn add_twice(a: u32, b: u32) -> u32 {
+    let x = a + b;
+    let y = a + b;
+    x * y
+}
@@ -0,0 +1,30 @@
+//! Check the translation of the trait methods with a default implementation
+#![allow(dead_code)]
+
+pub trait Greet {
+    fn name(&self) -> &str;
+
+    fn greet(&self) {
+        println!("{}", self.name());
+    }
+
+    fn greet_twice(&self) {
+        self.greet();
+        self.greet();
+    }
+}
+
+pub struct Person {
+    pub name: String,
+}
+
+/// Overrides `greet`, but not `greet_twice`
+impl Greet for Person {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn greet(&self) {
+        println!("Hello, {}", self.name());
+    }
+}
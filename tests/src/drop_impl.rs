@@ -0,0 +1,18 @@
+//! Check the drops of the values whose type has a custom `Drop` impl
+#![allow(dead_code)]
+
+pub struct Counter {
+    pub count: u32,
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        self.count = 0;
+    }
+}
+
+/// Dropping `y` may panic: we then need to drop `x` in the cleanup block
+pub fn drop_two() {
+    let _x = Counter { count: 0 };
+    let _y = Counter { count: 1 };
+}
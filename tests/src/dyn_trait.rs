@@ -0,0 +1,15 @@
+//! Exercise the translation of trait objects (`dyn Trait`), and of the calls
+//! dispatched through them.
+#![allow(dead_code)]
+
+fn call_through_trait(x: &dyn Fn(u32) -> u32) -> u32 {
+    x(0)
+}
+
+fn take_send(x: &(dyn Fn(u32) -> u32 + Send)) -> u32 {
+    x(1)
+}
+
+fn call_twice(x: &mut dyn FnMut(u32) -> u32) -> u32 {
+    x(0) + x(1)
+}
@@ -0,0 +1,12 @@
+//! Exercise `--emit-mir`: we dump the MIR of the translated functions next to
+//! the generated files.
+
+/// The built MIR has 5 blocks: the test, the two branches, and the return.
+/// The `else` branch is reached through an intermediate `goto` block.
+pub fn max(x: u32, y: u32) -> u32 {
+    if x < y {
+        y
+    } else {
+        x
+    }
+}
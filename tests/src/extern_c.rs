@@ -0,0 +1,10 @@
+//! Exercise the translation of the functions declared in `extern` blocks.
+#![allow(dead_code)]
+
+extern "C" {
+    fn abs(x: i32) -> i32;
+}
+
+fn call_abs(x: i32) -> i32 {
+    unsafe { abs(x) }
+}
@@ -0,0 +1,19 @@
+//! Trait impls on external types: the methods refer to the external types,
+//! which are either assumed or opaque.
+#![allow(dead_code)]
+
+pub trait Total {
+    fn total(&self) -> u32;
+}
+
+impl Total for Vec<u32> {
+    fn total(&self) -> u32 {
+        self.len() as u32
+    }
+}
+
+impl Total for std::time::Duration {
+    fn total(&self) -> u32 {
+        self.as_secs() as u32
+    }
+}
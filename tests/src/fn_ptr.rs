@@ -0,0 +1,35 @@
+//! Exercise the translation of function pointers and indirect calls.
+#![allow(dead_code)]
+
+fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+fn apply(f: fn(u32) -> u32, x: u32) -> u32 {
+    f(x)
+}
+
+fn apply_incr(x: u32) -> u32 {
+    apply(incr, x)
+}
+
+/// Storing a function pointer in a structure
+struct Handler {
+    callback: fn(u32) -> u32,
+    value: u32,
+}
+
+fn new_handler(value: u32) -> Handler {
+    Handler {
+        callback: incr,
+        value,
+    }
+}
+
+fn run_handler(h: &Handler) -> u32 {
+    (h.callback)(h.value)
+}
+
+fn fn_ptr_to_addr(f: fn(u32) -> u32) -> *const u8 {
+    f as *const u8
+}
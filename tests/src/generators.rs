@@ -0,0 +1,31 @@
+//! Exercise the translation of the generators, including the generators
+//! the `async` functions are desugared to: we generate the enumeration of the
+//! states of the generator, whose variants record the variables live across
+//! the `yield`s.
+#![feature(generators)]
+
+/// The generator has two suspension points: `x` is live across the first
+/// one (it is used to compute `y`), and nothing is live across the second
+/// one.
+pub fn yield_ints() {
+    let _gen = || {
+        let x = 1u32;
+        yield x;
+        let y = x + 2;
+        yield y;
+    };
+}
+
+/// An `async` function is desugared to a generator, which implements
+/// `Future`
+pub async fn one() -> u32 {
+    1
+}
+
+/// Each `.await` polls the awaited future, and yields while the future is
+/// pending: the generator has two suspension points. The value of `x` is
+/// live across the second one.
+pub async fn add_ones() -> u32 {
+    let x = one().await;
+    x + one().await
+}
@@ -0,0 +1,18 @@
+//! Exercise the translation of the higher-ranked trait bounds.
+//!
+//! Calling a closure goes through the `Fn*` traits, which we don't support
+//! yet (see `closures.rs`): we only check the signatures.
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+fn call_twice<F: for<'a> Fn(&'a u32)>(f: F) {}
+
+/// The constraint on the output of the closure refers to the bound region
+fn apply_ref<F>(f: F)
+where
+    F: for<'a> Fn(&'a u32) -> &'a u32,
+{
+}
+
+/// A bound without binder, with an associated type constraint
+fn sum_all<I: Iterator<Item = u32>>(it: I) {}
@@ -0,0 +1,42 @@
+//! Exercise the reconstruction of the `if let ... { ... } else { ... }`
+//! statements.
+
+pub fn get_or_zero(opt: Option<u32>) -> u32 {
+    if let Some(x) = opt {
+        x + 1
+    } else {
+        0
+    }
+}
+
+pub enum Shape {
+    Circle(u32),
+    Square(u32),
+}
+
+pub fn circle_radius(s: &Shape) -> u32 {
+    let mut r = 0;
+    if let Shape::Circle(x) = s {
+        r = *x;
+    }
+    r
+}
+
+fn decr(n: u32) -> Option<u32> {
+    if n == 0 {
+        None
+    } else {
+        Some(n - 1)
+    }
+}
+
+/// The `while let` loops exit with a `break` in the `else` branch: they are
+/// reconstructed as `let ... else`.
+pub fn count_down(mut n: u32) -> u32 {
+    let mut steps = 0;
+    while let Some(m) = decr(n) {
+        n = m;
+        steps += 1;
+    }
+    steps
+}
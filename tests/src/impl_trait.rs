@@ -0,0 +1,13 @@
+//! Exercise the translation of `impl Trait` in return position.
+//!
+//! We can't call the methods of the traits yet (calls to trait methods are
+//! not supported), but we can return values of opaque types.
+#![allow(dead_code)]
+
+fn up_to(n: u32) -> impl Iterator<Item = u32> {
+    0..n
+}
+
+fn up_to_ten() {
+    let _ = up_to(10);
+}
@@ -0,0 +1,32 @@
+//! Exercise the translation of `UnsafeCell`, and the detection of the fields
+//! with interior mutability.
+#![allow(dead_code)]
+use std::cell::{Cell, UnsafeCell};
+
+struct MyCell {
+    v: UnsafeCell<u32>,
+}
+
+/// Contains an `UnsafeCell` transitively
+struct Wrapper {
+    cell: MyCell,
+    x: u32,
+}
+
+/// The `UnsafeCell` is behind a reference: the field itself can't be mutated
+struct Shared<'a> {
+    r: &'a MyCell,
+}
+
+/// `Cell` is built on top of `UnsafeCell`
+struct Counter {
+    count: Cell<u32>,
+}
+
+fn get(c: &MyCell) -> u32 {
+    unsafe { *c.v.get() }
+}
+
+fn incr(c: &Counter) {
+    c.count.set(c.count.get() + 1)
+}
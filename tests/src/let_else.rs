@@ -0,0 +1,13 @@
+//! Exercise the reconstruction of the `let ... else { ... }` statements.
+
+pub fn process(x: Option<u32>) -> u32 {
+    let Some(v) = x else {
+        return 0;
+    };
+    v
+}
+
+pub fn first_or_panic(x: Option<(u32, u32)>) -> u32 {
+    let Some((a, _)) = x else { panic!() };
+    a + 1
+}
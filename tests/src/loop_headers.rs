@@ -0,0 +1,15 @@
+//! Check that the loop headers are detected
+#![allow(dead_code)]
+
+/// Compute `x^n` by repeated squaring
+pub fn binpow(mut x: u64, mut n: u32) -> u64 {
+    let mut res = 1;
+    while n > 0 {
+        if n % 2 == 1 {
+            res *= x;
+        }
+        x *= x;
+        n /= 2;
+    }
+    res
+}
@@ -0,0 +1,7 @@
+//! Check the statements coming from macro expansions (`--strip-macro-metadata`)
+#![allow(dead_code)]
+
+pub fn assert_one(x: u32) -> u32 {
+    assert!(x == 1);
+    x + 1
+}
@@ -29,15 +29,36 @@ enum E1 {
 ///     goto bb2; // bb2 gets duplicated, not bb4
 /// }
 /// ```
-/// We could detect the "fused" branches by noticing that blocks bb1 and bb4 are
-/// gotos, and checking that they goto the same block, but it is annoying, really,
-/// especially because it makes the control-flow reconstruction more ad-hoc. The
-/// main problem is that it would be difficult to make the distinction between
-/// a goto we need to ignore, and a "real" goto.
-/// Consequently, whenever branhces are fused, don't use `--no-code-duplication`.
+/// We detect the "fused" branches by noticing that blocks bb1 and bb4 are
+/// trivial gotos, and checking that they goto the same block. Note that it
+/// is sometimes difficult to make the distinction between a goto we need to
+/// ignore, and a "real" goto (for instance, if the blocks contain some
+/// statements). Consequently, the fused branches which don't directly join
+/// (see `matches_duplicate.rs`) are tested without `--no-code-duplication`.
 fn test1(x: E1) -> bool {
     match x {
         E1::V1 | E1::V2 => true,
         E1::V3 => false,
     }
 }
+
+/// Or-patterns over integers
+fn test_or_int(x: u32) -> u32 {
+    match x {
+        1 | 2 => 0,
+        _ => 1,
+    }
+}
+
+/// The discriminants of the variants are not their indices
+enum E2 {
+    V1 = 3,
+    V2 = -1,
+}
+
+fn test_discriminants(x: E2) -> u32 {
+    match x {
+        E2::V1 => 0,
+        E2::V2 => 1,
+    }
+}
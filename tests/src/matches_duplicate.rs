@@ -30,3 +30,19 @@ fn test3(x: E2) -> u32 {
     let z = id(3);
     return y + z;
 }
+
+enum E3 {
+    V1,
+    V2,
+    V3,
+}
+
+/// Nested or-patterns. The match is compiled to a switch over `x`, whose
+/// branch `E3::V1 | E3::V2` contains a switch over `y`: the two switches
+/// share their otherwise branch, which leads to code duplication.
+fn test_nested_or(x: E3, y: u32) -> u32 {
+    match (x, y) {
+        (E3::V1 | E3::V2, 0 | 1) => 0,
+        _ => 1,
+    }
+}
@@ -0,0 +1,20 @@
+//! Exercise the translation of the methods, which are linked to the type
+//! for which they are defined.
+
+pub struct Counter {
+    pub count: u32,
+}
+
+impl Counter {
+    pub fn new() -> Self {
+        Counter { count: 0 }
+    }
+
+    pub fn incr(&mut self) {
+        self.count += 1;
+    }
+}
+
+pub fn not_a_method(c: &mut Counter) {
+    c.incr()
+}
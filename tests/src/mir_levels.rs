@@ -0,0 +1,26 @@
+//! Exercise the translation of some functions at a specific MIR level (see
+//! `--mir-level-for` and the `charon::mir_level` attribute)
+#![feature(register_tool)]
+#![register_tool(charon)]
+
+/// Translated at the level of the crate
+pub fn sum_default() -> u32 {
+    let x = 1;
+    let y = 2;
+    x + y
+}
+
+/// Translated from the optimized MIR: the constants are propagated
+#[charon::mir_level = "optimized"]
+pub fn sum_attribute() -> u32 {
+    let x = 1;
+    let y = 2;
+    x + y
+}
+
+/// Translated from the optimized MIR if we use `--mir-level-for`
+pub fn sum_cli() -> u32 {
+    let x = 1;
+    let y = 2;
+    x + y
+}
@@ -0,0 +1,17 @@
+//! Exercise the computation of the module tree.
+
+pub fn root_fun() {}
+
+pub mod outer {
+    pub struct Foo {
+        pub x: u32,
+    }
+
+    pub mod inner {
+        pub const ZERO: u32 = 0;
+
+        pub fn get(f: &super::Foo) -> u32 {
+            f.x + ZERO
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Exercise the translation of the `PhantomData` fields.
+use std::marker::PhantomData;
+
+pub struct MyRef<'a, T> {
+    pub ptr: *const T,
+    pub _marker: PhantomData<&'a T>,
+}
+
+pub fn from_ref<'a, T>(x: &'a T) -> MyRef<'a, T> {
+    MyRef {
+        ptr: x as *const T,
+        _marker: PhantomData,
+    }
+}
@@ -0,0 +1,18 @@
+//! Exercise the translation of the promoted constants, which are translated
+//! to anonymous globals.
+
+pub const S: &[u8] = &[1, 2, 3];
+
+pub fn get_slice() -> &'static [u8] {
+    &[1, 2, 3]
+}
+
+pub fn sum_s() -> u32 {
+    let mut sum = 0;
+    let mut i = 0;
+    while i < S.len() {
+        sum += S[i] as u32;
+        i += 1;
+    }
+    sum
+}
@@ -0,0 +1,26 @@
+//! The range types of `std::ops`
+#![allow(dead_code)]
+
+pub fn make_range(a: usize, b: usize) -> std::ops::Range<usize> {
+    a..b
+}
+
+pub fn make_range_from(a: usize) -> std::ops::RangeFrom<usize> {
+    a..
+}
+
+pub fn make_range_to(b: usize) -> std::ops::RangeTo<usize> {
+    ..b
+}
+
+pub fn make_range_full() -> std::ops::RangeFull {
+    ..
+}
+
+pub fn make_range_inclusive(a: usize, b: usize) -> std::ops::RangeInclusive<usize> {
+    a..=b
+}
+
+pub fn range_len(r: std::ops::Range<usize>) -> usize {
+    r.end - r.start
+}
@@ -0,0 +1,15 @@
+//! Building raw pointers from their parts (a data pointer and some metadata)
+#![feature(ptr_metadata)]
+use std::ptr;
+
+pub fn slice_from_parts(p: *const u32, len: usize) -> *const [u32] {
+    ptr::from_raw_parts(p as *const (), len)
+}
+
+pub fn slice_from_parts_mut(p: *mut u32, len: usize) -> *mut [u32] {
+    ptr::from_raw_parts_mut(p as *mut (), len)
+}
+
+pub fn thin_from_parts(p: *const u32) -> *const u32 {
+    ptr::from_raw_parts(p as *const (), ())
+}
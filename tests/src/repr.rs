@@ -0,0 +1,30 @@
+//! Exercise the translation of the `#[repr(...)]` attributes.
+#![allow(dead_code)]
+
+#[repr(C)]
+struct Foo {
+    a: u8,
+    b: u32,
+}
+
+#[repr(C, packed(2))]
+struct Packed {
+    a: u8,
+    b: u32,
+}
+
+#[repr(align(8))]
+struct Aligned(u32);
+
+#[repr(u8)]
+enum Tag {
+    A = 1,
+    B = 2,
+}
+
+#[repr(transparent)]
+struct Wrapper(u64);
+
+fn get_b(x: &Foo) -> u32 {
+    x.b
+}
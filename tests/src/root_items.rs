@@ -0,0 +1,21 @@
+//! Exercise the distinction between the declarations which appear in the
+//! crate, and those we translate because they are used (here, the external
+//! type `Ordering`).
+use std::cmp::Ordering;
+
+fn helper(x: u32, y: u32) -> Ordering {
+    if x < y {
+        Ordering::Less
+    } else if x == y {
+        Ordering::Equal
+    } else {
+        Ordering::Greater
+    }
+}
+
+pub fn is_less(x: u32, y: u32) -> bool {
+    match helper(x, y) {
+        Ordering::Less => true,
+        _ => false,
+    }
+}
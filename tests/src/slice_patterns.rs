@@ -0,0 +1,29 @@
+//! Exercise the translation of the slice patterns, which introduce constant
+//! indices and subslices in the places.
+
+pub fn sum_prefix(s: &[u8]) -> u32 {
+    match s {
+        [a, b, rest @ ..] => (*a as u32) + (*b as u32) + (rest.len() as u32),
+        [a] => *a as u32,
+        [] => 0,
+    }
+}
+
+pub fn last(s: &[u32]) -> u32 {
+    match s {
+        [.., x] => *x,
+        [] => 0,
+    }
+}
+
+pub fn array_tail(a: [u32; 4]) -> [u32; 3] {
+    let [_, tail @ ..] = a;
+    tail
+}
+
+pub fn strip_first(s: &[u8]) -> &[u8] {
+    match s {
+        [_, rest @ ..] => rest,
+        [] => s,
+    }
+}
@@ -0,0 +1,21 @@
+//! Check the `--split-output` option: we generate one file per module.
+#![allow(dead_code)]
+
+pub mod geometry {
+    pub struct Point {
+        pub x: u32,
+        pub y: u32,
+    }
+
+    pub fn origin() -> Point {
+        Point { x: 0, y: 0 }
+    }
+}
+
+pub fn norm1(p: geometry::Point) -> u32 {
+    p.x + p.y
+}
+
+pub fn norm1_origin() -> u32 {
+    norm1(geometry::origin())
+}
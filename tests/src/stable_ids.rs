@@ -0,0 +1,10 @@
+//! Check the stable ids of the declarations (`--stable-ids`)
+#![allow(dead_code)]
+
+pub struct Wrapper<T, const N: usize> {
+    pub values: [T; N],
+}
+
+pub fn first<T: Copy, const N: usize>(w: &Wrapper<T, N>) -> T {
+    w.values[0]
+}
@@ -0,0 +1,11 @@
+//! Check that the `StorageLive` and `StorageDead` statements are preserved
+#![allow(dead_code)]
+
+pub fn scoped(x: u32) -> u32 {
+    let mut y = 0;
+    {
+        let z = x + 1;
+        y += z;
+    }
+    y
+}
@@ -0,0 +1,11 @@
+//! Exercise the translation of the string literals.
+
+pub const HELLO: &str = "hello, world";
+
+pub fn greet() -> &'static str {
+    "hi"
+}
+
+pub fn hello_len() -> usize {
+    HELLO.len()
+}
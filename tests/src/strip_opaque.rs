@@ -0,0 +1,21 @@
+//! Exercise `--strip-opaque`: the items of the opaque modules which are not
+//! used by the transparent items are removed
+#![allow(dead_code)]
+
+pub mod utils {
+    pub fn used(x: u32) -> u32 {
+        x + 1
+    }
+
+    pub fn unused_helper(x: u32) -> u32 {
+        x * 2
+    }
+
+    pub struct UnusedType {
+        pub x: u32,
+    }
+}
+
+pub fn call_used(x: u32) -> u32 {
+    utils::used(x)
+}
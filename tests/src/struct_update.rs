@@ -0,0 +1,23 @@
+//! Exercise the translation of the struct update syntax
+#![allow(dead_code)]
+
+pub struct Foo {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+pub fn update(a: Foo) -> Foo {
+    let b = Foo { x: 2, ..a };
+    b
+}
+
+/// The base has borrowed fields
+pub struct Refs<'a> {
+    pub x: &'a u32,
+    pub y: &'a mut u32,
+}
+
+pub fn update_refs<'a>(a: Refs<'a>, x: &'a u32) -> Refs<'a> {
+    Refs { x, ..a }
+}
@@ -0,0 +1,18 @@
+//! Exercise the translation of the struct-like enum variants
+#![allow(dead_code)]
+
+pub enum E {
+    Variant { x: u32, y: u64 },
+    Other(u32),
+}
+
+pub fn mk_variant(x: u32, y: u64) -> E {
+    E::Variant { x, y }
+}
+
+pub fn get_x(e: &E) -> u32 {
+    match e {
+        E::Variant { x, .. } => *x,
+        E::Other(x) => *x,
+    }
+}
@@ -0,0 +1,29 @@
+//! Check the information about the methods defined in trait impls
+#![allow(dead_code)]
+
+pub struct MyList {
+    pub values: Vec<u32>,
+}
+
+impl Iterator for MyList {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        self.values.pop()
+    }
+}
+
+impl MyList {
+    /// Not a trait impl
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+}
+
+pub fn sum(l: &mut MyList) -> u32 {
+    let mut s = 0;
+    while let Some(x) = l.next() {
+        s += x;
+    }
+    s
+}
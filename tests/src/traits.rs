@@ -0,0 +1,36 @@
+//! Exercise the translation of the trait declarations.
+#![allow(dead_code)]
+
+trait Named {
+    fn name(&self) -> u32;
+}
+
+trait Shape: Named {
+    /// A required method
+    fn area(&self) -> u32;
+
+    /// A provided method
+    fn double_area(&self) -> u32 {
+        2 * self.area()
+    }
+}
+
+struct Square {
+    side: u32,
+}
+
+impl Named for Square {
+    fn name(&self) -> u32 {
+        0
+    }
+}
+
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.side * self.side
+    }
+}
+
+fn square_area(s: &Square) -> u32 {
+    s.area()
+}
@@ -0,0 +1,19 @@
+//! Check the reconstruction of the `?` operator (`--reconstruct-try`)
+#![allow(dead_code)]
+
+use std::num::ParseIntError as ParseError;
+
+pub fn parse(s: &str) -> Result<u32, ParseError> {
+    Ok(s.parse::<u32>()? + 1)
+}
+
+pub fn parse_both(s0: &str, s1: &str) -> Result<u32, ParseError> {
+    let x = parse(s0)?;
+    let y = parse(s1)?;
+    Ok(x.wrapping_add(y))
+}
+
+pub fn first_or_zero(v: Option<u32>) -> Option<u32> {
+    let x = v?;
+    Some(x)
+}
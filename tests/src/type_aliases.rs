@@ -0,0 +1,26 @@
+//! Exercise the preservation of the type aliases (`--preserve-type-aliases`).
+
+pub type MyVec = Vec<u32>;
+
+/// A generic alias: it is translated, but not restored in the signatures
+pub type Pair<T> = (T, T);
+
+pub struct Wrapper {
+    pub v: MyVec,
+}
+
+pub fn make_vec() -> MyVec {
+    Vec::new()
+}
+
+pub fn push(v: &mut MyVec, x: u32) {
+    v.push(x)
+}
+
+pub fn wrap(v: MyVec) -> Wrapper {
+    Wrapper { v }
+}
+
+pub fn swap(p: Pair<u32>) -> Pair<u32> {
+    (p.1, p.0)
+}
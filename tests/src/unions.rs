@@ -0,0 +1,27 @@
+//! Exercise the translation of unions.
+#![allow(dead_code)]
+
+#[repr(C)]
+union IntOrUint {
+    i: i32,
+    u: u32,
+}
+
+fn read_int(x: IntOrUint) -> i32 {
+    unsafe { x.i }
+}
+
+fn write_int(x: &mut IntOrUint, i: i32) {
+    x.i = i;
+}
+
+/// A union used to reinterpret bytes (similarly to `transmute`)
+union Bytes {
+    word: u32,
+    bytes: [u8; 4],
+}
+
+fn to_bytes(word: u32) -> [u8; 4] {
+    let b = Bytes { word };
+    unsafe { b.bytes }
+}
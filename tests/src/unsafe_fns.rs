@@ -0,0 +1,22 @@
+//! Exercise the detection of the unsafe functions, and of the functions which
+//! (transitively) call them.
+
+/// # Safety
+/// `p` must be valid for reads.
+pub unsafe fn read_raw(p: *const u32) -> u32 {
+    *p
+}
+
+/// A safe wrapper around an unsafe function
+pub fn read(x: &u32) -> u32 {
+    unsafe { read_raw(x as *const u32) }
+}
+
+/// Calls the unsafe function through the safe wrapper
+pub fn read_twice(x: &u32) -> u32 {
+    read(x) + read(x)
+}
+
+pub fn add(x: u32, y: u32) -> u32 {
+    x + y
+}
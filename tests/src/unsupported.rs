@@ -0,0 +1,24 @@
+//! Exercise the partial translations (see `--errors-as-warnings`): we don't
+//! support the floating-point numbers.
+#![feature(core_intrinsics)]
+
+/// The type of the field is not supported: the type is opaque
+pub struct Point {
+    pub x: f32,
+}
+
+/// The body uses floating-point numbers: the function is opaque
+pub fn half(x: u32) -> u32 {
+    (x as f32 / 2.0) as u32
+}
+
+/// This function is fully supported
+pub fn double(x: u32) -> u32 {
+    2 * x
+}
+
+/// The call to the intrinsic becomes an intrinsic statement in the optimized
+/// MIR, which we don't support
+pub fn assume_unchecked(b: bool) {
+    unsafe { core::intrinsics::assume(b) }
+}
@@ -0,0 +1,18 @@
+//! Exercise the translation of the variance of the generic parameters.
+
+/// Covariant in `T`
+pub struct Cov<T>(*const T);
+
+/// Invariant in `T`
+pub struct Inv<T>(*mut T);
+
+/// Covariant in `'a`, invariant in `T`
+pub struct Mixed<'a, T> {
+    pub r: &'a u32,
+    pub cell: Inv<T>,
+}
+
+/// Contravariant in `T`
+pub struct Contra<T> {
+    pub f: fn(T),
+}